@@ -0,0 +1,24 @@
+//! Endpoints for accounts.
+
+use crate::{Endpoint, Method, Page};
+use blog_db::models::{capabilities, credentials, users};
+
+pub const CREATE: Endpoint<users::NewNoMeta, users::DataNoMeta> = Endpoint::new(Method::Post, "/accounts");
+/// Up to a handful of users whose user name contains the `name` added to the url.
+pub const SEARCH: Endpoint<(), Vec<users::AuthorSummary>> = Endpoint::new(Method::Get, "/accounts");
+pub const GET: Endpoint<(), users::DataNoMeta> = Endpoint::new(Method::Get, "/accounts/<id>");
+/// The account logged in as.
+pub const GET_SELF: Endpoint<(), users::DataNoMeta> = Endpoint::new(Method::Get, "/accounts/me");
+/// A page of the capabilities the account holds and where each came from, oldest first, for the
+/// `limit` and `offset` added to the url.
+pub const CAPABILITIES: Endpoint<(), Page<capabilities::Grant>> =
+    Endpoint::new(Method::Get, "/accounts/<id>/capabilities");
+/// A page of the ways the account logged in as can sign in, without anything that could be used
+/// to, oldest first, for the `limit` and `offset` added to the url.
+pub const OWN_CREDENTIALS: Endpoint<(), Page<credentials::Summary>> =
+    Endpoint::new(Method::Get, "/accounts/me/credentials");
+pub const UPDATE: Endpoint<users::ChangedNoMeta, users::DataNoMeta> = Endpoint::new(Method::Patch, "/accounts/<id>");
+/// Edits the account logged in as.
+pub const UPDATE_SELF: Endpoint<users::ChangedNoMeta, users::DataNoMeta> =
+    Endpoint::new(Method::Patch, "/accounts/me");
+pub const DELETE: Endpoint<(), ()> = Endpoint::new(Method::Delete, "/accounts/<id>");
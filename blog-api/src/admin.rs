@@ -0,0 +1,20 @@
+//! Endpoints for running the site.
+
+use crate::{Endpoint, Method, Page};
+use blog_db::models::{site, stats, webhooks};
+
+pub const STATS: Endpoint<(), stats::Overview> = Endpoint::new(Method::Get, "/admin/stats");
+/// Starts removing old records, such as posts deleted long ago, in the background.
+pub const RETENTION: Endpoint<(), ()> = Endpoint::new(Method::Post, "/admin/retention");
+/// Switches the mode of the site, answering with the mode switched to.
+pub const MODE: Endpoint<site::Mode, site::Mode> = Endpoint::new(Method::Post, "/admin/mode");
+/// Changes how the site presents itself, answering with how it now does.
+pub const IDENTITY: Endpoint<site::IdentityChanges, site::Identity> = Endpoint::new(Method::Patch, "/admin/identity");
+/// A page of the webhooks, oldest first, for the `limit` and `offset` added to the url. Secrets
+/// are left out.
+pub const WEBHOOKS: Endpoint<(), Page<webhooks::Data>> = Endpoint::new(Method::Get, "/admin/webhooks");
+pub const CREATE_WEBHOOK: Endpoint<webhooks::NewNoMeta, webhooks::Data> = Endpoint::new(Method::Post, "/admin/webhooks");
+pub const UPDATE_WEBHOOK: Endpoint<webhooks::Changed, webhooks::Data> = Endpoint::new(Method::Patch, "/admin/webhooks/<id>");
+pub const DELETE_WEBHOOK: Endpoint<(), webhooks::Data> = Endpoint::new(Method::Delete, "/admin/webhooks/<id>");
+/// Sends a sample event to the webhook right away, succeeding if the webhook accepts it.
+pub const TEST_WEBHOOK: Endpoint<(), ()> = Endpoint::new(Method::Post, "/admin/webhooks/<id>/test");
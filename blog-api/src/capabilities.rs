@@ -0,0 +1,7 @@
+//! Endpoints for capabilities.
+
+use crate::{Endpoint, Method};
+use blog_db::models::capabilities;
+
+pub const GET: Endpoint<(), capabilities::Data> = Endpoint::new(Method::Get, "/capabilities/<id>");
+pub const DELETE: Endpoint<(), capabilities::Data> = Endpoint::new(Method::Delete, "/capabilities/<id>");
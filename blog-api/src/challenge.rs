@@ -0,0 +1,7 @@
+//! Endpoints handing out the proof of work asked of anonymous callers of public endpoints.
+
+use crate::{Endpoint, Method};
+use login_enum::challenge::Challenge;
+
+/// A new challenge, which anyone may ask for.
+pub const ISSUE: Endpoint<(), Challenge> = Endpoint::new(Method::Get, "/challenge");
@@ -0,0 +1,10 @@
+//! Endpoints for credentials.
+
+use crate::{Endpoint, Method};
+use login_enum::{ChangePassword, CreatePassword};
+
+pub const CREATE_PASSWORD: Endpoint<CreatePassword, ()> = Endpoint::new(Method::Post, "/credentials/pws");
+/// The id of the password of the account logged in as.
+pub const OWN_PASSWORD: Endpoint<(), uuid::Uuid> = Endpoint::new(Method::Get, "/credentials/pws/me");
+pub const CHANGE_PASSWORD: Endpoint<ChangePassword, ()> = Endpoint::new(Method::Patch, "/credentials/pws/<id>");
+pub const DELETE_PASSWORD: Endpoint<(), ()> = Endpoint::new(Method::Delete, "/credentials/pws/<id>");
@@ -0,0 +1,172 @@
+//! The endpoints of the blog's api, shared by the server that mounts them and the client that
+//! calls them, so that the two cannot drift apart.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::{fmt, marker::PhantomData};
+
+pub mod accounts;
+pub mod admin;
+pub mod capabilities;
+pub mod challenge;
+pub mod credentials;
+pub mod login;
+pub mod posts;
+pub mod series;
+pub mod site;
+pub mod tags;
+
+pub use blog_db::models::page::{self, Page};
+
+/// Where the api is mounted.
+pub const ROOT: &str = "/api";
+/// What is encoded in the parameters filled into a path, which is all but the unreserved
+/// characters of a url.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+/// An endpoint of the api, taking a `B` as its body and answering with an `R`. Endpoints without a
+/// body, or without anything in their answer, use `()`.
+pub struct Endpoint<B, R> {
+    pub method: Method,
+    /// The path under [`ROOT`], written the way rocket routes are, such as `/posts/<id>`.
+    pub path: &'static str,
+    types: PhantomData<(B, R)>,
+}
+impl<B, R> Clone for Endpoint<B, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<B, R> Copy for Endpoint<B, R> {}
+impl<B, R> fmt::Debug for Endpoint<B, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {}{}", self.method, ROOT, self.path)
+    }
+}
+impl<B, R> Endpoint<B, R> {
+    pub const fn new(method: Method, path: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            types: PhantomData,
+        }
+    }
+    /// The url of the endpoint, with `params` percent-encoded into its dynamic segments in order.
+    /// Every dynamic segment must be given a parameter. Missing ones are left empty.
+    pub fn url(&self, params: &[&dyn fmt::Display]) -> String {
+        let mut params = params.iter();
+        let mut url = ROOT.to_owned();
+        for segment in self.path.split('/').skip(1) {
+            url.push('/');
+            if segment.starts_with('<') && segment.ends_with('>') {
+                let param = params.next();
+                debug_assert!(param.is_some(), "Missing the {} parameter of {:?}.", segment, self);
+                if let Some(param) = param {
+                    url.extend(utf8_percent_encode(&param.to_string(), SEGMENT));
+                }
+            } else {
+                url.push_str(segment);
+            }
+        }
+        debug_assert!(params.next().is_none(), "Too many parameters for {:?}.", self);
+        url
+    }
+}
+impl<B, T> Endpoint<B, Page<T>> {
+    /// The url of the page starting `offset` rows into the listing, holding up to `limit` rows if
+    /// asked for. `params` are filled in as by [`url`](Self::url).
+    pub fn page_url(
+        &self,
+        params: &[&dyn fmt::Display],
+        limit: Option<i64>,
+        offset: i64,
+    ) -> String {
+        match limit {
+            Some(limit) => format!("{}?limit={}&offset={}", self.url(params), limit, offset),
+            None => format!("{}?offset={}", self.url(params), offset),
+        }
+    }
+}
+
+/// Whether a rocket route with `route_method` and `route` is mounted where an endpoint with
+/// `method` and `path` is. The query of the route is left out, since queries are added to urls
+/// separately. Meant for [`assert_route!`], which checks this while compiling.
+pub const fn is_route(method: Method, path: &str, route_method: Method, route: &str) -> bool {
+    if method as u8 != route_method as u8 {
+        return false;
+    }
+    let (path, route) = (path.as_bytes(), route.as_bytes());
+    let mut i = 0;
+    while i < path.len() && i < route.len() {
+        if path[i] != route[i] {
+            return false;
+        }
+        i += 1;
+    }
+    i == path.len() && (i == route.len() || route[i] == b'?')
+}
+
+/// Fails to compile unless the rocket route with `method` and `route` is mounted where the
+/// endpoint is. Placed next to the handler, with the same path as its route attribute.
+#[macro_export]
+macro_rules! assert_route {
+    ($endpoint:expr, $method:ident, $route:literal) => {
+        const _: () = [()][!$crate::is_route(
+            $endpoint.method,
+            $endpoint.path,
+            $crate::Method::$method,
+            $route,
+        ) as usize];
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn params_are_filled_in_order() {
+        assert_eq!(posts::GET.url(&[&"a-post"]), "/api/posts/a-post");
+        assert_eq!(posts::TAGS.url(&[&7]), "/api/posts/7/tags");
+        assert_eq!(posts::LIST.url(&[]), "/api/posts");
+    }
+
+    #[test]
+    fn params_are_percent_encoded() {
+        assert_eq!(posts::GET.url(&[&"a/b?c#d%e"]), "/api/posts/a%2Fb%3Fc%23d%25e");
+        assert_eq!(
+            posts::GET.url(&[&"caf\u{e9} au_lait.v2~"]),
+            "/api/posts/caf%C3%A9%20au_lait.v2~"
+        );
+    }
+
+    #[test]
+    fn pages_are_asked_for_in_the_query() {
+        assert_eq!(tags::LIST.page_url(&[], None, 0), "/api/tags?offset=0");
+        assert_eq!(
+            accounts::CAPABILITIES.page_url(&[&"me"], Some(20), 40),
+            "/api/accounts/me/capabilities?limit=20&offset=40"
+        );
+    }
+
+    #[test]
+    fn routes_match_up_to_their_query() {
+        assert!(is_route(Method::Get, "/posts", Method::Get, "/posts?<offset>&<lim>"));
+        assert!(is_route(Method::Get, "/posts/<id>", Method::Get, "/posts/<id>"));
+        assert!(!is_route(Method::Get, "/posts/<id>", Method::Patch, "/posts/<id>"));
+        assert!(!is_route(Method::Get, "/posts/<id>", Method::Get, "/posts/<id>/tags"));
+        assert!(!is_route(Method::Get, "/posts/<id>/tags", Method::Get, "/posts/<id>"));
+    }
+}
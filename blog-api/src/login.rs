@@ -0,0 +1,9 @@
+//! Endpoints for sessions.
+
+use crate::{Endpoint, Method};
+use blog_db::models::users;
+use login_enum::Authentication;
+
+/// Starts a session. Refusals are explained with a [`LoginFailure`](login_enum::LoginFailure).
+pub const LOG_IN: Endpoint<Authentication, users::DataNoMeta> = Endpoint::new(Method::Post, "/login");
+pub const LOG_OUT: Endpoint<(), ()> = Endpoint::new(Method::Delete, "/login");
@@ -0,0 +1,36 @@
+//! Endpoints for posts.
+
+use crate::{Endpoint, Method};
+use blog_db::models::{posts, reactions, stats, tags};
+
+/// The posts matching the query added to the url, narrowed down to those published, drafts, or
+/// archived by its `state`.
+pub const LIST: Endpoint<(), Vec<posts::Listed>> = Endpoint::new(Method::Get, "/posts");
+/// How many posts are published, drafts, or archived, for those who may see them all.
+pub const COUNTS: Endpoint<(), stats::PostCounts> = Endpoint::new(Method::Get, "/posts/counts");
+/// Creates a post, crediting the listed authors or else whoever is logged in.
+pub const CREATE: Endpoint<posts::NewNoMeta, posts::Authored> = Endpoint::new(Method::Post, "/posts");
+/// The post with an id or short name, along with its authors, where it is in its series, and its
+/// body rendered.
+pub const GET: Endpoint<(), posts::Authored> = Endpoint::new(Method::Get, "/posts/<id>");
+/// Succeeds if the post may be edited by whoever is logged in.
+pub const EDITABLE: Endpoint<(), ()> = Endpoint::new(Method::Get, "/posts/<id>/editable");
+/// Changes the post, and replaces its authors if they are listed.
+pub const UPDATE: Endpoint<posts::Update, ()> = Endpoint::new(Method::Patch, "/posts/<id>");
+pub const DELETE: Endpoint<(), ()> = Endpoint::new(Method::Delete, "/posts/<id>");
+/// Publishes the post, along with any last changes.
+pub const PUBLISH: Endpoint<posts::Changed, ()> = Endpoint::new(Method::Post, "/posts/<id>/publish");
+pub const ARCHIVE: Endpoint<(), ()> = Endpoint::new(Method::Post, "/posts/<id>/archive");
+/// The body of the post rendered to html, with its code highlighted, along with its headings.
+pub const RENDERED: Endpoint<(), posts::Rendered> = Endpoint::new(Method::Get, "/posts/<id>/rendered");
+/// The post as an html document that stands on its own, downloaded as a file, for the `format`
+/// added to the url.
+pub const EXPORT: Endpoint<(), String> = Endpoint::new(Method::Get, "/posts/<id>/export");
+pub const TAGS: Endpoint<(), Vec<tags::Data>> = Endpoint::new(Method::Get, "/posts/<id>/tags");
+/// Replaces the tags of the post with those named.
+pub const SET_TAGS: Endpoint<Vec<String>, Vec<tags::Data>> = Endpoint::new(Method::Put, "/posts/<id>/tags");
+/// Leaves a reaction on the post, once per kind for each reader. Answers with the reactions the
+/// post now has, and with `409` along with them if the reader had already left it.
+pub const REACT: Endpoint<reactions::Reaction, reactions::Counts> = Endpoint::new(Method::Post, "/posts/<id>/reactions");
+/// Takes back a reaction left on the post, answering with the reactions the post now has.
+pub const RETRACT: Endpoint<reactions::Reaction, reactions::Counts> = Endpoint::new(Method::Delete, "/posts/<id>/reactions");
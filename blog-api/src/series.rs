@@ -0,0 +1,15 @@
+//! Endpoints for series, which are posts meant to be read in order.
+
+use crate::{Endpoint, Method, Page};
+use blog_db::models::series;
+
+/// A page of the series, by title, for the `limit` and `offset` added to the url.
+pub const LIST: Endpoint<(), Page<series::Data>> = Endpoint::new(Method::Get, "/series");
+pub const CREATE: Endpoint<series::NewNoMeta, series::Data> = Endpoint::new(Method::Post, "/series");
+/// The series with an id or short name, along with its parts in order.
+pub const GET: Endpoint<(), series::WithParts> = Endpoint::new(Method::Get, "/series/<id>");
+pub const UPDATE: Endpoint<series::Changed, series::Data> = Endpoint::new(Method::Patch, "/series/<id>");
+/// Deletes the series. Its posts are left as they are.
+pub const DELETE: Endpoint<(), series::Data> = Endpoint::new(Method::Delete, "/series/<id>");
+/// Replaces the parts of the series with the listed posts, in order.
+pub const SET_POSTS: Endpoint<Vec<uuid::Uuid>, Vec<series::Part>> = Endpoint::new(Method::Put, "/series/<id>/posts");
@@ -0,0 +1,9 @@
+//! Endpoints describing the site as a whole.
+
+use crate::{Endpoint, Method};
+use blog_db::models::site;
+
+/// The mode the site is in, which anyone may check.
+pub const STATUS: Endpoint<(), site::Mode> = Endpoint::new(Method::Get, "/site/status");
+/// How the site presents itself: its title, tagline and logo.
+pub const IDENTITY: Endpoint<(), site::Identity> = Endpoint::new(Method::Get, "/site/identity");
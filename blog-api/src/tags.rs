@@ -0,0 +1,7 @@
+//! Endpoints for tags.
+
+use crate::{Endpoint, Method, Page};
+use blog_db::models::tags;
+
+/// A page of the tags in use, by name, for the `limit` and `offset` added to the url.
+pub const LIST: Endpoint<(), Page<tags::Data>> = Endpoint::new(Method::Get, "/tags");
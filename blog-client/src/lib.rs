@@ -1,8 +1,22 @@
-#![feature(drain_filter, move_ref_pattern)]
+// Followups reach the store through `StoreCallback`, so nothing needs raw access to it.
+#![forbid(unsafe_code)]
 
 #[macro_use]
 extern crate seed;
 
+/// The text for `key` in the locale the client is shown in, with the named arguments filled in,
+/// such as `tr!("editor.remove_tag", name = tag)`.
+#[macro_export]
+macro_rules! tr {
+    ($key:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        htmlgen::i18n::translate(
+            $crate::shared::i18n::current(),
+            $key,
+            &[$((stringify!($name), $value.to_string())),*],
+        )
+    };
+}
+
 mod logging;
 #[cfg(debug_assertions)]
 pub use logging::realtime_log_change;
@@ -19,6 +33,161 @@ use seed::prelude::*;
 use tap::*;
 
 fn update(msg: M, model: &mut Model, orders: &mut impl Orders<M, M>) {
+    let session = session_of(&model.store);
+    handle(msg, model, orders);
+    recheck_access(model, session, orders);
+    follow_account_theme(model);
+    follow_account_locale(model);
+    guard_unload(model, orders);
+    listen_for_editor_shortcuts(model, orders);
+    observe_listing_end(model, orders);
+    if !matches!(model.loc, locations::Location::Editor(_)) {
+        model.save_retry = None;
+    }
+}
+
+/// Holds back a page change away from unsaved edits and opens the dialog asking what to do with
+/// them. Returns whether the page change was held back.
+fn hold_navigation(model: &mut Model, loc: &locations::Location, url_changed: bool) -> bool {
+    if !model.has_unsaved_changes() || *loc == model.loc {
+        return false;
+    }
+    log::debug!("Holding back page change away from unsaved changes.");
+    model.pending_navigation = Some(model::PendingNavigation {
+        loc: loc.clone(),
+        url_changed,
+    });
+    true
+}
+
+/// Who is logged in, as far as the store knows.
+fn session_of(store: &model::Store) -> (bool, Option<uuid::Uuid>) {
+    (store.session_checked, store.user.as_ref().map(|user| user.id))
+}
+
+/// Loads the page again once who is logged in changes, if whether it may be seen depends on that.
+/// A page held back until the session was checked for loads once it is.
+fn recheck_access(model: &Model, before: (bool, Option<uuid::Uuid>), orders: &mut impl Orders<M, M>) {
+    if session_of(&model.store) == before {
+        return;
+    }
+    if let Some(page) = model.loc.guarded_page() {
+        log::debug!("Checking access to the page again.");
+        orders.send_msg(M::ChangePage(page));
+    }
+}
+
+/// Remembers how far down the page being left was scrolled, and where to scroll the page of the
+/// history entry now shown to once it renders. Entries visited for the first time start at the top.
+fn enter_history_entry(model: &mut Model) {
+    if let Some(left) = model.history_entry {
+        model.scroll_positions.insert(left, shared::history::scroll_y());
+    }
+    let entered = shared::history::current_entry(model.next_history_entry);
+    if entered == model.next_history_entry {
+        model.next_history_entry += 1;
+    }
+    model.history_entry = Some(entered);
+    model.pending_scroll = Some(model.scroll_positions.get(&entered).copied().unwrap_or(0.));
+}
+
+/// Moves focus to the heading of a page navigated to, so that screen readers start reading from
+/// it, and marks the page as current in the menu. Pages rendered again as their content loads keep
+/// focus where it is.
+fn announce_page(model: &mut Model) {
+    let page = model.loc.to_url().to_string();
+    if model.focused_page.as_ref() == Some(&page) {
+        return;
+    }
+    // Focus already starts at the top of the first page.
+    if model.focused_page.is_some() && !shared::focus::focus_heading() {
+        return;
+    }
+    if let Ok(path) = seed::window().location().pathname() {
+        shared::focus::mark_current_page(&path);
+    }
+    model.focused_page = Some(page);
+}
+
+/// Switches to the theme saved to the user's account once it loads, since it wins over whatever
+/// this browser had.
+fn follow_account_theme(model: &mut Model) {
+    let saved = model.store.user.as_ref().and_then(|user| user.preferences.theme);
+    if let Some(theme) = saved.filter(|theme| *theme != model.store.theme) {
+        model.store.theme = theme;
+        shared::theme::apply(theme);
+    }
+}
+
+/// Switches to the locale saved to the user's account once it loads, since it wins over whatever
+/// this browser had.
+fn follow_account_locale(model: &mut Model) {
+    let saved = model.store.user.as_ref()
+        .and_then(|user| user.preferences.locale.as_deref())
+        .and_then(shared::i18n::Locale::from_tag);
+    if let Some(locale) = saved.filter(|locale| *locale != model.store.locale) {
+        switch_locale(model, locale);
+    }
+}
+
+/// Shows text in the locale, including the menu outside of seed.
+fn switch_locale(model: &mut Model, locale: shared::i18n::Locale) {
+    model.store.locale = locale;
+    shared::i18n::apply(locale);
+    shared::views::replace_nav(if model.store.user.is_some() {
+        shared::Authorization::LoggedIn
+    } else {
+        shared::Authorization::LoggedOut
+    });
+}
+
+/// Makes the browser ask before closing the tab while there are unsaved edits.
+fn guard_unload(model: &mut Model, orders: &mut impl Orders<M, M>) {
+    match (model.has_unsaved_changes(), model.unload_guard.is_some()) {
+        (true, false) => {
+            let handle = orders.stream_with_handle(streams::window_event(Ev::BeforeUnload, |e| {
+                e.prevent_default();
+                if let Some(e) = e.dyn_ref::<web_sys::BeforeUnloadEvent>() {
+                    e.set_return_value("");
+                }
+            }));
+            model.unload_guard = Some(model::WindowListener::new(handle));
+        }
+        (false, true) => model.unload_guard = None,
+        _ => (),
+    }
+}
+
+/// Listens for the editor's keyboard shortcuts while the editor is open.
+fn listen_for_editor_shortcuts(model: &mut Model, orders: &mut impl Orders<M, M>) {
+    let in_editor = matches!(model.loc, locations::Location::Editor(_));
+    match (in_editor, model.editor_shortcuts.is_some()) {
+        (true, false) => {
+            let handle = locations::editor::shortcuts::listen(orders);
+            model.editor_shortcuts = Some(model::WindowListener::new(handle));
+        }
+        (false, true) => model.editor_shortcuts = None,
+        _ => (),
+    }
+}
+
+/// Watches for the end of the listing to come into view while there are more posts to load.
+fn observe_listing_end(model: &mut Model, orders: &mut impl Orders<M, M>) {
+    let next = match &model.loc {
+        locations::Location::Listing(s) => model
+            .store
+            .listing_for(&s.query_or_default())
+            .and_then(|listing| listing.next.clone()),
+        _ => None,
+    };
+    match next {
+        Some(next) if model.listing_observer.as_ref().map_or(false, |o| o.watches(&next)) => (),
+        Some(next) => model.listing_observer = locations::listing::infinite::observe(next, orders),
+        None => model.listing_observer = None,
+    }
+}
+
+fn handle(msg: M, model: &mut Model, orders: &mut impl Orders<M, M>) {
     log::info!("Processing message {:?}.", msg);
     match msg {
         M::NoOp => (),
@@ -30,31 +199,86 @@ fn update(msg: M, model: &mut Model, orders: &mut impl Orders<M, M>) {
         }
         M::UrlChanged(subs::UrlChanged(url)) => {
             log::debug!("Processing url {:?} change...", url.path());
+            // Links and the back and forward buttons have switched history entries by now.
+            enter_history_entry(model);
             if let Some(m) = routes(url) {
                 orders.skip().send_msg(m);
             }
         }
         M::ChangeMenu(is_logged_in) => shared::views::replace_nav(is_logged_in),
         M::ChangePageAndUrl(loc) => {
+            if hold_navigation(model, &loc, false) {
+                return;
+            }
             log::debug!("Running page change (programmatic)...");
             seed::push_route(loc.to_url());
+            enter_history_entry(model);
             orders.skip().send_msg(M::ChangePage(loc));
         }
         M::ChangePage(loc) => {
+            if hold_navigation(model, &loc, true) {
+                // The link already changed the url, so point it back at the editor.
+                seed::push_route(model.loc.to_url());
+                enter_history_entry(model);
+                model.pending_scroll = None;
+                return;
+            }
             log::debug!("Running page change...");
             loc.prep_page_for_render(&model.loc, &model.store, orders);
             orders.skip();
         }
+        M::UnsavedChanges(choice) => {
+            let pending = match model.pending_navigation.take() {
+                Some(pending) => pending,
+                None => return,
+            };
+            match choice {
+                messages::UnsavedChoice::Stay => (),
+                messages::UnsavedChoice::Discard => {
+                    update(M::Location(locations::M::Editor(locations::editor::M::Discard)), model, orders);
+                    update(pending.resume(), model, orders);
+                }
+                messages::UnsavedChoice::SaveAndGo => {
+                    let save = match &mut model.loc {
+                        locations::Location::Editor(s) => {
+                            s.attempt_save(locations::editor::slug_prefix(model.store.user.as_ref()))
+                        }
+                        _ => None,
+                    };
+                    match save {
+                        // If saving fails, the editor is still dirty and the dialog opens again.
+                        Some(save) => {
+                            orders.perform_cmd(async move { M::Grouped(vec![save.await, pending.resume()]) });
+                        }
+                        None => update(
+                            M::Notify(shared::notifications::Level::Error, "This post cannot be saved.".to_owned()),
+                            model,
+                            orders,
+                        ),
+                    }
+                }
+            }
+        }
         M::RenderPage(loc) => {
             log::debug!("Running render...");
             match loc.find_redirect(&model.store) {
                 Ok(loc) => {
-                    orders.skip().send_msg(M::ChangePageAndUrl(loc));
+                    orders.skip().send_msg(loc.into_redirect());
                 }
                 Err(loc) => {
                     model.loc = loc;
+                    seed::document().set_title(&model.loc.title(&model.store));
                     orders.force_render_now();
-                    if let Some(m) = model.loc.post_load_msgs() {
+                    // Scroll once there is a page to scroll, rather than while it is still loading.
+                    if !model.loc.is_loading() {
+                        if let Some(y) = model.pending_scroll.take() {
+                            shared::history::scroll_to(y);
+                        }
+                        announce_page(model);
+                    }
+                    // A page held back until the session is checked for is set up once it loads.
+                    let is_allowed = model.loc.access(&model.store) == locations::Access::Allowed;
+                    if let Some(m) = model.loc.post_load_msgs().filter(|_| is_allowed) {
                         orders.send_msg(m);
                     }
                 }
@@ -62,8 +286,13 @@ fn update(msg: M, model: &mut Model, orders: &mut impl Orders<M, M>) {
         }
         M::StoreOp(op) => {
             log::debug!("Running store operation...");
+            let retitles = matches!(op, model::StoreOperations::SiteIdentity(_));
             model.store.exec(op);
-            orders.skip();
+            if retitles {
+                seed::document().set_title(&model.loc.title(&model.store));
+            } else {
+                orders.skip();
+            }
         }
         M::StoreOpWithAction(op, f) => {
             log::debug!("Store operation with follow up action detected.");
@@ -76,6 +305,37 @@ fn update(msg: M, model: &mut Model, orders: &mut impl Orders<M, M>) {
             model.store.exec(op);
             update(m(), model, orders);
         }
+        M::SaveOffline => {
+            let attempt = model.save_retry.as_ref().map_or(0, |retry| retry.attempt + 1);
+            let delay = locations::editor::drafts::retry_delay_ms(attempt);
+            log::warn!("Save could not reach the server. Retrying in {}ms.", delay);
+            let timer = orders.perform_cmd_with_handle(cmds::timeout(delay, || {
+                M::Location(locations::M::Editor(locations::editor::M::RetrySave))
+            }));
+            model.save_retry = Some(model::SaveRetry::new(attempt, timer));
+        }
+        M::SaveOnline => model.save_retry = None,
+        M::SetTheme(theme) => {
+            model.store.theme = theme;
+            shared::theme::apply(theme);
+            if let Some(user) = model.store.user.as_mut() {
+                user.preferences.theme = Some(theme);
+                orders.perform_cmd(shared::preferences::save(user.preferences.clone()));
+            }
+        }
+        M::SetLocale(locale) => {
+            switch_locale(model, locale);
+            shared::i18n::remember(locale);
+            if let Some(user) = model.store.user.as_mut() {
+                user.preferences.locale = Some(locale.tag().to_owned());
+                orders.perform_cmd(shared::preferences::save(user.preferences.clone()));
+            }
+        }
+        M::Notify(level, text) => notify(model, orders, level, text, None),
+        M::NotifyWithAction(level, text, action) => notify(model, orders, level, text, Some(action)),
+        M::Dismiss(id) => {
+            model.notifications.retain(|n| n.id != id);
+        }
         // TODO remove boilerplate with macro?
         M::Location(msg) => {
             log::debug!("Handling location msg...");
@@ -85,8 +345,41 @@ fn update(msg: M, model: &mut Model, orders: &mut impl Orders<M, M>) {
     }
 }
 
+fn notify(
+    model: &mut Model,
+    orders: &mut impl Orders<M, M>,
+    level: shared::notifications::Level,
+    text: String,
+    action: Option<shared::notifications::Action>,
+) {
+    use shared::notifications::{Notification, DISMISS_AFTER_MS, MAX_SHOWN};
+    let id = model.next_notification_id;
+    model.next_notification_id += 1;
+    model.notifications.push(Notification { id, level, text, action });
+    if model.notifications.len() > MAX_SHOWN {
+        let excess = model.notifications.len() - MAX_SHOWN;
+        model.notifications.drain(..excess);
+    }
+    orders.perform_cmd(cmds::timeout(DISMISS_AFTER_MS, move || M::Dismiss(id)));
+}
+
 fn init(url: Url, orders: &mut impl Orders<M, M>) -> Model {
     log::info!("Running init with url {:?}", url);
+    shared::history::manage_scroll_restoration();
+    let theme = shared::theme::initial();
+    shared::theme::apply(theme);
+    let locale = shared::i18n::initial();
+    let initial = shared::initial_state::take();
+    // The server picked the language of the menu going by the browser, which an earlier pick on
+    // this browser wins over.
+    if shared::i18n::apply(locale) {
+        let logged_in = initial.as_ref().map_or(false, |state| state.user.is_some());
+        shared::views::replace_nav(if logged_in {
+            shared::Authorization::LoggedIn
+        } else {
+            shared::Authorization::LoggedOut
+        });
+    }
     orders
         .subscribe(M::UrlChanged)
         .notify(subs::UrlChanged(url.clone()))
@@ -94,23 +387,65 @@ fn init(url: Url, orders: &mut impl Orders<M, M>) -> Model {
         .subscribe(|listing| M::Location(locations::M::Listing(listing)))
         .subscribe(|viewer| M::Location(locations::M::Viewer(viewer)))
         .subscribe(|editor| M::Location(locations::M::Editor(editor)))
-        .perform_cmd(async {
-            let user = locations::login::find_current_user().await?;
-            Some(M::Grouped(vec![
-                M::StoreOp(model::StoreOperations::User(user)),
-                M::ChangeMenu(shared::Authorization::LoggedIn),
-            ]))
-        });
+        .subscribe(|settings| M::Location(locations::M::Settings(settings)))
+        .subscribe(|dashboard| M::Location(locations::M::Dashboard(dashboard)));
+    // Only ask for what the server did not hand over with the page.
+    if initial.is_none() {
+        orders
+            .perform_cmd(async {
+                match locations::login::find_current_user().await {
+                    Some(user) => M::Grouped(vec![
+                        M::StoreOp(model::StoreOperations::User(user)),
+                        M::ChangeMenu(shared::Authorization::LoggedIn),
+                    ]),
+                    None => M::StoreOp(model::StoreOperations::NoSession),
+                }
+            })
+            .perform_cmd(shared::site_mode::load())
+            .perform_cmd(shared::site_identity::load());
+    }
     if let Some(m) = routes(url) {
         orders.send_msg(m);
     }
-    Model::default()
+    let mut model = Model {
+        store: model::Store {
+            theme,
+            locale,
+            ..model::Store::default()
+        },
+        // Entries left over from before a reload keep their ids, so new ones start past them.
+        next_history_entry: js_sys::Date::now() as u64,
+        ..Model::default()
+    };
+    if let Some(state) = initial {
+        model.store.exec(model::StoreOperations::SiteMode(state.mode));
+        model.store.exec(model::StoreOperations::SiteIdentity(state.identity));
+        model = model.with_user(state.user);
+        follow_account_theme(&mut model);
+        follow_account_locale(&mut model);
+    }
+    model
 }
 
 fn view(m: &Model) -> impl IntoNodes<M> {
-    let Model { loc: l, store: s } = m;
+    let Model { loc: l, store: s, notifications: n, pending_navigation: p, save_retry: r, .. } = m;
     log::info!("Rendering location {:?} with global state {:?}.", l, s);
-    locations::view(l, s)
+    let mut nodes = vec![div![
+        attrs! { At::Class => "client-header" },
+        span![attrs! { At::Class => "site-title" }, &s.site_identity.title],
+        shared::theme::toggle(s.theme),
+        shared::i18n::picker(s.locale),
+    ]];
+    nodes.extend(shared::site_mode::banner(&s.site_mode));
+    nodes.extend(locations::view(l, s));
+    if r.is_some() {
+        nodes.push(shared::views::offline_indicator());
+    }
+    if p.is_some() {
+        nodes.push(shared::views::unsaved_changes_dialog());
+    }
+    nodes.push(shared::notifications::view(n));
+    nodes
 }
 
 fn routes(url: Url) -> Option<M> {
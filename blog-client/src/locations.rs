@@ -4,11 +4,15 @@ use serde::{Deserialize, Serialize};
 use crate::{
     messages::{AsyncM as GlobalAsyncM, M as GlobalM},
     model::{Store as GlobalS, StoreOperations as GSOp},
+    shared::{loadable::{self, Loadable}, retry::FailReason, views},
 };
 
+pub mod dashboard;
 pub mod editor;
 pub mod listing;
 pub mod login;
+pub mod series;
+pub mod settings;
 pub mod viewer;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)] 
@@ -17,6 +21,8 @@ pub enum M {
     Editor(editor::M),
     Viewer(viewer::M),
     Listing(listing::M),
+    Settings(settings::M),
+    Dashboard(dashboard::M),
 }
 
 pub fn update(msg: M, loc: &mut Location, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
@@ -33,6 +39,12 @@ pub fn update(msg: M, loc: &mut Location, gs: &GlobalS, orders: &mut impl Orders
         (M::Listing(m), Location::Listing(s)) => {
             listing::update(m, s, gs, orders);
         }
+        (M::Settings(m), Location::Settings(s)) => {
+            settings::update(m, s, gs, orders);
+        }
+        (M::Dashboard(m), Location::Dashboard(s)) => {
+            dashboard::update(m, s, gs, orders);
+        }
         _ => {
             log::warn!("Delayed message ignored.");
             orders.skip();
@@ -40,14 +52,29 @@ pub fn update(msg: M, loc: &mut Location, gs: &GlobalS, orders: &mut impl Orders
     }
 }
 
+/// Whether the user may see a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    Allowed,
+    /// Nobody is logged in, and the page is only for those who are.
+    RequiresLogin,
+    /// The user is logged in, but may not see the page.
+    Forbidden,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Location {
     Login(login::S),
     Viewer(viewer::S),
     Listing(listing::S),
+    Series(series::S),
     Editor(editor::S),
+    Settings(settings::S),
+    Dashboard(dashboard::S),
     Logout,
     NotFound,
+    /// The page the user was refused, kept so that it can be tried again.
+    Forbidden(Box<Location>),
 }
 impl Default for Location {
     fn default() -> Self {
@@ -55,37 +82,98 @@ impl Default for Location {
     }
 }
 impl Location {
+    /// Whether the user may see the page.
+    pub fn access(&self, gs: &GlobalS) -> Access {
+        match self {
+            Location::Editor(s) => editor::access(s, gs),
+            Location::Settings(_) => settings::access(gs),
+            Location::Dashboard(_) => dashboard::access(gs),
+            _ => Access::Allowed,
+        }
+    }
     pub fn find_redirect(self, gs: &GlobalS) -> Result<Self, Self> {
+        match self.access(gs) {
+            Access::Allowed => (),
+            // Until the session is checked for, the user may yet turn out to be logged in.
+            Access::RequiresLogin if !gs.session_checked => return Err(self),
+            Access::RequiresLogin => return Ok(Location::Login(login::S::returning_to(&self))),
+            Access::Forbidden => return Ok(Location::Forbidden(Box::new(self))),
+        }
         match &self {
             Location::Login(_) if gs.user.is_some() => Ok(Location::Listing(listing::S::default())),
-            // TODO Editor hops to not found. Pressing back will load the editor properly.
-            Location::Editor(s) if editor::is_restricted_from(s, gs) => Ok(Location::NotFound),
-            Location::Viewer(_) if viewer::is_restricted_from(gs) => Ok(Location::NotFound),
+            Location::Viewer(s) if viewer::is_restricted_from(s, gs) => Ok(Location::NotFound),
             _ => Err(self),
         }
     }
+    /// The message going to the page redirected to. Refusals are shown in place of the page
+    /// refused, keeping its url, so that going back does not land on the refusal again.
+    pub fn into_redirect(self) -> GlobalM {
+        match self {
+            Location::Forbidden(_) => GlobalM::RenderPage(self),
+            _ => GlobalM::ChangePageAndUrl(self),
+        }
+    }
+    /// The page to load again once who is logged in changes, if whether it may be seen depends on
+    /// that.
+    pub fn guarded_page(&self) -> Option<Location> {
+        match self {
+            Location::Forbidden(page) => Some((**page).clone()),
+            Location::Editor(_) | Location::Settings(_) | Location::Dashboard(_) => Some(self.clone()),
+            _ => None,
+        }
+    }
     pub fn fetch_req(self, gs: &GlobalS) -> Result<std::pin::Pin<Box<dyn GlobalAsyncM>>, Self> {
         match self {
-            Location::Listing(store) => Ok(Box::pin(listing::data_load(store))),
+            Location::Listing(s) => match gs.listing_for(&s.query_or_default()) {
+                // Pages loaded by scrolling are kept as they are, rather than swapped for the first
+                // page, so that coming back to the listing finds it as it was left.
+                Some(cached) if cached.is_fresh() || cached.pages > 1 => Err(Location::Listing(listing::S {
+                    listing: Loadable::Loaded(()),
+                    ..s
+                })),
+                _ => {
+                    let etag = listing::cached_etag(&s, gs);
+                    Ok(Box::pin(listing::data_load(s, etag)))
+                }
+            },
             Location::Logout => Ok(Box::pin(login::logout_trigger())),
-            Location::Editor(editor::S::Undetermined(post_id)) if gs.has_cached_post(&post_id) => {
+            Location::Editor(editor::S::Undetermined(post_id, _)) if gs.has_cached_post(&post_id) => {
+                let post = gs.post.as_ref().unwrap();
                 Err(Location::Editor(editor::S::Old(
-                    gs.post.as_ref().unwrap().clone(),
+                    post.clone(),
+                    Default::default(),
+                    editor::Access::Unchecked,
                     Default::default(),
+                    editor::Authors::of(post),
+                    Default::default(),
+                    Default::default(),
+                    None,
                 )))
             }
-            Location::Editor(editor::S::Undetermined(post_id)) if !gs.has_cached_post(&post_id) => {
+            Location::Editor(editor::S::Undetermined(post_id, _)) if !gs.has_cached_post(&post_id) => {
                 Ok(Box::pin(editor::load_post(post_id.clone())))
             }
             Location::Viewer(viewer::S {
                 post_marker: pm, ..
             }) => {
-                if gs.has_cached_post(&pm) {
-                    Err(Location::Viewer(pm.into()))
-                } else {
-                    Ok(Box::pin(viewer::load_post(pm.clone())))
+                match gs.post.as_ref() {
+                    Some(post) if pm.refers_to(post) => Err(Location::Viewer(viewer::S {
+                        post_marker: pm,
+                        post: Loadable::Loaded(post.clone()),
+                        tags: vec![],
+                        confirming: None,
+                        reacting: vec![],
+                    })),
+                    _ => Ok(Box::pin(viewer::load_post(pm.clone()))),
                 }
             }
+            Location::Series(s) if s.series.is_pending() => Ok(Box::pin(series::load_series(s.slug))),
+            Location::Settings(s) if s.needs_loading() => match gs.user.as_ref() {
+                Some(user) => Ok(Box::pin(settings::load(user.id))),
+                None => Err(Location::Settings(s)),
+            },
+            Location::Dashboard(s) if gs.site_stats.is_some() => Err(Location::Dashboard(s)),
+            Location::Dashboard(_) => Ok(Box::pin(dashboard::load_stats())),
             _ => Err(self),
         }
     }
@@ -99,9 +187,13 @@ impl Location {
             Self::Login(s) => s.to_url(),
             Self::Viewer(s) => s.to_url(),
             Self::Listing(s) => s.to_url(),
+            Self::Series(s) => s.to_url(),
             Self::Editor(s) => s.to_url(),
+            Self::Settings(s) => s.to_url(),
+            Self::Dashboard(s) => s.to_url(),
             Self::Logout => Url::new().set_path(vec!["blog", "logout"]),
             Self::NotFound => Url::new().set_path(vec!["blog", "404"]),
+            Self::Forbidden(page) => page.to_url(),
         }
     }
 }
@@ -116,37 +208,206 @@ impl Location {
         let loc = match self.find_redirect(&gs) {
             Ok(redirect) => {
                 log::trace!("Attempt to redirect to another page.");
-                orders.skip().send_msg(GlobalM::ChangePageAndUrl(redirect));
+                orders.skip().send_msg(redirect.into_redirect());
                 return;
             }
             Err(loc) => loc,
         };
+        if loc.access(gs) != Access::Allowed {
+            log::trace!("Holding back the page until the session is checked for.");
+            orders.skip().send_msg(GlobalM::RenderPage(loc));
+            return;
+        }
+        let loading = loc.with_load(Loadable::Loading);
         match loc.fetch_req(gs) {
             Ok(req) => {
                 log::trace!("Attempt to fetch data.");
                 orders.skip().perform_cmd(req);
+                if let Some(loading) = loading {
+                    orders.send_msg(GlobalM::RenderPage(loading));
+                }
             }
             Err(loc) => {
                 log::trace!("Attempt to render page directly, since data is already present.");
+                if let Some(refresh) = loc.refresh_req(gs) {
+                    orders.perform_cmd(refresh);
+                }
                 orders.skip().send_msg(GlobalM::RenderPage(loc));
             }
         }
     }
+    /// The title of the page, shown as the title of the document and in the browser history.
+    pub fn title(&self, gs: &GlobalS) -> String {
+        let page = match self {
+            Location::Listing(s) => listing::heading(s, gs),
+            Location::Viewer(viewer::S { post: Loadable::Loaded(post), .. }) => post.title.clone(),
+            Location::Viewer(viewer::S { post: Loadable::Failed(FailReason::NotFound), .. }) => {
+                "Post not found".to_owned()
+            }
+            Location::Viewer(_) => "Post".to_owned(),
+            Location::Series(series::S { series: Loadable::Loaded(series), .. }) => series.series.title.clone(),
+            Location::Series(_) => "Series".to_owned(),
+            Location::Editor(editor::S::New(post, ..)) if !post.title.is_empty() => {
+                format!("Editing {}", post.title)
+            }
+            Location::Editor(editor::S::New(..)) => "New post".to_owned(),
+            Location::Editor(editor::S::Old(post, ..)) => format!("Editing {}", post.title),
+            Location::Editor(editor::S::Undetermined(..)) => "Editor".to_owned(),
+            Location::Login(_) => "Log in".to_owned(),
+            Location::Settings(_) => "Settings".to_owned(),
+            Location::Dashboard(_) => "Dashboard".to_owned(),
+            Location::Logout => "Logging out".to_owned(),
+            Location::NotFound => "Page not found".to_owned(),
+            Location::Forbidden(_) => "Forbidden".to_owned(),
+        };
+        htmlgen::data::page_title(Some(&page), &gs.site_identity.title)
+    }
+    /// Whether the page is still waiting on the data it renders.
+    pub fn is_loading(&self) -> bool {
+        match self {
+            Location::Listing(s) => s.listing.is_pending(),
+            Location::Viewer(s) => s.post.is_pending(),
+            Location::Series(s) => s.series.is_pending(),
+            Location::Editor(s) => s.load().is_pending(),
+            _ => false,
+        }
+    }
+    /// The page with its fetch status set to `load`, for pages that wait on a fetch.
+    fn with_load(&self, load: Loadable<()>) -> Option<Self> {
+        match self {
+            Location::Listing(s) => Some(Location::Listing(listing::S {
+                listing: load,
+                ..s.clone()
+            })),
+            Location::Viewer(s) => Some(Location::Viewer(viewer::S {
+                post_marker: s.post_marker.clone(),
+                post: match load {
+                    Loadable::Loaded(()) => return None,
+                    Loadable::NotAsked => Loadable::NotAsked,
+                    Loadable::Loading => Loadable::Loading,
+                    Loadable::Failed(reason) => Loadable::Failed(reason),
+                },
+                tags: vec![],
+                confirming: None,
+                reacting: vec![],
+            })),
+            Location::Series(s) => Some(Location::Series(series::S {
+                slug: s.slug.clone(),
+                series: match load {
+                    Loadable::Loaded(()) => return None,
+                    Loadable::NotAsked => Loadable::NotAsked,
+                    Loadable::Loading => Loadable::Loading,
+                    Loadable::Failed(reason) => Loadable::Failed(reason),
+                },
+            })),
+            Location::Editor(editor::S::Undetermined(post_id, _)) => {
+                Some(Location::Editor(editor::S::Undetermined(post_id.clone(), load)))
+            }
+            _ => None,
+        }
+    }
+    /// Refreshes cached data the page is rendered from directly, in the background.
+    fn refresh_req(&self, gs: &GlobalS) -> Option<std::pin::Pin<Box<dyn GlobalAsyncM>>> {
+        match self {
+            Location::Listing(s) => match gs.listing_for(&s.query_or_default()) {
+                Some(cached) if cached.pages > 1 => None,
+                _ => Some(Box::pin(listing::refresh(s.clone(), listing::cached_etag(s, gs)))),
+            },
+            _ => None,
+        }
+    }
     pub fn post_load_msgs(&self) -> Option<GlobalM> {
         match self {
             Location::Login(_) => Some(GlobalM::Location(M::Login(login::M::SetFocus))),
+            Location::Listing(listing::S { listing: Loadable::Loaded(()), counts: None, .. }) => {
+                Some(GlobalM::Location(M::Listing(listing::M::LoadCounts)))
+            }
+            Location::Viewer(viewer::S { post: Loadable::Loaded(_), .. }) => {
+                Some(GlobalM::Location(M::Viewer(viewer::M::LoadTags)))
+            }
+            Location::Editor(editor::S::Old(_, _, editor::Access::Unchecked, ..)) => Some(GlobalM::Grouped(vec![
+                GlobalM::Location(M::Editor(editor::M::RestoreDraft)),
+                GlobalM::Location(M::Editor(editor::M::CheckAccess)),
+                GlobalM::Location(M::Editor(editor::M::LoadTags)),
+            ])),
+            Location::Editor(editor::S::New(..)) => Some(GlobalM::Grouped(vec![
+                GlobalM::Location(M::Editor(editor::M::ApplyDefaults)),
+                GlobalM::Location(M::Editor(editor::M::RestoreDraft)),
+                GlobalM::Location(M::Editor(editor::M::LoadTags)),
+            ])),
             _ => None,
         }
     }
 }
 
 pub fn view(loc: &Location, gs: &GlobalS) -> Vec<Node<GlobalM>> {
+    const LISTING_FAILURE: loadable::Failure<'static> = loadable::Failure {
+        attempt: "attempt.load_posts",
+        not_found: "not_found.posts",
+    };
+    const POST_FAILURE: loadable::Failure<'static> = loadable::Failure {
+        attempt: "attempt.load_post",
+        not_found: "not_found.post",
+    };
+    const SERIES_FAILURE: loadable::Failure<'static> = loadable::Failure {
+        attempt: "attempt.load_series",
+        not_found: "not_found.series",
+    };
+    // The page as it was before its data was asked for fetches the data again once navigated to.
+    let retry = || GlobalM::ChangePage(loc.with_load(Loadable::NotAsked).unwrap_or_else(|| loc.clone()));
     match loc {
+        // The page is held back until the session is checked for.
+        _ if loc.access(gs) != Access::Allowed => vec![views::loading()],
         Location::Logout => vec![h1!["Logging out..."]],
-        Location::Listing(s) => listing::render(s, gs).map_msg(M::Listing),
-        Location::Login(s) => vec![login::render(s, gs).map_msg(M::Login)],
-        Location::Viewer(s) => vec![viewer::render(s, gs).map_msg(M::Viewer)],
-        Location::Editor(s) => editor::render(s, gs).map_msg(M::Editor),
+        // A cached copy of the listing stays up while a fresher one loads.
+        Location::Listing(s) if s.listing.is_pending() && gs.listing_for(&s.query_or_default()).is_some() => {
+            let mut nodes = vec![views::refreshing()];
+            nodes.extend(listing::render(s, gs).map_msg(M::Listing).map_msg(GlobalM::Location));
+            nodes
+        }
+        Location::Listing(s) => loadable::view(
+            s.listing.as_ref(),
+            &LISTING_FAILURE,
+            retry(),
+            || vec![listing::render_skeleton(&listing::heading(s, gs))],
+            |_| listing::render(s, gs).map_msg(M::Listing).map_msg(GlobalM::Location),
+        ),
+        Location::Login(s) => vec![login::render(s, gs).map_msg(M::Login).map_msg(GlobalM::Location)],
+        Location::Viewer(s) => loadable::view(
+            s.post.as_ref(),
+            &POST_FAILURE,
+            retry(),
+            || vec![viewer::render_skeleton()],
+            |post| vec![viewer::render(post, s, gs).map_msg(M::Viewer).map_msg(GlobalM::Location)],
+        ),
+        Location::Series(s) => loadable::view(
+            s.series.as_ref(),
+            &SERIES_FAILURE,
+            retry(),
+            || vec![series::render_skeleton()],
+            |loaded| vec![series::render(loaded)],
+        ),
+        Location::Editor(editor::S::Undetermined(marker, Loadable::Failed(FailReason::NotFound))) => {
+            editor::render_not_found(marker).map_msg(M::Editor).map_msg(GlobalM::Location)
+        }
+        Location::Editor(s) => loadable::view(
+            s.load(),
+            &POST_FAILURE,
+            retry(),
+            || vec![views::loading()],
+            |_| editor::render(s, gs).map_msg(M::Editor).map_msg(GlobalM::Location),
+        ),
+        Location::Settings(s) => vec![settings::render(s, gs).map_msg(M::Settings).map_msg(GlobalM::Location)],
+        Location::Dashboard(s) => vec![dashboard::render(s, gs).map_msg(M::Dashboard).map_msg(GlobalM::Location)],
         Location::NotFound => vec![p!["Page not found!"]],
-    }.map_msg(GlobalM::Location)
+        Location::Forbidden(_) => vec![div![
+            attrs! { At::Class => "load-failed" },
+            h1!["Forbidden"],
+            p!["You are not allowed to see this page."],
+            a![
+                attrs! { At::Href => listing::S::url_root() },
+                "Back to the posts",
+            ],
+        ]],
+    }
 }
@@ -0,0 +1,40 @@
+use tap::*;
+
+use crate::{
+    locations::{Access, Location},
+    messages::{M as GlobalM, StoreCallback},
+    model::{Store as GlobalS, StoreOperations as GSOp},
+    requests::{self, api},
+    shared::retry,
+};
+
+mod messages;
+mod state;
+mod views;
+pub use messages::{update, M};
+pub use state::S;
+pub use views::render;
+
+pub async fn load_stats() -> GlobalM {
+    let res = requests::get(requests::Get::new(api::admin::STATS.url(&[]))).await.and_then(|res| res.json());
+    match res {
+        // Only admins may see the stats, which the client cannot tell ahead of time.
+        Err(retry::FailReason::Forbidden) => GlobalM::RenderPage(Location::Forbidden(Box::new(Location::Dashboard(S)))),
+        Err(reason) => reason.notify("attempt.load_stats"),
+        Ok(stats) => GlobalM::StoreOpWithAction(GSOp::SiteStats(stats), StoreCallback::new(|gs| {
+            gs.site_stats
+                .as_ref()
+                .map(|_| GlobalM::RenderPage(Location::Dashboard(S)))
+                .tap_none(|| log::error!("Site stats loaded but were not saved to store."))
+                .unwrap_or(GlobalM::NoOp)
+        })),
+    }
+}
+
+pub fn access(gs: &GlobalS) -> Access {
+    if gs.user.is_some() {
+        Access::Allowed
+    } else {
+        Access::RequiresLogin
+    }
+}
@@ -0,0 +1,22 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::dashboard::{self, S},
+    messages::M as GlobalM,
+    model::Store as GlobalS,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum M {
+    Refresh,
+}
+
+pub fn update(m: M, _s: &mut S, _gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    match m {
+        M::Refresh => {
+            log::trace!("Refreshing site stats...");
+            orders.perform_cmd(dashboard::load_stats());
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use seed::browser::url::Url;
+use serde::{Deserialize, Serialize};
+
+/// The dashboard has no state of its own; the stats it shows live in the global store.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S;
+impl S {
+    pub fn to_url(&self) -> Url {
+        Url::new().set_path(&["blog", "dashboard"])
+    }
+}
@@ -0,0 +1,52 @@
+use seed::prelude::*;
+
+use crate::{
+    locations::dashboard::{M, S},
+    model::Store as GlobalS,
+    shared,
+};
+use db_models::models::stats;
+
+fn card(label: &str, value: String) -> Node<M> {
+    div![
+        attrs! { At::Class => "stat-card" },
+        p![attrs! { At::Class => "stat-value as-h2" }, value],
+        p![attrs! { At::Class => "stat-label" }, label],
+    ]
+}
+
+fn cards(stats: &stats::Site) -> Node<M> {
+    div![
+        attrs! { At::Class => "stat-cards" },
+        card("Users", stats.users.to_string()),
+        card("Drafts", stats.draft_posts.to_string()),
+        card("Published", stats.published_posts.to_string()),
+        card("Archived", stats.archived_posts.to_string()),
+        card("Deleted", stats.deleted_posts.to_string()),
+        card(
+            "Newest post",
+            stats
+                .newest_post_at
+                .map_or_else(|| "None yet".to_owned(), |at| at.format("%F").to_string()),
+        ),
+    ]
+}
+
+pub fn render(_s: &S, gs: &GlobalS) -> Node<M> {
+    div![
+        attrs! { At::Class => "dashboard" },
+        h1!["Dashboard"],
+        match gs.site_stats.as_ref() {
+            Some(stats) => cards(stats),
+            None => shared::views::loading(),
+        },
+        button![
+            attrs! { At::Class => "inline-button" },
+            "Refresh",
+            ev(Ev::Click, |e| {
+                e.prevent_default();
+                M::Refresh
+            }),
+        ],
+    ]
+}
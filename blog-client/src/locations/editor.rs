@@ -1,56 +1,128 @@
-use tap::*;
+use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 
 use crate::{
-    locations::Location,
-    messages::{M as GlobalM, StoreCallback},
+    locations::{self, M as LocationM},
+    messages::M as GlobalM,
     model::{PostMarker, Store as GlobalS, StoreOperations as GSOp},
+    requests::{self, api},
     shared::retry,
 };
 use db_models::models::*;
 
+pub mod drafts;
 mod messages;
+pub mod preview;
+pub mod shortcuts;
 mod state;
 mod views;
 pub use messages::{update, M};
-pub use state::S;
-pub use views::render;
-
-const POST_LOAD_MSG: retry::LogPair<'static> = retry::LogPair {
-    pre_completion: "loading editor post",
-    post_completion: "parsing loaded editor post",
-};
+pub use state::{slug_prefix, Access, Authors, ModalState, PublishSummary, Tags, S};
+pub use views::{render, render_not_found};
 
+/// Fetches the post to edit, and hands it to the editor waiting on it once it is in the store.
 pub async fn load_post(post_marker: PostMarker) -> GlobalM {
-    const POSTS_URL: &str = "/api/posts";
-    let url = format!("{}/{}", POSTS_URL, post_marker);
-    let fo = retry::fetch_json_with_retry(
-        url.into(),
-        &POST_LOAD_MSG,
+    let url = api::posts::GET.url(&[&post_marker]);
+    let fo = requests::get(requests::Get::new(url)).await.and_then(|res| res.json());
+    match fo {
+        Err(reason) => GlobalM::Location(LocationM::Editor(M::ResolveFailed(reason))),
+        Ok(obj) => GlobalM::StoreOpWithMessage(
+            GSOp::Post(post_marker, obj),
+            || GlobalM::Location(LocationM::Editor(M::Resolved)),
+        ),
+    }
+}
+/// Asks the server whether the current user may edit the post, so that saving can be disabled
+/// ahead of time instead of failing.
+pub async fn check_access(post_id: uuid::Uuid) -> GlobalM {
+    let req = api::call(&api::posts::EDITABLE, &[&post_id]);
+    let access = match seed::browser::fetch::fetch(req).await {
+        Ok(res) if res.status().is_ok() => Access::Allowed,
+        Ok(res) if res.status().code == 403 => match res.text().await {
+            Ok(code) if code == posts::NOT_OWNER_ERROR => Access::NotOwner,
+            _ => Access::Allowed,
+        },
+        // Leave other failures to be reported when saving.
+        _ => Access::Allowed,
+    };
+    GlobalM::Location(LocationM::Editor(M::AccessChecked(access)))
+}
+pub async fn load_tags(post_id: uuid::Uuid) -> GlobalM {
+    const TAGS_LOAD_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "loading post tags",
+        post_completion: "parsing loaded post tags",
+    };
+    let res: Result<Vec<tags::Data>, _> = retry::fetch_json_with_retry(
+        api::call(&api::posts::TAGS, &[&post_id]),
+        &TAGS_LOAD_MSG,
         None,
     ).await;
-    match fo {
-        Err(_) => GlobalM::NoOp,
-        Ok(obj) => GlobalM::StoreOpWithAction(GSOp::Post(post_marker, obj), StoreCallback::new(|gs| {
-            gs.post
-                .as_ref()
-                .map(|post| GlobalM::RenderPage(Location::Editor(S::Old(
-                    post.clone(),
-                    posts::Changed::default(),
-                ))))
-                .tap_none(|| log::error!("Post loaded but was not saved to store."))
-                .unwrap_or(GlobalM::NoOp)
-        }))
+    match res {
+        Err(reason) => reason.notify("attempt.load_tags"),
+        Ok(loaded) => GlobalM::Location(LocationM::Editor(M::TagsLoaded(
+            loaded.into_iter().map(|tag| tag.name).collect(),
+        ))),
+    }
+}
+/// Loads the first page of the tags on the site, as large as pages come, to suggest while typing.
+/// Suggestions are a convenience, so failures are only logged.
+pub async fn load_known_tags() -> GlobalM {
+    const KNOWN_TAGS_LOAD_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "loading tag suggestions",
+        post_completion: "parsing tag suggestions",
+    };
+    let res: Result<api::Page<tags::Data>, _> = retry::fetch_json_with_retry(
+        api::call_page(&api::tags::LIST, &[], Some(api::page::MAX_LIMIT), 0),
+        &KNOWN_TAGS_LOAD_MSG,
+        None,
+    ).await;
+    match res {
+        Err(reason) => {
+            log::warn!("Failed to load tag suggestions: {:?}.", reason);
+            GlobalM::NoOp
+        }
+        Ok(known) => GlobalM::Location(LocationM::Editor(M::KnownTags(
+            known.items.into_iter().map(|tag| tag.name).collect(),
+        ))),
+    }
+}
+/// Looks for users to credit on the post. Only users who may edit the posts of others may search,
+/// so a refusal restricts the editor to crediting the user and those already credited.
+pub async fn search_authors(query: String) -> GlobalM {
+    const AUTHORS_SEARCH_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "searching for authors",
+        post_completion: "parsing found authors",
+    };
+    let url = format!(
+        "{}?name={}",
+        api::accounts::SEARCH.url(&[]),
+        percent_encode(query.as_bytes(), NON_ALPHANUMERIC),
+    );
+    let res: Result<Vec<users::AuthorSummary>, _> = retry::fetch_json_with_retry(
+        seed::browser::fetch::Request::new(url),
+        &AUTHORS_SEARCH_MSG,
+        retry::NO_RETRY,
+    ).await;
+    match res {
+        Err(retry::FailReason::Unauthorized) | Err(retry::FailReason::Forbidden) => {
+            GlobalM::Location(LocationM::Editor(M::AuthorSearchRefused))
+        }
+        Err(reason) => {
+            log::warn!("Failed to search for authors: {:?}.", reason);
+            GlobalM::NoOp
+        }
+        Ok(found) => GlobalM::Location(LocationM::Editor(M::AuthorsFound(query, found))),
     }
 }
-pub fn is_restricted_from(s: &S, gs: &GlobalS) -> bool {
+pub fn access(s: &S, gs: &GlobalS) -> locations::Access {
     if let Some(user) = gs.user.as_ref() {
         // TODO move this check onto the server for security
         match s {
-            S::Old(stored_post, _) => !stored_post.is_published() && !user.can_see_unpublished,
-            S::New(_) => false,
-            S::Undetermined(_) => false,
+            S::Old(stored_post, ..) if !stored_post.is_published() && !user.can_see_unpublished => {
+                locations::Access::Forbidden
+            }
+            S::Old(..) | S::New(..) | S::Undetermined(..) => locations::Access::Allowed,
         }
     } else {
-        true
+        locations::Access::RequiresLogin
     }
 }
@@ -0,0 +1,132 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::locations::editor::S;
+use db_models::models::*;
+
+/// Prefixes the local storage keys drafts are kept under.
+const KEY_PREFIX: &str = "editor-draft:";
+/// How long the first retry of a save that could not reach the server waits.
+const FIRST_RETRY_MS: u32 = 2_000;
+/// The longest a retry waits, however many retries came before it.
+const MAX_RETRY_MS: u32 = 60_000;
+
+/// The edits made in the editor, as kept in local storage until the server has them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Edits {
+    New(posts::NewNoMeta),
+    Old {
+        /// The post as the server had it when the edits were made.
+        base: posts::DataNoMeta,
+        changed: posts::Changed,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Draft {
+    edits: Edits,
+    /// The names of the tags as edited, if they were changed.
+    tags: Option<Vec<String>>,
+}
+
+/// What became of the draft found when the editor was opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Restored {
+    /// The draft was put back into the editor.
+    Newer,
+    /// The post was changed on the server since the draft was made, so the draft was dropped.
+    Stale,
+}
+
+/// The local storage key of the draft of the post, which is the post id, or `new` for a post that
+/// has not been created yet.
+fn key(s: &S) -> Option<String> {
+    let id = match s {
+        S::New(..) => "new".to_owned(),
+        S::Old(post, ..) => post.id.to_hyphenated_ref().to_string(),
+        S::Undetermined(..) => return None,
+    };
+    Some(format!("{}{}", KEY_PREFIX, id))
+}
+
+/// Drops the draft of a new post, once it has been created.
+pub fn clear_new() {
+    if let Some(key) = key(&S::default()) {
+        let _ = LocalStorage::remove(key);
+    }
+}
+
+/// Mirrors the edits into local storage, or removes the draft once nothing is left unsaved.
+pub fn sync(s: &S) {
+    let key = match key(s) {
+        Some(key) => key,
+        None => return,
+    };
+    if !s.is_dirty() {
+        let _ = LocalStorage::remove(key);
+        return;
+    }
+    let edits = match s {
+        S::New(post, ..) => Edits::New(post.clone()),
+        S::Old(base, changed, ..) => Edits::Old {
+            base: base.clone(),
+            changed: changed.clone(),
+        },
+        S::Undetermined(..) => return,
+    };
+    let draft = Draft {
+        edits,
+        tags: s.tags().and_then(|tags| tags.changed()),
+    };
+    if let Err(e) = LocalStorage::insert(key, &draft) {
+        log::error!("Failed to keep the draft in local storage: {:?}.", e);
+    }
+}
+
+/// Puts the draft of the post back into the editor, if it has one and the post has not changed on
+/// the server since.
+pub fn restore(s: &mut S) -> Option<Restored> {
+    let key = key(s)?;
+    let draft: Draft = LocalStorage::get(&key).ok()?;
+    let restored_tags = draft.tags;
+    match (s, draft.edits) {
+        (S::New(post, tags, ..), Edits::New(drafted)) => {
+            *post = drafted;
+            if let Some(names) = restored_tags {
+                tags.names = names;
+            }
+        }
+        (S::Old(post, changed, _, tags, ..), Edits::Old { base, changed: drafted })
+            if base.updated_at >= post.updated_at =>
+        {
+            *changed = drafted;
+            tags.restored = restored_tags;
+        }
+        _ => {
+            let _ = LocalStorage::remove(key);
+            return Some(Restored::Stale);
+        }
+    }
+    Some(Restored::Newer)
+}
+
+/// How long to wait before retrying a save, after `attempt` retries failed to reach the server.
+pub fn retry_delay_ms(attempt: u32) -> u32 {
+    FIRST_RETRY_MS
+        .checked_shl(attempt)
+        .filter(|delay| *delay >> attempt == FIRST_RETRY_MS)
+        .map_or(MAX_RETRY_MS, |delay| delay.min(MAX_RETRY_MS))
+}
+
+#[cfg(test)]
+mod test {
+    use super::retry_delay_ms;
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_a_cap() {
+        let delays: Vec<_> = (0..7).map(retry_delay_ms).collect();
+        assert_eq!(delays, vec![2_000, 4_000, 8_000, 16_000, 32_000, 60_000, 60_000]);
+        assert_eq!(retry_delay_ms(31), 60_000);
+        assert_eq!(retry_delay_ms(u32::MAX), 60_000);
+    }
+}
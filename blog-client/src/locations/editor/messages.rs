@@ -1,16 +1,30 @@
 use seed::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::pin::Pin;
 
 use crate::{
-    locations::{editor::S, Location},
-    messages::M as GlobalM,
-    model::Store as GlobalS,
+    locations::{
+        self,
+        editor::{self, Access, ModalState, S},
+        Location, M as LocationM,
+    },
+    messages::{AsyncM as GlobalAsyncM, M as GlobalM},
+    model::{PostMarker, Store as GlobalS, User},
+    shared::{
+        self,
+        loadable::Loadable,
+        notifications,
+        post_actions::{self, Action},
+        retry::FailReason,
+    },
 };
 use db_models::models::*;
+use login_enum::ValidationErrors;
 
 fn update_post(to_update: &mut posts::DataNoMeta, updated: &posts::DataNoMeta) {
     to_update.created_by = updated.created_by;
     to_update.created_at = updated.created_at;
+    to_update.updated_at = updated.updated_at;
     to_update.published_by = updated.published_by;
     to_update.published_at = updated.published_at;
     to_update.archived_by = updated.archived_by;
@@ -20,33 +34,315 @@ fn update_post(to_update: &mut posts::DataNoMeta, updated: &posts::DataNoMeta) {
     to_update.title = updated.title.clone();
     to_update.body = updated.body.clone();
     to_update.slug = updated.slug.clone();
+    to_update.meta_description = updated.meta_description.clone();
+    to_update.social_image = updated.social_image.clone();
+    to_update.authors = updated.authors.clone();
+    to_update.visibility = updated.visibility;
+}
+
+/// The user, as credited on the posts they write.
+fn summary_of(user: &User) -> users::AuthorSummary {
+    users::AuthorSummary {
+        id: user.id,
+        user_name: user.user_name.clone(),
+        first_name: None,
+        last_name: None,
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum M {
+    /// The post being fetched is in the store.
+    Resolved,
+    /// The post could not be fetched.
+    ResolveFailed(FailReason),
+    /// Starts a new post at the slug of the post that was not found.
+    CreateWithSlug,
     Title(String),
     Body(String),
     Slug(String),
+    /// What the post is described with where it is linked to.
+    MetaDescription(String),
+    /// The url of the image shown where the post is linked to.
+    SocialImage(String),
+    /// Asks the user to confirm publishing the post.
     Publish,
+    /// The user confirmed publishing the post.
+    ConfirmPublish,
+    /// The user closed whatever the editor asked them to confirm, without confirming it.
+    DismissModal,
+    /// Asks the user to confirm archiving or deleting the post.
+    AskTo(Action),
+    /// The user confirmed archiving the post.
+    Archive,
+    /// The user confirmed deleting the post.
+    Delete,
     Save,
+    /// Saves again after a save could not reach the server, unless the edits were saved since.
+    RetrySave,
+    /// Wraps the selected body text in bold markers.
+    Bold,
+    /// Wraps the selected body text in italic markers.
+    Italic,
+    /// Shows or hides the preview of the body.
+    TogglePreview,
+    /// Renders the preview, if the body was not edited again since the render numbered so was
+    /// scheduled.
+    RenderPreview(u64),
+    /// Scrolls the preview along with the body.
+    SyncPreviewScroll,
+    /// The tag name being typed in.
+    TagDraft(String),
+    AddTag(String),
+    RemoveTag(String),
 
     SyncPost,
+    /// The server has the edits.
+    Saved,
+    /// The server refused the edits, naming the fields at fault.
+    Invalid(ValidationErrors),
+    /// The user chose to leave without saving.
+    Discard,
+    /// Starts a new post the way the user starts new posts, unless it was edited already.
+    ApplyDefaults,
+    /// Puts back the edits kept in local storage, if any.
+    RestoreDraft,
+    CheckAccess,
+    AccessChecked(Access),
+    LoadTags,
+    /// The names of the tags the server has for the post.
+    TagsLoaded(Vec<String>),
+    /// The server has the tags, whose names are given.
+    TagsSaved(Vec<String>),
+    /// The names of every tag on the site.
+    KnownTags(Vec<String>),
+    /// The user name being searched for, to credit on the post.
+    AuthorQuery(String),
+    /// The users found for the query.
+    AuthorsFound(String, Vec<users::AuthorSummary>),
+    /// The server refused to search, so the user may only credit themselves.
+    AuthorSearchRefused,
+    /// Credits a user on the post as an editor, to be promoted if need be.
+    AddAuthor(users::AuthorSummary),
+    /// Credits the user on the post as an author.
+    CreditSelf,
+    RemoveAuthor(uuid::Uuid),
+    AuthorRole(uuid::Uuid, post_authors::Role),
+    /// The server credits the users given on the post.
+    AuthorsSaved(Vec<post_authors::Author>),
+}
+
+/// What applying a message asks of the rest of the app. The state changes are made directly, so
+/// these are all that is left to check of a message without a browser.
+pub enum Effect {
+    /// Runs the request, sending on the message it resolves to.
+    Perform(Pin<Box<dyn GlobalAsyncM>>),
+    Send(GlobalM),
+    /// Renders the preview numbered so once typing pauses.
+    SchedulePreview(u64),
+    /// Scrolls the preview along with the body once the page has been rendered again.
+    SyncPreviewScrollAfterRender,
+    /// Scrolls the preview along with the body now, without rendering the page again.
+    SyncPreviewScroll,
+    /// Changes the page to the editor at the url, without going through the router.
+    PushRoute(Url),
+    /// Drops the draft of a new post, once it has been created.
+    ClearNewDraft,
+    /// Mirrors the edits into local storage.
+    SyncDraft,
+    /// Titles the document after the post being edited.
+    Retitle,
+    /// Moves focus into the modal once it is rendered, so that it can be answered or dismissed
+    /// from the keyboard.
+    FocusModal,
+}
+impl std::fmt::Debug for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Perform(_) => write!(f, "Perform(request -- no display)"),
+            Self::Send(m) => f.debug_tuple("Send").field(m).finish(),
+            Self::SchedulePreview(scheduled) => f.debug_tuple("SchedulePreview").field(scheduled).finish(),
+            Self::SyncPreviewScrollAfterRender => write!(f, "SyncPreviewScrollAfterRender"),
+            Self::SyncPreviewScroll => write!(f, "SyncPreviewScroll"),
+            Self::PushRoute(url) => f.debug_tuple("PushRoute").field(url).finish(),
+            Self::ClearNewDraft => write!(f, "ClearNewDraft"),
+            Self::SyncDraft => write!(f, "SyncDraft"),
+            Self::Retitle => write!(f, "Retitle"),
+            Self::FocusModal => write!(f, "FocusModal"),
+        }
+    }
+}
+
+/// Archives or deletes the post, if the user was asked to confirm it.
+fn take_post_action(action: Action, s: &mut S, gs: &GlobalS, effects: &mut Vec<Effect>) {
+    if s.modal() != Some(ModalState::Confirm(action)) {
+        log::debug!("Ignoring {:?} that was not asked to be confirmed.", action);
+        return;
+    }
+    s.set_modal(None);
+    match (s.old_ref(), gs.user.as_ref(), action) {
+        (Some(post), Some(user), Action::Archive) => {
+            let then = GlobalM::Location(LocationM::Editor(M::SyncPost));
+            let req = post_actions::archive(post.clone(), user.id, then);
+            effects.push(Effect::Perform(Box::pin(req)));
+        }
+        (Some(post), Some(_), Action::Delete) => {
+            // Nothing is left to save once the post is gone.
+            let then = GlobalM::Location(LocationM::Editor(M::Discard));
+            effects.push(Effect::Perform(Box::pin(post_actions::delete(post.id, then))));
+        }
+        _ => log::error!("Attempted {:?} without a saved post or while logged out.", action),
+    }
+}
+
+/// Renders the preview once typing pauses, if it is shown.
+fn schedule_preview(s: &mut S, effects: &mut Vec<Effect>) {
+    if let Some(preview) = s.preview_mut().filter(|preview| preview.enabled) {
+        effects.push(Effect::SchedulePreview(preview.schedule()));
+    }
 }
 
 pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
-    use M::*;
-    match s {
-        S::Undetermined(_) => return,
+    for effect in transition(m, s, gs) {
+        match effect {
+            Effect::Perform(cmd) => {
+                orders.perform_cmd(cmd);
+            }
+            Effect::Send(m) => {
+                orders.send_msg(m);
+            }
+            Effect::SchedulePreview(scheduled) => {
+                orders.perform_cmd(cmds::timeout(editor::preview::DEBOUNCE_MS, move || {
+                    GlobalM::Location(LocationM::Editor(M::RenderPreview(scheduled)))
+                }));
+            }
+            Effect::SyncPreviewScrollAfterRender => {
+                orders.after_next_render(|_| GlobalM::Location(LocationM::Editor(M::SyncPreviewScroll)));
+            }
+            Effect::SyncPreviewScroll => {
+                orders.skip();
+                editor::preview::sync_scroll();
+            }
+            Effect::PushRoute(url) => seed::push_route(url),
+            Effect::ClearNewDraft => editor::drafts::clear_new(),
+            Effect::SyncDraft => editor::drafts::sync(s),
+            Effect::Retitle => seed::document().set_title(&Location::Editor(s.clone()).title(gs)),
+            Effect::FocusModal => {
+                orders.after_next_render(|_| {
+                    shared::views::focus_modal();
+                    GlobalM::NoOp
+                });
+            }
+        }
+    }
+}
+
+/// Applies `m` to the editor while it waits on the post named by `marker`, which only takes in the
+/// outcome of the fetch.
+///
+/// Nothing can be typed in before the post is shown, so swapping the post in loses no edits. Edits
+/// kept in local storage are restored once it is, as when the post was already cached.
+fn resolve(m: M, marker: PostMarker, s: &mut S, gs: &GlobalS, effects: &mut Vec<Effect>) {
+    match m {
+        M::Resolved => {
+            let post = match gs.post.as_ref().filter(|post| marker.refers_to(post)) {
+                Some(post) => post,
+                None => {
+                    log::warn!("Resolved {}, but the store has another post.", marker);
+                    return;
+                }
+            };
+            let old = S::Old(
+                post.clone(),
+                posts::Changed::default(),
+                Access::Unchecked,
+                editor::Tags::default(),
+                editor::Authors::of(post),
+                editor::preview::Preview::default(),
+                ValidationErrors::new(),
+                None,
+            );
+            // Posts the user may not see are turned away by the router.
+            if editor::access(&old, gs) != locations::Access::Allowed {
+                effects.push(Effect::Send(GlobalM::RenderPage(Location::Editor(old))));
+                return;
+            }
+            *s = old;
+            effects.push(Effect::Retitle);
+            effects.extend(Location::Editor(s.clone()).post_load_msgs().map(Effect::Send));
+        }
+        M::ResolveFailed(reason) => *s = S::Undetermined(marker, Loadable::Failed(reason)),
+        M::CreateWithSlug => {
+            let slug = match marker {
+                PostMarker::Slug(slug) => slug,
+                PostMarker::Uuid(_) => return,
+            };
+            let defaults = gs.user.as_ref().map(|user| user.preferences.post_defaults.clone());
+            *s = S::new_with(&defaults.unwrap_or_default());
+            s.update_slug(slug);
+            effects.push(Effect::PushRoute(s.to_url()));
+            effects.push(Effect::Retitle);
+            // Restoring the draft of a new post would replace the slug.
+            effects.push(Effect::Send(GlobalM::Location(LocationM::Editor(M::LoadTags))));
+        }
         _ => (),
-    };
+    }
+}
+
+/// The field the message edits, whose errors no longer apply once it is edited.
+fn edited_field(m: &M) -> Option<&'static str> {
+    match m {
+        M::Title(_) => Some("title"),
+        M::Body(_) | M::Bold | M::Italic => Some("body"),
+        M::Slug(_) => Some("slug"),
+        M::MetaDescription(_) => Some("meta_description"),
+        M::SocialImage(_) => Some("social_image"),
+        M::AddAuthor(_) | M::CreditSelf | M::RemoveAuthor(_) | M::AuthorRole(..) => Some("authors"),
+        _ => None,
+    }
+}
+
+/// Applies `m` to the state, returning what else it asks for, in the order it is to be done.
+pub fn transition(m: M, s: &mut S, gs: &GlobalS) -> Vec<Effect> {
+    use M::*;
+    let mut effects = vec![];
+    if let S::Undetermined(marker, _) = s {
+        let marker = marker.clone();
+        resolve(m, marker, s, gs, &mut effects);
+        return effects;
+    }
+    // Everything that changes the edits or what the server has of them.
+    let edits_changed = matches!(
+        m,
+        Title(_) | Body(_) | Slug(_) | MetaDescription(_) | SocialImage(_) | Bold | Italic | AddTag(_) | RemoveTag(_)
+            | SyncPost | Saved | Discard | TagsSaved(_)
+    );
+    if let (Some(field), Some(errors)) = (edited_field(&m), s.errors_mut()) {
+        errors.clear(field);
+    }
     match m {
         Title(title) => s.update_title(title),
-        Body(body) => s.update_body(body),
+        Body(body) => {
+            s.update_body(body);
+            schedule_preview(s, &mut effects);
+        }
         Slug(slug) => s.update_slug(slug),
+        MetaDescription(description) => s.update_meta_description(description),
+        SocialImage(image) => s.update_social_image(image),
+        Publish | ConfirmPublish if !s.is_publishable() => log::debug!("Ignoring publish of unpublishable post."),
+        Publish if gs.user.is_none() => log::error!("Attempted publish while not logged in."),
         Publish => {
+            s.set_modal(Some(ModalState::ConfirmPublish));
+            effects.push(Effect::FocusModal);
+        }
+        ConfirmPublish if s.modal() != Some(ModalState::ConfirmPublish) => {
+            log::debug!("Ignoring publish that was not asked to be confirmed.")
+        }
+        ConfirmPublish => {
+            s.set_modal(None);
             if let Some(user) = gs.user.as_ref() {
                 if let Some(req) = s.attempt_publish(user) {
-                    orders.perform_cmd(req);
+                    effects.push(Effect::Perform(req));
                 } else {
                     log::error!("Failed to create publish request.")
                 }
@@ -54,28 +350,468 @@ pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, G
                 log::error!("Attempted publish while not logged in.")
             }
         }
+        AskTo(action) if !s.post_actions(gs.user.as_ref()).contains(&action) => {
+            log::debug!("Ignoring {:?} the user may not take.", action)
+        }
+        AskTo(action) => {
+            s.set_modal(Some(ModalState::Confirm(action)));
+            effects.push(Effect::FocusModal);
+        }
+        Archive => take_post_action(Action::Archive, s, gs, &mut effects),
+        Delete => take_post_action(Action::Delete, s, gs, &mut effects),
         Save => {
-            if let Some(req) = s.attempt_save() {
-                orders.perform_cmd(req);
+            if let Some(req) = s.attempt_save(editor::slug_prefix(gs.user.as_ref())) {
+                effects.push(Effect::Perform(req));
             } else {
                 log::error!("Failed to create save request.");
             }
         }
+        RetrySave if !s.is_dirty() => effects.push(Effect::Send(GlobalM::SaveOnline)),
+        RetrySave => {
+            log::info!("Retrying save that could not reach the server.");
+            if let Some(req) = s.attempt_save(editor::slug_prefix(gs.user.as_ref())) {
+                effects.push(Effect::Perform(req));
+            }
+        }
+        Bold | Italic => {
+            let marker = if m == Bold { "**" } else { "*" };
+            match editor::shortcuts::wrap_body_selection(marker) {
+                Some(body) => {
+                    s.update_body(body);
+                    schedule_preview(s, &mut effects);
+                }
+                None => log::warn!("Could not find the selection to format."),
+            }
+        }
+        TogglePreview => {
+            let body = s.body().unwrap_or("").to_owned();
+            if let Some(preview) = s.preview_mut() {
+                preview.enabled = !preview.enabled;
+                preview.rendered = body;
+            }
+            effects.push(Effect::SyncPreviewScrollAfterRender);
+        }
+        RenderPreview(scheduled) => {
+            let body = s.body().unwrap_or("").to_owned();
+            if let Some(preview) = s.preview_mut() {
+                preview.render(scheduled, &body);
+            }
+            effects.push(Effect::SyncPreviewScrollAfterRender);
+        }
+        SyncPreviewScroll => effects.push(Effect::SyncPreviewScroll),
+        DismissModal => s.set_modal(None),
 
         SyncPost => {
             if let Some(updated) = &gs.post {
                 match s {
-                    S::Old(post, _) if post.id == updated.id => update_post(post, updated),
+                    S::Old(post, ..) if post.id == updated.id => update_post(post, updated),
                     _ => {
-                        orders.send_msg(GlobalM::ChangePageAndUrl(Location::Editor(S::Old(
+                        // Swap in the saved post directly instead of navigating, so that leaving
+                        // the unsaved new post behind doesn't trip the unsaved changes guard.
+                        if let S::New(..) = s {
+                            effects.push(Effect::ClearNewDraft);
+                        }
+                        let tags = s.tags_mut().map(std::mem::take).unwrap_or_default();
+                        let preview = s.preview_mut().map(std::mem::take).unwrap_or_default();
+                        *s = S::Old(
                             updated.clone(),
                             posts::Changed::default(),
-                        ))));
+                            Access::Allowed,
+                            tags,
+                            editor::Authors::of(updated),
+                            preview,
+                            ValidationErrors::new(),
+                            None,
+                        );
+                        effects.push(Effect::PushRoute(s.to_url()));
                     }
                 }
+                effects.push(Effect::Send(GlobalM::SaveOnline));
             } else {
                 log::warn!("Attempted to sync with nonexistent post.");
             }
         }
+        Saved => {
+            s.mark_saved();
+            if let Some(errors) = s.errors_mut() {
+                *errors = ValidationErrors::new();
+            }
+            effects.push(Effect::Send(GlobalM::SaveOnline));
+        }
+        Invalid(found) => {
+            if let Some(errors) = s.errors_mut() {
+                *errors = found;
+            }
+        }
+        ApplyDefaults => match gs.user.as_ref() {
+            Some(user) if matches!(s, S::New(..)) && !s.is_dirty() => {
+                *s = S::new_with(&user.preferences.post_defaults);
+            }
+            _ => (),
+        },
+        RestoreDraft => {
+            let text = match editor::drafts::restore(s) {
+                Some(editor::drafts::Restored::Newer) => "Restored the unsaved changes kept on this device.",
+                Some(editor::drafts::Restored::Stale) => {
+                    "Dropped the changes kept on this device, since the post was changed elsewhere after they were made."
+                }
+                None => return effects,
+            };
+            schedule_preview(s, &mut effects);
+            effects.push(Effect::Send(GlobalM::Notify(notifications::Level::Info, text.to_owned())));
+        }
+        Discard => s.discard_changes(),
+        CheckAccess => {
+            if let Some(post) = s.old_ref() {
+                effects.push(Effect::Perform(Box::pin(editor::check_access(post.id))));
+            }
+        }
+        AccessChecked(access) => s.set_access(access),
+        TagDraft(draft) => {
+            if let Some(tags) = s.tags_mut() {
+                tags.draft = draft;
+            }
+        }
+        AddTag(name) => {
+            if let Some(tags) = s.tags_mut() {
+                tags.add(&name);
+            }
+        }
+        RemoveTag(name) => {
+            if let Some(tags) = s.tags_mut() {
+                tags.remove(&name);
+            }
+        }
+        LoadTags => {
+            if let Some(post) = s.old_ref() {
+                effects.push(Effect::Perform(Box::pin(editor::load_tags(post.id))));
+            }
+            effects.push(Effect::Perform(Box::pin(editor::load_known_tags())));
+        }
+        TagsLoaded(names) | TagsSaved(names) => {
+            if let Some(tags) = s.tags_mut() {
+                tags.set_saved(names);
+            }
+        }
+        KnownTags(names) => {
+            if let Some(tags) = s.tags_mut() {
+                tags.known = names;
+            }
+        }
+        AuthorQuery(query) => {
+            if let Some(authors) = s.authors_mut() {
+                authors.query = query.clone();
+                authors.found.clear();
+                if !authors.restricted && !query.trim().is_empty() {
+                    effects.push(Effect::Perform(Box::pin(editor::search_authors(query))));
+                }
+            }
+        }
+        AuthorsFound(query, found) => {
+            if let Some(authors) = s.authors_mut().filter(|authors| authors.query == query) {
+                authors.found = found;
+            }
+        }
+        AuthorSearchRefused => {
+            if let Some(authors) = s.authors_mut() {
+                authors.restricted = true;
+                authors.found.clear();
+            }
+        }
+        AddAuthor(user) => {
+            let me = gs.user.as_ref().map(summary_of);
+            if let Some(authors) = s.authors_mut() {
+                // A new post credits the user alone until told otherwise, so they come first.
+                if let (true, Some(me)) = (authors.credited.is_empty(), me) {
+                    authors.add(me, post_authors::Role::Author);
+                }
+                authors.add(user, post_authors::Role::Editor);
+            }
+        }
+        CreditSelf => {
+            let me = gs.user.as_ref().map(summary_of);
+            if let (Some(authors), Some(me)) = (s.authors_mut(), me) {
+                authors.add(me, post_authors::Role::Author);
+            }
+        }
+        RemoveAuthor(user_id) => {
+            if let Some(authors) = s.authors_mut() {
+                authors.remove(user_id);
+            }
+        }
+        AuthorRole(user_id, role) => {
+            if let Some(authors) = s.authors_mut() {
+                authors.set_role(user_id, role);
+            }
+        }
+        AuthorsSaved(saved) => {
+            if let Some(authors) = s.authors_mut() {
+                authors.saved = saved;
+            }
+        }
+        // Answers to a fetch the editor stopped waiting on.
+        Resolved | ResolveFailed(_) | CreateWithSlug => (),
+    }
+    if edits_changed {
+        effects.push(Effect::SyncDraft);
+    }
+    effects
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn saved(id: u128, title: &str) -> posts::DataNoMeta {
+        let now = chrono::Utc::now();
+        posts::DataNoMeta {
+            id: uuid::Uuid::from_u128(id),
+            created_at: now,
+            created_by: uuid::Uuid::nil(),
+            updated_at: now,
+            published_at: None,
+            published_by: None,
+            archived_at: None,
+            archived_by: None,
+            deleted_at: None,
+            deleted_by: None,
+            title: title.to_owned(),
+            body: String::new(),
+            slug: None,
+            meta_description: String::new(),
+            social_image: String::new(),
+            authors: vec![],
+            series: vec![],
+            outline: vec![],
+            html: None,
+            visibility: posts::Visibility::default(),
+            reactions: Default::default(),
+        }
+    }
+
+    fn editing(post: posts::DataNoMeta) -> S {
+        S::Old(
+            post,
+            posts::Changed::default(),
+            Access::Allowed,
+            editor::Tags::default(),
+            editor::Authors::default(),
+            editor::preview::Preview::default(),
+            ValidationErrors::new(),
+            None,
+        )
+    }
+
+    fn with_synced(post: posts::DataNoMeta) -> GlobalS {
+        GlobalS {
+            post: Some(post),
+            ..GlobalS::default()
+        }
+    }
+
+    fn ben() -> User {
+        User {
+            id: uuid::Uuid::from_u128(9),
+            user_name: "ben".to_owned(),
+            name: crate::model::Name {
+                first: "Ben".to_owned(),
+                last: "Xu".to_owned(),
+                nickname: "ben".to_owned(),
+            },
+            can_see_unpublished: false,
+            preferences: users::Preferences::default(),
+        }
+    }
+
+    #[test]
+    fn blank_slugs_clear_the_slug() {
+        let mut s = S::default();
+        transition(M::Slug("first-post".to_owned()), &mut s, &GlobalS::default());
+        transition(M::Slug("  ".to_owned()), &mut s, &GlobalS::default());
+        match s {
+            S::New(post, ..) => assert_eq!(post.slug, None),
+            s => panic!("expected a new post, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn new_posts_start_with_the_defaults_of_the_user() {
+        let defaults = users::PostDefaults {
+            tags: vec!["rust".to_owned()],
+            slug_prefix: "notes-".to_owned(),
+            editor_mode: users::EditorMode::Preview,
+        };
+        let mut s = S::new_with(&defaults);
+        assert!(!s.is_dirty());
+        assert!(s.preview().map_or(false, |preview| preview.enabled));
+        assert_eq!(s.tags().map(|tags| tags.names.clone()), Some(vec!["rust".to_owned()]));
+        transition(M::RemoveTag("rust".to_owned()), &mut s, &GlobalS::default());
+        assert!(s.is_dirty());
+        assert_eq!(
+            editor::state::prefixed_slug(Some("first"), &defaults.slug_prefix),
+            Some("notes-first".to_owned())
+        );
+        assert_eq!(
+            editor::state::prefixed_slug(Some("notes-first"), &defaults.slug_prefix),
+            Some("notes-first".to_owned())
+        );
+    }
+
+    #[test]
+    fn nothing_is_done_before_the_post_is_fetched() {
+        let mut s = S::Undetermined(PostMarker::Uuid(uuid::Uuid::nil()), Loadable::NotAsked);
+        let before = s.clone();
+        let effects = transition(M::Publish, &mut s, &GlobalS::default());
+        assert!(effects.is_empty(), "unexpected {:?}", effects);
+        assert_eq!(s, before);
+    }
+
+    #[test]
+    fn fetched_posts_are_edited_in_place() {
+        let mut fetched = saved(1, "fetched");
+        fetched.slug = Some("fetched".to_owned());
+        fetched.published_at = Some(chrono::Utc::now());
+        let gs = GlobalS {
+            user: Some(ben()),
+            ..with_synced(fetched.clone())
+        };
+        let mut s = S::Undetermined(PostMarker::Slug("fetched".to_owned()), Loadable::Loading);
+        let effects = transition(M::Resolved, &mut s, &gs);
+        assert_eq!(s.old_ref(), Some(&fetched));
+        assert!(matches!(effects.first(), Some(Effect::Retitle)), "unexpected {:?}", effects);
+
+        // Another post in the store leaves the editor waiting.
+        let mut s = S::Undetermined(PostMarker::Slug("other".to_owned()), Loadable::Loading);
+        assert!(transition(M::Resolved, &mut s, &gs).is_empty());
+        assert_eq!(s.load(), Loadable::Loading);
+    }
+
+    #[test]
+    fn missing_posts_may_be_created_at_their_slug() {
+        let mut s = S::Undetermined(PostMarker::Slug("missing".to_owned()), Loadable::Loading);
+        transition(M::ResolveFailed(FailReason::NotFound), &mut s, &GlobalS::default());
+        assert_eq!(s.load(), Loadable::Failed(FailReason::NotFound));
+        let effects = transition(M::CreateWithSlug, &mut s, &GlobalS::default());
+        match &s {
+            S::New(post, ..) => assert_eq!(post.slug.as_deref(), Some("missing")),
+            s => panic!("expected a new post, got {:?}", s),
+        }
+        assert!(matches!(effects.first(), Some(Effect::PushRoute(_))), "unexpected {:?}", effects);
+    }
+
+    #[test]
+    fn syncing_another_post_moves_the_editor_to_it() {
+        let mut s = editing(saved(1, "old"));
+        let gs = with_synced(saved(2, "new"));
+        let effects = transition(M::SyncPost, &mut s, &gs);
+        assert_eq!(s.old_ref(), gs.post.as_ref());
+        match effects.as_slice() {
+            [Effect::PushRoute(url), Effect::Send(GlobalM::SaveOnline), Effect::SyncDraft] => {
+                assert_eq!(url.to_string(), s.to_url().to_string())
+            }
+            effects => panic!("unexpected {:?}", effects),
+        }
+    }
+
+    #[test]
+    fn syncing_a_created_post_drops_its_draft() {
+        let mut s = S::default();
+        let effects = transition(M::SyncPost, &mut s, &with_synced(saved(2, "new")));
+        assert!(
+            matches!(effects.first(), Some(Effect::ClearNewDraft)),
+            "unexpected {:?}",
+            effects
+        );
+        assert!(effects.iter().any(|effect| matches!(effect, Effect::PushRoute(_))));
+    }
+
+    #[test]
+    fn syncing_the_same_post_merges_it_in_place() {
+        let mut s = editing(saved(1, "old"));
+        transition(M::Title("edited".to_owned()), &mut s, &GlobalS::default());
+        let mut updated = saved(1, "renamed elsewhere");
+        updated.slug = Some("renamed".to_owned());
+        let effects = transition(M::SyncPost, &mut s, &with_synced(updated.clone()));
+        assert!(
+            !effects.iter().any(|effect| matches!(effect, Effect::PushRoute(_))),
+            "unexpected {:?}",
+            effects
+        );
+        match s {
+            S::Old(post, changed, ..) => {
+                assert_eq!(post.title, updated.title);
+                assert_eq!(post.slug, updated.slug);
+                assert_eq!(changed.title, Some("edited".to_owned()));
+            }
+            s => panic!("expected a saved post, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn refused_fields_are_cleared_once_edited() {
+        let mut s = editing(saved(1, "old"));
+        let gs = GlobalS::default();
+        let mut errors = ValidationErrors::single("title", "required", "Enter a title.");
+        errors.add("slug", "invalid", "Use only letters, numbers, and dashes.");
+        transition(M::Invalid(errors), &mut s, &gs);
+        assert_eq!(s.errors().unwrap().fields().collect::<Vec<_>>(), vec!["slug", "title"]);
+
+        transition(M::Title("new".to_owned()), &mut s, &gs);
+        assert_eq!(s.errors().unwrap().fields().collect::<Vec<_>>(), vec!["slug"]);
+        transition(M::Saved, &mut s, &gs);
+        assert!(s.errors().unwrap().is_empty());
+    }
+
+    #[test]
+    fn publishing_waits_for_confirmation() {
+        let mut s = editing(saved(1, "unslugged"));
+        let gs = GlobalS {
+            user: Some(ben()),
+            ..GlobalS::default()
+        };
+        let effects = transition(M::Publish, &mut s, &gs);
+        assert!(matches!(effects.as_slice(), [Effect::FocusModal]), "unexpected {:?}", effects);
+        assert_eq!(s.modal(), Some(ModalState::ConfirmPublish));
+        let summary = s.publish_summary(&ben()).unwrap();
+        assert!(summary.at_id);
+        assert_eq!(
+            summary.path.as_deref(),
+            Some("/blog/posts/00000000-0000-0000-0000-000000000001")
+        );
+
+        transition(M::DismissModal, &mut s, &gs);
+        assert_eq!(s.modal(), None);
+        assert!(transition(M::ConfirmPublish, &mut s, &gs).is_empty());
+
+        transition(M::Publish, &mut s, &gs);
+        let effects = transition(M::ConfirmPublish, &mut s, &gs);
+        assert_eq!(s.modal(), None);
+        assert!(matches!(effects.as_slice(), [Effect::Perform(_)]), "unexpected {:?}", effects);
+    }
+
+    #[test]
+    fn archiving_and_deleting_wait_for_confirmation() {
+        let mut s = editing(saved(1, "old"));
+        let gs = GlobalS {
+            user: Some(ben()),
+            ..GlobalS::default()
+        };
+        assert_eq!(s.post_actions(gs.user.as_ref()), vec![Action::Archive, Action::Delete]);
+        assert!(transition(M::Delete, &mut s, &gs).is_empty());
+
+        let effects = transition(M::AskTo(Action::Delete), &mut s, &gs);
+        assert!(matches!(effects.as_slice(), [Effect::FocusModal]), "unexpected {:?}", effects);
+        assert!(transition(M::Archive, &mut s, &gs).is_empty());
+        assert_eq!(s.modal(), Some(ModalState::Confirm(Action::Delete)));
+        let effects = transition(M::Delete, &mut s, &gs);
+        assert!(matches!(effects.as_slice(), [Effect::Perform(_)]), "unexpected {:?}", effects);
+        assert_eq!(s.modal(), None);
+
+        // Archived posts can only be deleted.
+        let mut archived = saved(1, "old");
+        archived.archived_at = Some(chrono::Utc::now());
+        let mut s = editing(archived);
+        assert!(transition(M::AskTo(Action::Archive), &mut s, &gs).is_empty());
+        assert_eq!(s.modal(), None);
     }
 }
@@ -0,0 +1,99 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use htmlgen::markdown::render_markdown;
+
+/// How long typing has to pause before the preview catches up with the body.
+pub const DEBOUNCE_MS: u32 = 200;
+/// Selects the body textarea the preview follows.
+const BODY_FIELD: &str = ".editor-body > textarea";
+/// Selects the pane the preview is rendered into.
+const PREVIEW_PANE: &str = ".editor-preview";
+
+/// The rendered preview of the body, shown next to it when enabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Preview {
+    pub enabled: bool,
+    /// The body as of the last time the preview was rendered.
+    pub rendered: String,
+    /// Counts the edits to the body, so that only the render scheduled by the latest edit runs.
+    pub pending: u64,
+}
+impl Preview {
+    /// Notes an edit to the body, returning the number to render the preview with once typing
+    /// pauses.
+    pub fn schedule(&mut self) -> u64 {
+        self.pending += 1;
+        self.pending
+    }
+    /// Renders the body, unless another edit has been scheduled since `scheduled`.
+    pub fn render(&mut self, scheduled: u64, body: &str) {
+        if scheduled == self.pending {
+            self.rendered = body.to_owned();
+        }
+    }
+}
+
+/// Renders the markdown the same way the viewer does, so that the preview matches the published
+/// post, save for code, which only the server highlights.
+pub fn view<M: 'static>(preview: &Preview) -> Node<M> {
+    div![
+        attrs! { At::Class => "editor-preview post" },
+        raw![render_markdown(&preview.rendered).html.as_str()],
+    ]
+}
+
+/// Scrolls the preview to the same proportion of the way down as the body textarea.
+pub fn sync_scroll() {
+    let document = seed::document();
+    let (body, pane) = match (
+        document.query_selector(BODY_FIELD).ok().flatten(),
+        document.query_selector(PREVIEW_PANE).ok().flatten(),
+    ) {
+        (Some(body), Some(pane)) => (body, pane),
+        _ => return,
+    };
+    let scroll_top = proportional_scroll(
+        body.scroll_top(),
+        body.scroll_height() - body.client_height(),
+        pane.scroll_height() - pane.client_height(),
+    );
+    pane.set_scroll_top(scroll_top);
+}
+
+/// The scroll offset `to_range` is scrolled to when scrolled as far along as `from_top` is along
+/// `from_range`, where the ranges are how far each can scroll.
+fn proportional_scroll(from_top: i32, from_range: i32, to_range: i32) -> i32 {
+    if from_range <= 0 || to_range <= 0 {
+        return 0;
+    }
+    let from_top = from_top.max(0).min(from_range);
+    (i64::from(from_top) * i64::from(to_range) / i64::from(from_range)) as i32
+}
+
+#[cfg(test)]
+mod test {
+    use super::{proportional_scroll, Preview};
+
+    #[test]
+    fn scrolls_in_proportion() {
+        assert_eq!(proportional_scroll(50, 100, 300), 150);
+        assert_eq!(proportional_scroll(100, 100, 40), 40);
+        assert_eq!(proportional_scroll(0, 100, 40), 0);
+        // Either side may be too short to scroll at all, or overscrolled.
+        assert_eq!(proportional_scroll(30, 0, 40), 0);
+        assert_eq!(proportional_scroll(30, 100, 0), 0);
+        assert_eq!(proportional_scroll(130, 100, 40), 40);
+    }
+
+    #[test]
+    fn only_the_latest_edit_renders() {
+        let mut preview = Preview::default();
+        let first = preview.schedule();
+        let second = preview.schedule();
+        preview.render(first, "# stale");
+        assert_eq!(preview.rendered, "");
+        preview.render(second, "# latest");
+        assert_eq!(preview.rendered, "# latest");
+    }
+}
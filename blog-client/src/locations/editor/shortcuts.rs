@@ -0,0 +1,101 @@
+use seed::prelude::*;
+
+use crate::{
+    locations::{editor::M, M as LocationM},
+    messages::M as GlobalM,
+};
+
+/// Selects the editor fields the shortcuts apply to.
+const EDITOR_FIELDS: &str = ".editor input, .editor textarea";
+/// Selects the body textarea that formatting shortcuts apply to.
+const BODY_FIELD: &str = ".editor-body > textarea";
+
+/// Listens on the window for shortcuts pressed in the editor fields, until the handle is dropped.
+pub fn listen(orders: &mut impl Orders<GlobalM, GlobalM>) -> StreamHandle {
+    orders.stream_with_handle(streams::window_event(Ev::KeyDown, |e| {
+        let e = e.dyn_into::<web_sys::KeyboardEvent>().ok()?;
+        if !(e.ctrl_key() || e.meta_key()) || e.alt_key() {
+            return None;
+        }
+        let focused = seed::document().active_element()?;
+        if !focused.matches(EDITOR_FIELDS).unwrap_or(false) {
+            return None;
+        }
+        let in_body = focused.matches(BODY_FIELD).unwrap_or(false);
+        let m = match e.key().as_str() {
+            "s" | "S" => M::Save,
+            "Enter" => M::Publish,
+            "b" | "B" if in_body => M::Bold,
+            "i" | "I" if in_body => M::Italic,
+            _ => return None,
+        };
+        e.prevent_default();
+        Some(GlobalM::Location(LocationM::Editor(m)))
+    }))
+}
+
+/// Wraps the selection in the body textarea with `marker` on both sides, keeping the same text
+/// selected. Returns the new body, or [`None`] if the textarea can't be found.
+pub fn wrap_body_selection(marker: &str) -> Option<String> {
+    let textarea = seed::document()
+        .query_selector(BODY_FIELD)
+        .ok()??
+        .dyn_into::<web_sys::HtmlTextAreaElement>()
+        .ok()?;
+    let start = textarea.selection_start().ok()??;
+    let end = textarea.selection_end().ok()??;
+    let (body, start, end) = wrap_selection(&textarea.value(), start, end, marker);
+    textarea.set_value(&body);
+    let _ = textarea.set_selection_range(start, end);
+    Some(body)
+}
+
+/// Wraps the text between the UTF-16 offsets `start` and `end`, as the DOM reports selections,
+/// with `marker`. Returns the new text and the offsets of the originally selected text in it.
+fn wrap_selection(text: &str, start: u32, end: u32, marker: &str) -> (String, u32, u32) {
+    let byte_offset = |utf16_offset: u32| {
+        let mut units = 0;
+        text.char_indices()
+            .find(|(_, c)| {
+                let reached = units >= utf16_offset;
+                units += c.len_utf16() as u32;
+                reached
+            })
+            .map_or(text.len(), |(i, _)| i)
+    };
+    let (start, end) = (start.min(end), start.max(end));
+    let (start_byte, end_byte) = (byte_offset(start), byte_offset(end));
+    let wrapped = format!(
+        "{}{}{}{}{}",
+        &text[..start_byte],
+        marker,
+        &text[start_byte..end_byte],
+        marker,
+        &text[end_byte..],
+    );
+    let shift = marker.encode_utf16().count() as u32;
+    (wrapped, start + shift, end + shift)
+}
+
+#[cfg(test)]
+mod test {
+    use super::wrap_selection;
+
+    #[test]
+    fn wraps_selection_by_utf16_offsets() {
+        assert_eq!(
+            wrap_selection("make this bold", 5, 9, "**"),
+            ("make **this** bold".to_owned(), 7, 11)
+        );
+        assert_eq!(wrap_selection("", 0, 0, "*"), ("**".to_owned(), 1, 1));
+        // The emoji takes up two UTF-16 code units but four bytes.
+        assert_eq!(
+            wrap_selection("😀 hi", 3, 5, "*"),
+            ("😀 *hi*".to_owned(), 4, 6)
+        );
+        assert_eq!(
+            wrap_selection("backwards", 9, 4, "*"),
+            ("back*wards*".to_owned(), 5, 10)
+        );
+    }
+}
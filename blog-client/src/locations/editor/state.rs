@@ -3,45 +3,204 @@ use serde::{Deserialize, Serialize};
 use tap::*;
 
 use crate::{
-    locations::{editor::M, Location, M as LocationM},
+    locations::{
+        editor::{preview::Preview, M},
+        Location, M as LocationM,
+    },
     messages::{AsyncM as GlobalAsyncM, M as GlobalM, StoreCallback},
     model::{
-        PostMarker, StoreOperations as GSOp, User,
+        ListingInvalidation, PostMarker, StoreOperations as GSOp, User,
     },
-    shared::retry,
+    requests::api,
+    shared::{loadable::Loadable, notifications, post_actions, retry::{self, FailReason}},
 };
 use db_models::models::*;
+use login_enum::ValidationErrors;
+
+/// Whether the current user may make changes to the post being edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Access {
+    /// The server has not been asked yet.
+    Unchecked,
+    Allowed,
+    /// The post belongs to someone else, and the user cannot modify posts of others.
+    NotOwner,
+}
+impl Default for Access {
+    fn default() -> Self {
+        Self::Unchecked
+    }
+}
+
+/// The tags of the post being edited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Tags {
+    /// The names of the tags as edited, in order.
+    pub names: Vec<String>,
+    /// The names of the tags the server has for the post, or [`None`] if they have not been
+    /// loaded yet.
+    pub saved: Option<Vec<String>>,
+    /// The name being typed in.
+    pub draft: String,
+    /// The names of every tag on the site, to suggest while typing.
+    pub known: Vec<String>,
+    /// The names of the tags in a draft restored before the tags the server has were loaded.
+    pub restored: Option<Vec<String>>,
+}
+impl Tags {
+    /// The tags of a post that has not been saved yet, which starts with the tags named.
+    pub fn unsaved(names: &[String]) -> Self {
+        let mut names = names.to_vec();
+        names.sort();
+        Self {
+            names: names.clone(),
+            saved: Some(names),
+            ..Self::default()
+        }
+    }
+    pub fn add(&mut self, name: &str) {
+        let name = name.trim();
+        if !name.is_empty() && !self.names.iter().any(|n| n == name) {
+            self.names.push(name.to_owned());
+            self.names.sort();
+        }
+        self.draft.clear();
+    }
+    pub fn remove(&mut self, name: &str) {
+        self.names.retain(|n| n != name);
+    }
+    /// Takes the tags the server has, keeping the tags added in the meantime.
+    pub fn set_saved(&mut self, mut saved: Vec<String>) {
+        saved.sort();
+        if self.saved.is_none() {
+            self.names = self.restored.take().unwrap_or_else(|| saved.clone());
+        }
+        self.saved = Some(saved);
+    }
+    /// The names of the tags to send to the server, if they were changed.
+    pub fn changed(&self) -> Option<Vec<String>> {
+        match &self.saved {
+            Some(saved) if *saved != self.names => Some(self.names.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// The users credited on the post being edited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Authors {
+    /// The users as credited in the editor, in the order of the byline. Left empty on a new post,
+    /// the user is credited alone.
+    pub credited: Vec<post_authors::Author>,
+    /// The users the server credits on the post.
+    pub saved: Vec<post_authors::Author>,
+    /// The user name being searched for.
+    pub query: String,
+    /// The users found for the query.
+    pub found: Vec<users::AuthorSummary>,
+    /// Whether the user may only credit themselves and the users already credited. Only known
+    /// once the server refuses a search.
+    pub restricted: bool,
+}
+impl Authors {
+    /// The users credited on a saved post.
+    pub fn of(post: &posts::DataNoMeta) -> Self {
+        Self {
+            credited: post.authors.clone(),
+            saved: post.authors.clone(),
+            ..Self::default()
+        }
+    }
+    pub fn add(&mut self, user: users::AuthorSummary, role: post_authors::Role) {
+        if !self.credited.iter().any(|author| author.user.id == user.id) {
+            self.credited.push(post_authors::Author { user, role });
+        }
+        self.query.clear();
+        self.found.clear();
+    }
+    pub fn remove(&mut self, user_id: uuid::Uuid) {
+        self.credited.retain(|author| author.user.id != user_id);
+    }
+    pub fn set_role(&mut self, user_id: uuid::Uuid, role: post_authors::Role) {
+        if let Some(author) = self.credited.iter_mut().find(|author| author.user.id == user_id) {
+            author.role = role;
+        }
+    }
+    /// The users to credit, if they were changed.
+    pub fn changed(&self) -> Option<Vec<post_authors::Author>> {
+        if self.credited != self.saved {
+            Some(self.credited.clone())
+        } else {
+            None
+        }
+    }
+}
+
+/// The users to send to the server in place of those credited now.
+fn assigned(authors: &[post_authors::Author]) -> Vec<post_authors::Assigned> {
+    authors.iter().map(post_authors::Assigned::from).collect()
+}
+
+/// What the editor is asking the user to confirm, over the rest of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ModalState {
+    /// Publishing the post, as [`S::publish_summary`] describes it.
+    ConfirmPublish,
+    /// Archiving or deleting the post.
+    Confirm(post_actions::Action),
+}
+
+/// What publishing a post makes public, for the user to look over first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishSummary {
+    /// Where the post will be found. New posts without a slug get their id once they are created,
+    /// so there is no path to show for them yet.
+    pub path: Option<String>,
+    /// Whether no slug is set, so that the post is published at its id.
+    pub at_id: bool,
+    /// The user names of those credited on the post, in the order of the byline.
+    pub byline: Vec<String>,
+    pub tags: Vec<String>,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum S {
-    Undetermined(PostMarker),
-    New(posts::NewNoMeta),
-    Old(posts::DataNoMeta, posts::Changed),
+    /// The post to edit, while it is fetched.
+    Undetermined(PostMarker, Loadable<()>),
+    New(posts::NewNoMeta, Tags, Authors, Preview, ValidationErrors, Option<ModalState>),
+    Old(posts::DataNoMeta, posts::Changed, Access, Tags, Authors, Preview, ValidationErrors, Option<ModalState>),
 }
 
 impl From<PostMarker> for S {
     fn from(s: PostMarker) -> Self {
-        Self::Undetermined(s)
+        Self::Undetermined(s, Loadable::NotAsked)
     }
 }
 impl S {
+    /// How far along fetching the post is. Only an undetermined post is still waiting on it.
+    pub fn load(&self) -> Loadable<&()> {
+        match self {
+            Self::Undetermined(_, load) => load.as_ref(),
+            _ => Loadable::Loaded(&()),
+        }
+    }
     pub fn to_url(&self) -> Url {
         const DEFAULT_SLUG: &'static str = "new";
         let opt_slug = match self {
-            S::New(_) => None,
-            S::Old(post, _) => {
+            S::New(..) => None,
+            S::Old(post, ..) => {
                 let marker: PostMarker = post.into();
                 Some(marker.to_slug())
             }
-            S::Undetermined(pm) => Some(pm.to_slug()),
+            S::Undetermined(pm, _) => Some(pm.to_slug()),
         };
         let slug = opt_slug.as_ref().map_or(DEFAULT_SLUG, String::as_str);
         Url::new().set_path(vec!["blog", "edit", slug])
     }
     pub fn is_publishable(&self) -> bool {
         match self {
-            Self::New(_) => true,
-            Self::Old(post, _) => match post {
+            Self::New(..) => true,
+            Self::Old(post, ..) => match post {
                 // If not published, or archived but not deleted, allow publish button.
                 posts::DataNoMeta {
                     published_at: None,
@@ -56,21 +215,208 @@ impl S {
                 } => true,
                 _ => false,
             },
-            Self::Undetermined(_) => false,
+            Self::Undetermined(..) => false,
         }
     }
     pub fn old_ref(&self) -> Option<&posts::DataNoMeta> {
         match self {
-            Self::Old(p, _) => Some(p),
+            Self::Old(p, ..) => Some(p),
             _ => None,
         }
     }
+    pub fn tags(&self) -> Option<&Tags> {
+        match self {
+            Self::New(_, tags, ..) | Self::Old(_, _, _, tags, ..) => Some(tags),
+            Self::Undetermined(..) => None,
+        }
+    }
+    pub fn tags_mut(&mut self) -> Option<&mut Tags> {
+        match self {
+            Self::New(_, tags, ..) | Self::Old(_, _, _, tags, ..) => Some(tags),
+            Self::Undetermined(..) => None,
+        }
+    }
+    pub fn authors(&self) -> Option<&Authors> {
+        match self {
+            Self::New(_, _, authors, ..) | Self::Old(_, _, _, _, authors, ..) => Some(authors),
+            Self::Undetermined(..) => None,
+        }
+    }
+    pub fn authors_mut(&mut self) -> Option<&mut Authors> {
+        match self {
+            Self::New(_, _, authors, ..) | Self::Old(_, _, _, _, authors, ..) => Some(authors),
+            Self::Undetermined(..) => None,
+        }
+    }
+    pub fn preview(&self) -> Option<&Preview> {
+        match self {
+            Self::New(_, _, _, preview, ..) | Self::Old(_, _, _, _, _, preview, ..) => Some(preview),
+            Self::Undetermined(..) => None,
+        }
+    }
+    pub fn preview_mut(&mut self) -> Option<&mut Preview> {
+        match self {
+            Self::New(_, _, _, preview, ..) | Self::Old(_, _, _, _, _, preview, ..) => Some(preview),
+            Self::Undetermined(..) => None,
+        }
+    }
+    /// What the server found wrong with the edits when they were last sent.
+    pub fn errors(&self) -> Option<&ValidationErrors> {
+        match self {
+            Self::New(_, _, _, _, errors, _) | Self::Old(_, _, _, _, _, _, errors, _) => Some(errors),
+            Self::Undetermined(..) => None,
+        }
+    }
+    pub fn errors_mut(&mut self) -> Option<&mut ValidationErrors> {
+        match self {
+            Self::New(_, _, _, _, errors, _) | Self::Old(_, _, _, _, _, _, errors, _) => Some(errors),
+            Self::Undetermined(..) => None,
+        }
+    }
+    /// What the editor is asking the user to confirm, if anything.
+    pub fn modal(&self) -> Option<ModalState> {
+        match self {
+            Self::New(.., modal) | Self::Old(.., modal) => *modal,
+            Self::Undetermined(..) => None,
+        }
+    }
+    /// Asks the user to confirm `modal`, or stops asking. Only posts being edited ask anything.
+    pub fn set_modal(&mut self, new_modal: Option<ModalState>) {
+        match self {
+            Self::New(.., modal) | Self::Old(.., modal) => *modal = new_modal,
+            Self::Undetermined(..) => (),
+        }
+    }
+    /// What `user` may do to the saved post besides editing it. The server having allowed edits
+    /// stands in for the capabilities over the posts of others, which the client does not know.
+    pub fn post_actions(&self, user: Option<&User>) -> Vec<post_actions::Action> {
+        match self {
+            Self::Old(post, _, Access::Allowed, ..) if user.is_some() => {
+                post_actions::Action::applicable_to(post)
+            }
+            Self::Old(post, _, Access::Unchecked, ..) => post_actions::Action::offered(user, post),
+            _ => vec![],
+        }
+    }
+    /// What publishing the post as edited by `user` makes public.
+    pub fn publish_summary(&self, user: &User) -> Option<PublishSummary> {
+        let (slug, path_of_id) = match self {
+            Self::New(post, ..) => {
+                let prefix = &user.preferences.post_defaults.slug_prefix;
+                (prefixed_slug(post.slug.as_deref(), prefix), None)
+            }
+            Self::Old(post, ..) => {
+                let id = post.id.to_hyphenated_ref().to_string();
+                (post.slug.clone(), Some(id))
+            }
+            Self::Undetermined(..) => return None,
+        };
+        let at_id = slug.is_none();
+        let path = slug.or(path_of_id).map(|marker| format!("/blog/posts/{}", marker));
+        let credited = self.authors().map_or(&[][..], |authors| &authors.credited[..]);
+        let byline = match (self, credited) {
+            (Self::New(..), []) => vec![user.user_name.clone()],
+            (_, credited) => credited.iter().map(|author| author.user.user_name.clone()).collect(),
+        };
+        let tags = self.tags().map(|tags| tags.names.clone()).unwrap_or_default();
+        Some(PublishSummary { path, at_id, byline, tags })
+    }
+    /// The body as edited.
+    pub fn body(&self) -> Option<&str> {
+        match self {
+            Self::New(post, ..) => Some(&post.body),
+            Self::Old(post, changed, ..) => Some(changed.body.as_ref().unwrap_or(&post.body)),
+            Self::Undetermined(..) => None,
+        }
+    }
+    /// What the post is described with where it is linked to, and the url of the image shown
+    /// there, as edited.
+    pub fn seo(&self) -> Option<(&str, &str)> {
+        match self {
+            Self::New(post, ..) => Some((&post.meta_description, &post.social_image)),
+            Self::Old(post, changed, ..) => Some((
+                changed.meta_description.as_ref().unwrap_or(&post.meta_description),
+                changed.social_image.as_ref().unwrap_or(&post.social_image),
+            )),
+            Self::Undetermined(..) => None,
+        }
+    }
+    /// Whether changes to the post may be saved. Posts are assumed editable until the server
+    /// says otherwise.
+    pub fn is_savable(&self) -> bool {
+        match self {
+            Self::Old(_, _, Access::NotOwner, ..) | Self::Undetermined(..) => false,
+            _ => true,
+        }
+    }
+    pub fn set_access(&mut self, new_access: Access) {
+        if let Self::Old(_, _, access, ..) = self {
+            *access = new_access;
+        }
+    }
+    /// Whether there are edits that would be lost by leaving the editor.
+    pub fn is_dirty(&self) -> bool {
+        match self {
+            Self::New(post, tags, authors, ..) => {
+                !post.title.is_empty()
+                    || !post.body.is_empty()
+                    || post.slug.is_some()
+                    || !post.meta_description.is_empty()
+                    || !post.social_image.is_empty()
+                    || tags.changed().is_some()
+                    || authors.changed().is_some()
+            }
+            Self::Old(_, changed, _, tags, authors, ..) => {
+                !changed.is_empty()
+                    || tags.changed().is_some()
+                    || authors.changed().is_some()
+            }
+            Self::Undetermined(..) => false,
+        }
+    }
+    /// Folds the edits into the post once the server has them, so that the editor is clean again.
+    pub fn mark_saved(&mut self) {
+        match self {
+            Self::Old(post, changed, _, _, authors, ..) => {
+                if let Some(title) = changed.title.take() {
+                    post.title = title;
+                }
+                if let Some(body) = changed.body.take() {
+                    post.body = body;
+                }
+                if let Some(description) = changed.meta_description.take() {
+                    post.meta_description = description;
+                }
+                if let Some(image) = changed.social_image.take() {
+                    post.social_image = image;
+                }
+                authors.saved = authors.credited.clone();
+                post.authors = authors.saved.clone();
+            }
+            Self::New(post, ..) => *post = posts::NewNoMeta::default(),
+            Self::Undetermined(..) => (),
+        }
+    }
+    /// Throws the edits away.
+    pub fn discard_changes(&mut self) {
+        match self {
+            Self::Old(_, changed, ..) => *changed = posts::Changed::default(),
+            Self::New(post, ..) => *post = posts::NewNoMeta::default(),
+            Self::Undetermined(..) => (),
+        }
+        if let Some(tags) = self.tags_mut() {
+            tags.names = tags.saved.clone().unwrap_or_default();
+        }
+        if let Some(authors) = self.authors_mut() {
+            authors.credited = authors.saved.clone();
+        }
+    }
     pub fn update_title(&mut self, title: String) {
         match self {
-            Self::Old(_, changed) => {
+            Self::Old(_, changed, ..) => {
                 changed.title = Some(title);
             }
-            Self::New(post) => {
+            Self::New(post, ..) => {
                 post.title = title;
             }
             _ => (),
@@ -78,22 +424,37 @@ impl S {
     }
     pub fn update_body(&mut self, body: String) {
         match self {
-            Self::Old(_, changed) => {
+            Self::Old(_, changed, ..) => {
                 changed.body = Some(body);
             }
-            Self::New(post) => {
+            Self::New(post, ..) => {
                 post.body = body;
             }
             _ => (),
         }
     }
+    pub fn update_meta_description(&mut self, description: String) {
+        match self {
+            Self::Old(_, changed, ..) => changed.meta_description = Some(description),
+            Self::New(post, ..) => post.meta_description = description,
+            _ => (),
+        }
+    }
+    pub fn update_social_image(&mut self, image: String) {
+        let image = image.trim().to_owned();
+        match self {
+            Self::Old(_, changed, ..) => changed.social_image = Some(image),
+            Self::New(post, ..) => post.social_image = image,
+            _ => (),
+        }
+    }
     pub fn update_slug(&mut self, slug: String) {
         let slug = match slug.trim() {
             "" => None,
             _ => Some(slug),
         };
         match self {
-            Self::New(post) => {
+            Self::New(post, ..) => {
                 post.slug = slug;
             }
             _ => (),
@@ -102,83 +463,252 @@ impl S {
 }
 impl Default for S {
     fn default() -> Self {
-        Self::New(posts::NewNoMeta::default())
+        Self::new_with(&users::PostDefaults::default())
+    }
+}
+impl S {
+    /// A new post, as the user starts new posts.
+    ///
+    /// The default tags are taken as what the post has, so that they alone leave the editor clean,
+    /// and the server is asked to tag the post with them unless they are changed.
+    pub fn new_with(defaults: &users::PostDefaults) -> Self {
+        Self::New(
+            posts::NewNoMeta::default(),
+            Tags::unsaved(&defaults.tags),
+            Authors::default(),
+            Preview {
+                enabled: defaults.editor_mode == users::EditorMode::Preview,
+                ..Preview::default()
+            },
+            ValidationErrors::new(),
+            None,
+        )
+    }
+}
+
+/// What the slugs of the new posts of `user` start with.
+pub fn slug_prefix(user: Option<&User>) -> &str {
+    user.map_or("", |user| user.preferences.post_defaults.slug_prefix.as_str())
+}
+
+/// The slug to create a post with, given the slug typed in after the prefix the user starts slugs
+/// with.
+pub fn prefixed_slug(slug: Option<&str>, prefix: &str) -> Option<String> {
+    slug.map(|slug| {
+        if slug.starts_with(prefix) {
+            slug.to_owned()
+        } else {
+            format!("{}{}", prefix, slug)
+        }
+    })
+}
+
+/// Replaces the tags on the saved post with the ones named, if they were changed. The post itself
+/// has been saved by then, so a failure here only loses the tags.
+async fn save_tags(post_id: uuid::Uuid, names: Option<Vec<String>>) -> GlobalM {
+    const SAVE_TAGS_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "saving post tags",
+        post_completion: "parsing saved post tags",
+    };
+
+    let names = if let Some(names) = names {
+        names
+    } else {
+        return GlobalM::NoOp;
+    };
+    let req = api::call_with(&api::posts::SET_TAGS, &[&post_id], &names);
+    let req = if let Ok(req) = req {
+        req
+    } else {
+        return GlobalM::NoOp;
+    };
+    let res: Result<Vec<tags::Data>, _> = retry::fetch_json_with_retry(
+        req,
+        &SAVE_TAGS_MSG,
+        retry::NO_RETRY,
+    ).await;
+    match res {
+        Err(reason) => reason.notify("attempt.save_tags"),
+        Ok(saved) => GlobalM::Location(LocationM::Editor(M::TagsSaved(
+            saved.into_iter().map(|tag| tag.name).collect(),
+        ))),
+    }
+}
+
+/// Replaces the users credited on the saved post, if they were changed. The post itself has been
+/// saved by then, so a failure here only loses the credits.
+async fn save_authors(post_id: uuid::Uuid, authors: Option<Vec<post_authors::Author>>) -> GlobalM {
+    const SAVE_AUTHORS_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "saving post authors",
+        post_completion: "considering saved post authors",
+    };
+
+    let authors = if let Some(authors) = authors {
+        authors
+    } else {
+        return GlobalM::NoOp;
+    };
+    let update = posts::Update {
+        authors: Some(assigned(&authors)),
+        ..posts::Update::default()
+    };
+    let req = api::call_with(&api::posts::UPDATE, &[&post_id], &update);
+    let req = if let Ok(req) = req {
+        req
+    } else {
+        return GlobalM::NoOp;
+    };
+    let res = retry::fetch_text_with_retry(
+        req,
+        &SAVE_AUTHORS_MSG,
+        retry::NO_RETRY,
+    ).await;
+    match res {
+        Err(reason) => reason.notify("attempt.save_authors"),
+        Ok(_) => GlobalM::Location(LocationM::Editor(M::AuthorsSaved(authors))),
     }
 }
 
 impl S {
-    async fn attempt_save_async_new(post: posts::NewNoMeta) -> GlobalM {
-        const CREATE_POST_URL: &str = "/api/posts";
+    /// What to create a new post with. The server tags it with the default tags if they were left
+    /// alone, and otherwise the tags are saved once it is created.
+    fn to_create(
+        post: &posts::NewNoMeta,
+        tags: &Tags,
+        authors: &Authors,
+        slug_prefix: &str,
+    ) -> posts::NewNoMeta {
+        posts::NewNoMeta {
+            slug: prefixed_slug(post.slug.as_deref(), slug_prefix),
+            authors: assigned(&authors.credited),
+            default_tags: tags.changed().is_none()
+                && tags.saved.as_ref().map_or(false, |saved| !saved.is_empty()),
+            ..post.clone()
+        }
+    }
+    /// What to do when the server refuses to save the post. Failing to reach it at all only keeps
+    /// the edits offline if `offline`, since the rest can't be retried later.
+    fn refused(rejection: retry::Rejection, attempt: &str, offline: bool) -> GlobalM {
+        match rejection {
+            retry::Rejection::Invalid(errors) => GlobalM::Location(LocationM::Editor(M::Invalid(errors))),
+            retry::Rejection::Conflict(why) => {
+                log::error!("Saving the post was refused: {}.", why);
+                FailReason::Other.notify(attempt)
+            }
+            retry::Rejection::Failed(FailReason::Network) if offline => GlobalM::SaveOffline,
+            retry::Rejection::Failed(reason) => reason.notify(attempt),
+        }
+    }
+    /// Reads the post the server created.
+    async fn created(res: seed::browser::fetch::Response, attempt: &str) -> Result<posts::DataNoMeta, GlobalM> {
+        res.json().await.map_err(|e| {
+            log::error!("Failed to parse the created post: {:?}.", e);
+            FailReason::Other.notify(attempt)
+        })
+    }
+    async fn attempt_save_async_new(post: posts::NewNoMeta, tags: Option<Vec<String>>) -> GlobalM {
         const NEW_SAVE_MSG: retry::LogPair<'static> = retry::LogPair {
             pre_completion: "creating new post",
             post_completion: "parsing created post",
         };
 
-        let req = Request::new(CREATE_POST_URL)
-            .method(Method::Post)
-            .json(&post);
+        let req = api::call_with(&api::posts::CREATE, &[], &post);
         let req = if let Ok(req) = req {
             req
         } else {
             return GlobalM::NoOp;
         };
-        let res = retry::fetch_json_with_retry(
-            req,
-            &NEW_SAVE_MSG,
-            None,
-        ).await;
+        let res = match retry::fetch_validated(req, &NEW_SAVE_MSG).await {
+            Ok(res) => Self::created(res, "attempt.create_post").await,
+            Err(rejection) => Err(Self::refused(rejection, "attempt.create_post", true)),
+        };
         match res {
-            Err(_) => GlobalM::NoOp,
-            Ok(obj) => GlobalM::StoreOpWithMessage(
-                GSOp::PostWithoutMarker(obj),
-                || GlobalM::Location(LocationM::Editor(M::SyncPost))
-            ),
+            Err(m) => m,
+            Ok(obj) => {
+                let tags_saved = save_tags(obj.id, tags).await;
+                GlobalM::Grouped(vec![
+                    GlobalM::StoreOp(GSOp::InvalidateListings(ListingInvalidation::All)),
+                    GlobalM::StoreOpWithMessage(
+                        GSOp::PostWithoutMarker(obj),
+                        || GlobalM::Location(LocationM::Editor(M::SyncPost))
+                    ),
+                    tags_saved,
+                ])
+            }
         }
     }
-    async fn attempt_save_async_old(mut post: posts::DataNoMeta, changes: posts::Changed) -> GlobalM {
-        const UPDATE_POST_BASE_URL: &str = "/api/posts";
+    async fn attempt_save_async_old(
+        mut post: posts::DataNoMeta,
+        changes: posts::Changed,
+        tags: Option<Vec<String>>,
+        authors: Option<Vec<post_authors::Author>>,
+    ) -> GlobalM {
         const SAVE_OLD_MSG: retry::LogPair<'static> = retry::LogPair {
             pre_completion: "saving old post",
             post_completion: "considering changes to post",
         };
 
-        let url = format!("{}/{}", UPDATE_POST_BASE_URL, post.id);
-        let req = Request::new(url)
-            .method(Method::Patch)
-            .json(&changes);
+        let update = posts::Update {
+            changes: changes.clone(),
+            authors: authors.as_deref().map(assigned),
+        };
+        let req = api::call_with(&api::posts::UPDATE, &[&post.id], &update);
         let req = if let Ok(req) = req {
             req
         } else {
             return GlobalM::NoOp;
         };
-        let res = retry::fetch_text_with_retry(
-            req,
-            &SAVE_OLD_MSG,
-            None,
-        ).await;
-        match res {
-            Err(_) => GlobalM::NoOp,
+        match retry::fetch_validated(req, &SAVE_OLD_MSG).await {
+            Err(rejection) => Self::refused(rejection, "attempt.save_post", true),
             Ok(_) => {
                 if let Some(title) = changes.title {
                     post.title = title;
                 }
                 if let Some(body) = changes.body {
+                    // What the server rendered no longer matches, until the post is fetched again.
                     post.body = body;
+                    post.html = None;
+                    post.outline = vec![];
+                }
+                if let Some(description) = changes.meta_description {
+                    post.meta_description = description;
+                }
+                if let Some(image) = changes.social_image {
+                    post.social_image = image;
                 }
-                GlobalM::StoreOp(GSOp::PostRaw(post))
+                if let Some(authors) = authors {
+                    post.authors = authors;
+                }
+                let tags_saved = save_tags(post.id, tags).await;
+                GlobalM::Grouped(vec![
+                    GlobalM::StoreOp(GSOp::InvalidateListings(ListingInvalidation::Post(post.id))),
+                    GlobalM::StoreOp(GSOp::PostRaw(post)),
+                    GlobalM::Location(LocationM::Editor(M::Saved)),
+                    tags_saved,
+                    GlobalM::Notify(notifications::Level::Info, "Post saved.".to_owned()),
+                ])
             }
         }
     }
-    pub fn attempt_save(&mut self) -> Option<std::pin::Pin<Box<dyn GlobalAsyncM>>> {
+    /// Saves the post, creating it with its slug after `slug_prefix` if it is new.
+    pub fn attempt_save(&mut self, slug_prefix: &str) -> Option<std::pin::Pin<Box<dyn GlobalAsyncM>>> {
         // TODO Consider removing the clone here somehow.
         match self {
-            Self::New(post) => Some(Box::pin(Self::attempt_save_async_new(post.clone()))),
-            Self::Old(post, changes) => Some(Box::pin(Self::attempt_save_async_old(post.clone(), changes.clone()))),
-            Self::Undetermined(_) => None,
+            Self::New(post, tags, authors, ..) => {
+                let post = Self::to_create(post, tags, authors, slug_prefix);
+                Some(Box::pin(Self::attempt_save_async_new(post, tags.changed())))
+            }
+            Self::Old(_, _, Access::NotOwner, ..) => None,
+            Self::Old(post, changes, _, tags, authors, ..) => Some(Box::pin(Self::attempt_save_async_old(
+                post.clone(),
+                changes.clone(),
+                tags.changed(),
+                authors.changed(),
+            ))),
+            Self::Undetermined(..) => None,
         }
     }
-    async fn attempt_publish_async_new(mut post: posts::NewNoMeta, user_id: uuid::Uuid) -> GlobalM {
-        const CREATE_POST_URL: &str = "/api/posts";
+    async fn attempt_publish_async_new(mut post: posts::NewNoMeta, tags: Option<Vec<String>>, user_id: uuid::Uuid) -> GlobalM {
         const PUB_NEW_MSG: retry::LogPair<'static> = retry::LogPair {
             pre_completion: "saving and publishing new post",
             post_completion: "parsing created post",
@@ -186,69 +716,83 @@ impl S {
         post.published_at = Some(chrono::Utc::now());
         post.published_by = Some(user_id);
         // save
-        let req = Request::new(CREATE_POST_URL)
-            .method(Method::Post)
-            .json(&post);
+        let req = api::call_with(&api::posts::CREATE, &[], &post);
         let req = if let Ok(req) = req {
             req
         } else {
             return GlobalM::NoOp;
         };
-        let res = retry::fetch_json_with_retry(
-            req,
-            &PUB_NEW_MSG,
-            None,
-        ).await;
+        let res = match retry::fetch_validated(req, &PUB_NEW_MSG).await {
+            Ok(res) => Self::created(res, "attempt.publish_post").await,
+            Err(rejection) => Err(Self::refused(rejection, "attempt.publish_post", false)),
+        };
         match res {
-            Err(_) => GlobalM::NoOp,
-            Ok(obj) => GlobalM::StoreOpWithAction(GSOp::PostWithoutMarker(obj), StoreCallback::new(|gs| {
-                gs.post.as_ref().map(|post| GlobalM::ChangePageAndUrl(Location::Viewer(
-                    PostMarker::Uuid(post.id).into(),
-                )))
-                .tap_none(|| log::error!("Post loaded but was not saved."))
-                .unwrap_or(GlobalM::NoOp)
-            }))
+            Err(m) => m,
+            Ok(obj) => {
+                // Leaving with tags that failed to save trips the unsaved changes guard.
+                let tags_saved = save_tags(obj.id, tags).await;
+                GlobalM::Grouped(vec![
+                    GlobalM::StoreOp(GSOp::InvalidateListings(ListingInvalidation::All)),
+                    GlobalM::Location(LocationM::Editor(M::Saved)),
+                    tags_saved,
+                    GlobalM::StoreOpWithAction(GSOp::PostWithoutMarker(obj), StoreCallback::new(|gs| {
+                        gs.post.as_ref().map(|post| {
+                            GlobalM::ChangePageAndUrl(Location::Viewer(PostMarker::Uuid(post.id).into()))
+                        })
+                        .tap_none(|| log::error!("Post loaded but was not saved."))
+                        .unwrap_or(GlobalM::NoOp)
+                    })),
+                ])
+            }
         }
     }
-    async fn attempt_publish_async_old(post: posts::DataNoMeta, changed: posts::Changed) -> GlobalM {
+    async fn attempt_publish_async_old(
+        post: posts::DataNoMeta,
+        changed: posts::Changed,
+        tags: Option<Vec<String>>,
+        authors: Option<Vec<post_authors::Author>>,
+    ) -> GlobalM {
         const PUB_OLD_MSG: retry::LogPair<'static> = retry::LogPair {
             pre_completion: "saving and publishing old post",
             post_completion: "parsing published post",
         };
-        let url = format!("/api/posts/{}/publish", post.id);
-        let req = Request::new(url)
-            .method(Method::Post);
-        let req = if changed.title.is_some() || changed.body.is_some() {
-            if let Ok(req) = req.json(&changed) {
+        let req = if !changed.is_empty() {
+            if let Ok(req) = api::call_with(&api::posts::PUBLISH, &[&post.id], &changed) {
                 req
             } else {
                 return GlobalM::NoOp;
             }
         } else {
-            req
+            api::call(&api::posts::PUBLISH, &[&post.id])
         };
 
-        let res = retry::fetch_text_with_retry(
-            req, 
-            &PUB_OLD_MSG,
-            None,
-        ).await;
-        match res {
-            Ok(_) => GlobalM::ChangePageAndUrl(Location::Viewer(
-                PostMarker::Uuid(post.id).into(),
-            )),
-            Err(_) => GlobalM::NoOp,
+        match retry::fetch_validated(req, &PUB_OLD_MSG).await {
+            // Publishing moves the post into the published listing, wherever its date puts it.
+            Ok(_) => GlobalM::Grouped(vec![
+                GlobalM::StoreOp(GSOp::InvalidateListings(ListingInvalidation::All)),
+                GlobalM::Location(LocationM::Editor(M::Saved)),
+                save_tags(post.id, tags).await,
+                save_authors(post.id, authors).await,
+                GlobalM::ChangePageAndUrl(Location::Viewer(PostMarker::Uuid(post.id).into())),
+            ]),
+            Err(rejection) => Self::refused(rejection, "attempt.publish_post", false),
         }
     }
     pub fn attempt_publish(&mut self, user: &User) -> Option<std::pin::Pin<Box<dyn GlobalAsyncM>>> {
         match self {
-            Self::Undetermined(_) => None,
-            Self::New(post) => {
-                Some(Box::pin(Self::attempt_publish_async_new(post.clone(), user.id)))
-            }
-            Self::Old(post, changed) => {
-                Some(Box::pin(Self::attempt_publish_async_old(post.clone(), changed.clone())))
+            Self::Undetermined(..) => None,
+            Self::New(post, tags, authors, ..) => {
+                let slug_prefix = &user.preferences.post_defaults.slug_prefix;
+                let post = Self::to_create(post, tags, authors, slug_prefix);
+                Some(Box::pin(Self::attempt_publish_async_new(post, tags.changed(), user.id)))
             }
+            Self::Old(_, _, Access::NotOwner, ..) => None,
+            Self::Old(post, changed, _, tags, authors, ..) => Some(Box::pin(Self::attempt_publish_async_old(
+                post.clone(),
+                changed.clone(),
+                tags.changed(),
+                authors.changed(),
+            ))),
         }
     }
 }
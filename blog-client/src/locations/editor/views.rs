@@ -1,25 +1,101 @@
 use seed::prelude::*;
 
 use crate::{
-    locations::editor::{M, S},
-    model::Store as GlobalS,
+    locations::{
+        editor::{preview, slug_prefix, Authors, ModalState, PublishSummary, Tags, M, S},
+        listing,
+    },
+    model::{PostMarker, Store as GlobalS, User},
+    shared::{post_actions::Action, views::{field_errors, form_errors}},
 };
+use db_models::models::*;
+use login_enum::ValidationErrors;
 
-pub fn render(s: &S, _gs: &GlobalS) -> Vec<Node<M>> {
+pub fn render(s: &S, gs: &GlobalS) -> Vec<Node<M>> {
+    let user = gs.user.as_ref();
     vec![
         heading(),
-        editor(s).unwrap_or_else(crate::shared::views::loading),
+        editor(s, user, slug_prefix(user)).unwrap_or_else(crate::shared::views::loading),
+        match (s.modal(), user) {
+            (Some(ModalState::ConfirmPublish), Some(user)) => s
+                .publish_summary(user)
+                .map_or_else(|| empty![], |summary| publish_confirmation(&summary)),
+            (Some(ModalState::Confirm(action)), Some(_)) => {
+                let title = s.old_ref().map_or("", |post| post.title.as_str());
+                let confirm = match action {
+                    Action::Archive => M::Archive,
+                    Action::Delete => M::Delete,
+                };
+                action.confirmation(title, confirm, M::DismissModal)
+            }
+            _ => empty![],
+        },
     ]
 }
 
+/// Asks before publishing, showing where the post will be found and who and what it is listed under.
+fn publish_confirmation(summary: &PublishSummary) -> Node<M> {
+    let location = match (&summary.path, summary.at_id) {
+        (Some(path), false) => p![tr!("editor.publish_confirm.at", path = path)],
+        (Some(path), true) => p![
+            attrs! { At::Class => "editor-notice" },
+            tr!("editor.publish_confirm.at_id", path = path),
+        ],
+        (None, _) => p![
+            attrs! { At::Class => "editor-notice" },
+            tr!("editor.publish_confirm.at_new_id"),
+        ],
+    };
+    let listed = |names: &[String]| {
+        if names.is_empty() {
+            tr!("editor.publish_confirm.none")
+        } else {
+            names.join(", ")
+        }
+    };
+    crate::shared::views::modal(
+        tr!("editor.publish_confirm.title"),
+        vec![
+            location,
+            p![tr!("editor.publish_confirm.byline", names = listed(&summary.byline))],
+            p![tr!("editor.publish_confirm.tags", names = listed(&summary.tags))],
+            p![tr!("editor.publish_confirm.visibility")],
+        ],
+        (tr!("editor.publish_confirm.confirm"), M::ConfirmPublish),
+        M::DismissModal,
+    )
+}
+
+/// The page for a post that could not be found, which offers to start one at its slug.
+pub fn render_not_found(marker: &PostMarker) -> Vec<Node<M>> {
+    vec![div![
+        attrs! { At::Class => "load-failed" },
+        h1![tr!("not_found.post")],
+        p![tr!("common.not_found")],
+        if let PostMarker::Slug(slug) = marker {
+            button![
+                attrs! { At::Class => "inline-button" },
+                tr!("editor.create_at_slug", slug = slug),
+                ev(Ev::Click, |_| M::CreateWithSlug),
+            ]
+        } else {
+            empty![]
+        },
+        a![
+            attrs! { At::Href => listing::S::url_root() },
+            tr!("common.back_to_posts"),
+        ],
+    ]]
+}
+
 pub fn heading() -> Node<M> {
     h1![attrs! { At::Class => "as-h3" }, "Editing"]
 }
 
 fn get_title_slug_body(s: &S) -> Option<(&str, Option<&str>, &str)> {
     let (t, slug, b) = match s {
-        S::New(post) => (&post.title, post.slug.as_ref(), &post.body),
-        S::Old(post, changed) => (
+        S::New(post, ..) => (&post.title, post.slug.as_ref(), &post.body),
+        S::Old(post, changed, ..) => (
             changed.title.as_ref().unwrap_or(&post.title),
             post.slug.as_ref(),
             changed.body.as_ref().unwrap_or(&post.body),
@@ -30,24 +106,34 @@ fn get_title_slug_body(s: &S) -> Option<(&str, Option<&str>, &str)> {
     Some((t, slug, b))
 }
 fn title_field(title: &str) -> Node<M> {
+    let count = title.chars().count();
+    let max = posts::TITLE_MAX_CHARS;
+    let counter_class = if count > max { "char-counter over-limit" } else { "char-counter" };
     div![
         attrs! { At::Class => "editor-title" },
         input![
             {
                 let mut attrs = attrs! {
-                    At::Placeholder => "Title";
+                    At::Placeholder => tr!("editor.title");
+                    At::AriaLabel => tr!("editor.title");
                     At::Type => "text";
                     At::Name => "title",
                     At::Value => title,
+                    At::MaxLength => max;
                 };
                 attrs.add_multiple(At::Class, &["single-line-text-entry", "as-h1"]);
                 attrs
             },
             input_ev(Ev::Input, M::Title),
         ],
+        p![
+            attrs! { At::Class => counter_class; At::from("aria-live") => "polite" },
+            tr!("editor.title_count", count = count, max = max),
+        ],
     ]
 }
-fn slug_field(slug: &str, hint: &str) -> Node<M> {
+/// The slug of the post, typed in after the `prefix` it is created with.
+fn slug_field(slug: &str, hint: &str, prefix: &str) -> Node<M> {
     div![
         attrs! { At::Class => "editor-slug" },
         label![
@@ -58,13 +144,14 @@ fn slug_field(slug: &str, hint: &str) -> Node<M> {
                 attrs.add_multiple(At::Class, &["same-line-label", "as-pre"]);
                 attrs
             },
-            "/blog/posts/",
+            format!("/blog/posts/{}", prefix),
         ],
         input![
             {
                 let mut attrs = attrs! {
                     At::Placeholder => hint;
                     At::Type => "text";
+                    At::Id => "slug";
                     At::Name => "slug",
                     At::Value => slug,
                 };
@@ -75,7 +162,8 @@ fn slug_field(slug: &str, hint: &str) -> Node<M> {
         ],
     ]
 }
-fn body_field(body: &str) -> Node<M> {
+fn body_field(body: &str, preview: Option<&preview::Preview>) -> Node<M> {
+    let preview = preview.filter(|preview| preview.enabled);
     div![
         attrs! {
             At::Class => "editor-body",
@@ -83,7 +171,8 @@ fn body_field(body: &str) -> Node<M> {
         textarea![
             {
                 let mut attrs = attrs! {
-                    At::Placeholder => "Write your post here!";
+                    At::Placeholder => tr!("editor.body_placeholder");
+                    At::AriaLabel => tr!("editor.body");
                     At::Type => "text";
                     At::Name => "body",
                 };
@@ -92,10 +181,271 @@ fn body_field(body: &str) -> Node<M> {
             },
             body,
             input_ev(Ev::Input, M::Body),
+            ev(Ev::Scroll, |_| M::SyncPreviewScroll),
+        ],
+        preview.map_or_else(|| empty![], preview::view),
+    ]
+}
+fn preview_toggle(s: &S) -> Node<M> {
+    let enabled = s.preview().map_or(false, |preview| preview.enabled);
+    input![
+        attrs! {
+            At::Class => "inline-button",
+            At::Type => "button",
+            At::Value => if enabled { tr!("editor.hide_preview") } else { tr!("editor.preview") },
+        },
+        ev(Ev::Click, |_| M::TogglePreview),
+    ]
+}
+/// What the post is described with where it is linked to, tucked away since most posts do well
+/// enough with what is derived from their body.
+fn seo_field(description: &str, image: &str, errors: &ValidationErrors) -> Node<M> {
+    let count = description.chars().count();
+    let shown = posts::META_DESCRIPTION_SHOWN_CHARS;
+    let counter_class = if count > shown { "char-counter over-limit" } else { "char-counter" };
+    details![
+        attrs! { At::Class => "editor-seo" },
+        summary![tr!("editor.seo")],
+        label![attrs! { At::For => "meta-description" }, tr!("editor.seo.description")],
+        textarea![
+            {
+                let mut attrs = attrs! {
+                    At::Id => "meta-description";
+                    At::Name => "meta_description";
+                    At::Placeholder => tr!("editor.seo.description_placeholder");
+                    At::MaxLength => posts::META_DESCRIPTION_MAX_CHARS;
+                    At::Rows => 3;
+                };
+                attrs.add_multiple(At::Class, &["multi-line-text-entry"]);
+                attrs
+            },
+            description,
+            input_ev(Ev::Input, M::MetaDescription),
+        ],
+        field_errors(errors, "meta_description"),
+        p![
+            attrs! { At::Class => counter_class; At::from("aria-live") => "polite" },
+            tr!("editor.seo.count", count = count, shown = shown),
+            if count > shown {
+                span![" ", tr!("editor.seo.too_long")]
+            } else {
+                empty![]
+            },
+        ],
+        label![attrs! { At::For => "social-image" }, tr!("editor.seo.image")],
+        input![
+            {
+                let mut attrs = attrs! {
+                    At::Id => "social-image";
+                    At::Name => "social_image";
+                    At::Type => "text";
+                    At::Placeholder => tr!("editor.seo.image_placeholder");
+                    At::Value => image;
+                };
+                attrs.add_multiple(At::Class, &["single-line-text-entry"]);
+                attrs
+            },
+            input_ev(Ev::Input, M::SocialImage),
         ],
+        field_errors(errors, "social_image"),
     ]
 }
-fn action_buttons(s: &S) -> Node<M> {
+/// The id of the list of tags suggested while typing.
+const TAG_SUGGESTIONS_ID: &str = "editor-tag-suggestions";
+fn tags_field(tags: &Tags) -> Node<M> {
+    if tags.saved.is_none() {
+        return div![
+            attrs! { At::Class => "editor-tags" },
+            p![attrs! { At::Class => "editor-notice" }, tr!("editor.loading_tags")],
+        ];
+    }
+    let chips = tags.names.iter().map(|name| {
+        let removed = name.clone();
+        span![
+            attrs! { At::Class => "tag-chip" },
+            name.as_str(),
+            button![
+                attrs! {
+                    At::Class => "tag-chip-remove";
+                    At::Type => "button";
+                    At::AriaLabel => tr!("editor.remove_tag", name = name);
+                },
+                "×",
+                ev(Ev::Click, move |_| M::RemoveTag(removed)),
+            ],
+        ]
+    });
+    let suggestions = tags
+        .known
+        .iter()
+        .filter(|known| !tags.names.contains(known))
+        .map(|known| option![attrs! { At::Value => known }]);
+    let draft = tags.draft.clone();
+    div![
+        attrs! { At::Class => "editor-tags" },
+        chips,
+        input![
+            {
+                let mut attrs = attrs! {
+                    At::Placeholder => tr!("editor.add_tag");
+                    At::AriaLabel => tr!("editor.tags");
+                    At::Type => "text";
+                    At::Name => "tags";
+                    At::List => TAG_SUGGESTIONS_ID,
+                    At::Value => tags.draft,
+                };
+                attrs.add_multiple(At::Class, &["single-line-text-entry", "tag-entry"]);
+                attrs
+            },
+            input_ev(Ev::Input, M::TagDraft),
+            // Picking a suggestion replaces the text, without any key being pressed.
+            ev(Ev::Change, |e| {
+                let name = e.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?.value();
+                Some(M::AddTag(name))
+            }),
+            keyboard_ev(Ev::KeyDown, move |e| {
+                if e.key() != "Enter" || e.ctrl_key() || e.meta_key() {
+                    return None;
+                }
+                e.prevent_default();
+                Some(M::AddTag(draft))
+            }),
+        ],
+        datalist![attrs! { At::Id => TAG_SUGGESTIONS_ID }, suggestions],
+    ]
+}
+/// The id of the list of users found while searching.
+const AUTHOR_SUGGESTIONS_ID: &str = "editor-author-suggestions";
+fn role_name(role: post_authors::Role) -> String {
+    match role {
+        post_authors::Role::Author => tr!("editor.role.author"),
+        post_authors::Role::Editor => tr!("editor.role.editor"),
+    }
+}
+fn authors_field(authors: &Authors, user_id: Option<uuid::Uuid>) -> Node<M> {
+    let credited = authors.credited.iter().map(|author| {
+        let user_id = author.user.id;
+        let name = author.user.display_name();
+        let roles = post_authors::Role::ALL.iter().map(|role| {
+            option![
+                attrs! {
+                    At::Value => role.as_str();
+                    At::Selected => (*role == author.role).as_at_value();
+                },
+                role_name(*role),
+            ]
+        });
+        span![
+            attrs! { At::Class => "author-chip" },
+            name.as_str(),
+            select![
+                attrs! { At::AriaLabel => tr!("editor.author_role", name = name) },
+                roles,
+                input_ev(Ev::Change, move |value| {
+                    post_authors::Role::from_name(&value).map(|role| M::AuthorRole(user_id, role))
+                }),
+            ],
+            button![
+                attrs! {
+                    At::Class => "tag-chip-remove";
+                    At::Type => "button";
+                    At::AriaLabel => tr!("editor.remove_author", name = name);
+                },
+                "×",
+                ev(Ev::Click, move |_| M::RemoveAuthor(user_id)),
+            ],
+        ]
+    });
+    let unlisted = if authors.credited.is_empty() {
+        p![attrs! { At::Class => "editor-notice" }, tr!("editor.credited_to_you")]
+    } else {
+        empty![]
+    };
+    let picker = if authors.restricted {
+        let credits_self = user_id.map_or(true, |id| authors.credited.iter().any(|a| a.user.id == id));
+        div![
+            p![attrs! { At::Class => "editor-notice" }, tr!("editor.authors_restricted")],
+            if credits_self {
+                empty![]
+            } else {
+                button![
+                    attrs! {
+                        At::Class => "inline-button";
+                        At::Type => "button";
+                    },
+                    tr!("editor.credit_yourself"),
+                    ev(Ev::Click, |_| M::CreditSelf),
+                ]
+            },
+        ]
+    } else {
+        let found = authors.found.clone();
+        let suggestions = authors
+            .found
+            .iter()
+            .map(|user| option![attrs! { At::Value => user.user_name }, user.display_name()]);
+        div![
+            input![
+                {
+                    let mut attrs = attrs! {
+                        At::Placeholder => tr!("editor.add_author");
+                        At::AriaLabel => tr!("editor.authors");
+                        At::Type => "text";
+                        At::Name => "authors";
+                        At::List => AUTHOR_SUGGESTIONS_ID,
+                        At::Value => authors.query,
+                    };
+                    attrs.add_multiple(At::Class, &["single-line-text-entry", "tag-entry"]);
+                    attrs
+                },
+                input_ev(Ev::Input, M::AuthorQuery),
+                // Picking a suggestion replaces the text with the user name found.
+                ev(Ev::Change, move |e| {
+                    let name = e.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?.value();
+                    let user = found.into_iter().find(|user| user.user_name == name)?;
+                    Some(M::AddAuthor(user))
+                }),
+            ],
+            datalist![attrs! { At::Id => AUTHOR_SUGGESTIONS_ID }, suggestions],
+        ]
+    };
+    div![
+        attrs! { At::Class => "editor-authors" },
+        credited,
+        unlisted,
+        picker,
+    ]
+}
+/// Archives or deletes the post, after asking.
+fn post_action_button(action: Action) -> Node<M> {
+    button![
+        attrs! {
+            At::Class => "inline-button";
+            At::Type => "button";
+        },
+        action.label(),
+        ev(Ev::Click, move |_| M::AskTo(action)),
+    ]
+}
+fn action_buttons(s: &S, actions: &[Action]) -> Node<M> {
+    if !s.is_savable() {
+        return div![
+            attrs! {
+                At::Class => "editor-actions",
+            },
+            input![attrs! {
+                At::Class => "inline-button",
+                At::Type => "submit",
+                At::Value => "Save",
+                At::Disabled => true.as_at_value(),
+            }],
+            preview_toggle(s),
+            p![
+                attrs! { At::Class => "editor-notice" },
+                "This post belongs to someone else, and you do not have permission to edit posts of others.",
+            ],
+        ];
+    }
     div![
         attrs! {
             At::Class => "editor-actions",
@@ -126,9 +476,12 @@ fn action_buttons(s: &S) -> Node<M> {
         } else {
             empty![]
         },
+        preview_toggle(s),
+        actions.iter().copied().map(post_action_button),
     ]
 }
-pub fn editor(s: &S) -> Option<Node<M>> {
+pub fn editor(s: &S, user: Option<&User>, slug_prefix: &str) -> Option<Node<M>> {
+    let user_id = user.map(|user| user.id);
     let (title, slug, body) = get_title_slug_body(s)?;
     let slug_hint_mem = slug
         .map(|_| None)
@@ -138,11 +491,24 @@ pub fn editor(s: &S) -> Option<Node<M>> {
         .map(String::as_str)
         .or(slug)
         .unwrap_or("");
+    let errors = s.errors()?;
     Some(div![
         attrs! { At::Class => "editor" },
         title_field(title),
-        slug_field(slug.unwrap_or(""), slug_hint),
-        body_field(body),
-        action_buttons(s),
+        field_errors(errors, "title"),
+        slug_field(
+            slug.unwrap_or(""),
+            slug_hint,
+            if let S::New(..) = s { slug_prefix } else { "" },
+        ),
+        field_errors(errors, "slug"),
+        body_field(body, s.preview()),
+        field_errors(errors, "body"),
+        s.tags().map_or_else(|| empty![], tags_field),
+        s.authors().map_or_else(|| empty![], |authors| authors_field(authors, user_id)),
+        field_errors(errors, "authors"),
+        s.seo().map_or_else(|| empty![], |(description, image)| seo_field(description, image, errors)),
+        form_errors(errors, &["title", "slug", "body", "authors", "meta_description", "social_image"]),
+        action_buttons(s, &s.post_actions(user)),
     ])
 }
@@ -1,33 +1,123 @@
 use crate::{
-    locations::Location,
+    locations::{Location, M as LocationM},
     messages::M as GlobalM,
-    model::StoreOperations as GSOp,
-    requests::PostQuery,
-    shared::retry,
+    model::{Store as GlobalS, StoreOperations as GSOp},
+    requests::{self, api, PostFilter, PostQuery},
+    shared::{loadable::Loadable, retry},
 };
+use db_models::models::posts;
 
+pub mod infinite;
 mod messages;
 mod state;
 mod views;
 pub use messages::{update, M};
 pub use state::S;
-pub use views::render;
-
-pub async fn data_load(s: S) -> GlobalM {
-    const POST_LOAD_MSG: retry::LogPair<'static> = retry::LogPair {
-        pre_completion: "fetching posts",
-        post_completion: "parsing fetched posts",
-    };
-    const POSTS_URL: &str = "/api/posts";
-    let query = s.query.unwrap_or_else(PostQuery::default);
-    let url = format!("{}?{}", POSTS_URL, query);
-    let res = retry::fetch_json_with_retry(
-        url.into(), 
-        &POST_LOAD_MSG,
-        None,
-    ).await;
+pub use views::{render, render_skeleton};
+
+/// The page of the listing the server sent, or that the cached page is still current.
+enum Fetched {
+    Changed(Vec<posts::Listed>, Option<String>),
+    Unchanged,
+}
+impl Fetched {
+    fn into_store_op(self, query: PostQuery) -> GSOp {
+        match self {
+            Self::Changed(posts, etag) => GSOp::PostListing(query, posts, etag),
+            Self::Unchanged => GSOp::PostListingUnchanged(query),
+        }
+    }
+}
+
+/// Fetches a page of the listing, asking the server to skip resending it if `etag` still matches.
+async fn fetch_listing(query: &PostQuery, etag: Option<String>) -> Result<Fetched, retry::FailReason> {
+    let url = format!("{}?{}", api::posts::LIST.url(&[]), query);
+    let res = requests::get(requests::Get::conditional(url, etag)).await?;
+    if res.is_not_modified() {
+        return Ok(Fetched::Unchanged);
+    }
+    Ok(Fetched::Changed(res.json()?, res.etag))
+}
+
+/// The heading of the listing, naming what the posts were narrowed down by.
+pub fn heading(s: &S, gs: &GlobalS) -> String {
+    match s.query.as_ref().and_then(PostQuery::filter) {
+        Some(PostFilter::Author(id)) => {
+            let listed_author = gs
+                .listing_for(&s.query_or_default())
+                .and_then(|listing| listing.author(*id));
+            match listed_author {
+                Some(author) => format!("Posts by {}", author.display_name()),
+                None => "Posts by this author".to_owned(),
+            }
+        }
+        Some(PostFilter::Tag(name)) => format!("Posts tagged {}", name),
+        None => "Posts".to_owned(),
+    }
+}
+
+/// The ETag of the cached page for the query, if there is one to revalidate.
+pub fn cached_etag(s: &S, gs: &GlobalS) -> Option<String> {
+    gs.listing_for(&s.query_or_default())
+        .and_then(|listing| listing.etag.clone())
+}
+
+pub async fn data_load(s: S, etag: Option<String>) -> GlobalM {
+    let query = s.query_or_default();
+    match fetch_listing(&query, etag).await {
+        Err(reason) => GlobalM::RenderPage(Location::Listing(S {
+            listing: Loadable::Failed(reason),
+            ..s
+        })),
+        Ok(fetched) => GlobalM::Grouped(vec![
+            GlobalM::StoreOp(fetched.into_store_op(query)),
+            GlobalM::RenderPage(Location::Listing(S {
+                listing: Loadable::Loaded(()),
+                ..s
+            })),
+        ]),
+    }
+}
+
+/// Refreshes the cached page shown, patching it in place. The cached page stays up if this fails.
+pub async fn refresh(s: S, etag: Option<String>) -> GlobalM {
+    let query = s.query_or_default();
+    match fetch_listing(&query, etag).await {
+        Err(reason) => {
+            log::warn!("Could not refresh the cached posts: {:?}.", reason);
+            GlobalM::NoOp
+        }
+        Ok(fetched) => GlobalM::StoreOpWithMessage(fetched.into_store_op(query), || GlobalM::NoOp),
+    }
+}
+
+/// Fetches `next`, the page after those cached for `query`, to add it to them.
+pub async fn load_more(query: PostQuery, next: PostQuery) -> GlobalM {
+    let loaded = GlobalM::Location(LocationM::Listing(M::MoreLoaded));
+    match fetch_listing(&next, None).await {
+        Ok(Fetched::Changed(posts, _)) => GlobalM::Grouped(vec![
+            GlobalM::StoreOp(GSOp::MorePosts(query, posts)),
+            loaded,
+        ]),
+        Ok(Fetched::Unchanged) => {
+            log::warn!("The server said a page it was not asked to revalidate was unchanged.");
+            loaded
+        }
+        Err(reason) => GlobalM::Grouped(vec![loaded, reason.notify("attempt.load_more_posts")]),
+    }
+}
+
+/// Counts the posts of each tab. The tabs read fine without their counts, so failures are only
+/// logged.
+pub async fn load_counts() -> GlobalM {
+    let res = requests::get(requests::Get::new(api::posts::COUNTS.url(&[])))
+        .await
+        .and_then(|res| res.json());
     match res {
-        Err(_) => GlobalM::NoOp,
-        Ok(obj) => GlobalM::StoreOpWithMessage(GSOp::PostListing(query, obj), || GlobalM::RenderPage(Location::Listing(S { query: None }))),
+        Ok(counts) => GlobalM::Location(LocationM::Listing(M::CountsLoaded(counts))),
+        Err(reason) => {
+            log::warn!("Could not count the posts of each tab: {:?}.", reason);
+            GlobalM::NoOp
+        }
     }
 }
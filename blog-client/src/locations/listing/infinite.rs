@@ -0,0 +1,83 @@
+//! Loading more of the listing as its end is scrolled into view.
+
+use seed::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::{
+    locations::{listing::M, M as LocationM},
+    messages::M as GlobalM,
+    requests::PostQuery,
+};
+
+/// The id of the element at the end of the listing that is watched for.
+pub const SENTINEL_ID: &str = "listing-sentinel";
+/// How far below the screen the end of the listing starts loading, so it is there once scrolled to.
+const ROOT_MARGIN: &str = "0px 0px 400px 0px";
+
+/// Whether the browser can tell when the end of the listing is in view. Where it can't, more posts
+/// are loaded by a button instead.
+pub fn is_supported() -> bool {
+    js_sys::Reflect::has(&seed::window(), &"IntersectionObserver".into()).unwrap_or(false)
+}
+
+/// Watches the end of the listing while `next` is the page to load, asking for it whenever the end
+/// comes into view. Stops watching once dropped.
+pub struct Observer {
+    next: PostQuery,
+    observer: web_sys::IntersectionObserver,
+    _callback: Closure<dyn FnMut(js_sys::Array)>,
+}
+impl Observer {
+    /// Whether this is still watching for `next` to be loaded.
+    pub fn watches(&self, next: &PostQuery) -> bool {
+        self.next == *next
+    }
+}
+impl Drop for Observer {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+impl std::fmt::Debug for Observer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Observer {{ next: {} }}", self.next)
+    }
+}
+
+/// Starts watching the end of the listing once it is next rendered, or [`None`] if the browser
+/// can't. Loads asked for while one is in flight are dropped by the listing.
+pub fn observe(next: PostQuery, orders: &mut impl Orders<GlobalM, GlobalM>) -> Option<Observer> {
+    if !is_supported() {
+        return None;
+    }
+    let send = orders.msg_sender();
+    let callback = Closure::wrap(Box::new(move |entries: js_sys::Array| {
+        let in_view = entries.iter().any(|entry| {
+            entry
+                .dyn_into::<web_sys::IntersectionObserverEntry>()
+                .map_or(false, |entry| entry.is_intersecting())
+        });
+        if in_view {
+            send(Some(GlobalM::Location(LocationM::Listing(M::LoadMore))));
+        }
+    }) as Box<dyn FnMut(js_sys::Array)>);
+    let observer = web_sys::IntersectionObserver::new_with_options(
+        callback.as_ref().unchecked_ref(),
+        web_sys::IntersectionObserverInit::new().root_margin(ROOT_MARGIN),
+    )
+    .map_err(|e| log::warn!("Could not watch the end of the listing: {:?}.", e))
+    .ok()?;
+    let watching = observer.clone();
+    orders.after_next_render(move |_| {
+        match seed::document().get_element_by_id(SENTINEL_ID) {
+            Some(sentinel) => watching.observe(&sentinel),
+            None => log::debug!("The listing was left before its end was rendered."),
+        }
+        GlobalM::NoOp
+    });
+    Some(Observer {
+        next,
+        observer,
+        _callback: callback,
+    })
+}
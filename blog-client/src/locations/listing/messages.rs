@@ -1,13 +1,45 @@
 use seed::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::{locations::listing::S, messages::M as GlobalM, model::Store as GlobalS};
+use crate::{
+    locations::listing::{self, S},
+    messages::M as GlobalM,
+    model::Store as GlobalS,
+};
+use db_models::models::stats;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub enum M {}
+pub enum M {
+    /// Counts the posts of each tab, for users who see the tabs.
+    LoadCounts,
+    CountsLoaded(stats::PostCounts),
+    /// Fetches the page after those cached, as the end of the listing comes into view.
+    LoadMore,
+    /// The page after those cached was fetched, or could not be.
+    MoreLoaded,
+}
 
-pub fn update(m: M, _s: &mut S, _gs: &GlobalS, _orders: &mut impl Orders<GlobalM, GlobalM>) {
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
     match m {
-        // M:: => {}
+        M::LoadCounts => {
+            if gs.user.is_some() {
+                orders.perform_cmd(listing::load_counts());
+            }
+        }
+        M::CountsLoaded(counts) => s.counts = Some(counts),
+        M::LoadMore => {
+            let query = s.query_or_default();
+            let next = gs.listing_for(&query).and_then(|listing| listing.next.clone());
+            match next {
+                Some(next) if !s.loading_more => {
+                    s.loading_more = true;
+                    orders.perform_cmd(listing::load_more(query, next));
+                }
+                _ => {
+                    orders.skip();
+                }
+            }
+        }
+        M::MoreLoaded => s.loading_more = false,
     }
 }
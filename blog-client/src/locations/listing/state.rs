@@ -1,13 +1,68 @@
 use seed::prelude::*;
 use serde::{Deserialize, Serialize};
+use tap::*;
 
-use crate::requests::PostQuery;
+use crate::{
+    requests::{PostFilter, PostQuery, PostState},
+    shared::loadable::Loadable,
+};
+use db_models::models::stats;
+use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct S {
     pub query: Option<PostQuery>,
+    /// The posts themselves are cached in the store, so this only follows fetching them.
+    pub listing: Loadable<()>,
+    /// How many posts each tab lists, once counted for users who see the tabs.
+    pub counts: Option<stats::PostCounts>,
+    /// Whether the page after those cached is being fetched, so it is only asked for once.
+    pub loading_more: bool,
 }
 impl S {
+    /// The posts on the page of them described by `search`, such as `?tab=drafts`.
+    pub fn from_search(search: &seed::browser::url::UrlSearch) -> Self {
+        Self {
+            query: Self::parse_search(search),
+            ..Self::default()
+        }
+    }
+    /// The posts narrowed down by `filter`, on the page of them described by `search`.
+    pub fn filtered(filter: PostFilter, search: &seed::browser::url::UrlSearch) -> Self {
+        let query = Self::parse_search(search).unwrap_or_default();
+        Self {
+            query: Some(query.filtered(filter)),
+            ..Self::default()
+        }
+    }
+    /// The query described by `search`, if there is one. Searches that can't be read fall back to
+    /// the first page.
+    fn parse_search(search: &seed::browser::url::UrlSearch) -> Option<PostQuery> {
+        use std::convert::TryFrom;
+        if search.iter().count() == 0 {
+            return None;
+        }
+        let query = PostQuery::try_from(search)
+            .tap_err(|e| log::error!("Attempting to parse search {:?} led to error: {}.", search, e))
+            .unwrap_or_default();
+        Some(query)
+    }
+
+    /// The tab of the listing shown. Listings without one show the published posts.
+    pub fn tab(&self) -> PostState {
+        self.query.as_ref().and_then(PostQuery::state).unwrap_or(PostState::Published)
+    }
+
+    /// The first page of the tab for `state`, narrowed down as this page is.
+    pub fn tab_url(&self, state: PostState) -> Url {
+        Self::generate_url(&self.query_or_default().in_state(Some(state)))
+    }
+
+    /// The query the page lists, which is the first page when none is given.
+    pub fn query_or_default(&self) -> PostQuery {
+        self.query.clone().unwrap_or_default()
+    }
+
     pub fn to_url(&self) -> Url {
         if let Some(q) = &self.query {
             Self::generate_url(q)
@@ -20,8 +75,21 @@ impl S {
         Url::new().set_path(vec!["blog"])
     }
 
+    /// The first page of the posts narrowed down by `filter`.
+    pub fn filtered_url(filter: &PostFilter) -> Url {
+        match filter {
+            PostFilter::Author(id) => Url::new().set_path(&["blog", "authors", id.to_string().as_str()]),
+            PostFilter::Tag(name) => {
+                let name = percent_encode(name.as_bytes(), NON_ALPHANUMERIC).to_string();
+                Url::new().set_path(&["blog", "tags", name.as_str()])
+            }
+        }
+    }
+
     pub fn generate_url(q: &PostQuery) -> Url {
-        Self::url_root().set_search(q)
+        q.filter()
+            .map_or_else(Self::url_root, Self::filtered_url)
+            .set_search(q)
     }
 
     pub fn generate_next_url(&self) -> Option<Url> {
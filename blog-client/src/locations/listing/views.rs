@@ -1,13 +1,17 @@
 use seed::prelude::*;
 
 use crate::{
-    locations::listing::{M, S},
-    model::{Name, Store as GlobalS},
+    locations::listing::{self, M, S},
+    model::{CachedListing, Store as GlobalS},
+    requests::{PostFilter, PostQuery, PostState},
     shared,
 };
-use db_models::models::posts;
+use db_models::models::{posts, stats, users};
 
-fn render_post(p: &posts::BasicData, author: Option<&Name>) -> Node<M> {
+/// How many placeholder entries stand in for the posts while they load.
+const SKELETON_ROWS: usize = 5;
+
+fn render_post(p: &posts::BasicData, author: &users::AuthorSummary) -> Node<M> {
     log::debug!("Not called");
     li![
         attrs! {
@@ -33,37 +37,67 @@ fn render_post(p: &posts::BasicData, author: Option<&Name>) -> Node<M> {
                 .map(|d| d.to_string())
                 .unwrap_or_else(|| "Unpublished".to_owned())
         ],
-        author.map_or_else(|| empty![], |n| n.to_view()), // TODO
+        p![
+            attrs! { At::Class => "post-byline" },
+            "By ",
+            a![
+                attrs! { At::Href => S::filtered_url(&PostFilter::Author(author.id)) },
+                author.display_name(),
+            ],
+        ],
+        if p.reactions > 0 {
+            p![
+                attrs! { At::Class => "post-reactions" },
+                tr!("listing.reactions", count = p.reactions),
+            ]
+        } else {
+            empty![]
+        },
+    ]
+}
+/// Placeholder entries sized like those of `render_post`.
+fn render_skeleton_rows<Ms: Clone>() -> Node<Ms> {
+    ul![(0..SKELETON_ROWS).map(|_| li![
+        attrs! { At::Class => "post-item" },
+        shared::views::skeleton::block("40%", "1.5em"),
+        shared::views::skeleton::block("10em", "1em"),
+    ])]
+}
+/// The listing as it is laid out before any posts are known.
+pub fn render_skeleton<Ms: Clone>(heading: &str) -> Node<Ms> {
+    div![
+        attrs! {
+            At::Class => "post-list";
+            At::from("aria-busy") => "true";
+        },
+        h1![heading],
+        render_skeleton_rows(),
     ]
 }
-fn render_post_list(empty_msg: &str, posts: &[posts::BasicData]) -> Node<M> {
+fn render_post_list<'a>(empty_msg: Node<M>, posts: impl IntoIterator<Item = &'a posts::Listed>) -> Node<M> {
+    let posts: Vec<_> = posts.into_iter().collect();
     if posts.is_empty() {
         log::debug!("Calling render_post_list.");
-        p![attrs! {At::Class => "no-post-text"}, empty_msg,]
+        div![attrs! {At::Class => "no-post-text"}, empty_msg]
     } else {
         log::debug!("Not called");
         ul![
             posts
-                .iter()
-                .map(|p| -> Node<M> { render_post(p, None) }) // TODO load authors
+                .into_iter()
+                .map(|(p, author)| -> Node<M> { render_post(p, author) })
         ]
     }
 }
-pub fn render_post_pagination_buttons(s: &S) -> Node<M> {
-    // TODO
+/// The end of the listing. While there is more to list, more is loaded once the end is scrolled
+/// into view, or by a button where that can't be told.
+fn render_listing_end(s: &S, listing: Option<&CachedListing>) -> Node<M> {
+    let listing = match listing {
+        Some(listing) => listing,
+        None => return empty![],
+    };
     div![
         attrs! {
-            At::Class => "pagination-buttons";
-        },
-        match s.generate_next_url() {
-            Some(url) => a![
-                attrs! {
-                    At::Class => "next";
-                    At::Href => url;
-                },
-                "Next >"
-            ],
-            None => empty![],
+            At::Class => "listing-end";
         },
         match s.generate_prev_url() {
             Some(url) => a![
@@ -75,40 +109,137 @@ pub fn render_post_pagination_buttons(s: &S) -> Node<M> {
             ],
             None => empty![],
         },
+        match &listing.next {
+            // The sentinel comes first, so that it stays the same element as what follows it
+            // changes.
+            Some(_) => vec![
+                div![attrs! {
+                    At::Id => listing::infinite::SENTINEL_ID;
+                    At::from("aria-hidden") => "true";
+                }],
+                if s.loading_more {
+                    ul![
+                        attrs! {
+                            At::Class => "listing-more";
+                            At::from("aria-busy") => "true";
+                        },
+                        li![
+                            attrs! { At::Class => "post-item" },
+                            shared::views::skeleton::block("40%", "1.5em"),
+                            shared::views::skeleton::block("10em", "1em"),
+                        ],
+                    ]
+                } else if listing::infinite::is_supported() {
+                    empty![]
+                } else {
+                    button![
+                        attrs! { At::Class => "inline-button" },
+                        tr!("listing.load_more"),
+                        ev(Ev::Click, |_| M::LoadMore),
+                    ]
+                },
+            ],
+            None if listing.published.is_empty() && listing.unpublished.is_empty() => vec![],
+            None => vec![p![attrs! { At::Class => "listing-end-marker" }, tr!("listing.end")]],
+        },
     ]
 }
+/// What to say when there are no posts to list.
+fn empty_msg(filter: Option<&PostFilter>) -> Node<M> {
+    p![match filter {
+        Some(PostFilter::Author(_)) => "No posts by this author yet.".to_owned(),
+        Some(PostFilter::Tag(name)) => format!("No posts are tagged {} yet.", name),
+        None => "Coming soon.".to_owned(),
+    }]
+}
+/// What to say when a tab has no posts to list.
+fn empty_tab_msg(state: PostState) -> Node<M> {
+    match state {
+        PostState::Published => p![tr!("listing.empty.published")],
+        PostState::Draft => p![
+            tr!("listing.empty.drafts"),
+            " ",
+            a![
+                attrs! { At::Href => "/blog/editor/new" },
+                tr!("listing.start_writing"),
+            ],
+        ],
+        PostState::Archived => p![tr!("listing.empty.archived")],
+    }
+}
+/// Switches between the posts in each state, for users who may see them all.
+fn render_tabs(s: &S, counts: Option<&stats::PostCounts>) -> Node<M> {
+    let active = s.tab();
+    nav![
+        attrs! {
+            At::Class => "listing-tabs";
+            At::from("aria-label") => tr!("listing.tabs");
+        },
+        PostState::ALL.iter().copied().map(|state| {
+            let (key, count) = match state {
+                PostState::Published => ("listing.tab.published", counts.map(|c| c.published)),
+                PostState::Draft => ("listing.tab.drafts", counts.map(|c| c.drafts)),
+                PostState::Archived => ("listing.tab.archived", counts.map(|c| c.archived)),
+            };
+            let label = match count {
+                Some(count) => format!("{} ({})", tr!(key), count),
+                None => tr!(key),
+            };
+            let mut attrs = attrs! {
+                At::Class => "listing-tab";
+                At::Href => s.tab_url(state);
+            };
+            if state == active {
+                attrs.add(At::from("aria-current"), "page");
+            }
+            a![attrs, label]
+        }),
+    ]
+}
+/// The posts of the tab shown. Posts are split by whether they were published when cached, so
+/// archived posts may be on either side.
+fn tab_posts(state: PostState, listing: &CachedListing) -> Vec<&posts::Listed> {
+    match state {
+        PostState::Published => listing.published.iter().collect(),
+        PostState::Draft => listing.unpublished.iter().collect(),
+        PostState::Archived => listing.published.iter().chain(&listing.unpublished).collect(),
+    }
+}
 pub fn render(s: &S, gs: &GlobalS) -> Vec<Node<M>> {
+    let listing = gs.listing_for(&s.query_or_default());
+    let filter = s.query.as_ref().and_then(PostQuery::filter);
+    // Only users who may see unpublished posts have more than one tab to pick from.
+    let tabbed = gs.user.as_ref().map_or(false, |user| user.can_see_unpublished);
     vec![
         div![
             attrs! {
                 At::Class => "post-list";
             },
-            h1!["Posts"],
-            if let Some(posts) = gs.published_posts.as_ref() {
-                log::debug!("Calling published render.");
-                vec![render_post_list("Coming soon.", posts.as_slice())]
+            h1![listing::heading(s, gs)],
+            match filter {
+                Some(_) => a![
+                    attrs! { At::Href => S::url_root() },
+                    "All posts",
+                ],
+                None => empty![],
+            },
+            if tabbed {
+                render_tabs(s, s.counts.as_ref())
             } else {
-                log::debug!("No posts found.");
-                vec![shared::views::loading()]
+                empty![]
+            },
+            match listing {
+                Some(listing) if tabbed => {
+                    let state = s.tab();
+                    render_post_list(empty_tab_msg(state), tab_posts(state, listing))
+                }
+                Some(listing) => render_post_list(empty_msg(filter), &listing.published),
+                None => {
+                    log::debug!("No posts found.");
+                    render_skeleton_rows()
+                }
             },
         ],
-        match gs {
-            GlobalS {
-                user: Some(user),
-                unpublished_posts: Some(posts),
-                ..
-            } if user.can_see_unpublished => {
-                log::debug!("Calling unpublished render.");
-                div![
-                    attrs! {
-                        At::Class => "unpublished-post-list";
-                    },
-                    h1!["Unpublished Drafts"],
-                    render_post_list("None found.", posts.as_slice()),
-                ]
-            }
-            _ => empty![],
-        },
-        render_post_pagination_buttons(s),
+        render_listing_end(s, listing),
     ]
 }
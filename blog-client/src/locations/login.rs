@@ -2,6 +2,7 @@ use crate::{
     locations::*,
     messages::{M as GlobalM},
     model::StoreOperations as GSOp,
+    requests::{self, api},
     shared::{Authorization, retry},
 };
 use db_models::models::users;
@@ -10,7 +11,7 @@ mod messages;
 mod state;
 mod views;
 pub use messages::{M, update};
-pub use state::S;
+pub use state::{Gate, S};
 pub use views::render;
 
 const LOGOUT_MSG: retry::LogPair<'static> = retry::LogPair {
@@ -18,21 +19,15 @@ const LOGOUT_MSG: retry::LogPair<'static> = retry::LogPair {
     post_completion: "reading log out response",
 };
 
-const FIND_ME_MSG: retry::LogPair<'static> = retry::LogPair {
-    pre_completion: "discovering myself",
-    post_completion: "reading what was discovered",
-};
-
 pub async fn logout_trigger() -> GlobalM {
-    const LOGOUT_URL: &str = "/api/login";
-    let req = Request::new(LOGOUT_URL).method(Method::Delete);
+    let req = api::call(&api::login::LOG_OUT, &[]);
     let res = retry::fetch_text_with_retry(
         req,
         &LOGOUT_MSG,
-        None,
+        retry::NO_RETRY,
     ).await;
     match res {
-        Err(_) => GlobalM::NoOp,
+        Err(reason) => reason.notify("attempt.log_out"),
         Ok(obj) => GlobalM::StoreOpWithMessage(GSOp::RemoveUser(obj), || GlobalM::Grouped(vec![
             GlobalM::ChangeMenu(Authorization::LoggedOut),
             GlobalM::ChangePageAndUrl(Location::Listing(listing::S::default())),
@@ -40,15 +35,37 @@ pub async fn logout_trigger() -> GlobalM {
     }
 }
 
+/// `next` if it is a path on this site, so that logging in cannot send the user somewhere else.
+/// Browsers read a leading `/\` like `//`, and drop tabs and newlines, so those are refused too.
+pub fn same_origin_path(next: &str) -> Option<String> {
+    let is_path = next.starts_with('/') && !next.starts_with("//") && !next.starts_with("/\\");
+    if is_path && !next.chars().any(char::is_control) {
+        Some(next.to_owned())
+    } else {
+        log::warn!("Ignoring page {:?} to go to after logging in.", next);
+        None
+    }
+}
+
 pub async fn find_current_user() -> Option<users::DataNoMeta> {
-    const SELF_URL: &str = "/api/accounts/me";
     log::info!("Detecting if already logged in...");
-    let res = retry::fetch_json_with_retry(
-        SELF_URL.into(),
-        &FIND_ME_MSG,
-        Some(1),
-    ).await;
+    let res = requests::get(requests::Get::new(api::accounts::GET_SELF.url(&[]))).await.and_then(|res| res.json());
     res.ok()
 }
 
+#[cfg(test)]
+mod test {
+    use super::same_origin_path;
 
+    #[test]
+    fn only_paths_on_this_site_are_followed() {
+        assert_eq!(same_origin_path("/blog/profile"), Some("/blog/profile".to_owned()));
+        assert_eq!(same_origin_path("/blog?lim=10&offset=0"), Some("/blog?lim=10&offset=0".to_owned()));
+        assert_eq!(same_origin_path("https://example.com/blog"), None);
+        assert_eq!(same_origin_path("//example.com/blog"), None);
+        assert_eq!(same_origin_path("/\\example.com/blog"), None);
+        assert_eq!(same_origin_path("/\t/example.com/blog"), None);
+        assert_eq!(same_origin_path("blog/profile"), None);
+        assert_eq!(same_origin_path(""), None);
+    }
+}
@@ -1,13 +1,15 @@
 
 use seed::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::pin::Pin;
 use tap::*;
 
 use crate::{
-    locations::login::S,
-    messages::M as GlobalM,
+    locations::login::{state, Gate, S},
+    messages::{AsyncM as GlobalAsyncM, M as GlobalM},
     model::Store as GlobalS,
 };
+use login_enum::challenge::{Answer, Descriptor};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum M {
@@ -25,13 +27,63 @@ pub enum M {
     CreateCredential,
 
     CreateSession,
+    /// The server asked for a challenge to be passed before logging in.
+    Challenged(Descriptor),
+    /// The challenge was passed, so logging in is retried with the answer.
+    Answered(Answer),
 
     SetFocus,
 }
 
+/// What applying a message asks of the rest of the app, apart from the changes to the form.
+pub enum Effect {
+    /// Runs the request, sending on the message it resolves to.
+    Perform(Pin<Box<dyn GlobalAsyncM>>),
+    /// Moves the focus to the user name field.
+    FocusUserName,
+}
+impl std::fmt::Debug for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Perform(_) => write!(f, "Perform(request -- no display)"),
+            Self::FocusUserName => write!(f, "FocusUserName"),
+        }
+    }
+}
+
+fn focus_user_name() -> Option<()> {
+    log::trace!("Setting form focus...");
+    let el: web_sys::HtmlElement = seed::body()
+        .query_selector("input[name=username]")
+        .tap_err(|_| log::error!("Could not find username field!"))
+        .ok()??
+        .dyn_into()
+        .tap_err(|_| log::error!("Input field is not an HtmlElement!"))
+        .ok()?;
+    el.focus()
+        .tap_err(|_| log::error!("Failed to focus on the username form input."))
+        .ok()?;
+    Some(())
+}
+
 pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    for effect in transition(m, s, gs) {
+        match effect {
+            Effect::Perform(cmd) => {
+                orders.perform_cmd(cmd);
+            }
+            Effect::FocusUserName => {
+                let _ = focus_user_name();
+            }
+        }
+    }
+}
+
+/// Applies `m` to the form, returning what else it asks for.
+pub fn transition(m: M, s: &mut S, gs: &GlobalS) -> Vec<Effect> {
     // TODO better logging.
     log::debug!("Updating login page with {:?}", m);
+    let mut effects = vec![];
     match m {
         // Fields always available, whether signing up or logging in.
         M::UserName(un) => s.username = un,
@@ -46,33 +98,97 @@ pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, G
         // API calls
         M::CreateUser => {
             log::trace!("Creating a user...");
-            orders.perform_cmd(s.create_user_post());
+            effects.push(Effect::Perform(Box::pin(s.create_user_post())));
         }
         M::CreateSession => {
-            log::trace!("Creating a session...");
-            orders.perform_cmd(s.create_session_post());
+            // Logging in is retried on its own once the challenge is passed.
+            if s.gate == Gate::Open {
+                log::trace!("Creating a session...");
+                effects.push(Effect::Perform(Box::pin(s.create_session_post(None))));
+            }
+        }
+        M::Challenged(Descriptor::ProofOfWork(challenge)) => {
+            log::trace!("Solving a challenge of difficulty {}...", challenge.difficulty);
+            s.gate = Gate::Solving;
+            effects.push(Effect::Perform(Box::pin(state::solve(challenge))));
+        }
+        M::Challenged(Descriptor::External { provider, site_key }) => {
+            s.gate = Gate::Waiting { provider, site_key };
+        }
+        M::Answered(answer) => {
+            if s.gate != Gate::Open {
+                log::trace!("Creating a session with the answer to the challenge...");
+                s.gate = Gate::Open;
+                effects.push(Effect::Perform(Box::pin(s.create_session_post(Some(answer)))));
+            }
         }
         M::CreateCredential => {
             log::trace!("Creating credentials...");
             if let Some(u) = gs.user.as_ref() {
-                orders.perform_cmd(s.create_credential_post(u));
+                effects.push(Effect::Perform(Box::pin(s.create_credential_post(u))));
             }
         }
-        M::SetFocus => {
-            let _ = (|| {
-                log::trace!("Setting form focus...");
-                let el: web_sys::HtmlElement = seed::body()
-                    .query_selector("input[name=username]")
-                    .tap_err(|_| log::error!("Could not find username field!"))
-                    .ok()??
-                    .dyn_into()
-                    .tap_err(|_| log::error!("Input field is not an HtmlElement!"))
-                    .ok()?;
-                el.focus()
-                    .tap_err(|_| log::error!("Failed to focus on the username form input."))
-                    .ok()?;
-                Some(())
-            })();
-        }
+        M::SetFocus => effects.push(Effect::FocusUserName),
+    }
+    effects
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_requests_with_what_they_need_are_sent() {
+        let mut s = S::default();
+        let effects = transition(M::CreateCredential, &mut s, &GlobalS::default());
+        assert!(effects.is_empty(), "unexpected {:?}", effects);
+        let effects = transition(M::CreateSession, &mut s, &GlobalS::default());
+        assert!(matches!(effects.as_slice(), [Effect::Perform(_)]), "unexpected {:?}", effects);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fields_are_filled_in_without_effects() {
+        let mut s = S::default();
+        let gs = GlobalS::default();
+        assert!(transition(M::UserName("ben".to_owned()), &mut s, &gs).is_empty());
+        assert!(transition(M::SetCreateMode(true), &mut s, &gs).is_empty());
+        assert!(transition(M::Email("ben@example.com".to_owned()), &mut s, &gs).is_empty());
+        assert_eq!(s.username, "ben");
+        assert!(s.is_create_mode);
+        assert_eq!(s.email.as_deref(), Some("ben@example.com"));
+        let effects = transition(M::SetFocus, &mut s, &gs);
+        assert!(matches!(effects.as_slice(), [Effect::FocusUserName]), "unexpected {:?}", effects);
+    }
+
+    #[test]
+    fn logging_in_waits_on_the_challenge() {
+        let mut s = S::default();
+        let gs = GlobalS::default();
+        let challenge = login_enum::challenge::Challenge {
+            token: "v1.local.token".to_owned(),
+            difficulty: 16,
+        };
+        let effects = transition(M::Challenged(Descriptor::ProofOfWork(challenge)), &mut s, &gs);
+        assert!(matches!(effects.as_slice(), [Effect::Perform(_)]), "unexpected {:?}", effects);
+        assert_eq!(s.gate, Gate::Solving);
+        assert!(transition(M::CreateSession, &mut s, &gs).is_empty());
+
+        let answer = Answer::ProofOfWork {
+            token: "v1.local.token".to_owned(),
+            nonce: 7,
+        };
+        let effects = transition(M::Answered(answer.clone()), &mut s, &gs);
+        assert!(matches!(effects.as_slice(), [Effect::Perform(_)]), "unexpected {:?}", effects);
+        assert_eq!(s.gate, Gate::Open);
+        // Answers are only sent once.
+        assert!(transition(M::Answered(answer), &mut s, &gs).is_empty());
+
+        let external = Descriptor::External {
+            provider: "captcha".to_owned(),
+            site_key: "site".to_owned(),
+        };
+        assert!(transition(M::Challenged(external), &mut s, &gs).is_empty());
+        assert!(matches!(s.gate, Gate::Waiting { .. }), "unexpected {:?}", s.gate);
+        assert!(transition(M::CreateSession, &mut s, &gs).is_empty());
+    }
+}
@@ -4,14 +4,18 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     locations::{Location, M as LocationM, listing, login::M},
-    messages::{AsyncM as GlobalAsyncM, M as GlobalM},
+    messages::{AsyncM as GlobalAsyncM, M as GlobalM, RouteMatch},
     model::{
         StoreOperations as GSOp, User as StoreUser,
     },
-    shared::{Authorization, retry},
+    requests::api,
+    shared::{Authorization, challenge, notifications, retry},
 };
 use db_models::models::users;
-use login_enum::{Authentication, CreatePassword, Password};
+use login_enum::{
+    challenge::{Answer, Challenge},
+    Authentication, CreatePassword, LoginFailure, Password,
+};
 
 const CREATE_USER_MSG: retry::LogPair<'static> = retry::LogPair {
     pre_completion: "creating user",
@@ -26,6 +30,24 @@ const CREATE_SESSION_MSG: retry::LogPair<'static> = retry::LogPair {
     post_completion: "parsing created session",
 };
 
+/// Where logging in stands with the challenge the server asks for once too many attempts failed
+/// from the same address.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Gate {
+    /// Nothing needs to be passed before logging in, or the answer is on its way.
+    Open,
+    /// A proof of work is being solved, after which logging in is retried.
+    Solving,
+    /// The user is asked to pass a check set by another service, such as a CAPTCHA, after which
+    /// logging in is retried.
+    Waiting { provider: String, site_key: String },
+}
+impl Default for Gate {
+    fn default() -> Self {
+        Self::Open
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct S {
     pub is_create_mode: bool,
@@ -36,10 +58,27 @@ pub struct S {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub email: Option<String>,
+
+    /// The path of the page to go to once logged in, already checked to be on this site.
+    pub next: Option<String>,
+    // Nothing is solving the challenge once the page is restored, so it is asked for again.
+    #[serde(skip)]
+    pub gate: Gate,
 }
 impl S {
+    /// The login page, going on to `page` once logged in.
+    pub fn returning_to(page: &Location) -> Self {
+        Self {
+            next: Some(page.to_url().to_string()),
+            ..Self::default()
+        }
+    }
     pub fn to_url(&self) -> Url {
-        Url::new().set_path(&["blog", "login"])
+        let url = Url::new().set_path(&["blog", "login"]);
+        match &self.next {
+            Some(next) => url.set_search(seed::browser::url::UrlSearch::new(vec![("next", vec![next.as_str()])])),
+            None => url,
+        }
     }
 }
 impl S {
@@ -54,22 +93,24 @@ impl S {
     }
 
     async fn create_user_post_async<'a>(data: users::NewNoMeta) -> GlobalM {
-        const CREATE_USER_URL: &'static str = "/api/accounts";
-        let req = Request::new(CREATE_USER_URL)
-            .method(Method::Post)
-            .json(&data);
+        let req = api::call_with(&api::accounts::CREATE, &[], &data);
         let req = if let Ok(req) = req {
             req
         } else {
             return GlobalM::NoOp;
         };
+        // Signing up happens before logging in, so the server asks for proof of work first.
+        let req = match challenge::prove(req).await {
+            Ok(req) => req,
+            Err(reason) => return reason.notify("attempt.create_account"),
+        };
         let res = retry::fetch_json_with_retry(
             req,
             &CREATE_CREDENTIAL_MSG,
-            None,
+            retry::NO_RETRY,
         ).await;
         match res {
-            Err(_) => GlobalM::NoOp,
+            Err(reason) => reason.notify("attempt.create_account"),
             Ok(obj) =>
                 GlobalM::StoreOpWithMessage(GSOp::User(obj), || GlobalM::Grouped(vec![
                     GlobalM::Location(LocationM::Login(M::CreateCredential)),
@@ -88,10 +129,7 @@ impl S {
     }
 
     async fn create_credential_post_async(pw: CreatePassword) -> GlobalM {
-        const CREDENTIAL_URL: &str = "/api/credentials/pws";
-        let req = Request::new(CREDENTIAL_URL)
-            .method(Method::Post)
-            .json(&pw);
+        let req = api::call_with(&api::credentials::CREATE_PASSWORD, &[], &pw);
         let req = if let Ok(req) = req {
             req
         } else {
@@ -100,47 +138,98 @@ impl S {
         let res = retry::fetch_with_retry(
             req,
             &CREATE_USER_MSG,
-            None,
+            retry::NO_RETRY,
         ).await;
         match res {
-            Err(_) => GlobalM::NoOp,
+            Err(reason) => reason.notify("attempt.set_password"),
             Ok(_) => GlobalM::ChangePageAndUrl(Location::Listing(listing::S::default())),
         }
     }
 
-    pub fn create_session_post(&self) -> impl GlobalAsyncM {
+    /// Logs in, sending `answer` along if the server asked for a challenge to be passed first.
+    pub fn create_session_post(&self, answer: Option<Answer>) -> impl GlobalAsyncM {
         let auth = Authentication::Password(Password {
             user_name: self.username.clone(),
             password: self.password.clone(),
         });
-        Self::create_session_post_async(auth)
+        Self::create_session_post_async(auth, answer, self.next.clone())
     }
 
-    async fn create_session_post_async(auth: Authentication) -> GlobalM {
+    async fn create_session_post_async(
+        auth: Authentication,
+        answer: Option<Answer>,
+        next: Option<String>,
+    ) -> GlobalM {
         log::info!("Creating session...");
         use crate::locations::*;
-        const LOGIN_URL: &str = "/api/login";
-        let req = Request::new(LOGIN_URL)
-            .method(Method::Post)
-            .json(&auth);
+        let req = api::call_with(&api::login::LOG_IN, &[], &auth);
         let req = if let Ok(req) = req {
             req
         } else {
             return GlobalM::NoOp;
         };
-        let res = retry::fetch_json_with_retry(
-            req,
-            &CREATE_SESSION_MSG,
-            None,
-        ).await;
-        log::info!("Session created with res {:?}.", res);
-        match res {
-            // TODO Display error message.
-            Err(_) => GlobalM::NoOp,
-            Ok(obj) => GlobalM::StoreOpWithMessage(GSOp::User(obj), || GlobalM::Grouped(vec![
-                GlobalM::ChangePageAndUrl(Location::Listing(listing::S::default())),
-                GlobalM::ChangeMenu(Authorization::LoggedIn),
-            ]))
+        let req = match &answer {
+            Some(answer) => challenge::with_answer(req, answer),
+            None => req,
+        };
+        // Logging in is not retried, since a refused login stays refused.
+        let res = retry::fetch_explained(req, &CREATE_SESSION_MSG, LoginFailure::STATUS_CODES).await;
+        let user = match res {
+            Err(retry::Refusal::Explained(LoginFailure::ChallengeRequired { challenge })) => {
+                log::info!("Asked to pass a challenge before logging in.");
+                return GlobalM::Location(LocationM::Login(M::Challenged(challenge)));
+            }
+            Err(retry::Refusal::Explained(failure)) => return GlobalM::Notify(
+                notifications::Level::Error,
+                describe_failure(failure),
+            ),
+            Err(retry::Refusal::Failed(reason)) => return reason.notify("attempt.log_in"),
+            Ok(res) => match res.json().await {
+                Ok(user) => user,
+                Err(e) => {
+                    log::error!("Failed to parse the logged in user: {:?}.", e);
+                    return retry::FailReason::Other.notify("attempt.log_in");
+                }
+            },
+        };
+        log::info!("Session created for {:?}.", user);
+        let mut msgs = vec![
+            GlobalM::StoreOp(GSOp::User(user)),
+            GlobalM::ChangeMenu(Authorization::LoggedIn),
+        ];
+        let next = match next.as_deref().map(str::parse::<Url>) {
+            Some(Ok(url)) => RouteMatch::location(url),
+            _ => Some(Location::Listing(listing::S::default())),
+        };
+        // Pages outside of the blog are being loaded instead.
+        if let Some(next) = next {
+            msgs.push(GlobalM::ChangePageAndUrl(next));
+        }
+        GlobalM::Grouped(msgs)
+    }
+}
+
+/// Solves the proof of work the server asked for, answering it with the solution.
+pub async fn solve(challenge: Challenge) -> GlobalM {
+    let nonce = challenge::solve(&challenge).await;
+    let answer = Answer::ProofOfWork {
+        token: challenge.token,
+        nonce,
+    };
+    GlobalM::Location(LocationM::Login(M::Answered(answer)))
+}
+
+/// Tells the user why the server refused to log them in.
+fn describe_failure(failure: LoginFailure) -> String {
+    match failure {
+        LoginFailure::BadCredentials => "Could not log in: the username or password is wrong.".to_owned(),
+        LoginFailure::Locked => "Could not log in: this account is locked.".to_owned(),
+        LoginFailure::RateLimited { retry_after_secs } => format!(
+            "Could not log in: there were too many attempts. Try again in {} seconds.",
+            retry_after_secs,
+        ),
+        LoginFailure::ChallengeRequired { .. } => {
+            "Could not log in: a check that you are not a bot is needed first.".to_owned()
         }
     }
 }
\ No newline at end of file
@@ -2,9 +2,40 @@
 use seed::prelude::*;
 
 use crate::{
-    locations::login::{M, S},
+    locations::login::{Gate, M, S},
     model::Store as GlobalS,
 };
+use login_enum::challenge::Answer;
+
+/// What the user is told of the challenge the server asked for, if any.
+///
+/// External checks are drawn into the container by the script of their provider, found by its
+/// `data-provider` and `data-site-key`. Once passed, the script hands over its response by setting
+/// it as the value of the `challenge_response` input and dispatching an `input` event on it.
+fn render_gate(gate: &Gate) -> Node<M> {
+    match gate {
+        Gate::Open => empty![],
+        Gate::Solving => p![
+            attrs! { At::Class => "login-challenge" },
+            "Checking that you are not a bot, this may take a few seconds...",
+        ],
+        Gate::Waiting { provider, site_key } => div![
+            attrs! {
+                At::Class => "login-challenge";
+                At::from("data-provider") => provider;
+                At::from("data-site-key") => site_key;
+            },
+            p!["Too many attempts to log in came from your network. Complete the check below to continue."],
+            input![
+                attrs! {
+                    At::Type => "hidden";
+                    At::Name => "challenge_response";
+                },
+                input_ev(Ev::Input, |response| M::Answered(Answer::External { response })),
+            ],
+        ],
+    }
+}
 
 pub fn render(s: &S, _gs: &GlobalS) -> Node<M> {
     div![
@@ -40,6 +71,7 @@ pub fn render(s: &S, _gs: &GlobalS) -> Node<M> {
                     input_ev(Ev::Input, M::Password),
                 ],
             ],
+            render_gate(&s.gate),
             if s.is_create_mode {
                 vec![
                     div![
@@ -103,6 +135,7 @@ pub fn render(s: &S, _gs: &GlobalS) -> Node<M> {
                     attrs! {
                         At::Type => "submit",
                         At::Value => if is_create_mode { "Sign up" } else { "Sign in" },
+                        At::Disabled => (!is_create_mode && s.gate != Gate::Open).as_at_value(),
                     },
                     ev(Ev::Click, move |e| {
                         e.prevent_default();
@@ -0,0 +1,23 @@
+use crate::{
+    locations::Location,
+    messages::M as GlobalM,
+    requests::{self, api},
+    shared::loadable::Loadable,
+};
+
+mod state;
+mod views;
+pub use state::S;
+pub use views::{part_url, render, render_skeleton};
+
+pub async fn load_series(slug: String) -> GlobalM {
+    let url = api::series::GET.url(&[&slug]);
+    let res = requests::get(requests::Get::new(url)).await.and_then(|res| res.json());
+    GlobalM::RenderPage(Location::Series(S {
+        slug,
+        series: match res {
+            Ok(series) => Loadable::Loaded(series),
+            Err(reason) => Loadable::Failed(reason),
+        },
+    }))
+}
@@ -0,0 +1,25 @@
+use seed::browser::url::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::shared::loadable::Loadable;
+use db_models::series;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    /// The friendly name of the series, or its id.
+    pub slug: String,
+    pub series: Loadable<series::WithParts>,
+}
+impl From<String> for S {
+    fn from(slug: String) -> Self {
+        Self {
+            slug,
+            series: Loadable::NotAsked,
+        }
+    }
+}
+impl S {
+    pub fn to_url(&self) -> Url {
+        Url::new().set_path(&["blog", "series", self.slug.as_str()])
+    }
+}
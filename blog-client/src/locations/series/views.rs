@@ -0,0 +1,51 @@
+use seed::prelude::*;
+
+use crate::shared::views::skeleton;
+use db_models::series;
+
+/// How many parts stand in for the list while it loads.
+const SKELETON_PARTS: usize = 3;
+
+/// Where the post with the provided id is read.
+pub fn part_url(part: &series::Part) -> String {
+    format!("/blog/posts/{}", part.slug.as_ref().map_or_else(|| part.id.to_string(), Clone::clone))
+}
+
+/// The parts of the series, in the order they are meant to be read.
+pub fn render<Ms: Clone>(series: &series::WithParts) -> Node<Ms> {
+    div![
+        attrs! { At::Class => "series" },
+        h1![series.series.title.as_str()],
+        if series.series.description.is_empty() {
+            empty![]
+        } else {
+            p![attrs! { At::Class => "series-description" }, series.series.description.as_str()]
+        },
+        if series.parts.is_empty() {
+            p![tr!("series.empty")]
+        } else {
+            ol![
+                attrs! { At::Class => "series-parts" },
+                series.parts.iter().map(|part| li![
+                    a![attrs! { At::Href => part_url(part) }, part.title.as_str()],
+                    if part.published_at.is_none() {
+                        span![attrs! { At::Class => "series-draft" }, format!(" ({})", tr!("series.draft"))]
+                    } else {
+                        empty![]
+                    },
+                ]),
+            ]
+        },
+    ]
+}
+/// The series as it is laid out before it arrives.
+pub fn render_skeleton<Ms: Clone>() -> Node<Ms> {
+    div![
+        attrs! {
+            At::Class => "series";
+            At::from("aria-busy") => "true";
+        },
+        skeleton::block("50%", "2em"),
+        (0..SKELETON_PARTS).map(|_| skeleton::lines(1)),
+    ]
+}
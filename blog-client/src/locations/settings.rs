@@ -0,0 +1,155 @@
+use seed::{browser::url::Url, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::{Access, Location, M as LocationM},
+    messages::M as GlobalM,
+    model::Store as GlobalS,
+    shared::{
+        retry::{self, FailReason},
+        views,
+    },
+};
+use login_enum::ValidationErrors;
+
+mod capabilities;
+mod deletion;
+mod name;
+mod password;
+mod post_defaults;
+mod sign_in;
+
+/// The settings of the account logged in as, each section editing one part of it.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    pub name: name::S,
+    pub password: password::S,
+    pub sign_in: sign_in::S,
+    pub post_defaults: post_defaults::S,
+    pub deletion: deletion::S,
+    pub capabilities: capabilities::S,
+}
+impl S {
+    pub fn to_url(&self) -> Url {
+        Url::new().set_path(&["blog", "profile"])
+    }
+    /// Whether the settings still need the capabilities or sign-in methods of the account to be
+    /// loaded.
+    pub fn needs_loading(&self) -> bool {
+        self.capabilities.grants.is_none() || self.sign_in.methods.is_pending()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum M {
+    Name(name::M),
+    Password(password::M),
+    SignIn(sign_in::M),
+    PostDefaults(post_defaults::M),
+    Deletion(deletion::M),
+    Capabilities(capabilities::M),
+}
+
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    match m {
+        M::Name(m) => name::update(m, &mut s.name, gs, orders),
+        M::Password(m) => password::update(m, &mut s.password, gs, orders),
+        M::SignIn(m) => sign_in::update(m, &mut s.sign_in, gs, orders),
+        M::PostDefaults(m) => post_defaults::update(m, &mut s.post_defaults, gs, orders),
+        M::Deletion(m) => deletion::update(m, &mut s.deletion, gs, orders),
+        M::Capabilities(m) => capabilities::update(m, &mut s.capabilities, gs, orders),
+    }
+}
+
+pub fn render(s: &S, gs: &GlobalS) -> Node<M> {
+    let user = match gs.user.as_ref() {
+        Some(user) => user,
+        None => return empty![],
+    };
+    div![
+        attrs! { At::Class => "settings" },
+        h1!["Settings"],
+        name::view(&s.name, user).map_msg(M::Name),
+        password::view(&s.password).map_msg(M::Password),
+        sign_in::view(&s.sign_in).map_msg(M::SignIn),
+        post_defaults::view(&s.post_defaults, user).map_msg(M::PostDefaults),
+        capabilities::view(&s.capabilities).map_msg(M::Capabilities),
+        deletion::view(&s.deletion, user).map_msg(M::Deletion),
+    ]
+}
+
+/// Loads the capabilities and the sign-in methods of the account. The page is shown without the
+/// sign-in methods if only they fail to load.
+pub async fn load(user_id: uuid::Uuid) -> GlobalM {
+    match capabilities::load(user_id, 0).await {
+        Err(reason) => reason.notify("attempt.load_permissions"),
+        Ok(grants) => GlobalM::RenderPage(Location::Settings(S {
+            capabilities: capabilities::S {
+                grants: Some(grants),
+            },
+            sign_in: sign_in::S {
+                methods: sign_in::load(0).await,
+                ..sign_in::S::default()
+            },
+            ..S::default()
+        })),
+    }
+}
+
+pub fn access(gs: &GlobalS) -> Access {
+    if gs.user.is_some() {
+        Access::Allowed
+    } else {
+        Access::RequiresLogin
+    }
+}
+
+fn to_global(m: M) -> GlobalM {
+    GlobalM::Location(LocationM::Settings(m))
+}
+
+/// What the server made of a section's submission.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Outcome<T> {
+    Done(T),
+    /// The server named the fields at fault.
+    Invalid(ValidationErrors),
+    /// The server refused, and said why.
+    Refused(String),
+    Failed(FailReason),
+}
+impl<T> From<retry::Rejection> for Outcome<T> {
+    fn from(rejection: retry::Rejection) -> Self {
+        match rejection {
+            retry::Rejection::Invalid(errors) => Self::Invalid(errors),
+            retry::Rejection::Conflict(why) => Self::Refused(why),
+            retry::Rejection::Failed(reason) => Self::Failed(reason),
+        }
+    }
+}
+
+/// A labelled text field and the errors found with it.
+fn field<Ms: 'static>(
+    label: &str,
+    name: &str,
+    input_type: &str,
+    value: &str,
+    errors: &ValidationErrors,
+    on_input: impl FnOnce(String) -> Ms + Clone + 'static,
+) -> Node<Ms> {
+    div![
+        attrs! { At::Class => "settings-field" },
+        label![attrs! { At::For => name }, label],
+        input![
+            attrs! {
+                At::Class => "single-line-text-entry";
+                At::Type => input_type;
+                At::Name => name;
+                At::Id => name;
+                At::Value => value;
+            },
+            input_ev(Ev::Input, on_input),
+        ],
+        views::field_errors(errors, name),
+    ]
+}
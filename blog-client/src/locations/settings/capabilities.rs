@@ -0,0 +1,137 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::settings,
+    messages::M as GlobalM,
+    model::Store as GlobalS,
+    requests::api::{self, Page},
+    shared::{self, retry},
+};
+use db_models::models::capabilities;
+
+const LOAD_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "loading capabilities",
+    post_completion: "parsing loaded capabilities",
+};
+const REVOKE_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "revoking capability",
+    post_completion: "parsing revoked capability",
+};
+
+/// The page of the grants being viewed. Whether the viewer may revoke a grant is left to the
+/// server, since the capability allowing it may be on another page.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    pub grants: Option<Page<capabilities::Grant>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum M {
+    /// Asks for the page of the grants starting at the offset.
+    Page(i64),
+    Loaded(Page<capabilities::Grant>),
+    Revoke(uuid::Uuid),
+    Revoked,
+}
+
+/// Loads the page of the grants of the user starting `offset` grants in.
+pub async fn load(
+    user_id: uuid::Uuid,
+    offset: i64,
+) -> Result<Page<capabilities::Grant>, retry::FailReason> {
+    let req = api::call_page(&api::accounts::CAPABILITIES, &[&user_id], None, offset);
+    retry::fetch_json_with_retry(req, &LOAD_MSG, None).await
+}
+
+async fn load_page(user_id: uuid::Uuid, offset: i64) -> GlobalM {
+    match load(user_id, offset).await {
+        Err(reason) => reason.notify("attempt.load_permissions"),
+        Ok(grants) => settings::to_global(settings::M::Capabilities(M::Loaded(grants))),
+    }
+}
+
+async fn revoke(id: uuid::Uuid) -> GlobalM {
+    let req = api::call(&api::capabilities::DELETE, &[&id]);
+    let res = retry::fetch_text_with_retry(req, &REVOKE_MSG, retry::NO_RETRY).await;
+    match res {
+        Err(reason) => reason.notify("attempt.revoke_permission"),
+        Ok(_) => settings::to_global(settings::M::Capabilities(M::Revoked)),
+    }
+}
+
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    let user_id = match gs.user.as_ref() {
+        Some(user) => user.id,
+        None => return,
+    };
+    match m {
+        M::Page(offset) => {
+            orders.perform_cmd(load_page(user_id, offset));
+        }
+        M::Loaded(grants) => s.grants = Some(grants),
+        M::Revoke(id) => {
+            log::trace!("Revoking capability {:?}...", id);
+            orders.perform_cmd(revoke(id));
+        }
+        // Later grants shift onto the page in place of the revoked one.
+        M::Revoked => {
+            let offset = s.grants.as_ref().map_or(0, |grants| grants.offset);
+            orders.perform_cmd(load_page(user_id, offset));
+        }
+    }
+}
+
+fn render_grant(grant: &capabilities::Grant) -> Node<M> {
+    li![
+        attrs! { At::Class => "capability-item" },
+        p![
+            attrs! { At::Class => "capability-name" },
+            grant.capability.as_str(),
+        ],
+        p![
+            attrs! { At::Class => "capability-source" },
+            match &grant.source {
+                capabilities::GrantSource::Direct { .. } => "Granted directly".to_owned(),
+                capabilities::GrantSource::Role { role_name, .. } => {
+                    format!("Granted through the {} role", role_name)
+                }
+            },
+            format!(" on {}", grant.granted_at),
+        ],
+        match &grant.source {
+            capabilities::GrantSource::Direct { id } => {
+                let id = *id;
+                button![
+                    attrs! { At::Class => "inline-button" },
+                    "Revoke",
+                    ev(Ev::Click, move |e| {
+                        e.prevent_default();
+                        M::Revoke(id)
+                    }),
+                ]
+            }
+            _ => empty![],
+        },
+    ]
+}
+
+pub fn view(s: &S) -> Node<M> {
+    section![
+        attrs! { At::Class => "settings-section" },
+        h2![attrs! { At::Class => "as-h3" }, "Capabilities"],
+        match s.grants.as_ref() {
+            Some(grants) if grants.total == 0 => {
+                p![
+                    attrs! { At::Class => "no-capability-text" },
+                    "None granted."
+                ]
+            }
+            Some(grants) => div![
+                ul![grants.items.iter().map(render_grant)],
+                shared::views::pager(grants, M::Page),
+            ],
+            None => shared::views::loading(),
+        },
+    ]
+}
@@ -0,0 +1,137 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::{
+        login,
+        settings::{self, Outcome},
+    },
+    messages::M as GlobalM,
+    model::{Store as GlobalS, User},
+    requests::api,
+    shared::{notifications::Level, retry},
+};
+
+const DELETE_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "deleting account",
+    post_completion: "parsing deleted account",
+};
+
+/// Deleting the account, which has to be confirmed by typing its user name.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    pub typed: String,
+    pub deleting: bool,
+    /// Why the server refused to delete the account, such as it being the last administrator.
+    pub refusal: Option<String>,
+}
+impl S {
+    fn is_confirmed(&self, user: &User) -> bool {
+        self.typed == user.user_name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum M {
+    Type(String),
+    Delete,
+    Deleted(Outcome<()>),
+}
+
+fn to_global(m: M) -> GlobalM {
+    settings::to_global(settings::M::Deletion(m))
+}
+
+async fn delete(id: uuid::Uuid) -> GlobalM {
+    let req = api::call(&api::accounts::DELETE, &[&id]);
+    let outcome = match retry::fetch_validated(req, &DELETE_MSG).await {
+        Ok(_) => Outcome::Done(()),
+        Err(rejection) => rejection.into(),
+    };
+    to_global(M::Deleted(outcome))
+}
+
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    match m {
+        M::Type(typed) => {
+            s.refusal = None;
+            s.typed = typed;
+        }
+        M::Delete => match gs.user.as_ref() {
+            Some(user) if !s.deleting && s.is_confirmed(user) => {
+                s.deleting = true;
+                orders.perform_cmd(delete(user.id));
+            }
+            _ => {}
+        },
+        M::Deleted(outcome) => {
+            s.deleting = false;
+            match outcome {
+                Outcome::Done(()) => {
+                    // The session still points at the deleted account until logged out of.
+                    orders
+                        .send_msg(GlobalM::Notify(
+                            Level::Info,
+                            "Your account was deleted.".to_owned(),
+                        ))
+                        .perform_cmd(login::logout_trigger());
+                }
+                Outcome::Refused(why) => s.refusal = Some(why),
+                Outcome::Invalid(errors) => {
+                    log::error!("Deleting the account was refused as invalid: {:?}.", errors);
+                    orders.send_msg(retry::FailReason::Other.notify("attempt.delete_account"));
+                }
+                Outcome::Failed(reason) => {
+                    orders.send_msg(reason.notify("attempt.delete_account"));
+                }
+            }
+        }
+    }
+}
+
+pub fn view(s: &S, user: &User) -> Node<M> {
+    let can_delete = s.is_confirmed(user) && !s.deleting;
+    section![
+        attrs! { At::Class => "settings-section settings-danger" },
+        h2![attrs! { At::Class => "as-h3" }, "Delete account"],
+        p![
+            "This cannot be undone. Type your user name, ",
+            strong![user.user_name.as_str()],
+            ", to confirm.",
+        ],
+        form![
+            div![
+                attrs! { At::Class => "settings-field" },
+                input![
+                    attrs! {
+                        At::Class => "single-line-text-entry";
+                        At::Type => "text";
+                        At::Name => "confirm_user_name";
+                        At::AutoComplete => "off";
+                        At::Value => s.typed;
+                    },
+                    input_ev(Ev::Input, M::Type),
+                ],
+                s.refusal.as_ref().map_or_else(
+                    || empty![],
+                    |why| p![attrs! { At::Class => "field-errors" }, why.as_str()],
+                ),
+            ],
+            button![
+                attrs! {
+                    At::Type => "submit";
+                    At::Disabled => (!can_delete).as_at_value();
+                },
+                if s.deleting {
+                    "Deleting..."
+                } else {
+                    "Delete account"
+                },
+                ev(Ev::Click, |e| {
+                    e.prevent_default();
+                    M::Delete
+                }),
+            ],
+        ],
+    ]
+}
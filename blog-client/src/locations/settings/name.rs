@@ -0,0 +1,184 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::settings::{self, Outcome},
+    messages::M as GlobalM,
+    model::{Name, Store as GlobalS, StoreOperations as GSOp, User},
+    requests::api,
+    shared::{retry, views},
+};
+use db_models::models::users;
+use login_enum::ValidationErrors;
+
+const SAVE_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "saving name",
+    post_completion: "parsing account with saved name",
+};
+
+/// Edits to the name of the account. Fields left alone show the name as the store has it.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    pub first: Option<String>,
+    pub last: Option<String>,
+    pub errors: ValidationErrors,
+    /// The name from before the save being waited on, to go back to if the save fails.
+    pub saving: Option<Name>,
+}
+impl S {
+    /// The edits checked the same way the server checks them.
+    fn validate(&self) -> ValidationErrors {
+        let fields = [
+            ("first_name", &self.first, "Enter a first name."),
+            ("last_name", &self.last, "Enter a last name."),
+        ];
+        let mut errors = ValidationErrors::new();
+        for (field, value, message) in fields.iter() {
+            if value.as_deref().map_or(false, |v| v.trim().is_empty()) {
+                errors.add(field, "required", *message);
+            }
+        }
+        errors
+    }
+    fn changes(&self) -> users::ChangedNoMeta {
+        users::ChangedNoMeta {
+            user_name: None,
+            first_name: self.first.as_ref().map(|v| v.trim().to_owned()),
+            last_name: self.last.as_ref().map(|v| v.trim().to_owned()),
+            email: None,
+            preferences: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum M {
+    First(String),
+    Last(String),
+    Save,
+    Saved(Outcome<users::DataNoMeta>),
+}
+
+fn to_global(m: M) -> GlobalM {
+    settings::to_global(settings::M::Name(m))
+}
+
+async fn save(changes: users::ChangedNoMeta) -> GlobalM {
+    let req = api::call_with(&api::accounts::UPDATE_SELF, &[], &changes);
+    let req = match req {
+        Ok(req) => req,
+        Err(e) => {
+            log::error!("Failed to serialize the name: {:?}.", e);
+            return to_global(M::Saved(Outcome::Failed(retry::FailReason::Other)));
+        }
+    };
+    let outcome = match retry::fetch_validated(req, &SAVE_MSG).await {
+        Ok(res) => match res.json().await {
+            Ok(user) => Outcome::Done(user),
+            Err(e) => {
+                log::error!("Failed to parse the account with the saved name: {:?}.", e);
+                Outcome::Failed(retry::FailReason::Other)
+            }
+        },
+        Err(rejection) => rejection.into(),
+    };
+    to_global(M::Saved(outcome))
+}
+
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    match m {
+        M::First(first) => {
+            s.errors.clear("first_name");
+            s.first = Some(first);
+        }
+        M::Last(last) => {
+            s.errors.clear("last_name");
+            s.last = Some(last);
+        }
+        M::Save => {
+            let user = match gs.user.as_ref() {
+                Some(user) if s.saving.is_none() => user,
+                _ => return,
+            };
+            s.errors = s.validate();
+            if !s.errors.is_empty() {
+                return;
+            }
+            let changes = s.changes();
+            let mut renamed = user.name.clone();
+            if let Some(first) = changes.first_name.clone() {
+                renamed.first = first;
+            }
+            if let Some(last) = changes.last_name.clone() {
+                renamed.last = last;
+            }
+            // Shown straight away, and taken back if the server does not take it.
+            s.saving = Some(user.name.clone());
+            orders
+                .send_msg(GlobalM::StoreOp(GSOp::Rename(renamed)))
+                .perform_cmd(save(changes));
+        }
+        M::Saved(outcome) => {
+            let roll_back = s
+                .saving
+                .take()
+                .map(|previous| GlobalM::StoreOp(GSOp::Rename(previous)));
+            let failure = match outcome {
+                Outcome::Done(user) => {
+                    s.first = None;
+                    s.last = None;
+                    orders.send_msg(GlobalM::StoreOp(GSOp::User(user)));
+                    return;
+                }
+                Outcome::Invalid(errors) => {
+                    s.errors = errors;
+                    None
+                }
+                Outcome::Refused(why) => {
+                    log::error!("Saving the name was refused: {}.", why);
+                    Some(retry::FailReason::Other.notify("attempt.save_name"))
+                }
+                Outcome::Failed(reason) => Some(reason.notify("attempt.save_name")),
+            };
+            roll_back.into_iter().chain(failure).for_each(|m| {
+                orders.send_msg(m);
+            });
+        }
+    }
+}
+
+pub fn view(s: &S, user: &User) -> Node<M> {
+    let first = s.first.as_deref().unwrap_or(&user.name.first);
+    let last = s.last.as_deref().unwrap_or(&user.name.last);
+    section![
+        attrs! { At::Class => "settings-section" },
+        h2![attrs! { At::Class => "as-h3" }, "Name"],
+        form![
+            settings::field(
+                "First name",
+                "first_name",
+                "text",
+                first,
+                &s.errors,
+                M::First
+            ),
+            settings::field("Last name", "last_name", "text", last, &s.errors, M::Last),
+            views::form_errors(&s.errors, &["first_name", "last_name"]),
+            button![
+                attrs! {
+                    At::Type => "submit";
+                    At::Disabled => s.saving.is_some().as_at_value();
+                },
+                if s.saving.is_some() {
+                    "Saving..."
+                } else {
+                    "Save name"
+                },
+                ev(Ev::Click, |e| {
+                    e.prevent_default();
+                    M::Save
+                }),
+            ],
+        ],
+    ]
+}
@@ -0,0 +1,182 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::settings::{self, Outcome},
+    messages::M as GlobalM,
+    model::Store as GlobalS,
+    requests::api,
+    shared::{notifications::Level, retry, views},
+};
+use login_enum::{ChangePassword, ValidationErrors};
+
+const FIND_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "finding password",
+    post_completion: "parsing found password",
+};
+const CHANGE_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "changing password",
+    post_completion: "parsing changed password",
+};
+
+/// A change of password, which only takes effect once the server confirms it.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    // Passwords are never written anywhere the location might be kept.
+    #[serde(skip)]
+    pub current: String,
+    #[serde(skip)]
+    pub new: String,
+    #[serde(skip)]
+    pub confirm: String,
+    pub errors: ValidationErrors,
+    pub saving: bool,
+}
+impl S {
+    /// Checks what the server checks, and that the new password was typed the same way twice.
+    fn validate(&self) -> ValidationErrors {
+        let mut errors = ValidationErrors::new();
+        if self.current.is_empty() {
+            errors.add("current", "required", "Enter your current password.");
+        }
+        if self.new.is_empty() {
+            errors.add("new", "required", "Enter a new password.");
+        }
+        if self.confirm != self.new {
+            errors.add("confirm", "mismatch", "The passwords do not match.");
+        }
+        errors
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum M {
+    Current(String),
+    New(String),
+    Confirm(String),
+    Save,
+    Saved(Outcome<()>),
+}
+
+fn to_global(m: M) -> GlobalM {
+    settings::to_global(settings::M::Password(m))
+}
+
+async fn change(change: ChangePassword) -> Outcome<()> {
+    let id: uuid::Uuid =
+        match retry::fetch_validated(api::call(&api::credentials::OWN_PASSWORD, &[]), &FIND_MSG).await {
+            Ok(res) => match res.json().await {
+                Ok(id) => id,
+                Err(e) => {
+                    log::error!("Failed to parse the id of the password: {:?}.", e);
+                    return Outcome::Failed(retry::FailReason::Other);
+                }
+            },
+            Err(rejection) => return rejection.into(),
+        };
+    let req = match api::call_with(&api::credentials::CHANGE_PASSWORD, &[&id], &change) {
+        Ok(req) => req,
+        Err(e) => {
+            log::error!("Failed to serialize the password change: {:?}.", e);
+            return Outcome::Failed(retry::FailReason::Other);
+        }
+    };
+    match retry::fetch_validated(req, &CHANGE_MSG).await {
+        Ok(_) => Outcome::Done(()),
+        Err(rejection) => rejection.into(),
+    }
+}
+
+pub fn update(m: M, s: &mut S, _gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    match m {
+        M::Current(current) => {
+            s.errors.clear("current");
+            s.current = current;
+        }
+        M::New(new) => {
+            s.errors.clear("new");
+            s.new = new;
+        }
+        M::Confirm(confirm) => {
+            s.errors.clear("confirm");
+            s.confirm = confirm;
+        }
+        M::Save => {
+            if s.saving {
+                return;
+            }
+            s.errors = s.validate();
+            if !s.errors.is_empty() {
+                return;
+            }
+            s.saving = true;
+            let req = ChangePassword {
+                current: Some(s.current.clone()),
+                new: s.new.clone(),
+            };
+            orders.perform_cmd(async { to_global(M::Saved(change(req).await)) });
+        }
+        M::Saved(outcome) => {
+            s.saving = false;
+            match outcome {
+                Outcome::Done(()) => {
+                    *s = S::default();
+                    orders.send_msg(GlobalM::Notify(
+                        Level::Info,
+                        "Your password was changed.".to_owned(),
+                    ));
+                }
+                Outcome::Invalid(errors) => s.errors = errors,
+                Outcome::Refused(why) => {
+                    log::error!("Changing the password was refused: {}.", why);
+                    orders.send_msg(retry::FailReason::Other.notify("attempt.change_password"));
+                }
+                Outcome::Failed(reason) => {
+                    orders.send_msg(reason.notify("attempt.change_password"));
+                }
+            }
+        }
+    }
+}
+
+pub fn view(s: &S) -> Node<M> {
+    section![
+        attrs! { At::Class => "settings-section" },
+        h2![attrs! { At::Class => "as-h3" }, "Password"],
+        form![
+            settings::field(
+                "Current password",
+                "current",
+                "password",
+                &s.current,
+                &s.errors,
+                M::Current
+            ),
+            settings::field("New password", "new", "password", &s.new, &s.errors, M::New),
+            settings::field(
+                "Confirm new password",
+                "confirm",
+                "password",
+                &s.confirm,
+                &s.errors,
+                M::Confirm
+            ),
+            views::form_errors(&s.errors, &["current", "new", "confirm"]),
+            button![
+                attrs! {
+                    At::Type => "submit";
+                    At::Disabled => s.saving.as_at_value();
+                },
+                if s.saving {
+                    "Changing..."
+                } else {
+                    "Change password"
+                },
+                ev(Ev::Click, |e| {
+                    e.prevent_default();
+                    M::Save
+                }),
+            ],
+        ],
+    ]
+}
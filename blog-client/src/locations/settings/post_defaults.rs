@@ -0,0 +1,229 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::settings::{self, Outcome},
+    messages::M as GlobalM,
+    model::{Store as GlobalS, StoreOperations as GSOp, User},
+    requests::api,
+    shared::{retry, views},
+};
+use db_models::models::users;
+use login_enum::ValidationErrors;
+
+const SAVE_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "saving post defaults",
+    post_completion: "parsing account with saved post defaults",
+};
+
+/// Edits to what new posts start with. Fields left alone show the defaults as the store has them.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    /// The names of the default tags, separated by commas.
+    pub tags: Option<String>,
+    pub slug_prefix: Option<String>,
+    pub editor_mode: Option<users::EditorMode>,
+    pub errors: ValidationErrors,
+    pub saving: bool,
+}
+impl S {
+    /// The defaults as edited, starting from `saved`.
+    fn defaults(&self, saved: &users::PostDefaults) -> users::PostDefaults {
+        users::PostDefaults {
+            tags: match &self.tags {
+                Some(tags) => split_tags(tags),
+                None => saved.tags.clone(),
+            },
+            slug_prefix: match &self.slug_prefix {
+                Some(prefix) => prefix.trim().to_owned(),
+                None => saved.slug_prefix.clone(),
+            },
+            editor_mode: self.editor_mode.unwrap_or(saved.editor_mode),
+        }
+    }
+}
+
+/// The tag names in `tags`, trimmed, without the blank ones.
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum M {
+    Tags(String),
+    SlugPrefix(String),
+    EditorMode(users::EditorMode),
+    Save,
+    Saved(Outcome<users::DataNoMeta>),
+}
+
+fn to_global(m: M) -> GlobalM {
+    settings::to_global(settings::M::PostDefaults(m))
+}
+
+async fn save(changes: users::ChangedNoMeta) -> GlobalM {
+    let req = api::call_with(&api::accounts::UPDATE_SELF, &[], &changes);
+    let req = match req {
+        Ok(req) => req,
+        Err(e) => {
+            log::error!("Failed to serialize the post defaults: {:?}.", e);
+            return to_global(M::Saved(Outcome::Failed(retry::FailReason::Other)));
+        }
+    };
+    let outcome = match retry::fetch_validated(req, &SAVE_MSG).await {
+        Ok(res) => match res.json().await {
+            Ok(user) => Outcome::Done(user),
+            Err(e) => {
+                log::error!("Failed to parse the account with the saved post defaults: {:?}.", e);
+                Outcome::Failed(retry::FailReason::Other)
+            }
+        },
+        Err(rejection) => rejection.into(),
+    };
+    to_global(M::Saved(outcome))
+}
+
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    match m {
+        M::Tags(tags) => {
+            s.errors.clear("tags");
+            s.tags = Some(tags);
+        }
+        M::SlugPrefix(prefix) => {
+            s.errors.clear("slug_prefix");
+            s.slug_prefix = Some(prefix);
+        }
+        M::EditorMode(mode) => s.editor_mode = Some(mode),
+        M::Save => {
+            let user = match gs.user.as_ref() {
+                Some(user) if !s.saving => user,
+                _ => return,
+            };
+            // Setting preferences replaces all of them, so the others are sent along as they are.
+            let preferences = users::Preferences {
+                post_defaults: s.defaults(&user.preferences.post_defaults),
+                ..user.preferences.clone()
+            };
+            let changes = users::ChangedNoMeta {
+                user_name: None,
+                first_name: None,
+                last_name: None,
+                email: None,
+                preferences: Some(preferences),
+            };
+            s.saving = true;
+            orders.perform_cmd(save(changes));
+        }
+        M::Saved(outcome) => {
+            s.saving = false;
+            match outcome {
+                Outcome::Done(user) => {
+                    *s = S::default();
+                    orders.send_msg(GlobalM::StoreOp(GSOp::User(user)));
+                }
+                Outcome::Invalid(errors) => s.errors = errors,
+                Outcome::Refused(why) => {
+                    log::error!("Saving the post defaults was refused: {}.", why);
+                    orders.send_msg(retry::FailReason::Other.notify("attempt.save_post_defaults"));
+                }
+                Outcome::Failed(reason) => {
+                    orders.send_msg(reason.notify("attempt.save_post_defaults"));
+                }
+            }
+        }
+    }
+}
+
+pub fn view(s: &S, user: &User) -> Node<M> {
+    let saved = &user.preferences.post_defaults;
+    let tags = s.tags.clone().unwrap_or_else(|| saved.tags.join(", "));
+    let prefix = s.slug_prefix.as_deref().unwrap_or(&saved.slug_prefix);
+    let mode = s.editor_mode.unwrap_or(saved.editor_mode);
+    let modes = [
+        (users::EditorMode::Edit, "edit", "Only the body"),
+        (users::EditorMode::Preview, "preview", "The body and its preview"),
+    ];
+    section![
+        attrs! { At::Class => "settings-section" },
+        h2![attrs! { At::Class => "as-h3" }, "New posts"],
+        form![
+            settings::field(
+                "Tags, separated by commas",
+                "tags",
+                "text",
+                &tags,
+                &s.errors,
+                M::Tags
+            ),
+            settings::field(
+                "Start slugs with",
+                "slug_prefix",
+                "text",
+                prefix,
+                &s.errors,
+                M::SlugPrefix
+            ),
+            div![
+                attrs! { At::Class => "settings-field" },
+                label![attrs! { At::For => "editor_mode" }, "Open the editor with"],
+                select![
+                    attrs! { At::Name => "editor_mode"; At::Id => "editor_mode" },
+                    modes.iter().map(|(option, value, text)| {
+                        option![
+                            attrs! {
+                                At::Value => *value;
+                                At::Selected => (*option == mode).as_at_value();
+                            },
+                            *text,
+                        ]
+                    }),
+                    input_ev(Ev::Change, move |value| {
+                        modes
+                            .iter()
+                            .find(|(_, name, _)| *name == value)
+                            .map(|(mode, ..)| M::EditorMode(*mode))
+                    }),
+                ],
+            ],
+            views::form_errors(&s.errors, &["tags", "slug_prefix"]),
+            button![
+                attrs! {
+                    At::Type => "submit";
+                    At::Disabled => s.saving.as_at_value();
+                },
+                if s.saving { "Saving..." } else { "Save defaults" },
+                ev(Ev::Click, |e| {
+                    e.prevent_default();
+                    M::Save
+                }),
+            ],
+        ],
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fields_left_alone_keep_the_saved_defaults() {
+        let saved = users::PostDefaults {
+            tags: vec!["rust".to_owned()],
+            slug_prefix: "notes-".to_owned(),
+            editor_mode: users::EditorMode::Preview,
+        };
+        assert_eq!(S::default().defaults(&saved), saved);
+        let edited = S {
+            tags: Some(" web, ,rust ,".to_owned()),
+            ..S::default()
+        };
+        assert_eq!(
+            edited.defaults(&saved).tags,
+            vec!["web".to_owned(), "rust".to_owned()]
+        );
+    }
+}
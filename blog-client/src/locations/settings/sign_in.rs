@@ -0,0 +1,315 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::settings::{self, Outcome},
+    messages::M as GlobalM,
+    model::Store as GlobalS,
+    requests::api::{self, Page},
+    shared::{loadable::Loadable, notifications::Level, retry, views},
+};
+use db_models::models::credentials::{self, Kind};
+use login_enum::{CreatePassword, ValidationErrors};
+
+const LOAD_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "loading sign-in methods",
+    post_completion: "parsing loaded sign-in methods",
+};
+const ADD_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "adding password",
+    post_completion: "parsing added password",
+};
+const REMOVE_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "removing password",
+    post_completion: "parsing removed password",
+};
+
+/// The ways the account can sign in, a page at a time, each removable through the endpoints of
+/// its kind as long as another remains.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct S {
+    pub methods: Loadable<Page<credentials::Summary>>,
+    /// The credential waiting on the server to remove it, if any.
+    pub removing: Option<uuid::Uuid>,
+    /// Why the server refused to remove a credential, such as it being the last one.
+    pub refusal: Option<String>,
+    // Passwords are never written anywhere the location might be kept.
+    #[serde(skip)]
+    pub new: String,
+    #[serde(skip)]
+    pub confirm: String,
+    pub errors: ValidationErrors,
+    pub adding: bool,
+}
+impl S {
+    /// Whether the account has a password, assuming it does if one may be on another page.
+    fn has_password(&self) -> bool {
+        match &self.methods {
+            Loadable::Loaded(methods) => {
+                methods.total > methods.items.len() as i64
+                    || methods.items.iter().any(|m| m.kind == Kind::Password)
+            }
+            _ => true,
+        }
+    }
+    /// Whether removing a credential would leave another to sign in with. The server checks
+    /// again, since the list may be out of date.
+    fn can_remove(&self) -> bool {
+        match &self.methods {
+            Loadable::Loaded(methods) => methods.total > 1 && self.removing.is_none(),
+            _ => false,
+        }
+    }
+    fn offset(&self) -> i64 {
+        match &self.methods {
+            Loadable::Loaded(methods) => methods.offset,
+            _ => 0,
+        }
+    }
+    fn validate(&self) -> ValidationErrors {
+        let mut errors = ValidationErrors::new();
+        if self.new.is_empty() {
+            errors.add("new_password", "required", "Enter a password.");
+        }
+        if self.confirm != self.new {
+            errors.add("confirm_password", "mismatch", "The passwords do not match.");
+        }
+        errors
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum M {
+    /// Asks for the page of the ways to sign in starting at the offset.
+    Page(i64),
+    Loaded(Loadable<Page<credentials::Summary>>),
+    Remove(uuid::Uuid),
+    Removed(Outcome<()>),
+    New(String),
+    Confirm(String),
+    Add,
+    Added(Outcome<()>),
+}
+
+fn to_global(m: M) -> GlobalM {
+    settings::to_global(settings::M::SignIn(m))
+}
+
+/// Loads the page of the ways the account logged in as can sign in starting `offset` ways in.
+pub async fn load(offset: i64) -> Loadable<Page<credentials::Summary>> {
+    let req = api::call_page(&api::accounts::OWN_CREDENTIALS, &[], None, offset);
+    match retry::fetch_json_with_retry(req, &LOAD_MSG, None).await {
+        Ok(methods) => Loadable::Loaded(methods),
+        Err(reason) => Loadable::Failed(reason),
+    }
+}
+
+async fn remove(id: uuid::Uuid) -> GlobalM {
+    let req = api::call(&api::credentials::DELETE_PASSWORD, &[&id]);
+    let outcome = match retry::fetch_validated(req, &REMOVE_MSG).await {
+        Ok(_) => Outcome::Done(()),
+        Err(rejection) => rejection.into(),
+    };
+    to_global(M::Removed(outcome))
+}
+
+async fn add(password: CreatePassword) -> GlobalM {
+    let req = match api::call_with(&api::credentials::CREATE_PASSWORD, &[], &password) {
+        Ok(req) => req,
+        Err(e) => {
+            log::error!("Failed to serialize the new password: {:?}.", e);
+            return to_global(M::Added(Outcome::Failed(retry::FailReason::Other)));
+        }
+    };
+    let outcome = match retry::fetch_validated(req, &ADD_MSG).await {
+        Ok(_) => Outcome::Done(()),
+        Err(rejection) => rejection.into(),
+    };
+    to_global(M::Added(outcome))
+}
+
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
+    match m {
+        M::Page(offset) => {
+            orders.perform_cmd(async move { to_global(M::Loaded(load(offset).await)) });
+        }
+        M::Loaded(methods) => s.methods = methods,
+        M::Remove(id) => {
+            if s.can_remove() {
+                s.refusal = None;
+                s.removing = Some(id);
+                orders.perform_cmd(remove(id));
+            }
+        }
+        M::Removed(outcome) => {
+            s.removing = None;
+            match outcome {
+                // Later ways to sign in shift onto the page in place of the removed one.
+                Outcome::Done(()) => {
+                    let offset = s.offset();
+                    orders.perform_cmd(async move { to_global(M::Loaded(load(offset).await)) });
+                }
+                Outcome::Refused(why) => s.refusal = Some(why),
+                Outcome::Invalid(errors) => {
+                    log::error!("Removing the password was refused as invalid: {:?}.", errors);
+                    orders.send_msg(retry::FailReason::Other.notify("attempt.remove_sign_in"));
+                }
+                Outcome::Failed(reason) => {
+                    orders.send_msg(reason.notify("attempt.remove_sign_in"));
+                }
+            }
+        }
+        M::New(new) => {
+            s.errors.clear("new_password");
+            s.new = new;
+        }
+        M::Confirm(confirm) => {
+            s.errors.clear("confirm_password");
+            s.confirm = confirm;
+        }
+        M::Add => match gs.user.as_ref() {
+            Some(user) if !s.adding => {
+                s.errors = s.validate();
+                if !s.errors.is_empty() {
+                    return;
+                }
+                s.adding = true;
+                orders.perform_cmd(add(CreatePassword {
+                    user_id: user.id,
+                    password: s.new.clone(),
+                }));
+            }
+            _ => {}
+        },
+        M::Added(outcome) => {
+            s.adding = false;
+            match outcome {
+                Outcome::Done(()) => {
+                    s.new.clear();
+                    s.confirm.clear();
+                    orders
+                        .send_msg(GlobalM::Notify(
+                            Level::Info,
+                            "A password was added to your account.".to_owned(),
+                        ))
+                        .perform_cmd(async { to_global(M::Loaded(load(0).await)) });
+                }
+                Outcome::Invalid(errors) => s.errors = errors,
+                Outcome::Refused(why) => {
+                    log::error!("Adding a password was refused: {}.", why);
+                    orders.send_msg(retry::FailReason::Other.notify("attempt.add_sign_in"));
+                }
+                Outcome::Failed(reason) => {
+                    orders.send_msg(reason.notify("attempt.add_sign_in"));
+                }
+            }
+        }
+    }
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Password => "Password",
+        Kind::Google => "Google",
+    }
+}
+
+fn render_method(method: &credentials::Summary, s: &S) -> Node<M> {
+    let id = method.id;
+    li![
+        attrs! { At::Class => "sign-in-item" },
+        p![
+            attrs! { At::Class => "sign-in-name" },
+            kind_name(method.kind),
+            method
+                .label
+                .as_ref()
+                .map_or_else(String::new, |label| format!(" ({})", label)),
+        ],
+        p![
+            attrs! { At::Class => "sign-in-dates" },
+            format!("Added on {}", method.created_at.format("%Y-%m-%d")),
+            match method.last_used_at {
+                Some(used) => format!(", last used on {}", used.format("%Y-%m-%d")),
+                None => ", last use unknown".to_owned(),
+            },
+        ],
+        // Only passwords can be removed here for now, since they are the only kind with
+        // endpoints to manage them.
+        if method.kind == Kind::Password {
+            button![
+                attrs! {
+                    At::Class => "inline-button";
+                    At::Type => "button";
+                    At::Disabled => (!s.can_remove()).as_at_value();
+                },
+                if s.removing == Some(id) {
+                    "Removing..."
+                } else {
+                    "Remove"
+                },
+                ev(Ev::Click, move |e| {
+                    e.prevent_default();
+                    M::Remove(id)
+                }),
+            ]
+        } else {
+            empty![]
+        },
+    ]
+}
+
+fn render_add_password(s: &S) -> Node<M> {
+    form![
+        attrs! { At::Class => "sign-in-add" },
+        settings::field("Password", "new_password", "password", &s.new, &s.errors, M::New),
+        settings::field(
+            "Confirm password",
+            "confirm_password",
+            "password",
+            &s.confirm,
+            &s.errors,
+            M::Confirm
+        ),
+        views::form_errors(&s.errors, &["new_password", "confirm_password"]),
+        button![
+            attrs! {
+                At::Type => "submit";
+                At::Disabled => s.adding.as_at_value();
+            },
+            if s.adding {
+                "Adding..."
+            } else {
+                "Add a password"
+            },
+            ev(Ev::Click, |e| {
+                e.prevent_default();
+                M::Add
+            }),
+        ],
+    ]
+}
+
+pub fn view(s: &S) -> Node<M> {
+    section![
+        attrs! { At::Class => "settings-section" },
+        h2![attrs! { At::Class => "as-h3" }, "Sign-in methods"],
+        match &s.methods {
+            Loadable::Loaded(methods) => div![
+                ul![methods.items.iter().map(|m| render_method(m, s))],
+                views::pager(methods, M::Page),
+            ],
+            Loadable::Failed(_) => p!["Your sign-in methods could not be loaded."],
+            Loadable::NotAsked | Loadable::Loading => views::loading(),
+        },
+        s.refusal.as_ref().map_or_else(
+            || empty![],
+            |why| p![attrs! { At::Class => "field-errors" }, why.as_str()],
+        ),
+        if s.has_password() {
+            empty![]
+        } else {
+            render_add_password(s)
+        },
+    ]
+}
@@ -1,39 +1,41 @@
 use tap::*;
 
 use crate::{
-    locations::{Location},
+    locations::{Location, M as LocationM},
     messages::{M as GlobalM, StoreCallback},
     model::{PostMarker, Store as GlobalS, StoreOperations as GSOp},
-    shared::retry,
+    requests::{self, api},
+    shared::{loadable::Loadable, retry},
 };
+use db_models::models::{reactions, tags};
 
 mod messages;
 mod state;
 mod views;
 pub use messages::{M, update};
 pub use state::S;
-pub use views::render;
-
-const POST_LOAD_MSGS: retry::LogPair<'static> = retry::LogPair {
-    pre_completion: "loading post",
-    post_completion: "parsing loaded post",
-};
+pub use views::{render, render_skeleton};
 
 pub async fn load_post(post_marker: PostMarker) -> GlobalM {
-    const POSTS_URL: &str = "/api/posts";
-    let url = format!("{}/{}", POSTS_URL, post_marker);
-    let fo = retry::fetch_json_with_retry(
-        url.into(),
-        &POST_LOAD_MSGS,
-        None,
-    ).await;
+    let url = api::posts::GET.url(&[&post_marker]);
+    let fo = requests::get(requests::Get::new(url)).await.and_then(|res| res.json());
     match fo {
-        Err(_) => GlobalM::NoOp,
+        Err(reason) => GlobalM::RenderPage(Location::Viewer(S {
+            post_marker,
+            post: Loadable::Failed(reason),
+            tags: vec![],
+            confirming: None,
+            reacting: vec![],
+        })),
         Ok(obj) => GlobalM::StoreOpWithAction(GSOp::Post(post_marker, obj), StoreCallback::new(|gs| {
             gs.post
                 .as_ref()
                 .map(|post| GlobalM::RenderPage(Location::Viewer(S {
                     post_marker: PostMarker::Uuid(post.id),
+                    post: Loadable::Loaded(post.clone()),
+                    tags: vec![],
+                    confirming: None,
+                    reacting: vec![],
                 })))
                 .tap_none(|| log::error!("Post loaded but was not saved to store."))
                 .unwrap_or(GlobalM::NoOp)
@@ -41,15 +43,60 @@ pub async fn load_post(post_marker: PostMarker) -> GlobalM {
     }
 }
 
-pub fn is_restricted_from(gs: &GlobalS) -> bool {
-    if let GlobalS {
-        post: Some(post),
-        user,
-        ..
-    } = gs
-    {
+/// Loads the tags of the post, to link to the other posts tagged the same. The post reads fine
+/// without them, so failures are only logged.
+pub async fn load_tags(post_id: uuid::Uuid) -> GlobalM {
+    let url = api::posts::TAGS.url(&[&post_id]);
+    let res: Result<Vec<tags::Data>, _> = requests::get(requests::Get::new(url)).await.and_then(|res| res.json());
+    match res {
+        Err(reason) => {
+            log::warn!("Failed to load the tags of the post: {:?}.", reason);
+            GlobalM::NoOp
+        }
+        Ok(loaded) => GlobalM::Location(LocationM::Viewer(M::TagsLoaded(
+            loaded.into_iter().map(|tag| tag.name).collect(),
+        ))),
+    }
+}
+
+/// Leaves a reaction of `kind` on the post if `added`, or takes it back otherwise. The server
+/// answers a reaction it already had with the counts all the same, which settles it just as well.
+pub async fn react(post_id: uuid::Uuid, kind: reactions::Kind, added: bool) -> GlobalM {
+    const REACT_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "reacting to post",
+        post_completion: "reading reactions to post",
+    };
+    let failed = |reason: retry::FailReason| GlobalM::Grouped(vec![
+        reason.notify("attempt.react"),
+        GlobalM::Location(LocationM::Viewer(M::ReactionFailed(kind, added))),
+    ]);
+    let reaction = reactions::Reaction { kind };
+    let endpoint = if added { &api::posts::REACT } else { &api::posts::RETRACT };
+    let req = match api::call_with(endpoint, &[&post_id], &reaction) {
+        Ok(req) => req,
+        Err(e) => {
+            log::error!("Failed to serialize the reaction: {:?}.", e);
+            return failed(retry::FailReason::Other);
+        }
+    };
+    let counts = match retry::fetch_explained(req, &REACT_MSG, &[retry::CONFLICT_CODE]).await {
+        Ok(res) => match res.json().await {
+            Ok(counts) => counts,
+            Err(e) => {
+                log::error!("Failed to parse the reactions to the post: {:?}.", e);
+                return failed(retry::FailReason::Other);
+            }
+        },
+        Err(retry::Refusal::Explained(counts)) => counts,
+        Err(retry::Refusal::Failed(reason)) => return failed(reason),
+    };
+    GlobalM::Location(LocationM::Viewer(M::Reacted(kind, counts)))
+}
+
+pub fn is_restricted_from(s: &S, gs: &GlobalS) -> bool {
+    if let Loadable::Loaded(post) = &s.post {
         !post.is_published()
-            && user
+            && gs.user
                 .as_ref()
                 .map(|u| !u.can_see_unpublished)
                 .unwrap_or(true)
@@ -1,17 +1,105 @@
 use seed::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use db_models::reactions;
+
 use crate::{
-    locations::viewer::S,
+    locations::{viewer::{self, S}, M as LocationM},
     messages::M as GlobalM,
-    model::Store as GlobalS,
+    model::{ListingInvalidation, Store as GlobalS, StoreOperations as GSOp},
+    shared::{
+        loadable::Loadable,
+        post_actions::{self, Action},
+        views,
+    },
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub enum M {}
+pub enum M {
+    LoadTags,
+    TagsLoaded(Vec<String>),
+    /// Asks the user to confirm archiving or deleting the post.
+    AskTo(Action),
+    /// The user confirmed the action they were asked about.
+    Confirm,
+    /// The user closed the confirmation without confirming.
+    DismissModal,
+    /// Shows the post as the store has it, such as once it is archived.
+    SyncPost,
+    /// Reacts to the post with the kind of reaction, or takes it back if the reader already had.
+    React(reactions::Kind),
+    /// The server settled the reaction, answering with the counts of each kind.
+    Reacted(reactions::Kind, reactions::Counts),
+    /// The reaction, added if true, did not reach the server, and is undone.
+    ReactionFailed(reactions::Kind, bool),
+}
 
-pub fn update(m: M, _s: &mut S, _gs: &GlobalS, _orders: &mut impl Orders<GlobalM, GlobalM>) {
+pub fn update(m: M, s: &mut S, gs: &GlobalS, orders: &mut impl Orders<GlobalM, GlobalM>) {
     match m {
-        // M:: => {}
+        M::LoadTags => {
+            if let Loadable::Loaded(post) = &s.post {
+                orders.perform_cmd(viewer::load_tags(post.id));
+            }
+        }
+        M::TagsLoaded(tags) => s.tags = tags,
+        M::AskTo(action) => {
+            if let Loadable::Loaded(post) = &s.post {
+                if Action::offered(gs.user.as_ref(), post).contains(&action) {
+                    s.confirming = Some(action);
+                    orders.after_next_render(|_| {
+                        views::focus_modal();
+                        GlobalM::NoOp
+                    });
+                }
+            }
+        }
+        M::Confirm => match (s.confirming.take(), &s.post, gs.user.as_ref()) {
+            (Some(Action::Archive), Loadable::Loaded(post), Some(user)) => {
+                let then = GlobalM::Location(LocationM::Viewer(M::SyncPost));
+                orders.perform_cmd(post_actions::archive(post.clone(), user.id, then));
+            }
+            (Some(Action::Delete), Loadable::Loaded(post), Some(_)) => {
+                orders.perform_cmd(post_actions::delete(post.id, GlobalM::NoOp));
+            }
+            (confirming, _, _) => log::debug!("Ignoring confirmation of {:?}.", confirming),
+        },
+        M::DismissModal => s.confirming = None,
+        M::SyncPost => {
+            if let (Loadable::Loaded(post), Some(updated)) = (&mut s.post, &gs.post) {
+                if post.id == updated.id {
+                    *post = updated.clone();
+                }
+            }
+        }
+        M::React(kind) => {
+            if let Loadable::Loaded(post) = &mut s.post {
+                if s.reacting.contains(&kind) {
+                    return;
+                }
+                let added = !gs.has_reacted(post.id, kind);
+                post.reactions.adjust(kind, added);
+                s.reacting.push(kind);
+                orders
+                    .send_msg(GlobalM::StoreOp(GSOp::Reacted(post.id, kind, added)))
+                    .perform_cmd(viewer::react(post.id, kind, added));
+            }
+        }
+        M::Reacted(kind, counts) => {
+            s.reacting.retain(|reacting| *reacting != kind);
+            if let Loadable::Loaded(post) = &mut s.post {
+                post.reactions = counts;
+                orders.send_msg(GlobalM::Grouped(vec![
+                    GlobalM::StoreOp(GSOp::InvalidateListings(ListingInvalidation::Post(post.id))),
+                    GlobalM::StoreOp(GSOp::PostRaw(post.clone())),
+                ]));
+            }
+        }
+        M::ReactionFailed(kind, added) => {
+            s.reacting.retain(|reacting| *reacting != kind);
+            if let Loadable::Loaded(post) = &mut s.post {
+                post.reactions.adjust(kind, !added);
+                orders.send_msg(GlobalM::StoreOp(GSOp::Reacted(post.id, kind, !added)));
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,15 +1,32 @@
 use seed::browser::url::Url;
 use serde::{Deserialize, Serialize};
 
-use crate::model::PostMarker;
+use crate::{
+    model::PostMarker,
+    shared::{loadable::Loadable, post_actions::Action},
+};
+use db_models::{posts, reactions};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct S {
     pub post_marker: PostMarker,
+    pub post: Loadable<posts::DataNoMeta>,
+    /// The names of the tags of the post, once they load.
+    pub tags: Vec<String>,
+    /// The action on the post the user is asked to confirm, if any.
+    pub confirming: Option<Action>,
+    /// The kinds of reaction sent to the server and not answered yet.
+    pub reacting: Vec<reactions::Kind>,
 }
 impl From<PostMarker> for S {
     fn from(s: PostMarker) -> Self {
-        Self { post_marker: s }
+        Self {
+            post_marker: s,
+            post: Loadable::NotAsked,
+            tags: vec![],
+            confirming: None,
+            reacting: vec![],
+        }
     }
 }
 impl S {
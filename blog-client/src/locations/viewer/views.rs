@@ -1,22 +1,257 @@
 use seed::prelude::*;
 
 use crate::{
-    locations::viewer::{M, S},
+    locations::{
+        listing, series,
+        viewer::{M, S},
+    },
     model::Store as GlobalS,
-    shared,
+    requests::{api, PostFilter},
+    shared::{post_actions::Action, views::skeleton},
 };
-use db_models::posts;
+use db_models::{
+    post_authors, posts, reactions,
+    series::{Membership, Part},
+};
+use htmlgen::markdown::render_markdown;
+
+/// How many paragraphs of placeholder text stand in for the post while it loads.
+const SKELETON_PARAGRAPHS: usize = 3;
+/// How many words a post needs before it gets a table of contents.
+const TOC_MIN_WORDS: usize = 800;
+/// How many headings a post needs before it gets a table of contents.
+const TOC_MIN_HEADINGS: usize = 3;
+
+/// The names of the users, each linking to more of their posts, separated by commas.
+fn names(authors: &[&post_authors::Author]) -> Vec<Node<M>> {
+    let mut nodes = vec![];
+    for (i, author) in authors.iter().enumerate() {
+        if i != 0 {
+            nodes.push(Node::new_text(", "));
+        }
+        let filter = PostFilter::Author(author.user.id);
+        nodes.push(a![
+            attrs! { At::Href => listing::S::filtered_url(&filter) },
+            author.user.display_name(),
+        ]);
+    }
+    nodes
+}
+/// Names everyone credited on the post. Posts nobody is credited on fall back to linking the posts
+/// of their creator.
+fn byline(post: &posts::DataNoMeta) -> Node<M> {
+    if post.authors.is_empty() {
+        return p![
+            attrs! { At::Class => "post-byline" },
+            a![
+                attrs! { At::Href => listing::S::filtered_url(&PostFilter::Author(post.created_by)) },
+                tr!("viewer.more_by_author"),
+            ],
+        ];
+    }
+    let (writers, editors): (Vec<_>, Vec<_>) = post
+        .authors
+        .iter()
+        .partition(|author| author.role == post_authors::Role::Author);
+    p![
+        attrs! { At::Class => "post-byline" },
+        tr!("viewer.by"),
+        " ",
+        names(&writers),
+        if editors.is_empty() {
+            vec![]
+        } else {
+            let mut nodes = vec![Node::new_text(format!(" · {} ", tr!("viewer.edited_by")))];
+            nodes.extend(names(&editors));
+            nodes
+        },
+    ]
+}
+
+/// Where the post is in a series, linking the series and the parts on either side.
+fn series_nav(membership: &Membership) -> Node<M> {
+    let series_url = series::S::from(membership.slug.clone()).to_url();
+    let neighbour = |class: &str, key: &str, part: &Option<Part>| match part {
+        Some(part) => a![
+            attrs! {
+                At::Class => class;
+                At::Href => series::part_url(part);
+            },
+            tr!(key, title = part.title.as_str()),
+        ],
+        None => empty![],
+    };
+    nav![
+        attrs! { At::Class => "series-nav" },
+        p![a![
+            attrs! { At::Href => series_url },
+            tr!(
+                "viewer.series_part",
+                part = membership.part,
+                parts = membership.parts,
+                title = membership.title.as_str()
+            ),
+        ]],
+        neighbour("prev", "viewer.prev_part", &membership.prev),
+        neighbour("next", "viewer.next_part", &membership.next),
+    ]
+}
+
+/// Links to the headings of long posts, kept in view while reading.
+fn toc(post: &posts::DataNoMeta) -> Node<M> {
+    let long = post.body.split_whitespace().count() >= TOC_MIN_WORDS;
+    if !long || post.outline.len() < TOC_MIN_HEADINGS {
+        return empty![];
+    }
+    let top = post.outline.iter().map(|heading| heading.level).min().unwrap_or(1);
+    nav![
+        attrs! {
+            At::Class => "post-toc";
+            At::AriaLabel => tr!("viewer.contents");
+        },
+        p![attrs! { At::Class => "post-toc-title" }, tr!("viewer.contents")],
+        ol![post.outline.iter().map(|heading| li![
+            attrs! { At::Class => format!("toc-depth-{}", heading.level - top) },
+            a![
+                attrs! { At::Href => format!("#{}", heading.id) },
+                heading.text.as_str(),
+            ],
+        ])],
+    ]
+}
 
-pub fn render(s: &S, gs: &GlobalS) -> Node<M> {
-    match gs.post.as_ref() {
-        Some(post) if s.post_marker.refers_to(post) => render_post(post),
-        _ => shared::views::loading(),
+/// Notes posts that are archived, or not published yet, above their title.
+fn banner(visibility: posts::Visibility) -> Node<M> {
+    let key = match visibility {
+        posts::Visibility::Published => return empty![],
+        posts::Visibility::Archived => "viewer.archived",
+        posts::Visibility::Draft => "viewer.draft",
+    };
+    p![
+        attrs! {
+            At::Class => "post-banner";
+            At::from("role") => "note";
+        },
+        tr!(key),
+    ]
+}
+
+/// Names the kind of reaction, and how many readers left it, for those who can't see the emoji.
+fn reaction_label(kind: reactions::Kind, count: i64) -> String {
+    let key = match kind {
+        reactions::Kind::Like => "reactions.like",
+        reactions::Kind::Heart => "reactions.heart",
+        reactions::Kind::Rocket => "reactions.rocket",
+        reactions::Kind::Eyes => "reactions.eyes",
+    };
+    tr!(key, count = count)
+}
+
+/// The reactions left on the post, each a button that reacts with its kind or takes it back.
+fn reaction_bar(post: &posts::DataNoMeta, gs: &GlobalS) -> Node<M> {
+    if !post.is_published() {
+        return empty![];
     }
+    div![
+        attrs! {
+            At::Class => "reaction-bar";
+            At::from("role") => "group";
+            At::AriaLabel => tr!("viewer.reactions");
+        },
+        reactions::Kind::ALL.iter().copied().map(|kind| {
+            let count = post.reactions.get(kind);
+            button![
+                attrs! {
+                    At::Class => "reaction";
+                    At::Type => "button";
+                    At::AriaLabel => reaction_label(kind, count);
+                    At::from("aria-pressed") => gs.has_reacted(post.id, kind).to_string();
+                },
+                span![attrs! { At::from("aria-hidden") => "true" }, kind.emoji()],
+                if count > 0 {
+                    span![attrs! { At::Class => "reaction-count" }, count.to_string()]
+                } else {
+                    empty![]
+                },
+                ev(Ev::Click, move |_| M::React(kind)),
+            ]
+        }),
+    ]
 }
-fn render_post(post: &posts::DataNoMeta) -> Node<M> {
+
+/// Tucks away what the user may do to the post, so that it doesn't distract from reading. Anyone
+/// may download the post, which the browser does by following the link.
+fn overflow_menu(post: &posts::DataNoMeta, actions: &[Action]) -> Node<M> {
+    details![
+        attrs! { At::Class => "post-menu" },
+        summary![
+            attrs! { At::AriaLabel => tr!("post_actions.menu") },
+            "⋯",
+        ],
+        a![
+            attrs! {
+                At::Class => "inline-button";
+                At::Href => format!("{}?format=html", api::posts::EXPORT.url(&[&post.id]));
+                At::Download => "";
+            },
+            tr!("post_actions.download"),
+        ],
+        actions.iter().copied().map(|action| button![
+            attrs! {
+                At::Class => "inline-button";
+                At::Type => "button";
+            },
+            action.label(),
+            ev(Ev::Click, move |_| M::AskTo(action)),
+        ]),
+    ]
+}
+
+pub fn render(post: &posts::DataNoMeta, s: &S, gs: &GlobalS) -> Node<M> {
+    let tags = &s.tags;
     div![
         attrs! { At::Class => "post" },
+        overflow_menu(post, &Action::offered(gs.user.as_ref(), post)),
+        banner(post.visibility),
         h1![post.title.as_str()],
-        md![post.body.as_str()],
+        byline(post),
+        post.series.iter().map(series_nav),
+        toc(post),
+        // The server highlights code, which the browser leaves plain.
+        match post.html.as_ref() {
+            Some(html) => raw![html.as_str()],
+            None => raw![render_markdown(&post.body).html.as_str()],
+        },
+        post.series.iter().map(series_nav),
+        if tags.is_empty() {
+            empty![]
+        } else {
+            ul![
+                attrs! { At::Class => "tag-chips" },
+                tags.iter().map(|name| li![a![
+                    attrs! {
+                        At::Class => "tag-chip";
+                        At::Href => listing::S::filtered_url(&PostFilter::Tag(name.clone()));
+                    },
+                    name.as_str(),
+                ]]),
+            ]
+        },
+        reaction_bar(post, gs),
+        match s.confirming {
+            Some(action) => action.confirmation(&post.title, M::Confirm, M::DismissModal),
+            None => empty![],
+        },
     ]
-}
\ No newline at end of file
+}
+/// The post as it is laid out before it arrives.
+pub fn render_skeleton<Ms: Clone>() -> Node<Ms> {
+    div![
+        attrs! {
+            At::Class => "post";
+            At::from("aria-busy") => "true";
+        },
+        skeleton::block("70%", "2em"),
+        (0..SKELETON_PARAGRAPHS).map(|_| skeleton::lines(4)),
+    ]
+}
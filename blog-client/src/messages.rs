@@ -1,10 +1,10 @@
 use crate::{
-    locations::{Location, M as LocationM, editor, listing, login},
+    locations::{Location, M as LocationM, dashboard, editor, listing, login, series, settings},
     model,
-    requests::PostQuery,
-    shared::Authorization,
+    requests::PostFilter,
+    shared::{self, notifications, Authorization},
 };
-use tap::*;
+use db_models::models::users;
 use serde::{Deserialize, Serialize};
 
 pub trait AsyncM: std::future::Future<Output = M> {}
@@ -34,6 +34,14 @@ impl std::fmt::Debug for StoreCallback {
     }
 }
 
+/// What to do with unsaved edits when leaving the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnsavedChoice {
+    Stay,
+    Discard,
+    SaveAndGo,
+}
+
 #[derive(Debug, Clone)]
 pub enum M {
     // Url changes!
@@ -52,6 +60,18 @@ pub enum M {
     ),
     StoreOpWithMessage(model::StoreOperations, fn() -> M),
     StoreOp(model::StoreOperations),
+    UnsavedChanges(UnsavedChoice),
+    // The theme the user picked, saved to their account if they are logged in
+    SetTheme(users::Theme),
+    // The language the user picked, saved to their account if they are logged in
+    SetLocale(shared::i18n::Locale),
+    // Saving while the server is out of reach
+    SaveOffline,
+    SaveOnline,
+    // Notifications
+    Notify(notifications::Level, String),
+    NotifyWithAction(notifications::Level, String, notifications::Action),
+    Dismiss(u64),
     // Location specific
     Location(LocationM),
     // Empty message
@@ -77,6 +97,10 @@ impl RouteMatch {
         self.0
     }
     fn msg_from_url(url: seed::Url) -> Option<M> {
+        Self::location(url).map(M::ChangePage)
+    }
+    /// The page at `url`. Urls outside of the blog are loaded instead, and have no page.
+    pub fn location(url: seed::Url) -> Option<Location> {
         log::info!("Routing url {:?}.", url);
         let path = url.path();
         // Verify that the first path component is "blog".
@@ -95,25 +119,19 @@ impl RouteMatch {
 
         let loc = match (root.as_ref(), potential_id) {
             // TODO convert next two patterns into or-patterns when the feature is implemented.
-            ("home", None) | ("home", Some("")) => {
-                use std::convert::TryFrom;
-                let search = url.search();
-                let query = if search.iter().count() == 0 {
-                    let q = PostQuery::try_from(url.search())
-                        .tap_err(|e| log::error!("Attempting to parse url {:?} led to error: {}.", url, e))
-                        .ok()?;
-                    Some(q)
-                } else {
-                    None
-                };
-                Location::Listing(listing::S {
-                    query,
-                })
+            ("home", None) | ("home", Some("")) => Location::Listing(listing::S::from_search(url.search())),
+            ("authors", Some(id)) => match uuid::Uuid::parse_str(id) {
+                Ok(id) => Location::Listing(listing::S::filtered(PostFilter::Author(id), url.search())),
+                Err(_) => Location::NotFound,
+            },
+            ("tags", Some(name)) if name != "" => {
+                Location::Listing(listing::S::filtered(PostFilter::Tag(name.to_owned()), url.search()))
             },
             ("posts", Some(id)) => {
                 let marker: model::PostMarker = id.into();
                 Location::Viewer(marker.into())
             },
+            ("series", Some(slug)) if slug != "" => Location::Series(series::S::from(slug.to_owned())),
             ("editor", id) => Location::Editor(match id {
                 None | Some("new") => editor::S::default(),
                 Some(id) => {
@@ -121,11 +139,18 @@ impl RouteMatch {
                     marker.into()
                 }
             }),
-            ("login", None) | ("login", Some("")) => Location::Login(login::S::default()),
+            ("login", None) | ("login", Some("")) => Location::Login(login::S {
+                next: url.search().get("next")
+                    .and_then(|next| next.get(0))
+                    .and_then(|next| login::same_origin_path(next)),
+                ..Default::default()
+            }),
+            ("profile", None) | ("profile", Some("")) => Location::Settings(settings::S::default()),
+            ("dashboard", None) | ("dashboard", Some("")) => Location::Dashboard(dashboard::S),
             ("logout", None) | ("logout", Some("")) => Location::Logout,
             _ => Location::NotFound,
         };
-        Some(M::ChangePage(loc))
+        Some(loc)
     }
 }
 impl From<seed::Url> for RouteMatch {
@@ -1,8 +1,14 @@
 use seed::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::{locations::*, requests};
-use db_models::models::{posts, users};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+use crate::{locations::*, messages::M, requests, shared::notifications::Notification};
+use db_models::models::{posts, reactions, site, stats, users};
+
+/// How long a cached post listing is shown as is, while it is refreshed in the background.
+const LISTING_TTL_SECS: i64 = 60;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Name {
@@ -18,19 +24,23 @@ impl Name {
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct User {
     pub id: uuid::Uuid,
+    pub user_name: String,
     pub name: Name,
     pub can_see_unpublished: bool,
+    pub preferences: users::Preferences,
 }
 impl From<users::DataNoMeta> for User {
     fn from(u: users::DataNoMeta) -> User {
         Self {
             id: u.id,
+            user_name: u.user_name,
             name: crate::model::Name {
                 first: u.first_name.unwrap_or_else(|| "unknown".to_owned()),
                 last: u.last_name.unwrap_or_else(|| "unknown".to_owned()),
                 nickname: "unknown".to_owned(),
             },
             can_see_unpublished: true,
+            preferences: u.preferences,
         }
     }
 }
@@ -44,11 +54,16 @@ impl PostMarker {
     fn create_slug(slug: &str) -> Self {
         Self::Slug(slug.to_owned())
     }
+    /// Whether the marker names `post`, by either its id or its slug. Both are checked whichever
+    /// the marker was made from, since a slug may look like an id, and a post fetched by one is
+    /// the same post when asked for by the other.
     pub fn refers_to(&self, post: &posts::DataNoMeta) -> bool {
-        match self {
-            Self::Uuid(id) => id == &post.id,
-            Self::Slug(s) => Some(s) == post.slug.as_ref(),
-        }
+        let by_slug = post.slug.as_deref() == Some(self.to_slug().as_str());
+        let by_id = match self {
+            Self::Uuid(id) => *id == post.id,
+            Self::Slug(s) => uuid::Uuid::parse_str(s).map_or(false, |id| id == post.id),
+        };
+        by_slug || by_id
     }
     pub fn to_slug(&self) -> String {
         match self {
@@ -87,9 +102,28 @@ pub enum StoreOperations {
     Post(PostMarker, posts::DataNoMeta),
     PostWithoutMarker(posts::DataNoMeta),
     PostRaw(posts::DataNoMeta),
-    PostListing(requests::PostQuery, Vec<posts::BasicData>),
+    /// A fetched page of the post listing, with the ETag the server sent along with it.
+    PostListing(requests::PostQuery, Vec<posts::Listed>, Option<String>),
+    /// The page after those cached for the query, fetched as the listing is scrolled to its end.
+    MorePosts(requests::PostQuery, Vec<posts::Listed>),
+    /// The server confirmed the cached page of the post listing is still current.
+    PostListingUnchanged(requests::PostQuery),
+    InvalidateListings(ListingInvalidation),
+    /// The post was deleted, so it is dropped wherever it is cached.
+    RemovePost(uuid::Uuid),
+    /// The reader reacted to the post with the kind of reaction, or took it back if not.
+    Reacted(uuid::Uuid, reactions::Kind, bool),
     User(users::DataNoMeta),
+    /// Shows the name of the user as changed, ahead of the server confirming the change.
+    Rename(Name),
     RemoveUser(String),
+    /// The check for a session when the app started found none.
+    NoSession,
+    SiteStats(stats::Site),
+    /// Whether the site takes changes, as checked when the app started.
+    SiteMode(site::Mode),
+    /// How the site presents itself, as the server has it.
+    SiteIdentity(site::Identity),
 }
 impl PartialEq for StoreOperations {
     fn eq(&self, rhs: &StoreOperations) -> bool {
@@ -97,8 +131,19 @@ impl PartialEq for StoreOperations {
             (Self::Post(lhs, _), Self::Post(rhs, _)) => lhs == rhs,
             (Self::PostRaw(lhs), Self::PostRaw(rhs)) => lhs == rhs,
             (Self::PostWithoutMarker(_), Self::PostWithoutMarker(_)) => false,
-            (Self::PostListing(lhs, _), Self::PostListing(rhs, _)) => lhs == rhs,
+            (Self::PostListing(lhs, _, _), Self::PostListing(rhs, _, _)) => lhs == rhs,
+            (Self::MorePosts(lhs, _), Self::MorePosts(rhs, _)) => lhs == rhs,
+            (Self::PostListingUnchanged(lhs), Self::PostListingUnchanged(rhs)) => lhs == rhs,
+            (Self::InvalidateListings(lhs), Self::InvalidateListings(rhs)) => lhs == rhs,
+            (Self::RemovePost(lhs), Self::RemovePost(rhs)) => lhs == rhs,
+            (Self::Reacted(lhs, lhs_kind, lhs_added), Self::Reacted(rhs, rhs_kind, rhs_added)) => {
+                (lhs, lhs_kind, lhs_added) == (rhs, rhs_kind, rhs_added)
+            }
             (Self::RemoveUser(_), Self::RemoveUser(_)) => true,
+            (Self::NoSession, Self::NoSession) => true,
+            (Self::SiteStats(lhs), Self::SiteStats(rhs)) => lhs == rhs,
+            (Self::SiteMode(lhs), Self::SiteMode(rhs)) => lhs == rhs,
+            (Self::SiteIdentity(lhs), Self::SiteIdentity(rhs)) => lhs == rhs,
             _ => false,
         }
     }
@@ -109,76 +154,477 @@ impl std::hash::Hash for StoreOperations {
         match self {
             Self::Post(q, _) => q.hash(state),
             Self::PostRaw(p) => p.hash(state),
-            Self::PostListing(q, _) => q.hash(state),
+            Self::PostListing(q, _, _) => q.hash(state),
+            Self::MorePosts(q, _) => q.hash(state),
+            Self::PostListingUnchanged(q) => q.hash(state),
+            Self::InvalidateListings(i) => i.hash(state),
+            Self::RemovePost(id) => id.hash(state),
+            Self::Reacted(id, kind, added) => (id, kind, added).hash(state),
             Self::User(_) => (),
+            Self::Rename(_) => (),
             Self::RemoveUser(_) => (),
+            Self::NoSession => (),
             Self::PostWithoutMarker(_) => (),
+            Self::SiteStats(s) => s.hash(state),
+            Self::SiteMode(m) => m.hash(state),
+            Self::SiteIdentity(i) => i.hash(state),
         }
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Which cached pages of the post listing a change to a post made out of date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListingInvalidation {
+    /// The post changed in place, so only the pages showing it are out of date.
+    Post(uuid::Uuid),
+    /// Posts were added or moved around, which may shift any page.
+    All,
+}
+
+/// A page of the post listing, as last fetched for its query, along with any pages after it loaded
+/// as the listing was scrolled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedListing {
+    pub published: Vec<posts::Listed>,
+    pub unpublished: Vec<posts::Listed>,
+    pub fetched_at: DateTime<Utc>,
+    /// Sent back to the server when refreshing, so it can skip resending an unchanged page.
+    pub etag: Option<String>,
+    /// The page to load next, or [`None`] once the server ran out of posts.
+    pub next: Option<requests::PostQuery>,
+    /// How many pages the listing holds.
+    pub pages: usize,
+}
+impl CachedListing {
+    fn new(q: &requests::PostQuery, fetched: Vec<posts::Listed>, etag: Option<String>) -> Self {
+        let next = Self::page_after(q, fetched.len());
+        let (published, unpublished) = Self::split(fetched);
+        Self {
+            published,
+            unpublished,
+            fetched_at: Utc::now(),
+            etag,
+            next,
+            pages: 1,
+        }
+    }
+    /// The available posts of a page, split by whether they are published.
+    fn split(fetched: Vec<posts::Listed>) -> (Vec<posts::Listed>, Vec<posts::Listed>) {
+        fetched
+            .into_iter()
+            .filter(|(post, _)| post.deleted_at.is_none())
+            .partition(|(post, _)| post.is_published())
+    }
+    /// The page after `q`, unless the `count` posts fetched for it fell short of a full page.
+    fn page_after(q: &requests::PostQuery, count: usize) -> Option<requests::PostQuery> {
+        q.page_size()
+            .filter(|size| count >= *size)
+            .and_then(|_| q.generate_next())
+    }
+    /// Adds the page after those held. Posts shifted onto it since the earlier pages were fetched
+    /// are only listed once.
+    fn extend(&mut self, q: &requests::PostQuery, fetched: Vec<posts::Listed>) {
+        self.next = Self::page_after(q, fetched.len());
+        self.pages += 1;
+        let fetched = fetched
+            .into_iter()
+            .filter(|(post, _)| !self.shows(post.id))
+            .collect();
+        let (published, unpublished) = Self::split(fetched);
+        self.published.extend(published);
+        self.unpublished.extend(unpublished);
+    }
+    /// Whether the page is recent enough to show straight away.
+    pub fn is_fresh(&self) -> bool {
+        Utc::now() - self.fetched_at < Duration::seconds(LISTING_TTL_SECS)
+    }
+    fn shows(&self, id: uuid::Uuid) -> bool {
+        self.published.iter().chain(&self.unpublished).any(|(post, _)| post.id == id)
+    }
+    /// Drops the post from the page, leaving the rest of it as fetched.
+    fn remove(&mut self, id: uuid::Uuid) {
+        self.published.retain(|(post, _)| post.id != id);
+        self.unpublished.retain(|(post, _)| post.id != id);
+    }
+    /// The author with `id`, if they wrote any of the posts on the page.
+    pub fn author(&self, id: uuid::Uuid) -> Option<&users::AuthorSummary> {
+        self.published
+            .iter()
+            .chain(&self.unpublished)
+            .map(|(_, author)| author)
+            .find(|author| author.id == id)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Store {
-    pub published_posts: Option<Vec<posts::BasicData>>,
-    pub unpublished_posts: Option<Vec<posts::BasicData>>,
+    /// The pages of the post listing fetched so far.
+    pub listings: HashMap<requests::PostQuery, CachedListing>,
     pub post: Option<posts::DataNoMeta>,
     pub user: Option<User>,
+    /// Whether the session, if any, has been checked for. Until then, a missing `user` does not
+    /// mean that nobody is logged in.
+    pub session_checked: bool,
+    pub site_stats: Option<stats::Site>,
+    /// Whether the site takes changes.
+    pub site_mode: site::Mode,
+    /// How the site presents itself, such as its title.
+    pub site_identity: site::Identity,
+    /// The theme the page is shown in.
+    pub theme: users::Theme,
+    /// The language text is shown in.
+    pub locale: crate::shared::i18n::Locale,
+    /// The reactions the reader left on posts, as far as they are known. The server only keeps who
+    /// reacted as a hash, so reactions from earlier visits are learned of when left again.
+    pub reactions: HashSet<(uuid::Uuid, reactions::Kind)>,
 }
 impl Store {
     pub fn exec(&mut self, op: StoreOperations) {
         use StoreOperations::*;
         match op {
-            PostListing(_q, fetched) => {
+            PostListing(q, fetched, etag) => {
                 log::trace!("Post listing store operation triggered.");
-                // TODO use query data to implement cache.
-                let mut available_posts: Vec<_> = fetched
-                    .into_iter()
-                    .filter(|post| post.deleted_at.is_none())
-                    .collect();
-                let published = available_posts
-                    .drain_filter(|post| post.is_published())
-                    .collect();
-                let unpublished = available_posts;
-                self.published_posts.replace(published);
-                self.unpublished_posts.replace(unpublished);
+                let listing = CachedListing::new(&q, fetched, etag);
+                self.listings.insert(q, listing);
+            }
+            MorePosts(q, fetched) => {
+                log::trace!("Appending the next page of the post listing.");
+                match self.listings.get_mut(&q) {
+                    Some(listing) => match listing.next.clone() {
+                        Some(next) => listing.extend(&next, fetched),
+                        None => log::warn!("Fetched more posts for a listing that had run out."),
+                    },
+                    None => log::debug!("Dropping more posts for a listing no longer cached."),
+                }
+            }
+            PostListingUnchanged(q) => {
+                log::trace!("Post listing confirmed unchanged.");
+                if let Some(listing) = self.listings.get_mut(&q) {
+                    listing.fetched_at = Utc::now();
+                }
+            }
+            InvalidateListings(ListingInvalidation::Post(id)) => {
+                log::trace!("Dropping cached post listings showing {}.", id);
+                self.listings.retain(|_, listing| !listing.shows(id));
+            }
+            InvalidateListings(ListingInvalidation::All) => {
+                log::trace!("Dropping all cached post listings.");
+                self.listings.clear();
+            }
+            RemovePost(id) => {
+                log::trace!("Removing deleted post {} from the store.", id);
+                for listing in self.listings.values_mut() {
+                    listing.remove(id);
+                }
+                if self.post.as_ref().map_or(false, |post| post.id == id) {
+                    self.post = None;
+                }
+            }
+            Reacted(id, kind, added) => {
+                log::trace!("Reaction store operation triggered.");
+                if added {
+                    self.reactions.insert((id, kind));
+                } else {
+                    self.reactions.remove(&(id, kind));
+                }
+            }
+            Rename(name) => {
+                log::trace!("User rename operation triggered.");
+                if let Some(user) = self.user.as_mut() {
+                    user.name = name;
+                }
             }
             RemoveUser(_) => {
                 log::trace!("User clear operation triggered.");
                 self.user = None;
+                // Reactions were left as the user, not as whoever reads on.
+                self.reactions.clear();
+                self.session_checked = true;
+            }
+            NoSession => {
+                log::trace!("No session found.");
+                self.session_checked = true;
             }
             User(fo) => {
                 log::trace!("User store operation triggered.");
-                let parsed = fo.into();
+                let parsed: User = fo.into();
+                if self.user.as_ref().map(|user| user.id) != Some(parsed.id) {
+                    self.reactions.clear();
+                }
                 self.user.replace(parsed);
+                self.session_checked = true;
+            }
+            Post(marker, fo) => {
+                if !marker.refers_to(&fo) {
+                    log::warn!("Fetched post {} for {}, which names another post.", fo.id, marker);
+                }
+                self.post.replace(fo);
             }
-            Post(_, fo) | PostWithoutMarker(fo) => {
+            PostWithoutMarker(fo) => {
                 self.post.replace(fo);
             }
             PostRaw(raw_post) => {
                 self.post.replace(raw_post);
             }
+            SiteStats(stats) => {
+                log::trace!("Site stats store operation triggered.");
+                self.site_stats.replace(stats);
+            }
+            SiteMode(mode) => {
+                log::trace!("Site mode store operation triggered.");
+                self.site_mode = mode;
+            }
+            SiteIdentity(identity) => {
+                log::trace!("Site identity store operation triggered.");
+                self.site_identity = identity;
+            }
         }
     }
-    pub fn has_cached_post(&self, id: &PostMarker) -> bool {
-        use PostMarker::*;
-        match (&self.post, &id) {
-            (Some(db_models::posts::DataNoMeta { id: cached_id, .. }), Uuid(id)) => {
-                *id == *cached_id
-            }
-            (
-                Some(db_models::posts::DataNoMeta {
-                    slug: Some(cached_slug),
-                    ..
-                }),
-                Slug(slug),
-            ) => *slug == *cached_slug,
-            _ => false,
+    pub fn listing_for(&self, q: &requests::PostQuery) -> Option<&CachedListing> {
+        self.listings.get(q)
+    }
+    /// Whether the reader is known to have reacted to the post with `kind`.
+    pub fn has_reacted(&self, post_id: uuid::Uuid, kind: reactions::Kind) -> bool {
+        self.reactions.contains(&(post_id, kind))
+    }
+    /// Whether the post named by `marker` is cached, however it was fetched.
+    pub fn has_cached_post(&self, marker: &PostMarker) -> bool {
+        self.post.as_ref().map_or(false, |post| marker.refers_to(post))
+    }
+}
+
+/// A page change held back until the user decides what to do with their unsaved edits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PendingNavigation {
+    pub loc: Location,
+    /// Whether the browser already shows the url of `loc`, as it does when a link is followed.
+    pub url_changed: bool,
+}
+impl PendingNavigation {
+    /// The message that carries on with the page change.
+    pub fn resume(self) -> M {
+        if self.url_changed {
+            M::ChangePage(self.loc)
+        } else {
+            M::ChangePageAndUrl(self.loc)
         }
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A window event listener that is removed once this is dropped.
+pub struct WindowListener {
+    _handle: StreamHandle,
+}
+impl WindowListener {
+    pub fn new(handle: StreamHandle) -> Self {
+        Self { _handle: handle }
+    }
+}
+impl std::fmt::Debug for WindowListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WindowListener")
+    }
+}
+
+/// A save that could not reach the server, retried until it does.
+pub struct SaveRetry {
+    /// How many retries came before the one waiting.
+    pub attempt: u32,
+    _timer: CmdHandle,
+}
+impl SaveRetry {
+    pub fn new(attempt: u32, timer: CmdHandle) -> Self {
+        Self { attempt, _timer: timer }
+    }
+}
+impl std::fmt::Debug for SaveRetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SaveRetry {{ attempt: {} }}", self.attempt)
+    }
+}
+
+#[derive(Default, Debug)]
 pub struct Model {
     pub store: Store,
     pub loc: Location,
+    /// Toasts currently shown, oldest first.
+    pub notifications: Vec<Notification>,
+    pub next_notification_id: u64,
+    /// The page change waiting on the unsaved changes dialog, if it is open.
+    pub pending_navigation: Option<PendingNavigation>,
+    /// Keeps the browser asking before the tab is closed while there are unsaved changes.
+    pub unload_guard: Option<WindowListener>,
+    /// The editor's keyboard shortcuts, held while the editor is open.
+    pub editor_shortcuts: Option<WindowListener>,
+    /// Loads more of the listing as its end is scrolled to, held while there is more to load.
+    pub listing_observer: Option<listing::infinite::Observer>,
+    /// The retry of a save that could not reach the server. Dropping it cancels the retry.
+    pub save_retry: Option<SaveRetry>,
+    /// The id of the history entry shown.
+    pub history_entry: Option<u64>,
+    /// The id the next new history entry gets.
+    pub next_history_entry: u64,
+    /// How far down each history entry was scrolled when it was left.
+    pub scroll_positions: HashMap<u64, f64>,
+    /// Where to scroll to once the page navigated to has rendered.
+    pub pending_scroll: Option<f64>,
+    /// The url of the page whose heading was last focused.
+    pub focused_page: Option<String>,
+}
+impl Model {
+    /// The model with the session already checked, and `user` logged in if anyone is, such as
+    /// when the server said who is as it served the page.
+    pub fn with_user(mut self, user: Option<users::DataNoMeta>) -> Self {
+        self.store.exec(match user {
+            Some(user) => StoreOperations::User(user),
+            None => StoreOperations::NoSession,
+        });
+        self
+    }
+    /// Whether leaving the current page would throw away edits.
+    pub fn has_unsaved_changes(&self) -> bool {
+        match &self.loc {
+            Location::Editor(s) => s.is_dirty(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn id() -> uuid::Uuid {
+        uuid::Uuid::from_u128(7)
+    }
+
+    fn post(slug: Option<&str>) -> posts::DataNoMeta {
+        let now = chrono::Utc::now();
+        posts::DataNoMeta {
+            id: id(),
+            created_at: now,
+            created_by: uuid::Uuid::nil(),
+            updated_at: now,
+            published_at: None,
+            published_by: None,
+            archived_at: None,
+            archived_by: None,
+            deleted_at: None,
+            deleted_by: None,
+            title: "Cached".to_owned(),
+            body: String::new(),
+            slug: slug.map(str::to_owned),
+            meta_description: String::new(),
+            social_image: String::new(),
+            authors: vec![],
+            series: vec![],
+            outline: vec![],
+            html: None,
+            visibility: posts::Visibility::default(),
+            reactions: Default::default(),
+        }
+    }
+
+    fn cached_by(marker: PostMarker) -> Store {
+        let mut store = Store::default();
+        store.exec(StoreOperations::Post(marker, post(Some("cached"))));
+        store
+    }
+
+    #[test]
+    fn posts_fetched_by_slug_are_found_by_either_marker() {
+        let store = cached_by(PostMarker::Slug("cached".to_owned()));
+        assert!(store.has_cached_post(&PostMarker::Slug("cached".to_owned())));
+        assert!(store.has_cached_post(&PostMarker::Uuid(id())));
+    }
+
+    #[test]
+    fn posts_fetched_by_id_are_found_by_either_marker() {
+        let store = cached_by(PostMarker::Uuid(id()));
+        assert!(store.has_cached_post(&PostMarker::Uuid(id())));
+        assert!(store.has_cached_post(&PostMarker::Slug("cached".to_owned())));
+    }
+
+    #[test]
+    fn other_posts_are_not_found() {
+        let store = cached_by(PostMarker::Uuid(id()));
+        assert!(!store.has_cached_post(&PostMarker::Uuid(uuid::Uuid::from_u128(8))));
+        assert!(!store.has_cached_post(&PostMarker::Slug("other".to_owned())));
+        assert!(!Store::default().has_cached_post(&PostMarker::Uuid(id())));
+    }
+
+    #[test]
+    fn slugs_shaped_like_ids_still_match_the_slug() {
+        let slug = uuid::Uuid::from_u128(9).to_hyphenated_ref().to_string();
+        let marker = PostMarker::from(slug.as_str());
+        assert_eq!(marker, PostMarker::Uuid(uuid::Uuid::from_u128(9)));
+        assert!(marker.refers_to(&post(Some(&slug))));
+    }
+
+    fn listed(id: u128) -> posts::Listed {
+        let now = chrono::Utc::now();
+        let post = posts::BasicData {
+            id: uuid::Uuid::from_u128(id),
+            created_at: now,
+            updated_at: now,
+            published_at: Some(now),
+            archived_at: None,
+            deleted_at: None,
+            title: "Listed".to_owned(),
+            slug: None,
+            reactions: 0,
+        };
+        let author = users::AuthorSummary {
+            id: uuid::Uuid::nil(),
+            user_name: "ben".to_owned(),
+            first_name: None,
+            last_name: None,
+        };
+        (post, author)
+    }
+
+    #[test]
+    fn deleted_posts_are_dropped_from_cached_listings() {
+        let mut store = cached_by(PostMarker::Uuid(id()));
+        let query = requests::PostQuery::default();
+        store.exec(StoreOperations::PostListing(query.clone(), vec![listed(7), listed(8)], None));
+        store.exec(StoreOperations::RemovePost(id()));
+        assert_eq!(store.post, None);
+        let listing = store.listing_for(&query).unwrap();
+        assert_eq!(listing.published.len(), 1);
+        assert!(!listing.shows(id()));
+    }
+
+    #[test]
+    fn more_posts_are_appended_until_a_page_falls_short() {
+        let mut store = Store::default();
+        let query = requests::PostQuery::default();
+        let page_size = query.page_size().unwrap();
+        store.exec(StoreOperations::PostListing(query.clone(), (0..page_size as u128).map(listed).collect(), None));
+        assert_eq!(store.listing_for(&query).unwrap().next, query.generate_next());
+        // A post published since shifts the last post of the first page onto the second.
+        let second = (page_size as u128 - 1..2 * page_size as u128 - 1).map(listed).collect();
+        store.exec(StoreOperations::MorePosts(query.clone(), second));
+        let listing = store.listing_for(&query).unwrap();
+        assert_eq!(listing.pages, 2);
+        assert_eq!(listing.published.len(), 2 * page_size - 1);
+        assert!(listing.next.is_some());
+        store.exec(StoreOperations::MorePosts(query.clone(), vec![listed(100)]));
+        let listing = store.listing_for(&query).unwrap();
+        assert_eq!(listing.pages, 3);
+        assert_eq!(listing.published.len(), 2 * page_size);
+        assert_eq!(listing.next, None);
+    }
+
+    #[test]
+    fn reactions_are_forgotten_once_someone_else_reads_on() {
+        let mut store = Store::default();
+        store.exec(StoreOperations::Reacted(id(), reactions::Kind::Heart, true));
+        store.exec(StoreOperations::Reacted(id(), reactions::Kind::Eyes, true));
+        store.exec(StoreOperations::Reacted(id(), reactions::Kind::Eyes, false));
+        assert!(store.has_reacted(id(), reactions::Kind::Heart));
+        assert!(!store.has_reacted(id(), reactions::Kind::Eyes));
+        store.exec(StoreOperations::RemoveUser("ben".to_owned()));
+        assert!(!store.has_reacted(id(), reactions::Kind::Heart));
+    }
 }
@@ -5,6 +5,10 @@ use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 use serde::{Deserialize, Serialize};
 use tap::*;
 
+pub mod api;
+mod manager;
+pub use manager::{get, Fetched, Get};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SortOrdering {
     Ascending,
@@ -160,22 +164,77 @@ impl PostRange {
     }
 }
 
+/// Narrows the posts listed down to some of them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PostFilter {
+    /// Posts written by the user with the id.
+    Author(uuid::Uuid),
+    /// Posts tagged with the tag with the name.
+    Tag(String),
+}
+impl Display for PostFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Author(id) => write!(f, "author={}", id),
+            Self::Tag(name) => write!(f, "tag={}", percent_encode(name.as_bytes(), NON_ALPHANUMERIC)),
+        }
+    }
+}
+
+/// Narrows the posts listed down to those in one state, as each tab of the listing shows them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PostState {
+    Published,
+    Draft,
+    Archived,
+}
+impl PostState {
+    /// The tabs of the listing, in order.
+    pub const ALL: [Self; 3] = [Self::Published, Self::Draft, Self::Archived];
+    /// The name of the state as the server knows it.
+    fn api_name(self) -> &'static str {
+        match self {
+            Self::Published => "published",
+            Self::Draft => "draft",
+            Self::Archived => "archived",
+        }
+    }
+    /// The name of the tab in the url of the listing.
+    pub fn tab_name(self) -> &'static str {
+        match self {
+            Self::Published => "published",
+            Self::Draft => "drafts",
+            Self::Archived => "archived",
+        }
+    }
+    fn from_tab_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|state| state.tab_name() == name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PostQuery {
     Structured {
         range: PostRange,
         sort: Option<PostSort>,
+        filter: Option<PostFilter>,
+        state: Option<PostState>,
     },
 }
 impl Display for PostQuery {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Structured {
-                range,
-                sort: Some(sort),
-            } => write!(f, "{}&{}", range, sort),
-            Self::Structured { range, sort: None } => write!(f, "{}", range),
+        let Self::Structured { range, sort, filter, state } = self;
+        write!(f, "{}", range)?;
+        if let Some(sort) = sort {
+            write!(f, "&{}", sort)?;
         }
+        if let Some(filter) = filter {
+            write!(f, "&{}", filter)?;
+        }
+        if let Some(state) = state {
+            write!(f, "&state={}", state.api_name())?;
+        }
+        Ok(())
     }
 }
 impl Default for PostQuery {
@@ -183,19 +242,58 @@ impl Default for PostQuery {
         Self::Structured {
             range: PostRange::default(),
             sort: None,
+            filter: None,
+            state: None,
         }
     }
 }
 impl PostQuery {
+    /// The query narrowed down by `filter` instead of whatever narrowed it before.
+    pub fn filtered(self, filter: PostFilter) -> Self {
+        let Self::Structured { range, sort, state, .. } = self;
+        Self::Structured {
+            range,
+            sort,
+            filter: Some(filter),
+            state,
+        }
+    }
+    pub fn filter(&self) -> Option<&PostFilter> {
+        let Self::Structured { filter, .. } = self;
+        filter.as_ref()
+    }
+    /// The first page of the posts in `state`, narrowed down and sorted as before.
+    pub fn in_state(self, state: Option<PostState>) -> Self {
+        let Self::Structured { sort, filter, .. } = self;
+        Self::Structured {
+            range: PostRange::default(),
+            sort,
+            filter,
+            state,
+        }
+    }
+    pub fn state(&self) -> Option<PostState> {
+        let Self::Structured { state, .. } = self;
+        *state
+    }
+    /// How many posts a full page of the query holds, if it is paged at all.
+    pub fn page_size(&self) -> Option<usize> {
+        let Self::Structured { range, .. } = self;
+        range.clone().into_offset_and_lim().ok().map(|(_, lim)| lim)
+    }
     pub fn generate_next(&self) -> Option<PostQuery> {
         // TODO be smarter about how many posts are actually available.
         match self {
             Self::Structured {
                 range,
                 sort,
+                filter,
+                state,
             } => Some(Self::Structured {
                 range: range.generate_next()?,
                 sort: sort.clone(),
+                filter: filter.clone(),
+                state: *state,
             }),
         }
     }
@@ -204,9 +302,13 @@ impl PostQuery {
             Self::Structured {
                 range,
                 sort,
+                filter,
+                state,
             } => Some(Self::Structured {
                 range: range.generate_prev()?,
                 sort: sort.clone(),
+                filter: filter.clone(),
+                state: *state,
             }),
         }
     }
@@ -222,6 +324,7 @@ impl std::convert::TryFrom<&seed::browser::url::UrlSearch> for PostQuery {
         let mut ord_criteria = None;
         let mut stop_time = None;
         let mut start_time = None;
+        let mut state = None;
         for (k, vv) in search.iter() {
             let v = if let Some(v) = vv.get(0) { v.as_str() } else { continue; };
             let k = k.as_str();
@@ -238,6 +341,11 @@ impl std::convert::TryFrom<&seed::browser::url::UrlSearch> for PostQuery {
                 "offset" => { offset.replace(v); },
                 "stop_time" => { stop_time.replace(v); },
                 "start_time" => { start_time.replace(v); },
+                "tab" => {
+                    let tab = PostState::from_tab_name(v)
+                        .ok_or_else(|| format!("Unknown `tab` field {:?} in post query.", v))?;
+                    state.replace(tab);
+                },
                 _ => return Err(format!("Unknown search parameter {:?}.", k)),
             }
         }
@@ -302,15 +410,20 @@ impl std::convert::TryFrom<&seed::browser::url::UrlSearch> for PostQuery {
         Ok(PostQuery::Structured {
             range: post_range,
             sort: opt_sort_ordering,
+            filter: None,
+            state,
         })
     }
 }
 
 impl Into<seed::browser::url::UrlSearch> for &PostQuery {
     fn into(self) -> seed::browser::url::UrlSearch {
+        // The filter is part of the path of the page instead.
         let PostQuery::Structured {
             range,
             sort,
+            state,
+            ..
         } = self;
         let mut search = vec![];
         match range.clone().into_offset_and_lim() {
@@ -347,6 +460,33 @@ impl Into<seed::browser::url::UrlSearch> for &PostQuery {
                 },
             }
         }
+        if let Some(state) = state {
+            search.push(("tab".to_string(), vec![state.tab_name().to_string()]));
+        }
         seed::browser::url::UrlSearch::new(search)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use super::{PostFilter, PostQuery, PostState};
+
+    #[test]
+    fn filters_are_sent_along_with_the_range() {
+        let id = uuid::Uuid::nil();
+        let by_author = PostQuery::default().filtered(PostFilter::Author(id));
+        assert_eq!(by_author.to_string(), format!("lim=20&offset=0&author={}", id));
+        let tagged = PostQuery::default().filtered(PostFilter::Tag("rust & web".to_owned()));
+        assert_eq!(tagged.to_string(), "lim=20&offset=0&tag=rust%20%26%20web");
+        assert_eq!(tagged.filter(), Some(&PostFilter::Tag("rust & web".to_owned())));
+    }
+
+    #[test]
+    fn states_are_sent_by_their_api_name() {
+        let drafts = PostQuery::default().in_state(Some(PostState::Draft));
+        assert_eq!(drafts.to_string(), "lim=20&offset=0&state=draft");
+        assert_eq!(drafts.state(), Some(PostState::Draft));
+        assert_eq!(PostState::Draft.tab_name(), "drafts");
+        assert_eq!(PostState::from_tab_name("drafts"), Some(PostState::Draft));
+        assert_eq!(PostState::from_tab_name("draft"), None);
+    }
+}
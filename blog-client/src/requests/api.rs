@@ -0,0 +1,43 @@
+//! Requests to the endpoints of the api, as the server mounts them.
+
+use seed::browser::fetch::{self, Request};
+use serde::Serialize;
+use std::fmt::Display;
+
+pub use blog_api::*;
+
+fn method(method: Method) -> fetch::Method {
+    match method {
+        Method::Get => fetch::Method::Get,
+        Method::Post => fetch::Method::Post,
+        Method::Put => fetch::Method::Put,
+        Method::Patch => fetch::Method::Patch,
+        Method::Delete => fetch::Method::Delete,
+    }
+}
+
+/// A request to the endpoint without a body, with `params` filled into its path.
+pub fn call<'a, B, R>(endpoint: &Endpoint<B, R>, params: &[&dyn Display]) -> Request<'a> {
+    Request::new(endpoint.url(params)).method(method(endpoint.method))
+}
+
+/// A request for the page of the listing starting `offset` rows in, as by [`Endpoint::page_url`].
+pub fn call_page<'a, B, T>(
+    endpoint: &Endpoint<B, Page<T>>,
+    params: &[&dyn Display],
+    limit: Option<i64>,
+    offset: i64,
+) -> Request<'a> {
+    Request::new(endpoint.page_url(params, limit, offset)).method(method(endpoint.method))
+}
+
+/// A request to the endpoint, with `params` filled into its path and `body` sent as JSON.
+pub fn call_with<'a, B: Serialize, R>(
+    endpoint: &Endpoint<B, R>,
+    params: &[&dyn Display],
+    body: &B,
+) -> fetch::Result<Request<'a>> {
+    Request::new(endpoint.url(params))
+        .method(method(endpoint.method))
+        .json(body)
+}
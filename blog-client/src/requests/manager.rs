@@ -0,0 +1,280 @@
+//! Sends the GETs the store is filled from. Identical GETs in flight at the same time are sent
+//! once, and GETs that could not reach the server are retried a few times before giving up.
+//! Requests that change anything never go through here, since retrying those could repeat them.
+
+use seed::browser::fetch::{fetch, Header, Request};
+use serde::de::DeserializeOwned;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use crate::shared::retry::{FailReason, NOT_MODIFIED_CODE};
+
+/// How many times a GET is sent again after failing to reach the server.
+pub const RETRY_LIMIT: u32 = 3;
+/// How long the first retry waits, give or take the jitter.
+const FIRST_RETRY_MS: u32 = 300;
+
+/// A GET, and what tells it apart from other GETs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Get {
+    pub url: String,
+    /// Asks the server to skip resending a response it would give this ETag to.
+    pub if_none_match: Option<String>,
+}
+impl Get {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            if_none_match: None,
+        }
+    }
+    pub fn conditional(url: impl Into<String>, etag: Option<String>) -> Self {
+        Self {
+            url: url.into(),
+            if_none_match: etag,
+        }
+    }
+}
+
+/// A response to a GET, read in full so that everyone waiting on the GET gets a copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    pub status: u16,
+    pub etag: Option<String>,
+    pub body: String,
+}
+impl Fetched {
+    /// Whether the server confirmed that the copy matching the ETag sent is still current.
+    pub fn is_not_modified(&self) -> bool {
+        self.status == NOT_MODIFIED_CODE
+    }
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, FailReason> {
+        serde_json::from_str(&self.body).map_err(|e| {
+            log::error!("Failed to parse the response to a GET: {:?}.", e);
+            FailReason::Other
+        })
+    }
+}
+
+/// What to do about a GET asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Join {
+    /// Nothing like it is in flight, so it needs to be sent.
+    Send,
+    /// The same GET is already in flight, and its result will be handed over too.
+    Wait,
+}
+
+/// What to do once an attempt at a GET is back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Settle<R, W> {
+    /// Send the GET again after waiting this many milliseconds.
+    Retry(u32),
+    /// Hand the result to everyone waiting on it.
+    Done(Result<R, FailReason>, Vec<W>),
+}
+
+struct InFlight<W> {
+    failures: u32,
+    waiting: Vec<W>,
+}
+
+/// The GETs in flight, and who waits on each. This only decides what happens, and leaves the
+/// sending to its caller, so that it can be run against made up results.
+pub struct Manager<K, W> {
+    in_flight: HashMap<K, InFlight<W>>,
+}
+impl<K, W> Default for Manager<K, W> {
+    fn default() -> Self {
+        Self {
+            in_flight: HashMap::new(),
+        }
+    }
+}
+impl<K: Hash + Eq, W> Manager<K, W> {
+    /// Adds `waiter` to those waiting on the GET.
+    pub fn join(&mut self, key: K, waiter: W) -> Join {
+        match self.in_flight.get_mut(&key) {
+            Some(in_flight) => {
+                in_flight.waiting.push(waiter);
+                Join::Wait
+            }
+            None => {
+                self.in_flight.insert(key, InFlight {
+                    failures: 0,
+                    waiting: vec![waiter],
+                });
+                Join::Send
+            }
+        }
+    }
+    /// Takes in the result of an attempt at the GET. `jitter` is anywhere from 0 to 1, and spreads
+    /// out the retries of clients that failed at the same time.
+    pub fn settle<R>(&mut self, key: &K, result: Result<R, FailReason>, jitter: f64) -> Settle<R, W> {
+        let in_flight = match self.in_flight.get_mut(key) {
+            Some(in_flight) => in_flight,
+            None => return Settle::Done(result, vec![]),
+        };
+        // Only failing to reach the server is worth retrying. The server answers the same way
+        // again to anything else.
+        if let Err(FailReason::Network) = result {
+            if in_flight.failures < RETRY_LIMIT {
+                let delay = retry_delay_ms(in_flight.failures, jitter);
+                in_flight.failures += 1;
+                return Settle::Retry(delay);
+            }
+        }
+        let waiting = self.in_flight.remove(key).map_or_else(Vec::new, |in_flight| in_flight.waiting);
+        Settle::Done(result, waiting)
+    }
+}
+
+/// How long to wait before sending a GET again, after it failed `failures` times before.
+pub fn retry_delay_ms(failures: u32, jitter: f64) -> u32 {
+    let base = FIRST_RETRY_MS << failures.min(RETRY_LIMIT);
+    (f64::from(base) * (0.5 + jitter.max(0.).min(1.))) as u32
+}
+
+/// Where the result of a GET is left for one of those waiting on it.
+#[derive(Default)]
+struct Slot {
+    result: RefCell<Option<Result<Fetched, FailReason>>>,
+    waker: RefCell<Option<Waker>>,
+}
+impl Slot {
+    fn fill(&self, result: Result<Fetched, FailReason>) {
+        self.result.replace(Some(result));
+        if let Some(waker) = self.waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+}
+
+struct Waiting(Rc<Slot>);
+impl Future for Waiting {
+    type Output = Result<Fetched, FailReason>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.0.result.borrow_mut().take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                self.0.waker.replace(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+        }
+    }
+}
+
+thread_local! {
+    static MANAGER: RefCell<Manager<Get, Rc<Slot>>> = RefCell::new(Manager::default());
+}
+
+/// Sends the GET, or waits on the same GET if it is already in flight.
+pub async fn get(get: Get) -> Result<Fetched, FailReason> {
+    let slot = Rc::new(Slot::default());
+    let join = MANAGER.with(|manager| manager.borrow_mut().join(get.clone(), Rc::clone(&slot)));
+    if join == Join::Send {
+        wasm_bindgen_futures::spawn_local(drive(get));
+    } else {
+        log::debug!("Waiting on {} already in flight.", get.url);
+    }
+    Waiting(slot).await
+}
+
+async fn drive(get: Get) {
+    loop {
+        let result = send(&get).await;
+        let settle = MANAGER.with(|manager| manager.borrow_mut().settle(&get, result, js_sys::Math::random()));
+        match settle {
+            Settle::Retry(delay) => {
+                log::warn!("Could not reach the server for {}. Retrying in {}ms.", get.url, delay);
+                seed::app::cmds::timeout(delay, || ()).await;
+            }
+            Settle::Done(result, waiting) => {
+                for slot in waiting {
+                    slot.fill(result.clone());
+                }
+                return;
+            }
+        }
+    }
+}
+
+async fn send(get: &Get) -> Result<Fetched, FailReason> {
+    let req = match &get.if_none_match {
+        Some(etag) => Request::new(get.url.as_str()).header(Header::custom("If-None-Match", etag.clone())),
+        None => Request::new(get.url.as_str()),
+    };
+    let res = fetch(req).await.map_err(|e| FailReason::from(&e))?;
+    let status = res.status().code;
+    let res = if status == NOT_MODIFIED_CODE {
+        res
+    } else {
+        res.check_status().map_err(|e| FailReason::from(&e))?
+    };
+    let etag = res.raw_response().headers().get("ETag").ok().flatten();
+    let body = res.text().await.map_err(|e| FailReason::from(&e))?;
+    Ok(Fetched { status, etag, body })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identical_gets_in_flight_are_sent_once() {
+        let mut manager = Manager::default();
+        assert_eq!(manager.join("/api/posts", 1), Join::Send);
+        assert_eq!(manager.join("/api/posts", 2), Join::Wait);
+        assert_eq!(manager.join("/api/tags", 3), Join::Send);
+        assert_eq!(manager.settle(&"/api/posts", Ok("posts"), 0.5), Settle::Done(Ok("posts"), vec![1, 2]));
+        // Once settled, the same GET is sent again rather than answered from the last result.
+        assert_eq!(manager.join("/api/posts", 4), Join::Send);
+    }
+
+    #[test]
+    fn network_failures_are_retried_up_to_the_limit() {
+        let mut manager = Manager::default();
+        manager.join("/api/posts", 1);
+        for _ in 0..RETRY_LIMIT {
+            match manager.settle::<()>(&"/api/posts", Err(FailReason::Network), 0.5) {
+                Settle::Retry(_) => (),
+                settle => panic!("Expected a retry, got {:?}.", settle),
+            }
+        }
+        assert_eq!(
+            manager.settle::<()>(&"/api/posts", Err(FailReason::Network), 0.5),
+            Settle::Done(Err(FailReason::Network), vec![1]),
+        );
+    }
+
+    #[test]
+    fn answers_from_the_server_are_not_retried() {
+        let mut manager = Manager::default();
+        for (waiter, reason) in vec![FailReason::NotFound, FailReason::Unauthorized, FailReason::Server(500)]
+            .into_iter()
+            .enumerate()
+        {
+            manager.join("/api/posts/1", waiter);
+            assert_eq!(
+                manager.settle::<()>(&"/api/posts/1", Err(reason), 0.5),
+                Settle::Done(Err(reason), vec![waiter]),
+            );
+        }
+    }
+
+    #[test]
+    fn retries_back_off_with_jitter() {
+        assert_eq!(retry_delay_ms(0, 0.5), FIRST_RETRY_MS);
+        assert_eq!(retry_delay_ms(1, 0.5), FIRST_RETRY_MS * 2);
+        assert_eq!(retry_delay_ms(2, 0.5), FIRST_RETRY_MS * 4);
+        assert_eq!(retry_delay_ms(0, 0.), FIRST_RETRY_MS / 2);
+        assert_eq!(retry_delay_ms(0, 1.), FIRST_RETRY_MS * 3 / 2);
+    }
+}
@@ -1,5 +1,17 @@
+pub mod challenge;
+pub mod focus;
+pub mod history;
+pub mod i18n;
+pub mod initial_state;
+pub mod loadable;
+pub mod post_actions;
+pub mod preferences;
 pub mod views;
 pub mod retry;
+pub mod site_identity;
+pub mod site_mode;
+pub mod theme;
+pub mod notifications;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Authorization {
@@ -0,0 +1,66 @@
+//! Solves the proof of work the server asks of anonymous callers of public endpoints, such as
+//! signing up.
+//!
+//! The search runs on the UI thread, a chunk of nonces at a time, handing control back to the
+//! browser for a frame between chunks so that the page keeps responding while it works.
+
+use seed::browser::fetch::{Header, Request};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{requests::api, shared::retry};
+use login_enum::challenge::{Answer, Challenge};
+
+/// How many nonces are tried before yielding to the browser.
+const NONCES_PER_FRAME: u64 = 4096;
+
+const FETCH_MSG: retry::LogPair<'static> = retry::LogPair {
+    pre_completion: "fetching challenge",
+    post_completion: "parsing challenge",
+};
+
+/// Waits for the browser to draw the next frame.
+async fn next_frame() {
+    let frame = js_sys::Promise::new(&mut |resolve, _| {
+        if seed::window().request_animation_frame(&resolve).is_err() {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
+        }
+    });
+    let _ = JsFuture::from(frame).await;
+}
+
+/// Finds the first nonce solving `challenge`.
+pub async fn solve(challenge: &Challenge) -> u64 {
+    let mut nonce = 0;
+    loop {
+        let chunk_end = nonce + NONCES_PER_FRAME;
+        while nonce < chunk_end {
+            if challenge.is_solved_by(nonce) {
+                return nonce;
+            }
+            nonce += 1;
+        }
+        next_frame().await;
+    }
+}
+
+/// Asks the server for a challenge and solves it, adding the solution to `req`.
+pub async fn prove(req: Request<'_>) -> Result<Request<'_>, retry::FailReason> {
+    let challenge: Challenge = retry::fetch_json_with_retry(
+        api::call(&api::challenge::ISSUE, &[]),
+        &FETCH_MSG,
+        None,
+    ).await?;
+    let nonce = solve(&challenge).await;
+    log::debug!("Solved a challenge of difficulty {} with {}.", challenge.difficulty, nonce);
+    Ok(with_answer(req, &Answer::ProofOfWork {
+        token: challenge.token,
+        nonce,
+    }))
+}
+
+/// Sends `answer` along with `req`.
+pub fn with_answer<'a>(req: Request<'a>, answer: &Answer) -> Request<'a> {
+    answer.headers()
+        .into_iter()
+        .fold(req, |req, (name, value)| req.header(Header::custom(name, value)))
+}
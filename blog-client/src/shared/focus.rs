@@ -0,0 +1,104 @@
+//! Moves focus along with page changes. The whole page is swapped out on navigation, so without
+//! this, screen readers are left on a link that is no longer there instead of the new page.
+
+use seed::prelude::*;
+use tap::*;
+
+/// Focuses the main heading of the page, making it focusable first if need be. Returns whether
+/// there was a heading to focus.
+pub fn focus_heading() -> bool {
+    let heading = match seed::document().query_selector("main h1") {
+        Ok(Some(heading)) => heading,
+        _ => return false,
+    };
+    // Headings can only be focused from script, rather than being tabbed to.
+    if !heading.has_attribute("tabindex") {
+        let _ = heading
+            .set_attribute("tabindex", "-1")
+            .tap_err(|_| log::error!("Failed to make the heading focusable."));
+    }
+    heading
+        .dyn_into::<web_sys::HtmlElement>()
+        .tap_err(|_| log::error!("Heading is not an HtmlElement!"))
+        .ok()
+        .and_then(|heading| {
+            heading
+                .focus()
+                .tap_err(|_| log::error!("Failed to focus on the heading."))
+                .ok()
+        })
+        .is_some()
+}
+
+/// Marks the links in the menu to `path`, or else to the section it is in, as going to the current
+/// page, the way the server marks them as it renders the menu.
+pub fn mark_current_page(path: &str) {
+    let links = match seed::document().query_selector_all("nav a") {
+        Ok(links) => links,
+        Err(_) => return,
+    };
+    let links: Vec<_> = (0..links.length())
+        .filter_map(|i| links.item(i).and_then(|link| link.dyn_into::<web_sys::Element>().ok()))
+        .map(|link| (link.get_attribute("href").unwrap_or_default(), link))
+        .collect();
+    let current = htmlgen::data::current_link(links.iter().map(|(href, _)| href.as_str()), path);
+    for (href, link) in links.iter() {
+        let is_current = Some(href.as_str()) == current;
+        if is_current {
+            let _ = link.set_attribute("aria-current", "page");
+        } else {
+            let _ = link.remove_attribute("aria-current");
+        }
+        let _ = link.class_list().toggle_with_force("active", is_current);
+        if let Some(item) = link.parent_element().filter(|item| item.tag_name() == "LI") {
+            let _ = item.class_list().toggle_with_force("active", is_current);
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod test {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn focus_lands_on_the_heading_of_the_new_page() {
+        let document = seed::document();
+        let main = document.create_element("main").unwrap();
+        main.set_inner_html("<a href=\"/blog/posts/1\">A post</a>");
+        seed::body().append_child(&main).unwrap();
+        let link: web_sys::HtmlElement = main.first_element_child().unwrap().dyn_into().unwrap();
+        link.focus().unwrap();
+
+        // Navigating swaps out the content of the page.
+        main.set_inner_html("<h1>A post</h1><p>Body</p>");
+        assert!(focus_heading());
+        let heading = main.first_element_child().unwrap();
+        assert_eq!(document.active_element(), Some(heading.clone()));
+        assert_eq!(heading.get_attribute("tabindex").as_deref(), Some("-1"));
+
+        main.set_inner_html("<p>No heading</p>");
+        assert!(!focus_heading());
+        main.remove();
+    }
+
+    #[wasm_bindgen_test]
+    fn only_links_to_the_page_are_marked_current() {
+        let nav = seed::document().create_element("nav").unwrap();
+        nav.set_inner_html("<a href=\"/blog\">Blog</a><a href=\"/blog/login\" aria-current=\"page\">Login</a>");
+        seed::body().append_child(&nav).unwrap();
+        mark_current_page("/blog");
+        let blog = nav.first_element_child().unwrap();
+        let login = blog.next_element_sibling().unwrap();
+        assert_eq!(blog.get_attribute("aria-current").as_deref(), Some("page"));
+        assert_eq!(login.get_attribute("aria-current"), None);
+
+        // Pages under a linked section mark the innermost section.
+        mark_current_page("/blog/login/reset");
+        assert_eq!(blog.get_attribute("aria-current"), None);
+        assert_eq!(login.get_attribute("aria-current").as_deref(), Some("page"));
+        nav.remove();
+    }
+}
@@ -0,0 +1,46 @@
+use wasm_bindgen::JsValue;
+
+/// The field added to seed's history state to tell history entries apart.
+const ENTRY_FIELD: &str = "entry_id";
+
+/// Takes over restoring the scroll position from the browser, which would otherwise restore it
+/// before the page it belongs to has rendered.
+pub fn manage_scroll_restoration() {
+    if let Ok(history) = seed::window().history() {
+        let _ = history.set_scroll_restoration(web_sys::ScrollRestoration::Manual);
+    }
+}
+
+/// The id of the current history entry, giving it `new_id` if it does not have one yet, as is the
+/// case for newly pushed entries.
+pub fn current_entry(new_id: u64) -> u64 {
+    let history = match seed::window().history() {
+        Ok(history) => history,
+        Err(_) => return new_id,
+    };
+    // Seed keeps the url of the entry in the state, so that has to be kept intact.
+    let mut state = history
+        .state()
+        .ok()
+        .and_then(|state| state.as_string())
+        .and_then(|state| serde_json::from_str::<serde_json::Value>(&state).ok())
+        .or_else(|| serde_json::to_value(seed::Url::current()).ok())
+        .unwrap_or_else(|| serde_json::json!({}));
+    if let Some(id) = state.get(ENTRY_FIELD).and_then(serde_json::Value::as_u64) {
+        return id;
+    }
+    if let Some(fields) = state.as_object_mut() {
+        fields.insert(ENTRY_FIELD.to_owned(), new_id.into());
+    }
+    let _ = history.replace_state(&JsValue::from_str(&state.to_string()), "");
+    new_id
+}
+
+/// How far down the page is scrolled.
+pub fn scroll_y() -> f64 {
+    seed::window().scroll_y().unwrap_or(0.)
+}
+
+pub fn scroll_to(y: f64) {
+    seed::window().scroll_to_with_x_and_y(0., y);
+}
@@ -0,0 +1,73 @@
+//! The language the client is shown in. Text is looked up through [`tr!`](crate::tr), which goes
+//! by the locale last applied.
+
+use seed::prelude::*;
+use std::cell::Cell;
+
+use crate::messages::M as GlobalM;
+pub use htmlgen::i18n::Locale;
+
+/// The local storage key the locale picked on this browser is kept under.
+const STORAGE_KEY: &str = "locale";
+
+thread_local! {
+    static CURRENT: Cell<Locale> = Cell::new(Locale::default());
+}
+
+/// The locale text is shown in.
+pub fn current() -> Locale {
+    CURRENT.with(Cell::get)
+}
+
+/// The locale picked on this browser before, or else the one the browser is set to.
+pub fn initial() -> Locale {
+    if let Ok(locale) = LocalStorage::get(STORAGE_KEY) {
+        return locale;
+    }
+    seed::window()
+        .navigator()
+        .language()
+        .and_then(|tag| Locale::from_tag(&tag))
+        .unwrap_or_default()
+}
+
+/// Switches text over to the locale. Returns whether the page was in another language before.
+pub fn apply(locale: Locale) -> bool {
+    CURRENT.with(|current| current.set(locale));
+    let root = match seed::document().document_element() {
+        Some(root) => root,
+        None => return false,
+    };
+    let changed = root.get_attribute("lang").and_then(|tag| Locale::from_tag(&tag)) != Some(locale);
+    let _ = root.set_attribute("lang", locale.tag());
+    changed
+}
+
+/// Remembers the locale picked on this browser, over the one the browser is set to.
+pub fn remember(locale: Locale) {
+    if let Err(e) = LocalStorage::insert(STORAGE_KEY, &locale) {
+        log::error!("Failed to keep the locale in local storage: {:?}.", e);
+    }
+}
+
+/// Lets the user pick the locale.
+pub fn picker(locale: Locale) -> Node<GlobalM> {
+    label![
+        attrs! { At::Class => "locale-picker" },
+        tr!("locale.label"),
+        select![
+            Locale::ALL.iter().map(|option| {
+                option![
+                    attrs! { At::Value => option.tag(); At::from("lang") => option.tag() },
+                    if *option == locale {
+                        attrs! { At::Selected => AtValue::None }
+                    } else {
+                        attrs! {}
+                    },
+                    option.name(),
+                ]
+            }),
+            input_ev(Ev::Change, |tag| Locale::from_tag(&tag).map(GlobalM::SetLocale)),
+        ],
+    ]
+}
@@ -0,0 +1,48 @@
+//! What the server knew when it served the page, handed over in `window.__INITIAL_STATE__` so that
+//! the client does not ask for it again.
+use wasm_bindgen::JsValue;
+
+use db_models::models::site::InitialState;
+
+/// The name the server hands the state over under.
+const GLOBAL: &str = "__INITIAL_STATE__";
+
+/// The state the server handed over, or [`None`] if it handed over nothing usable, in which case
+/// the client asks for it instead.
+pub fn take() -> Option<InitialState> {
+    let global = js_sys::Reflect::get(&seed::window(), &JsValue::from_str(GLOBAL)).ok()?;
+    if global.is_undefined() || global.is_null() {
+        log::debug!("No initial state was handed over.");
+        return None;
+    }
+    let json = js_sys::JSON::stringify(&global).ok()?.as_string()?;
+    parse(&json)
+}
+
+fn parse(json: &str) -> Option<InitialState> {
+    serde_json::from_str(json)
+        .map_err(|e| log::warn!("Ignoring malformed initial state due to {:?}.", e))
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use db_models::models::site;
+
+    #[test]
+    fn only_well_formed_state_is_used() {
+        let parsed = parse(r#"{"user":null,"mode":{"mode":"normal"}}"#);
+        assert_eq!(
+            parsed,
+            Some(site::InitialState {
+                user: None,
+                mode: site::Mode::Normal,
+                identity: site::Identity::default(),
+            })
+        );
+        assert_eq!(parse(r#"{"user":{"id":"nope"},"mode":{"mode":"normal"}}"#), None);
+        assert_eq!(parse("[]"), None);
+        assert_eq!(parse(""), None);
+    }
+}
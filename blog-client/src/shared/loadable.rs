@@ -0,0 +1,78 @@
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::listing,
+    messages::M as GlobalM,
+    shared::retry::FailReason,
+};
+
+/// The data a page is rendered from, and how far along fetching it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Loadable<T> {
+    NotAsked,
+    Loading,
+    Loaded(T),
+    Failed(FailReason),
+}
+impl<T> Default for Loadable<T> {
+    fn default() -> Self {
+        Self::NotAsked
+    }
+}
+impl<T> Loadable<T> {
+    pub fn as_ref(&self) -> Loadable<&T> {
+        match self {
+            Self::NotAsked => Loadable::NotAsked,
+            Self::Loading => Loadable::Loading,
+            Self::Loaded(t) => Loadable::Loaded(t),
+            Self::Failed(reason) => Loadable::Failed(*reason),
+        }
+    }
+    /// Whether the data has yet to arrive, and has not failed to.
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::NotAsked | Self::Loading)
+    }
+}
+
+/// What to tell the user when loading a page fails.
+pub struct Failure<'a> {
+    /// The key of what was being done, such as "attempt.load_post".
+    pub attempt: &'a str,
+    /// The key of the heading shown when the thing asked for does not exist, such as
+    /// "not_found.post".
+    pub not_found: &'a str,
+}
+
+/// Renders the page with `loaded` once its data is in, with `pending` until then, or what became
+/// of loading it. `retry` fetches the data again.
+pub fn view<T>(
+    load: Loadable<&T>,
+    failure: &Failure,
+    retry: GlobalM,
+    pending: impl FnOnce() -> Vec<Node<GlobalM>>,
+    loaded: impl FnOnce(&T) -> Vec<Node<GlobalM>>,
+) -> Vec<Node<GlobalM>> {
+    match load {
+        Loadable::NotAsked | Loadable::Loading => pending(),
+        Loadable::Loaded(t) => loaded(t),
+        Loadable::Failed(FailReason::NotFound) => vec![div![
+            attrs! { At::Class => "load-failed" },
+            h1![tr!(failure.not_found)],
+            p![tr!("common.not_found")],
+            a![
+                attrs! { At::Href => listing::S::url_root() },
+                tr!("common.back_to_posts"),
+            ],
+        ]],
+        Loadable::Failed(reason) => vec![div![
+            attrs! { At::Class => "load-failed" },
+            p![reason.describe(failure.attempt)],
+            button![
+                attrs! { At::Class => "inline-button" },
+                tr!("common.try_again"),
+                ev(Ev::Click, move |_| retry),
+            ],
+        ]],
+    }
+}
@@ -0,0 +1,102 @@
+use seed::prelude::*;
+
+use crate::{
+    locations::{login, Location},
+    messages::M as GlobalM,
+    shared::retry::FailReason,
+};
+
+/// How long a notification stays up before it dismisses itself.
+pub const DISMISS_AFTER_MS: u32 = 6000;
+/// The most notifications shown at once. The oldest are dropped to make room for new ones.
+pub const MAX_SHOWN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    Info,
+    Error,
+}
+impl Level {
+    fn class(self) -> &'static str {
+        match self {
+            Self::Info => "toast-info",
+            Self::Error => "toast-error",
+        }
+    }
+}
+
+/// Something the user can do straight from a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    LogIn,
+}
+impl Action {
+    fn view(self) -> Node<GlobalM> {
+        match self {
+            Self::LogIn => button![
+                tr!("toast.log_in_again"),
+                ev(Ev::Click, |_| GlobalM::ChangePageAndUrl(Location::Login(
+                    login::S::default()
+                ))),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Notification {
+    pub id: u64,
+    pub level: Level,
+    pub text: String,
+    pub action: Option<Action>,
+}
+
+impl FailReason {
+    /// Tells the user that `attempt` failed, and why. `attempt` is the key of what was being done,
+    /// such as "attempt.load_post".
+    pub fn describe(self, attempt: &str) -> String {
+        let attempt = tr!(attempt);
+        match self {
+            Self::Network => tr!("failure.network", attempt = attempt),
+            Self::Unauthorized => tr!("failure.unauthorized", attempt = attempt),
+            Self::Forbidden => tr!("failure.forbidden", attempt = attempt),
+            Self::NotFound => tr!("failure.not_found", attempt = attempt),
+            Self::Conflict => tr!("failure.conflict", attempt = attempt),
+            Self::Invalid => tr!("failure.invalid", attempt = attempt),
+            Self::TooLarge => tr!("failure.too_large", attempt = attempt),
+            Self::Unavailable => tr!("failure.unavailable", attempt = attempt),
+            Self::Server(code) => tr!("failure.server", attempt = attempt, code = code),
+            Self::Other => tr!("failure.other", attempt = attempt),
+        }
+    }
+    /// The message notifying the user that `attempt` failed, and why.
+    pub fn notify(self, attempt: &str) -> GlobalM {
+        let text = self.describe(attempt);
+        match self {
+            Self::Unauthorized => GlobalM::NotifyWithAction(Level::Error, text, Action::LogIn),
+            _ => GlobalM::Notify(Level::Error, text),
+        }
+    }
+}
+
+pub fn view(shown: &[Notification]) -> Node<GlobalM> {
+    div![
+        attrs! {
+            At::Class => "toasts";
+            At::from("role") => "status";
+        },
+        shown.iter().map(|n| {
+            let id = n.id;
+            div![
+                attrs! { At::Class => format!("toast {}", n.level.class()) },
+                p![n.text.as_str()],
+                n.action.map_or_else(|| empty![], Action::view),
+                button![
+                    attrs! { At::Class => "toast-dismiss" },
+                    tr!("toast.dismiss"),
+                    ev(Ev::Click, move |_| GlobalM::Dismiss(id)),
+                ],
+            ]
+        }),
+    ]
+}
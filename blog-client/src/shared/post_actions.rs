@@ -0,0 +1,113 @@
+//! Archiving and deleting posts, offered by the editor and the viewer to whoever may manage the
+//! post, once they confirm it.
+
+use seed::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locations::{listing, Location},
+    messages::M as GlobalM,
+    model::{ListingInvalidation, StoreOperations as GSOp, User},
+    requests::api,
+    shared::{notifications, retry},
+};
+use db_models::models::posts;
+
+/// A change to a post that can't be undone from the client, and so is confirmed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    /// Takes the post out of the listing, keeping it at its url.
+    Archive,
+    /// Removes the post, leaving it to be purged later.
+    Delete,
+}
+impl Action {
+    /// The actions that make sense for `post` as it is now, whoever takes them.
+    pub fn applicable_to(post: &posts::DataNoMeta) -> Vec<Self> {
+        if post.deleted_at.is_some() {
+            vec![]
+        } else if post.archived_at.is_some() {
+            vec![Self::Delete]
+        } else {
+            vec![Self::Archive, Self::Delete]
+        }
+    }
+    /// The actions `user` may take on `post`, going by whether they created or are credited on it.
+    /// The server checks again, and also lets users holding capabilities over the posts of others
+    /// take them.
+    pub fn offered(user: Option<&User>, post: &posts::DataNoMeta) -> Vec<Self> {
+        let manages = user.map_or(false, |user| {
+            post.created_by == user.id || post.authors.iter().any(|author| author.user.id == user.id)
+        });
+        if manages {
+            Self::applicable_to(post)
+        } else {
+            vec![]
+        }
+    }
+    /// The label of the button that asks to take the action.
+    pub fn label(self) -> String {
+        match self {
+            Self::Archive => tr!("post_actions.archive"),
+            Self::Delete => tr!("post_actions.delete"),
+        }
+    }
+    /// Asks the user to confirm the action on the post titled `title`.
+    pub fn confirmation<Ms: Clone + 'static>(self, title: &str, confirm: Ms, cancel: Ms) -> Node<Ms> {
+        let (question, consequence) = match self {
+            Self::Archive => ("post_actions.archive_confirm.title", "post_actions.archive_confirm.body"),
+            Self::Delete => ("post_actions.delete_confirm.title", "post_actions.delete_confirm.body"),
+        };
+        super::views::modal(
+            tr!(question, title = title),
+            vec![p![tr!(consequence)]],
+            (self.label(), confirm),
+            cancel,
+        )
+    }
+}
+
+/// Archives the post, then sends `then` once the store has the post as archived.
+pub async fn archive(mut post: posts::DataNoMeta, user_id: uuid::Uuid, then: GlobalM) -> GlobalM {
+    const ARCHIVE_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "archiving post",
+        post_completion: "reading archived post",
+    };
+    let req = api::call(&api::posts::ARCHIVE, &[&post.id]);
+    match retry::fetch_text_with_retry(req, &ARCHIVE_MSG, retry::NO_RETRY).await {
+        Ok(_) => {
+            // The server answers without the post, so it is archived in place just as the server
+            // did it.
+            post.archived_at = Some(chrono::Utc::now());
+            post.archived_by = Some(user_id);
+            if post.published_at.is_some() {
+                post.visibility = posts::Visibility::Archived;
+            }
+            GlobalM::Grouped(vec![
+                GlobalM::StoreOp(GSOp::InvalidateListings(ListingInvalidation::Post(post.id))),
+                GlobalM::StoreOp(GSOp::PostRaw(post)),
+                then,
+                GlobalM::Notify(notifications::Level::Info, tr!("post_actions.archived")),
+            ])
+        }
+        Err(reason) => reason.notify("attempt.archive_post"),
+    }
+}
+
+/// Deletes the post, then sends `then` before leaving for the listing.
+pub async fn delete(post_id: uuid::Uuid, then: GlobalM) -> GlobalM {
+    const DELETE_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "deleting post",
+        post_completion: "confirming deletion",
+    };
+    let req = api::call(&api::posts::DELETE, &[&post_id]);
+    match retry::fetch_text_with_retry(req, &DELETE_MSG, retry::NO_RETRY).await {
+        Ok(_) => GlobalM::Grouped(vec![
+            GlobalM::StoreOp(GSOp::RemovePost(post_id)),
+            then,
+            GlobalM::Notify(notifications::Level::Info, tr!("post_actions.deleted")),
+            GlobalM::ChangePageAndUrl(Location::Listing(listing::S::default())),
+        ]),
+        Err(reason) => reason.notify("attempt.delete_post"),
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{messages::M as GlobalM, requests::api, shared::retry};
+use db_models::models::users;
+
+/// Saves the preferences as the logged in user's, so that they follow them to other devices. This
+/// replaces all of the preferences saved before.
+pub async fn save(preferences: users::Preferences) -> GlobalM {
+    const SAVE_PREFERENCES_MSG: retry::LogPair<'static> = retry::LogPair {
+        pre_completion: "saving preferences",
+        post_completion: "parsing account with saved preferences",
+    };
+    let changes = users::ChangedNoMeta {
+        user_name: None,
+        first_name: None,
+        last_name: None,
+        email: None,
+        preferences: Some(preferences),
+    };
+    let req = api::call_with(&api::accounts::UPDATE_SELF, &[], &changes);
+    let req = if let Ok(req) = req {
+        req
+    } else {
+        return GlobalM::NoOp;
+    };
+    match retry::fetch_with_retry(req, &SAVE_PREFERENCES_MSG, retry::NO_RETRY).await {
+        // The preferences are already applied here, so only the other devices miss out.
+        Err(reason) => reason.notify("attempt.save_preferences"),
+        Ok(_) => GlobalM::NoOp,
+    }
+}
@@ -1,8 +1,15 @@
 use seed::browser::fetch::{fetch, Response, Request, Result as FetchResult};
 
 mod error;
+pub use error::FailReason;
 
 const RETRY_LIM: usize = 10;
+/// The retry limit of requests that change anything, which are sent only once since retrying them
+/// could repeat the change.
+pub const NO_RETRY: Option<usize> = Some(1);
+pub const NOT_MODIFIED_CODE: u16 = 304;
+pub const CONFLICT_CODE: u16 = 409;
+pub const UNPROCESSABLE_CODE: u16 = 422;
 
 pub struct LogPair<'a> {
     pub pre_completion: &'a str,
@@ -14,7 +21,7 @@ pub enum AllowRetry {
     Disallow,
 }
 
-async fn fetch_conditional<'a>(req: Request<'a>, logging_msg: &LogPair<'a>) -> Result<Response, AllowRetry> {
+async fn fetch_conditional<'a>(req: Request<'a>, logging_msg: &LogPair<'a>) -> Result<Response, (AllowRetry, FailReason)> {
     fetch(req).await
         .map_err(|e| error::process_fetch_err(
             e,
@@ -28,9 +35,10 @@ pub struct RetryResult {
     pub response: Response,
 }
 
-pub async fn fetch_with_retry<'a>(req: Request<'a>, logging_msg: &LogPair<'a>, retry_lim: Option<usize>) -> Result<RetryResult, ()> {
+pub async fn fetch_with_retry<'a>(req: Request<'a>, logging_msg: &LogPair<'a>, retry_lim: Option<usize>) -> Result<RetryResult, FailReason> {
     // TODO Figure out a good default retry limit.
     let retry_lim = retry_lim.unwrap_or(RETRY_LIM);
+    let mut failure = FailReason::Other;
     for retry_cnt in 0..retry_lim {
         if retry_cnt != 0 {
             let next_retry = ordinal::Ordinal(retry_cnt + 1);
@@ -40,22 +48,34 @@ pub async fn fetch_with_retry<'a>(req: Request<'a>, logging_msg: &LogPair<'a>, r
         let fetch_attempt = fetch_conditional(req.clone(), logging_msg).await;
         let res = match fetch_attempt {
             Ok(res) => res,
-            Err(AllowRetry::Allow) =>  {
+            Err((AllowRetry::Allow, reason)) =>  {
+                failure = reason;
                 continue;
             },
-            Err(AllowRetry::Disallow) =>  {
+            Err((AllowRetry::Disallow, reason)) =>  {
+                failure = reason;
                 break;
             },
         };
 
+        // Only conditional requests get this, and it means the copy already held is current.
+        if res.status().code == NOT_MODIFIED_CODE {
+            return Ok(RetryResult {
+                retries: retry_cnt,
+                response: res,
+            });
+        }
+
         let status_check = res.check_status()
             .map_err(|e| error::process_fetch_err(e, logging_msg.pre_completion, error::FailSource::Confirm));
         let res = match status_check {
             Ok(res) => res,
-            Err(AllowRetry::Allow) =>  {
+            Err((AllowRetry::Allow, reason)) =>  {
+                failure = reason;
                 continue;
             },
-            Err(AllowRetry::Disallow) =>  {
+            Err((AllowRetry::Disallow, reason)) =>  {
+                failure = reason;
                 break;
             },
         };
@@ -66,7 +86,7 @@ pub async fn fetch_with_retry<'a>(req: Request<'a>, logging_msg: &LogPair<'a>, r
         });
     }
     log::error!("Hit retry limit or abort while {}, force aborting.", logging_msg.pre_completion);
-    Err(())
+    Err(failure)
 }
 
 pub async fn fetch_process_with_retry<'a, 'b, T, FutT, F>(
@@ -74,12 +94,13 @@ pub async fn fetch_process_with_retry<'a, 'b, T, FutT, F>(
     logging_msg: &LogPair<'a>,
     retry_lim: Option<usize>,
     process_res: F
-) -> Result<T, ()>
+) -> Result<T, FailReason>
     where
         FutT: std::future::Future<Output = FetchResult<T>>,
         F: Fn(&Response) -> FutT,
 {
     let retry_lim = retry_lim.unwrap_or(RETRY_LIM);
+    let mut failure = FailReason::Other;
     let mut retry_cnt = 0;
     while retry_cnt < retry_lim {
         if retry_cnt != 0 {
@@ -98,17 +119,74 @@ pub async fn fetch_process_with_retry<'a, 'b, T, FutT, F>(
             .map_err(|e| error::process_fetch_err(e, logging_msg.post_completion, error::FailSource::Parsing));
         let res = match process_attempt {
             Ok(obj) => obj,
-            Err(AllowRetry::Allow) => {
+            Err((AllowRetry::Allow, reason)) => {
+                failure = reason;
                 continue;
             },
-            Err(AllowRetry::Disallow) => {
+            Err((AllowRetry::Disallow, reason)) => {
+                failure = reason;
                 break;
             },
         };
         return Ok(res);
     };
     log::error!("Hit retry limit or abort while {}, force aborting.", logging_msg.pre_completion);
-    Err(())
+    Err(failure)
+}
+
+/// Why the server did not carry out a submitted form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Rejection {
+    /// The server named the fields at fault.
+    Invalid(login_enum::ValidationErrors),
+    /// The server refused because of the state of things, and explained why.
+    Conflict(String),
+    Failed(FailReason),
+}
+
+/// Submits a form, without retrying since the user is around to fix and resend it. Responses
+/// refusing the form are read for what the server said was wrong with it.
+pub async fn fetch_validated<'a>(req: Request<'a>, logging_msg: &LogPair<'a>) -> Result<Response, Rejection> {
+    let res = fetch_conditional(req, logging_msg).await
+        .map_err(|(_, reason)| Rejection::Failed(reason))?;
+    match res.status().code {
+        UNPROCESSABLE_CODE => match res.json().await {
+            Ok(errors) => Err(Rejection::Invalid(errors)),
+            Err(e) => Err(Rejection::Failed(error::process_fetch_err(e, logging_msg.post_completion, error::FailSource::Parsing).1)),
+        },
+        CONFLICT_CODE => match res.text().await {
+            Ok(why) => Err(Rejection::Conflict(why)),
+            Err(e) => Err(Rejection::Failed(error::process_fetch_err(e, logging_msg.post_completion, error::FailSource::Parsing).1)),
+        },
+        _ => res.check_status()
+            .map_err(|e| Rejection::Failed(error::process_fetch_err(e, logging_msg.pre_completion, error::FailSource::Confirm).1)),
+    }
+}
+
+/// Why the server did not carry out a request whose refusals are explained in the body.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Refusal<E> {
+    Explained(E),
+    Failed(FailReason),
+}
+
+/// Sends a request once. Responses with one of the `explained` statuses are read for why the
+/// server refused it.
+pub async fn fetch_explained<'a, E: 'static + serde::de::DeserializeOwned>(
+    req: Request<'a>,
+    logging_msg: &LogPair<'a>,
+    explained: &[u16],
+) -> Result<Response, Refusal<E>> {
+    let res = fetch_conditional(req, logging_msg).await
+        .map_err(|(_, reason)| Refusal::Failed(reason))?;
+    if explained.contains(&res.status().code) {
+        return match res.json().await {
+            Ok(why) => Err(Refusal::Explained(why)),
+            Err(e) => Err(Refusal::Failed(error::process_fetch_err(e, logging_msg.post_completion, error::FailSource::Parsing).1)),
+        };
+    }
+    res.check_status()
+        .map_err(|e| Refusal::Failed(error::process_fetch_err(e, logging_msg.pre_completion, error::FailSource::Confirm).1))
 }
 
 #[deprecated = "Should use `fetch_process_with_retry` once it's bug free."]
@@ -116,8 +194,9 @@ pub async fn fetch_json_with_retry<'a, T: 'static + serde::de::DeserializeOwned>
     req: Request<'a>,
     logging_msg: &LogPair<'a>,
     retry_lim: Option<usize>,
-) -> Result<T, ()> {
+) -> Result<T, FailReason> {
     let retry_lim = retry_lim.unwrap_or(RETRY_LIM);
+    let mut failure = FailReason::Other;
     let mut retry_cnt = 0;
     while retry_cnt < retry_lim {
         if retry_cnt != 0 {
@@ -136,17 +215,19 @@ pub async fn fetch_json_with_retry<'a, T: 'static + serde::de::DeserializeOwned>
             .map_err(|e| error::process_fetch_err(e, logging_msg.post_completion, error::FailSource::Parsing));
         let res = match process_attempt {
             Ok(obj) => obj,
-            Err(AllowRetry::Allow) => {
+            Err((AllowRetry::Allow, reason)) => {
+                failure = reason;
                 continue;
             },
-            Err(AllowRetry::Disallow) => {
+            Err((AllowRetry::Disallow, reason)) => {
+                failure = reason;
                 break;
             },
         };
         return Ok(res);
     };
     log::error!("Hit retry limit or abort while {}, force aborting.", logging_msg.pre_completion);
-    Err(())
+    Err(failure)
 }
 
 #[deprecated = "Should use `fetch_process_with_retry` once it's bug free."]
@@ -154,8 +235,9 @@ pub async fn fetch_text_with_retry<'a>(
     req: Request<'a>,
     logging_msg: &LogPair<'a>,
     retry_lim: Option<usize>,
-) -> Result<String, ()> {
+) -> Result<String, FailReason> {
     let retry_lim = retry_lim.unwrap_or(RETRY_LIM);
+    let mut failure = FailReason::Other;
     let mut retry_cnt = 0;
     while retry_cnt < retry_lim {
         if retry_cnt != 0 {
@@ -174,17 +256,19 @@ pub async fn fetch_text_with_retry<'a>(
             .map_err(|e| error::process_fetch_err(e, logging_msg.post_completion, error::FailSource::Parsing));
         let res = match process_attempt {
             Ok(obj) => obj,
-            Err(AllowRetry::Allow) => {
+            Err((AllowRetry::Allow, reason)) => {
+                failure = reason;
                 continue;
             },
-            Err(AllowRetry::Disallow) => {
+            Err((AllowRetry::Disallow, reason)) => {
+                failure = reason;
                 break;
             },
         };
         return Ok(res);
     };
     log::error!("Hit retry limit or abort while {}, force aborting.", logging_msg.pre_completion);
-    Err(())
+    Err(failure)
 }
 
 #[cfg(test)]
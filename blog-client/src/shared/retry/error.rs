@@ -1,14 +1,21 @@
 use seed::browser::fetch::FetchError;
+use serde::{Deserialize, Serialize};
 
 use super::AllowRetry;
 
+const UNAUTHORIZED_CODE: u16 = 401;
+const FORBIDDEN_CODE: u16 = 403;
+const NOT_FOUND_CODE: u16 = 404;
 const TIME_OUT_CODE: u16 = 408;
 const RESOURCE_CONFLICT_CODE: u16 = 409;
+const PAYLOAD_TOO_LARGE_CODE: u16 = 413;
 const TEAPOT_CODE: u16 = 418;
+const UNPROCESSABLE_CODE: u16 = 422;
 const TOO_EARLY_CODE: u16 = 425;
 const TOO_MANY_CODE: u16 = 429;
 const BAD_GATEWAY_CODE: u16 = 502;
-const SERVICE_UNAVAILABLE_CODE: u16 = 504;
+const SERVICE_UNAVAILABLE_CODE: u16 = 503;
+const GATEWAY_TIMEOUT_CODE: u16 = 504;
 
 struct ErrorReportInfo<T> {
     serde: T,
@@ -32,6 +39,51 @@ impl<T> ErrorReportInfo<T> {
     }
 }
 
+/// Why a request ultimately failed, coarse enough to tell the user what to do about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FailReason {
+    /// The server could not be reached at all.
+    Network,
+    /// The server wants the user to log in (again).
+    Unauthorized,
+    /// The user is logged in, but not allowed to do this.
+    Forbidden,
+    /// The thing asked for does not exist.
+    NotFound,
+    /// Something with the same name, such as a user name, already exists.
+    Conflict,
+    /// The change refers to something that does not exist, such as a deleted user.
+    Invalid,
+    /// What was sent is larger than the server accepts, such as a very long post.
+    TooLarge,
+    /// The server is up, but too busy or cannot reach its database. Worth retrying shortly.
+    Unavailable,
+    /// The server broke, with the given status code.
+    Server(u16),
+    /// Anything else, such as a response that could not be parsed.
+    Other,
+}
+
+impl From<&FetchError> for FailReason {
+    fn from(e: &FetchError) -> Self {
+        match e {
+            FetchError::DomException(_) | FetchError::PromiseError(_) | FetchError::NetworkError(_) => Self::Network,
+            FetchError::StatusError(status) => match status.code {
+                UNAUTHORIZED_CODE => Self::Unauthorized,
+                FORBIDDEN_CODE => Self::Forbidden,
+                NOT_FOUND_CODE => Self::NotFound,
+                RESOURCE_CONFLICT_CODE => Self::Conflict,
+                UNPROCESSABLE_CODE => Self::Invalid,
+                PAYLOAD_TOO_LARGE_CODE => Self::TooLarge,
+                SERVICE_UNAVAILABLE_CODE => Self::Unavailable,
+                code @ 500..=599 => Self::Server(code),
+                _ => Self::Other,
+            },
+            FetchError::SerdeError(_) | FetchError::RequestError(_) => Self::Other,
+        }
+    }
+}
+
 pub(super) enum FailSource {
     Initial,
     Confirm,
@@ -67,7 +119,7 @@ fn log_err<Err: std::fmt::Debug>(e: Err, name: &str, msg: &str, is_possible: boo
     log::error!("Encountered {} {:?} while {}. {}", name, e, msg, continuation_status_msg);
 }
 
-pub(super) fn process_fetch_err(e: FetchError, msg: &str, source: FailSource) -> AllowRetry {
+pub(super) fn process_fetch_err(e: FetchError, msg: &str, source: FailSource) -> (AllowRetry, FailReason) {
     const ERR_POSSIBILITY: ErrorReportInfo<FailSourceVar<bool>> = ErrorReportInfo {
         serde: FailSourceVar { initial: false, confirm: false, parsing: true },
         dom: FailSourceVar { initial: true, confirm: false, parsing: false },
@@ -98,6 +150,7 @@ pub(super) fn process_fetch_err(e: FetchError, msg: &str, source: FailSource) ->
     let is_possible = *ERR_POSSIBILITY.resolve(&e).resolve(&source);
     let can_retry = *RETRY_ALLOWANCE.resolve(&e).resolve(&source);
     let name = *ERROR_NAME.resolve(&e);
+    let reason = FailReason::from(&e);
 
     // Need another match and repetitive calls due to `e` having different types.
     let passes_secondary_check = match e {
@@ -122,13 +175,15 @@ pub(super) fn process_fetch_err(e: FetchError, msg: &str, source: FailSource) ->
             true
         },
         FetchError::StatusError(e) => match e.code {
+            // Not conflicts: the server answers those when a name is taken, which retrying will not
+            // change.
             TIME_OUT_CODE
-            | RESOURCE_CONFLICT_CODE
             | TEAPOT_CODE
             | TOO_EARLY_CODE
             | TOO_MANY_CODE
             | BAD_GATEWAY_CODE
-            | SERVICE_UNAVAILABLE_CODE => {
+            | SERVICE_UNAVAILABLE_CODE
+            | GATEWAY_TIMEOUT_CODE => {
                 log_err(e, name, msg, is_possible, can_retry);
                 true
             },
@@ -139,9 +194,10 @@ pub(super) fn process_fetch_err(e: FetchError, msg: &str, source: FailSource) ->
         },
     };
 
-    if is_possible && can_retry && passes_secondary_check {
+    let allow = if is_possible && can_retry && passes_secondary_check {
         AllowRetry::Allow
     } else {
         AllowRetry::Disallow
-    }
+    };
+    (allow, reason)
 }
\ No newline at end of file
@@ -0,0 +1,21 @@
+use crate::{
+    messages::M as GlobalM,
+    model::StoreOperations as GSOp,
+    requests::{self, api},
+};
+
+/// Finds out how the site presents itself, so that the header and the titles of pages match the
+/// pages the server renders.
+pub async fn load() -> GlobalM {
+    let res = requests::get(requests::Get::new(api::site::IDENTITY.url(&[])))
+        .await
+        .and_then(|res| res.json());
+    match res {
+        Ok(identity) => GlobalM::StoreOp(GSOp::SiteIdentity(identity)),
+        // The site is still usable under the title it is built with.
+        Err(reason) => {
+            log::warn!("Could not find out how the site presents itself: {:?}.", reason);
+            GlobalM::NoOp
+        }
+    }
+}
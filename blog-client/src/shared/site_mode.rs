@@ -0,0 +1,41 @@
+use seed::prelude::*;
+
+use crate::{
+    messages::M as GlobalM,
+    model::StoreOperations as GSOp,
+    requests::{self, api},
+};
+use db_models::models::site;
+
+/// Checks whether the site is read only or under maintenance, so that users are told before their
+/// changes are refused.
+pub async fn load() -> GlobalM {
+    let res = requests::get(requests::Get::new(api::site::STATUS.url(&[])))
+        .await
+        .and_then(|res| res.json());
+    match res {
+        Ok(mode) => GlobalM::StoreOp(GSOp::SiteMode(mode)),
+        // Changes are refused with the message anyways, so the banner can be missed.
+        Err(reason) => {
+            log::warn!("Could not check the mode of the site: {:?}.", reason);
+            GlobalM::NoOp
+        }
+    }
+}
+
+/// Tells the user that the site takes no changes, and why, unless everything works as usual.
+pub fn banner(mode: &site::Mode) -> Option<Node<GlobalM>> {
+    let notice = match mode {
+        site::Mode::Normal => return None,
+        site::Mode::ReadOnly { .. } => tr!("site_mode.read_only"),
+        site::Mode::Maintenance { .. } => tr!("site_mode.maintenance"),
+    };
+    Some(div![
+        attrs! {
+            At::Class => "site-mode-banner";
+            At::from("role") => "status";
+        },
+        strong![notice],
+        mode.message().map(|message| span![" ", message]),
+    ])
+}
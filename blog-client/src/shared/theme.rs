@@ -0,0 +1,53 @@
+use seed::prelude::*;
+
+use crate::messages::M as GlobalM;
+use db_models::models::users::Theme;
+
+/// The local storage key the theme is kept under, for visitors and for before the user's own
+/// preference has loaded.
+const STORAGE_KEY: &str = "theme";
+/// Matches when the browser asks for a light theme.
+const PREFERS_LIGHT: &str = "(prefers-color-scheme: light)";
+
+/// The theme chosen on this browser before, or else the one the browser prefers.
+pub fn initial() -> Theme {
+    if let Ok(theme) = LocalStorage::get(STORAGE_KEY) {
+        return theme;
+    }
+    let prefers_light = seed::window()
+        .match_media(PREFERS_LIGHT)
+        .ok()
+        .flatten()
+        .map_or(false, |query| query.matches());
+    if prefers_light {
+        Theme::Light
+    } else {
+        Theme::Dark
+    }
+}
+
+/// Switches the page over to the theme and remembers it on this browser.
+pub fn apply(theme: Theme) {
+    if let Some(root) = seed::document().document_element() {
+        let _ = root.set_attribute("data-theme", theme.as_str());
+    }
+    if let Err(e) = LocalStorage::insert(STORAGE_KEY, &theme) {
+        log::error!("Failed to keep the theme in local storage: {:?}.", e);
+    }
+}
+
+pub fn toggle(theme: Theme) -> Node<GlobalM> {
+    let other = theme.toggled();
+    let (name, label) = match other {
+        Theme::Light => (tr!("theme.light"), tr!("theme.light_mode")),
+        Theme::Dark => (tr!("theme.dark"), tr!("theme.dark_mode")),
+    };
+    button![
+        attrs! {
+            At::Class => "theme-toggle inline-button";
+            At::Title => tr!("theme.switch", theme = name);
+        },
+        label,
+        ev(Ev::Click, move |_| GlobalM::SetTheme(other)),
+    ]
+}
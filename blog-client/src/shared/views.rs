@@ -1,49 +1,235 @@
-use crate::shared::Authorization;
+use crate::{
+    messages::{UnsavedChoice, M as GlobalM},
+    shared::Authorization,
+};
+use blog_api::Page;
+use login_enum::ValidationErrors;
 use seed::prelude::*;
 
+pub mod skeleton;
+
+/// What is wrong with the field, to show under its input.
+pub fn field_errors<Ms: 'static>(errors: &ValidationErrors, field: &str) -> Node<Ms> {
+    let violations = errors.field(field);
+    if violations.is_empty() {
+        return empty![];
+    }
+    ul![
+        attrs! { At::Class => "field-errors" },
+        violations.iter().map(|v| li![v.message.as_str()]),
+    ]
+}
+
+/// What is wrong with the form as a whole, along with what is wrong with any field not among the
+/// `shown` ones, so that no error goes unseen. Shown above the submit button.
+pub fn form_errors<Ms: 'static>(errors: &ValidationErrors, shown: &[&str]) -> Node<Ms> {
+    let messages: Vec<Node<Ms>> = errors
+        .fields()
+        .filter(|field| !shown.contains(field))
+        .flat_map(|field| errors.field(field))
+        .map(|v| li![v.message.as_str()])
+        .collect();
+    if messages.is_empty() {
+        return empty![];
+    }
+    ul![
+        attrs! {
+            At::Class => "field-errors form-errors";
+            At::from("role") => "alert";
+        },
+        messages,
+    ]
+}
+
 pub fn loading<M: Clone>() -> seed::virtual_dom::Node<M> {
-    p!["Loading!"]
+    p![tr!("common.loading")]
 }
 
-fn nav_menu(is_logged_in: Authorization) -> String {
-    if is_logged_in == Authorization::LoggedIn {
-        htmlgen::data::Menu(&[
-            htmlgen::data::MenuItem {
-                text: "Home",
-                link: Some("/"),
-                children: None,
-            },
-            htmlgen::data::MenuItem {
-                text: "Blog",
-                link: Some("/blog"),
-                children: None,
+/// Marks cached content that is shown while a fresher copy loads.
+pub fn refreshing<M: Clone>() -> Node<M> {
+    p![
+        attrs! {
+            At::Class => "refresh-indicator";
+            At::from("role") => "status";
+        },
+        tr!("common.refreshing"),
+    ]
+}
+
+/// Where `page` is in its listing, between buttons to the pages around it. Each button sends what
+/// `to` makes of the offset of its page. Listings that fit on one page get nothing.
+pub fn pager<T, Ms: 'static>(page: &Page<T>, to: impl Fn(i64) -> Ms + Clone + 'static) -> Node<Ms> {
+    let (prev, next) = (page.prev_offset(), page.next_offset());
+    if prev.is_none() && next.is_none() {
+        return empty![];
+    }
+    let button = |label: String, offset: Option<i64>| match offset {
+        Some(offset) => {
+            let to = to.clone();
+            button![
+                attrs! {
+                    At::Class => "inline-button";
+                    At::Type => "button";
+                },
+                label,
+                ev(Ev::Click, move |e| {
+                    e.prevent_default();
+                    to(offset)
+                }),
+            ]
+        }
+        None => button![
+            attrs! {
+                At::Class => "inline-button";
+                At::Type => "button";
+                At::Disabled => true.as_at_value();
             },
-            htmlgen::data::MenuItem {
-                text: "Create Post",
-                link: Some("/blog/editor/new"),
-                children: None,
+            label,
+        ],
+    };
+    nav![
+        attrs! { At::Class => "pager" },
+        button(tr!("pager.previous"), prev),
+        span![tr!(
+            "pager.position",
+            first = (page.offset + 1).min(page.total),
+            last = (page.offset + page.items.len() as i64).min(page.total),
+            total = page.total,
+        )],
+        button(tr!("pager.next"), next),
+    ]
+}
+
+/// Asks what to do with unsaved edits before leaving the editor.
+pub fn unsaved_changes_dialog() -> Node<GlobalM> {
+    let choice = |label: String, choice: UnsavedChoice| {
+        button![
+            attrs! { At::Class => "inline-button" },
+            label,
+            ev(Ev::Click, move |_| GlobalM::UnsavedChanges(choice)),
+        ]
+    };
+    div![
+        attrs! {
+            At::Class => "dialog-backdrop";
+        },
+        div![
+            attrs! {
+                At::Class => "dialog";
+                At::from("role") => "alertdialog";
+                At::from("aria-modal") => "true";
             },
-            htmlgen::data::MenuItem {
-                text: "Logout",
-                link: Some("/blog/logout"),
-                children: None,
+            p![tr!("unsaved.prompt")],
+            div![
+                attrs! { At::Class => "dialog-actions" },
+                choice(tr!("unsaved.stay"), UnsavedChoice::Stay),
+                choice(tr!("unsaved.discard"), UnsavedChoice::Discard),
+                choice(tr!("unsaved.save_and_go"), UnsavedChoice::SaveAndGo),
+            ],
+        ],
+    ]
+}
+
+/// Selects the modal shown over the page, if any.
+const MODAL: &str = ".dialog[aria-modal]";
+
+/// Puts a question to the user over the rest of the page. `confirm` answers it, while `cancel`
+/// and the Escape key dismiss it.
+pub fn modal<Ms: Clone + 'static>(
+    title: String,
+    content: Vec<Node<Ms>>,
+    (confirm_label, confirm): (String, Ms),
+    cancel: Ms,
+) -> Node<Ms> {
+    let dismiss = cancel.clone();
+    div![
+        attrs! {
+            At::Class => "dialog-backdrop";
+        },
+        div![
+            attrs! {
+                At::Class => "dialog";
+                At::from("role") => "dialog";
+                At::from("aria-modal") => "true";
+                At::from("aria-labelledby") => "dialog-title";
+                At::TabIndex => "-1";
             },
-        ])
+            keyboard_ev(Ev::KeyDown, move |e| {
+                if e.key() == "Escape" {
+                    Some(dismiss)
+                } else {
+                    None
+                }
+            }),
+            h2![attrs! { At::Id => "dialog-title" }, title],
+            content,
+            div![
+                attrs! { At::Class => "dialog-actions" },
+                button![
+                    attrs! { At::Class => "inline-button" },
+                    tr!("common.cancel"),
+                    ev(Ev::Click, move |_| cancel),
+                ],
+                button![
+                    attrs! { At::Class => "inline-button" },
+                    confirm_label,
+                    ev(Ev::Click, move |_| confirm),
+                ],
+            ],
+        ],
+    ]
+}
+
+/// Moves focus into the modal shown, so that keyboard users land on it and Escape reaches it.
+pub fn focus_modal() {
+    if let Some(modal) = seed::document()
+        .query_selector(MODAL)
+        .ok()
+        .flatten()
+        .and_then(|e| e.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = modal.focus();
+    }
+}
+
+/// Tells the user that saving is being retried while the server is out of reach.
+pub fn offline_indicator() -> Node<GlobalM> {
+    div![
+        attrs! {
+            At::Class => "offline-indicator";
+            At::from("role") => "status";
+        },
+        tr!("offline.notice"),
+    ]
+}
+
+/// The links of the menu, each with the key of its text.
+fn nav_links(is_logged_in: Authorization) -> &'static [(&'static str, &'static str)] {
+    if is_logged_in == Authorization::LoggedIn {
+        &[
+            ("menu.home", "/"),
+            ("menu.blog", "/blog"),
+            ("menu.create_post", "/blog/editor/new"),
+            ("menu.settings", "/blog/profile"),
+            ("menu.logout", "/blog/logout"),
+        ]
     } else {
-        htmlgen::data::Menu(&[
-            htmlgen::data::MenuItem {
-                text: "Home",
-                link: Some("/"),
-                children: None,
-            },
-            htmlgen::data::MenuItem {
-                text: "Blog",
-                link: Some("/blog"),
-                children: None,
-            },
-        ])
+        &[
+            ("menu.home", "/"),
+            ("menu.blog", "/blog"),
+            ("menu.login", "/blog/login"),
+        ]
     }
-    .into_string()
+}
+fn nav_menu(is_logged_in: Authorization) -> String {
+    let links = nav_links(is_logged_in);
+    let text: Vec<_> = links.iter().map(|(key, _)| tr!(key)).collect();
+    let items: Vec<_> = links
+        .iter()
+        .zip(text.iter())
+        .map(|((_, link), text)| htmlgen::data::MenuItem::link(text, link))
+        .collect();
+    htmlgen::data::Menu(&items).into_string()
 }
 pub fn replace_nav(is_logged_in: Authorization) {
     let html = nav_menu(is_logged_in);
@@ -56,4 +242,7 @@ pub fn replace_nav(is_logged_in: Authorization) {
     mock_node.set_inner_html(html.as_str());
     let replacement = mock_node.children().item(0).unwrap();
     header_node.replace_child(&replacement, &menu_node).unwrap();
+    if let Ok(path) = seed::window().location().pathname() {
+        super::focus::mark_current_page(&path);
+    }
 }
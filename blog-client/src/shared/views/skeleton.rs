@@ -0,0 +1,26 @@
+//! Placeholders shaped like the content being loaded, so that the page does not jump around once
+//! the content arrives.
+
+use seed::prelude::*;
+
+/// A shimmering block `width` wide and `height` tall, both in any CSS length.
+pub fn block<M: Clone>(width: &str, height: &str) -> Node<M> {
+    div![
+        attrs! {
+            At::Class => "skeleton";
+            At::from("aria-hidden") => "true";
+        },
+        style! {
+            St::Width => width;
+            St::Height => height;
+        },
+    ]
+}
+
+/// `count` lines of text, the last cut short like the end of a paragraph.
+pub fn lines<M: Clone>(count: usize) -> Node<M> {
+    div![
+        attrs! { At::Class => "skeleton-lines" },
+        (0..count).map(|i| block(if i + 1 == count { "60%" } else { "100%" }, "1em")),
+    ]
+}
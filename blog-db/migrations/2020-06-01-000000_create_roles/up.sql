@@ -0,0 +1,54 @@
+CREATE TABLE roles (
+    -- management
+    id uuid NOT NULL UNIQUE PRIMARY KEY,
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    created_by uuid REFERENCES users(id),
+    -- basic info
+    name TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL
+);
+
+CREATE TABLE role_capabilities (
+    -- junction
+    role_id uuid NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    capability TEXT NOT NULL,
+    -- managerial
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    created_by uuid REFERENCES users(id),
+    -- enforce no dupes
+    CONSTRAINT role_capability_pk PRIMARY KEY (role_id, capability)
+);
+
+CREATE TABLE user_roles (
+    -- junction
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role_id uuid NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    -- managerial
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    created_by uuid REFERENCES users(id),
+    -- enforce no dupes
+    CONSTRAINT user_role_pk PRIMARY KEY (user_id, role_id)
+);
+
+-- Default roles. Ids are fixed so that they can be referred to by other scripts.
+INSERT INTO roles (id, name, description) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'admin', 'Full control over the blog, its users, and their capabilities.'),
+    ('00000000-0000-4000-8000-000000000002', 'author', 'Can write, publish, and archive posts.');
+
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'edit_post'),
+    ('00000000-0000-4000-8000-000000000001', 'create_post'),
+    ('00000000-0000-4000-8000-000000000001', 'delete_post'),
+    ('00000000-0000-4000-8000-000000000001', 'publish_post'),
+    ('00000000-0000-4000-8000-000000000001', 'archive_post'),
+    ('00000000-0000-4000-8000-000000000001', 'create_user'),
+    ('00000000-0000-4000-8000-000000000001', 'edit_user'),
+    ('00000000-0000-4000-8000-000000000001', 'delete_user'),
+    ('00000000-0000-4000-8000-000000000001', 'edit_user_credentials'),
+    ('00000000-0000-4000-8000-000000000001', 'grant_capability'),
+    ('00000000-0000-4000-8000-000000000001', 'view_capability'),
+    ('00000000-0000-4000-8000-000000000001', 'delete_capability'),
+    ('00000000-0000-4000-8000-000000000002', 'edit_post'),
+    ('00000000-0000-4000-8000-000000000002', 'create_post'),
+    ('00000000-0000-4000-8000-000000000002', 'publish_post'),
+    ('00000000-0000-4000-8000-000000000002', 'archive_post');
@@ -0,0 +1,4 @@
+DROP INDEX capabilities_expires_at_idx;
+ALTER TABLE capabilities
+    DROP COLUMN scope,
+    DROP COLUMN expires_at;
@@ -0,0 +1,4 @@
+ALTER TABLE capabilities
+    ADD COLUMN expires_at TIMESTAMPTZ,
+    ADD COLUMN scope TEXT;
+CREATE INDEX capabilities_expires_at_idx ON capabilities (expires_at) WHERE expires_at IS NOT NULL;
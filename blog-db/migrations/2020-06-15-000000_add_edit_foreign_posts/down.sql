@@ -0,0 +1,2 @@
+DELETE FROM capabilities WHERE capability = 'edit_foreign_posts';
+DELETE FROM role_capabilities WHERE capability = 'edit_foreign_posts';
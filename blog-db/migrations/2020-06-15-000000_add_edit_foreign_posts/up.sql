@@ -0,0 +1,3 @@
+-- Editing posts created by someone else now needs its own capability. Admins keep the ability.
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'edit_foreign_posts');
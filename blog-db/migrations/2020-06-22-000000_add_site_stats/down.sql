@@ -0,0 +1,4 @@
+DELETE FROM capabilities WHERE capability = 'view_site_stats';
+DELETE FROM role_capabilities WHERE capability = 'view_site_stats';
+
+DROP INDEX posts_created_at_idx;
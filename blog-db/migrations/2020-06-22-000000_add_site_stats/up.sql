@@ -0,0 +1,5 @@
+-- Lets the newest post be found without scanning every post.
+CREATE INDEX posts_created_at_idx ON posts (created_at);
+
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'view_site_stats');
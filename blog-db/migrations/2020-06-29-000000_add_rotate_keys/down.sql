@@ -0,0 +1,2 @@
+DELETE FROM capabilities WHERE capability = 'rotate_keys';
+DELETE FROM role_capabilities WHERE capability = 'rotate_keys';
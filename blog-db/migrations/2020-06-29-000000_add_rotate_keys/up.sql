@@ -0,0 +1,2 @@
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'rotate_keys');
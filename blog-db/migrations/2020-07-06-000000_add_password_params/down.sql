@@ -0,0 +1,4 @@
+ALTER TABLE passwords
+    DROP COLUMN argon2_mem_kib,
+    DROP COLUMN argon2_iterations,
+    DROP COLUMN argon2_parallelism;
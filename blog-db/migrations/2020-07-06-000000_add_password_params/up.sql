@@ -0,0 +1,9 @@
+-- Every existing hash was made with the parameters argon2rs defaults to.
+ALTER TABLE passwords
+    ADD COLUMN argon2_mem_kib INTEGER NOT NULL DEFAULT 4096,
+    ADD COLUMN argon2_iterations INTEGER NOT NULL DEFAULT 3,
+    ADD COLUMN argon2_parallelism INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE passwords
+    ALTER COLUMN argon2_mem_kib DROP DEFAULT,
+    ALTER COLUMN argon2_iterations DROP DEFAULT,
+    ALTER COLUMN argon2_parallelism DROP DEFAULT;
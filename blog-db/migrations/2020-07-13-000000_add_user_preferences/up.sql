@@ -0,0 +1,3 @@
+-- Settings each user chose for how the site looks to them, such as the color theme.
+ALTER TABLE users
+    ADD COLUMN preferences JSONB NOT NULL DEFAULT '{}';
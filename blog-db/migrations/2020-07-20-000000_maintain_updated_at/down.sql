@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS set_updated_at ON users;
+DROP TRIGGER IF EXISTS set_updated_at ON posts;
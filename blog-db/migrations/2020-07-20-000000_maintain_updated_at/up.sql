@@ -0,0 +1,4 @@
+-- Keep `updated_at` current on every update that changes the row, using the trigger from the
+-- initial setup. Updates that leave the row as it was keep the old timestamp.
+SELECT diesel_manage_updated_at('posts');
+SELECT diesel_manage_updated_at('users');
@@ -0,0 +1,71 @@
+-- Fails if any of the columns made nullable were cleared since.
+ALTER TABLE user_roles
+    DROP CONSTRAINT user_roles_created_by_fkey,
+    ADD CONSTRAINT user_roles_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id);
+
+ALTER TABLE role_capabilities
+    DROP CONSTRAINT role_capabilities_created_by_fkey,
+    ADD CONSTRAINT role_capabilities_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id);
+
+ALTER TABLE roles
+    DROP CONSTRAINT roles_created_by_fkey,
+    ADD CONSTRAINT roles_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id);
+
+ALTER TABLE users
+    DROP CONSTRAINT users_created_by_fkey,
+    DROP CONSTRAINT users_updated_by_fkey,
+    ADD CONSTRAINT users_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id),
+    ADD CONSTRAINT users_updated_by_fkey FOREIGN KEY (updated_by) REFERENCES users(id);
+
+ALTER TABLE tags
+    DROP CONSTRAINT tags_created_by_fkey,
+    ADD CONSTRAINT tags_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id),
+    ALTER COLUMN created_by SET NOT NULL;
+
+ALTER TABLE posts
+    DROP CONSTRAINT posts_created_by_fkey,
+    DROP CONSTRAINT posts_updated_by_fkey,
+    DROP CONSTRAINT posts_published_by_fkey,
+    DROP CONSTRAINT posts_archived_by_fkey,
+    DROP CONSTRAINT posts_deleted_by_fkey,
+    ADD CONSTRAINT posts_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id),
+    ADD CONSTRAINT posts_updated_by_fkey FOREIGN KEY (updated_by) REFERENCES users(id),
+    ADD CONSTRAINT posts_published_by_fkey FOREIGN KEY (published_by) REFERENCES users(id),
+    ADD CONSTRAINT posts_archived_by_fkey FOREIGN KEY (archived_by) REFERENCES users(id),
+    ADD CONSTRAINT posts_deleted_by_fkey FOREIGN KEY (deleted_by) REFERENCES users(id),
+    ALTER COLUMN updated_by SET NOT NULL;
+
+ALTER TABLE post_tag_junctions
+    DROP CONSTRAINT post_tag_junctions_post_id_fkey,
+    DROP CONSTRAINT post_tag_junctions_tag_id_fkey,
+    DROP CONSTRAINT post_tag_junctions_created_by_fkey,
+    ADD CONSTRAINT post_tag_junctions_post_id_fkey FOREIGN KEY (post_id) REFERENCES posts(id),
+    ADD CONSTRAINT post_tag_junctions_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES tags(id),
+    ADD CONSTRAINT post_tag_junctions_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id),
+    ALTER COLUMN created_by SET NOT NULL;
+
+ALTER TABLE google_sso
+    DROP CONSTRAINT google_sso_user_id_fkey,
+    DROP CONSTRAINT google_sso_created_by_fkey,
+    DROP CONSTRAINT google_sso_updated_by_fkey,
+    ADD CONSTRAINT google_sso_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id),
+    ADD CONSTRAINT google_sso_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id),
+    ADD CONSTRAINT google_sso_updated_by_fkey FOREIGN KEY (updated_by) REFERENCES users(id),
+    ALTER COLUMN created_by SET NOT NULL,
+    ALTER COLUMN updated_by SET NOT NULL;
+
+ALTER TABLE passwords
+    DROP CONSTRAINT passwords_user_id_fkey,
+    DROP CONSTRAINT passwords_created_by_fkey,
+    DROP CONSTRAINT passwords_updated_by_fkey,
+    ADD CONSTRAINT passwords_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id),
+    ADD CONSTRAINT passwords_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id),
+    ADD CONSTRAINT passwords_updated_by_fkey FOREIGN KEY (updated_by) REFERENCES users(id),
+    ALTER COLUMN created_by SET NOT NULL,
+    ALTER COLUMN updated_by SET NOT NULL;
+
+ALTER TABLE capabilities
+    DROP CONSTRAINT capabilities_user_id_fkey,
+    DROP CONSTRAINT capabilities_created_by_fkey,
+    ADD CONSTRAINT permissions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id),
+    ADD CONSTRAINT permissions_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id);
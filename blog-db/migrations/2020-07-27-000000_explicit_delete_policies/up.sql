@@ -0,0 +1,100 @@
+-- What happens to rows that refer to a deleted row:
+-- * Credentials, capabilities, and post to tag junctions are deleted along with what they belong
+--   to.
+-- * Posts keep their author, so users with posts cannot be deleted.
+-- * Every other record of who did something is cleared, so that deleting a user who once edited,
+--   published, or granted something is not refused.
+
+-- Capabilities kept the constraint names from when the table was called permissions.
+ALTER TABLE capabilities
+    DROP CONSTRAINT permissions_user_id_fkey,
+    DROP CONSTRAINT permissions_created_by_fkey,
+    ADD CONSTRAINT capabilities_user_id_fkey
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    ADD CONSTRAINT capabilities_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE passwords
+    ALTER COLUMN created_by DROP NOT NULL,
+    ALTER COLUMN updated_by DROP NOT NULL,
+    DROP CONSTRAINT passwords_user_id_fkey,
+    DROP CONSTRAINT passwords_created_by_fkey,
+    DROP CONSTRAINT passwords_updated_by_fkey,
+    ADD CONSTRAINT passwords_user_id_fkey
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    ADD CONSTRAINT passwords_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT passwords_updated_by_fkey
+        FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE google_sso
+    ALTER COLUMN created_by DROP NOT NULL,
+    ALTER COLUMN updated_by DROP NOT NULL,
+    DROP CONSTRAINT google_sso_user_id_fkey,
+    DROP CONSTRAINT google_sso_created_by_fkey,
+    DROP CONSTRAINT google_sso_updated_by_fkey,
+    ADD CONSTRAINT google_sso_user_id_fkey
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    ADD CONSTRAINT google_sso_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT google_sso_updated_by_fkey
+        FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE post_tag_junctions
+    ALTER COLUMN created_by DROP NOT NULL,
+    DROP CONSTRAINT post_tag_junctions_post_id_fkey,
+    DROP CONSTRAINT post_tag_junctions_tag_id_fkey,
+    DROP CONSTRAINT post_tag_junctions_created_by_fkey,
+    ADD CONSTRAINT post_tag_junctions_post_id_fkey
+        FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE,
+    ADD CONSTRAINT post_tag_junctions_tag_id_fkey
+        FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+    ADD CONSTRAINT post_tag_junctions_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE posts
+    ALTER COLUMN updated_by DROP NOT NULL,
+    DROP CONSTRAINT posts_created_by_fkey,
+    DROP CONSTRAINT posts_updated_by_fkey,
+    DROP CONSTRAINT posts_published_by_fkey,
+    DROP CONSTRAINT posts_archived_by_fkey,
+    DROP CONSTRAINT posts_deleted_by_fkey,
+    ADD CONSTRAINT posts_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE RESTRICT,
+    ADD CONSTRAINT posts_updated_by_fkey
+        FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT posts_published_by_fkey
+        FOREIGN KEY (published_by) REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT posts_archived_by_fkey
+        FOREIGN KEY (archived_by) REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT posts_deleted_by_fkey
+        FOREIGN KEY (deleted_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE tags
+    ALTER COLUMN created_by DROP NOT NULL,
+    DROP CONSTRAINT tags_created_by_fkey,
+    ADD CONSTRAINT tags_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE users
+    DROP CONSTRAINT users_created_by_fkey,
+    DROP CONSTRAINT users_updated_by_fkey,
+    ADD CONSTRAINT users_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT users_updated_by_fkey
+        FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE roles
+    DROP CONSTRAINT roles_created_by_fkey,
+    ADD CONSTRAINT roles_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE role_capabilities
+    DROP CONSTRAINT role_capabilities_created_by_fkey,
+    ADD CONSTRAINT role_capabilities_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE user_roles
+    DROP CONSTRAINT user_roles_created_by_fkey,
+    ADD CONSTRAINT user_roles_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,3 @@
+DELETE FROM capabilities WHERE capability = 'change_site_mode';
+DELETE FROM role_capabilities WHERE capability = 'change_site_mode';
+DROP TABLE site_settings;
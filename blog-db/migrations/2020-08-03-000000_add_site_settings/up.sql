@@ -0,0 +1,13 @@
+-- Settings of the site as a whole. There is only ever the one row, which `id` enforces.
+CREATE TABLE site_settings (
+    id BOOLEAN NOT NULL PRIMARY KEY DEFAULT TRUE CHECK (id),
+    -- Whether the site takes changes. See `blog_db::models::site::Mode`.
+    mode JSONB NOT NULL DEFAULT '{"mode": "normal"}',
+    updated_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    updated_by uuid REFERENCES users(id) ON DELETE SET NULL
+);
+INSERT INTO site_settings DEFAULT VALUES;
+SELECT diesel_manage_updated_at('site_settings');
+
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'change_site_mode');
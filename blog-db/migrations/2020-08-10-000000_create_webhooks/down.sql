@@ -0,0 +1,4 @@
+DELETE FROM capabilities WHERE capability = 'manage_webhooks';
+DELETE FROM role_capabilities WHERE capability = 'manage_webhooks';
+DROP TABLE webhook_dead_letters;
+DROP TABLE webhooks;
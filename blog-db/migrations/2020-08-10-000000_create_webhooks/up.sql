@@ -0,0 +1,31 @@
+-- Urls pinged when things happen on the site, such as a post being published.
+CREATE TABLE webhooks (
+    id uuid NOT NULL PRIMARY KEY,
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    created_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    updated_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    updated_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    url TEXT NOT NULL,
+    -- Deliveries are signed with this, so that receivers can tell they came from the site.
+    secret TEXT NOT NULL,
+    -- The events delivered, one bit each. See `blog_db::models::webhooks::Event`.
+    event_mask INTEGER NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE
+);
+SELECT diesel_manage_updated_at('webhooks');
+
+-- Deliveries that failed for good, kept so that admins can see what was missed.
+CREATE TABLE webhook_dead_letters (
+    id uuid NOT NULL PRIMARY KEY,
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    webhook_id uuid NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    -- The status of the last attempt, if the webhook answered at all.
+    last_status INTEGER,
+    error TEXT NOT NULL
+);
+
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'manage_webhooks');
@@ -0,0 +1,20 @@
+-- Who wrote each post, in the order of its byline. `posts.created_by` stays the owner of the
+-- record, and is credited as the only author of every post written before co-authors existed.
+CREATE TABLE post_authors (
+    -- junction
+    post_id uuid NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- See `blog_db::models::post_authors::Role`.
+    role TEXT NOT NULL CHECK (role IN ('author', 'editor')),
+    -- Where in the byline the user is listed, starting from 0.
+    position INTEGER NOT NULL,
+    -- managerial
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    created_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    -- enforce no dupes
+    CONSTRAINT post_authors_pk PRIMARY KEY (post_id, user_id)
+);
+CREATE INDEX post_authors_user_id_idx ON post_authors (user_id);
+
+INSERT INTO post_authors (post_id, user_id, role, position, created_by)
+    SELECT id, created_by, 'author', 0, created_by FROM posts;
@@ -0,0 +1,3 @@
+DROP TABLE series_posts;
+DROP FUNCTION series_posts_close_gap();
+DROP TABLE series;
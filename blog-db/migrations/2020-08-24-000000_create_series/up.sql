@@ -0,0 +1,39 @@
+-- Posts read in order, such as the parts of a multi-part write up.
+CREATE TABLE series (
+    id uuid NOT NULL PRIMARY KEY,
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    created_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    updated_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    updated_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    title TEXT NOT NULL,
+    slug TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT ''
+);
+SELECT diesel_manage_updated_at('series');
+
+CREATE TABLE series_posts (
+    -- junction
+    series_id uuid NOT NULL REFERENCES series(id) ON DELETE CASCADE,
+    post_id uuid NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    -- Where in the series the post is, starting from 0, with no gaps.
+    position INTEGER NOT NULL,
+    -- managerial
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    created_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    -- enforce no dupes
+    CONSTRAINT series_posts_pk PRIMARY KEY (series_id, post_id),
+    -- Checked at commit, so that parts can be moved past each other.
+    CONSTRAINT series_posts_position_unique UNIQUE (series_id, position) DEFERRABLE INITIALLY DEFERRED
+);
+CREATE INDEX series_posts_post_id_idx ON series_posts (post_id);
+
+-- Moves the parts after a removed part up, so that positions stay dense whatever removed it.
+CREATE FUNCTION series_posts_close_gap() RETURNS trigger AS $$
+BEGIN
+    UPDATE series_posts SET position = position - 1
+        WHERE series_id = OLD.series_id AND position > OLD.position;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER series_posts_close_gap AFTER DELETE ON series_posts
+    FOR EACH ROW EXECUTE PROCEDURE series_posts_close_gap();
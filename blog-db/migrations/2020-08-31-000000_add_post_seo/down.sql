@@ -0,0 +1,3 @@
+ALTER TABLE posts
+    DROP COLUMN social_image,
+    DROP COLUMN meta_description;
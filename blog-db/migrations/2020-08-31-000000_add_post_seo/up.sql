@@ -0,0 +1,4 @@
+-- What a post is described with where it is linked to. Left empty, they are derived from the post.
+ALTER TABLE posts
+    ADD COLUMN meta_description TEXT NOT NULL DEFAULT '',
+    ADD COLUMN social_image TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,4 @@
+DELETE FROM capabilities WHERE capability = 'run_retention';
+DELETE FROM role_capabilities WHERE capability = 'run_retention';
+DROP INDEX webhook_dead_letters_created_at_idx;
+DROP INDEX posts_deleted_at_idx;
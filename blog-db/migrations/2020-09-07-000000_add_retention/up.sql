@@ -0,0 +1,6 @@
+-- Lets the retention job find what it removes without scanning every row.
+CREATE INDEX posts_deleted_at_idx ON posts (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX webhook_dead_letters_created_at_idx ON webhook_dead_letters (created_at);
+
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'run_retention');
@@ -0,0 +1,3 @@
+DELETE FROM capabilities WHERE capability = 'change_site_identity';
+DELETE FROM role_capabilities WHERE capability = 'change_site_identity';
+ALTER TABLE site_settings DROP COLUMN identity;
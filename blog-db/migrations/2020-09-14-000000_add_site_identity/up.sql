@@ -0,0 +1,6 @@
+-- How admins had the site present itself, or null to go by the configuration of the server. See
+-- `blog_db::models::site::Identity`.
+ALTER TABLE site_settings ADD COLUMN identity JSONB;
+
+INSERT INTO role_capabilities (role_id, capability) VALUES
+    ('00000000-0000-4000-8000-000000000001', 'change_site_identity');
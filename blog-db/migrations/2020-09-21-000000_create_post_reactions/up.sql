@@ -0,0 +1,12 @@
+-- The reactions left on each post, once per kind by each reader.
+CREATE TABLE post_reactions (
+    post_id uuid NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    -- See `blog_db::models::reactions::Kind`.
+    kind TEXT NOT NULL CHECK (kind IN ('like', 'heart', 'rocket', 'eyes')),
+    -- Who reacted, hashed so that neither users nor sessions can be read back out of it.
+    actor TEXT NOT NULL,
+    -- managerial
+    created_at timestamp with time zone NOT NULL DEFAULT (now() at time zone 'utc'),
+    -- enforce no dupes
+    CONSTRAINT post_reactions_pk PRIMARY KEY (post_id, kind, actor)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE google_sso DROP COLUMN last_used_at;
+ALTER TABLE passwords DROP COLUMN last_used_at;
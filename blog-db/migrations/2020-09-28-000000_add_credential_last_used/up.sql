@@ -0,0 +1,4 @@
+-- When each credential was last signed in with, shown to its owner so that they can tell which
+-- they still use. Null until the first sign in after this migration.
+ALTER TABLE passwords ADD COLUMN last_used_at TIMESTAMPTZ;
+ALTER TABLE google_sso ADD COLUMN last_used_at TIMESTAMPTZ;
@@ -0,0 +1,361 @@
+//! Deterministic data for development databases and tests: an admin, a couple of authors, a set
+//! of tags, and posts in every state. Ids are fixed, so that fixtures can be linked to and looked
+//! up by tests.
+
+use crate::{models::*, query::*};
+
+/// The user name of the fixture admin.
+pub const ADMIN_USER_NAME: &str = "admin";
+/// The password of the fixture admin.
+pub const ADMIN_PASSWORD: &str = "admin-password";
+/// The password of every other fixture user.
+pub const USER_PASSWORD: &str = "user-password";
+/// How many fixture posts there are in each [`PostState`].
+pub const POSTS_PER_STATE: usize = 5;
+
+/// The role granted to the fixture admin. Created by the migrations.
+const ADMIN_ROLE: &str = "admin";
+/// The role granted to every other fixture user. Created by the migrations.
+const AUTHOR_ROLE: &str = "author";
+
+/// A fixed id, told apart from ids generated for real records by its leading `f`s. `kind` keeps
+/// the ids of different tables apart.
+fn fixture_id(kind: u16, n: usize) -> uuid::Uuid {
+    uuid::Uuid::from_u128(
+        0xffff_ffff_0000_4000_8000_0000_0000_0000 | (u128::from(kind) << 80) | n as u128,
+    )
+}
+
+/// A fixture user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub id: uuid::Uuid,
+    pub user_name: &'static str,
+    pub first_name: &'static str,
+    pub last_name: &'static str,
+    pub password: &'static str,
+    /// Whether the user holds the admin role rather than the author role.
+    pub is_admin: bool,
+}
+impl User {
+    /// The email of the user, at a domain reserved for examples.
+    pub fn email(&self) -> String {
+        format!("{}@example.com", self.user_name)
+    }
+}
+
+/// Every fixture user. The admin comes first.
+pub fn users() -> Vec<User> {
+    let user = |n, user_name, first_name, last_name, password, is_admin| User {
+        id: fixture_id(1, n),
+        user_name,
+        first_name,
+        last_name,
+        password,
+        is_admin,
+    };
+    vec![
+        user(0, ADMIN_USER_NAME, "Ada", "Admin", ADMIN_PASSWORD, true),
+        user(1, "alice", "Alice", "Author", USER_PASSWORD, false),
+        user(2, "bob", "Bob", "Blogger", USER_PASSWORD, false),
+    ]
+}
+
+/// A fixture tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub id: uuid::Uuid,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Every fixture tag.
+pub fn tags() -> Vec<Tag> {
+    [
+        ("rust", "Posts about the Rust programming language."),
+        ("web", "Posts about building for the web."),
+        ("databases", "Posts about storing things."),
+        ("meta", "Posts about this blog."),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(n, (name, description))| Tag {
+        id: fixture_id(2, n),
+        name,
+        description,
+    })
+    .collect()
+}
+
+/// The state a fixture post is left in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PostState {
+    Draft,
+    Published,
+    Archived,
+    Deleted,
+}
+impl PostState {
+    pub const ALL: [PostState; 4] = [
+        PostState::Draft,
+        PostState::Published,
+        PostState::Archived,
+        PostState::Deleted,
+    ];
+}
+
+/// A fixture post.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Post {
+    pub id: uuid::Uuid,
+    /// The fixture user who wrote the post, and who moved it into its state.
+    pub author: uuid::Uuid,
+    pub title: String,
+    pub body: String,
+    pub slug: String,
+    pub state: PostState,
+    /// The names of the fixture tags on the post.
+    pub tags: Vec<&'static str>,
+}
+
+/// Every fixture post, [`POSTS_PER_STATE`] in each state, spread across the fixture users and
+/// tags.
+pub fn posts() -> Vec<Post> {
+    let users = users();
+    let tags = tags();
+    (0..PostState::ALL.len() * POSTS_PER_STATE)
+        .map(|n| {
+            let state = PostState::ALL[n % PostState::ALL.len()];
+            let tag_count = n % tags.len();
+            Post {
+                id: fixture_id(3, n),
+                author: users[n % users.len()].id,
+                title: format!("Fixture post {}", n + 1),
+                body: format!(
+                    "# Fixture post {}\n\nThis post was {:?} when seeded.\n\n```rust\nfn main() \
+                     {{\n    println!(\"Post {}\");\n}}\n```\n",
+                    n + 1,
+                    state,
+                    n + 1
+                ),
+                slug: format!("fixture-post-{}", n + 1),
+                state,
+                tags: tags
+                    .iter()
+                    .cycle()
+                    .skip(n)
+                    .take(tag_count)
+                    .map(|tag| tag.name)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// A password hashed the way the server stores it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashedPassword {
+    pub hash: String,
+    pub salt: String,
+    pub argon2_mem_kib: i32,
+    pub argon2_iterations: i32,
+    pub argon2_parallelism: i32,
+}
+
+/// Why seeding did not happen.
+#[derive(Debug)]
+pub enum SeedError {
+    /// There are already this many users, and seeding was not forced.
+    NotEmpty(i64),
+    Query(Error),
+}
+impl std::fmt::Display for SeedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotEmpty(count) => write!(
+                f,
+                "the database already has {} users; pass --force to seed anyway",
+                count
+            ),
+            Self::Query(e) => write!(f, "could not insert the fixtures: {}", e),
+        }
+    }
+}
+impl From<Error> for SeedError {
+    fn from(e: Error) -> Self {
+        Self::Query(e)
+    }
+}
+impl From<diesel::result::Error> for SeedError {
+    fn from(e: diesel::result::Error) -> Self {
+        Self::Query(e.into())
+    }
+}
+
+/// Inserts every fixture in a single transaction, through the same queries the handlers use.
+/// `hash_password` hashes the password of each user. Refuses to seed databases that already have
+/// users unless `force`d, and fails without inserting anything if a fixture is already present.
+pub fn seed<C: DBConn>(
+    db: &C,
+    force: bool,
+    mut hash_password: impl FnMut(&str) -> HashedPassword,
+) -> Result<(), SeedError> {
+    let existing = db.count_users()?;
+    if existing != 0 && !force {
+        return Err(SeedError::NotEmpty(existing));
+    }
+    db.transaction(|tx| {
+        let fixture_users = users();
+        let admin = fixture_users[0].id;
+        let admin_role = tx.find_role_by_name(ADMIN_ROLE)?.id;
+        let author_role = tx.find_role_by_name(AUTHOR_ROLE)?.id;
+        for user in fixture_users.iter() {
+            let email = user.email();
+            let creator = if user.is_admin { None } else { Some(admin) };
+            tx.create_user(users::NewWithId::with_id(
+                user.id,
+                users::New {
+                    user_name: user.user_name,
+                    created_by: creator,
+                    updated_by: creator,
+                    first_name: user.first_name,
+                    last_name: user.last_name,
+                    email: email.as_str(),
+                },
+            ))?;
+            let hashed = hash_password(user.password);
+            tx.create_pw_hash(credentials::pw::New {
+                created_by: user.id,
+                updated_by: user.id,
+                user_id: user.id,
+                hash: hashed.hash.as_str(),
+                salt: hashed.salt.as_str(),
+                argon2_mem_kib: hashed.argon2_mem_kib,
+                argon2_iterations: hashed.argon2_iterations,
+                argon2_parallelism: hashed.argon2_parallelism,
+            })?;
+            tx.grant_role_to_user(user_roles::New {
+                user_id: user.id,
+                role_id: if user.is_admin { admin_role } else { author_role },
+                created_by: admin,
+            })?;
+        }
+        for tag in tags() {
+            tx.create_tag(tags::NewWithId::with_id(
+                tag.id,
+                tags::New {
+                    name: tag.name,
+                    description: tag.description,
+                    created_by: admin,
+                },
+            ))?;
+        }
+        for post in posts() {
+            let new = posts::NewNoMeta {
+                slug: Some(post.slug.clone()),
+                ..posts::NewNoMeta::new_with_no_flags(post.title.clone(), post.body.clone())
+            };
+            tx.insert_post(posts::NewWithId::with_id(
+                post.id,
+                (&new, post.author).into(),
+            ))?;
+            tx.replace_post_tags(post.id, &post.tags, post.author)?;
+            match post.state {
+                PostState::Draft => 0,
+                PostState::Published => {
+                    tx.publish_post_with_id(post.id, posts::Publishing::new(post.author))?
+                }
+                PostState::Archived => {
+                    tx.publish_post_with_id(post.id, posts::Publishing::new(post.author))?;
+                    tx.archive_post_with_id(post.id, posts::Archival::new(post.author))?
+                }
+                PostState::Deleted => {
+                    tx.delete_post_with_id(post.id, &posts::Deletion::new(post.author))?
+                }
+            };
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn fixture_ids_are_unique_and_stable() {
+        let ids: Vec<_> = users()
+            .into_iter()
+            .map(|user| user.id)
+            .chain(tags().into_iter().map(|tag| tag.id))
+            .chain(posts().into_iter().map(|post| post.id))
+            .collect();
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+        assert_eq!(users()[0].user_name, ADMIN_USER_NAME);
+        assert_eq!(
+            users()[0].id.to_string(),
+            "ffffffff-0001-4000-8000-000000000000"
+        );
+        assert_eq!(posts(), posts());
+    }
+
+    #[test]
+    fn every_state_has_its_share_of_posts() {
+        let posts = posts();
+        for state in PostState::ALL.iter() {
+            let count = posts.iter().filter(|post| post.state == *state).count();
+            assert_eq!(count, POSTS_PER_STATE, "{:?}", state);
+        }
+        let tag_names: HashSet<_> = tags().into_iter().map(|tag| tag.name).collect();
+        assert!(posts
+            .iter()
+            .flat_map(|post| post.tags.iter())
+            .all(|name| tag_names.contains(name)));
+    }
+
+    struct TestConn(diesel::PgConnection);
+    impl DBConn for TestConn {
+        fn conn(&self) -> &diesel::PgConnection {
+            &self.0
+        }
+    }
+
+    fn unhashed(password: &str) -> HashedPassword {
+        HashedPassword {
+            hash: password.to_owned(),
+            salt: String::new(),
+            argon2_mem_kib: 0,
+            argon2_iterations: 0,
+            argon2_parallelism: 0,
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn seeding_fills_every_state_once() {
+        use diesel::Connection;
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for db tests.");
+        let conn =
+            diesel::PgConnection::establish(url.as_str()).expect("Failed to connect to database.");
+        conn.begin_test_transaction()
+            .expect("Failed to begin test transaction.");
+        let db = TestConn(conn);
+        let before = db.get_site_stats().unwrap();
+        seed(&db, true, unhashed).unwrap();
+        let after = db.get_site_stats().unwrap();
+        assert_eq!(after.users, before.users + users().len() as i64);
+        let added = POSTS_PER_STATE as i64;
+        assert_eq!(after.draft_posts, before.draft_posts + added);
+        assert_eq!(after.published_posts, before.published_posts + added);
+        assert_eq!(after.archived_posts, before.archived_posts + added);
+        assert_eq!(after.deleted_posts, before.deleted_posts + added);
+        let admin = db.find_user_by_user_name(ADMIN_USER_NAME).unwrap();
+        assert_eq!(admin.id, users()[0].id);
+
+        match seed(&db, false, unhashed) {
+            Err(SeedError::NotEmpty(count)) => assert_eq!(count, after.users),
+            res => panic!("Expected seeding a full database to be refused, got {:?}.", res),
+        }
+    }
+}
@@ -1,5 +1,3 @@
-#![feature(type_ascription)]
-
 //! A collection of types and migrations for use with diesel and postgresql specifically for my
 //! website.
 
@@ -14,8 +12,19 @@ extern crate diesel;
 pub mod schema;
 
 #[cfg(feature = "client")]
-pub use models::{capabilities, credentials, post_tag_junctions, posts, tags, users};
+pub use models::{
+    capabilities, credentials, page, post_authors, post_tag_junctions, posts, reactions,
+    role_capabilities, roles, series, site, stats, tags, user_roles, users, webhooks,
+};
 
+#[cfg(feature = "server")]
+#[macro_use]
+extern crate diesel_migrations;
+/// The `migrations` folder, embedded for the server to apply.
+#[cfg(feature = "server")]
+pub mod migrations;
+#[cfg(feature = "server")]
+pub mod fixtures;
 #[cfg(feature = "server")]
 pub mod query;
 #[cfg(feature = "server")]
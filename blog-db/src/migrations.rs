@@ -0,0 +1,190 @@
+//! The migrations in the `migrations` folder, embedded so that the server can bring the database
+//! up to date by itself.
+
+use diesel::{pg::PgConnection, result::Error as DieselError};
+use diesel_migrations::{MigrationConnection, RunMigrationsError};
+use std::{io, time::Instant};
+
+embed_migrations!();
+
+/// The version of the newest embedded migration. Databases migrated past it were migrated by a
+/// newer binary, and may not have the schema this one expects.
+pub const LATEST_MIGRATION: &str = "20200928000000";
+
+/// What kept the database from being migrated.
+#[derive(Debug)]
+pub enum Error {
+    /// The database has a migration newer than [`LATEST_MIGRATION`].
+    SchemaAhead { database: String },
+    /// The database is missing migrations up to [`LATEST_MIGRATION`], if it was migrated at all.
+    SchemaBehind { database: Option<String> },
+    /// The applied migrations could not be read.
+    Query(DieselError),
+    /// A migration failed. Its changes were rolled back.
+    Migration(RunMigrationsError),
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::SchemaAhead { database } => write!(
+                f,
+                "database schema is at migration {}, but this binary only knows up to {}",
+                database, LATEST_MIGRATION
+            ),
+            Self::SchemaBehind { database } => write!(
+                f,
+                "database schema is at migration {}, but this binary expects {}; migrate it first",
+                database.as_deref().unwrap_or("none"),
+                LATEST_MIGRATION
+            ),
+            Self::Query(e) => write!(f, "could not read the applied migrations: {}", e),
+            Self::Migration(e) => write!(f, "migration failed: {}", e),
+        }
+    }
+}
+impl From<DieselError> for Error {
+    fn from(e: DieselError) -> Self {
+        Self::Query(e)
+    }
+}
+impl From<RunMigrationsError> for Error {
+    fn from(e: RunMigrationsError) -> Self {
+        Self::Migration(e)
+    }
+}
+
+/// Fails if the database was migrated past [`LATEST_MIGRATION`]. Databases that were never
+/// migrated pass.
+pub fn check_schema_version(conn: &PgConnection) -> Result<(), Error> {
+    conn.setup()?;
+    match conn.latest_run_migration_version()? {
+        Some(database) if database.as_str() > LATEST_MIGRATION => {
+            Err(Error::SchemaAhead { database })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Fails unless the database was migrated exactly up to [`LATEST_MIGRATION`], for tools that
+/// change the database without migrating it first.
+pub fn check_schema_current(conn: &PgConnection) -> Result<(), Error> {
+    check_schema_version(conn)?;
+    match conn.latest_run_migration_version()? {
+        Some(database) if database.as_str() == LATEST_MIGRATION => Ok(()),
+        database => Err(Error::SchemaBehind { database }),
+    }
+}
+
+/// Applies every embedded migration the database has not seen yet, each in its own transaction,
+/// after making sure the database is not ahead of this binary. Returns the versions applied.
+pub fn run_pending_migrations(conn: &PgConnection) -> Result<Vec<String>, Error> {
+    check_schema_version(conn)?;
+    let started = Instant::now();
+    let mut out = AppliedLog::default();
+    embedded_migrations::run_with_output(conn, &mut out)?;
+    log::info!(
+        "Applied {} migrations in {:?}.",
+        out.applied.len(),
+        started.elapsed()
+    );
+    Ok(out.applied)
+}
+
+/// Logs the migrations diesel reports running as they start, and remembers their versions.
+#[derive(Default)]
+struct AppliedLog {
+    line: Vec<u8>,
+    applied: Vec<String>,
+}
+impl io::Write for AppliedLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in buf {
+            if *byte != b'\n' {
+                self.line.push(*byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).into_owned();
+            self.line.clear();
+            if let Some(version) = line.strip_prefix("Running migration ") {
+                log::info!("Applying migration {}...", version);
+                self.applied.push(version.to_owned());
+            }
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! These run against the database pointed to by `DATABASE_URL`, inside a scratch schema that
+    //! is never committed. Run with `cargo test --features server -- --ignored`.
+
+    use super::*;
+    use diesel::{connection::SimpleConnection, Connection};
+
+    fn scratch() -> PgConnection {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for db tests.");
+        let conn = PgConnection::establish(url.as_str()).expect("Failed to connect to database.");
+        conn.begin_test_transaction()
+            .expect("Failed to begin test transaction.");
+        conn.batch_execute(
+            "CREATE SCHEMA scratch_migrations; SET LOCAL search_path TO scratch_migrations;",
+        )
+        .expect("Failed to create scratch schema.");
+        conn
+    }
+
+    #[test]
+    fn latest_migration_matches_migrations_folder() {
+        let folder = concat!(env!("CARGO_MANIFEST_DIR"), "/migrations");
+        let latest = std::fs::read_dir(folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .map(|name| {
+                let stamp = name.split('_').next().unwrap();
+                stamp.replace('-', "")
+            })
+            .max()
+            .unwrap();
+        assert_eq!(latest, LATEST_MIGRATION);
+    }
+
+    #[test]
+    #[ignore]
+    fn rerunning_migrations_applies_nothing() {
+        let conn = scratch();
+        let applied = run_pending_migrations(&conn).unwrap();
+        assert_eq!(applied.last().map(String::as_str), Some(LATEST_MIGRATION));
+        assert_eq!(run_pending_migrations(&conn).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    #[ignore]
+    fn newer_schemas_are_refused() {
+        let conn = scratch();
+        run_pending_migrations(&conn).unwrap();
+        conn.batch_execute(
+            "INSERT INTO __diesel_schema_migrations (version) VALUES ('99991231000000');",
+        )
+        .unwrap();
+        match run_pending_migrations(&conn) {
+            Err(Error::SchemaAhead { database }) => assert_eq!(database, "99991231000000"),
+            res => panic!("Expected the newer schema to be refused, got {:?}.", res),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn only_current_schemas_are_current() {
+        let conn = scratch();
+        match check_schema_current(&conn) {
+            Err(Error::SchemaBehind { database: None }) => {}
+            res => panic!("Expected the empty schema to be behind, got {:?}.", res),
+        }
+        run_pending_migrations(&conn).unwrap();
+        check_schema_current(&conn).unwrap();
+    }
+}
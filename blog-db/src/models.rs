@@ -4,7 +4,21 @@
 
 pub mod capabilities;
 pub mod credentials;
+pub mod page;
+pub mod post_authors;
 pub mod post_tag_junctions;
 pub mod posts;
+pub mod reactions;
+pub mod role_capabilities;
+pub mod roles;
+pub mod series;
+pub mod site;
+pub mod stats;
 pub mod tags;
+pub mod user_roles;
 pub mod users;
+pub mod webhooks;
+
+/// Postgres prefixes its binary `jsonb` format with a version number, of which there is only one.
+#[cfg(feature = "diesel")]
+const JSONB_VERSION: u8 = 1;
@@ -2,12 +2,208 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt, str::FromStr};
 
 #[cfg(feature = "diesel")]
 use crate::schema::*;
 
+/// Declares [`Capability`] alongside the canonical name stored in the database for each
+/// variant, and [`for_each_capability`](crate::for_each_capability) to declare more for each
+/// variant elsewhere. Adding a capability is a single line in the invocation below.
+macro_rules! capabilities {
+    ($($(#[$meta:meta])* $variant:ident => $name:literal,)*) => {
+        /// A capability that may be held by a user, either directly or through a role.
+        ///
+        /// Serializes to and from the same canonical name that is stored in the database.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+        pub enum Capability {
+            $(
+                $(#[$meta])*
+                #[serde(rename = $name)]
+                $variant,
+            )*
+        }
+        impl Capability {
+            /// Every known capability.
+            pub const ALL: &'static [Capability] = &[$(Self::$variant,)*];
+            /// The canonical name of the capability, as stored in the database.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                }
+            }
+        }
+        impl FromStr for Capability {
+            type Err = UnknownCapability;
+            fn from_str(name: &str) -> Result<Self, Self::Err> {
+                match name {
+                    $($name => Ok(Self::$variant),)*
+                    unknown => Err(UnknownCapability(unknown.to_owned())),
+                }
+            }
+        }
+        /// Invokes the macro named `$callback` with every [`Capability`] variant, each preceded by
+        /// its attributes, such as `$callback! { #[doc = "..."] EditPost, ... }`.
+        #[macro_export]
+        macro_rules! for_each_capability {
+            ($callback:ident) => {
+                $callback! { $($(#[$meta])* $variant,)* }
+            };
+        }
+    };
+}
+capabilities! {
+    /// Capability allowing for editing of posts.
+    EditPost => "edit_post",
+    /// Capability allowing for creation of posts.
+    CreatePost => "create_post",
+    /// Capability allowing for deletion of posts.
+    DeletePost => "delete_post",
+    /// Capability allowing for publishing of posts.
+    PublishPost => "publish_post",
+    /// Capability allowing for archival of posts.
+    ArchivePost => "archive_post",
+    /// Capability allowing for creation of other users.
+    CreateUser => "create_user",
+    /// Capability allowing for editing of other users.
+    EditUser => "edit_user",
+    /// Capability allowing for deletion of other users.
+    DeleteUser => "delete_user",
+    /// Capability allowing for editing/deletion of login credentials.
+    EditUserCredentials => "edit_user_credentials",
+    /// Capability to grant capabilities to other users.
+    ///
+    /// NOTE: Can only grant capabilities they already have.
+    GrantCapability => "grant_capability",
+    /// Capability to view capabilities of other users.
+    ViewCapability => "view_capability",
+    /// Capability to delete capabilities of other users.
+    DeleteCapability => "delete_capability",
+    /// Capability allowing for editing, deleting, publishing, and archiving posts created by
+    /// other users.
+    EditForeignPosts => "edit_foreign_posts",
+    /// Capability to view statistics about the whole site.
+    ViewSiteStats => "view_site_stats",
+    /// Capability to rotate the keys tokens are encrypted with.
+    RotateKeys => "rotate_keys",
+    /// Capability to make the site read only or put it under maintenance, and to keep making
+    /// changes while it is read only.
+    ChangeSiteMode => "change_site_mode",
+    /// Capability to change how the site presents itself, such as its title and logo.
+    ChangeSiteIdentity => "change_site_identity",
+    /// Capability to manage the webhooks pinged when things happen on the site.
+    ManageWebhooks => "manage_webhooks",
+    /// Capability to remove deleted posts and other old records right away, rather than waiting
+    /// for the daily run.
+    RunRetention => "run_retention",
+}
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl TryFrom<&str> for Capability {
+    type Error = UnknownCapability;
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+impl TryFrom<&Data> for Capability {
+    type Error = UnknownCapability;
+    fn try_from(data: &Data) -> Result<Self, Self::Error> {
+        data.capability.parse()
+    }
+}
+impl TryFrom<&Effective> for Capability {
+    type Error = UnknownCapability;
+    fn try_from(effective: &Effective) -> Result<Self, Self::Error> {
+        effective.capability.parse()
+    }
+}
+impl TryFrom<&Grant> for Capability {
+    type Error = UnknownCapability;
+    fn try_from(grant: &Grant) -> Result<Self, Self::Error> {
+        grant.capability.parse()
+    }
+}
+impl TryFrom<&crate::models::role_capabilities::Data> for Capability {
+    type Error = UnknownCapability;
+    fn try_from(data: &crate::models::role_capabilities::Data) -> Result<Self, Self::Error> {
+        data.capability.parse()
+    }
+}
+
+/// A capability name that does not correspond to any known [`Capability`], usually from a row
+/// written by a newer or older version of the site.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownCapability(pub String);
+impl fmt::Display for UnknownCapability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown capability `{}`", self.0)
+    }
+}
+impl std::error::Error for UnknownCapability {}
+
+/// Restricts a grant to a single target. Unscoped grants apply everywhere.
+///
+/// Stored in the database as `<kind>:<id>`, such as `post:<uuid>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Scope {
+    /// The grant only applies to the post with this id.
+    Post(uuid::Uuid),
+    /// The grant only applies to posts with the tag with this id.
+    Tag(uuid::Uuid),
+}
+impl Scope {
+    /// Whether the scope covers a post with the provided id and tags.
+    pub fn covers_post(&self, post_id: uuid::Uuid, tag_ids: &[uuid::Uuid]) -> bool {
+        match self {
+            Self::Post(id) => *id == post_id,
+            Self::Tag(id) => tag_ids.contains(id),
+        }
+    }
+}
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Post(id) => write!(f, "post:{}", id),
+            Self::Tag(id) => write!(f, "tag:{}", id),
+        }
+    }
+}
+impl FromStr for Scope {
+    type Err = InvalidScope;
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidScope(scope.to_owned());
+        let mut parts = scope.splitn(2, ':');
+        let kind = parts.next().ok_or_else(invalid)?;
+        let id = parts
+            .next()
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(invalid)?;
+        match kind {
+            "post" => Ok(Self::Post(id)),
+            "tag" => Ok(Self::Tag(id)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A scope stored in the database that could not be parsed into a [`Scope`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvalidScope(pub String);
+impl fmt::Display for InvalidScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid capability scope `{}`", self.0)
+    }
+}
+impl std::error::Error for InvalidScope {}
+
 /// Data representing a complete row in the table.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(
     feature = "diesel",
     derive(Identifiable, Associations, Queryable),
@@ -25,6 +221,10 @@ pub struct Data {
     pub user_id: uuid::Uuid,
     /// The capability represented by the row.
     pub capability: String,
+    /// When the capability stops applying. Never, if absent.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// What the capability is restricted to, as formatted by [`Scope`]. Everything, if absent.
+    pub scope: Option<String>,
 }
 
 /// Data representing a new capability, but with an id. This is a convenience struct so that the
@@ -40,6 +240,10 @@ pub struct NewWithId<'a> {
     user_id: uuid::Uuid,
     /// The capability represented by this record.
     capability: &'a str,
+    /// When the capability stops applying.
+    expires_at: Option<DateTime<Utc>>,
+    /// What the capability is restricted to.
+    scope: Option<String>,
 }
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(feature = "server")]
@@ -50,6 +254,8 @@ impl<'a> From<New<'a>> for NewWithId<'a> {
             created_by: new.created_by,
             user_id: new.user_id,
             capability: new.capability,
+            expires_at: new.expires_at,
+            scope: new.scope.map(|scope| scope.to_string()),
         }
     }
 }
@@ -63,4 +269,109 @@ pub struct New<'a> {
     pub user_id: uuid::Uuid,
     /// The capability category itself.
     pub capability: &'a str,
+    /// When the capability stops applying. Never, if absent.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// What the capability is restricted to. Everything, if absent.
+    pub scope: Option<Scope>,
+}
+
+/// A capability held by a user, either granted directly or through one of their roles.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(QueryableByName))]
+pub struct Effective {
+    /// The capability held.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::Text")]
+    pub capability: String,
+    /// When the capability stops applying. Never, if absent.
+    #[cfg_attr(
+        feature = "diesel",
+        sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>"
+    )]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// What the capability is restricted to, as formatted by [`Scope`]. Everything, if absent.
+    #[cfg_attr(
+        feature = "diesel",
+        sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"
+    )]
+    pub scope: Option<String>,
+}
+
+/// Where a user's capability comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum GrantSource {
+    /// Granted directly, through the row with the provided id in the `capabilities` table.
+    Direct {
+        /// The id of the row granting the capability.
+        id: uuid::Uuid,
+    },
+    /// Granted through a role held by the user.
+    Role {
+        /// The id of the role.
+        role_id: uuid::Uuid,
+        /// The name of the role.
+        role_name: String,
+    },
+}
+
+/// A capability held by a user, annotated with how and when it was granted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Grant {
+    /// The capability held.
+    pub capability: String,
+    /// How the capability was granted.
+    pub source: GrantSource,
+    /// The user who granted the capability or role, if any.
+    pub granted_by: Option<uuid::Uuid>,
+    /// When the capability or role was granted.
+    pub granted_at: DateTime<Utc>,
+    /// When the capability stops applying. Never, if absent.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// What the capability is restricted to, as formatted by [`Scope`]. Everything, if absent.
+    pub scope: Option<String>,
+}
+impl From<Data> for Grant {
+    fn from(data: Data) -> Self {
+        Self {
+            capability: data.capability,
+            source: GrantSource::Direct { id: data.id },
+            granted_by: data.created_by,
+            granted_at: data.created_at,
+            expires_at: data.expires_at,
+            scope: data.scope,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for &capability in Capability::ALL {
+            assert_eq!(capability.as_str().parse(), Ok(capability));
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        assert_eq!(
+            "rule_the_world".parse::<Capability>(),
+            Err(UnknownCapability("rule_the_world".to_owned())),
+        );
+    }
+
+    #[test]
+    fn scopes_round_trip() {
+        let id = uuid::Uuid::nil();
+        for &scope in &[Scope::Post(id), Scope::Tag(id)] {
+            assert_eq!(scope.to_string().parse(), Ok(scope));
+        }
+        assert!("post".parse::<Scope>().is_err());
+        assert!("user:00000000-0000-0000-0000-000000000000"
+            .parse::<Scope>()
+            .is_err());
+    }
 }
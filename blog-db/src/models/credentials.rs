@@ -1,5 +1,6 @@
 //! Represents all methods to sign into the site.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Data needed for single sign on (sso). Currently unimplemented
@@ -65,18 +66,26 @@ pub mod pw {
         pub id: uuid::Uuid,
         /// Time the row was created.
         pub created_at: DateTime<Utc>,
-        /// Who created the row.
-        pub created_by: uuid::Uuid,
+        /// Who created the row. [`None`] if they were deleted.
+        pub created_by: Option<uuid::Uuid>,
         /// Last time this row was updated.
         pub updated_at: DateTime<Utc>,
-        /// Who updated the row.
-        pub updated_by: uuid::Uuid,
+        /// Who updated the row. [`None`] if they were deleted.
+        pub updated_by: Option<uuid::Uuid>,
         /// The id of the user this password belongs to.
         pub user_id: uuid::Uuid,
         /// A hash of the password.
         pub hash: String,
         /// The salt used when hashing the password.
         pub salt: String,
+        /// The argon2 memory cost, in KiB, the hash was made with.
+        pub argon2_mem_kib: i32,
+        /// The argon2 number of passes the hash was made with.
+        pub argon2_iterations: i32,
+        /// The argon2 number of lanes the hash was made with.
+        pub argon2_parallelism: i32,
+        /// Last time the password was signed in with, if it has been since this was tracked.
+        pub last_used_at: Option<DateTime<Utc>>,
     }
 
     /// Represents a new row to be added to the table.
@@ -95,6 +104,12 @@ pub mod pw {
         hash: &'a str,
         /// The salt used when hashing the password.
         salt: &'a str,
+        /// The argon2 memory cost, in KiB, the hash was made with.
+        argon2_mem_kib: i32,
+        /// The argon2 number of passes the hash was made with.
+        argon2_iterations: i32,
+        /// The argon2 number of lanes the hash was made with.
+        argon2_parallelism: i32,
     }
     #[cfg(not(target_arch = "wasm32"))]
     #[cfg(feature = "server")]
@@ -107,6 +122,9 @@ pub mod pw {
                 user_id: new.user_id,
                 hash: new.hash,
                 salt: new.salt,
+                argon2_mem_kib: new.argon2_mem_kib,
+                argon2_iterations: new.argon2_iterations,
+                argon2_parallelism: new.argon2_parallelism,
             }
         }
     }
@@ -124,6 +142,12 @@ pub mod pw {
         pub hash: &'a str,
         /// The salt used when hashing the password.
         pub salt: &'a str,
+        /// The argon2 memory cost, in KiB, the hash was made with.
+        pub argon2_mem_kib: i32,
+        /// The argon2 number of passes the hash was made with.
+        pub argon2_iterations: i32,
+        /// The argon2 number of lanes the hash was made with.
+        pub argon2_parallelism: i32,
     }
 
     /// Represents a set of changes to the row.
@@ -136,6 +160,12 @@ pub mod pw {
         pub hash: Option<String>,
         /// The salt used when hashing the password.
         pub salt: Option<String>,
+        /// The argon2 memory cost, in KiB, the hash was made with.
+        pub argon2_mem_kib: Option<i32>,
+        /// The argon2 number of passes the hash was made with.
+        pub argon2_iterations: Option<i32>,
+        /// The argon2 number of lanes the hash was made with.
+        pub argon2_parallelism: Option<i32>,
     }
 }
 
@@ -159,3 +189,61 @@ impl Data {
         result_data.map(Self::from)
     }
 }
+
+/// The kinds of credential a user can sign in with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// A row in the `passwords` table.
+    Password,
+    /// A row in the `google_sso` table.
+    Google,
+}
+impl Kind {
+    /// The name of the kind, as the queries listing credentials name it.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Password => "password",
+            Self::Google => "google",
+        }
+    }
+    /// The kind named `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Password, Self::Google]
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str() == name)
+    }
+}
+
+/// What the owner of a credential is shown of it, leaving out anything that could be used to sign
+/// in with it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Summary {
+    /// The id of the credential, within the table of its kind.
+    pub id: uuid::Uuid,
+    /// What kind of credential it is.
+    pub kind: Kind,
+    /// Time the credential was added.
+    pub created_at: DateTime<Utc>,
+    /// Last time the credential was signed in with. [`None`] if it hasn't been since this was
+    /// tracked.
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// What the owner named the credential, for kinds they can have several of.
+    pub label: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kinds_are_listed_by_name() {
+        for kind in [Kind::Password, Kind::Google].iter().copied() {
+            assert_eq!(Kind::from_name(kind.as_str()), Some(kind));
+        }
+        assert_eq!(Kind::from_name("fido"), None);
+    }
+}
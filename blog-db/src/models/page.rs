@@ -0,0 +1,85 @@
+//! The envelope of listings that are handed out a page at a time, so that no listing answers with
+//! more rows than it can bear.
+
+use serde::{Deserialize, Serialize};
+
+/// The most rows a page may hold. Larger limits are brought down to this rather than refused.
+pub const MAX_LIMIT: i64 = 200;
+/// How many rows a page holds when no limit is asked for.
+pub const DEFAULT_LIMIT: i64 = 50;
+
+/// The limit to page with, given the one asked for, between one and [`MAX_LIMIT`].
+pub fn clamp_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIMIT).max(1).min(MAX_LIMIT)
+}
+
+/// A page of a listing, along with where it is in the listing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The number of rows in the whole listing.
+    pub total: i64,
+    /// The most rows the page could hold.
+    pub limit: i64,
+    /// The number of rows of the listing before the page.
+    pub offset: i64,
+}
+impl<T> Page<T> {
+    /// The same page, with its items turned into others by `f`.
+    pub fn map_items<U, F: FnOnce(Vec<T>) -> Vec<U>>(self, f: F) -> Page<U> {
+        Page {
+            items: f(self.items),
+            total: self.total,
+            limit: self.limit,
+            offset: self.offset,
+        }
+    }
+    /// The offset of the page before this one, if there is one.
+    pub fn prev_offset(&self) -> Option<i64> {
+        if self.offset > 0 {
+            Some((self.offset - self.limit).max(0))
+        } else {
+            None
+        }
+    }
+    /// The offset of the page after this one, if there is one.
+    pub fn next_offset(&self) -> Option<i64> {
+        let next = self.offset + self.limit;
+        if next < self.total {
+            Some(next)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn page(total: i64, limit: i64, offset: i64) -> Page<()> {
+        Page {
+            items: vec![],
+            total,
+            limit,
+            offset,
+        }
+    }
+
+    #[test]
+    fn limits_are_clamped() {
+        assert_eq!(clamp_limit(None), DEFAULT_LIMIT);
+        assert_eq!(clamp_limit(Some(0)), 1);
+        assert_eq!(clamp_limit(Some(10_000)), MAX_LIMIT);
+    }
+
+    #[test]
+    fn neighbouring_pages_stay_within_the_listing() {
+        assert_eq!(page(120, 50, 0).prev_offset(), None);
+        assert_eq!(page(120, 50, 0).next_offset(), Some(50));
+        assert_eq!(page(120, 50, 30).prev_offset(), Some(0));
+        assert_eq!(page(120, 50, 100).next_offset(), None);
+        assert_eq!(page(0, 50, 0).next_offset(), None);
+    }
+}
@@ -0,0 +1,74 @@
+//! A collection of types related to the users credited on a post, which may be more than the
+//! user who created it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::users::AuthorSummary;
+#[cfg(feature = "diesel")]
+use crate::schema::*;
+
+/// What a user credited on a post did for it. Either may edit the post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Wrote the post.
+    Author,
+    /// Helped shape the post without writing it.
+    Editor,
+}
+impl Role {
+    /// Every role.
+    pub const ALL: [Role; 2] = [Role::Author, Role::Editor];
+    /// The name of the role, as stored in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Author => "author",
+            Self::Editor => "editor",
+        }
+    }
+    /// The role stored in the database as `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|role| role.as_str() == name)
+    }
+}
+
+/// A user credited on a post, in the order of the byline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Author {
+    pub user: AuthorSummary,
+    pub role: Role,
+}
+
+/// A user to credit on a post, as sent when creating or changing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Assigned {
+    pub user_id: uuid::Uuid,
+    pub role: Role,
+}
+impl From<&Author> for Assigned {
+    fn from(author: &Author) -> Self {
+        Self {
+            user_id: author.user.id,
+            role: author.role,
+        }
+    }
+}
+
+/// A new credit on a post.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "post_authors")]
+pub struct New {
+    /// The post the user is credited on.
+    pub post_id: uuid::Uuid,
+    /// The user credited.
+    pub user_id: uuid::Uuid,
+    /// The name of the [`Role`] of the user.
+    pub role: &'static str,
+    /// Where in the byline the user is listed, starting from 0.
+    pub position: i32,
+    /// The user id of the creator of this relation.
+    pub created_by: uuid::Uuid,
+}
@@ -23,8 +23,8 @@ pub struct PostTagJunction {
     pub post_id: uuid::Uuid,
     /// The tag id represented by this relation.
     pub tag_id: uuid::Uuid,
-    /// The user id of the creator of this relation.
-    pub created_by: uuid::Uuid,
+    /// The user id of the creator of this relation. [`None`] if the creator was deleted.
+    pub created_by: Option<uuid::Uuid>,
 }
 
 /// A new post to tag relation.
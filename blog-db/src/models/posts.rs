@@ -3,11 +3,24 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::{post_authors, reactions, series};
+
 #[cfg(feature = "diesel")]
 use crate::schema::*;
 
+/// The body of a `403` returned when modifying a post created by someone else without the
+/// capability to do so.
+pub const NOT_OWNER_ERROR: &str = "not_post_owner";
+/// The most characters the title of a post may have.
+pub const TITLE_MAX_CHARS: usize = 300;
+/// The most characters the description of a post may have.
+pub const META_DESCRIPTION_MAX_CHARS: usize = 300;
+/// How many characters of the description of a post search results tend to show.
+pub const META_DESCRIPTION_SHOWN_CHARS: usize = 160;
+
 /// Data representing a complete row in the table.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(
     feature = "diesel",
     derive(Identifiable, Queryable),
@@ -22,8 +35,8 @@ pub struct Data {
     pub created_by: uuid::Uuid,
     /// The time at which the record was last updated.
     pub updated_at: DateTime<Utc>,
-    /// The id of the user who last updated the record.
-    pub updated_by: uuid::Uuid,
+    /// The id of the user who last updated the record. [`None`] if that user was deleted.
+    pub updated_by: Option<uuid::Uuid>,
     /// The time at which the record was published. [`None`] means that the record has not been
     /// published.
     pub published_at: Option<DateTime<Utc>>,
@@ -48,6 +61,12 @@ pub struct Data {
     pub body: String,
     /// Friendly name for the blog post.
     pub slug: Option<String>,
+    /// What the post is described with where it is linked to. Left empty, the start of the body
+    /// is used.
+    pub meta_description: String,
+    /// The url of the image shown where the post is linked to. Left empty, the first image of the
+    /// body is used.
+    pub social_image: String,
 }
 impl Data {
     /// Strips the meta data before sending it to a client.
@@ -56,9 +75,88 @@ impl Data {
     }
 }
 
+/// How a post is shown to whoever asked for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    Published,
+    /// Still shown, but marked as no longer kept up to date.
+    Archived,
+    /// Not published yet, so only shown to those who may edit it.
+    Draft,
+}
+impl Default for Visibility {
+    fn default() -> Self {
+        Self::Published
+    }
+}
+impl Visibility {
+    /// How the post is shown, going by its state alone. Deleted posts are not shown at all.
+    pub fn of(post: &Data) -> Option<Self> {
+        if post.deleted_at.is_some() {
+            None
+        } else if post.published_at.is_none() {
+            Some(Self::Draft)
+        } else if post.archived_at.is_some() {
+            Some(Self::Archived)
+        } else {
+            Some(Self::Published)
+        }
+    }
+}
+
+/// A post along with the users credited on it and the series it is part of, as answered when a
+/// post is fetched or created.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Authored {
+    #[serde(flatten)]
+    pub post: Data,
+    /// The users credited on the post, in the order of the byline.
+    pub authors: Vec<post_authors::Author>,
+    /// Where the post is in each series it is part of. A post is created outside of any series.
+    #[serde(default)]
+    pub series: Vec<series::Membership>,
+    /// The headings of the body, in order, for a table of contents.
+    #[serde(default)]
+    pub outline: Vec<Heading>,
+    /// The body rendered to html, with its code highlighted.
+    #[serde(default)]
+    pub html: Option<String>,
+    /// How the post is shown to whoever asked for it.
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// How many of each kind of reaction the post has.
+    #[serde(default)]
+    pub reactions: reactions::Counts,
+}
+
+/// The body of a post rendered to html, with its code highlighted, along with its headings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Rendered {
+    pub html: String,
+    /// The headings of the body, in order, for a table of contents.
+    pub outline: Vec<Heading>,
+}
+
+/// A heading of a post, as listed in its table of contents.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Heading {
+    /// 1 for the largest headings, through 6.
+    pub level: u8,
+    /// The text of the heading, without any formatting.
+    pub text: String,
+    /// The id of the heading in the rendered body, without the `#`.
+    pub id: String,
+}
+
 /// Almost the same as [`Data`](crate::models::posts::Data) but without the id, created, and
 /// updated information.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DataNoMeta {
     /// The id of the record.
     pub id: uuid::Uuid,
@@ -66,6 +164,8 @@ pub struct DataNoMeta {
     pub created_at: DateTime<Utc>,
     /// The id of the user who created the record.
     pub created_by: uuid::Uuid,
+    /// The time at which the record last changed.
+    pub updated_at: DateTime<Utc>,
     /// The time at which the record was published. [`None`] means that the record has not been
     /// published.
     pub published_at: Option<DateTime<Utc>>,
@@ -90,13 +190,45 @@ pub struct DataNoMeta {
     pub body: String,
     /// Friendly name for the blog post.
     pub slug: Option<String>,
+    /// What the post is described with where it is linked to. Left empty, the start of the body
+    /// is used.
+    #[serde(default)]
+    pub meta_description: String,
+    /// The url of the image shown where the post is linked to. Left empty, the first image of the
+    /// body is used.
+    #[serde(default)]
+    pub social_image: String,
+    /// The users credited on the post, in the order of the byline. Left empty by the responses
+    /// that do not list them.
+    #[serde(default)]
+    pub authors: Vec<post_authors::Author>,
+    /// Where the post is in each series it is part of. Left empty by the responses that do not
+    /// list them.
+    #[serde(default)]
+    pub series: Vec<series::Membership>,
+    /// The headings of the body, in order. Left empty by the responses that do not list them.
+    #[serde(default)]
+    pub outline: Vec<Heading>,
+    /// The body rendered by the server, with its code highlighted. Left out by the responses that
+    /// do not render it, and dropped once the body is edited.
+    #[serde(default)]
+    pub html: Option<String>,
+    /// How the post is shown to whoever asked for it.
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// How many of each kind of reaction the post has. Left at none by the responses that do not
+    /// count them.
+    #[serde(default)]
+    pub reactions: reactions::Counts,
 }
 impl From<Data> for DataNoMeta {
     fn from(d: Data) -> Self {
+        let visibility = Visibility::of(&d).unwrap_or_default();
         Self {
             id: d.id,
             created_at: d.created_at,
             created_by: d.created_by,
+            updated_at: d.updated_at,
             published_at: d.published_at,
             published_by: d.published_by,
             archived_at: d.archived_at,
@@ -106,6 +238,27 @@ impl From<Data> for DataNoMeta {
             title: d.title,
             body: d.body,
             slug: d.slug,
+            meta_description: d.meta_description,
+            social_image: d.social_image,
+            authors: vec![],
+            series: vec![],
+            outline: vec![],
+            html: None,
+            visibility,
+            reactions: reactions::Counts::default(),
+        }
+    }
+}
+impl From<Authored> for DataNoMeta {
+    fn from(authored: Authored) -> Self {
+        Self {
+            authors: authored.authors,
+            series: authored.series,
+            outline: authored.outline,
+            html: authored.html,
+            visibility: authored.visibility,
+            reactions: authored.reactions,
+            ..authored.post.into()
         }
     }
 }
@@ -115,8 +268,13 @@ impl DataNoMeta {
     }
 }
 
-/// Data representing the id, title, the publishing time, and author of a post.
+/// A post in a listing, along with who wrote it.
+pub type Listed = (BasicData, super::users::AuthorSummary);
+
+/// Data representing the id, title, and the publishing time of a post. Leaves out the body, so
+/// that listings do not fetch it.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(
     feature = "diesel",
     derive(Identifiable, Queryable),
@@ -127,6 +285,8 @@ pub struct BasicData {
     pub id: uuid::Uuid,
     /// The time at which the record was created.
     pub created_at: DateTime<Utc>,
+    /// The time at which the record last changed.
+    pub updated_at: DateTime<Utc>,
     /// The time at which the record was published. [`None`] means that the record has not been
     /// published.
     pub published_at: Option<DateTime<Utc>>,
@@ -138,10 +298,11 @@ pub struct BasicData {
     pub deleted_at: Option<DateTime<Utc>>,
     /// The title of the blog post.
     pub title: String,
-    /// The body of the blog post.
-    pub body: String,
     /// Friendly name for the blog post.
     pub slug: Option<String>,
+    /// How many reactions of any kind the post has.
+    #[serde(default)]
+    pub reactions: i64,
 }
 impl BasicData {
     pub fn is_published(&self) -> bool {
@@ -150,25 +311,32 @@ impl BasicData {
 }
 #[cfg(feature = "diesel")]
 impl BasicData {
-    pub const COLUMNS: (
-        posts::id,
-        posts::created_at,
-        posts::published_at,
-        posts::archived_at,
-        posts::deleted_at,
-        posts::title,
-        posts::body,
-        posts::slug,
-    ) = (
+    /// The columns the struct is loaded from. The reactions are counted by a subquery.
+    pub fn columns() -> (
         posts::id,
         posts::created_at,
+        posts::updated_at,
         posts::published_at,
         posts::archived_at,
         posts::deleted_at,
         posts::title,
-        posts::body,
         posts::slug,
-    );
+        diesel::expression::SqlLiteral<diesel::sql_types::BigInt>,
+    ) {
+        (
+            posts::id,
+            posts::created_at,
+            posts::updated_at,
+            posts::published_at,
+            posts::archived_at,
+            posts::deleted_at,
+            posts::title,
+            posts::slug,
+            diesel::dsl::sql(
+                "(SELECT COUNT(*) FROM post_reactions WHERE post_reactions.post_id = posts.id)",
+            ),
+        )
+    }
 }
 
 /// Represents a new post.
@@ -211,13 +379,23 @@ pub struct NewWithId<'a> {
     body: &'a str,
     /// The friendly name for the blog post.
     slug: Option<&'a str>,
+    /// What the post is described with where it is linked to.
+    meta_description: &'a str,
+    /// The url of the image shown where the post is linked to.
+    social_image: &'a str,
 }
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(feature = "server")]
 impl<'a> From<New<'a>> for NewWithId<'a> {
     fn from(new: New<'a>) -> Self {
+        Self::with_id(uuid::Uuid::new_v4(), new)
+    }
+}
+impl<'a> NewWithId<'a> {
+    /// Gives the new post a chosen id rather than a random one.
+    pub fn with_id(id: uuid::Uuid, new: New<'a>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4(),
+            id,
             created_by: new.created_by,
             updated_by: new.updated_by,
 
@@ -231,6 +409,8 @@ impl<'a> From<New<'a>> for NewWithId<'a> {
             title: new.title,
             body: new.body,
             slug: new.slug,
+            meta_description: new.meta_description,
+            social_image: new.social_image,
         }
     }
 }
@@ -268,6 +448,10 @@ pub struct New<'a> {
     pub body: &'a str,
     /// The friendly name for the blog post.
     pub slug: Option<&'a str>,
+    /// What the post is described with where it is linked to.
+    pub meta_description: &'a str,
+    /// The url of the image shown where the post is linked to.
+    pub social_image: &'a str,
 }
 impl<'a> From<(&'a NewNoMeta, uuid::Uuid)> for New<'a> {
     fn from((reference, creator): (&'a NewNoMeta, uuid::Uuid)) -> Self {
@@ -285,6 +469,8 @@ impl<'a> From<(&'a NewNoMeta, uuid::Uuid)> for New<'a> {
             title: reference.title.as_str(),
             body: reference.body.as_str(),
             slug: reference.slug.as_ref().map(String::as_str),
+            meta_description: reference.meta_description.as_str(),
+            social_image: reference.social_image.as_str(),
         }
     }
 }
@@ -292,12 +478,14 @@ impl<'a> From<(&'a NewNoMeta, uuid::Uuid)> for New<'a> {
 #[cfg(feature = "server")]
 impl<'a> From<(&'a NewNoMeta, uuid::Uuid)> for NewWithId<'a> {
     fn from(conv: (&'a NewNoMeta, uuid::Uuid)) -> Self {
-        (conv.into(): New).into()
+        let new: New = conv.into();
+        new.into()
     }
 }
 
 /// Represents a new post without an id as well as the created by and updated by fields.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct NewNoMeta {
     /// The time at which the record was published. [`None`] means that the record has not been
     /// published.
@@ -324,6 +512,21 @@ pub struct NewNoMeta {
     pub body: String,
     /// The friendly name for the blog post.
     pub slug: Option<String>,
+    /// What the post is described with where it is linked to. Left empty, the start of the body
+    /// is used.
+    #[serde(default)]
+    pub meta_description: String,
+    /// The url of the image shown where the post is linked to. Left empty, the first image of the
+    /// body is used.
+    #[serde(default)]
+    pub social_image: String,
+    /// The users to credit on the post, in the order of the byline. Left empty, the creator is
+    /// credited as the only author.
+    #[serde(default)]
+    pub authors: Vec<post_authors::Assigned>,
+    /// Whether to tag the post with the tags its creator tags new posts with by default.
+    #[serde(default)]
+    pub default_tags: bool,
 }
 impl NewNoMeta {
     /// Default everything other than the title and body to [`None`].
@@ -339,18 +542,49 @@ impl NewNoMeta {
             title,
             body,
             slug: None,
+            meta_description: String::new(),
+            social_image: String::new(),
+            authors: vec![],
+            default_tags: false,
         }
     }
 }
 
 /// Struct representing changes to the body and title of the post.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "diesel", derive(AsChangeset), table_name = "posts")]
 pub struct Changed {
     /// The title of the blog post.
     pub title: Option<String>,
     /// The body of the blog post.
     pub body: Option<String>,
+    /// What the post is described with where it is linked to. Empty to derive it from the body.
+    pub meta_description: Option<String>,
+    /// The url of the image shown where the post is linked to. Empty to use the first image of
+    /// the body.
+    pub social_image: Option<String>,
+}
+impl Changed {
+    /// Whether nothing is changed.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.body.is_none()
+            && self.meta_description.is_none()
+            && self.social_image.is_none()
+    }
+}
+
+/// Changes to a post, along with who to credit on it.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Update {
+    #[serde(flatten)]
+    pub changes: Changed,
+    /// The users to credit on the post from now on, in the order of the byline. [`None`] leaves
+    /// them as they are.
+    #[serde(default)]
+    pub authors: Option<Vec<post_authors::Assigned>>,
 }
 
 /// Struct representing the editing of the blog post.
@@ -361,7 +595,8 @@ pub struct Editing {
     updated_by: uuid::Uuid,
 }
 impl Editing {
-    /// Constructs the struct with assumed time of editing (now).
+    /// Constructs the struct. The time of editing is set by the database, and only if the post
+    /// changed.
     pub fn new(updated_by: uuid::Uuid) -> Self {
         Self { updated_by }
     }
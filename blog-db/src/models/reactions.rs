@@ -0,0 +1,140 @@
+//! Models used to represent the reactions readers leave on posts, a lighter way to respond to a
+//! post than writing back.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel")]
+use crate::schema::*;
+
+/// What a reader reacted to a post with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Like,
+    Heart,
+    Rocket,
+    Eyes,
+}
+impl Kind {
+    /// Every kind, in the order they are shown.
+    pub const ALL: [Kind; 4] = [Kind::Like, Kind::Heart, Kind::Rocket, Kind::Eyes];
+    /// The name of the kind, as stored in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Like => "like",
+            Self::Heart => "heart",
+            Self::Rocket => "rocket",
+            Self::Eyes => "eyes",
+        }
+    }
+    /// The kind stored in the database as `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.as_str() == name)
+    }
+    /// The emoji the kind is shown as.
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Self::Like => "\u{1F44D}",
+            Self::Heart => "\u{2764}\u{FE0F}",
+            Self::Rocket => "\u{1F680}",
+            Self::Eyes => "\u{1F440}",
+        }
+    }
+}
+
+/// A reaction to leave on a post, or take back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Reaction {
+    pub kind: Kind,
+}
+
+/// How many of each kind of reaction a post has.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Counts {
+    pub like: i64,
+    pub heart: i64,
+    pub rocket: i64,
+    pub eyes: i64,
+}
+impl Counts {
+    /// The number of reactions of `kind`.
+    pub fn get(&self, kind: Kind) -> i64 {
+        match kind {
+            Kind::Like => self.like,
+            Kind::Heart => self.heart,
+            Kind::Rocket => self.rocket,
+            Kind::Eyes => self.eyes,
+        }
+    }
+    fn get_mut(&mut self, kind: Kind) -> &mut i64 {
+        match kind {
+            Kind::Like => &mut self.like,
+            Kind::Heart => &mut self.heart,
+            Kind::Rocket => &mut self.rocket,
+            Kind::Eyes => &mut self.eyes,
+        }
+    }
+    /// Counts one more reaction of `kind` if `added`, or one less otherwise, never going below
+    /// none.
+    pub fn adjust(&mut self, kind: Kind, added: bool) {
+        let count = self.get_mut(kind);
+        *count = if added { *count + 1 } else { (*count - 1).max(0) };
+    }
+    /// The number of reactions of every kind.
+    pub fn total(&self) -> i64 {
+        Kind::ALL.iter().map(|kind| self.get(*kind)).sum()
+    }
+    /// Tallies the number of reactions of each kind named, skipping names no longer known.
+    pub fn tally(counted: impl IntoIterator<Item = (String, i64)>) -> Self {
+        counted
+            .into_iter()
+            .filter_map(|(name, count)| Some((Kind::from_name(&name)?, count)))
+            .fold(Self::default(), |mut counts, (kind, count)| {
+                *counts.get_mut(kind) += count;
+                counts
+            })
+    }
+}
+
+/// A new reaction on a post.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "post_reactions")]
+pub struct New<'a> {
+    /// The post reacted to.
+    pub post_id: uuid::Uuid,
+    /// The name of the [`Kind`] of reaction.
+    pub kind: &'static str,
+    /// Who reacted, hashed.
+    pub actor: &'a str,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kinds_are_stored_by_name() {
+        for kind in Kind::ALL.iter().copied() {
+            assert_eq!(Kind::from_name(kind.as_str()), Some(kind));
+        }
+        assert_eq!(Kind::from_name("clap"), None);
+    }
+
+    #[test]
+    fn counts_are_tallied_and_never_go_negative() {
+        let mut counts = Counts::tally(vec![
+            ("heart".to_owned(), 2),
+            ("eyes".to_owned(), 1),
+            ("clap".to_owned(), 5),
+        ]);
+        assert_eq!(counts, Counts { heart: 2, eyes: 1, ..Counts::default() });
+        assert_eq!(counts.total(), 3);
+        counts.adjust(Kind::Like, false);
+        counts.adjust(Kind::Heart, true);
+        assert_eq!(counts.get(Kind::Like), 0);
+        assert_eq!(counts.get(Kind::Heart), 3);
+    }
+}
@@ -0,0 +1,34 @@
+//! A collection of types related to the capabilities that make up a role.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel")]
+use crate::schema::*;
+
+/// Data representing a single capability belonging to a role.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(Queryable))]
+pub struct Data {
+    /// The role this capability belongs to.
+    pub role_id: uuid::Uuid,
+    /// The capability represented by the row.
+    pub capability: String,
+    /// The time this row was created.
+    pub created_at: DateTime<Utc>,
+    /// The creator of this record. Capabilities seeded by migrations have no creator.
+    pub created_by: Option<uuid::Uuid>,
+}
+
+/// A new capability for a role.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "role_capabilities")]
+pub struct New<'a> {
+    /// The role this capability will belong to.
+    pub role_id: uuid::Uuid,
+    /// The capability itself.
+    pub capability: &'a str,
+    /// The creator of this record.
+    pub created_by: uuid::Uuid,
+}
@@ -0,0 +1,86 @@
+//! Models used to represent roles, which are named sets of capabilities that can be granted to
+//! users.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel")]
+use crate::schema::*;
+
+/// Data representing a complete row in the table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "diesel",
+    derive(Identifiable, Queryable),
+    table_name = "roles"
+)]
+pub struct Data {
+    /// The id of the row.
+    pub id: uuid::Uuid,
+    /// The time this row was created.
+    pub created_at: DateTime<Utc>,
+    /// The creator of this record. Roles seeded by migrations have no creator.
+    pub created_by: Option<uuid::Uuid>,
+    /// The unique name of the role.
+    pub name: String,
+    /// A short description of the role.
+    pub description: String,
+}
+
+/// Data to be inserted as a new row in the table. Automatically adds an id to the struct
+/// [`New`](crate::models::roles::New).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "roles")]
+pub struct NewWithId<'a> {
+    /// The id of the new record.
+    id: uuid::Uuid,
+    /// The creator of the role.
+    created_by: uuid::Uuid,
+    /// The name of the role.
+    name: &'a str,
+    /// A short description of the role.
+    description: &'a str,
+}
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "server")]
+impl<'a> From<New<'a>> for NewWithId<'a> {
+    fn from(new: New<'a>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            created_by: new.created_by,
+            name: new.name,
+            description: new.description,
+        }
+    }
+}
+
+/// A simple new role record, without the id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct New<'a> {
+    /// The creator of the role.
+    pub created_by: uuid::Uuid,
+    /// The name of the role.
+    pub name: &'a str,
+    /// A short description of the role.
+    pub description: &'a str,
+}
+impl<'a> From<(&'a NewNoMeta, uuid::Uuid)> for New<'a> {
+    fn from((source, creator): (&'a NewNoMeta, uuid::Uuid)) -> Self {
+        Self {
+            created_by: creator,
+            name: &source.name,
+            description: &source.description,
+        }
+    }
+}
+
+/// Represents a new role without meta info.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NewNoMeta {
+    /// The name of the role.
+    pub name: String,
+    /// A short description of the role.
+    pub description: String,
+}
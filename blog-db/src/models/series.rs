@@ -0,0 +1,235 @@
+//! Models used to represent series, which are posts meant to be read in order, such as the parts
+//! of a multi-part write up.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel")]
+use crate::schema::*;
+
+/// Data representing a complete row in the table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "diesel",
+    derive(Identifiable, Queryable),
+    table_name = "series"
+)]
+pub struct Data {
+    /// The id of the row.
+    pub id: uuid::Uuid,
+    /// The time this row was created.
+    pub created_at: DateTime<Utc>,
+    /// The creator of this record, unless they were deleted.
+    pub created_by: Option<uuid::Uuid>,
+    /// The time this row was last changed.
+    pub updated_at: DateTime<Utc>,
+    /// Who last changed this record, unless they were deleted.
+    pub updated_by: Option<uuid::Uuid>,
+    /// The title of the series.
+    pub title: String,
+    /// The friendly name of the series, used in its url.
+    pub slug: String,
+    /// A short description of the series.
+    pub description: String,
+}
+
+/// Data to be inserted as a new row in the table. Automatically adds an id to the struct
+/// [`New`](crate::models::series::New).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "series")]
+pub struct NewWithId<'a> {
+    /// The id of the new record.
+    id: uuid::Uuid,
+    /// The creator of the series.
+    created_by: uuid::Uuid,
+    /// Who last changed the series, which is its creator.
+    updated_by: uuid::Uuid,
+    /// The title of the series.
+    title: &'a str,
+    /// The friendly name of the series.
+    slug: &'a str,
+    /// A short description of the series.
+    description: &'a str,
+}
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "server")]
+impl<'a> From<New<'a>> for NewWithId<'a> {
+    fn from(new: New<'a>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            created_by: new.created_by,
+            updated_by: new.created_by,
+            title: new.title,
+            slug: new.slug,
+            description: new.description,
+        }
+    }
+}
+
+/// A simple new series record, without the id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct New<'a> {
+    /// The creator of the series.
+    pub created_by: uuid::Uuid,
+    /// The title of the series.
+    pub title: &'a str,
+    /// The friendly name of the series.
+    pub slug: &'a str,
+    /// A short description of the series.
+    pub description: &'a str,
+}
+impl<'a> From<(&'a NewNoMeta, uuid::Uuid)> for New<'a> {
+    fn from((source, creator): (&'a NewNoMeta, uuid::Uuid)) -> Self {
+        Self {
+            created_by: creator,
+            title: &source.title,
+            slug: &source.slug,
+            description: &source.description,
+        }
+    }
+}
+
+/// Represents a new series without meta info.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NewNoMeta {
+    /// The title of the series.
+    pub title: String,
+    /// The friendly name of the series, used in its url.
+    pub slug: String,
+    /// A short description of the series.
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Changes to a series. Fields left out are kept as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(AsChangeset), table_name = "series")]
+pub struct Changed {
+    /// The title of the series.
+    pub title: Option<String>,
+    /// The friendly name of the series.
+    pub slug: Option<String>,
+    /// A short description of the series.
+    pub description: Option<String>,
+}
+
+/// A post in a series, as listed alongside the other parts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(Queryable))]
+pub struct Part {
+    /// The id of the post.
+    pub id: uuid::Uuid,
+    /// The title of the post.
+    pub title: String,
+    /// The friendly name of the post, if it has one.
+    pub slug: Option<String>,
+    /// When the post was published, if it was.
+    pub published_at: Option<DateTime<Utc>>,
+}
+#[cfg(feature = "diesel")]
+impl Part {
+    /// The columns of `posts` a part is loaded from.
+    pub const COLUMNS: (
+        posts::id,
+        posts::title,
+        posts::slug,
+        posts::published_at,
+    ) = (posts::id, posts::title, posts::slug, posts::published_at);
+}
+
+/// A series along with its parts, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WithParts {
+    #[serde(flatten)]
+    pub series: Data,
+    pub parts: Vec<Part>,
+}
+
+/// Where a post is in a series it is part of, with the parts around it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Membership {
+    /// The id of the series.
+    pub series_id: uuid::Uuid,
+    /// The title of the series.
+    pub title: String,
+    /// The friendly name of the series.
+    pub slug: String,
+    /// Which part the post is, starting from 1.
+    pub part: usize,
+    /// How many parts the series has.
+    pub parts: usize,
+    /// The part before the post, unless it comes first.
+    pub prev: Option<Part>,
+    /// The part after the post, unless it comes last.
+    pub next: Option<Part>,
+}
+impl Membership {
+    /// Where the post with the provided id is among the `parts` of the `series`, if it is one.
+    pub fn of(series: &Data, parts: &[Part], post_id: uuid::Uuid) -> Option<Self> {
+        let index = parts.iter().position(|part| part.id == post_id)?;
+        Some(Self {
+            series_id: series.id,
+            title: series.title.clone(),
+            slug: series.slug.clone(),
+            part: index + 1,
+            parts: parts.len(),
+            prev: index.checked_sub(1).map(|prev| parts[prev].clone()),
+            next: parts.get(index + 1).cloned(),
+        })
+    }
+}
+
+/// A post to add to a series.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "series_posts")]
+pub struct NewPart {
+    /// The series the post is added to.
+    pub series_id: uuid::Uuid,
+    /// The post added.
+    pub post_id: uuid::Uuid,
+    /// Where in the series the post is, starting from 0.
+    pub position: i32,
+    /// The user id of the creator of this relation.
+    pub created_by: uuid::Uuid,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn part(id: u128, title: &str) -> Part {
+        Part {
+            id: uuid::Uuid::from_u128(id),
+            title: title.to_owned(),
+            slug: None,
+            published_at: None,
+        }
+    }
+
+    #[test]
+    fn membership_counts_from_one_and_marks_the_neighbours() {
+        let series = Data {
+            id: uuid::Uuid::nil(),
+            created_at: Utc::now(),
+            created_by: None,
+            updated_at: Utc::now(),
+            updated_by: None,
+            title: "Series".to_owned(),
+            slug: "series".to_owned(),
+            description: String::new(),
+        };
+        let parts = [part(1, "one"), part(2, "two"), part(3, "three")];
+        let first = Membership::of(&series, &parts, parts[0].id).unwrap();
+        assert_eq!((first.part, first.parts), (1, 3));
+        assert_eq!((first.prev, first.next), (None, Some(parts[1].clone())));
+        let last = Membership::of(&series, &parts, parts[2].id).unwrap();
+        assert_eq!((last.part, last.prev, last.next), (3, Some(parts[1].clone()), None));
+        assert_eq!(Membership::of(&series, &parts, uuid::Uuid::from_u128(4)), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Models describing the state of the site as a whole.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel")]
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::Jsonb,
+};
+
+/// Whether the site takes changes. Switched by admins, such as before running a risky migration.
+/// Stored as JSON, so that new modes don't need a migration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(AsExpression, FromSqlRow),
+    sql_type = "Jsonb"
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Mode {
+    /// Everything works as usual.
+    Normal,
+    /// Everything can be read, but only admins can change anything.
+    ReadOnly {
+        /// Shown to users, such as why the site is read only and for how long.
+        message: String,
+    },
+    /// Nobody can change anything, and the blog shows the message instead of loading.
+    Maintenance {
+        /// Shown to users in place of the blog.
+        message: String,
+    },
+}
+impl Default for Mode {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+impl Mode {
+    /// What users are told, unless everything works as usual.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Self::Normal => None,
+            Self::ReadOnly { message } | Self::Maintenance { message } => Some(message.as_str()),
+        }
+    }
+}
+
+/// How the site presents itself, in the titles of its pages, its header, the previews of its pages
+/// and its mail. Configured for the server, and changed by admins without a restart. Stored as
+/// JSON, so that new parts don't need a migration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(AsExpression, FromSqlRow),
+    sql_type = "Jsonb"
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Identity {
+    /// The name of the site, which ends the title of every page.
+    pub title: String,
+    /// What the site is, which describes the pages that do not describe themselves.
+    pub tagline: String,
+    /// The logo in the header of every page.
+    pub logo: Logo,
+}
+impl Default for Identity {
+    fn default() -> Self {
+        Self {
+            title: "Benjamin Xu".to_owned(),
+            tagline: "Benjamin Xu's personal site.".to_owned(),
+            logo: Logo::Asset {
+                path: "/public/img/branding.svg".to_owned(),
+            },
+        }
+    }
+}
+
+/// The logo of the site.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Logo {
+    /// An image served by the site, such as `/public/img/branding.svg`.
+    Asset { path: String },
+    /// The markup of an svg, placed in the page as is.
+    Svg { svg: String },
+}
+
+/// Changes to how the site presents itself. Parts left out are kept as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IdentityChanges {
+    pub title: Option<String>,
+    pub tagline: Option<String>,
+    pub logo: Option<Logo>,
+}
+impl IdentityChanges {
+    /// `identity` with the changes made to it.
+    pub fn apply_to(self, identity: Identity) -> Identity {
+        Identity {
+            title: self.title.unwrap_or(identity.title),
+            tagline: self.tagline.unwrap_or(identity.tagline),
+            logo: self.logo.unwrap_or(identity.logo),
+        }
+    }
+}
+
+/// What the server knew when it served the page the blog loads from, embedded in the page so that
+/// the blog does not ask for it again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitialState {
+    /// The user logged in, or [`None`] if nobody is.
+    pub user: Option<super::users::DataNoMeta>,
+    /// Whether the site takes changes.
+    pub mode: Mode,
+    /// How the site presents itself.
+    #[serde(default)]
+    pub identity: Identity,
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<Jsonb, Pg> for Mode {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes).split_first() {
+            Some((&super::JSONB_VERSION, json)) => serde_json::from_slice(json).map_err(Into::into),
+            _ => Err("Unsupported JSONB encoding version".into()),
+        }
+    }
+}
+#[cfg(feature = "diesel")]
+impl ToSql<Jsonb, Pg> for Mode {
+    fn to_sql<W: std::io::Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(&[super::JSONB_VERSION])?;
+        serde_json::to_writer(out, self)
+            .map(|_| IsNull::No)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<Jsonb, Pg> for Identity {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes).split_first() {
+            Some((&super::JSONB_VERSION, json)) => serde_json::from_slice(json).map_err(Into::into),
+            _ => Err("Unsupported JSONB encoding version".into()),
+        }
+    }
+}
+#[cfg(feature = "diesel")]
+impl ToSql<Jsonb, Pg> for Identity {
+    fn to_sql<W: std::io::Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(&[super::JSONB_VERSION])?;
+        serde_json::to_writer(out, self)
+            .map(|_| IsNull::No)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn modes_are_tagged_by_name() {
+        assert_eq!(
+            serde_json::to_string(&Mode::Normal).unwrap(),
+            r#"{"mode":"normal"}"#
+        );
+        let read_only = Mode::ReadOnly {
+            message: "Migrating.".to_owned(),
+        };
+        let json = r#"{"mode":"read_only","message":"Migrating."}"#;
+        assert_eq!(serde_json::to_string(&read_only).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Mode>(json).unwrap(), read_only);
+        assert_eq!(read_only.message(), Some("Migrating."));
+    }
+
+    #[test]
+    fn identity_changes_keep_what_they_leave_out() {
+        let changes: IdentityChanges =
+            serde_json::from_str(r#"{"logo":{"kind":"svg","svg":"<svg></svg>"}}"#).unwrap();
+        let changed = changes.apply_to(Identity::default());
+        assert_eq!(changed.title, Identity::default().title);
+        assert_eq!(
+            changed.logo,
+            Logo::Svg {
+                svg: "<svg></svg>".to_owned()
+            }
+        );
+    }
+}
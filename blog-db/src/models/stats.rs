@@ -0,0 +1,74 @@
+//! A collection of types summarizing the contents of the database.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Counts of the rows making up the site, computed by the database.
+///
+/// Posts are counted by their furthest state: deleted before archived before published. Posts in
+/// none of those states are drafts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(QueryableByName))]
+pub struct Site {
+    /// The number of users.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub users: i64,
+    /// The number of posts that have never been published.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub draft_posts: i64,
+    /// The number of posts that are currently published.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub published_posts: i64,
+    /// The number of posts that have been archived.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub archived_posts: i64,
+    /// The number of posts that have been deleted.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub deleted_posts: i64,
+    /// When the newest post was created, if there are any posts.
+    #[cfg_attr(
+        feature = "diesel",
+        sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>"
+    )]
+    pub newest_post_at: Option<DateTime<Utc>>,
+}
+
+/// How many posts are listed under each state, as counted for the tabs of the listing. Counted the
+/// same way as in [`Site`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(QueryableByName))]
+pub struct PostCounts {
+    /// The number of posts that are currently published.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub published: i64,
+    /// The number of posts that have never been published.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub drafts: i64,
+    /// The number of posts that have been archived.
+    #[cfg_attr(feature = "diesel", sql_type = "diesel::sql_types::BigInt")]
+    pub archived: i64,
+}
+
+/// When the server last removed what it keeps no longer, if it did since it started. Each kind of
+/// record is only counted as removed once all of it was.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Retention {
+    /// When posts deleted long enough ago were last deleted for good.
+    pub deleted_posts_purged_at: Option<DateTime<Utc>>,
+    /// When webhook deliveries that failed long enough ago were last forgotten.
+    pub dead_letters_purged_at: Option<DateTime<Utc>>,
+    /// When capability grants past their expiry were last deleted.
+    pub expired_grants_purged_at: Option<DateTime<Utc>>,
+}
+
+/// Everything shown about the site as a whole to those administering it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Overview {
+    #[serde(flatten)]
+    pub site: Site,
+    pub retention: Retention,
+}
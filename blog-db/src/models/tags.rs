@@ -7,6 +7,7 @@ use crate::schema::*;
 
 /// Data representing a complete row in the table.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(
     feature = "diesel",
     derive(Identifiable, Queryable),
@@ -43,8 +44,14 @@ pub struct NewWithId<'a> {
 #[cfg(feature = "server")]
 impl<'a> From<New<'a>> for NewWithId<'a> {
     fn from(new_tag: New<'a>) -> Self {
+        Self::with_id(uuid::Uuid::new_v4(), new_tag)
+    }
+}
+impl<'a> NewWithId<'a> {
+    /// Gives the new tag a chosen id rather than a random one.
+    pub fn with_id(id: uuid::Uuid, new_tag: New<'a>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4(),
+            id,
             name: new_tag.name,
             description: new_tag.description,
             created_by: new_tag.created_by,
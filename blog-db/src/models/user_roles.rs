@@ -0,0 +1,34 @@
+//! A collection of types related to the many to many relation between users and roles.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel")]
+use crate::schema::*;
+
+/// Data representing a single user to role relation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(Queryable))]
+pub struct Data {
+    /// The user holding the role.
+    pub user_id: uuid::Uuid,
+    /// The role held by the user.
+    pub role_id: uuid::Uuid,
+    /// The time this row was created.
+    pub created_at: DateTime<Utc>,
+    /// The user id of the creator of this relation.
+    pub created_by: Option<uuid::Uuid>,
+}
+
+/// A new user to role relation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "user_roles")]
+pub struct New {
+    /// The user being granted the role.
+    pub user_id: uuid::Uuid,
+    /// The role being granted.
+    pub role_id: uuid::Uuid,
+    /// The user id of the creator of this relation.
+    pub created_by: uuid::Uuid,
+}
@@ -5,6 +5,122 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "diesel")]
 use crate::schema::*;
+#[cfg(feature = "diesel")]
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::Jsonb,
+};
+
+/// The color theme the site is shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+}
+impl Default for Theme {
+    /// The site was designed dark first.
+    fn default() -> Self {
+        Self::Dark
+    }
+}
+impl Theme {
+    /// The other theme, for toggling between the two.
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Light => Self::Dark,
+            Self::Dark => Self::Light,
+        }
+    }
+    /// The name of the theme, as it is serialized and set on the page.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+}
+
+/// How the editor opens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum EditorMode {
+    /// With only the body to edit.
+    Edit,
+    /// With the rendered body shown next to it.
+    Preview,
+}
+impl Default for EditorMode {
+    fn default() -> Self {
+        Self::Edit
+    }
+}
+
+/// What the user starts new posts with. Only ever suggested: posts are checked as usual, and the
+/// server only goes by these when asked to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PostDefaults {
+    /// The names of the tags new posts are tagged with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// What the slugs of new posts start with. Empty for no prefix.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub slug_prefix: String,
+    /// How the editor opens on new posts.
+    #[serde(default)]
+    pub editor_mode: EditorMode,
+}
+impl PostDefaults {
+    /// Whether nothing was set, which is how the defaults are left out of the preferences.
+    pub fn is_unset(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Settings the user chose for how the site looks and behaves for them. Stored as JSON, so that
+/// new settings don't need a migration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(AsExpression, FromSqlRow),
+    sql_type = "Jsonb"
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Preferences {
+    /// The color theme, unless the user left it up to their browser.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    /// The language tag of the language the site is shown in, unless the user left it up to their
+    /// browser.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// What new posts start with.
+    #[serde(default, skip_serializing_if = "PostDefaults::is_unset")]
+    pub post_defaults: PostDefaults,
+}
+#[cfg(feature = "diesel")]
+impl FromSql<Jsonb, Pg> for Preferences {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes).split_first() {
+            Some((&super::JSONB_VERSION, json)) => serde_json::from_slice(json).map_err(Into::into),
+            _ => Err("Unsupported JSONB encoding version".into()),
+        }
+    }
+}
+#[cfg(feature = "diesel")]
+impl ToSql<Jsonb, Pg> for Preferences {
+    fn to_sql<W: std::io::Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(&[super::JSONB_VERSION])?;
+        serde_json::to_writer(out, self)
+            .map(|_| IsNull::No)
+            .map_err(Into::into)
+    }
+}
 
 /// Data representing a complete row in the table.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -32,6 +148,8 @@ pub struct Data {
     pub last_name: Option<String>,
     /// Optional email.
     pub email: Option<String>,
+    /// How the site looks to the user.
+    pub preferences: Preferences,
 }
 impl Data {
     /// Strip meta data from user to send back to client.
@@ -40,8 +158,55 @@ impl Data {
     }
 }
 
+/// The parts of a user shown alongside what they wrote, such as in the bylines of posts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(Queryable))]
+pub struct AuthorSummary {
+    /// Id of the user.
+    pub id: uuid::Uuid,
+    /// User's user name.
+    pub user_name: String,
+    /// Optional first name.
+    pub first_name: Option<String>,
+    /// Optional last name.
+    pub last_name: Option<String>,
+}
+impl AuthorSummary {
+    /// The full name of the author, or their user name if they gave no name.
+    pub fn display_name(&self) -> String {
+        let names: Vec<&str> = self
+            .first_name
+            .iter()
+            .chain(self.last_name.iter())
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if names.is_empty() {
+            self.user_name.clone()
+        } else {
+            names.join(" ")
+        }
+    }
+}
+#[cfg(feature = "diesel")]
+impl AuthorSummary {
+    pub const COLUMNS: (
+        users::id,
+        users::user_name,
+        users::first_name,
+        users::last_name,
+    ) = (
+        users::id,
+        users::user_name,
+        users::first_name,
+        users::last_name,
+    );
+}
+
 /// Data representing the user building_clocks()
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct DataNoMeta {
     /// Id of the record.
     pub id: uuid::Uuid,
@@ -61,6 +226,9 @@ pub struct DataNoMeta {
     pub last_name: Option<String>,
     /// Optional email.
     pub email: Option<String>,
+    /// How the site looks to the user.
+    #[serde(default)]
+    pub preferences: Preferences,
 }
 impl From<Data> for DataNoMeta {
     fn from(data: Data) -> Self {
@@ -74,6 +242,7 @@ impl From<Data> for DataNoMeta {
             first_name: data.first_name,
             last_name: data.last_name,
             email: data.email,
+            preferences: data.preferences,
         }
     }
 }
@@ -101,8 +270,14 @@ pub struct NewWithId<'a> {
 #[cfg(feature = "server")]
 impl<'a> From<New<'a>> for NewWithId<'a> {
     fn from(new: New<'a>) -> Self {
+        Self::with_id(uuid::Uuid::new_v4(), new)
+    }
+}
+impl<'a> NewWithId<'a> {
+    /// Gives the new user a chosen id rather than a random one.
+    pub fn with_id(id: uuid::Uuid, new: New<'a>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4(),
+            id,
             user_name: new.user_name,
             created_by: new.created_by,
             updated_by: new.updated_by,
@@ -144,6 +319,7 @@ impl<'a> From<(&'a NewNoMeta, Option<uuid::Uuid>)> for New<'a> {
 
 /// Represents a new user without meta info.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct NewNoMeta {
     /// User's user name.
     pub user_name: String,
@@ -169,6 +345,8 @@ pub struct Changed<'a> {
     pub last_name: Option<&'a str>,
     /// The email of the user.
     pub email: Option<&'a str>,
+    /// Replaces everything the user had set before.
+    pub preferences: Option<Preferences>,
 }
 impl<'a> From<(&'a ChangedNoMeta, Option<uuid::Uuid>)> for Changed<'a> {
     fn from((source, updater): (&'a ChangedNoMeta, Option<uuid::Uuid>)) -> Self {
@@ -178,12 +356,14 @@ impl<'a> From<(&'a ChangedNoMeta, Option<uuid::Uuid>)> for Changed<'a> {
             first_name: source.first_name.as_ref().map(String::as_str),
             last_name: source.last_name.as_ref().map(String::as_str),
             email: source.email.as_ref().map(String::as_str),
+            preferences: source.preferences.clone(),
         }
     }
 }
 
 /// A list of changes without meta information.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ChangedNoMeta {
     /// The users's user name.
     pub user_name: Option<String>,
@@ -193,4 +373,60 @@ pub struct ChangedNoMeta {
     pub last_name: Option<String>,
     /// The email of the user.
     pub email: Option<String>,
+    /// Replaces everything the user had set before.
+    #[serde(default)]
+    pub preferences: Option<Preferences>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn themes_serialize_as_their_names() {
+        for &theme in &[Theme::Light, Theme::Dark] {
+            let name = format!("\"{}\"", theme.as_str());
+            assert_eq!(serde_json::to_string(&theme).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn post_defaults_are_read_from_partial_json() {
+        let preferences: Preferences =
+            serde_json::from_str(r#"{"post_defaults":{"tags":["rust"]}}"#).unwrap();
+        assert_eq!(preferences.post_defaults.tags, vec!["rust".to_owned()]);
+        assert_eq!(preferences.post_defaults.editor_mode, EditorMode::Edit);
+        let preview = PostDefaults {
+            editor_mode: EditorMode::Preview,
+            ..PostDefaults::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&preview).unwrap(),
+            r#"{"editor_mode":"preview"}"#
+        );
+    }
+
+    #[test]
+    fn unset_preferences_match_the_column_default() {
+        assert_eq!(serde_json::to_string(&Preferences::default()).unwrap(), "{}");
+        assert_eq!(
+            serde_json::from_str::<Preferences>("{}").unwrap(),
+            Preferences::default(),
+        );
+    }
+
+    #[test]
+    fn authors_without_names_go_by_their_user_name() {
+        let mut author = AuthorSummary {
+            id: uuid::Uuid::nil(),
+            user_name: "alterion".to_owned(),
+            first_name: Some("Ben".to_owned()),
+            last_name: Some("Xu".to_owned()),
+        };
+        assert_eq!(author.display_name(), "Ben Xu");
+        author.last_name = Some(" ".to_owned());
+        assert_eq!(author.display_name(), "Ben");
+        author.first_name = None;
+        assert_eq!(author.display_name(), "alterion");
+    }
 }
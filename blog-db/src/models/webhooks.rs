@@ -0,0 +1,241 @@
+//! Models used to represent webhooks, which are urls pinged when things happen on the site, and the
+//! deliveries to them that failed for good.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "diesel")]
+use crate::schema::*;
+
+/// Something happening on the site that webhooks can be pinged about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    PostPublished,
+    PostArchived,
+    CommentCreated,
+}
+impl Event {
+    pub const ALL: [Event; 3] = [Event::PostPublished, Event::PostArchived, Event::CommentCreated];
+    /// The bit of the event in [`Data::event_mask`]. Stored in the database, so bits must never be
+    /// reused.
+    pub fn bit(self) -> i32 {
+        match self {
+            Self::PostPublished => 1 << 0,
+            Self::PostArchived => 1 << 1,
+            Self::CommentCreated => 1 << 2,
+        }
+    }
+    /// The mask with the bit of every listed event set.
+    pub fn mask(events: &[Event]) -> i32 {
+        events.iter().fold(0, |mask, event| mask | event.bit())
+    }
+    /// The name of the event, as it is serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PostPublished => "post_published",
+            Self::PostArchived => "post_archived",
+            Self::CommentCreated => "comment_created",
+        }
+    }
+}
+
+/// Data representing a complete row in the table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "diesel",
+    derive(Identifiable, Queryable),
+    table_name = "webhooks"
+)]
+pub struct Data {
+    /// The id of the row.
+    pub id: uuid::Uuid,
+    /// The time this row was created.
+    pub created_at: DateTime<Utc>,
+    /// The creator of this record, unless they were deleted.
+    pub created_by: Option<uuid::Uuid>,
+    /// The time this row was last changed.
+    pub updated_at: DateTime<Utc>,
+    /// Who last changed this record, unless they were deleted.
+    pub updated_by: Option<uuid::Uuid>,
+    /// Where deliveries are posted to.
+    pub url: String,
+    /// What deliveries are signed with. Never sent back out once set.
+    #[serde(skip_serializing, default)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
+    pub secret: String,
+    /// The events delivered, with the [`Event::bit`] of each set.
+    pub event_mask: i32,
+    /// Whether anything is delivered at all.
+    pub enabled: bool,
+}
+impl Data {
+    /// Whether the webhook is pinged about `event`.
+    pub fn wants(&self, event: Event) -> bool {
+        self.enabled && self.event_mask & event.bit() != 0
+    }
+}
+
+/// Data to be inserted as a new row in the table. Automatically adds an id to the struct
+/// [`New`](crate::models::webhooks::New).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(Insertable), table_name = "webhooks")]
+pub struct NewWithId<'a> {
+    /// The id of the new record.
+    id: uuid::Uuid,
+    /// The creator of the webhook.
+    created_by: uuid::Uuid,
+    /// Who last changed the webhook, which is its creator.
+    updated_by: uuid::Uuid,
+    /// Where deliveries are posted to.
+    url: &'a str,
+    /// What deliveries are signed with.
+    secret: &'a str,
+    /// The events delivered.
+    event_mask: i32,
+    /// Whether anything is delivered at all.
+    enabled: bool,
+}
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "server")]
+impl<'a> From<New<'a>> for NewWithId<'a> {
+    fn from(new: New<'a>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            created_by: new.created_by,
+            updated_by: new.created_by,
+            url: new.url,
+            secret: new.secret,
+            event_mask: new.event_mask,
+            enabled: new.enabled,
+        }
+    }
+}
+
+/// A simple new webhook record, without the id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct New<'a> {
+    /// The creator of the webhook.
+    pub created_by: uuid::Uuid,
+    /// Where deliveries are posted to.
+    pub url: &'a str,
+    /// What deliveries are signed with.
+    pub secret: &'a str,
+    /// The events delivered.
+    pub event_mask: i32,
+    /// Whether anything is delivered at all.
+    pub enabled: bool,
+}
+impl<'a> From<(&'a NewNoMeta, uuid::Uuid)> for New<'a> {
+    fn from((source, creator): (&'a NewNoMeta, uuid::Uuid)) -> Self {
+        Self {
+            created_by: creator,
+            url: &source.url,
+            secret: &source.secret,
+            event_mask: source.event_mask,
+            enabled: source.enabled,
+        }
+    }
+}
+
+/// Represents a new webhook without meta info.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NewNoMeta {
+    /// Where deliveries are posted to.
+    pub url: String,
+    /// What deliveries are signed with.
+    pub secret: String,
+    /// The events delivered, with the [`Event::bit`] of each set.
+    pub event_mask: i32,
+    /// Whether anything is delivered at all. Webhooks start enabled.
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Changes to a webhook. Fields left out are kept as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "diesel", derive(AsChangeset), table_name = "webhooks")]
+pub struct Changed {
+    /// Where deliveries are posted to.
+    pub url: Option<String>,
+    /// What deliveries are signed with.
+    pub secret: Option<String>,
+    /// The events delivered.
+    pub event_mask: Option<i32>,
+    /// Whether anything is delivered at all.
+    pub enabled: Option<bool>,
+}
+
+/// A delivery that failed for good, as a complete row in the table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "diesel",
+    derive(Identifiable, Queryable),
+    table_name = "webhook_dead_letters"
+)]
+pub struct DeadLetter {
+    /// The id of the row.
+    pub id: uuid::Uuid,
+    /// The time the delivery was given up on.
+    pub created_at: DateTime<Utc>,
+    /// The webhook the delivery was for.
+    pub webhook_id: uuid::Uuid,
+    /// The name of the event delivered.
+    pub event: String,
+    /// The body of the delivery.
+    pub payload: String,
+    /// How many times delivery was attempted.
+    pub attempts: i32,
+    /// The status of the last attempt, if the webhook answered at all.
+    pub last_status: Option<i32>,
+    /// Why the last attempt failed.
+    pub error: String,
+}
+
+/// A delivery that failed for good, to be recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(Insertable),
+    table_name = "webhook_dead_letters"
+)]
+pub struct NewDeadLetter<'a> {
+    /// The id of the new record.
+    pub id: uuid::Uuid,
+    /// The webhook the delivery was for.
+    pub webhook_id: uuid::Uuid,
+    /// The name of the event delivered.
+    pub event: &'a str,
+    /// The body of the delivery.
+    pub payload: &'a str,
+    /// How many times delivery was attempted.
+    pub attempts: i32,
+    /// The status of the last attempt, if the webhook answered at all.
+    pub last_status: Option<i32>,
+    /// Why the last attempt failed.
+    pub error: &'a str,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_have_their_own_bits() {
+        let mask = Event::mask(&Event::ALL);
+        assert_eq!(mask.count_ones() as usize, Event::ALL.len());
+        assert_eq!(Event::mask(&[Event::PostPublished]), 1);
+        for event in Event::ALL.iter() {
+            let name = serde_json::to_string(event).unwrap();
+            assert_eq!(name, format!("\"{}\"", event.as_str()));
+        }
+    }
+}
@@ -2,7 +2,13 @@
 
 use chrono::{DateTime, Utc};
 
-use diesel::prelude::*;
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
+    query_dsl::LoadQuery,
+    sql_types::BigInt,
+};
 use rocket::{http::RawStr, request::FromFormValue};
 
 use crate::{models::*, schema};
@@ -37,6 +43,35 @@ impl<'v> FromFormValue<'v> for SortOrdering {
         }
     }
 }
+/// Which posts to list, by how far along they are. Deleted posts are never listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostState {
+    /// Posts published and not archived since.
+    Published,
+    /// Posts never published.
+    Draft,
+    /// Posts archived, whether or not they were published first.
+    Archived,
+}
+impl<'v> FromFormValue<'v> for PostState {
+    type Error = &'v RawStr;
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "published" => Ok(Self::Published),
+            "draft" => Ok(Self::Draft),
+            "archived" => Ok(Self::Archived),
+            _ => Err(form_value),
+        }
+    }
+}
+/// Narrows a listing of posts down to those credited to a user, tagged with a tag, or both.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PostFilter {
+    /// Only posts the user with the id is credited on.
+    pub author: Option<uuid::Uuid>,
+    /// Only posts tagged with the tag with the name.
+    pub tag: Option<String>,
+}
 /// A set of conditions for obtaining a list of posts.
 #[derive(Debug)]
 pub enum PostListing {
@@ -66,25 +101,406 @@ pub enum PostListing {
     },
 }
 
+/// What Postgres says when it cancels a statement for running past `statement_timeout`.
+const STATEMENT_TIMEOUT_MESSAGE: &str = "canceling statement due to statement timeout";
+
+/// Errors from the queries, sorted by what the caller can do about them.
+#[derive(Debug)]
+pub enum Error {
+    /// No row matched.
+    NotFound,
+    /// The change would have duplicated a value that must be unique.
+    UniqueViolation {
+        /// The name of the violated constraint, such as `posts_slug_key`.
+        constraint: String,
+    },
+    /// The change referred to a row that does not exist, or would have left rows referring to one
+    /// that does not.
+    ForeignKeyViolation,
+    /// The database could not be reached.
+    Connection,
+    /// The statement ran past the statement timeout of the connection, and was cancelled.
+    Timeout,
+    /// Anything else.
+    Other(diesel::result::Error),
+}
+impl From<diesel::result::Error> for Error {
+    fn from(e: diesel::result::Error) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error as Diesel};
+        match e {
+            Diesel::NotFound => Self::NotFound,
+            Diesel::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                Self::UniqueViolation {
+                    constraint: info.constraint_name().unwrap_or_default().to_owned(),
+                }
+            }
+            Diesel::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                Self::ForeignKeyViolation
+            }
+            Diesel::DatabaseError(DatabaseErrorKind::UnableToSendCommand, _) => Self::Connection,
+            // Diesel does not tell cancellations apart, so the message Postgres sends is matched.
+            Diesel::DatabaseError(_, info) if info.message().starts_with(STATEMENT_TIMEOUT_MESSAGE) => {
+                Self::Timeout
+            }
+            e => Self::Other(e),
+        }
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("no matching row"),
+            Self::UniqueViolation { constraint } => {
+                write!(f, "unique constraint `{}` violated", constraint)
+            }
+            Self::ForeignKeyViolation => f.write_str("foreign key constraint violated"),
+            Self::Connection => f.write_str("could not reach the database"),
+            Self::Timeout => f.write_str("statement timed out"),
+            Self::Other(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Errors from deletions that must leave something behind, such as at least one user holding a
+/// capability, or a way for a user to sign in.
+#[derive(Debug)]
+pub enum GuardedDeletionError {
+    /// The deletion would have left nobody holding the capability, or the user without a
+    /// credential, and was rolled back.
+    LastHolder,
+    /// Database errored when attempting operation.
+    Query(Error),
+}
+impl From<Error> for GuardedDeletionError {
+    fn from(e: Error) -> Self {
+        Self::Query(e)
+    }
+}
+impl From<diesel::result::Error> for GuardedDeletionError {
+    fn from(e: diesel::result::Error) -> Self {
+        Self::Query(e.into())
+    }
+}
+
+/// Used to count rows in queries diesel cannot express.
+#[derive(QueryableByName)]
+struct Count {
+    #[sql_type = "diesel::sql_types::BigInt"]
+    count: i64,
+}
+
+/// A credential as listed across the tables of each kind, in a form diesel can read.
+#[derive(QueryableByName)]
+struct CredentialRow {
+    #[sql_type = "diesel::sql_types::Uuid"]
+    id: uuid::Uuid,
+    #[sql_type = "diesel::sql_types::Text"]
+    kind: String,
+    #[sql_type = "diesel::sql_types::Timestamptz"]
+    created_at: DateTime<Utc>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>"]
+    last_used_at: Option<DateTime<Utc>>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    label: Option<String>,
+}
+impl CredentialRow {
+    /// The credential, unless its kind is not one [`credentials::Kind`] knows of.
+    fn into_summary(self) -> Option<credentials::Summary> {
+        Some(credentials::Summary {
+            id: self.id,
+            kind: credentials::Kind::from_name(&self.kind)?,
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+            label: self.label,
+        })
+    }
+}
+
+/// Every credential of the user bound to `$1`, with its kind named as by [`credentials::Kind`].
+/// Diesel lacks support for UNION, so this is written out by hand.
+const USER_CREDENTIALS: &str = "SELECT id, 'password' AS kind, created_at, last_used_at, \
+    NULL::text AS label FROM passwords WHERE user_id = $1 \
+    UNION ALL \
+    SELECT id, 'google' AS kind, created_at, last_used_at, NULL::text AS label \
+    FROM google_sso WHERE user_id = $1";
+
+/// A grant as listed across direct grants and roles, in a form diesel can read. Only grants
+/// through roles have a role name, and their `source_id` is the id of the role.
+#[derive(QueryableByName)]
+struct GrantRow {
+    #[sql_type = "diesel::sql_types::Text"]
+    capability: String,
+    #[sql_type = "diesel::sql_types::Uuid"]
+    source_id: uuid::Uuid,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    role_name: Option<String>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Uuid>"]
+    granted_by: Option<uuid::Uuid>,
+    #[sql_type = "diesel::sql_types::Timestamptz"]
+    granted_at: DateTime<Utc>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>"]
+    expires_at: Option<DateTime<Utc>>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    scope: Option<String>,
+}
+impl From<GrantRow> for capabilities::Grant {
+    fn from(row: GrantRow) -> Self {
+        let source = match row.role_name {
+            Some(role_name) => capabilities::GrantSource::Role {
+                role_id: row.source_id,
+                role_name,
+            },
+            None => capabilities::GrantSource::Direct { id: row.source_id },
+        };
+        Self {
+            capability: row.capability,
+            source,
+            granted_by: row.granted_by,
+            granted_at: row.granted_at,
+            expires_at: row.expires_at,
+            scope: row.scope,
+        }
+    }
+}
+
+/// Every unexpired grant of the user bound to `$1`, whether direct or through a role. A
+/// capability granted through a role is unique to the role, so grants are told apart by
+/// `source_id` and `capability` together. Diesel lacks support for UNION, so this is written out
+/// by hand.
+const USER_GRANTS: &str = "SELECT capability, id AS source_id, NULL::text AS role_name, \
+    created_by AS granted_by, created_at AS granted_at, expires_at, scope \
+    FROM capabilities \
+    WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > now()) \
+    UNION ALL \
+    SELECT role_capabilities.capability, roles.id, roles.name, user_roles.created_by, \
+    user_roles.created_at, NULL::timestamptz, NULL::text \
+    FROM user_roles \
+    INNER JOIN roles ON roles.id = user_roles.role_id \
+    INNER JOIN role_capabilities ON role_capabilities.role_id = roles.id \
+    WHERE user_roles.user_id = $1";
+
+/// Loads a page of `query`, a query written out by hand listing what belongs to the user bound
+/// to `$1`, ordered by `order`. The limit is clamped and pages are split as by
+/// [`Paginate::paginate`], so `order` has to end in a unique column too. The rows of the whole
+/// query are counted in the same transaction as the page is read.
+fn load_user_page<R: diesel::deserialize::QueryableByName<Pg>>(
+    conn: &PgConnection,
+    query: &str,
+    order: &str,
+    user_id: uuid::Uuid,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<page::Page<R>, Error> {
+    let (limit, offset) = (page::clamp_limit(limit), offset.unwrap_or(0).max(0));
+    conn.transaction(|| {
+        let items = diesel::sql_query(format!(
+            "SELECT * FROM ({}) AS paged ORDER BY {} LIMIT $2 OFFSET $3",
+            query, order
+        ))
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<BigInt, _>(limit)
+        .bind::<BigInt, _>(offset)
+        .load(conn)?;
+        let total = diesel::sql_query(format!(
+            "SELECT COUNT(*) AS count FROM ({}) AS listed",
+            query
+        ))
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .get_result::<Count>(conn)?
+        .count;
+        Ok(page::Page {
+            items,
+            total,
+            limit,
+            offset,
+        })
+    })
+}
+
+/// A column a page is ordered by, named as in the paged query's output, and which way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageOrder {
+    Asc(&'static str),
+    Desc(&'static str),
+}
+
+/// Queries whose rows are listed a page at a time.
+pub trait Paginate: Query + Sized {
+    /// Narrows the query down to `limit` rows after the first `offset`, counting the rows of the
+    /// whole query in the same round trip. The limit is clamped by [`page::clamp_limit`], and
+    /// missing offsets start at the first row. Load it with [`Paginated::load_page`].
+    ///
+    /// Rows are ordered by `order` after the query is narrowed down, since Postgres keeps no order
+    /// the query itself had. Pages only split the rows cleanly if `order` ends in a unique column,
+    /// and the query fails to build without one.
+    fn paginate(
+        self,
+        order: &'static [PageOrder],
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Paginated<Self> {
+        Paginated {
+            query: self,
+            order,
+            limit: page::clamp_limit(limit),
+            offset: offset.unwrap_or(0).max(0),
+        }
+    }
+}
+impl<T: Query> Paginate for T {}
+
+/// A query narrowed down to a page by [`Paginate::paginate`]. Each row carries the number of rows
+/// of the whole query, counted by a window over the query before it is narrowed down.
+#[derive(Debug, Clone, Copy)]
+pub struct Paginated<T> {
+    query: T,
+    order: &'static [PageOrder],
+    limit: i64,
+    offset: i64,
+}
+impl<T> Paginated<T> {
+    /// Loads the page. Pages past the last row are empty, and count the rows of the query again
+    /// to tell how far off they are.
+    pub fn load_page<U>(self, conn: &PgConnection) -> Result<page::Page<U>, Error>
+    where
+        Self: LoadQuery<PgConnection, (U, i64)> + Clone,
+    {
+        let (limit, offset) = (self.limit, self.offset);
+        let first = Self {
+            limit: 1,
+            offset: 0,
+            ..self.clone()
+        };
+        let rows: Vec<(U, i64)> = self.load(conn)?;
+        let total = match rows.first() {
+            Some((_, total)) => *total,
+            None if offset > 0 => first.load::<(U, i64)>(conn)?.first().map_or(0, |(_, total)| *total),
+            None => 0,
+        };
+        Ok(page::Page {
+            items: rows.into_iter().map(|(item, _)| item).collect(),
+            total,
+            limit,
+            offset,
+        })
+    }
+}
+// The order is not part of the type, so the statement cannot be cached by it.
+impl<T> QueryId for Paginated<T> {
+    type QueryId = ();
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+impl<T: Query> Query for Paginated<T> {
+    type SqlType = (T::SqlType, BigInt);
+}
+impl<T> RunQueryDsl<PgConnection> for Paginated<T> {}
+impl<T: QueryFragment<Pg>> QueryFragment<Pg> for Paginated<T> {
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        out.push_sql("SELECT *, COUNT(*) OVER () FROM (");
+        self.query.walk_ast(out.reborrow())?;
+        if self.order.is_empty() {
+            return Err(diesel::result::Error::QueryBuilderError(
+                "Pages must be ordered.".into(),
+            ));
+        }
+        out.push_sql(") AS paged ORDER BY ");
+        for (i, order) in self.order.iter().enumerate() {
+            if i > 0 {
+                out.push_sql(", ");
+            }
+            let (column, direction) = match order {
+                PageOrder::Asc(column) => (column, " ASC"),
+                PageOrder::Desc(column) => (column, " DESC"),
+            };
+            out.push_identifier(column)?;
+            out.push_sql(direction);
+        }
+        out.push_sql(" LIMIT ");
+        out.push_bind_param::<BigInt, _>(&self.limit)?;
+        out.push_sql(" OFFSET ");
+        out.push_bind_param::<BigInt, _>(&self.offset)?;
+        Ok(())
+    }
+}
+
+/// Key for the advisory lock serializing deletions guarded by
+/// [`CapabilityQuery::delete_preserving_capability`].
+const CAPABILITY_GUARD_LOCK: i64 = 0x6361_7073;
+
 pub trait DBConn {
     fn conn(&self) -> &PgConnection;
+    /// Runs `f` in a transaction, keeping what it did only if it succeeds. `f` is handed a
+    /// [`Transaction`] with the same queries as `self`. Transactions started inside of `f` become
+    /// savepoints, so only their own changes are rolled back if they fail.
+    fn transaction<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        Self: Sized,
+        E: From<diesel::result::Error>,
+        F: FnOnce(&Transaction) -> Result<T, E>,
+    {
+        let conn = self.conn();
+        conn.transaction(|| f(&Transaction(conn)))
+    }
+}
+
+/// A connection in the middle of a transaction started by [`DBConn::transaction`].
+pub struct Transaction<'a>(&'a PgConnection);
+impl DBConn for Transaction<'_> {
+    fn conn(&self) -> &PgConnection {
+        self.0
+    }
 }
 
 pub trait PostQuery: DBConn {
-    /// Find posts based on the provided conditions.
-    fn find_posts_with_post_listing_conditions(
+    /// Find posts based on the provided conditions, narrowed down by `filter`, along with their
+    /// authors. The authors are joined in, so that the whole listing takes one query. Deleted
+    /// posts are never listed.
+    fn posts_with_authors(
         &self,
         conditions: PostListing,
+        filter: &PostFilter,
         show_unpublished: bool,
-    ) -> Result<Vec<posts::BasicData>, diesel::result::Error> {
-        log::debug!("Attempting to find posts with {:?} query.", conditions);
-        let query = schema::posts::table;
-        let query = if show_unpublished {
-            query.into_boxed()
-        } else {
-            query.filter(schema::posts::published_at.is_not_null()).into_boxed()
-        };
-        match conditions {
+        state: Option<PostState>,
+    ) -> Result<Vec<posts::Listed>, Error> {
+        use schema::posts::{archived_at, deleted_at, id, published_at, title};
+        log::debug!(
+            "Attempting to find {:?} posts matching {:?} with {:?} query.",
+            state,
+            filter,
+            conditions
+        );
+        let mut query = schema::posts::table
+            .inner_join(schema::users::table.on(schema::users::id.eq(schema::posts::created_by)))
+            .select((posts::BasicData::columns(), users::AuthorSummary::COLUMNS))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+        if !show_unpublished {
+            query = query.filter(published_at.is_not_null());
+        }
+        query = match state {
+            None => query,
+            Some(PostState::Published) => query
+                .filter(archived_at.is_null())
+                .filter(published_at.is_not_null()),
+            Some(PostState::Draft) => query
+                .filter(archived_at.is_null())
+                .filter(published_at.is_null()),
+            Some(PostState::Archived) => query.filter(archived_at.is_not_null()),
+        };
+        if let Some(author) = filter.author {
+            let credited = schema::post_authors::table
+                .filter(schema::post_authors::user_id.eq(author))
+                .select(schema::post_authors::post_id);
+            query = query.filter(id.eq_any(credited));
+        }
+        if let Some(tag) = filter.tag.as_ref() {
+            let tagged = schema::post_tag_junctions::table
+                .inner_join(schema::tags::table)
+                .filter(schema::tags::name.eq(tag))
+                .select(schema::post_tag_junctions::post_id);
+            query = query.filter(id.eq_any(tagged));
+        }
+        let (order_by, ord) = match conditions {
             PostListing::Date {
                 start,
                 stop,
@@ -92,33 +508,10 @@ pub trait PostQuery: DBConn {
                 ord,
                 limit,
             } => {
-                let query = query
-                    .select(posts::BasicData::COLUMNS)
-                    .filter(
-                        schema::posts::published_at
-                            .gt(start)
-                            .and(schema::posts::published_at.lt(stop)),
-                    )
+                query = query
+                    .filter(published_at.gt(start).and(published_at.lt(stop)))
                     .limit(limit as i64);
-                // TODO try to make this better (maybe Box?)
-                match order_by {
-                    OrderingField::Date => match ord {
-                        SortOrdering::Ascending => query
-                            .order(schema::posts::published_at.asc())
-                            .load(self.conn()),
-                        SortOrdering::Descending => query
-                            .order(schema::posts::published_at.desc())
-                            .load(self.conn()),
-                    },
-                    OrderingField::AlphabeticalTitle => match ord {
-                        SortOrdering::Ascending => {
-                            query.order(schema::posts::title.asc()).load(self.conn())
-                        }
-                        SortOrdering::Descending => {
-                            query.order(schema::posts::title.desc()).load(self.conn())
-                        }
-                    },
-                }
+                (order_by, ord)
             }
             PostListing::LimAndOffset {
                 offset,
@@ -126,52 +519,66 @@ pub trait PostQuery: DBConn {
                 order_by,
                 ord,
             } => {
-                match order_by {
-                    OrderingField::Date => match ord {
-                        SortOrdering::Ascending => query
-                            .select(posts::BasicData::COLUMNS)
-                            .order(schema::posts::published_at.asc())
-                            .offset(offset as i64)
-                            .limit(lim as i64)
-                            .load(self.conn()),
-                        SortOrdering::Descending => query
-                            .select(posts::BasicData::COLUMNS)
-                            .order(schema::posts::published_at.desc())
-                            .offset(offset as i64)
-                            .limit(lim as i64)
-                            .load(self.conn()),
-                    },
-                    OrderingField::AlphabeticalTitle => match ord {
-                        SortOrdering::Ascending => query
-                            .select(posts::BasicData::COLUMNS)
-                            .order(schema::posts::title.asc())
-                            .offset(offset as i64)
-                            .limit(lim as i64)
-                            .load(self.conn()),
-                        SortOrdering::Descending => query
-                            .select(posts::BasicData::COLUMNS)
-                            .order(schema::posts::title.desc())
-                            .offset(offset as i64)
-                            .limit(lim as i64)
-                            .load(self.conn()),
-                    },
-                }
+                query = query.offset(offset as i64).limit(lim as i64);
+                (order_by, ord)
             }
-        }
+        };
+        let query = match (order_by, ord) {
+            (OrderingField::Date, SortOrdering::Ascending) => query.order(published_at.asc()),
+            (OrderingField::Date, SortOrdering::Descending) => query.order(published_at.desc()),
+            (OrderingField::AlphabeticalTitle, SortOrdering::Ascending) => query.order(title.asc()),
+            (OrderingField::AlphabeticalTitle, SortOrdering::Descending) => {
+                query.order(title.desc())
+            }
+        };
+        query.load(self.conn()).map_err(Error::from)
     }
 
-    /// Inserts the provided new post into the database. Returns the inserted post on success.
+    /// Inserts the provided new post into the database, crediting its creator as its only
+    /// author. Returns the inserted post on success.
     fn insert_post<'a, N: Into<posts::NewWithId<'a>>>(
         &self,
         new: N,
-    ) -> Result<posts::Data, diesel::result::Error> {
-        diesel::insert_into(schema::posts::table)
-            .values(&new.into())
-            .get_result(self.conn())
+    ) -> Result<posts::Data, Error> {
+        self.conn()
+            .transaction(|| {
+                let post: posts::Data = diesel::insert_into(schema::posts::table)
+                    .values(&new.into())
+                    .get_result(self.conn())?;
+                diesel::insert_into(schema::post_authors::table)
+                    .values(&post_authors::New {
+                        post_id: post.id,
+                        user_id: post.created_by,
+                        role: post_authors::Role::Author.as_str(),
+                        position: 0,
+                        created_by: post.created_by,
+                    })
+                    .execute(self.conn())?;
+                Ok(post)
+            })
+            .map_err(Error::from)
     }
     /// Find the provided new post into the database. Returns the inserted post on success.
-    fn find_post_with_id(&self, id: uuid::Uuid) -> Result<posts::Data, diesel::result::Error> {
-        schema::posts::table.find(id).get_result(self.conn())
+    fn find_post_with_id(&self, id: uuid::Uuid) -> Result<posts::Data, Error> {
+        schema::posts::table
+            .find(id)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find the post with the provided friendly name.
+    fn find_post_with_slug(&self, slug: &str) -> Result<posts::Data, Error> {
+        schema::posts::table
+            .filter(schema::posts::slug.eq(slug))
+            .first(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find the ids of the tags on the post with the provided id.
+    fn find_post_tag_ids(&self, id: uuid::Uuid) -> Result<Vec<uuid::Uuid>, Error> {
+        schema::post_tag_junctions::table
+            .filter(schema::post_tag_junctions::post_id.eq(id))
+            .select(schema::post_tag_junctions::tag_id)
+            .load(self.conn())
+            .map_err(Error::from)
     }
     /// Given an id and a changeset, update the matching row. Returns either the number of rows
     /// updated or an error.
@@ -180,10 +587,11 @@ pub trait PostQuery: DBConn {
         &self,
         id: uuid::Uuid,
         update: &posts::Changed,
-    ) -> Result<usize, diesel::result::Error> {
+    ) -> Result<usize, Error> {
         diesel::update(schema::posts::table.find(id))
             .set(update)
             .execute(self.conn())
+            .map_err(Error::from)
     }
     /// Given an id, delete the matching row. Returns either the number of rows updated or an
     /// error.
@@ -192,10 +600,11 @@ pub trait PostQuery: DBConn {
         &self,
         id: uuid::Uuid,
         deletion: &posts::Deletion,
-    ) -> Result<usize, diesel::result::Error> {
+    ) -> Result<usize, Error> {
         diesel::update(schema::posts::table.find(id))
             .set(deletion)
             .execute(self.conn())
+            .map_err(Error::from)
     }
     /// Given an id, publish the matching row. Returns either the number of rows updated or an
     /// error.
@@ -203,10 +612,11 @@ pub trait PostQuery: DBConn {
         &self,
         id: uuid::Uuid,
         publishing: posts::Publishing,
-    ) -> Result<usize, diesel::result::Error> {
+    ) -> Result<usize, Error> {
         diesel::update(schema::posts::table.find(id))
             .set(publishing)
             .execute(self.conn())
+            .map_err(Error::from)
     }
     /// Given an id, archive the matching row. Returns either the number of rows updated or an
     /// error.
@@ -214,24 +624,241 @@ pub trait PostQuery: DBConn {
         &self,
         id: uuid::Uuid,
         archival: posts::Archival,
-    ) -> Result<usize, diesel::result::Error> {
+    ) -> Result<usize, Error> {
         diesel::update(schema::posts::table.find(id))
             .set(archival)
             .execute(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find the users credited on the post with the provided id, in the order of the byline.
+    fn find_post_authors(&self, id: uuid::Uuid) -> Result<Vec<post_authors::Author>, Error> {
+        let rows: Vec<(users::AuthorSummary, String)> = schema::post_authors::table
+            .inner_join(schema::users::table)
+            .filter(schema::post_authors::post_id.eq(id))
+            .select((users::AuthorSummary::COLUMNS, schema::post_authors::role))
+            .order(schema::post_authors::position.asc())
+            .load(self.conn())?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(user, role)| match post_authors::Role::from_name(&role) {
+                Some(role) => Some(post_authors::Author { user, role }),
+                None => {
+                    log::error!("Skipping author {:?} with unknown role {:?}.", user.id, role);
+                    None
+                }
+            })
+            .collect())
+    }
+    /// Find the ids of the users credited on the post with the provided id.
+    fn find_post_author_ids(&self, id: uuid::Uuid) -> Result<Vec<uuid::Uuid>, Error> {
+        schema::post_authors::table
+            .filter(schema::post_authors::post_id.eq(id))
+            .select(schema::post_authors::user_id)
+            .load(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find the post with the provided id, along with the users credited on it and where it is
+    /// in the series it is part of. See [`find_authored_post`](PostQuery::find_authored_post).
+    fn find_authored_post_with_id(
+        &self,
+        id: uuid::Uuid,
+        show_unpublished: bool,
+    ) -> Result<posts::Authored, Error>
+    where
+        Self: Sized,
+    {
+        let post = self.find_post_with_id(id)?;
+        self.find_authored_post(post, show_unpublished)
+    }
+    /// Finds the users credited on the post, where it is in the series it is part of, and the
+    /// reactions left on it. Unpublished parts are only counted if `show_unpublished`. The
+    /// rendered body and its outline are left for the caller to fill in, and the post is taken to
+    /// be shown as its state says.
+    fn find_authored_post(
+        &self,
+        post: posts::Data,
+        show_unpublished: bool,
+    ) -> Result<posts::Authored, Error>
+    where
+        Self: Sized,
+    {
+        let authors = self.find_post_authors(post.id)?;
+        let series = self.find_post_series(post.id, show_unpublished)?;
+        let reactions = self.count_reactions(post.id)?;
+        Ok(posts::Authored {
+            visibility: posts::Visibility::of(&post).unwrap_or_default(),
+            post,
+            authors,
+            series,
+            outline: vec![],
+            html: None,
+            reactions,
+        })
+    }
+    /// Replace the users credited on the post with the provided id, keeping the order they are
+    /// given in. Returns the users now credited on the post.
+    fn replace_post_authors(
+        &self,
+        post_id: uuid::Uuid,
+        authors: &[post_authors::Assigned],
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<post_authors::Author>, Error> {
+        self.conn()
+            .transaction(|| {
+                diesel::delete(
+                    schema::post_authors::table
+                        .filter(schema::post_authors::post_id.eq(post_id)),
+                )
+                .execute(self.conn())?;
+                let credits: Vec<_> = authors
+                    .iter()
+                    .enumerate()
+                    .map(|(position, author)| post_authors::New {
+                        post_id,
+                        user_id: author.user_id,
+                        role: author.role.as_str(),
+                        position: position as i32,
+                        created_by: user_id,
+                    })
+                    .collect();
+                if !credits.is_empty() {
+                    diesel::insert_into(schema::post_authors::table)
+                        .values(&credits)
+                        .execute(self.conn())?;
+                }
+                Ok(())
+            })
+            .map_err(Error::from)?;
+        self.find_post_authors(post_id)
     }
 }
 impl<T: DBConn> PostQuery for T {}
 
+/// The columns making up [`tags::Data`].
+const TAG_COLUMNS: (
+    schema::tags::id,
+    schema::tags::name,
+    schema::tags::description,
+) = (
+    schema::tags::id,
+    schema::tags::name,
+    schema::tags::description,
+);
+
+pub trait TagQuery: DBConn {
+    /// Find a page of the tags, ordered by name.
+    fn find_tags_page(
+        &self,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<page::Page<tags::Data>, Error> {
+        schema::tags::table
+            .select(TAG_COLUMNS)
+            .paginate(&[PageOrder::Asc("name"), PageOrder::Asc("id")], limit, offset)
+            .load_page(self.conn())
+    }
+    /// Creates a tag. Returns the created tag.
+    fn create_tag<'a, N: Into<tags::NewWithId<'a>>>(
+        &self,
+        new: N,
+    ) -> Result<tags::Data, Error> {
+        diesel::insert_into(schema::tags::table)
+            .values(&new.into())
+            .returning(TAG_COLUMNS)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Deletes the tag with the provided id, taking it off every post it was on. Returns the
+    /// deleted tag.
+    fn delete_tag_with_id(&self, id: uuid::Uuid) -> Result<tags::Data, Error> {
+        diesel::delete(schema::tags::table.find(id))
+            .returning(TAG_COLUMNS)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find the tags on the post with the provided id, ordered by name.
+    fn find_post_tags(
+        &self,
+        post_id: uuid::Uuid,
+    ) -> Result<Vec<tags::Data>, Error> {
+        schema::tags::table
+            .inner_join(schema::post_tag_junctions::table)
+            .filter(schema::post_tag_junctions::post_id.eq(post_id))
+            .select(TAG_COLUMNS)
+            .order(schema::tags::name.asc())
+            .load(self.conn())
+            .map_err(Error::from)
+    }
+    /// Replace the tags on the post with the provided id with the named tags, creating the tags
+    /// that do not exist yet with an empty description. Returns the tags now on the post, ordered
+    /// by name.
+    fn replace_post_tags(
+        &self,
+        post_id: uuid::Uuid,
+        names: &[&str],
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<tags::Data>, Error> {
+        self.conn().transaction(|| {
+            diesel::delete(
+                schema::post_tag_junctions::table
+                    .filter(schema::post_tag_junctions::post_id.eq(post_id)),
+            )
+            .execute(self.conn())?;
+            if names.is_empty() {
+                return Ok(vec![]);
+            }
+            let new_tags: Vec<tags::NewWithId> = names
+                .iter()
+                .map(|name| {
+                    tags::New {
+                        name,
+                        description: "",
+                        created_by: user_id,
+                    }
+                    .into()
+                })
+                .collect();
+            diesel::insert_into(schema::tags::table)
+                .values(&new_tags)
+                .on_conflict(schema::tags::name)
+                .do_nothing()
+                .execute(self.conn())?;
+            let tags: Vec<tags::Data> = schema::tags::table
+                .filter(schema::tags::name.eq_any(names))
+                .select(TAG_COLUMNS)
+                .order(schema::tags::name.asc())
+                .load(self.conn())?;
+            let junctions: Vec<_> = tags
+                .iter()
+                .map(|tag| post_tag_junctions::NewPostTagJunction {
+                    post_id,
+                    tag_id: tag.id,
+                    created_by: user_id,
+                })
+                .collect();
+            diesel::insert_into(schema::post_tag_junctions::table)
+                .values(&junctions)
+                .execute(self.conn())?;
+            Ok(tags)
+        })
+        .map_err(Error::from)
+    }
+}
+impl<T: DBConn> TagQuery for T {}
+
 pub trait UserQuery: DBConn {
     /// Locate a user given an id.
-    fn find_user_by_id(&self, id: uuid::Uuid) -> Result<users::Data, diesel::result::Error> {
-        schema::users::table.find(id).get_result(self.conn())
+    fn find_user_by_id(&self, id: uuid::Uuid) -> Result<users::Data, Error> {
+        schema::users::table
+            .find(id)
+            .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Locate a user given an user name.
     fn find_user_by_user_name(
         &self,
         user_name: &str,
-    ) -> Result<users::Data, diesel::result::Error> {
+    ) -> Result<users::Data, Error> {
         use log::*;
         trace!("Searching for {:?}.", user_name);
         let query = schema::users::table.filter(schema::users::user_name.eq(user_name));
@@ -239,30 +866,64 @@ pub trait UserQuery: DBConn {
             "Query constructed: {}. Now running...",
             diesel::debug_query::<diesel::pg::Pg, _>(&query)
         );
-        query.first(self.conn())
+        query.first(self.conn()).map_err(Error::from)
+    }
+    /// Find up to `limit` users whose user name contains `text`, ignoring case, ordered by user
+    /// name.
+    fn search_users(&self, text: &str, limit: i64) -> Result<Vec<users::AuthorSummary>, Error> {
+        use diesel::PgTextExpressionMethods;
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        schema::users::table
+            .filter(schema::users::user_name.ilike(format!("%{}%", escaped)))
+            .select(users::AuthorSummary::COLUMNS)
+            .order(schema::users::user_name.asc())
+            .limit(limit)
+            .load(self.conn())
+            .map_err(Error::from)
+    }
+    /// Get every user, ordered by user name.
+    fn list_users(&self) -> Result<Vec<users::Data>, Error> {
+        schema::users::table
+            .order(schema::users::user_name.asc())
+            .load(self.conn())
+            .map_err(Error::from)
+    }
+    /// Count every user.
+    fn count_users(&self) -> Result<i64, Error> {
+        schema::users::table
+            .count()
+            .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Create a user from the provided user info.
     fn create_user<'a, N: Into<users::NewWithId<'a>>>(
         &self,
         new_user: N,
-    ) -> Result<users::Data, diesel::result::Error> {
+    ) -> Result<users::Data, Error> {
         diesel::insert_into(schema::users::table)
             .values(&new_user.into())
             .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Delete a user given the id.
-    fn delete_user_by_id(&self, id: uuid::Uuid) -> Result<users::Data, diesel::result::Error> {
-        diesel::delete(schema::users::table.find(id)).get_result(self.conn())
+    fn delete_user_by_id(&self, id: uuid::Uuid) -> Result<users::Data, Error> {
+        diesel::delete(schema::users::table.find(id))
+            .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Updates a user given the id and change set.
     fn update_user_by_id(
         &self,
         id: uuid::Uuid,
         update: users::Changed<'_>,
-    ) -> Result<users::Data, diesel::result::Error> {
+    ) -> Result<users::Data, Error> {
         diesel::update(schema::users::table.find(id))
             .set(update)
             .get_result(self.conn())
+            .map_err(Error::from)
     }
 }
 impl<T: DBConn> UserQuery for T {}
@@ -272,26 +933,29 @@ pub trait PWQuery: DBConn {
     fn find_pw_hash_by_user(
         &self,
         user: &users::Data,
-    ) -> Result<credentials::pw::Data, diesel::result::Error> {
+    ) -> Result<credentials::pw::Data, Error> {
         let query = credentials::pw::Data::belonging_to(user);
         log::trace!(
             "Query constructed: {}. Now running...",
             diesel::debug_query::<diesel::pg::Pg, _>(&query)
         );
-        query.first(self.conn())
+        query.first(self.conn()).map_err(Error::from)
     }
     /// Given the password's id, find it.
     fn find_pw_by_id(
         &self,
         id: uuid::Uuid,
-    ) -> Result<credentials::pw::Data, diesel::result::Error> {
-        schema::passwords::table.find(id).get_result(self.conn())
+    ) -> Result<credentials::pw::Data, Error> {
+        schema::passwords::table
+            .find(id)
+            .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Create a password hash given some information.
     fn create_pw_hash(
         &self,
         new_pw: credentials::pw::New,
-    ) -> Result<credentials::pw::Data, diesel::result::Error> {
+    ) -> Result<credentials::pw::Data, Error> {
         use log::*;
         let record = credentials::pw::NewWithId::from(new_pw);
         let query = diesel::insert_into(schema::passwords::table).values(&record);
@@ -299,88 +963,1679 @@ pub trait PWQuery: DBConn {
             "Running query to save password: {:?}",
             diesel::debug_query(&query)
         );
-        query.get_result(self.conn())
+        query.get_result(self.conn()).map_err(Error::from)
     }
     /// Update a password hash given the user id and changes.
     fn update_pw_hash_for_user_id(
         &self,
         user_id: uuid::Uuid,
         changed_pw: credentials::pw::Changed,
-    ) -> Result<credentials::pw::Data, diesel::result::Error> {
+    ) -> Result<credentials::pw::Data, Error> {
         diesel::update(schema::passwords::table.filter(schema::passwords::user_id.eq(user_id)))
             .set(&changed_pw)
             .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Count the number of password hashes given the user.
-    fn count_pw_by_user(&self, user: &users::Data) -> Result<i64, diesel::result::Error> {
+    fn count_pw_by_user(&self, user: &users::Data) -> Result<i64, Error> {
         credentials::pw::Data::belonging_to(user)
             .count()
             .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Delete the password hash given its id in the database.
     fn delete_pw_by_id(
         &self,
         id: uuid::Uuid,
-    ) -> Result<credentials::pw::Data, diesel::result::Error> {
-        diesel::delete(schema::passwords::table.find(id)).get_result(self.conn())
+    ) -> Result<credentials::pw::Data, Error> {
+        diesel::delete(schema::passwords::table.find(id))
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Note that the password with the given id was just signed in with.
+    fn mark_pw_used(&self, id: uuid::Uuid) -> Result<(), Error> {
+        diesel::update(schema::passwords::table.find(id))
+            .set(schema::passwords::last_used_at.eq(diesel::dsl::now))
+            .execute(self.conn())
+            .map(|_| ())
+            .map_err(Error::from)
     }
 }
 impl<T: DBConn> PWQuery for T {}
 
+pub trait CredentialQuery: DBConn {
+    /// Find a page of the credentials of the user, of whatever kind, oldest first.
+    fn find_credentials_page(
+        &self,
+        user_id: uuid::Uuid,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<page::Page<credentials::Summary>, Error> {
+        let rows: page::Page<CredentialRow> = load_user_page(
+            self.conn(),
+            USER_CREDENTIALS,
+            "created_at, id",
+            user_id,
+            limit,
+            offset,
+        )?;
+        Ok(rows.map_items(|rows| {
+            rows.into_iter()
+                .filter_map(CredentialRow::into_summary)
+                .collect()
+        }))
+    }
+    /// Count the credentials of the user, of whatever kind.
+    fn count_credentials_by_user(&self, user_id: uuid::Uuid) -> Result<i64, Error> {
+        diesel::sql_query(format!(
+            "SELECT COUNT(*) AS count FROM ({}) AS credentials",
+            USER_CREDENTIALS
+        ))
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .get_result::<Count>(self.conn())
+        .map(|c| c.count)
+        .map_err(Error::from)
+    }
+    /// Run `delete`, which deletes a credential of the user, rolling it back if the user would be
+    /// left without any way to sign in. The row of the user is locked meanwhile, so that
+    /// deleting two credentials at once can't leave the user with none either.
+    fn delete_preserving_credential<T, F: FnOnce() -> Result<T, Error>>(
+        &self,
+        user_id: uuid::Uuid,
+        delete: F,
+    ) -> Result<T, GuardedDeletionError> {
+        self.conn().transaction(|| {
+            schema::users::table
+                .find(user_id)
+                .select(schema::users::id)
+                .for_update()
+                .get_result::<uuid::Uuid>(self.conn())
+                .map_err(Error::from)?;
+            let deleted = delete()?;
+            if self.count_credentials_by_user(user_id)? == 0 {
+                Err(GuardedDeletionError::LastHolder)
+            } else {
+                Ok(deleted)
+            }
+        })
+    }
+}
+impl<T: DBConn> CredentialQuery for T {}
+
 pub trait CapabilityQuery: DBConn {
     /// Get capabilities based on the user.
     fn get_user_capabilities(
         &self,
         user: &users::Data,
-    ) -> Result<Vec<capabilities::Data>, diesel::result::Error> {
-        capabilities::Data::belonging_to(user).load(self.conn())
+    ) -> Result<Vec<capabilities::Data>, Error> {
+        capabilities::Data::belonging_to(user)
+            .load(self.conn())
+            .map_err(Error::from)
+    }
+    /// Get all unexpired capabilities the user holds, whether granted directly or through a role.
+    fn get_effective_user_capabilities(
+        &self,
+        user: &users::Data,
+    ) -> Result<Vec<capabilities::Effective>, Error> {
+        self.get_effective_capabilities_by_user_id(user.id)
+    }
+    /// Like [`get_effective_user_capabilities`](Self::get_effective_user_capabilities), for when
+    /// only the id of the user is at hand. Takes a single query.
+    fn get_effective_capabilities_by_user_id(
+        &self,
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<capabilities::Effective>, Error> {
+        // Diesel lacks support for UNION, so this is written out by hand.
+        diesel::sql_query(
+            "SELECT capability, expires_at, scope FROM capabilities \
+            WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > now()) \
+            UNION \
+            SELECT role_capabilities.capability, NULL::timestamptz, NULL::text \
+            FROM role_capabilities \
+            INNER JOIN user_roles ON user_roles.role_id = role_capabilities.role_id \
+            WHERE user_roles.user_id = $1",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .load(self.conn())
+        .map_err(Error::from)
+    }
+    /// Get every unexpired capability the user holds, annotated with where each comes from. A
+    /// capability granted both directly and through roles shows up once per grant. Meant for
+    /// tools that act on all of them; listings should use
+    /// [`get_user_capability_grants_page`](Self::get_user_capability_grants_page).
+    fn get_user_capability_grants(
+        &self,
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<capabilities::Grant>, Error> {
+        let rows: Vec<GrantRow> = diesel::sql_query(USER_GRANTS)
+            .bind::<diesel::sql_types::Uuid, _>(user_id)
+            .load(self.conn())?;
+        Ok(rows.into_iter().map(capabilities::Grant::from).collect())
+    }
+    /// Like [`get_user_capability_grants`](Self::get_user_capability_grants), a page at a time,
+    /// oldest first.
+    fn get_user_capability_grants_page(
+        &self,
+        user_id: uuid::Uuid,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<page::Page<capabilities::Grant>, Error> {
+        let rows: page::Page<GrantRow> = load_user_page(
+            self.conn(),
+            USER_GRANTS,
+            "granted_at, source_id, capability",
+            user_id,
+            limit,
+            offset,
+        )?;
+        Ok(rows.map_items(|rows| rows.into_iter().map(capabilities::Grant::from).collect()))
+    }
+    /// Count the users holding a capability everywhere, whether granted directly or through a
+    /// role. Expired and scoped grants are not counted.
+    fn count_users_with_capability(
+        &self,
+        capability: capabilities::Capability,
+    ) -> Result<i64, Error> {
+        diesel::sql_query(
+            "SELECT COUNT(*) AS count FROM ( \
+            SELECT users.id FROM users \
+            INNER JOIN capabilities ON capabilities.user_id = users.id \
+            WHERE capabilities.capability = $1 AND capabilities.scope IS NULL \
+            AND (capabilities.expires_at IS NULL OR capabilities.expires_at > now()) \
+            UNION \
+            SELECT users.id FROM users \
+            INNER JOIN user_roles ON user_roles.user_id = users.id \
+            INNER JOIN role_capabilities ON role_capabilities.role_id = user_roles.role_id \
+            WHERE role_capabilities.capability = $1 \
+            ) AS holders",
+        )
+        .bind::<diesel::sql_types::Text, _>(capability.as_str())
+        .get_result::<Count>(self.conn())
+        .map(|c| c.count)
+        .map_err(Error::from)
+    }
+    /// Run a deletion, rolling it back if it leaves nobody holding `capability` when somebody held
+    /// it before. Deletions when nobody held it go through, so that a site without holders, such
+    /// as one whose only holder's grant expired, can still be changed.
+    ///
+    /// Guarded deletions take the same advisory lock, so two concurrent deletions cannot both see
+    /// the other's holder as remaining.
+    fn delete_preserving_capability<T, F: FnOnce() -> Result<T, Error>>(
+        &self,
+        capability: capabilities::Capability,
+        delete: F,
+    ) -> Result<T, GuardedDeletionError> {
+        self.conn().transaction(|| {
+            diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+                .bind::<diesel::sql_types::BigInt, _>(CAPABILITY_GUARD_LOCK)
+                .execute(self.conn())?;
+            let held = self.count_users_with_capability(capability)? > 0;
+            let deleted = delete()?;
+            if held && self.count_users_with_capability(capability)? == 0 {
+                Err(GuardedDeletionError::LastHolder)
+            } else {
+                Ok(deleted)
+            }
+        })
     }
     /// Create all capabilities in the [`Vec`].
     fn create_all_capabilities<'a>(
         &'_ self,
         capabilities: Vec<capabilities::New<'a>>,
-    ) -> Result<Vec<capabilities::Data>, diesel::result::Error> {
+    ) -> Result<Vec<capabilities::Data>, Error> {
         let to_create: Vec<capabilities::NewWithId> =
             capabilities.into_iter().map(|new| new.into()).collect();
         diesel::insert_into(schema::capabilities::table)
             .values(to_create)
             .get_results(self.conn())
+            .map_err(Error::from)
     }
     /// Get all capabilities matching the provided id. There should only be one.
     fn get_capability_with_id(
         &self,
         id: uuid::Uuid,
-    ) -> Result<capabilities::Data, diesel::result::Error> {
-        schema::capabilities::table.find(id).get_result(self.conn())
+    ) -> Result<capabilities::Data, Error> {
+        schema::capabilities::table
+            .find(id)
+            .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Delete all capabilities matching the provided id. There should only be one.
     fn delete_capability_with_id(
         &self,
         id: uuid::Uuid,
-    ) -> Result<capabilities::Data, diesel::result::Error> {
-        diesel::delete(schema::capabilities::table.find(id)).get_result(self.conn())
+    ) -> Result<capabilities::Data, Error> {
+        diesel::delete(schema::capabilities::table.find(id))
+            .get_result(self.conn())
+            .map_err(Error::from)
     }
     /// Delete all capabilities matching the provided user_id. There can (will usually be) multiple.
     fn delete_capabilities_by_user_id(
         &self,
         user_id: uuid::Uuid,
-    ) -> Result<Vec<capabilities::Data>, diesel::result::Error> {
+    ) -> Result<Vec<capabilities::Data>, Error> {
         diesel::delete(
             schema::capabilities::table.filter(schema::capabilities::user_id.eq(user_id)),
         )
         .get_results(self.conn())
+        .map_err(Error::from)
     }
     /// Delete all capabilities with the listed ids.
     fn delete_capabilities_with_ids(
         &self,
         capability_ids: &[uuid::Uuid],
-    ) -> Result<Vec<capabilities::Data>, diesel::result::Error> {
+    ) -> Result<Vec<capabilities::Data>, Error> {
         diesel::delete(
             schema::capabilities::table.filter(schema::capabilities::id.eq_any(capability_ids)),
         )
         .get_results(self.conn())
+        .map_err(Error::from)
     }
 }
 impl<T: DBConn> CapabilityQuery for T {}
 
-// TODO tests?
+pub trait RoleQuery: DBConn {
+    /// Create a role from the provided role info.
+    fn create_role<'a, N: Into<roles::NewWithId<'a>>>(
+        &self,
+        new_role: N,
+    ) -> Result<roles::Data, Error> {
+        diesel::insert_into(schema::roles::table)
+            .values(&new_role.into())
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Locate a role given an id.
+    fn find_role_with_id(&self, id: uuid::Uuid) -> Result<roles::Data, Error> {
+        schema::roles::table
+            .find(id)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Locate a role given its name.
+    fn find_role_by_name(&self, name: &str) -> Result<roles::Data, Error> {
+        schema::roles::table
+            .filter(schema::roles::name.eq(name))
+            .first(self.conn())
+            .map_err(Error::from)
+    }
+    /// Get all the capabilities making up a role.
+    fn get_role_capabilities(
+        &self,
+        role_id: uuid::Uuid,
+    ) -> Result<Vec<role_capabilities::Data>, Error> {
+        schema::role_capabilities::table
+            .filter(schema::role_capabilities::role_id.eq(role_id))
+            .load(self.conn())
+            .map_err(Error::from)
+    }
+    /// Add all capabilities in the [`Vec`] to their roles. Capabilities already in the role are
+    /// skipped.
+    fn add_capabilities_to_role<'a>(
+        &self,
+        capabilities: Vec<role_capabilities::New<'a>>,
+    ) -> Result<Vec<role_capabilities::Data>, Error> {
+        diesel::insert_into(schema::role_capabilities::table)
+            .values(capabilities)
+            .on_conflict_do_nothing()
+            .get_results(self.conn())
+            .map_err(Error::from)
+    }
+    /// Remove the listed capabilities from a role.
+    fn remove_capabilities_from_role(
+        &self,
+        role_id: uuid::Uuid,
+        capabilities: &[capabilities::Capability],
+    ) -> Result<Vec<role_capabilities::Data>, Error> {
+        let capabilities: Vec<&str> = capabilities.iter().map(|c| c.as_str()).collect();
+        diesel::delete(
+            schema::role_capabilities::table
+                .filter(schema::role_capabilities::role_id.eq(role_id))
+                .filter(schema::role_capabilities::capability.eq_any(capabilities)),
+        )
+        .get_results(self.conn())
+        .map_err(Error::from)
+    }
+    /// Grant a role to a user.
+    fn grant_role_to_user(
+        &self,
+        grant: user_roles::New,
+    ) -> Result<user_roles::Data, Error> {
+        diesel::insert_into(schema::user_roles::table)
+            .values(&grant)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Revoke a role from a user.
+    fn revoke_role_from_user(
+        &self,
+        user_id: uuid::Uuid,
+        role_id: uuid::Uuid,
+    ) -> Result<user_roles::Data, Error> {
+        diesel::delete(schema::user_roles::table.find((user_id, role_id)))
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Get all roles held by a user.
+    fn get_user_roles(
+        &self,
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<roles::Data>, Error> {
+        schema::roles::table
+            .inner_join(schema::user_roles::table)
+            .filter(schema::user_roles::user_id.eq(user_id))
+            .select(schema::roles::all_columns)
+            .load(self.conn())
+            .map_err(Error::from)
+    }
+}
+impl<T: DBConn> RoleQuery for T {}
+
+pub trait StatsQuery: DBConn {
+    /// Count the users and posts of the site. Everything is aggregated by the database in a single
+    /// round trip, so no rows are loaded.
+    fn get_site_stats(&self) -> Result<stats::Site, Error> {
+        diesel::sql_query(
+            "SELECT \
+            (SELECT COUNT(*) FROM users) AS users, \
+            COUNT(*) FILTER ( \
+                WHERE deleted_at IS NULL AND archived_at IS NULL AND published_at IS NULL \
+            ) AS draft_posts, \
+            COUNT(*) FILTER ( \
+                WHERE deleted_at IS NULL AND archived_at IS NULL AND published_at IS NOT NULL \
+            ) AS published_posts, \
+            COUNT(*) FILTER ( \
+                WHERE deleted_at IS NULL AND archived_at IS NOT NULL \
+            ) AS archived_posts, \
+            COUNT(*) FILTER (WHERE deleted_at IS NOT NULL) AS deleted_posts, \
+            (SELECT MAX(created_at) FROM posts) AS newest_post_at \
+            FROM posts",
+        )
+        .get_result(self.conn())
+        .map_err(Error::from)
+    }
+    /// Count the posts listed under each [`PostState`], the same way
+    /// [`get_site_stats`](Self::get_site_stats) does but without counting anything else.
+    fn count_posts_by_state(&self) -> Result<stats::PostCounts, Error> {
+        diesel::sql_query(
+            "SELECT \
+            COUNT(*) FILTER ( \
+                WHERE deleted_at IS NULL AND archived_at IS NULL AND published_at IS NOT NULL \
+            ) AS published, \
+            COUNT(*) FILTER ( \
+                WHERE deleted_at IS NULL AND archived_at IS NULL AND published_at IS NULL \
+            ) AS drafts, \
+            COUNT(*) FILTER (WHERE deleted_at IS NULL AND archived_at IS NOT NULL) AS archived \
+            FROM posts",
+        )
+        .get_result(self.conn())
+        .map_err(Error::from)
+    }
+}
+impl<T: DBConn> StatsQuery for T {}
+
+pub trait SiteQuery: DBConn {
+    /// Find the mode the site was last switched to.
+    fn find_site_mode(&self) -> Result<site::Mode, Error> {
+        schema::site_settings::table
+            .select(schema::site_settings::mode)
+            .first(self.conn())
+            .map_err(Error::from)
+    }
+    /// Switch the site to `mode`. Returns the mode switched to.
+    fn set_site_mode(
+        &self,
+        mode: &site::Mode,
+        updated_by: uuid::Uuid,
+    ) -> Result<site::Mode, Error> {
+        diesel::update(schema::site_settings::table)
+            .set((
+                schema::site_settings::mode.eq(mode),
+                schema::site_settings::updated_by.eq(updated_by),
+            ))
+            .returning(schema::site_settings::mode)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find how admins last had the site present itself, if they ever changed it.
+    fn find_site_identity(&self) -> Result<Option<site::Identity>, Error> {
+        schema::site_settings::table
+            .select(schema::site_settings::identity)
+            .first(self.conn())
+            .map_err(Error::from)
+    }
+    /// Have the site present itself as `identity`. Returns the identity saved.
+    fn set_site_identity(
+        &self,
+        identity: &site::Identity,
+        updated_by: uuid::Uuid,
+    ) -> Result<site::Identity, Error> {
+        let saved: Option<site::Identity> = diesel::update(schema::site_settings::table)
+            .set((
+                schema::site_settings::identity.eq(identity),
+                schema::site_settings::updated_by.eq(updated_by),
+            ))
+            .returning(schema::site_settings::identity)
+            .get_result(self.conn())?;
+        saved.ok_or(Error::NotFound)
+    }
+}
+impl<T: DBConn> SiteQuery for T {}
+
+pub trait WebhookQuery: DBConn {
+    /// Create a webhook. Returns the created webhook.
+    fn create_webhook<'a, N: Into<webhooks::NewWithId<'a>>>(
+        &self,
+        new: N,
+    ) -> Result<webhooks::Data, Error> {
+        diesel::insert_into(schema::webhooks::table)
+            .values(&new.into())
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find a page of the webhooks, oldest first.
+    fn find_webhooks_page(
+        &self,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<page::Page<webhooks::Data>, Error> {
+        schema::webhooks::table
+            .paginate(
+                &[PageOrder::Asc("created_at"), PageOrder::Asc("id")],
+                limit,
+                offset,
+            )
+            .load_page(self.conn())
+    }
+    /// Locate a webhook given an id.
+    fn find_webhook_with_id(&self, id: uuid::Uuid) -> Result<webhooks::Data, Error> {
+        schema::webhooks::table
+            .find(id)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find the enabled webhooks that are pinged about `event`.
+    fn find_webhooks_for(&self, event: webhooks::Event) -> Result<Vec<webhooks::Data>, Error> {
+        let enabled: Vec<webhooks::Data> = schema::webhooks::table
+            .filter(schema::webhooks::enabled.eq(true))
+            .load(self.conn())?;
+        Ok(enabled.into_iter().filter(|hook| hook.wants(event)).collect())
+    }
+    /// Change the webhook with the provided id. Returns the changed webhook.
+    fn update_webhook_with_id(
+        &self,
+        id: uuid::Uuid,
+        changed: &webhooks::Changed,
+        updated_by: uuid::Uuid,
+    ) -> Result<webhooks::Data, Error> {
+        diesel::update(schema::webhooks::table.find(id))
+            .set((changed, schema::webhooks::updated_by.eq(updated_by)))
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Delete the webhook with the provided id, along with its dead letters. Returns the deleted
+    /// webhook.
+    fn delete_webhook_with_id(&self, id: uuid::Uuid) -> Result<webhooks::Data, Error> {
+        diesel::delete(schema::webhooks::table.find(id))
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Record a delivery that failed for good.
+    fn record_dead_letter(
+        &self,
+        letter: &webhooks::NewDeadLetter,
+    ) -> Result<webhooks::DeadLetter, Error> {
+        diesel::insert_into(schema::webhook_dead_letters::table)
+            .values(letter)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+}
+impl<T: DBConn> WebhookQuery for T {}
+
+pub trait ReactionQuery: DBConn {
+    /// Count the reactions of each kind on the post with the provided id.
+    fn count_reactions(&self, post_id: uuid::Uuid) -> Result<reactions::Counts, Error> {
+        use diesel::dsl::count_star;
+        use schema::post_reactions;
+        let counted: Vec<(String, i64)> = post_reactions::table
+            .filter(post_reactions::post_id.eq(post_id))
+            .group_by(post_reactions::kind)
+            .select((post_reactions::kind, count_star()))
+            .load(self.conn())?;
+        Ok(reactions::Counts::tally(counted))
+    }
+    /// Leave a reaction on a post, unless the actor already left one of the same kind. Returns
+    /// whether it was left.
+    fn add_reaction(&self, new: &reactions::New) -> Result<bool, Error> {
+        diesel::insert_into(schema::post_reactions::table)
+            .values(new)
+            .on_conflict_do_nothing()
+            .execute(self.conn())
+            .map(|inserted| inserted == 1)
+            .map_err(Error::from)
+    }
+    /// Take back a reaction left on a post. Returns whether there was one to take back.
+    fn remove_reaction(
+        &self,
+        post_id: uuid::Uuid,
+        kind: reactions::Kind,
+        actor: &str,
+    ) -> Result<bool, Error> {
+        use schema::post_reactions;
+        diesel::delete(post_reactions::table.find((post_id, kind.as_str(), actor)))
+            .execute(self.conn())
+            .map(|deleted| deleted == 1)
+            .map_err(Error::from)
+    }
+}
+impl<T: DBConn> ReactionQuery for T {}
+
+pub trait RetentionQuery: DBConn {
+    /// Delete for good up to `batch` of the posts deleted before `deleted_before`, along with
+    /// their tags, authors, and places in series. Returns the number of posts deleted.
+    fn purge_deleted_posts(
+        &self,
+        deleted_before: DateTime<Utc>,
+        batch: i64,
+    ) -> Result<usize, Error> {
+        use schema::posts;
+        let expired = posts::table
+            .select(posts::id)
+            .filter(posts::deleted_at.lt(deleted_before))
+            .limit(batch);
+        diesel::delete(posts::table.filter(posts::id.eq_any(expired)))
+            .execute(self.conn())
+            .map_err(Error::from)
+    }
+    /// Delete up to `batch` of the dead letters recorded before `recorded_before`. Returns the
+    /// number of dead letters deleted.
+    fn purge_dead_letters(
+        &self,
+        recorded_before: DateTime<Utc>,
+        batch: i64,
+    ) -> Result<usize, Error> {
+        use schema::webhook_dead_letters as letters;
+        let expired = letters::table
+            .select(letters::id)
+            .filter(letters::created_at.lt(recorded_before))
+            .limit(batch);
+        diesel::delete(letters::table.filter(letters::id.eq_any(expired)))
+            .execute(self.conn())
+            .map_err(Error::from)
+    }
+    /// Delete up to `batch` of the capability grants that have expired, which are ignored
+    /// wherever capabilities are read anyway. Returns the number of grants deleted.
+    fn purge_expired_capabilities(&self, batch: i64) -> Result<usize, Error> {
+        use schema::capabilities;
+        let expired = capabilities::table
+            .select(capabilities::id)
+            .filter(capabilities::expires_at.le(diesel::dsl::now))
+            .limit(batch);
+        diesel::delete(capabilities::table.filter(capabilities::id.eq_any(expired)))
+            .execute(self.conn())
+            .map_err(Error::from)
+    }
+}
+impl<T: DBConn> RetentionQuery for T {}
+
+pub trait SeriesQuery: DBConn {
+    /// Create a series. Returns the created series.
+    fn create_series<'a, N: Into<series::NewWithId<'a>>>(
+        &self,
+        new: N,
+    ) -> Result<series::Data, Error> {
+        diesel::insert_into(schema::series::table)
+            .values(&new.into())
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find a page of the series, ordered by title.
+    fn find_series_page(
+        &self,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<page::Page<series::Data>, Error> {
+        schema::series::table
+            .paginate(&[PageOrder::Asc("title"), PageOrder::Asc("id")], limit, offset)
+            .load_page(self.conn())
+    }
+    /// Locate a series given an id.
+    fn find_series_with_id(&self, id: uuid::Uuid) -> Result<series::Data, Error> {
+        schema::series::table
+            .find(id)
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Locate a series given its friendly name.
+    fn find_series_with_slug(&self, slug: &str) -> Result<series::Data, Error> {
+        schema::series::table
+            .filter(schema::series::slug.eq(slug))
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Change the series with the provided id. Returns the changed series.
+    fn update_series_with_id(
+        &self,
+        id: uuid::Uuid,
+        changed: &series::Changed,
+        updated_by: uuid::Uuid,
+    ) -> Result<series::Data, Error> {
+        diesel::update(schema::series::table.find(id))
+            .set((changed, schema::series::updated_by.eq(updated_by)))
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Delete the series with the provided id. Its posts are left as they are. Returns the deleted
+    /// series.
+    fn delete_series_with_id(&self, id: uuid::Uuid) -> Result<series::Data, Error> {
+        diesel::delete(schema::series::table.find(id))
+            .get_result(self.conn())
+            .map_err(Error::from)
+    }
+    /// Find the posts in the series with the provided id, in order. Deleted posts are left out,
+    /// as are unpublished ones unless `show_unpublished`.
+    fn find_series_parts(
+        &self,
+        series_id: uuid::Uuid,
+        show_unpublished: bool,
+    ) -> Result<Vec<series::Part>, Error> {
+        let mut query = schema::series_posts::table
+            .inner_join(schema::posts::table)
+            .filter(schema::series_posts::series_id.eq(series_id))
+            .filter(schema::posts::deleted_at.is_null())
+            .select(series::Part::COLUMNS)
+            .order(schema::series_posts::position.asc())
+            .into_boxed();
+        if !show_unpublished {
+            query = query.filter(schema::posts::published_at.is_not_null());
+        }
+        query.load(self.conn()).map_err(Error::from)
+    }
+    /// Replace the posts in the series with the provided id with the posts with the provided ids,
+    /// in the order given. Either every post is placed or none are. Returns the posts now in the
+    /// series.
+    fn replace_series_posts(
+        &self,
+        series_id: uuid::Uuid,
+        post_ids: &[uuid::Uuid],
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<series::Part>, Error> {
+        self.conn()
+            .transaction(|| {
+                diesel::delete(
+                    schema::series_posts::table
+                        .filter(schema::series_posts::series_id.eq(series_id)),
+                )
+                .execute(self.conn())?;
+                let parts: Vec<_> = post_ids
+                    .iter()
+                    .enumerate()
+                    .map(|(position, post_id)| series::NewPart {
+                        series_id,
+                        post_id: *post_id,
+                        position: position as i32,
+                        created_by: user_id,
+                    })
+                    .collect();
+                if !parts.is_empty() {
+                    diesel::insert_into(schema::series_posts::table)
+                        .values(&parts)
+                        .execute(self.conn())?;
+                }
+                Ok(())
+            })
+            .map_err(Error::from)?;
+        self.find_series_parts(series_id, true)
+    }
+    /// Find where the post with the provided id is in each series it is part of, ordered by the
+    /// title of the series. Unpublished parts are only counted if `show_unpublished`.
+    fn find_post_series(
+        &self,
+        post_id: uuid::Uuid,
+        show_unpublished: bool,
+    ) -> Result<Vec<series::Membership>, Error> {
+        let containing: Vec<series::Data> = schema::series::table
+            .inner_join(schema::series_posts::table)
+            .filter(schema::series_posts::post_id.eq(post_id))
+            .select(schema::series::all_columns)
+            .order(schema::series::title.asc())
+            .load(self.conn())?;
+        let mut memberships = vec![];
+        for series in containing {
+            let parts = self.find_series_parts(series.id, show_unpublished)?;
+            memberships.extend(series::Membership::of(&series, &parts, post_id));
+        }
+        Ok(memberships)
+    }
+}
+impl<T: DBConn> SeriesQuery for T {}
+
+#[cfg(test)]
+mod test {
+    //! These run against the database pointed to by `DATABASE_URL`, inside a transaction that is
+    //! never committed. Run with `cargo test --features server -- --ignored`.
+
+    use super::*;
+
+    const GUARDED: capabilities::Capability = capabilities::Capability::GrantCapability;
+
+    struct TestConn(PgConnection);
+    impl DBConn for TestConn {
+        fn conn(&self) -> &PgConnection {
+            &self.0
+        }
+    }
+
+    fn connect() -> TestConn {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for db tests.");
+        let conn = PgConnection::establish(url.as_str()).expect("Failed to connect to database.");
+        conn.begin_test_transaction()
+            .expect("Failed to begin test transaction.");
+        TestConn(conn)
+    }
+
+    fn grant(
+        db: &TestConn,
+        user_name: &str,
+        expires_at: Option<DateTime<Utc>>,
+        scope: Option<capabilities::Scope>,
+    ) -> (users::Data, capabilities::Data) {
+        let user = db
+            .create_user(users::New {
+                user_name,
+                created_by: None,
+                updated_by: None,
+                first_name: "first",
+                last_name: "last",
+                email: "admin@example.com",
+            })
+            .unwrap();
+        let mut caps = db
+            .create_all_capabilities(vec![capabilities::New {
+                created_by: user.id,
+                user_id: user.id,
+                capability: GUARDED.as_str(),
+                expires_at,
+                scope,
+            }])
+            .unwrap();
+        (user, caps.remove(0))
+    }
+
+    fn admin(db: &TestConn, user_name: &str) -> (users::Data, capabilities::Data) {
+        grant(db, user_name, None, None)
+    }
+
+    /// Existing rows in the database may already hold the capability, so the removals have to
+    /// be measured relative to them.
+    fn strip_existing_holders(db: &TestConn) {
+        diesel::delete(
+            schema::capabilities::table
+                .filter(schema::capabilities::capability.eq(GUARDED.as_str())),
+        )
+        .execute(db.conn())
+        .unwrap();
+        diesel::delete(schema::user_roles::table)
+            .execute(db.conn())
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn last_holder_is_kept() {
+        let db = connect();
+        strip_existing_holders(&db);
+        let (_, cap) = admin(&db, "only_admin");
+        let res =
+            db.delete_preserving_capability(GUARDED, || db.delete_capability_with_id(cap.id));
+        assert!(matches!(res, Err(GuardedDeletionError::LastHolder)));
+        assert!(db.get_capability_with_id(cap.id).is_ok());
+    }
+
+    #[test]
+    #[ignore]
+    fn second_to_last_holder_can_be_removed() {
+        let db = connect();
+        strip_existing_holders(&db);
+        let (_, first) = admin(&db, "first_admin");
+        let (_, second) = admin(&db, "second_admin");
+        let res =
+            db.delete_preserving_capability(GUARDED, || db.delete_capability_with_id(first.id));
+        assert!(res.is_ok());
+        let res =
+            db.delete_preserving_capability(GUARDED, || db.delete_capability_with_id(second.id));
+        assert!(matches!(res, Err(GuardedDeletionError::LastHolder)));
+        assert_eq!(db.count_users_with_capability(GUARDED).unwrap(), 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn self_revocation_of_last_holder_is_refused() {
+        let db = connect();
+        strip_existing_holders(&db);
+        let (user, _) = admin(&db, "self_revoker");
+        let res =
+            db.delete_preserving_capability(GUARDED, || db.delete_capabilities_by_user_id(user.id));
+        assert!(matches!(res, Err(GuardedDeletionError::LastHolder)));
+        assert_eq!(db.count_users_with_capability(GUARDED).unwrap(), 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn deletions_go_through_when_nobody_held_the_capability() {
+        let db = connect();
+        strip_existing_holders(&db);
+        let yesterday = Utc::now() - chrono::Duration::days(1);
+        let (_, cap) = grant(&db, "lapsed_admin", Some(yesterday), None);
+        let res =
+            db.delete_preserving_capability(GUARDED, || db.delete_capability_with_id(cap.id));
+        assert!(res.is_ok());
+        assert!(db.get_capability_with_id(cap.id).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn the_last_credential_of_a_user_is_kept() {
+        let db = connect();
+        let (user, _) = admin(&db, "credentialed");
+        let new_pw = |hash| credentials::pw::New {
+            created_by: user.id,
+            updated_by: user.id,
+            user_id: user.id,
+            hash,
+            salt: "salt",
+            argon2_mem_kib: 8,
+            argon2_iterations: 1,
+            argon2_parallelism: 1,
+        };
+        let first = db.create_pw_hash(new_pw("first")).unwrap();
+        let second = db.create_pw_hash(new_pw("second")).unwrap();
+        db.mark_pw_used(second.id).unwrap();
+        let listed = db.find_credentials_page(user.id, None, None).unwrap();
+        assert_eq!(listed.total, 2);
+        let listed = listed.items;
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().all(|c| c.kind == credentials::Kind::Password));
+        let used: Vec<_> = listed.iter().filter(|c| c.last_used_at.is_some()).map(|c| c.id).collect();
+        assert_eq!(used, vec![second.id]);
+
+        let res = db.delete_preserving_credential(user.id, || db.delete_pw_by_id(first.id));
+        assert!(res.is_ok());
+        let res = db.delete_preserving_credential(user.id, || db.delete_pw_by_id(second.id));
+        assert!(matches!(res, Err(GuardedDeletionError::LastHolder)));
+        assert_eq!(db.count_credentials_by_user(user.id).unwrap(), 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn expired_grants_are_absent_and_purged() {
+        let db = connect();
+        let yesterday = Utc::now() - chrono::Duration::days(1);
+        let (user, cap) = grant(&db, "expired_guest", Some(yesterday), None);
+        assert!(db
+            .get_effective_user_capabilities(&user)
+            .unwrap()
+            .is_empty());
+        assert!(db.get_user_capability_grants(user.id).unwrap().is_empty());
+        let listed = db.get_user_capability_grants_page(user.id, None, None).unwrap();
+        assert_eq!(listed.total, 0);
+        while db.purge_expired_capabilities(100).unwrap() == 100 {}
+        assert!(db.get_capability_with_id(cap.id).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn unexpired_grants_keep_their_scope() {
+        let db = connect();
+        let next_month = Utc::now() + chrono::Duration::days(30);
+        let scope = capabilities::Scope::Post(uuid::Uuid::new_v4());
+        let (user, cap) = grant(&db, "scoped_guest", Some(next_month), Some(scope));
+        let effective = db.get_effective_user_capabilities(&user).unwrap();
+        assert_eq!(effective.len(), 1);
+        assert_eq!(effective[0].scope, Some(scope.to_string()));
+        while db.purge_expired_capabilities(100).unwrap() == 100 {}
+        assert!(db.get_capability_with_id(cap.id).is_ok());
+    }
+
+    #[test]
+    #[ignore]
+    fn only_unscoped_unexpired_grants_are_holders() {
+        let db = connect();
+        strip_existing_holders(&db);
+        let yesterday = Utc::now() - chrono::Duration::days(1);
+        grant(&db, "expired_admin", Some(yesterday), None);
+        grant(
+            &db,
+            "scoped_admin",
+            None,
+            Some(capabilities::Scope::Tag(uuid::Uuid::new_v4())),
+        );
+        assert_eq!(db.count_users_with_capability(GUARDED).unwrap(), 0);
+        admin(&db, "real_admin");
+        assert_eq!(db.count_users_with_capability(GUARDED).unwrap(), 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn site_stats_count_posts_by_state() {
+        let db = connect();
+        let before = db.get_site_stats().unwrap();
+        let (author, _) = admin(&db, "stats_author");
+        let draft = posts::NewNoMeta {
+            title: "draft".to_owned(),
+            ..Default::default()
+        };
+        let published = posts::NewNoMeta {
+            title: "published".to_owned(),
+            published_at: Some(Utc::now()),
+            published_by: Some(author.id),
+            ..Default::default()
+        };
+        db.insert_post((&draft, author.id)).unwrap();
+        let newest = db.insert_post((&published, author.id)).unwrap();
+        let after = db.get_site_stats().unwrap();
+        assert_eq!(after.users, before.users + 1);
+        assert_eq!(after.draft_posts, before.draft_posts + 1);
+        assert_eq!(after.published_posts, before.published_posts + 1);
+        assert_eq!(after.archived_posts, before.archived_posts);
+        assert_eq!(after.deleted_posts, before.deleted_posts);
+        assert_eq!(after.newest_post_at, Some(newest.created_at));
+        let counts = db.count_posts_by_state().unwrap();
+        assert_eq!(counts.drafts, after.draft_posts);
+        assert_eq!(counts.published, after.published_posts);
+        assert_eq!(counts.archived, after.archived_posts);
+    }
+
+    #[test]
+    #[ignore]
+    fn listings_narrow_down_to_a_state() {
+        let db = connect();
+        let (author, _) = admin(&db, "state_author");
+        let draft = posts::NewNoMeta {
+            title: "zz_test_state_draft".to_owned(),
+            ..Default::default()
+        };
+        let created = db.insert_post((&draft, author.id)).unwrap();
+        let listed = |state| {
+            let conditions = PostListing::LimAndOffset {
+                offset: 0,
+                lim: 500,
+                order_by: OrderingField::Date,
+                ord: SortOrdering::Descending,
+            };
+            let posts = db
+                .posts_with_authors(conditions, &PostFilter::default(), true, Some(state))
+                .unwrap();
+            posts.iter().any(|(post, _)| post.id == created.id)
+        };
+        assert!(listed(PostState::Draft));
+        assert!(!listed(PostState::Published));
+        assert!(!listed(PostState::Archived));
+    }
+
+    #[test]
+    #[ignore]
+    fn deleted_posts_are_never_listed() {
+        let db = connect();
+        let (author, _) = admin(&db, "deleted_author");
+        let published = posts::NewNoMeta {
+            title: "zz_test_deleted".to_owned(),
+            published_at: Some(Utc::now()),
+            published_by: Some(author.id),
+            ..Default::default()
+        };
+        let created = db.insert_post((&published, author.id)).unwrap();
+        db.delete_post_with_id(created.id, &posts::Deletion::new(author.id))
+            .unwrap();
+        let states = [
+            None,
+            Some(PostState::Published),
+            Some(PostState::Draft),
+            Some(PostState::Archived),
+        ];
+        for &show_unpublished in &[false, true] {
+            for &state in states.iter() {
+                let conditions = PostListing::LimAndOffset {
+                    offset: 0,
+                    lim: 500,
+                    order_by: OrderingField::Date,
+                    ord: SortOrdering::Descending,
+                };
+                let posts = db
+                    .posts_with_authors(conditions, &PostFilter::default(), show_unpublished, state)
+                    .unwrap();
+                let listed = posts.iter().any(|(post, _)| post.id == created.id);
+                assert!(!listed, "{:?} with show_unpublished {}", state, show_unpublished);
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn listings_narrow_down_to_an_author_and_a_tag() {
+        let db = connect();
+        let (first, _) = admin(&db, "filtered_author");
+        let (second, _) = admin(&db, "unfiltered_author");
+        let post = posts::NewNoMeta {
+            title: "zz_test_filtered".to_owned(),
+            published_at: Some(Utc::now()),
+            ..Default::default()
+        };
+        let by_first = db.insert_post((&post, first.id)).unwrap();
+        let by_second = db.insert_post((&post, second.id)).unwrap();
+        db.replace_post_tags(by_first.id, &["zz_test_filter"], first.id)
+            .unwrap();
+        let listed = |filter: PostFilter| -> Vec<uuid::Uuid> {
+            let conditions = PostListing::LimAndOffset {
+                offset: 0,
+                lim: 500,
+                order_by: OrderingField::Date,
+                ord: SortOrdering::Descending,
+            };
+            let posts = db.posts_with_authors(conditions, &filter, true, None).unwrap();
+            posts
+                .into_iter()
+                .map(|(post, _)| post.id)
+                .filter(|id| *id == by_first.id || *id == by_second.id)
+                .collect()
+        };
+        let author = |id| PostFilter {
+            author: Some(id),
+            tag: None,
+        };
+        let tag = |name: &str| PostFilter {
+            author: None,
+            tag: Some(name.to_owned()),
+        };
+        assert_eq!(listed(author(first.id)), vec![by_first.id]);
+        assert_eq!(listed(author(second.id)), vec![by_second.id]);
+        assert_eq!(listed(tag("zz_test_filter")), vec![by_first.id]);
+        assert!(listed(tag("zz_test_missing")).is_empty());
+        let both = PostFilter {
+            author: Some(second.id),
+            tag: Some("zz_test_filter".to_owned()),
+        };
+        assert!(listed(both).is_empty());
+    }
+
+    /// Counts the queries run through it, since each query asks for the connection once.
+    struct CountingConn<'a> {
+        inner: &'a TestConn,
+        queries: std::cell::Cell<usize>,
+    }
+    impl DBConn for CountingConn<'_> {
+        fn conn(&self) -> &PgConnection {
+            self.queries.set(self.queries.get() + 1);
+            self.inner.conn()
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn effective_capabilities_take_one_query() {
+        let db = connect();
+        let next_month = Utc::now() + chrono::Duration::days(30);
+        let (user, _) = grant(&db, "counted_guest", Some(next_month), None);
+        let counting = CountingConn {
+            inner: &db,
+            queries: std::cell::Cell::new(0),
+        };
+        let effective = counting
+            .get_effective_capabilities_by_user_id(user.id)
+            .unwrap();
+        assert_eq!(counting.queries.get(), 1);
+        assert_eq!(effective.len(), 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn listing_authors_takes_one_query() {
+        let db = connect();
+        let (author, _) = admin(&db, "listed_author");
+        let post = posts::NewNoMeta {
+            title: "zz_test_listed".to_owned(),
+            published_at: Some(Utc::now()),
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            db.insert_post((&post, author.id)).unwrap();
+        }
+        let counting = CountingConn {
+            inner: &db,
+            queries: std::cell::Cell::new(0),
+        };
+        let listed = counting
+            .posts_with_authors(
+                PostListing::LimAndOffset {
+                    offset: 0,
+                    lim: 500,
+                    order_by: OrderingField::Date,
+                    ord: SortOrdering::Descending,
+                },
+                &PostFilter::default(),
+                true,
+                None,
+            )
+            .unwrap();
+        assert_eq!(counting.queries.get(), 1);
+        let ours: Vec<_> = listed
+            .iter()
+            .filter(|(post, _)| post.title == "zz_test_listed")
+            .collect();
+        assert_eq!(ours.len(), 50);
+        assert!(ours.iter().all(|(_, listed_by)| listed_by.id == author.id));
+    }
+
+    #[test]
+    #[ignore]
+    fn failed_transactions_leave_nothing_behind() {
+        let db = connect();
+        let (author, _) = admin(&db, "transaction_author");
+        let post = posts::NewNoMeta {
+            title: "rolled back".to_owned(),
+            ..Default::default()
+        };
+        let mut inserted = None;
+        let res: Result<(), Error> = db.transaction(|tx| {
+            inserted = Some(tx.insert_post((&post, author.id))?.id);
+            tx.replace_post_tags(inserted.unwrap(), &["zz_test_rolled_back"], author.id)?;
+            // Fails after the inserts, as a later step of a handler might.
+            Err(diesel::result::Error::RollbackTransaction.into())
+        });
+        assert!(res.is_err());
+        let inserted = inserted.expect("The post to have been inserted before the failure.");
+        assert!(matches!(db.find_post_with_id(inserted), Err(Error::NotFound)));
+        let tagged = schema::tags::table
+            .filter(schema::tags::name.eq("zz_test_rolled_back"))
+            .count()
+            .get_result::<i64>(db.conn())
+            .unwrap();
+        assert_eq!(tagged, 0);
+
+        let kept = db
+            .transaction(|tx| tx.insert_post((&post, author.id)))
+            .unwrap();
+        assert_eq!(db.find_post_with_id(kept.id).unwrap().title, "rolled back");
+    }
+
+    #[test]
+    #[ignore]
+    fn edits_bump_updated_at_unless_nothing_changed() {
+        let db = connect();
+        let (author, _) = admin(&db, "updated_at_author");
+        let post = posts::NewNoMeta {
+            title: "before".to_owned(),
+            ..Default::default()
+        };
+        let post = db.insert_post((&post, author.id)).unwrap();
+        // The whole test runs in one transaction, in which the current time does not move, so
+        // the post is made to look older instead.
+        let long_ago = Utc::now() - chrono::Duration::days(1);
+        diesel::update(schema::posts::table.find(post.id))
+            .set(schema::posts::updated_at.eq(long_ago))
+            .execute(db.conn())
+            .unwrap();
+        assert_eq!(db.find_post_with_id(post.id).unwrap().updated_at, long_ago);
+
+        let same = posts::Changed {
+            title: Some("before".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(db.update_post_with_id(post.id, &same).unwrap(), 1);
+        assert_eq!(db.find_post_with_id(post.id).unwrap().updated_at, long_ago);
+
+        let edited = posts::Changed {
+            title: Some("after".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(db.update_post_with_id(post.id, &edited).unwrap(), 1);
+        let updated = db.find_post_with_id(post.id).unwrap();
+        assert!(updated.updated_at > long_ago);
+        assert_eq!(updated.clone().strip_meta().updated_at, updated.updated_at);
+    }
+
+    #[test]
+    #[ignore]
+    fn deleting_a_tag_untags_posts_without_deleting_them() {
+        let db = connect();
+        let (author, _) = admin(&db, "tag_deletion_author");
+        let post = posts::NewNoMeta {
+            title: "tagged then untagged".to_owned(),
+            ..Default::default()
+        };
+        let post = db.insert_post((&post, author.id)).unwrap();
+        let tags = db
+            .replace_post_tags(post.id, &["zz_test_doomed", "zz_test_kept"], author.id)
+            .unwrap();
+        let deleted = db.delete_tag_with_id(tags[0].id).unwrap();
+        assert_eq!(deleted.name, "zz_test_doomed");
+        let left: Vec<_> = db
+            .find_post_tags(post.id)
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+        assert_eq!(left, vec!["zz_test_kept"]);
+        assert_eq!(db.find_post_with_id(post.id).unwrap().id, post.id);
+    }
+
+    #[test]
+    #[ignore]
+    fn deleting_users_keeps_posts_and_clears_what_they_did() {
+        let db = connect();
+        let (author, _) = admin(&db, "deletion_author");
+        let (editor, _) = admin(&db, "deletion_editor");
+        let post = posts::NewNoMeta {
+            title: "outlives its editor".to_owned(),
+            ..Default::default()
+        };
+        let post = db.insert_post((&post, author.id)).unwrap();
+        db.publish_post_with_id(post.id, posts::Publishing::new(editor.id))
+            .unwrap();
+
+        // Authors keep their posts, so they cannot be deleted while they have any.
+        assert!(matches!(
+            db.delete_user_by_id(author.id),
+            Err(Error::ForeignKeyViolation)
+        ));
+        // The editor goes, along with their capabilities, but the post stays published.
+        db.delete_user_by_id(editor.id).unwrap();
+        let post = db.find_post_with_id(post.id).unwrap();
+        assert_eq!(post.created_by, author.id);
+        assert_eq!(post.published_by, None);
+        assert!(post.published_at.is_some());
+        assert!(db.get_user_capabilities(&editor).unwrap().is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn post_tags_are_replaced_and_created_as_needed() {
+        let db = connect();
+        let (author, _) = admin(&db, "tag_author");
+        let post = posts::NewNoMeta {
+            title: "tagged".to_owned(),
+            ..Default::default()
+        };
+        let post = db.insert_post((&post, author.id)).unwrap();
+        let names = |tags: Vec<tags::Data>| -> Vec<String> {
+            tags.into_iter().map(|tag| tag.name).collect()
+        };
+        let first = db
+            .replace_post_tags(post.id, &["zz_test_rust", "zz_test_db"], author.id)
+            .unwrap();
+        assert_eq!(names(first.clone()), vec!["zz_test_db", "zz_test_rust"]);
+        let second = db
+            .replace_post_tags(post.id, &["zz_test_rust", "zz_test_web"], author.id)
+            .unwrap();
+        assert_eq!(names(second.clone()), vec!["zz_test_rust", "zz_test_web"]);
+        // The tag that was kept is reused rather than created again.
+        assert_eq!(first[1].id, second[0].id);
+        assert_eq!(
+            names(db.find_post_tags(post.id).unwrap()),
+            vec!["zz_test_rust", "zz_test_web"]
+        );
+        let untagged = db.replace_post_tags(post.id, &[], author.id).unwrap();
+        assert!(untagged.is_empty());
+        assert!(db.find_post_tags(post.id).unwrap().is_empty());
+        // Untagging the post leaves the tags themselves in place.
+        let kept = schema::tags::table
+            .filter(schema::tags::name.eq("zz_test_db"))
+            .count()
+            .get_result::<i64>(db.conn())
+            .unwrap();
+        assert_eq!(kept, 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn posts_credit_their_creator_until_authors_are_replaced() {
+        let db = connect();
+        let (owner, _) = admin(&db, "post_owner");
+        let (co_author, _) = admin(&db, "post_co_author");
+        let post = posts::NewNoMeta {
+            title: "co-written".to_owned(),
+            ..Default::default()
+        };
+        let post = db.insert_post((&post, owner.id)).unwrap();
+        let credited = |authors: Vec<post_authors::Author>| -> Vec<_> {
+            authors.into_iter().map(|a| (a.user.id, a.role)).collect()
+        };
+        assert_eq!(
+            credited(db.find_post_authors(post.id).unwrap()),
+            vec![(owner.id, post_authors::Role::Author)]
+        );
+        let authors = [
+            post_authors::Assigned {
+                user_id: co_author.id,
+                role: post_authors::Role::Author,
+            },
+            post_authors::Assigned {
+                user_id: owner.id,
+                role: post_authors::Role::Editor,
+            },
+        ];
+        let replaced = db.replace_post_authors(post.id, &authors, owner.id).unwrap();
+        assert_eq!(
+            credited(replaced),
+            vec![
+                (co_author.id, post_authors::Role::Author),
+                (owner.id, post_authors::Role::Editor),
+            ]
+        );
+        // The owner of the record does not change with the byline.
+        let authored = db.find_authored_post_with_id(post.id, true).unwrap();
+        assert_eq!(authored.post.created_by, owner.id);
+        assert_eq!(authored.authors.len(), 2);
+        let mut ids = db.find_post_author_ids(post.id).unwrap();
+        ids.sort();
+        let mut expected = vec![owner.id, co_author.id];
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    #[ignore]
+    fn series_positions_stay_dense_through_reorders_and_removals() {
+        let db = connect();
+        let (owner, _) = admin(&db, "series_owner");
+        diesel::delete(schema::series::table.filter(schema::series::slug.eq("dense")))
+            .execute(db.conn())
+            .unwrap();
+        let series = series::NewNoMeta {
+            title: "Dense".to_owned(),
+            slug: "dense".to_owned(),
+            description: String::new(),
+        };
+        let series = db.create_series((&series, owner.id)).unwrap();
+        let part = |title: &str| {
+            let post = posts::NewNoMeta {
+                title: title.to_owned(),
+                ..Default::default()
+            };
+            db.insert_post((&post, owner.id)).unwrap().id
+        };
+        let (first, second, third) = (part("first"), part("second"), part("third"));
+        let ids = |parts: Vec<series::Part>| -> Vec<_> { parts.into_iter().map(|p| p.id).collect() };
+        let placed = db
+            .replace_series_posts(series.id, &[first, second, third], owner.id)
+            .unwrap();
+        assert_eq!(ids(placed), vec![first, second, third]);
+        // Swapping places only holds together because the positions are checked on commit.
+        let reordered = db
+            .replace_series_posts(series.id, &[third, first, second], owner.id)
+            .unwrap();
+        assert_eq!(ids(reordered), vec![third, first, second]);
+
+        diesel::delete(schema::posts::table.find(first))
+            .execute(db.conn())
+            .unwrap();
+        let positions: Vec<i32> = schema::series_posts::table
+            .filter(schema::series_posts::series_id.eq(series.id))
+            .select(schema::series_posts::position)
+            .order(schema::series_posts::position.asc())
+            .load(db.conn())
+            .unwrap();
+        assert_eq!(positions, vec![0, 1]);
+
+        let membership = db.find_post_series(second, true).unwrap();
+        assert_eq!(membership.len(), 1);
+        assert_eq!((membership[0].part, membership[0].parts), (2, 2));
+        assert_eq!(membership[0].prev.as_ref().map(|p| p.id), Some(third));
+        assert_eq!(membership[0].next, None);
+        // None of the parts are published.
+        assert!(db.find_post_series(second, false).unwrap().is_empty());
+        db.delete_series_with_id(series.id).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn webhooks_are_found_by_event_and_take_their_dead_letters_along() {
+        let db = connect();
+        let (admin, _) = admin(&db, "webhook_admin");
+        diesel::delete(schema::webhooks::table)
+            .execute(db.conn())
+            .unwrap();
+        let hook = |url: &str, events: &[webhooks::Event]| webhooks::NewNoMeta {
+            url: url.to_owned(),
+            secret: "secret".to_owned(),
+            event_mask: webhooks::Event::mask(events),
+            enabled: true,
+        };
+        let published = hook("https://example.com/published", &[webhooks::Event::PostPublished]);
+        let published = db.create_webhook((&published, admin.id)).unwrap();
+        let archived = hook("https://example.com/archived", &[webhooks::Event::PostArchived]);
+        let archived = db.create_webhook((&archived, admin.id)).unwrap();
+        let found = db.find_webhooks_for(webhooks::Event::PostPublished).unwrap();
+        assert_eq!(found, vec![published.clone()]);
+
+        let disable = webhooks::Changed {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        db.update_webhook_with_id(published.id, &disable, admin.id)
+            .unwrap();
+        assert!(db
+            .find_webhooks_for(webhooks::Event::PostPublished)
+            .unwrap()
+            .is_empty());
+
+        let letter = db
+            .record_dead_letter(&webhooks::NewDeadLetter {
+                id: uuid::Uuid::new_v4(),
+                webhook_id: archived.id,
+                event: webhooks::Event::PostArchived.as_str(),
+                payload: "{}",
+                attempts: 4,
+                last_status: Some(502),
+                error: "bad gateway",
+            })
+            .unwrap();
+        db.delete_webhook_with_id(archived.id).unwrap();
+        let letters: i64 = schema::webhook_dead_letters::table
+            .filter(schema::webhook_dead_letters::id.eq(letter.id))
+            .count()
+            .get_result(db.conn())
+            .unwrap();
+        assert_eq!(letters, 0);
+    }
+
+    #[test]
+    #[ignore]
+    fn webhooks_are_listed_a_page_at_a_time() {
+        let db = connect();
+        let (admin, _) = admin(&db, "webhook_pager");
+        diesel::delete(schema::webhooks::table)
+            .execute(db.conn())
+            .unwrap();
+        let hooks: Vec<_> = (0..3)
+            .map(|i| {
+                let hook = webhooks::NewNoMeta {
+                    url: format!("https://example.com/{}", i),
+                    secret: "secret".to_owned(),
+                    event_mask: webhooks::Event::mask(&webhooks::Event::ALL),
+                    enabled: true,
+                };
+                db.create_webhook((&hook, admin.id)).unwrap()
+            })
+            .collect();
+        let mut sorted = hooks.clone();
+        sorted.sort_by_key(|hook| (hook.created_at, hook.id));
+
+        let first = db.find_webhooks_page(Some(2), None).unwrap();
+        assert_eq!((first.total, first.limit, first.offset), (3, 2, 0));
+        assert_eq!(first.items, sorted[..2].to_vec());
+        let second = db.find_webhooks_page(Some(2), first.next_offset()).unwrap();
+        assert_eq!(second.items, sorted[2..].to_vec());
+        assert_eq!(second.next_offset(), None);
+
+        let past = db.find_webhooks_page(Some(2), Some(10)).unwrap();
+        assert!(past.items.is_empty());
+        assert_eq!(past.total, 3);
+        let clamped = db.find_webhooks_page(Some(10_000), None).unwrap();
+        assert_eq!(clamped.limit, page::MAX_LIMIT);
+    }
+
+    #[test]
+    #[ignore]
+    fn walking_every_page_lists_each_webhook_once() {
+        let db = connect();
+        let (admin, _) = admin(&db, "webhook_walker");
+        diesel::delete(schema::webhooks::table)
+            .execute(db.conn())
+            .unwrap();
+        let mut hooks: Vec<_> = (0..7)
+            .map(|i| {
+                let hook = webhooks::NewNoMeta {
+                    url: format!("https://example.com/{}", i),
+                    secret: "secret".to_owned(),
+                    event_mask: webhooks::Event::mask(&webhooks::Event::ALL),
+                    enabled: true,
+                };
+                db.create_webhook((&hook, admin.id)).unwrap()
+            })
+            .collect();
+        hooks.sort_by_key(|hook| (hook.created_at, hook.id));
+
+        let mut walked = vec![];
+        let mut offset = None;
+        loop {
+            let page = db.find_webhooks_page(Some(2), offset).unwrap();
+            assert_eq!(page.total, 7);
+            walked.extend(page.items.iter().cloned());
+            offset = page.next_offset();
+            if offset.is_none() {
+                break;
+            }
+        }
+        assert_eq!(walked, hooks);
+    }
+
+    #[test]
+    #[ignore]
+    fn walking_every_page_lists_each_grant_once() {
+        let db = connect();
+        let (user, _) = admin(&db, "grant_walker");
+        let role = db
+            .create_role(roles::New {
+                created_by: user.id,
+                name: "zz_test_grant_walker",
+                description: "",
+            })
+            .unwrap();
+        let in_role = [
+            capabilities::Capability::CreatePost,
+            capabilities::Capability::EditPost,
+        ];
+        db.add_capabilities_to_role(
+            in_role
+                .iter()
+                .map(|c| role_capabilities::New {
+                    role_id: role.id,
+                    capability: c.as_str(),
+                    created_by: user.id,
+                })
+                .collect(),
+        )
+        .unwrap();
+        db.grant_role_to_user(user_roles::New {
+            user_id: user.id,
+            role_id: role.id,
+            created_by: user.id,
+        })
+        .unwrap();
+        let key = |grant: &capabilities::Grant| format!("{:?} {}", grant.source, grant.capability);
+        let mut grants: Vec<_> = db
+            .get_user_capability_grants(user.id)
+            .unwrap()
+            .iter()
+            .map(key)
+            .collect();
+        grants.sort();
+        assert_eq!(grants.len(), 3);
+
+        let mut walked = vec![];
+        let mut offset = None;
+        loop {
+            let page = db
+                .get_user_capability_grants_page(user.id, Some(2), offset)
+                .unwrap();
+            assert_eq!(page.total, 3);
+            walked.extend(page.items.iter().map(key));
+            offset = page.next_offset();
+            if offset.is_none() {
+                break;
+            }
+        }
+        walked.sort();
+        assert_eq!(walked, grants);
+    }
+
+    struct Unreachable;
+    impl diesel::result::DatabaseErrorInformation for Unreachable {
+        fn message(&self) -> &str {
+            "server closed the connection unexpectedly"
+        }
+        fn details(&self) -> Option<&str> {
+            None
+        }
+        fn hint(&self) -> Option<&str> {
+            None
+        }
+        fn table_name(&self) -> Option<&str> {
+            None
+        }
+        fn column_name(&self) -> Option<&str> {
+            None
+        }
+        fn constraint_name(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    #[test]
+    fn lost_connections_are_told_apart() {
+        use diesel::result::{DatabaseErrorKind, Error as Diesel};
+        let lost = Diesel::DatabaseError(
+            DatabaseErrorKind::UnableToSendCommand,
+            Box::new(Unreachable),
+        );
+        assert!(matches!(Error::from(lost), Error::Connection));
+        assert!(matches!(Error::from(Diesel::NotFound), Error::NotFound));
+        assert!(matches!(
+            Error::from(Diesel::RollbackTransaction),
+            Error::Other(Diesel::RollbackTransaction)
+        ));
+    }
+
+    #[test]
+    #[ignore]
+    fn constraint_violations_are_typed() {
+        let db = connect();
+        assert!(matches!(
+            db.find_user_by_id(uuid::Uuid::nil()),
+            Err(Error::NotFound)
+        ));
+        let (user, _) = admin(&db, "zz_test_duplicate");
+        let duplicate = db.create_user(users::New {
+            user_name: user.user_name.as_str(),
+            created_by: None,
+            updated_by: None,
+            first_name: "first",
+            last_name: "last",
+            email: "duplicate@example.com",
+        });
+        match duplicate {
+            Err(Error::UniqueViolation { constraint }) => assert!(!constraint.is_empty()),
+            res => panic!("Expected a unique violation, got {:?}.", res),
+        }
+        let orphan = db.create_all_capabilities(vec![capabilities::New {
+            created_by: user.id,
+            user_id: uuid::Uuid::nil(),
+            capability: GUARDED.as_str(),
+            expires_at: None,
+            scope: None,
+        }]);
+        assert!(matches!(orphan, Err(Error::ForeignKeyViolation)));
+    }
+}
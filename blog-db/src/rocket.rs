@@ -1,9 +1,108 @@
 pub use crate::query::*;
 
 use rocket::{fairing, http, logger, request, Outcome, Rocket, State};
+use std::time::{Duration, Instant};
 #[rocket_contrib::database("blog")]
 pub struct DB(diesel::prelude::PgConnection);
 
+/// How long a request waits for a database connection before giving up. Requests that give up
+/// fail with a 503, rather than holding on to a worker while the pool is exhausted.
+pub const CHECKOUT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long statements may run before Postgres cancels them, so that a slow or locked statement
+/// cannot hold on to a worker forever. Cancelled statements fail with [`Error::Timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatementTimeouts {
+    /// The limit for statements run through [`DB`].
+    pub standard: Duration,
+    /// The limit for statements run through [`LongDB`], by routes such as exports and bulk
+    /// changes.
+    pub long: Duration,
+}
+
+fn set_statement_timeout(
+    conn: &diesel::prelude::PgConnection,
+    timeout: Duration,
+) -> diesel::QueryResult<()> {
+    use diesel::connection::SimpleConnection;
+    conn.batch_execute(&format!("SET statement_timeout = {}", timeout.as_millis()))
+}
+
+/// Sets the standard statement timeout on every connection the pool opens.
+#[derive(Debug)]
+struct StandardStatementTimeout(Duration);
+impl rocket_contrib::databases::r2d2::CustomizeConnection<
+        diesel::prelude::PgConnection,
+        rocket_contrib::databases::diesel::r2d2::Error,
+    > for StandardStatementTimeout
+{
+    fn on_acquire(
+        &self,
+        conn: &mut diesel::prelude::PgConnection,
+    ) -> Result<(), rocket_contrib::databases::diesel::r2d2::Error> {
+        set_statement_timeout(conn, self.0)
+            .map_err(rocket_contrib::databases::diesel::r2d2::Error::QueryError)
+    }
+}
+
+impl DB {
+    /// Like [`DB::fairing`], but requests only wait `checkout` for a connection to free up, rather
+    /// than the 30 seconds r2d2 waits by default, and statements are cancelled once they run past
+    /// the `statements` timeouts.
+    pub fn fairing_with_timeouts(
+        checkout: Duration,
+        statements: StatementTimeouts,
+    ) -> impl fairing::Fairing {
+        use rocket_contrib::databases::{self, diesel::r2d2::ConnectionManager, r2d2};
+        fairing::AdHoc::on_attach("'blog' Database Pool", move |rocket: Rocket| {
+            let config = match databases::database_config("blog", rocket.config()) {
+                Ok(config) => config,
+                Err(e) => {
+                    log::error!("Could not read the database configuration: {:?}.", e);
+                    return Err(rocket);
+                }
+            };
+            let pool = r2d2::Pool::builder()
+                .max_size(config.pool_size)
+                .connection_timeout(checkout)
+                .connection_customizer(Box::new(StandardStatementTimeout(statements.standard)))
+                .build(ConnectionManager::new(config.url));
+            match pool {
+                Ok(pool) => Ok(rocket.manage(DBPool(pool)).manage(statements)),
+                Err(e) => {
+                    log::error!("Could not start the database pool: {:?}.", e);
+                    Err(rocket)
+                }
+            }
+        })
+    }
+}
+
+/// How busy the pool of connections behind [`DB`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Connections checked out by handlers.
+    pub in_use: u32,
+    /// Connections open and waiting to be checked out.
+    pub idle: u32,
+    /// How long checking out a connection took while sampling, or `None` if it timed out.
+    pub wait: Option<Duration>,
+}
+impl DBPool {
+    /// Samples how busy the pool is. A connection is checked out to time the wait, so this blocks
+    /// for as long as a handler asking for one would.
+    pub fn stats(&self) -> PoolStats {
+        let state = self.0.state();
+        let started = Instant::now();
+        let wait = self.0.get().ok().map(|_| started.elapsed());
+        PoolStats {
+            in_use: state.connections - state.idle_connections,
+            idle: state.idle_connections,
+            wait,
+        }
+    }
+}
+
 /// Logistics implementation.
 impl DBConn for DB {
     /// Access a reference to the connection actually used to connect to the DB. Deref gives the
@@ -12,3 +111,67 @@ impl DBConn for DB {
         &self.0
     }
 }
+
+/// A connection whose statements may run for the [long](StatementTimeouts::long) timeout, for
+/// routes such as exports and bulk changes. The standard timeout is put back once the request is
+/// done with it.
+pub struct LongDB {
+    db: DB,
+    standard: Duration,
+}
+impl<'a, 'r> request::FromRequest<'a, 'r> for LongDB {
+    type Error = ();
+    fn from_request(req: &'a request::Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let timeouts = *req.guard::<State<StatementTimeouts>>()?;
+        let db = req.guard::<DB>()?;
+        match set_statement_timeout(&db, timeouts.long) {
+            Ok(()) => Outcome::Success(Self {
+                db,
+                standard: timeouts.standard,
+            }),
+            Err(e) => {
+                log::error!("Could not lengthen the statement timeout due to {:?}.", e);
+                Outcome::Failure((http::Status::ServiceUnavailable, ()))
+            }
+        }
+    }
+}
+impl Drop for LongDB {
+    fn drop(&mut self) {
+        if let Err(e) = set_statement_timeout(&self.db, self.standard) {
+            log::error!("Could not restore the statement timeout due to {:?}.", e);
+        }
+    }
+}
+impl std::ops::Deref for LongDB {
+    type Target = DB;
+    fn deref(&self) -> &DB {
+        &self.db
+    }
+}
+impl DBConn for LongDB {
+    fn conn(&self) -> &diesel::prelude::PgConnection {
+        self.db.conn()
+    }
+}
+
+/// The status to answer with when a query fails: 404 when nothing matched, 409 when a unique
+/// value is taken, 422 when a referenced row is missing, 503 when the database is out of reach,
+/// 504 when the statement ran past its timeout, and 500 otherwise.
+impl From<&Error> for http::Status {
+    fn from(e: &Error) -> Self {
+        match e {
+            Error::NotFound => Self::NotFound,
+            Error::UniqueViolation { .. } => Self::Conflict,
+            Error::ForeignKeyViolation => Self::UnprocessableEntity,
+            Error::Connection => Self::ServiceUnavailable,
+            Error::Timeout => Self::GatewayTimeout,
+            Error::Other(_) => Self::InternalServerError,
+        }
+    }
+}
+impl From<Error> for http::Status {
+    fn from(e: Error) -> Self {
+        (&e).into()
+    }
+}
@@ -33,6 +33,18 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         capability -> Text,
+        /// The `expires_at` column of the `capabilities` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expires_at -> Nullable<Timestamptz>,
+        /// The `scope` column of the `capabilities` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        scope -> Nullable<Text>,
     }
 }
 
@@ -55,10 +67,10 @@ table! {
         created_at -> Timestamptz,
         /// The `created_by` column of the `google_sso` table.
         ///
-        /// Its SQL type is `Uuid`.
+        /// Its SQL type is `Nullable<Uuid>`.
         ///
         /// (Automatically generated by Diesel.)
-        created_by -> Uuid,
+        created_by -> Nullable<Uuid>,
         /// The `updated_at` column of the `google_sso` table.
         ///
         /// Its SQL type is `Timestamptz`.
@@ -67,16 +79,22 @@ table! {
         updated_at -> Timestamptz,
         /// The `updated_by` column of the `google_sso` table.
         ///
-        /// Its SQL type is `Uuid`.
+        /// Its SQL type is `Nullable<Uuid>`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_by -> Uuid,
+        updated_by -> Nullable<Uuid>,
         /// The `user_id` column of the `google_sso` table.
         ///
         /// Its SQL type is `Uuid`.
         ///
         /// (Automatically generated by Diesel.)
         user_id -> Uuid,
+        /// The `last_used_at` column of the `google_sso` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_used_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -99,10 +117,10 @@ table! {
         created_at -> Timestamptz,
         /// The `created_by` column of the `passwords` table.
         ///
-        /// Its SQL type is `Uuid`.
+        /// Its SQL type is `Nullable<Uuid>`.
         ///
         /// (Automatically generated by Diesel.)
-        created_by -> Uuid,
+        created_by -> Nullable<Uuid>,
         /// The `updated_at` column of the `passwords` table.
         ///
         /// Its SQL type is `Timestamptz`.
@@ -111,10 +129,10 @@ table! {
         updated_at -> Timestamptz,
         /// The `updated_by` column of the `passwords` table.
         ///
-        /// Its SQL type is `Uuid`.
+        /// Its SQL type is `Nullable<Uuid>`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_by -> Uuid,
+        updated_by -> Nullable<Uuid>,
         /// The `user_id` column of the `passwords` table.
         ///
         /// Its SQL type is `Uuid`.
@@ -133,6 +151,106 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         salt -> Varchar,
+        /// The `argon2_mem_kib` column of the `passwords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        argon2_mem_kib -> Int4,
+        /// The `argon2_iterations` column of the `passwords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        argon2_iterations -> Int4,
+        /// The `argon2_parallelism` column of the `passwords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        argon2_parallelism -> Int4,
+        /// The `last_used_at` column of the `passwords` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamptz>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    /// Representation of the `post_authors` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    post_authors (post_id, user_id) {
+        /// The `post_id` column of the `post_authors` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        post_id -> Uuid,
+        /// The `user_id` column of the `post_authors` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Uuid,
+        /// The `role` column of the `post_authors` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        role -> Text,
+        /// The `position` column of the `post_authors` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        position -> Int4,
+        /// The `created_at` column of the `post_authors` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `created_by` column of the `post_authors` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    /// Representation of the `post_reactions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    post_reactions (post_id, kind, actor) {
+        /// The `post_id` column of the `post_reactions` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        post_id -> Uuid,
+        /// The `kind` column of the `post_reactions` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Text,
+        /// The `actor` column of the `post_reactions` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        actor -> Text,
+        /// The `created_at` column of the `post_reactions` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
     }
 }
 
@@ -161,10 +279,10 @@ table! {
         created_at -> Timestamptz,
         /// The `created_by` column of the `post_tag_junctions` table.
         ///
-        /// Its SQL type is `Uuid`.
+        /// Its SQL type is `Nullable<Uuid>`.
         ///
         /// (Automatically generated by Diesel.)
-        created_by -> Uuid,
+        created_by -> Nullable<Uuid>,
     }
 }
 
@@ -199,10 +317,10 @@ table! {
         updated_at -> Timestamptz,
         /// The `updated_by` column of the `posts` table.
         ///
-        /// Its SQL type is `Uuid`.
+        /// Its SQL type is `Nullable<Uuid>`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_by -> Uuid,
+        updated_by -> Nullable<Uuid>,
         /// The `published_at` column of the `posts` table.
         ///
         /// Its SQL type is `Nullable<Timestamptz>`.
@@ -257,6 +375,221 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         slug -> Nullable<Varchar>,
+        /// The `meta_description` column of the `posts` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        meta_description -> Text,
+        /// The `social_image` column of the `posts` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        social_image -> Text,
+    }
+}
+
+table! {
+    /// Representation of the `role_capabilities` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    role_capabilities (role_id, capability) {
+        /// The `role_id` column of the `role_capabilities` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        role_id -> Uuid,
+        /// The `capability` column of the `role_capabilities` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        capability -> Text,
+        /// The `created_at` column of the `role_capabilities` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `created_by` column of the `role_capabilities` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Uuid>,
+    }
+}
+
+
+table! {
+    /// Representation of the `roles` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    roles (id) {
+        /// The `id` column of the `roles` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Uuid,
+        /// The `created_at` column of the `roles` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `created_by` column of the `roles` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Uuid>,
+        /// The `name` column of the `roles` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Text,
+        /// The `description` column of the `roles` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Text,
+    }
+}
+
+table! {
+    /// Representation of the `series` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    series (id) {
+        /// The `id` column of the `series` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Uuid,
+        /// The `created_at` column of the `series` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `created_by` column of the `series` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Uuid>,
+        /// The `updated_at` column of the `series` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamptz,
+        /// The `updated_by` column of the `series` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_by -> Nullable<Uuid>,
+        /// The `title` column of the `series` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        title -> Text,
+        /// The `slug` column of the `series` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        slug -> Text,
+        /// The `description` column of the `series` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Text,
+    }
+}
+
+table! {
+    /// Representation of the `series_posts` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    series_posts (series_id, post_id) {
+        /// The `series_id` column of the `series_posts` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        series_id -> Uuid,
+        /// The `post_id` column of the `series_posts` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        post_id -> Uuid,
+        /// The `position` column of the `series_posts` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        position -> Int4,
+        /// The `created_at` column of the `series_posts` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `created_by` column of the `series_posts` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    /// Representation of the `site_settings` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    site_settings (id) {
+        /// The `id` column of the `site_settings` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Bool,
+        /// The `mode` column of the `site_settings` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        mode -> Jsonb,
+        /// The `identity` column of the `site_settings` table.
+        ///
+        /// Its SQL type is `Nullable<Jsonb>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        identity -> Nullable<Jsonb>,
+        /// The `updated_at` column of the `site_settings` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamptz,
+        /// The `updated_by` column of the `site_settings` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_by -> Nullable<Uuid>,
     }
 }
 
@@ -279,10 +612,10 @@ table! {
         created_at -> Timestamptz,
         /// The `created_by` column of the `tags` table.
         ///
-        /// Its SQL type is `Uuid`.
+        /// Its SQL type is `Nullable<Uuid>`.
         ///
         /// (Automatically generated by Diesel.)
-        created_by -> Uuid,
+        created_by -> Nullable<Uuid>,
         /// The `name` column of the `tags` table.
         ///
         /// Its SQL type is `Text`.
@@ -298,6 +631,38 @@ table! {
     }
 }
 
+table! {
+    /// Representation of the `user_roles` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    user_roles (user_id, role_id) {
+        /// The `user_id` column of the `user_roles` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Uuid,
+        /// The `role_id` column of the `user_roles` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        role_id -> Uuid,
+        /// The `created_at` column of the `user_roles` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `created_by` column of the `user_roles` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Uuid>,
+    }
+}
+
 table! {
     /// Representation of the `users` table.
     ///
@@ -357,20 +722,165 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         email -> Nullable<Varchar>,
+        /// The `preferences` column of the `users` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        preferences -> Jsonb,
+    }
+}
+
+table! {
+    /// Representation of the `webhook_dead_letters` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    webhook_dead_letters (id) {
+        /// The `id` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Uuid,
+        /// The `created_at` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `webhook_id` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        webhook_id -> Uuid,
+        /// The `event` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        event -> Text,
+        /// The `payload` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        payload -> Text,
+        /// The `attempts` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        attempts -> Int4,
+        /// The `last_status` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_status -> Nullable<Int4>,
+        /// The `error` column of the `webhook_dead_letters` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        error -> Text,
+    }
+}
+
+table! {
+    /// Representation of the `webhooks` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    webhooks (id) {
+        /// The `id` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Uuid`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Uuid,
+        /// The `created_at` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamptz,
+        /// The `created_by` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Uuid>,
+        /// The `updated_at` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Timestamptz`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamptz,
+        /// The `updated_by` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Nullable<Uuid>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_by -> Nullable<Uuid>,
+        /// The `url` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Text,
+        /// The `secret` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        secret -> Text,
+        /// The `event_mask` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        event_mask -> Int4,
+        /// The `enabled` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        enabled -> Bool,
     }
 }
 
+joinable!(post_authors -> posts (post_id));
+joinable!(post_authors -> users (user_id));
+joinable!(post_reactions -> posts (post_id));
 joinable!(post_tag_junctions -> posts (post_id));
 joinable!(post_tag_junctions -> tags (tag_id));
 joinable!(post_tag_junctions -> users (created_by));
+joinable!(role_capabilities -> roles (role_id));
+joinable!(roles -> users (created_by));
+joinable!(series_posts -> posts (post_id));
+joinable!(series_posts -> series (series_id));
+joinable!(site_settings -> users (updated_by));
 joinable!(tags -> users (created_by));
+joinable!(user_roles -> roles (role_id));
+joinable!(user_roles -> users (user_id));
+joinable!(webhook_dead_letters -> webhooks (webhook_id));
 
 allow_tables_to_appear_in_same_query!(
     capabilities,
     google_sso,
     passwords,
+    post_authors,
+    post_reactions,
     post_tag_junctions,
     posts,
+    role_capabilities,
+    roles,
+    series,
+    series_posts,
+    site_settings,
     tags,
+    user_roles,
     users,
+    webhook_dead_letters,
+    webhooks,
 );
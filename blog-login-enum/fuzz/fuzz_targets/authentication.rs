@@ -0,0 +1,28 @@
+//! Feeds arbitrary request bodies through what the login handler does with them before looking
+//! anything up: deserializing them into credentials, then normalizing those.
+//!
+//! Run from `blog-login-enum` with `cargo +nightly fuzz run authentication`. The corpus holds
+//! the shapes of body the client sends, along with a few it never should. Bodies as large as the
+//! server reads are only tried when asked for, with `-- -max_len=2097152`.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use blog_login_enum::{
+    normalize::{MAX_PASSWORD_BYTES, MAX_USER_NAME_BYTES},
+    Authentication,
+};
+
+fuzz_target!(|body: &[u8]| {
+    let auth: Authentication = match serde_json::from_slice(body) {
+        Ok(auth) => auth,
+        Err(_) => return,
+    };
+    if let Ok(Authentication::Password(pw)) = auth.normalize() {
+        let user_name = pw.user_name.as_str();
+        assert!(!user_name.is_empty());
+        assert_eq!(user_name, user_name.trim());
+        assert!(user_name.len() <= MAX_USER_NAME_BYTES);
+        assert!(!user_name.contains('\0'));
+        assert!(pw.password.len() <= MAX_PASSWORD_BYTES);
+    }
+});
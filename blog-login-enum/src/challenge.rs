@@ -0,0 +1,133 @@
+//! The proof of work asked of anonymous callers of the public endpoints that change things, such
+//! as creating an account, so that bots cannot call them in bulk for free.
+//!
+//! The server hands out a [`Challenge`]. The caller searches for a nonce such that the SHA-256 of
+//! the token, a colon, and the nonce in decimal starts with at least `difficulty` zero bits, then
+//! sends the token and nonce along with the request in the [`TOKEN_HEADER`] and [`NONCE_HEADER`].
+//! Each token may only be spent once, and expires shortly after it is issued.
+//!
+//! Logging in asks for a challenge too, but only once too many attempts failed from the same
+//! address. The refusal then carries a [`Descriptor`] of the challenge to pass, which may be set by
+//! a provider other than this server, and the login is retried with its [`Answer`].
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// The header carrying the token of a solved challenge.
+pub const TOKEN_HEADER: &str = "X-Challenge-Token";
+/// The header carrying the nonce solving the challenge.
+pub const NONCE_HEADER: &str = "X-Challenge-Nonce";
+/// The header carrying the answer to a challenge set by an external provider, such as a CAPTCHA.
+pub const RESPONSE_HEADER: &str = "X-Challenge-Response";
+/// The status a request is refused with when it needs a solved challenge and has none, or one that
+/// is wrong, expired, or already spent.
+pub const STATUS_CODE: u16 = 428;
+
+/// A challenge to solve before calling a public endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Challenge {
+    /// Opaque to the caller, which only hashes it and sends it back.
+    pub token: String,
+    /// How many leading zero bits the hash must have. Zero when no work is asked for.
+    pub difficulty: u8,
+}
+impl Challenge {
+    /// Whether `nonce` solves the challenge.
+    pub fn is_solved_by(&self, nonce: u64) -> bool {
+        leading_zero_bits(&self.token, nonce) >= u32::from(self.difficulty)
+    }
+}
+
+/// A challenge to pass before a login is tried.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Descriptor {
+    /// A proof of work handed out by this server, solved as any other [`Challenge`].
+    ProofOfWork(Challenge),
+    /// A check set by another service, such as a CAPTCHA, shown with the script of `provider`.
+    External { provider: String, site_key: String },
+}
+
+/// What passes a [`Descriptor`], sent along with the retried request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Answer {
+    ProofOfWork { token: String, nonce: u64 },
+    /// What the external provider handed the caller once the check was passed.
+    External { response: String },
+}
+impl Answer {
+    /// The headers carrying the answer.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::ProofOfWork { token, nonce } => vec![
+                (TOKEN_HEADER, token.clone()),
+                (NONCE_HEADER, nonce.to_string()),
+            ],
+            Self::External { response } => vec![(RESPONSE_HEADER, response.clone())],
+        }
+    }
+    /// Reads the answer back from the headers, looked up by name with `header`.
+    pub fn from_headers<'a>(header: impl Fn(&str) -> Option<&'a str>) -> Option<Self> {
+        if let Some(response) = header(RESPONSE_HEADER) {
+            return Some(Self::External {
+                response: response.to_owned(),
+            });
+        }
+        let token = header(TOKEN_HEADER)?;
+        let nonce = header(NONCE_HEADER)?.parse().ok()?;
+        Some(Self::ProofOfWork {
+            token: token.to_owned(),
+            nonce,
+        })
+    }
+}
+
+/// The number of leading zero bits of the hash of `token` with `nonce`.
+pub fn leading_zero_bits(token: &str, nonce: u64) -> u32 {
+    let digest = Sha256::new()
+        .chain(token.as_bytes())
+        .chain(b":")
+        .chain(nonce.to_string().as_bytes())
+        .finalize();
+    let mut bits = 0;
+    for byte in digest.iter() {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn answers_are_read_back_from_their_headers() {
+        let answers = vec![
+            Answer::ProofOfWork {
+                token: "v1.local.token".to_owned(),
+                nonce: 42,
+            },
+            Answer::External {
+                response: "passed".to_owned(),
+            },
+        ];
+        for answer in answers {
+            let headers: HashMap<_, _> = answer.headers().into_iter().collect();
+            let read = Answer::from_headers(|name| headers.get(name).map(String::as_str));
+            assert_eq!(read, Some(answer));
+        }
+        assert_eq!(Answer::from_headers(|_| None), None);
+        let unparsable = |name: &str| match name {
+            TOKEN_HEADER => Some("v1.local.token"),
+            NONCE_HEADER => Some("nan"),
+            _ => None,
+        };
+        assert_eq!(Answer::from_headers(unparsable), None);
+    }
+}
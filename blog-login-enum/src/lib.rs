@@ -2,9 +2,15 @@
 
 use serde::{Deserialize, Serialize};
 
+pub mod challenge;
+pub mod normalize;
+pub mod validation;
+pub use validation::{ValidationErrors, Violation};
+
 /// Password authentication data. Separated from AuthenticationData to allow for impl blocks. Will
 /// go away once enum variants become types.
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Password {
     pub user_name: String,
     pub password: String,
@@ -13,6 +19,7 @@ pub struct Password {
 /// Actual data that needs to be verified before someone can log in.
 /// Currently only allows for passwords, but planning to support SSO and FIDO.
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Authentication {
     /// Data needed to fully specify a password credential from the request.
     Password(Password),
@@ -21,6 +28,7 @@ pub enum Authentication {
 /// Password authentication data. Separated from AuthenticationData to allow for impl blocks. Will
 /// go away once enum variants become types.
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CreatePassword {
     pub user_id: uuid::Uuid,
     pub password: String,
@@ -29,7 +37,56 @@ pub struct CreatePassword {
 /// Information for creating a credential.
 /// Currently only allows for passwords, but planning to support SSO and FIDO.
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Create {
     /// Data needed to fully specify a password credential from the request.
     Password(CreatePassword),
 }
+
+/// A new password for an account that already has one. Changing your own password requires the
+/// current one, so that a session left signed in cannot be used to lock you out.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChangePassword {
+    pub current: Option<String>,
+    pub new: String,
+}
+
+/// Why logging in failed, as the body of the failed response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum LoginFailure {
+    /// The user name or password is wrong. Which one is not said, so that whether an account
+    /// exists is not given away.
+    BadCredentials,
+    /// The account may not be logged in to for now.
+    Locked,
+    /// Too many attempts were made recently. Retrying works after the given number of seconds.
+    RateLimited { retry_after_secs: u64 },
+    /// Too many attempts failed from the same address recently. Retrying works once the challenge
+    /// is passed, with its [answer](challenge::Answer) sent along.
+    ChallengeRequired { challenge: challenge::Descriptor },
+}
+impl LoginFailure {
+    /// Every status a failure is responded with.
+    pub const STATUS_CODES: &'static [u16] = &[401, 423, 428, 429];
+
+    /// The status the failure is responded with.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Self::BadCredentials => 401,
+            Self::Locked => 423,
+            Self::RateLimited { .. } => 429,
+            Self::ChallengeRequired { .. } => challenge::STATUS_CODE,
+        }
+    }
+}
+
+/// The body of a 413 response, refusing a request whose body is larger than the route accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TooLarge {
+    /// The most bytes the route accepts in a body.
+    pub limit_bytes: u64,
+}
@@ -0,0 +1,102 @@
+//! The checks credentials submitted to log in pass before they are looked up, kept apart from the
+//! server so that they can be fuzzed on their own.
+//!
+//! Anything refused here is refused the same as a wrong password, so that the reason is not given
+//! away.
+
+use crate::{Authentication, Password};
+
+/// The longest user name accepted when logging in, in bytes, after trimming.
+pub const MAX_USER_NAME_BYTES: usize = 256;
+/// The longest password accepted when logging in, in bytes. Passwords are set through routes that
+/// take no larger bodies than this, so no password that was set can be longer.
+pub const MAX_PASSWORD_BYTES: usize = 64 * 1024;
+
+/// Why submitted credentials were refused before being looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Malformed {
+    /// The user name is empty, or only whitespace.
+    BlankUserName,
+    UserNameTooLong,
+    /// The user name has a control character, which no user name can have, such as a null byte.
+    UserNameControlCharacter,
+    PasswordTooLong,
+}
+
+impl Password {
+    /// The password with the whitespace around the user name trimmed, if it could belong to a
+    /// user at all.
+    pub fn normalize(self) -> Result<Self, Malformed> {
+        let user_name = self.user_name.trim();
+        if user_name.is_empty() {
+            Err(Malformed::BlankUserName)
+        } else if user_name.len() > MAX_USER_NAME_BYTES {
+            Err(Malformed::UserNameTooLong)
+        } else if user_name.chars().any(char::is_control) {
+            Err(Malformed::UserNameControlCharacter)
+        } else if self.password.len() > MAX_PASSWORD_BYTES {
+            Err(Malformed::PasswordTooLong)
+        } else {
+            Ok(Self {
+                user_name: user_name.to_owned(),
+                password: self.password,
+            })
+        }
+    }
+}
+
+impl Authentication {
+    /// The credentials as they are looked up, or why they could not belong to anyone.
+    pub fn normalize(self) -> Result<Self, Malformed> {
+        match self {
+            Self::Password(pw) => pw.normalize().map(Self::Password),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+
+    fn normalized(user_name: &str, password: &str) -> Result<String, Malformed> {
+        let auth = Authentication::Password(Password {
+            user_name: user_name.to_owned(),
+            password: password.to_owned(),
+        });
+        auth.normalize().map(|auth| match auth {
+            Authentication::Password(pw) => pw.user_name,
+        })
+    }
+
+    #[test]
+    fn user_names_are_trimmed() {
+        assert_eq!(normalized("  ben\t", "pw"), Ok("ben".to_owned()));
+        assert_eq!(normalized("b e n", "pw"), Ok("b e n".to_owned()));
+        assert_eq!(normalized("\u{3000}", "pw"), Err(Malformed::BlankUserName));
+        assert_eq!(normalized("", "pw"), Err(Malformed::BlankUserName));
+    }
+
+    #[test]
+    fn extreme_input_is_refused() {
+        let long = "a".repeat(1024 * 1024);
+        assert_eq!(normalized(&long, "pw"), Err(Malformed::UserNameTooLong));
+        assert_eq!(normalized("ben", &long), Err(Malformed::PasswordTooLong));
+        assert_eq!(normalized("b\0en", "pw"), Err(Malformed::UserNameControlCharacter));
+        assert_eq!(normalized("b\u{1b}[2Jen", "pw"), Err(Malformed::UserNameControlCharacter));
+        // Combining marks stacked on one letter are long in bytes but only one character wide.
+        let zalgo = format!("b{}", "\u{301}".repeat(MAX_USER_NAME_BYTES));
+        assert_eq!(normalized(&zalgo, "pw"), Err(Malformed::UserNameTooLong));
+    }
+
+    #[test]
+    fn passwords_are_left_alone() {
+        let auth = Authentication::Password(Password {
+            user_name: "ben".to_owned(),
+            password: " \0pässwörd ".to_owned(),
+        });
+        match auth.normalize() {
+            Ok(Authentication::Password(pw)) => assert_eq!(pw.password, " \0pässwörd "),
+            Err(e) => panic!("refused with {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! The body of every 422 response: what is wrong with each field of a submitted form, so that forms
+//! can show the errors next to the fields.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One thing wrong with a field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Violation {
+    /// What is wrong, for telling errors apart without reading the message, such as `required` or
+    /// `too_long`.
+    pub code: String,
+    /// What is wrong, written for the user.
+    pub message: String,
+    /// The values the message mentions, such as the most characters allowed, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+}
+impl Violation {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_owned(),
+            message: message.into(),
+            params: BTreeMap::new(),
+        }
+    }
+    /// Notes a value the message mentions.
+    pub fn param(&mut self, name: &str, value: impl ToString) -> &mut Self {
+        self.params.insert(name.to_owned(), value.to_string());
+        self
+    }
+}
+
+/// What is wrong with a submitted form, keyed by the path of each field at fault as it is
+/// serialized in the request, such as `title` or `seo.meta_description`. Fields without errors are
+/// left out, and what is wrong with the form as a whole is kept under [`FORM`](Self::FORM).
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct ValidationErrors(BTreeMap<String, Vec<Violation>>);
+impl ValidationErrors {
+    /// The key of what is wrong with the form as a whole, rather than any one field.
+    pub const FORM: &'static str = "form";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Errors naming a single thing wrong with a single field.
+    pub fn single(field: &str, code: &str, message: impl Into<String>) -> Self {
+        let mut errors = Self::new();
+        errors.add(field, code, message);
+        errors
+    }
+    /// Notes something wrong with the field, returning it so that the values its message mentions
+    /// can be added.
+    pub fn add(&mut self, field: &str, code: &str, message: impl Into<String>) -> &mut Violation {
+        let violations = self.0.entry(field.to_owned()).or_insert_with(Vec::new);
+        violations.push(Violation::new(code, message));
+        violations.last_mut().expect("A violation was just added.")
+    }
+    /// Notes everything wrong in `other` as well.
+    pub fn merge(&mut self, other: Self) {
+        for (field, violations) in other.0 {
+            self.0.entry(field).or_insert_with(Vec::new).extend(violations);
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// What is wrong with the field, if anything.
+    pub fn field(&self, field: &str) -> &[Violation] {
+        self.0.get(field).map_or(&[], Vec::as_slice)
+    }
+    /// The fields at fault, in order.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+    /// Forgets what is wrong with the field, such as once it is edited.
+    pub fn clear(&mut self, field: &str) {
+        self.0.remove(field);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn errors_are_keyed_by_field() {
+        let mut errors = ValidationErrors::new();
+        errors.add("title", "required", "Enter a title.");
+        errors
+            .add("title", "too_long", "Keep the title short.")
+            .param("max", 200);
+        errors.merge(ValidationErrors::single("body", "invalid", "Remove the NUL characters."));
+        assert_eq!(errors.fields().collect::<Vec<_>>(), vec!["body", "title"]);
+        assert_eq!(errors.field("title").len(), 2);
+        assert_eq!(errors.field("title")[1].params["max"], "200");
+        assert!(errors.field("slug").is_empty());
+        errors.clear("title");
+        assert_eq!(errors.fields().collect::<Vec<_>>(), vec!["body"]);
+        errors.clear("body");
+        assert!(errors.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+//! Token verification throughput with many concurrent readers of the rotating keys, compared
+//! against the keys being behind an [`RwLock`] as they used to be.
+//!
+//! Run with `cargo bench -p bundled-crypto --bench key_store`.
+
+use bundled_crypto::{
+    algo::cipher::xchacha20::poly1305,
+    token::paseto::{self, token, Protocol},
+    Generational, KeyRotator, RotatingKeyStore, RotationConfig,
+};
+use std::{
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Number of threads verifying tokens at once.
+const READERS: usize = 8;
+/// Number of tokens each thread verifies.
+const VERIFICATIONS: usize = 20_000;
+
+/// A source of the current keys.
+trait Keys: Send + Sync + 'static {
+    fn current(&self) -> Arc<RotatingKeyStore<poly1305::Algo>>;
+}
+impl Keys for RwLock<Arc<RotatingKeyStore<poly1305::Algo>>> {
+    fn current(&self) -> Arc<RotatingKeyStore<poly1305::Algo>> {
+        Arc::clone(&*self.read().unwrap())
+    }
+}
+impl Keys for bundled_crypto::RotatingKeyFixture<poly1305::Algo> {
+    fn current(&self) -> Arc<RotatingKeyStore<poly1305::Algo>> {
+        self.get_store().unwrap()
+    }
+}
+
+/// Verifies `token` [`VERIFICATIONS`] times from each of the [`READERS`], returning the number of
+/// verifications per second.
+fn throughput<K: Keys>(keys: Arc<K>, token: Vec<u8>) -> f64 {
+    let started = Instant::now();
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let keys = Arc::clone(&keys);
+            let token = token.clone();
+            thread::spawn(move || {
+                for _ in 0..VERIFICATIONS {
+                    let verified: Result<token::Data<String, ()>, _> =
+                        keys.current().attempt_with_retry(&mut |key, _| {
+                            paseto::V2Local::decrypt(token::Packed::new(token.clone()), key)
+                        });
+                    verified.expect("A valid token.");
+                }
+            })
+        })
+        .collect();
+    for reader in readers {
+        reader.join().expect("Reader to not panic.");
+    }
+    (READERS * VERIFICATIONS) as f64 / started.elapsed().as_secs_f64()
+}
+
+fn main() {
+    bundled_crypto::multithread_init().expect("Sodium to initialize.");
+    let rotator = KeyRotator::init(
+        poly1305::Algo,
+        RotationConfig::new(Duration::from_secs(60 * 60)),
+    );
+    let fixture = rotator.get_key_fixture();
+    let store = fixture.get_store().unwrap();
+    let tok = token::Data {
+        msg: "benchmark".to_owned(),
+        footer: None::<()>,
+    };
+    let token = paseto::V2Local::encrypt(tok, store.curr())
+        .unwrap()
+        .to_vec();
+
+    let locked = throughput(Arc::new(RwLock::new(store)), token.clone());
+    let swapped = throughput(Arc::new(fixture), token);
+    println!(
+        "{} readers behind an RwLock: {:.0} verifications/s",
+        READERS, locked
+    );
+    println!(
+        "{} readers of the swapped keys: {:.0} verifications/s",
+        READERS, swapped
+    );
+    println!("Speedup: {:.2}x", swapped / locked);
+    rotator.cleanup().expect("Rotation thread to exit cleanly.");
+}
@@ -0,0 +1,89 @@
+//! The committed baseline runs are compared against, so that a change can be judged without
+//! first checking out and benchmarking the code before it.
+//!
+//! The baseline holds the mean time of each benchmark, in nanoseconds, read from the estimates
+//! criterion leaves behind. Set `CRYPTO_BENCH_BASELINE=record` to overwrite it with the run just
+//! made, and name the machine in the file when recording on another one, since the numbers mean
+//! nothing elsewhere.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// The variable that, set to `record`, overwrites the baseline with the run just made.
+const MODE_VAR: &str = "CRYPTO_BENCH_BASELINE";
+/// How much slower than the baseline a benchmark may get before it is called out.
+const TOLERANCE: f64 = 0.10;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    /// Describes the machine the baseline was recorded on.
+    machine: Option<String>,
+    /// The mean time of each benchmark, in nanoseconds.
+    mean_ns: BTreeMap<String, f64>,
+}
+
+/// Just enough of the `estimates.json` criterion writes to read the mean back.
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+}
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+}
+
+fn baseline_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("benches/baseline.json")
+}
+
+/// Where criterion keeps its results, which is under the target directory of the workspace.
+fn criterion_dir() -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../target"))
+        .join("criterion")
+}
+
+/// The mean of the benchmark `id` in the run just made, if it was run.
+fn measured(id: &str) -> Option<f64> {
+    let path = criterion_dir().join(id).join("new/estimates.json");
+    let estimates: Estimates = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    Some(estimates.mean.point_estimate)
+}
+
+/// Compares the benchmarks `ids` of the run just made against the baseline, or records them as
+/// the baseline when asked to.
+pub fn compare_or_record(ids: &[String]) {
+    let path = baseline_path();
+    let mut baseline: Baseline = fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    let run: BTreeMap<_, _> = ids
+        .iter()
+        .filter_map(|id| measured(id).map(|mean| (id.clone(), mean)))
+        .collect();
+
+    if std::env::var(MODE_VAR).as_deref() == Ok("record") {
+        baseline.mean_ns.extend(run);
+        let json = serde_json::to_string_pretty(&baseline).expect("A serializable baseline.");
+        fs::write(&path, json + "\n").expect("The baseline to be writable.");
+        println!("Recorded the baseline in {}.", path.display());
+        return;
+    }
+
+    println!(
+        "Compared against the baseline recorded on {}:",
+        baseline.machine.as_deref().unwrap_or("an unnamed machine")
+    );
+    for (id, mean) in run {
+        match baseline.mean_ns.get(&id) {
+            Some(&base) => {
+                let change = mean / base - 1.0;
+                let flag = if change > TOLERANCE { "  <- slower" } else { "" };
+                println!("  {:<32} {:>+7.1}%{}", id, change * 100.0, flag);
+            }
+            None => println!("  {:<32} not in the baseline", id),
+        }
+    }
+}
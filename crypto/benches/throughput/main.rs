@@ -0,0 +1,187 @@
+//! Throughput of the work done on every login and every request: hashing passwords, sealing and
+//! unsealing tokens, and verifying tokens from many threads at once against the rotating keys.
+//!
+//! Run with `cargo bench -p bundled-crypto --bench throughput`. Criterion's own baselines work as
+//! usual, with `-- --save-baseline <name>` and `-- --baseline <name>`. Each run is also compared
+//! against the baseline committed in `benches/baseline.json`. See [`baseline`] for recording it.
+
+use bundled_crypto::{
+    algo::{
+        cipher::xchacha20::poly1305,
+        hash::{
+            argon2::{d, Argon2Params},
+            symmetric::Algo as _,
+        },
+        SafeGenerateKey,
+    },
+    token::sealed,
+    KeyRotator, RotatingKeyFixture, RotationConfig,
+};
+use criterion::{BenchmarkId, Criterion, Throughput};
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+mod baseline;
+
+/// Parameter sets to hash with, from the cheapest Argon2 allows to well past the defaults.
+const HASH_PARAMS: &[(&str, Argon2Params)] = &[
+    (
+        "minimal",
+        Argon2Params {
+            mem_kib: 8,
+            iterations: 1,
+            parallelism: 1,
+            hash_len: 32,
+        },
+    ),
+    (
+        "default",
+        Argon2Params {
+            mem_kib: 4096,
+            iterations: 3,
+            parallelism: 1,
+            hash_len: 32,
+        },
+    ),
+    (
+        "64mib",
+        Argon2Params {
+            mem_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+            hash_len: 32,
+        },
+    ),
+    (
+        "64mib_4_lanes",
+        Argon2Params {
+            mem_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+            hash_len: 32,
+        },
+    ),
+];
+/// Numbers of threads verifying tokens at once.
+const VERIFIERS: &[usize] = &[1, 4, 16];
+
+/// What a token carries, about the size of the capabilities of a user.
+fn claims() -> Vec<String> {
+    (0..16).map(|i| format!("capability-{}", i)).collect()
+}
+
+fn password_hashing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("password_hash");
+    // Each hash is slow by design, so fewer samples are enough.
+    group.sample_size(10);
+    for (name, params) in HASH_PARAMS {
+        let algo = d::Algo::with_params(*params).expect("Valid parameters.");
+        let key = d::Key::safe_generate(&());
+        group.bench_with_input(BenchmarkId::from_parameter(name), params, |b, _| {
+            b.iter(|| {
+                let msg = d::SigningData::new_default_hash_len(b"correct horse".to_vec(), None);
+                algo.sign(&msg, &key)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn tokens(c: &mut Criterion) {
+    let key = poly1305::Key::safe_generate(&());
+    let now = SystemTime::now();
+    let token = sealed::Token::new(claims(), 0, now, Duration::from_secs(60));
+    let packed = token.seal(&key).expect("A sealable token.");
+
+    let mut group = c.benchmark_group("token");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("seal", |b| b.iter(|| token.seal(&key).unwrap()));
+    group.bench_function("unseal", |b| {
+        b.iter(|| sealed::Token::<Vec<String>>::unseal(&packed, &key, now).unwrap())
+    });
+    group.finish();
+}
+
+/// Verifies `packed` `count` times, looking up its key in the current keys each time the way the
+/// server does.
+fn verify(fixture: &RotatingKeyFixture<poly1305::Algo>, packed: &str, count: u64) {
+    let now = SystemTime::now();
+    for _ in 0..count {
+        let store = fixture.get_store().unwrap();
+        let generation = sealed::key_id(packed)
+            .ok()
+            .and_then(|id| store.generation(id))
+            .expect("The token to be sealed with a current key.");
+        sealed::Token::<Vec<String>>::unseal(packed, &*generation.key, now)
+            .expect("A valid token.");
+    }
+}
+
+fn concurrent_verification(c: &mut Criterion) {
+    let rotator = KeyRotator::init(
+        poly1305::Algo,
+        RotationConfig::new(Duration::from_secs(60 * 60)),
+    );
+    let fixture = Arc::new(rotator.get_key_fixture());
+    let packed: Arc<str> = {
+        let store = fixture.get_store().unwrap();
+        let generation = store.curr_generation();
+        let lifetime = Duration::from_secs(60);
+        sealed::Token::new(claims(), generation.id, SystemTime::now(), lifetime)
+            .seal(&*generation.key)
+            .expect("A sealable token.")
+            .into()
+    };
+
+    let mut group = c.benchmark_group("concurrent_verification");
+    group.throughput(Throughput::Elements(1));
+    for &threads in VERIFIERS {
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            // Reported per verification, so the time falls as threads are added until they
+            // contend.
+            b.iter_custom(|iters| {
+                let per_thread = (iters / threads as u64).max(1);
+                let started = Instant::now();
+                let verifiers: Vec<_> = (0..threads)
+                    .map(|_| {
+                        let fixture = Arc::clone(&fixture);
+                        let packed = Arc::clone(&packed);
+                        thread::spawn(move || verify(&fixture, &packed, per_thread))
+                    })
+                    .collect();
+                for verifier in verifiers {
+                    verifier.join().expect("Verifier to not panic.");
+                }
+                let verified = per_thread * threads as u64;
+                started.elapsed().mul_f64(iters as f64 / verified as f64)
+            })
+        });
+    }
+    group.finish();
+    rotator.cleanup().expect("Rotation thread to exit cleanly.");
+}
+
+/// The ids of every benchmark, as criterion names them.
+fn benchmark_ids() -> Vec<String> {
+    let hashes = HASH_PARAMS
+        .iter()
+        .map(|(name, _)| format!("password_hash/{}", name));
+    let tokens = ["seal", "unseal"].iter().map(|op| format!("token/{}", op));
+    let verifications = VERIFIERS
+        .iter()
+        .map(|threads| format!("concurrent_verification/{}", threads));
+    hashes.chain(tokens).chain(verifications).collect()
+}
+
+fn main() {
+    bundled_crypto::multithread_init().expect("Sodium to initialize.");
+    let mut c = Criterion::default().configure_from_args();
+    password_hashing(&mut c);
+    tokens(&mut c);
+    concurrent_verification(&mut c);
+    c.final_summary();
+    baseline::compare_or_record(&benchmark_ids());
+}
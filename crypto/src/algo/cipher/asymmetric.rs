@@ -30,7 +30,7 @@ pub trait CanEncryptPublic: Algo
 where
     <Self as base::Algo>::Key: Key + HasPublic,
 {
-    type PublicKey = <Self::Key as HasPublic>::PublicKey;
+    type PublicKey;
     type Error;
     type Input: ?Sized;
     fn public_encrypt(key: &Self::PublicKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error>;
@@ -40,7 +40,7 @@ pub trait CanDecryptPublic: Algo
 where
     <Self as base::Algo>::Key: Key + HasPublic,
 {
-    type PublicKey = <Self::Key as HasPublic>::PublicKey;
+    type PublicKey;
     type Error;
     type Input: ?Sized;
     fn public_decrypt(key: &Self::PublicKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error>;
@@ -50,7 +50,7 @@ pub trait CanEncryptPrivate: Algo
 where
     <Self as base::Algo>::Key: Key + HasPrivate,
 {
-    type PrivateKey = <Self::Key as HasPrivate>::PrivateKey;
+    type PrivateKey;
     type Error;
     type Input: ?Sized;
     fn private_encrypt(key: &Self::PrivateKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error>;
@@ -60,7 +60,7 @@ pub trait CanDecryptPrivate: Algo
 where
     <Self as base::Algo>::Key: Key + HasPrivate,
 {
-    type PrivateKey = <Self::Key as HasPrivate>::PrivateKey;
+    type PrivateKey;
     type Error;
     type Input: ?Sized;
     fn private_decrypt(key: &Self::PrivateKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error>;
@@ -0,0 +1,259 @@
+//! Envelope encryption for secrets at rest, such as persisted keys and client secrets.
+//!
+//! Secrets are sealed with a [`MasterKey`] using XChaCha20-Poly1305, and the resulting
+//! [`Envelope`] records which master key sealed it so that a wrong key is reported as such
+//! instead of as tampering.
+
+use crate::{
+    algo::{
+        cipher::{
+            symmetric::{CanDecrypt, CanEncrypt},
+            xchacha20::poly1305,
+        },
+        SecretBytes,
+    },
+    encoding::{self, Base64, Encoding},
+    key_rotation::PersistableKey,
+};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::aead::xchacha20poly1305_ietf::{gen_nonce, Nonce, NONCEBYTES};
+use std::{convert::TryInto, env, fmt, fs, io, path::Path};
+use zeroize::Zeroize;
+
+/// The domain separation key for deriving the id of a master key from the key.
+const KEY_ID_CONTEXT: &[u8] = b"benxu.dev master key id";
+/// The number of bytes the id of a master key takes up in a serialized envelope.
+const KEY_ID_BYTES: usize = 8;
+/// The fewest bytes a secret must have to derive a master key from.
+pub const MIN_SECRET_BYTES: usize = 16;
+
+/// Errors from loading a [`MasterKey`].
+#[derive(Debug)]
+pub enum LoadError {
+    /// The environment variable is not set.
+    Missing,
+    /// The environment variable does not hold base64.
+    NotBase64(encoding::DecodeError),
+    /// The file could not be read.
+    Io(io::Error),
+    /// The secret holds fewer than [`MIN_SECRET_BYTES`] bytes.
+    TooShort,
+}
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Errors from opening an [`Envelope`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenError {
+    /// The envelope was sealed with a different master key, whose id is given.
+    WrongKey { key_id: u64 },
+    /// The bytes are too short to be an envelope.
+    Malformed,
+    /// The ciphertext or the additional data was tampered with.
+    Forged,
+}
+
+/// A key that secrets at rest are sealed with.
+#[derive(Clone)]
+pub struct MasterKey {
+    id: u64,
+    key: poly1305::Key,
+}
+impl fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MasterKey").field("id", &self.id).finish()
+    }
+}
+impl MasterKey {
+    /// Derives a master key from a secret of any length.
+    pub fn from_secret(secret: &[u8]) -> Self {
+        let hash = blake2_rfc::blake2b::blake2b(32, &[], secret);
+        let key = poly1305::Key::from_bytes(hash.as_bytes()).expect("A 32 byte hash.");
+        let id = blake2_rfc::blake2b::blake2b(KEY_ID_BYTES, KEY_ID_CONTEXT, hash.as_bytes());
+        let id = u64::from_be_bytes(id.as_bytes().try_into().expect("An 8 byte hash."));
+        Self { id, key }
+    }
+    /// Derives a master key from the contents of a file, which must hold at least
+    /// [`MIN_SECRET_BYTES`] bytes.
+    pub fn from_file(path: &Path) -> Result<Self, LoadError> {
+        Self::from_long_secret(SecretBytes::new(fs::read(path)?))
+    }
+    /// Derives a master key from the base64 encoded secret in an environment variable, which must
+    /// decode to at least [`MIN_SECRET_BYTES`] bytes.
+    pub fn from_env(name: &str) -> Result<Self, LoadError> {
+        let encoded = SecretBytes::new(env::var(name).map_err(|_| LoadError::Missing)?.into());
+        let secret = Base64::decode_bytes(&encoded).map_err(LoadError::NotBase64)?;
+        Self::from_long_secret(SecretBytes::new(secret))
+    }
+    fn from_long_secret(secret: SecretBytes) -> Result<Self, LoadError> {
+        if secret.len() < MIN_SECRET_BYTES {
+            return Err(LoadError::TooShort);
+        }
+        Ok(Self::from_secret(&secret))
+    }
+    /// The id of the key, which is recorded in the envelopes it seals.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+    /// The additional data actually authenticated, which binds the key id to the ciphertext.
+    fn bound_aad(&self, aad: &[u8]) -> Vec<u8> {
+        let mut bound = self.id.to_be_bytes().to_vec();
+        bound.extend_from_slice(aad);
+        bound
+    }
+    /// Encrypts `plaintext` under a fresh nonce. The same `aad` must be given to open it again.
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Envelope {
+        let nonce = gen_nonce();
+        let mut args = poly1305::EncryptArgs {
+            plaintext: plaintext.to_vec(),
+            aad: Some(self.bound_aad(aad)),
+            nonce: Some(nonce),
+        };
+        let ciphertext = poly1305::Algo
+            .encrypt(&self.key, &args)
+            .expect("Encryption with XChaCha20-Poly1305 never fails.");
+        args.plaintext.zeroize();
+        Envelope {
+            key_id: self.id,
+            nonce,
+            ciphertext,
+        }
+    }
+    /// Decrypts the envelope, checking that it was sealed with this key and `aad`.
+    pub fn open(&self, envelope: &Envelope, aad: &[u8]) -> Result<SecretBytes, OpenError> {
+        if envelope.key_id != self.id {
+            return Err(OpenError::WrongKey {
+                key_id: envelope.key_id,
+            });
+        }
+        let args = poly1305::DecryptArgs {
+            ciphertext: envelope.ciphertext.clone(),
+            aad: Some(self.bound_aad(aad)),
+            nonce: envelope.nonce,
+        };
+        poly1305::Algo
+            .decrypt(&self.key, &args)
+            .map(SecretBytes::new)
+            .map_err(|_| OpenError::Forged)
+    }
+}
+
+/// A sealed secret, along with what is needed to open it other than the key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// The id of the master key that sealed the secret.
+    pub key_id: u64,
+    /// The nonce the secret was sealed under.
+    pub nonce: Nonce,
+    /// The encrypted secret, followed by its authentication tag.
+    pub ciphertext: Vec<u8>,
+}
+impl Envelope {
+    /// Serializes the envelope as the key id, then the nonce, then the ciphertext.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(KEY_ID_BYTES + NONCEBYTES + self.ciphertext.len());
+        bytes.extend_from_slice(&self.key_id.to_be_bytes());
+        bytes.extend_from_slice(self.nonce.as_ref());
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+    /// Parses the output of [`to_bytes`](Envelope::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OpenError> {
+        if bytes.len() < KEY_ID_BYTES + NONCEBYTES {
+            return Err(OpenError::Malformed);
+        }
+        let (key_id, rest) = bytes.split_at(KEY_ID_BYTES);
+        let (nonce, ciphertext) = rest.split_at(NONCEBYTES);
+        Ok(Self {
+            key_id: u64::from_be_bytes(key_id.try_into().expect("Split at the id length.")),
+            nonce: Nonce::from_slice(nonce).expect("Split at the nonce length."),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use rand::{rngs::OsRng, Rng, RngCore};
+
+    fn master_key() -> MasterKey {
+        MasterKey::from_secret(b"master secret")
+    }
+
+    #[test]
+    fn sealed_secrets_open_again() {
+        let key = master_key();
+        for len in 0..64 {
+            let mut secret = vec![0; len];
+            OsRng.fill_bytes(&mut secret);
+            let envelope = key.seal(&secret, b"context");
+            assert_eq!(envelope.key_id, key.id());
+            let parsed = Envelope::from_bytes(&envelope.to_bytes()).unwrap();
+            assert_eq!(parsed, envelope);
+            assert_eq!(&*key.open(&parsed, b"context").unwrap(), secret.as_slice());
+        }
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let key = master_key();
+        let envelope = key.seal(b"client secret", b"context");
+        for _ in 0..64 {
+            let mut tampered = envelope.clone();
+            let i = OsRng.gen_range(0, tampered.ciphertext.len());
+            tampered.ciphertext[i] ^= 1 << OsRng.gen_range(0, 8);
+            assert_eq!(key.open(&tampered, b"context"), Err(OpenError::Forged));
+        }
+        let mut truncated = envelope.clone();
+        truncated.ciphertext.pop();
+        assert_eq!(key.open(&truncated, b"context"), Err(OpenError::Forged));
+        assert_eq!(
+            Envelope::from_bytes(&envelope.to_bytes()[..KEY_ID_BYTES + NONCEBYTES - 1]),
+            Err(OpenError::Malformed)
+        );
+    }
+
+    #[test]
+    fn wrong_aad_or_key_is_rejected() {
+        let key = master_key();
+        let envelope = key.seal(b"client secret", b"provider: google");
+        assert_eq!(
+            key.open(&envelope, b"provider: github"),
+            Err(OpenError::Forged)
+        );
+        assert_eq!(key.open(&envelope, b""), Err(OpenError::Forged));
+        let other = MasterKey::from_secret(b"another secret");
+        assert_eq!(
+            other.open(&envelope, b"provider: google"),
+            Err(OpenError::WrongKey { key_id: key.id() })
+        );
+        // Claiming another key id doesn't get past the authentication either.
+        let mut relabeled = other.seal(b"client secret", b"provider: google");
+        relabeled.key_id = key.id();
+        assert_eq!(
+            key.open(&relabeled, b"provider: google"),
+            Err(OpenError::Forged)
+        );
+    }
+
+    #[test]
+    fn short_secrets_are_not_loaded() {
+        let path = std::env::temp_dir().join(format!("master_key_{}", rand::random::<u64>()));
+        fs::write(&path, b"too short").unwrap();
+        match MasterKey::from_file(&path) {
+            Err(LoadError::TooShort) => {}
+            res => panic!("Expected a short secret, got {:?}.", res),
+        }
+        fs::write(&path, [7; MIN_SECRET_BYTES]).unwrap();
+        let loaded = MasterKey::from_file(&path).unwrap();
+        assert_eq!(
+            loaded.id(),
+            MasterKey::from_secret(&[7; MIN_SECRET_BYTES]).id()
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -4,5 +4,6 @@ pub mod asymmetric;
 pub mod symmetric;
 
 pub mod aes256;
+pub mod envelope;
 pub mod plaintext;
 pub mod xchacha20;
@@ -1,9 +1,14 @@
 //! A basic "cipher" that simply returns what was provided.
 
-use crate::algo::{
-    self as base,
-    cipher::{asymmetric as asymm, symmetric as symm},
+use crate::{
+    algo::{
+        self as base,
+        cipher::{asymmetric as asymm, symmetric as symm},
+        SecretBytes,
+    },
+    key_rotation::PersistableKey,
 };
+use std::convert::Infallible;
 
 pub struct Algo;
 
@@ -28,6 +33,14 @@ impl base::Algo for Algo {
 }
 
 impl symm::Key for () {}
+impl PersistableKey for () {
+    fn to_bytes(&self) -> SecretBytes {
+        SecretBytes::default()
+    }
+    fn from_bytes(_: &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
 impl symm::Algo for Algo {}
 impl symm::CanDecrypt for Algo {
     type Input = [u8];
@@ -62,7 +75,7 @@ impl asymm::HasPrivate for () {
 
 impl asymm::Algo for Algo {}
 impl asymm::CanDecryptPublic for Algo {
-    type Error = !;
+    type Error = Infallible;
     type Input = [u8];
     type PublicKey = ();
     fn public_decrypt(_: &Self::PublicKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error> {
@@ -70,7 +83,7 @@ impl asymm::CanDecryptPublic for Algo {
     }
 }
 impl asymm::CanEncryptPublic for Algo {
-    type Error = !;
+    type Error = Infallible;
     type Input = [u8];
     type PublicKey = ();
     fn public_encrypt(_: &Self::PublicKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error> {
@@ -78,7 +91,7 @@ impl asymm::CanEncryptPublic for Algo {
     }
 }
 impl asymm::CanDecryptPrivate for Algo {
-    type Error = !;
+    type Error = Infallible;
     type Input = [u8];
     type PrivateKey = ();
     fn private_decrypt(_: &Self::PrivateKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error> {
@@ -86,7 +99,7 @@ impl asymm::CanDecryptPrivate for Algo {
     }
 }
 impl asymm::CanEncryptPrivate for Algo {
-    type Error = !;
+    type Error = Infallible;
     type Input = [u8];
     type PrivateKey = ();
     fn private_encrypt(_: &Self::PrivateKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error> {
@@ -18,7 +18,7 @@ pub trait CanEncrypt: Algo
 where
     <Self as base::Algo>::Key: Key,
 {
-    type EKey = <Self as base::Algo>::Key;
+    type EKey;
     type Input: ?Sized;
     type Error;
     fn encrypt(&self, key: &Self::EKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error>;
@@ -27,7 +27,7 @@ pub trait CanDecrypt: Algo
 where
     <Self as base::Algo>::Key: Key,
 {
-    type DKey = <Self as base::Algo>::Key;
+    type DKey;
     type Input: ?Sized;
     type Error;
     fn decrypt(&self, key: &Self::DKey, data: &Self::Input) -> Result<Vec<u8>, Self::Error>;
@@ -3,13 +3,22 @@ use sodiumoxide::crypto::aead::xchacha20poly1305_ietf::{
     gen_key, gen_nonce, open, seal, Key as UnderlyingKey,
 };
 
-use crate::algo::{self as base, cipher::symmetric as symm};
+use crate::{
+    algo::{self as base, cipher::symmetric as symm, SecretBytes},
+    key_rotation::PersistableKey,
+};
+use std::fmt;
 
 #[derive(Clone)]
 pub struct Key {
-    store: Vec<u8>,
+    store: SecretBytes,
     key: UnderlyingKey,
 }
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Key").field("store", &self.store).finish()
+    }
+}
 impl base::SafeGenerateKey for Key {
     type Settings = ();
     fn safe_generate(_: &Self::Settings) -> Self {
@@ -21,7 +30,7 @@ impl symm::Key for Key {}
 impl Key {
     pub fn new(key: UnderlyingKey) -> Self {
         Self {
-            store: key.as_ref().to_vec(),
+            store: key.as_ref().into(),
             key: key,
         }
     }
@@ -29,6 +38,14 @@ impl Key {
         &self.key
     }
 }
+impl PersistableKey for Key {
+    fn to_bytes(&self) -> SecretBytes {
+        self.store.clone()
+    }
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        UnderlyingKey::from_slice(bytes).map(Self::new)
+    }
+}
 
 pub struct EncryptArgs {
     pub plaintext: Vec<u8>,
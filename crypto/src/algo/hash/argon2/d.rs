@@ -3,34 +3,35 @@
 use argon2rs::{Argon2, Variant};
 use rand::{rngs::OsRng, RngCore};
 
-use crate::algo::{self as base, hash::symmetric as sym};
+use super::{Argon2Params, ParamsError};
+use crate::algo::{self as base, hash::symmetric as sym, SecretBytes};
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Key {
     /// 0 < len < 33
-    secret_key: Vec<u8>,
+    secret_key: SecretBytes,
 }
 impl base::SafeGenerateKey for Key {
     type Settings = ();
     fn safe_generate(_: &Self::Settings) -> Self {
         let mut generated_secret = vec![0; Algo::SECRET_LEN as usize];
         OsRng.fill_bytes(generated_secret.as_mut_slice());
-        Key::new(generated_secret)
+        Key::new(generated_secret.into())
     }
 }
 impl sym::Key for Key {}
 impl Key {
-    pub fn new(secret: Vec<u8>) -> Self {
+    pub fn new(secret: SecretBytes) -> Self {
         Self { secret_key: secret }
     }
 }
 
 // TODO zero copy
 pub struct SigningData {
-    msg: Vec<u8>,
+    msg: SecretBytes,
     salt: [u8; Algo::SALT_LEN as usize],
-    /// 4 <= hash_len < 2^32
-    hash_len: u32,
+    /// 4 <= hash_len < 2^32, or [`None`] for the [`Argon2Params::hash_len`] of the [`Algo`].
+    hash_len: Option<u32>,
 }
 impl SigningData {
     pub fn new(
@@ -43,12 +44,11 @@ impl SigningData {
             OsRng.fill_bytes(&mut generated_salt);
             generated_salt
         });
-        let hash_len = hash_len.unwrap_or(32u32);
-        if hash_len < 4 {
+        if hash_len.map_or(false, |len| len < 4) {
             return Err(());
         }
         Ok(Self {
-            msg: msg,
+            msg: msg.into(),
             salt: salt,
             hash_len: hash_len,
         })
@@ -62,46 +62,57 @@ impl SigningData {
 }
 
 const SECRET_LEN: u8 = 32;
-pub struct Algo(Argon2, Option<Vec<u8>>);
+pub struct Algo(Argon2, Argon2Params);
 impl Algo {
     pub const SALT_LEN: u8 = 16;
     pub const SECRET_LEN: u8 = SECRET_LEN;
     pub const HASH_LEN: u8 = 32;
+    /// Hashes with the parameters, if Argon2 can run with them. Prefer this over
+    /// [`new`](base::Algo::new) for parameters that were not validated, such as those stored
+    /// alongside a hash.
+    pub fn with_params(params: Argon2Params) -> Result<Self, ParamsError> {
+        params.validate()?;
+        let argon2 = Argon2::new(
+            params.iterations,
+            params.parallelism,
+            params.mem_kib,
+            Variant::Argon2d,
+        )
+        .expect("Parameters checked to match what argon2rs accepts.");
+        Ok(Self(argon2, params))
+    }
+    /// The parameters hashes are made with.
+    pub fn params(&self) -> Argon2Params {
+        self.1
+    }
 }
 impl base::Algo for Algo {
     type Key = Key;
-    type ConstructionData = Option<Vec<u8>>;
+    type ConstructionData = Argon2Params;
     fn key_settings<'a>(&'a self) -> &() {
         &()
     }
-    fn new(secret: Self::ConstructionData) -> Self {
-        Self(Argon2::default(Variant::Argon2d), secret)
+    /// Panics if the parameters are invalid.
+    fn new(params: Self::ConstructionData) -> Self {
+        Self::with_params(params).expect("Valid argon2 parameters.")
     }
 }
 impl sym::Algo for Algo {
     type SigningInput = SigningData;
     fn sign(&self, msg: &Self::SigningInput, key: &Self::Key) -> Vec<u8> {
-        let mut buffer = vec![0; msg.hash_len as usize];
+        let mut buffer = vec![0; msg.hash_len.unwrap_or(self.1.hash_len) as usize];
         self.0.hash(
             buffer.as_mut_slice(),
-            msg.msg.as_slice(),
+            &msg.msg,
             &msg.salt[..],
-            key.secret_key.as_slice(),
+            &key.secret_key,
             &[],
         );
         buffer
     }
     type VerificationInput = SigningData;
     fn verify(&self, msg: &Self::VerificationInput, signature: &[u8], key: &Self::Key) -> bool {
-        let mut buffer = vec![0; msg.hash_len as usize];
-        self.0.hash(
-            buffer.as_mut_slice(),
-            msg.msg.as_slice(),
-            &msg.salt[..],
-            key.secret_key.as_slice(),
-            &[],
-        );
-        buffer.as_slice() == signature
+        SecretBytes::new(self.sign(msg, key)).ct_eq(signature)
     }
 }
 
@@ -1,3 +1,205 @@
 //! Argon2 hash functions. Currently only implements the `d` variation.
 
+use crate::algo::{hash::symmetric::Algo as _, SafeGenerateKey};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
 pub mod d;
+
+/// The most memory [`calibrate`] suggests using, in KiB, so that a slow machine is not told to
+/// spend all of its memory on a single hash.
+pub const MAX_CALIBRATED_MEM_KIB: u32 = 1024 * 1024;
+
+/// The cost parameters of Argon2. Changing any of them changes the hash, so they are stored
+/// alongside every hash made with them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Argon2Params {
+    /// The memory filled while hashing, in KiB. At least 8 per lane.
+    pub mem_kib: u32,
+    /// The number of passes over the memory. At least 1.
+    pub iterations: u32,
+    /// The number of lanes the memory is split into. Between 1 and 2^24 - 1.
+    pub parallelism: u32,
+    /// The length of the hash, in bytes. At least 4.
+    pub hash_len: u32,
+}
+impl Default for Argon2Params {
+    /// The parameters every hash was made with before they could be configured.
+    fn default() -> Self {
+        Self {
+            mem_kib: 4096,
+            iterations: 3,
+            parallelism: 1,
+            hash_len: 32,
+        }
+    }
+}
+impl Argon2Params {
+    /// Checks that Argon2 can run with the parameters.
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.iterations < 1 {
+            Err(ParamsError::TooFewIterations)
+        } else if self.parallelism < 1 || self.parallelism > 0x00ff_ffff {
+            Err(ParamsError::InvalidParallelism)
+        } else if u64::from(self.mem_kib) < 8 * u64::from(self.parallelism) {
+            Err(ParamsError::TooLittleMemory {
+                min_kib: 8 * u64::from(self.parallelism),
+            })
+        } else if self.hash_len < 4 {
+            Err(ParamsError::HashTooShort)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reasons Argon2 can't run with some [`Argon2Params`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamsError {
+    /// At least one pass is needed.
+    TooFewIterations,
+    /// The number of lanes must be between 1 and 2^24 - 1.
+    InvalidParallelism,
+    /// Every lane needs at least 8 KiB.
+    TooLittleMemory { min_kib: u64 },
+    /// The hash must be at least 4 bytes long.
+    HashTooShort,
+}
+
+/// Measures how long a single hash takes on this machine with the parameters.
+pub fn measure(params: Argon2Params) -> Result<Duration, ParamsError> {
+    let algo = d::Algo::with_params(params)?;
+    let key = d::Key::safe_generate(&());
+    let msg = d::SigningData::new_default_hash_len(b"calibration password".to_vec(), None);
+    let start = Instant::now();
+    algo.sign(&msg, &key);
+    Ok(start.elapsed())
+}
+
+/// Suggests parameters that take about `target` to hash a password on this machine, and never less
+/// than the [defaults](Argon2Params::default).
+///
+/// Memory is raised first, since it is what makes guessing on dedicated hardware expensive. Once
+/// it reaches [`MAX_CALIBRATED_MEM_KIB`], passes are added instead.
+pub fn calibrate(target: Duration) -> Argon2Params {
+    let mut params = Argon2Params::default();
+    let scale = |current: u32, elapsed: Duration| {
+        let factor = target.as_secs_f64() / elapsed.as_secs_f64().max(1e-6);
+        (f64::from(current) * factor).min(f64::from(u32::MAX)) as u32
+    };
+    let elapsed = measure(params).expect("Valid default parameters.");
+    params.mem_kib = scale(params.mem_kib, elapsed).clamp(params.mem_kib, MAX_CALIBRATED_MEM_KIB);
+    let elapsed = measure(params).expect("Memory to only grow.");
+    params.iterations = scale(params.iterations, elapsed).max(params.iterations);
+    params
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let valid = Argon2Params::default();
+        assert_eq!(valid.validate(), Ok(()));
+        let with = |f: fn(&mut Argon2Params)| {
+            let mut params = valid;
+            f(&mut params);
+            d::Algo::with_params(params).err()
+        };
+        assert_eq!(
+            with(|p| p.iterations = 0),
+            Some(ParamsError::TooFewIterations)
+        );
+        assert_eq!(
+            with(|p| p.parallelism = 0),
+            Some(ParamsError::InvalidParallelism)
+        );
+        assert_eq!(
+            with(|p| {
+                p.parallelism = 4;
+                p.mem_kib = 16;
+            }),
+            Some(ParamsError::TooLittleMemory { min_kib: 32 })
+        );
+        assert_eq!(with(|p| p.hash_len = 3), Some(ParamsError::HashTooShort));
+    }
+
+    #[test]
+    fn hashes_only_verify_with_the_params_they_were_made_with() {
+        let key = d::Key::safe_generate(&());
+        let msg = d::SigningData::new_default_hash_len(b"password".to_vec(), None);
+        let made_with = Argon2Params {
+            mem_kib: 64,
+            iterations: 2,
+            parallelism: 2,
+            hash_len: 16,
+        };
+        let hash = d::Algo::with_params(made_with).unwrap().sign(&msg, &key);
+        assert_eq!(hash.len(), 16);
+        assert!(d::Algo::with_params(made_with)
+            .unwrap()
+            .verify(&msg, &hash, &key));
+        let tuned = Argon2Params {
+            iterations: 3,
+            ..made_with
+        };
+        assert!(!d::Algo::with_params(tuned)
+            .unwrap()
+            .verify(&msg, &hash, &key));
+    }
+
+    #[test]
+    fn calibration_never_goes_below_the_defaults() {
+        let params = calibrate(Duration::from_millis(1));
+        let defaults = Argon2Params::default();
+        assert_eq!(params.validate(), Ok(()));
+        assert!(params.mem_kib >= defaults.mem_kib);
+        assert!(params.iterations >= defaults.iterations);
+        assert!(measure(params).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod prop_test {
+    use super::*;
+    use crate::prop_seed::run;
+    use proptest::prelude::*;
+    use std::convert::TryInto;
+
+    /// As cheap as Argon2 allows, since only whether hashes match is under test.
+    const FAST: Argon2Params = Argon2Params {
+        mem_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+        hash_len: 16,
+    };
+    const MEGABYTE: usize = 1 << 20;
+
+    /// Arbitrary unicode, along with the empty password and passwords of a megabyte.
+    fn password() -> impl Strategy<Value = String> {
+        prop_oneof![
+            8 => any::<String>(),
+            1 => Just(String::new()),
+            1 => any::<char>().prop_map(|c| c.to_string().repeat(MEGABYTE / c.len_utf8())),
+        ]
+    }
+
+    #[test]
+    fn hashes_verify_only_with_the_password_they_were_made_from() {
+        let algo = d::Algo::with_params(FAST).unwrap();
+        let key = d::Key::safe_generate(&());
+        let data = |password: &str, salt| {
+            d::SigningData::new_default_hash_len(password.as_bytes().to_vec(), salt)
+        };
+        run(32, (password(), password()), |(password, other)| {
+            let made = data(&password, None);
+            let hash = algo.sign(&made, &key);
+            let salt = made.salt().try_into().unwrap();
+            prop_assert!(algo.verify(&data(&password, Some(salt)), &hash, &key));
+            let guessed = algo.verify(&data(&other, Some(salt)), &hash, &key);
+            prop_assert_eq!(guessed, password == other);
+            Ok(())
+        });
+    }
+}
@@ -1,6 +1,6 @@
 //! Bcrypt implementation.
 
-use crate::algo::{self as base, hash::symmetric as sym};
+use crate::algo::{self as base, ct_eq, hash::symmetric as sym};
 
 #[derive(Clone)]
 pub struct Key(u8);
@@ -61,6 +61,7 @@ impl Algo {
 }
 impl base::Algo for Algo {
     type Key = Key;
+    type ConstructionData = ();
     fn key_settings<'a>(&'a self) -> &<<Self as base::Algo>::Key as base::Key>::Settings {
         &(bcrypt::DEFAULT_COST as u8)
     }
@@ -80,7 +81,7 @@ impl sym::Algo for Algo {
     }
     type VerificationInput = SigningData;
     fn verify(&self, msg: &Self::VerificationInput, signature: &[u8], key: &Self::Key) -> bool {
-        self.sign(msg, key).as_slice() == signature
+        ct_eq(&self.sign(msg, key), signature)
     }
 }
 
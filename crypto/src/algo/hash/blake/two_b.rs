@@ -1,7 +1,10 @@
 //! Blake2b implementation.
 
-use crate::algo::{self as base, hash::symmetric as sym};
-use blake2_rfc::blake2b::blake2b;
+use crate::algo::{
+    self as base,
+    hash::symmetric::{self as sym, StreamingAlgo as _},
+};
+use blake2_rfc::blake2b::{blake2b, Blake2b};
 use rand::{rngs::OsRng, RngCore};
 use std::ops::Deref;
 
@@ -45,14 +48,30 @@ impl base::Algo for Algo {
 impl sym::Algo for Algo {
     type SigningInput = [u8];
     fn sign(&self, msg: &Self::SigningInput, key: &Self::Key) -> Vec<u8> {
-        let key = &key;
-        blake2b(key.hash_len(), &key, msg).as_bytes().to_vec()
+        self.sign_streamed(msg, key)
     }
     type VerificationInput = [u8];
     fn verify(&self, msg: &Self::VerificationInput, signature: &[u8], key: &Self::Key) -> bool {
         blake2b(key.hash_len(), &key, msg) == *signature
     }
 }
+impl sym::StreamingAlgo for Algo {
+    type Signer = Signer;
+    fn signer(&self, key: &Self::Key) -> Self::Signer {
+        Signer(Blake2b::with_key(key.hash_len(), key))
+    }
+}
+
+/// An incremental Blake2b hash.
+pub struct Signer(Blake2b);
+impl sym::Signer for Signer {
+    fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk)
+    }
+    fn finalize(self) -> Vec<u8> {
+        self.0.finalize().as_bytes().to_vec()
+    }
+}
 
 impl AsRef<Key> for &Key {
     fn as_ref(&self) -> &Key {
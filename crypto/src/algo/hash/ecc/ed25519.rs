@@ -2,11 +2,13 @@
 
 use sodiumoxide::crypto::sign::ed25519::{
     gen_keypair, keypair_from_seed, sign_detached as ed25519_sign,
-    verify_detached as ed25519_verify, PublicKey, SecretKey, Seed, Signature,
+    verify_detached as ed25519_verify, SecretKey, Signature,
 };
+pub use sodiumoxide::crypto::sign::ed25519::{PublicKey, Seed};
 
 use crate::algo as base;
 use base::hash::asymmetric as asymm;
+use std::fmt;
 
 pub struct KeyPair {
     private: Option<SecretKey>,
@@ -20,6 +22,14 @@ impl KeyPair {
         }
     }
 }
+impl fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("private", &self.private.as_ref().map(|_| "<redacted>"))
+            .field("public", &self.public)
+            .finish()
+    }
+}
 impl Clone for KeyPair {
     fn clone(&self) -> Self {
         // source should be valid if KeyPair had been successfully created, especially since it
@@ -59,6 +69,7 @@ pub enum AlgoError {
 pub struct Algo;
 impl base::Algo for Algo {
     type Key = KeyPair;
+    type ConstructionData = ();
     fn key_settings<'a>(&'a self) -> &'a <<Self as base::Algo>::Key as base::Key>::Settings {
         &None
     }
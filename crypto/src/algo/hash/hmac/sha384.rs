@@ -1,4 +1,7 @@
-use crate::algo::{self as base, hash::symmetric as sym};
+use crate::algo::{
+    self as base,
+    hash::symmetric::{self as sym, StreamingAlgo as _},
+};
 use rand::{rngs::OsRng, RngCore};
 use ring::{digest, hmac};
 use std::{ops::Deref, sync::Arc};
@@ -40,15 +43,31 @@ impl base::Algo for Algo {
 impl sym::Algo for Algo {
     type SigningInput = [u8];
     fn sign(&self, input: &Self::SigningInput, key: &Self::Key) -> Vec<u8> {
-        let key = &key;
-        hmac::sign(&key, input).as_ref().to_vec()
+        self.sign_streamed(input, key)
     }
     type VerificationInput = [u8];
-    /// TODO ensure constant time
+    /// Compares the tags in constant time.
     fn verify(&self, input: &Self::VerificationInput, signature: &[u8], key: &Self::Key) -> bool {
         hmac::verify_with_own_key(&key, input, signature).is_ok()
     }
 }
+impl sym::StreamingAlgo for Algo {
+    type Signer = Signer;
+    fn signer(&self, key: &Self::Key) -> Self::Signer {
+        Signer(hmac::SigningContext::with_key(key))
+    }
+}
+
+/// An incremental HMAC-SHA384.
+pub struct Signer(hmac::SigningContext);
+impl sym::Signer for Signer {
+    fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk)
+    }
+    fn finalize(self) -> Vec<u8> {
+        self.0.sign().as_ref().to_vec()
+    }
+}
 
 impl AsRef<Key> for &Key {
     fn as_ref(&self) -> &Key {
@@ -83,6 +83,7 @@ impl From<ErrorStack> for AlgoError {
 pub struct Algo;
 impl base::Algo for Algo {
     type Key = KeyPair;
+    type ConstructionData = ();
     fn key_settings<'a>(&'a self) -> &'a <<Self as base::Algo>::Key as base::Key>::Settings {
         &()
     }
@@ -10,3 +10,79 @@ where
     type VerificationInput: ?Sized;
     fn verify(&self, input: &Self::VerificationInput, signature: &[u8], key: &Self::Key) -> bool;
 }
+
+/// A hash being computed over a message fed to it in chunks.
+pub trait Signer {
+    /// Feeds the next chunk of the message.
+    fn update(&mut self, chunk: &[u8]);
+    /// The hash of every chunk fed so far.
+    fn finalize(self) -> Vec<u8>;
+}
+
+/// A hash over plain bytes, which can be computed without holding the whole message in memory.
+///
+/// Password hashes such as Argon2 and bcrypt mix the salt and cost into the whole input at once,
+/// so they only have the one-shot [`Algo::sign`].
+pub trait StreamingAlgo: Algo<SigningInput = [u8]>
+where
+    <Self as base::Algo>::Key: Key,
+{
+    type Signer: Signer;
+    /// Starts hashing a message with the key.
+    fn signer(&self, key: &Self::Key) -> Self::Signer;
+    /// Hashes the whole message at once, which is what [`Algo::sign`] does for streaming hashes.
+    fn sign_streamed(&self, msg: &[u8], key: &Self::Key) -> Vec<u8> {
+        let mut signer = self.signer(key);
+        signer.update(msg);
+        signer.finalize()
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use crate::algo::{
+        hash::{blake::two_b, hmac::sha384},
+        Algo as _, SafeGenerateKey,
+    };
+    use rand::{rngs::OsRng, Rng, RngCore};
+
+    /// Checks that feeding random messages in chunks split at random points gives the same hash
+    /// as signing them at once.
+    fn chunked_matches_one_shot<A: StreamingAlgo>(algo: &A, key: &A::Key)
+    where
+        A::Key: Key,
+    {
+        for _ in 0..64 {
+            let mut msg = vec![0; OsRng.gen_range(0, 4096)];
+            OsRng.fill_bytes(&mut msg);
+            let mut splits: Vec<usize> = (0..OsRng.gen_range(0, 8))
+                .map(|_| OsRng.gen_range(0, msg.len() + 1))
+                .collect();
+            splits.sort_unstable();
+            let mut signer = algo.signer(key);
+            let mut start = 0;
+            for end in splits.into_iter().chain(Some(msg.len())) {
+                signer.update(&msg[start..end]);
+                start = end;
+            }
+            assert_eq!(signer.finalize(), algo.sign(&msg, key));
+        }
+    }
+
+    #[test]
+    fn blake2b_chunked_matches_one_shot() {
+        let algo = two_b::Algo::new(32);
+        chunked_matches_one_shot(&algo, &two_b::Key::safe_generate(&32));
+        chunked_matches_one_shot(&algo, &two_b::Key::new(vec![], 8));
+    }
+
+    #[test]
+    fn hmac_sha384_chunked_matches_one_shot() {
+        let key = sha384::Key::safe_generate(&());
+        chunked_matches_one_shot(&sha384::Algo::new(()), &key);
+        let msg = b"The quick brown fox jumps over the lazy dog";
+        let expected = ring::hmac::sign(&key, msg);
+        assert_eq!(sha384::Algo.sign(msg, &key), expected.as_ref());
+    }
+}
@@ -3,6 +3,10 @@
 pub mod cipher;
 pub mod hash;
 pub mod key_deriv;
+mod secret;
+pub mod sign;
+
+pub use secret::{ct_eq, SecretBytes};
 
 /// A trait implemented by all cryptographic algorithm keys. Allows for keys to be generated
 /// provided an instance of the algorithm exists.
@@ -27,7 +31,7 @@ pub trait SafeGenerateKey {
 }
 impl<S, K: SafeGenerateKey<Settings = S>> Key for K {
     type Settings = S;
-    type Error = !;
+    type Error = std::convert::Infallible;
     fn generate(settings: &<Self as SafeGenerateKey>::Settings) -> Result<Self, Self::Error> {
         Ok(Self::safe_generate(settings))
     }
@@ -37,7 +41,8 @@ impl<S, K: SafeGenerateKey<Settings = S>> Key for K {
 pub trait Algo {
     /// The [`Key`] associated with the [`Algo`].
     type Key: Key + Clone + Send + Sync;
-    type ConstructionData = ();
+    /// What is needed to construct the [`Algo`], such as its parameters.
+    type ConstructionData;
     /// Fetch the settings for key generation from the [`Algo`].
     fn key_settings<'a>(&'a self) -> &'a <<Self as Algo>::Key as Key>::Settings;
     fn new(data: Self::ConstructionData) -> Self;
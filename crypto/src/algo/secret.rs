@@ -0,0 +1,93 @@
+//! Bytes that are secret, such as keys and password hashes, and must neither leak through timing
+//! nor linger in memory.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::Deref};
+use zeroize::Zeroize;
+
+/// Compares two byte slices in time that depends only on their lengths, not their contents.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    ring::constant_time::verify_slices_are_equal(a, b).is_ok()
+}
+
+/// Secret bytes, which are compared in constant time, wiped when dropped, and never printed.
+/// Serialized as the bytes themselves.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretBytes(Vec<u8>);
+impl SecretBytes {
+    /// Takes ownership of the bytes, so that no copy outlives the wrapper.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+    /// Compares against other bytes in constant time.
+    pub fn ct_eq(&self, other: &[u8]) -> bool {
+        ct_eq(&self.0, other)
+    }
+}
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_vec())
+    }
+}
+impl Deref for SecretBytes {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl AsRef<[u8]> for SecretBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(&other.0)
+    }
+}
+impl PartialEq<[u8]> for SecretBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.ct_eq(other)
+    }
+}
+impl Eq for SecretBytes {}
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBytes(<{} bytes redacted>)", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+
+    #[test]
+    fn compares_by_contents() {
+        let secret = SecretBytes::new(vec![1, 2, 3]);
+        assert_eq!(secret, SecretBytes::from(&[1, 2, 3][..]));
+        assert_ne!(secret, SecretBytes::new(vec![1, 2, 4]));
+        assert_ne!(secret, SecretBytes::new(vec![1, 2]));
+        assert!(secret.ct_eq(&[1, 2, 3]));
+        assert!(!secret.ct_eq(&[]));
+    }
+
+    #[test]
+    fn debug_output_is_redacted() {
+        let secret = SecretBytes::new(vec![0xab; 32]);
+        let debug = format!("{:?}", secret);
+        assert_eq!(debug, "SecretBytes(<32 bytes redacted>)");
+        assert!(!debug.contains("171"));
+        assert!(!debug.to_lowercase().contains("ab"));
+    }
+}
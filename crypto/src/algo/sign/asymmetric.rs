@@ -0,0 +1,10 @@
+//! Signatures made with a private key and verified with the matching public key, so that the
+//! public key can be handed out to anyone needing to verify them.
+//!
+//! The algorithms themselves live with the other asymmetric algorithms in
+//! [`hash`](crate::algo::hash); this gathers the ones suited to signing tokens.
+
+pub use crate::algo::hash::{
+    asymmetric::{Algo, KeyPair},
+    ecc::ed25519,
+};
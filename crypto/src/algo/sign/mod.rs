@@ -0,0 +1,3 @@
+//! Signature algorithms, grouped by whether verifying needs the secret used to sign.
+
+pub mod asymmetric;
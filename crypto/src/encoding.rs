@@ -1,24 +1,327 @@
-//! Re-export version of a base64 encoding used throughout the library.
+//! Text encodings for binary data such as keys, hashes, and tokens.
+//!
+//! Decoding is strict, so that every piece of text decodes from exactly one encoding of the data,
+//! and errors point at the first byte of the text that is wrong.
 
-pub(crate) mod base64 {
-    pub use base64::DecodeError;
+/// Errors from decoding text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The byte at the offset is not part of the alphabet, or it is the last symbol and has bits
+    /// set that the data does not fill.
+    InvalidByte { offset: usize, byte: u8 },
+    /// The text ends partway through a group of symbols that starts at the offset.
+    InvalidLength { offset: usize },
+    /// The padding starting at the offset is misplaced, malformed, or missing.
+    InvalidPadding { offset: usize },
+}
+impl DecodeError {
+    /// The offset, in bytes, of the part of the text that could not be decoded.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Self::InvalidByte { offset, .. }
+            | Self::InvalidLength { offset }
+            | Self::InvalidPadding { offset } => offset,
+        }
+    }
+}
+
+/// A way of writing binary data as text.
+pub trait Encoding {
+    /// Encodes the data.
+    fn encode(data: &[u8]) -> String;
+    /// Decodes text, given as bytes, back into the data.
+    fn decode_bytes(text: &[u8]) -> Result<Vec<u8>, DecodeError>;
+    /// Decodes text back into the data.
+    fn decode(text: &str) -> Result<Vec<u8>, DecodeError> {
+        Self::decode_bytes(text.as_bytes())
+    }
+}
+
+/// Standard base64 as in RFC 4648, with padding.
+pub struct Base64;
+/// Standard base64 without padding, which PASETO tokens were packed with.
+pub struct Base64NoPad;
+/// URL and filename safe base64 as in RFC 4648, without padding.
+pub struct Base64UrlNoPad;
+/// Hex with lowercase digits. Decoding accepts either case.
+pub struct Hex;
+
+impl Encoding for Base64 {
+    fn encode(data: &[u8]) -> String {
+        base64::encode_config(data, base64::STANDARD)
+    }
+    fn decode_bytes(text: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        decode_base64(text, STANDARD_ALPHABET, true)
+    }
+}
+impl Encoding for Base64NoPad {
+    fn encode(data: &[u8]) -> String {
+        base64::encode_config(data, base64::STANDARD_NO_PAD)
+    }
+    fn decode_bytes(text: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        decode_base64(text, STANDARD_ALPHABET, false)
+    }
+}
+impl Encoding for Base64UrlNoPad {
+    fn encode(data: &[u8]) -> String {
+        base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+    }
+    fn decode_bytes(text: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        decode_base64(text, URL_SAFE_ALPHABET, false)
+    }
+}
+impl Encoding for Hex {
+    fn encode(data: &[u8]) -> String {
+        hex::encode(data)
+    }
+    fn decode_bytes(text: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        if text.len() % 2 == 1 {
+            return Err(DecodeError::InvalidLength {
+                offset: text.len() - 1,
+            });
+        }
+        let nibble = |offset: usize| {
+            let byte = text[offset];
+            (byte as char)
+                .to_digit(16)
+                .map(|digit| digit as u8)
+                .ok_or(DecodeError::InvalidByte { offset, byte })
+        };
+        (0..text.len())
+            .step_by(2)
+            .map(|i| Ok(nibble(i)? << 4 | nibble(i + 1)?))
+            .collect()
+    }
+}
+
+const STANDARD_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const PAD: u8 = b'=';
 
-    fn div_and_round_up(dividend: usize, divisor: usize) -> usize {
-        (dividend + (divisor - 1)) / divisor
+/// Decodes base64 in the alphabet, requiring padding to a multiple of four symbols if `padded`
+/// and rejecting it otherwise.
+fn decode_base64(text: &[u8], alphabet: &[u8; 64], padded: bool) -> Result<Vec<u8>, DecodeError> {
+    let data_len = text.iter().position(|&b| b == PAD).unwrap_or(text.len());
+    if data_len % 4 == 1 {
+        return Err(DecodeError::InvalidLength {
+            offset: data_len - 1,
+        });
+    }
+    if padded {
+        // Exactly enough padding must follow the symbols to end on a whole group.
+        let expected_len = (data_len + 3) / 4 * 4;
+        let padding_ok = text.len() == expected_len && text[data_len..].iter().all(|&b| b == PAD);
+        if !padding_ok {
+            return Err(DecodeError::InvalidPadding { offset: data_len });
+        }
+    } else if data_len != text.len() {
+        return Err(DecodeError::InvalidPadding { offset: data_len });
     }
 
-    pub fn encode_no_padding(data: &[u8]) -> Vec<u8> {
-        let mut encoded = vec![0; div_and_round_up(data.len(), 3) * 4];
-        let bytes_written =
-            base64::encode_config_slice(data, base64::STANDARD_NO_PAD, &mut encoded);
-        encoded.resize(bytes_written, 0);
-        encoded
+    let symbols = &text[..data_len];
+    let mut decoded = Vec::with_capacity(data_len * 3 / 4);
+    let mut acc: u32 = 0;
+    for (offset, &byte) in symbols.iter().enumerate() {
+        let value = alphabet
+            .iter()
+            .position(|&symbol| symbol == byte)
+            .ok_or(DecodeError::InvalidByte { offset, byte })?;
+        acc = acc << 6 | value as u32;
+        if offset % 4 == 3 {
+            decoded.extend_from_slice(&acc.to_be_bytes()[1..]);
+            acc = 0;
+        }
+    }
+    // A partial group of two or three symbols holds one or two bytes, and the bits of the last
+    // symbol past them must be zero.
+    let (bytes, unused_bits) = match data_len % 4 {
+        2 => (1, 4),
+        3 => (2, 2),
+        _ => (0, 0),
+    };
+    if acc & ((1 << unused_bits) - 1) != 0 {
+        return Err(DecodeError::InvalidByte {
+            offset: data_len - 1,
+            byte: text[data_len - 1],
+        });
     }
-    pub fn decode_no_padding(data: &[u8]) -> Result<Vec<u8>, base64::DecodeError> {
-        let mut decoded = vec![0; div_and_round_up(data.len(), 4) * 3];
-        let bytes_written =
-            base64::decode_config_slice(data, base64::STANDARD_NO_PAD, &mut decoded)?;
-        decoded.resize(bytes_written, 0);
-        Ok(decoded)
+    let acc = acc >> unused_bits;
+    decoded.extend_from_slice(&acc.to_be_bytes()[4 - bytes..]);
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use rand::{rngs::OsRng, Rng, RngCore};
+
+    fn random_data() -> impl Iterator<Item = Vec<u8>> {
+        (0..256).map(|i| {
+            let mut data = vec![0; i % 64 + OsRng.gen_range(0, 4)];
+            OsRng.fill_bytes(&mut data);
+            data
+        })
+    }
+
+    fn round_trips<E: Encoding>() {
+        for data in random_data() {
+            let encoded = E::encode(&data);
+            assert_eq!(E::decode(&encoded), Ok(data), "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn decoding_undoes_encoding() {
+        round_trips::<Base64>();
+        round_trips::<Base64NoPad>();
+        round_trips::<Base64UrlNoPad>();
+        round_trips::<Hex>();
+    }
+
+    #[test]
+    fn matches_the_rfc_4648_vectors() {
+        let vectors = [
+            ("", "", ""),
+            ("f", "Zg==", "66"),
+            ("fo", "Zm8=", "666f"),
+            ("foo", "Zm9v", "666f6f"),
+            ("foob", "Zm9vYg==", "666f6f62"),
+            ("fooba", "Zm9vYmE=", "666f6f6261"),
+            ("foobar", "Zm9vYmFy", "666f6f626172"),
+        ];
+        for (data, base64, hex) in vectors.iter() {
+            assert_eq!(Base64::encode(data.as_bytes()), *base64);
+            assert_eq!(
+                Base64NoPad::encode(data.as_bytes()),
+                base64.trim_end_matches('=')
+            );
+            assert_eq!(Hex::encode(data.as_bytes()), *hex);
+            assert_eq!(Hex::decode(&hex.to_uppercase()).unwrap(), data.as_bytes());
+        }
+        assert_eq!(Base64UrlNoPad::encode(&[0xfb, 0xff]), "-_8");
+        assert_eq!(Base64::encode(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn malformed_base64_points_at_the_problem() {
+        use DecodeError::*;
+        let cases: &[(&str, bool, DecodeError)] = &[
+            (
+                "Zm9v!mFy",
+                true,
+                InvalidByte {
+                    offset: 4,
+                    byte: b'!',
+                },
+            ),
+            (
+                "Zm9v-mFy",
+                true,
+                InvalidByte {
+                    offset: 4,
+                    byte: b'-',
+                },
+            ),
+            ("Zm9vYmF", true, InvalidPadding { offset: 7 }),
+            ("Zm9vYg=", true, InvalidPadding { offset: 6 }),
+            ("Zm9vYg===", true, InvalidPadding { offset: 6 }),
+            ("Zm=vYg==", true, InvalidPadding { offset: 2 }),
+            ("Zg=A", true, InvalidPadding { offset: 2 }),
+            ("Zm9vY", true, InvalidLength { offset: 4 }),
+            (
+                "Zm9vYh==",
+                true,
+                InvalidByte {
+                    offset: 5,
+                    byte: b'h',
+                },
+            ),
+            ("Zm9vYg==", false, InvalidPadding { offset: 6 }),
+            (
+                "Zm9vYg/",
+                false,
+                InvalidByte {
+                    offset: 6,
+                    byte: b'/',
+                },
+            ),
+        ];
+        for (text, padded, expected) in cases {
+            let res = if *padded {
+                Base64::decode(text)
+            } else {
+                Base64UrlNoPad::decode(text)
+            };
+            assert_eq!(res, Err(*expected), "{}", text);
+        }
+        assert_eq!(
+            Base64NoPad::decode("Zm9vYmE="),
+            Err(InvalidPadding { offset: 7 })
+        );
+    }
+
+    #[test]
+    fn malformed_hex_points_at_the_problem() {
+        use DecodeError::*;
+        assert_eq!(Hex::decode("abc"), Err(InvalidLength { offset: 2 }));
+        assert_eq!(
+            Hex::decode("00g0"),
+            Err(InvalidByte {
+                offset: 2,
+                byte: b'g'
+            })
+        );
+        for data in random_data().filter(|data| !data.is_empty()) {
+            let mut encoded = Hex::encode(&data).into_bytes();
+            let offset = OsRng.gen_range(0, encoded.len());
+            encoded[offset] = b'x';
+            let err = Hex::decode_bytes(&encoded).unwrap_err();
+            assert_eq!(err.offset(), offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod prop_test {
+    use super::*;
+    use crate::prop_seed::run;
+    use proptest::prelude::*;
+
+    const CASES: u32 = 256;
+
+    fn round_trips<E: Encoding>() {
+        run(CASES, prop::collection::vec(any::<u8>(), 0..512), |data| {
+            let encoded = E::encode(&data);
+            prop_assert_eq!(E::decode(&encoded), Ok(data), "{}", encoded);
+            Ok(())
+        });
+    }
+
+    /// Strict decoding means that text only decodes if it is the one encoding of its data.
+    fn only_decodes_canonical_text<E: Encoding>(alphabet: &'static str) {
+        run(CASES, alphabet, |text| {
+            if let Ok(data) = E::decode(&text) {
+                prop_assert_eq!(E::encode(&data), text);
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn arbitrary_bytes_round_trip() {
+        round_trips::<Base64>();
+        round_trips::<Base64NoPad>();
+        round_trips::<Base64UrlNoPad>();
+        round_trips::<Hex>();
+    }
+
+    #[test]
+    fn decoded_text_encodes_back_to_itself() {
+        only_decodes_canonical_text::<Base64>("[A-Za-z0-9+/=]{0,16}");
+        only_decodes_canonical_text::<Base64NoPad>("[A-Za-z0-9+/=]{0,16}");
+        only_decodes_canonical_text::<Base64UrlNoPad>("[A-Za-z0-9_=-]{0,16}");
+        only_decodes_canonical_text::<Hex>("[0-9a-f]{0,16}");
     }
 }
@@ -1,22 +1,93 @@
 //! Structs and methods used for storing keys and auto-cycling keys based on a periodic functions.
+//! Keys can also be cycled manually through [`Generational::rotate_now`].
 //!
-//! TOOD: Make sure to allow for a signal to be sent externally from the local machine to manually
-//! cycle keys.
+//! Keys are either rotated by a background thread, or lazily by whoever accesses them once they
+//! are due, as chosen through [`RotationMode`]. Both decide when to rotate with
+//! [`time_until_rotation`], reading the time from a [`Clock`].
 
 use crate::algo::{Algo, SafeGenerateKey};
+use arc_swap::ArcSwap;
 use std::{
-    sync::{
-        mpsc::{channel, RecvTimeoutError, Sender},
-        Arc, RwLock,
-    },
+    sync::{Arc, Condvar, Mutex},
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 use tap::*;
 
+mod persistence;
+pub use persistence::{
+    FilePersistence, KeyPersistence, PersistableKey, PersistedKey, PersistedKeys, PersistenceError,
+};
+
+/// A source of the current time, so that rotations can be tested without waiting.
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// What triggers scheduled rotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationMode {
+    /// A background thread wakes up and rotates the keys whenever they are due.
+    Threaded,
+    /// The first access to the keys after they are due rotates them. No thread is started.
+    Lazy,
+}
+
+/// How often keys are rotated, and for how long they are kept afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationConfig {
+    /// Time between rotations.
+    pub period: Duration,
+    /// Number of generations of keys kept, including the current one, so anything signed or
+    /// encrypted with a key can be verified for this many periods. At least one is always kept.
+    pub generations_kept: usize,
+    /// What triggers the rotations.
+    pub mode: RotationMode,
+}
+impl RotationConfig {
+    /// Rotates every `period` from a background thread, keeping the current and previous keys.
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            generations_kept: 2,
+            mode: RotationMode::Threaded,
+        }
+    }
+    /// How long a key can be used for verification after being generated.
+    pub fn lifetime(&self) -> Duration {
+        self.period * self.generations_kept.max(1) as u32
+    }
+}
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(2 * 60 * 60))
+    }
+}
+
+/// How long until a key generated at `generated_at` is due to be rotated, as of `now`. Zero if it
+/// is already due. Keys that claim to be from the future are treated as brand new.
+pub fn time_until_rotation(
+    generated_at: SystemTime,
+    now: SystemTime,
+    period: Duration,
+) -> Duration {
+    let age = now.duration_since(generated_at).unwrap_or_default();
+    period.checked_sub(age).unwrap_or_default()
+}
+
 /// A stable key store. Not very interesting.
 mod stable {
     use crate::algo::Algo;
+    use std::fmt;
 
     /// Maintains an algorithm and its key.
     pub struct KeyStore<A: Algo>(
@@ -39,56 +110,280 @@ mod stable {
             &self.0
         }
     }
+    impl<A: Algo> fmt::Debug for KeyStore<A>
+    where
+        A::Key: fmt::Debug,
+    {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_tuple("KeyStore").field(&self.1).finish()
+        }
+    }
 }
 pub use stable::KeyStore as StableKeyStore;
 
 /// A rotating key store/one to be used with the [`KeyRotator`](crate::KeyRotator).
 mod rotating {
-    use crate::algo::{Algo, SafeGenerateKey};
-    use std::sync::Arc;
+    use super::{
+        persistence::{PersistableKey, PersistedKey, PersistedKeys},
+        time_until_rotation, Clock, RotationConfig, RotationMode,
+    };
+    use crate::algo::{sign::asymmetric::KeyPair, Algo, SafeGenerateKey};
+    use std::{
+        fmt,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    /// Called with the new key store after every rotation.
+    pub(super) type RotationHook<A> = Arc<dyn Fn(&KeyStore<A>) + Send + Sync>;
+
+    /// A single generation of key.
+    pub struct Generation<K> {
+        /// Identifies the generation. Each generation has an id one more than the one before it.
+        pub id: u64,
+        /// A pointer to the key.
+        pub key: Arc<K>,
+        /// When the key was generated.
+        pub generated_at: SystemTime,
+    }
+    impl<K: fmt::Debug> fmt::Debug for Generation<K> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_struct("Generation")
+                .field("id", &self.id)
+                .field("key", &self.key)
+                .field("generated_at", &self.generated_at)
+                .finish()
+        }
+    }
+    impl<K> Clone for Generation<K> {
+        fn clone(&self) -> Self {
+            Self {
+                id: self.id,
+                key: Arc::clone(&self.key),
+                generated_at: self.generated_at,
+            }
+        }
+    }
 
-    /// A Send/Sync key store that keeps the last two keys.
+    /// A Send/Sync key store that keeps the last few generations of keys.
     pub struct KeyStore<A: Algo> {
         /// A pointer to the algorithm.
         pub algo: Arc<A>,
-        /// A pointer to the previous key. TODO: Rename as `prev`.
-        pub last: Arc<A::Key>,
-        /// A pointer to the current key.
-        pub curr: Arc<A::Key>,
+        /// The generations of keys that are kept, newest first. Never empty.
+        generations: Vec<Generation<A::Key>>,
+        /// How the keys are rotated.
+        config: RotationConfig,
+        /// Where the time comes from.
+        clock: Arc<dyn Clock>,
+        /// Called after every rotation, if set.
+        on_rotation: Option<RotationHook<A>>,
+    }
+    impl<A: Algo> fmt::Debug for KeyStore<A> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let generations: Vec<_> = self
+                .generations
+                .iter()
+                .map(|g| (g.id, g.generated_at))
+                .collect();
+            f.debug_struct("KeyStore")
+                .field("generations", &generations)
+                .field("config", &self.config)
+                .finish()
+        }
     }
     impl<K: SafeGenerateKey + Clone + Send + Sync, A: Algo<Key = K>> KeyStore<A> {
-        /// Creates a new [`KeyStore`], generating the initial two keys.
-        pub(super) fn new(alg: A) -> Self {
+        /// Creates a new [`KeyStore`], generating the initial key.
+        pub(super) fn new(alg: A, config: RotationConfig, clock: Arc<dyn Clock>) -> Self {
             let key = Arc::new(A::Key::safe_generate(alg.key_settings()));
+            let generated_at = clock.now();
+            Self::restored(
+                alg,
+                vec![Generation {
+                    id: 0,
+                    key,
+                    generated_at,
+                }],
+                config,
+                clock,
+            )
+        }
+        /// Creates a [`KeyStore`] from generations restored from persistence, newest first.
+        pub(super) fn restored(
+            alg: A,
+            mut generations: Vec<Generation<K>>,
+            config: RotationConfig,
+            clock: Arc<dyn Clock>,
+        ) -> Self {
+            assert!(!generations.is_empty(), "A key store needs a key.");
+            let config = RotationConfig {
+                generations_kept: config.generations_kept.max(1),
+                ..config
+            };
+            generations.truncate(config.generations_kept);
             Self {
                 algo: Arc::new(alg),
-                last: Arc::clone(&key),
-                curr: key,
+                generations,
+                config,
+                clock,
+                on_rotation: None,
             }
         }
-        /// Undertake involution. AKA progress the current key to the last key and generate a new
-        /// key.
+        /// Sets the function to call after every rotation.
+        pub(super) fn with_rotation_hook(self, on_rotation: RotationHook<A>) -> Self {
+            Self {
+                on_rotation: Some(on_rotation),
+                ..self
+            }
+        }
+        /// Undertake involution. AKA generate a new key to replace the current one, dropping the
+        /// oldest generation if too many are kept.
         pub(super) fn involute(&self) -> Arc<Self> {
+            let newest = Generation {
+                id: self.curr_generation().id + 1,
+                key: Arc::new(A::Key::safe_generate(self.algo.key_settings())),
+                generated_at: self.clock.now(),
+            };
+            let generations = std::iter::once(newest)
+                .chain(self.generations.iter().cloned())
+                .take(self.config.generations_kept)
+                .collect();
             Arc::new(Self {
                 algo: Arc::clone(&self.algo),
-                last: Arc::clone(&self.curr),
-                curr: Arc::new(A::Key::safe_generate(self.algo.key_settings())),
+                generations,
+                config: self.config,
+                clock: Arc::clone(&self.clock),
+                on_rotation: self.on_rotation.clone(),
             })
         }
-        /// Attempt to use the current key, then the previous key. The function `attempt` takes in
-        /// a key, and an optional result, which is populated if the first attempt fails.
+        /// Attempt to use each generation of key, newest first, until one succeeds. The function
+        /// `attempt` takes in a key, and an optional result, which is populated with the error of
+        /// the previous attempt if there was one.
         pub fn attempt_with_retry<T, E, F>(&self, attempt: &mut F) -> Result<T, E>
         where
             F: FnMut(&K, Option<E>) -> Result<T, E>,
         {
-            attempt(&*self.curr, None).or_else(|e| attempt(&*self.last, Some(e)))
+            let mut err = None;
+            for generation in self.generations.iter() {
+                match attempt(&*generation.key, err.take()) {
+                    Ok(t) => return Ok(t),
+                    Err(e) => err = Some(e),
+                }
+            }
+            Err(err.expect("At least one generation of key."))
         }
     }
+    impl<A: Algo> KeyStore<A> {
+        /// The current key, which new things should be signed or encrypted with.
+        pub fn curr(&self) -> &A::Key {
+            &*self.curr_generation().key
+        }
+        /// The current generation of key.
+        pub fn curr_generation(&self) -> &Generation<A::Key> {
+            &self.generations[0]
+        }
+        /// Every generation of key kept, newest first.
+        pub fn generations(&self) -> &[Generation<A::Key>] {
+            self.generations.as_slice()
+        }
+        /// The kept generation with `id`, if any.
+        pub fn generation(&self, id: u64) -> Option<&Generation<A::Key>> {
+            self.generations.iter().find(|g| g.id == id)
+        }
+        /// How the keys are rotated.
+        pub fn config(&self) -> RotationConfig {
+            self.config
+        }
+        /// How long until the current key is due to be rotated, or zero if it already is.
+        pub fn time_until_rotation(&self) -> Duration {
+            let generated_at = self.curr_generation().generated_at;
+            time_until_rotation(generated_at, self.clock.now(), self.config.period)
+        }
+        /// Whether the keys should be rotated when accessed.
+        pub(super) fn rotates_lazily(&self) -> bool {
+            self.config.mode == RotationMode::Lazy
+        }
+        /// Calls the rotation hook, if any, with this key store.
+        pub(super) fn rotated(&self) {
+            if let Some(on_rotation) = self.on_rotation.as_ref() {
+                on_rotation(self);
+            }
+        }
+    }
+    impl<K: PersistableKey, A: Algo<Key = K>> KeyStore<A> {
+        /// The keys in the form they are persisted in.
+        pub fn persisted(&self) -> PersistedKeys {
+            PersistedKeys {
+                generations: self
+                    .generations
+                    .iter()
+                    .map(|g| PersistedKey::new(g.id, &*g.key, g.generated_at))
+                    .collect(),
+            }
+        }
+    }
+
+    /// The public half of a generation of key pairs.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PublicGeneration<P> {
+        /// The id of the generation.
+        pub id: u64,
+        /// The public key.
+        pub key: P,
+        /// When the key pair was generated.
+        pub generated_at: SystemTime,
+    }
+    impl<K: KeyPair, A: Algo<Key = K>> KeyStore<A>
+    where
+        K::Public: Clone,
+    {
+        /// The public halves of every kept generation of key pairs, newest first, so that they can
+        /// be handed out without exposing the private halves.
+        pub fn public_keys(&self) -> Vec<PublicGeneration<K::Public>> {
+            self.generations
+                .iter()
+                .map(|g| PublicGeneration {
+                    id: g.id,
+                    key: g.key.public_key().clone(),
+                    generated_at: g.generated_at,
+                })
+                .collect()
+        }
+    }
+}
+pub use rotating::{Generation, KeyStore as RotatingKeyStore, PublicGeneration};
+
+/// The current keys of a [`KeyRotator`]. Rotations swap in a new [`RotatingKeyStore`] atomically,
+/// so reading the keys never waits on a lock, even while they are being rotated.
+pub struct SwappableKeyStore<A: Algo> {
+    /// The current keys.
+    current: ArcSwap<RotatingKeyStore<A>>,
+    /// Held while rotating so that concurrent rotations build on each other instead of one being
+    /// lost.
+    rotation: Mutex<()>,
+}
+impl<A: Algo> SwappableKeyStore<A> {
+    /// Starts out with `key_store` as the current keys.
+    pub(super) fn new(key_store: RotatingKeyStore<A>) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(key_store),
+            rotation: Mutex::new(()),
+        }
+    }
+    /// Replaces the current keys with the next generation and reports the rotation. Must only be
+    /// called while holding the rotation lock.
+    fn swap_in_next(&self) -> Arc<RotatingKeyStore<A>>
+    where
+        A::Key: SafeGenerateKey,
+    {
+        let next = self.current.load().involute();
+        self.current.store(Arc::clone(&next));
+        next.rotated();
+        next
+    }
 }
-pub use rotating::KeyStore as RotatingKeyStore;
 
 /// A convenience
-pub type RotatingKeyFixture<A> = Arc<RwLock<Arc<RotatingKeyStore<A>>>>;
+pub type RotatingKeyFixture<A> = Arc<SwappableKeyStore<A>>;
 
 /// A trait for pointers to things that can be auto generated from itself, but only one copy should
 /// exist at a time.
@@ -100,6 +395,9 @@ pub trait Generational {
     type Datum;
     /// Swaps out the current for the next version.
     fn advance_generation(&self) -> Result<&Self, Self::Error>;
+    /// Swaps out the current for the next version immediately, such as after the current one is
+    /// compromised, returning the next version.
+    fn rotate_now(&self) -> Result<Self::Datum, Self::Error>;
     /// Gets the current version.
     fn get_store(&self) -> Result<Self::Datum, Self::Error>;
 }
@@ -110,16 +408,81 @@ impl<K: SafeGenerateKey + Clone + Send + Sync, A: Algo<Key = K> + Send + Sync +
     type Error = Arc<RotatingKeyStore<A>>;
     type Datum = Arc<RotatingKeyStore<A>>;
     fn advance_generation(&self) -> Result<&Self, Self::Error> {
-        let mut key_store = self
-            .write()
-            .map_err(|rwlg| Arc::clone(&*rwlg.into_inner()))?;
-        *key_store = key_store.involute();
-        Ok(self)
+        self.rotate_now().map(|_| self)
     }
+    fn rotate_now(&self) -> Result<Self::Datum, Self::Error> {
+        let _rotating = self.rotation.lock().map_err(|_| self.current.load_full())?;
+        // Still holding the lock so that concurrent rotations are reported in order.
+        Ok(self.swap_in_next())
+    }
+    /// Gets the current keys. When rotating lazily, keys that are due are rotated first by
+    /// whichever caller gets the rotation lock, while everyone else carries on with the current
+    /// keys.
     fn get_store(&self) -> Result<Self::Datum, Self::Error> {
-        self.read()
-            .map(|rwlg| Arc::clone(&*rwlg))
-            .map_err(|poisoned_rwlg| Arc::clone(&*poisoned_rwlg.into_inner()))
+        let key_store = self.current.load_full();
+        if !key_store.rotates_lazily() || key_store.time_until_rotation() > Duration::new(0, 0) {
+            return Ok(key_store);
+        }
+        match self.rotation.try_lock() {
+            Ok(_rotating) => {
+                // Someone else may have rotated between reading and locking.
+                let key_store = self.current.load_full();
+                if key_store.time_until_rotation() == Duration::new(0, 0) {
+                    Ok(self.swap_in_next())
+                } else {
+                    Ok(key_store)
+                }
+            }
+            Err(_) => Ok(key_store),
+        }
+    }
+}
+
+/// A signal shared between the owner of a background task and the task itself, telling the task to
+/// stop. Cloning it yields another handle to the same signal.
+#[derive(Clone, Default)]
+pub struct Shutdown(Arc<(Mutex<bool>, Condvar)>);
+impl Shutdown {
+    /// Creates a signal that has not been sent.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sends the signal, waking everything waiting on it.
+    pub fn signal(&self) {
+        let (signaled, cvar) = &*self.0;
+        *signaled.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        cvar.notify_all();
+    }
+    /// Checks if the signal has been sent.
+    pub fn is_signaled(&self) -> bool {
+        let (signaled, _) = &*self.0;
+        *signaled.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Blocks until the signal is sent.
+    pub fn wait(&self) {
+        let (signaled, cvar) = &*self.0;
+        let mut signaled = signaled.lock().unwrap_or_else(|e| e.into_inner());
+        while !*signaled {
+            signaled = cvar.wait(signaled).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+    /// Blocks until the signal is sent or the timeout elapses, returning whether the signal was
+    /// sent.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (signaled, cvar) = &*self.0;
+        let mut signaled = signaled.lock().unwrap_or_else(|e| e.into_inner());
+        while !*signaled {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            signaled = cvar
+                .wait_timeout(signaled, deadline - now)
+                .map(|(guard, _)| guard)
+                .unwrap_or_else(|e| e.into_inner().0);
+        }
+        *signaled
     }
 }
 
@@ -130,47 +493,75 @@ impl<K: SafeGenerateKey + Clone + Send + Sync, A: Algo<Key = K> + Send + Sync +
 pub struct KeyRotator<A: Algo> {
     /// Allows access to the key store.
     pub key_store: RotatingKeyFixture<A>,
+    /// Tells the thread doing the rotations to stop.
+    shutdown: Shutdown,
     /// Internal handle to the thread doing these rotations.
-    kill_handle: Option<(Sender<()>, thread::JoinHandle<()>)>,
+    join_handle: Option<thread::JoinHandle<()>>,
 }
 
 impl<K: SafeGenerateKey + Clone + Send + Sync, A: Algo<Key = K> + Send + Sync + 'static>
     KeyRotator<A>
 {
     /// Initializes the key rotation mechanism.
-    pub fn init(alg: A, period_between_rotation: Option<Duration>) -> Self {
-        let local_copy = Arc::new(RwLock::new(Arc::new(RotatingKeyStore::new(alg))));
+    pub fn init(alg: A, config: RotationConfig) -> Self {
+        Self::init_with_shutdown(alg, config, Shutdown::new())
+    }
+    /// Initializes the key rotation mechanism, stopping the rotations once `shutdown` is
+    /// signaled. [`cleanup`](KeyRotator::cleanup) must still be called afterwards.
+    pub fn init_with_shutdown(alg: A, config: RotationConfig, shutdown: Shutdown) -> Self {
+        Self::init_with_clock(alg, config, shutdown, Arc::new(SystemClock))
+    }
+    /// Initializes the key rotation mechanism like
+    /// [`init_with_shutdown`](KeyRotator::init_with_shutdown), reading the time from `clock`.
+    pub fn init_with_clock(
+        alg: A,
+        config: RotationConfig,
+        shutdown: Shutdown,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self::spawn(RotatingKeyStore::new(alg, config, clock), config, shutdown)
+    }
+    /// Starts the thread rotating the keys whenever the current key is older than the period,
+    /// unless they are rotated lazily. Rotations through [`Generational::rotate_now`] push back
+    /// the next scheduled one.
+    fn spawn(key_store: RotatingKeyStore<A>, config: RotationConfig, shutdown: Shutdown) -> Self {
+        let local_copy = Arc::new(SwappableKeyStore::new(key_store));
+        if config.mode == RotationMode::Lazy {
+            return Self {
+                key_store: local_copy,
+                shutdown,
+                join_handle: None,
+            };
+        }
         let remote_copy = Arc::clone(&local_copy);
 
-        let (tx, rx) = channel();
-        const TWO_HOURS_IN_SECONDS: u64 = 2 * 60 * 60;
-        let period_between_rotation =
-            period_between_rotation.unwrap_or(Duration::from_secs(TWO_HOURS_IN_SECONDS));
-
+        let remote_shutdown = shutdown.clone();
         let handle = thread::spawn(move || {
             let key_store_fixture = remote_copy;
             loop {
                 use log::info;
-                let deadline = Instant::now() + period_between_rotation;
-                if let Err(_) = key_store_fixture.advance_generation() {
-                    use log::error;
-                    // TODO recover or exit everything gracefully
-                    error!("Key rotation thread crashed.");
-                    panic!("Thread crashed!");
-                }
-                let now = Instant::now();
-                let duration_to_wait = if now < deadline {
-                    deadline - now
-                } else {
-                    Duration::new(0, 0)
+                let rotated = key_store_fixture.get_store().and_then(|key_store| {
+                    match key_store.time_until_rotation() {
+                        remaining if remaining > Duration::new(0, 0) => Ok(remaining),
+                        _ => key_store_fixture.rotate_now().map(|_| config.period),
+                    }
+                });
+                let duration_to_wait = match rotated {
+                    Ok(duration_to_wait) => duration_to_wait,
+                    Err(_) => {
+                        use log::error;
+                        // TODO recover or exit everything gracefully
+                        error!("Key rotation thread crashed.");
+                        panic!("Thread crashed!");
+                    }
                 };
                 info!(
                     "Scheduled key exchange for {:?} from now.",
                     duration_to_wait
                 );
-                match rx.recv_timeout(duration_to_wait) {
-                    Err(RecvTimeoutError::Disconnected) => break,
-                    _ => (), // continue if nothing
+                if remote_shutdown.wait_timeout(duration_to_wait) {
+                    info!("Key rotation stopping.");
+                    break;
                 }
             }
             ()
@@ -178,29 +569,81 @@ impl<K: SafeGenerateKey + Clone + Send + Sync, A: Algo<Key = K> + Send + Sync +
 
         Self {
             key_store: local_copy,
-            kill_handle: Some((tx, handle)),
+            shutdown,
+            join_handle: Some(handle),
         }
     }
     /// Cleans up the key rotation. If not called before drop, will cause a panic.
     pub fn cleanup(mut self) -> Result<(), Box<dyn std::any::Any + std::marker::Send + 'static>> {
-        if let Some((tx, join_handle)) = self.kill_handle.take() {
-            drop(tx);
+        if let Some(join_handle) = self.join_handle.take() {
+            self.shutdown.signal();
             join_handle.join()
                 .tap_ok(|_| log::info!("KeyRotator thread killed."))
         } else {
-            log::warn!("Attempted to clean up KeyRotator multiple times.");
+            // Lazy rotation has no thread to stop.
             Ok(())
         }
     }
 }
 
+impl<K, A> KeyRotator<A>
+where
+    K: SafeGenerateKey + PersistableKey + Clone + Send + Sync,
+    A: Algo<Key = K> + Send + Sync + 'static,
+{
+    /// Initializes the key rotation mechanism like
+    /// [`init_with_shutdown`](KeyRotator::init_with_shutdown), but keeps the keys in `persistence`
+    /// so that they survive restarts.
+    ///
+    /// Previously persisted keys are picked up where they left off as long as they are still in
+    /// use. Keys that are too old, or that have timestamps that don't make sense, are discarded
+    /// and fresh keys are generated instead.
+    pub fn init_with_persistence(
+        alg: A,
+        config: RotationConfig,
+        shutdown: Shutdown,
+        persistence: Box<dyn KeyPersistence>,
+    ) -> Self {
+        let restored = match persistence.load() {
+            Ok(Some(keys)) => persistence::restore(&keys, config, SystemTime::now())
+                .map_err(|e| log::warn!("Discarding persisted keys due to: {:?}.", e))
+                .ok(),
+            Ok(None) => {
+                log::info!("No persisted keys found.");
+                None
+            }
+            Err(e) => {
+                log::warn!("Could not load persisted keys due to: {:?}.", e);
+                None
+            }
+        };
+        let save: rotating::RotationHook<A> = Arc::new(move |key_store| {
+            if let Err(e) = persistence.save(&key_store.persisted()) {
+                log::error!("Could not persist keys due to: {:?}.", e);
+            }
+        });
+        let key_store = match restored {
+            Some(generations) => {
+                log::info!("Restored {} generations of keys.", generations.len());
+                RotatingKeyStore::restored(alg, generations, config, Arc::new(SystemClock))
+            }
+            None => {
+                let key_store = RotatingKeyStore::new(alg, config, Arc::new(SystemClock));
+                save(&key_store);
+                key_store
+            }
+        };
+        Self::spawn(key_store.with_rotation_hook(save), config, shutdown)
+    }
+}
+
 impl<T: Algo> Drop for KeyRotator<T> {
     fn drop(&mut self) {
-        if let Some((tx, join_handle)) = self.kill_handle.take() {
+        if let Some(join_handle) = self.join_handle.take() {
             log::warn!(
                 "Attempted to drop KeyRotation without calling `cleanup`. This can cause deadlocks."
             );
-            drop(tx);
+            self.shutdown.signal();
             // Willfully ignored since the user should have already been warned by this point, but we don't want to crash.
             #[allow(unused_must_use)] {
                 join_handle.join()
@@ -225,3 +668,322 @@ impl<A: Algo> KeyRotator<A> {
         Arc::clone(&self.key_store)
     }
 }
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use crate::{
+        algo::cipher::{plaintext, xchacha20::poly1305},
+        token::paseto::{self, token, Protocol},
+    };
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    /// A clock that only moves when told to.
+    struct ManualClock(Mutex<SystemTime>);
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+    impl Clock for ManualClock {
+        fn now(&self) -> SystemTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn encrypt(key: &poly1305::Key, msg: &str) -> Vec<u8> {
+        let tok = token::Data {
+            msg: msg.to_owned(),
+            footer: None::<()>,
+        };
+        paseto::V2Local::encrypt(tok, key).unwrap().to_vec()
+    }
+
+    fn decrypt(store: &RotatingKeyStore<poly1305::Algo>, packed: &[u8]) -> Option<String> {
+        store
+            .attempt_with_retry(&mut |key, _| {
+                paseto::V2Local::decrypt(token::Packed::new(packed.to_vec()), key)
+            })
+            .map(|tok: token::Data<String, ()>| tok.msg)
+            .ok()
+    }
+
+    #[test]
+    fn shutdown_wakes_waiters() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.wait_timeout(Duration::from_millis(1)));
+        let remote = shutdown.clone();
+        let waiter = thread::spawn(move || remote.wait_timeout(Duration::from_secs(60)));
+        shutdown.signal();
+        assert!(waiter.join().expect("Waiter to not panic."));
+        assert!(shutdown.is_signaled());
+    }
+
+    #[test]
+    fn rotator_stops_promptly_once_signaled() {
+        let poll_interval = Duration::from_secs(60 * 60);
+        let shutdown = Shutdown::new();
+        let rotator = KeyRotator::init_with_shutdown(
+            plaintext::Algo,
+            RotationConfig::new(poll_interval),
+            shutdown.clone(),
+        );
+        let signaled_at = Instant::now();
+        shutdown.signal();
+        rotator.cleanup().expect("Rotation thread to exit cleanly.");
+        assert!(signaled_at.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn rotation_is_due_once_the_period_passes() {
+        let generated_at = SystemTime::UNIX_EPOCH + HOUR;
+        let until = |elapsed| time_until_rotation(generated_at, generated_at + elapsed, HOUR);
+        assert_eq!(until(Duration::new(0, 0)), HOUR);
+        assert_eq!(until(HOUR / 4), HOUR * 3 / 4);
+        assert_eq!(until(HOUR), Duration::new(0, 0));
+        assert_eq!(until(2 * HOUR), Duration::new(0, 0));
+        let from_the_future = time_until_rotation(generated_at, generated_at - HOUR, HOUR);
+        assert_eq!(from_the_future, HOUR);
+    }
+
+    #[test]
+    fn lazy_rotation_happens_on_access() {
+        let clock = Arc::new(ManualClock(Mutex::new(SystemTime::UNIX_EPOCH)));
+        let config = RotationConfig {
+            mode: RotationMode::Lazy,
+            ..RotationConfig::new(HOUR)
+        };
+        let rotator = KeyRotator::init_with_clock(
+            poly1305::Algo,
+            config,
+            Shutdown::new(),
+            Arc::clone(&clock) as Arc<dyn Clock>,
+        );
+        assert!(rotator.join_handle.is_none());
+        let packed = encrypt(rotator.key_store.get_store().unwrap().curr(), "first");
+
+        clock.advance(HOUR - Duration::from_secs(1));
+        let store = rotator.key_store.get_store().unwrap();
+        assert_eq!(store.curr_generation().id, 0);
+        assert_eq!(store.time_until_rotation(), Duration::from_secs(1));
+
+        clock.advance(Duration::from_secs(1));
+        let store = rotator.key_store.get_store().unwrap();
+        assert_eq!(store.curr_generation().id, 1);
+        assert_eq!(store.curr_generation().generated_at, clock.now());
+        assert_eq!(decrypt(&store, &packed).as_deref(), Some("first"));
+        let again = rotator.key_store.get_store().unwrap();
+        assert_eq!(again.curr_generation().id, 1);
+
+        clock.advance(3 * HOUR);
+        let store = rotator.key_store.get_store().unwrap();
+        assert_eq!(store.curr_generation().id, 2);
+        assert_eq!(decrypt(&store, &packed), None);
+        rotator.cleanup().unwrap();
+    }
+
+    #[test]
+    fn reads_never_wait_on_rotations() {
+        let config = RotationConfig::new(HOUR);
+        let key_store = RotatingKeyStore::new(plaintext::Algo, config, Arc::new(SystemClock));
+        let fixture: RotatingKeyFixture<_> = Arc::new(SwappableKeyStore::new(key_store));
+        let rotating = fixture.rotation.lock().unwrap();
+        assert_eq!(fixture.get_store().unwrap().curr_generation().id, 0);
+        drop(rotating);
+
+        let rotations: Vec<_> = (0..8)
+            .map(|_| {
+                let fixture = Arc::clone(&fixture);
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        fixture.rotate_now().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for rotation in rotations {
+            rotation.join().unwrap();
+        }
+        assert_eq!(fixture.get_store().unwrap().curr_generation().id, 80);
+    }
+
+    #[test]
+    fn every_kept_generation_verifies_newest_first() {
+        let config = RotationConfig {
+            generations_kept: 3,
+            ..RotationConfig::new(HOUR)
+        };
+        let fixture: RotatingKeyFixture<_> = Arc::new(SwappableKeyStore::new(
+            RotatingKeyStore::new(poly1305::Algo, config, Arc::new(SystemClock)),
+        ));
+        let oldest = encrypt(fixture.get_store().unwrap().curr(), "oldest");
+        fixture.rotate_now().unwrap();
+        let store = fixture.rotate_now().unwrap();
+        assert_eq!(store.curr_generation().id, 2);
+        assert_eq!(decrypt(&store, &oldest).as_deref(), Some("oldest"));
+        let mut attempted = vec![];
+        let _: Result<(), ()> = store.attempt_with_retry(&mut |key, _| {
+            attempted.push(key.to_bytes());
+            Err(())
+        });
+        let newest_first: Vec<_> = store
+            .generations()
+            .iter()
+            .map(|g| g.key.to_bytes())
+            .collect();
+        assert_eq!(attempted, newest_first);
+        let store = fixture.rotate_now().unwrap();
+        assert_eq!(store.generations().len(), 3);
+        assert_eq!(decrypt(&store, &oldest), None);
+    }
+
+    #[test]
+    fn signatures_verify_across_a_rotation_boundary() {
+        use crate::{algo::sign::asymmetric::ed25519, token::sealed};
+        let config = RotationConfig::new(HOUR);
+        let fixture: RotatingKeyFixture<_> = Arc::new(SwappableKeyStore::new(
+            RotatingKeyStore::new(ed25519::Algo, config, Arc::new(SystemClock)),
+        ));
+        let now = SystemTime::now();
+        let signer = fixture.get_store().unwrap();
+        let generation = signer.curr_generation();
+        let signed = sealed::Token::new("signed", generation.id, now, config.lifetime())
+            .seal(&*generation.key)
+            .unwrap();
+        let verify = |store: &RotatingKeyStore<ed25519::Algo>| {
+            let kid = sealed::key_id(&signed).unwrap();
+            let public = store.public_keys().into_iter().find(|g| g.id == kid)?;
+            sealed::Token::<String>::unseal(&signed, &public.key, now).ok()
+        };
+        assert!(verify(&signer).is_some());
+
+        let rotated = fixture.rotate_now().unwrap();
+        let ids: Vec<_> = rotated.public_keys().iter().map(|g| g.id).collect();
+        assert_eq!(ids, vec![1, 0]);
+        let verified = verify(&rotated).map(|t| t.claims.data);
+        assert_eq!(verified.as_deref(), Some("signed"));
+        let newest = rotated.curr_generation();
+        let resigned = sealed::Token::new("signed", newest.id, now, config.lifetime())
+            .seal(&*newest.key)
+            .unwrap();
+        let previous = &rotated.public_keys()[1].key;
+        assert!(sealed::Token::<String>::unseal(&resigned, previous, now).is_err());
+
+        let retired = fixture.rotate_now().unwrap();
+        assert!(verify(&retired).is_none());
+    }
+
+    #[test]
+    fn debug_output_never_contains_key_bytes() {
+        use crate::algo::hash::{argon2::d as argon2, asymmetric::KeyPair as _, ecc::ed25519};
+        /// The ways the bytes could show up if printed.
+        fn assert_redacted(debug: &str, bytes: &[u8]) {
+            let listed = format!("{:?}", bytes);
+            assert!(!debug.contains(&listed[1..listed.len() / 2]), "{}", debug);
+            assert!(!debug.contains(&hex::encode(bytes)), "{}", debug);
+            assert!(!debug.contains(&base64::encode(bytes)), "{}", debug);
+        }
+
+        let secret = vec![0xab; 32];
+        let pw_store = StableKeyStore::new(
+            argon2::Algo::new(Default::default()),
+            argon2::Key::new(secret.clone().into()),
+        );
+        assert_redacted(&format!("{:?}", pw_store), &secret);
+
+        let token_key = poly1305::Key::from_bytes(&[0x42; 32]).unwrap();
+        let generation = Generation {
+            id: 0,
+            key: Arc::new(token_key.clone()),
+            generated_at: SystemTime::now(),
+        };
+        assert_redacted(&format!("{:?}", generation), &[0x42; 32]);
+        let persisted = persistence::PersistedKey::new(0, &token_key, SystemTime::now());
+        assert_redacted(&format!("{:?}", persisted), &[0x42; 32]);
+
+        let seed = ed25519::Seed([0x24; 32]);
+        let pair = ed25519::KeyPair::safe_generate(&Some(seed));
+        let private = pair.private_key().unwrap().as_ref().to_vec();
+        let debug = format!("{:?}", pair);
+        assert_redacted(&debug, &private);
+        assert_redacted(&debug, &[0x24; 32]);
+    }
+
+    mod persisted {
+        use super::*;
+        use std::path::{Path, PathBuf};
+
+        fn key_file() -> PathBuf {
+            std::env::temp_dir().join(format!("token_keys_{}", rand::random::<u64>()))
+        }
+
+        fn start(path: &Path) -> KeyRotator<poly1305::Algo> {
+            let persistence = FilePersistence::from_secret(path.to_owned(), b"master secret");
+            KeyRotator::init_with_persistence(
+                poly1305::Algo,
+                RotationConfig::new(HOUR),
+                Shutdown::new(),
+                Box::new(persistence),
+            )
+        }
+
+        #[test]
+        fn tokens_survive_restarts() {
+            let path = key_file();
+            let rotator = start(&path);
+            let packed = encrypt(rotator.key_store.get_store().unwrap().curr(), "signed in");
+            rotator.cleanup().unwrap();
+
+            let rotator = start(&path);
+            let store = rotator.key_store.get_store().unwrap();
+            assert_eq!(decrypt(&store, &packed).as_deref(), Some("signed in"));
+            rotator.cleanup().unwrap();
+            std::fs::remove_file(path).unwrap();
+        }
+
+        #[test]
+        fn manual_rotations_are_persisted() {
+            let path = key_file();
+            let rotator = start(&path);
+            let packed = encrypt(rotator.key_store.get_store().unwrap().curr(), "signed in");
+            let rotated = rotator.get_key_fixture().rotate_now().unwrap();
+            assert_eq!(rotated.curr_generation().id, 1);
+            rotator.cleanup().unwrap();
+
+            let rotator = start(&path);
+            let store = rotator.key_store.get_store().unwrap();
+            assert_eq!(store.curr_generation().id, 1);
+            assert_eq!(decrypt(&store, &packed).as_deref(), Some("signed in"));
+            rotator.cleanup().unwrap();
+            std::fs::remove_file(path).unwrap();
+        }
+
+        #[test]
+        fn ancient_keys_are_not_resurrected() {
+            let path = key_file();
+            let persistence = FilePersistence::from_secret(path.clone(), b"master secret");
+            let config = RotationConfig::new(HOUR);
+            let mut ancient =
+                RotatingKeyStore::new(poly1305::Algo, config, Arc::new(SystemClock)).persisted();
+            let generated_at = SystemTime::now() - 3 * HOUR;
+            ancient.generations[0].generated_at = generated_at;
+            persistence.save(&ancient).unwrap();
+
+            let rotator = start(&path);
+            let store = rotator.key_store.get_store().unwrap();
+            assert!(store.curr_generation().generated_at > generated_at + HOUR);
+            assert_ne!(
+                store.persisted().generations[0].key,
+                ancient.generations[0].key
+            );
+            let saved = persistence
+                .load()
+                .unwrap()
+                .expect("Fresh keys to be persisted.");
+            assert_eq!(saved, store.persisted());
+            rotator.cleanup().unwrap();
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
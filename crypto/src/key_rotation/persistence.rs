@@ -0,0 +1,253 @@
+//! Persistence for the keys of a [`KeyRotator`](crate::KeyRotator), so that tokens issued before a
+//! restart can still be verified after it.
+
+use super::{Generation, RotationConfig};
+use crate::algo::{
+    cipher::envelope::{Envelope, MasterKey, OpenError},
+    SecretBytes,
+};
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use zeroize::Zeroize;
+
+/// Additional data bound to the encrypted key file, so that it can't be confused with anything
+/// else encrypted with the same master key.
+const FILE_AAD: &[u8] = b"benxu.dev key rotation v1";
+/// How far in the future a key may claim to have been generated, to allow for clocks drifting
+/// between restarts.
+const CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// A key that can be converted to and from bytes so that it can be persisted.
+pub trait PersistableKey: Sized {
+    /// The raw bytes of the key.
+    fn to_bytes(&self) -> SecretBytes;
+    /// Rebuilds the key from the output of [`to_bytes`](PersistableKey::to_bytes), returning
+    /// [`None`] if the bytes are not a valid key.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+/// A single generation of key as persisted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedKey {
+    /// The id of the generation of the key.
+    pub id: u64,
+    /// The raw bytes of the key.
+    pub key: SecretBytes,
+    /// When the key was generated.
+    pub generated_at: SystemTime,
+}
+impl PersistedKey {
+    /// Converts the key to bytes.
+    pub fn new<K: PersistableKey>(id: u64, key: &K, generated_at: SystemTime) -> Self {
+        Self {
+            id,
+            key: key.to_bytes(),
+            generated_at,
+        }
+    }
+}
+
+/// The keys of a [`RotatingKeyStore`](crate::RotatingKeyStore) as persisted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedKeys {
+    /// Every generation of key kept, newest first.
+    pub generations: Vec<PersistedKey>,
+}
+
+/// Errors while loading or saving keys.
+#[derive(Debug)]
+pub enum PersistenceError {
+    /// The keys could not be read or written.
+    Io(io::Error),
+    /// The keys could not be decrypted, either because the master key changed or because they
+    /// were tampered with.
+    Decryption(OpenError),
+    /// The keys were decrypted, but are not in the expected format.
+    Malformed(json::Error),
+}
+impl From<io::Error> for PersistenceError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<json::Error> for PersistenceError {
+    fn from(e: json::Error) -> Self {
+        Self::Malformed(e)
+    }
+}
+
+/// Somewhere for a [`KeyRotator`](crate::KeyRotator) to keep its keys between restarts.
+pub trait KeyPersistence: Send + Sync {
+    /// Loads the persisted keys, or [`None`] if no keys were ever saved.
+    fn load(&self) -> Result<Option<PersistedKeys>, PersistenceError>;
+    /// Replaces the persisted keys.
+    fn save(&self, keys: &PersistedKeys) -> Result<(), PersistenceError>;
+}
+
+/// Persists keys to a file, sealed in an [`Envelope`] with a master key.
+pub struct FilePersistence {
+    /// The file the keys are kept in.
+    path: PathBuf,
+    /// The key the keys are sealed with.
+    master_key: MasterKey,
+}
+impl FilePersistence {
+    /// Keeps the keys in the file at `path`, sealed with `master_key`.
+    pub fn new(path: PathBuf, master_key: MasterKey) -> Self {
+        Self { path, master_key }
+    }
+    /// Keeps the keys in the file at `path`, sealed with a master key derived from a secret of
+    /// any length.
+    pub fn from_secret(path: PathBuf, secret: &[u8]) -> Self {
+        Self::new(path, MasterKey::from_secret(secret))
+    }
+}
+impl KeyPersistence for FilePersistence {
+    fn load(&self) -> Result<Option<PersistedKeys>, PersistenceError> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let plaintext = Envelope::from_bytes(&contents)
+            .and_then(|envelope| self.master_key.open(&envelope, FILE_AAD))
+            .map_err(PersistenceError::Decryption)?;
+        Ok(Some(json::from_slice(&plaintext)?))
+    }
+    fn save(&self, keys: &PersistedKeys) -> Result<(), PersistenceError> {
+        let mut plaintext = json::to_vec(keys)?;
+        let envelope = self.master_key.seal(&plaintext, FILE_AAD);
+        // The plaintext holds every key, so it must not linger once sealed.
+        plaintext.zeroize();
+        let contents = envelope.to_bytes();
+        // Write to the side and move it into place so a crash never leaves a partial file.
+        let partial = self.path.with_extension("partial");
+        fs::write(&partial, contents)?;
+        fs::rename(&partial, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Reasons persisted keys are not restored.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum RestoreError {
+    /// No keys were persisted.
+    Empty,
+    /// The current key claims to have been generated in the future.
+    FromTheFuture,
+    /// The generations are not ordered newest first.
+    OutOfOrder,
+    /// The current key is too old to still be in use.
+    Expired,
+    /// The bytes of a key are not a valid key.
+    InvalidKey,
+}
+
+/// Validates the persisted keys against the rotation schedule, refusing to bring back keys that
+/// should have been retired already. Generations that are past their lifetime, or beyond the number
+/// kept, are dropped.
+pub(super) fn restore<K: PersistableKey>(
+    keys: &PersistedKeys,
+    config: RotationConfig,
+    now: SystemTime,
+) -> Result<Vec<Generation<K>>, RestoreError> {
+    let newest = keys.generations.first().ok_or(RestoreError::Empty)?;
+    if newest.generated_at > now + CLOCK_SKEW {
+        return Err(RestoreError::FromTheFuture);
+    }
+    let in_order = keys
+        .generations
+        .windows(2)
+        .all(|pair| pair[0].id > pair[1].id && pair[0].generated_at >= pair[1].generated_at);
+    if !in_order {
+        return Err(RestoreError::OutOfOrder);
+    }
+    let alive = |key: &&PersistedKey| {
+        now.duration_since(key.generated_at).unwrap_or_default() < config.lifetime()
+    };
+    if !alive(&newest) {
+        return Err(RestoreError::Expired);
+    }
+    keys.generations
+        .iter()
+        .take_while(alive)
+        .take(config.generations_kept.max(1))
+        .map(|persisted| {
+            Ok(Generation {
+                id: persisted.id,
+                key: Arc::new(K::from_bytes(&persisted.key).ok_or(RestoreError::InvalidKey)?),
+                generated_at: persisted.generated_at,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use crate::algo::{cipher::xchacha20::poly1305, SafeGenerateKey};
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    /// Generations with the given ages, with the newest first.
+    fn keys(now: SystemTime, ages: &[Duration]) -> PersistedKeys {
+        let generations = ages
+            .iter()
+            .zip((0..ages.len() as u64).rev())
+            .map(|(age, id)| PersistedKey::new(id, &poly1305::Key::safe_generate(&()), now - *age))
+            .collect();
+        PersistedKeys { generations }
+    }
+
+    #[test]
+    fn file_round_trips_only_with_the_master_key() {
+        let path = std::env::temp_dir().join(format!("token_keys_{}", rand::random::<u64>()));
+        let persistence = FilePersistence::from_secret(path.clone(), b"master secret");
+        assert!(persistence.load().unwrap().is_none());
+        let saved = keys(SystemTime::now(), &[HOUR, 2 * HOUR]);
+        persistence.save(&saved).unwrap();
+        assert_eq!(persistence.load().unwrap(), Some(saved));
+        let wrong_master = FilePersistence::from_secret(path.clone(), b"another secret");
+        match wrong_master.load() {
+            Err(PersistenceError::Decryption(OpenError::WrongKey { .. })) => {}
+            res => panic!("Expected a decryption error, got {:?}.", res),
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stale_or_inconsistent_keys_are_not_restored() {
+        let now = SystemTime::now();
+        let config = RotationConfig {
+            generations_kept: 3,
+            ..RotationConfig::new(HOUR)
+        };
+        let rejection = |keys: &PersistedKeys| restore::<poly1305::Key>(keys, config, now).err();
+        assert_eq!(rejection(&keys(now, &[])), Some(RestoreError::Empty));
+        assert_eq!(
+            rejection(&keys(now, &[3 * HOUR, 4 * HOUR])),
+            Some(RestoreError::Expired)
+        );
+        assert_eq!(
+            rejection(&keys(now, &[2 * HOUR, HOUR])),
+            Some(RestoreError::OutOfOrder)
+        );
+        let mut future = keys(now, &[HOUR]);
+        future.generations[0].generated_at = now + HOUR;
+        assert_eq!(rejection(&future), Some(RestoreError::FromTheFuture));
+        let restored = restore::<poly1305::Key>(
+            &keys(now, &[HOUR / 2, 2 * HOUR, 3 * HOUR, 4 * HOUR]),
+            config,
+            now,
+        )
+        .unwrap();
+        let ids: Vec<_> = restored.iter().map(|g| g.id).collect();
+        assert_eq!(ids, vec![3, 2]);
+    }
+}
@@ -1,11 +1,3 @@
-#![feature(
-    proc_macro_hygiene,
-    type_ascription,
-    decl_macro,
-    associated_type_defaults,
-    never_type
-)]
-
 //! A crate gathering various algorithms from different crypto libraries and attempting to unify
 //! their apis. Built to manage key rotations. Implements the PASETO token standard for standard
 //! web cookie/token-based authorization. Repackages some encoding libraries as well.
@@ -17,8 +9,12 @@ pub mod algo;
 pub mod encoding;
 pub mod key_rotation;
 pub mod token;
+
+#[cfg(test)]
+mod prop_seed;
 pub use key_rotation::{
-    Generational, KeyRotator, RotatingKeyFixture, RotatingKeyStore, StableKeyStore,
+    Clock, Generational, KeyRotator, RotatingKeyFixture, RotatingKeyStore, RotationConfig,
+    RotationMode, Shutdown, StableKeyStore, SwappableKeyStore, SystemClock,
 };
 
 /// Always call this if you need the sodiumoxide-implemented things to work multithreaded.
@@ -0,0 +1,50 @@
+//! Runs property tests from a seed, so that failures can be reproduced.
+//!
+//! The seed is read from [`SEED_VAR`] as 64 hex digits if it is set, such as by CI to run the same
+//! cases every time, or else picked at random. Failures print the seed to run them again with.
+
+use proptest::{
+    strategy::Strategy,
+    test_runner::{Config, RngAlgorithm, TestCaseError, TestRng, TestRunner},
+};
+use rand::{rngs::OsRng, RngCore};
+
+use crate::encoding::{Encoding, Hex};
+
+/// The environment variable the seed is read from.
+pub const SEED_VAR: &str = "CRYPTO_PROPTEST_SEED";
+
+/// The seed in [`SEED_VAR`], or else a random one.
+fn seed() -> [u8; 32] {
+    let mut seed = [0; 32];
+    match std::env::var(SEED_VAR) {
+        Ok(hex) => {
+            let decoded = Hex::decode(hex.trim())
+                .unwrap_or_else(|e| panic!("{} is not hex: {:?}", SEED_VAR, e));
+            assert_eq!(decoded.len(), seed.len(), "{} must be 64 hex digits", SEED_VAR);
+            seed.copy_from_slice(&decoded);
+        }
+        Err(_) => OsRng.fill_bytes(&mut seed),
+    }
+    seed
+}
+
+/// Checks `test` against `cases` values of `strategy`, shrinking the first failure.
+pub fn run<S: Strategy>(
+    cases: u32,
+    strategy: S,
+    test: impl Fn(S::Value) -> Result<(), TestCaseError>,
+) {
+    let seed = seed();
+    let config = Config {
+        cases,
+        // The seed stands in for the files proptest would keep failures in.
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &seed);
+    let mut runner = TestRunner::new_with_rng(config, rng);
+    if let Err(e) = runner.run(&strategy, test) {
+        panic!("{}\nRun again with {}={}", e, SEED_VAR, Hex::encode(&seed));
+    }
+}
@@ -3,3 +3,4 @@
 
 pub mod jwe;
 pub mod paseto;
+pub mod sealed;
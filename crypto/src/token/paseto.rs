@@ -69,7 +69,7 @@ pub trait KnownClaims {}
 /// Temporary impl, before the system actually works.
 impl KnownClaims for String {}
 
-mod util {
+pub(crate) mod util {
     /// A helper for copying a unsigned 64 bit int into a mutable slice.
     pub(super) fn append_u64_to_little_endian_byte_array(
         to_encode: u64,
@@ -102,7 +102,7 @@ mod util {
     }
 
     /// Implementation of the pre-auth encoding described by PASETO.
-    pub(super) fn multi_part_pre_auth_encoding(pieces: &[&[u8]]) -> Result<Vec<u8>, &'static str> {
+    pub(crate) fn multi_part_pre_auth_encoding(pieces: &[&[u8]]) -> Result<Vec<u8>, &'static str> {
         // precalc size
         const HEADER_SIZE: usize = 8;
         let mut total_size = 0;
@@ -7,7 +7,7 @@ use std::{
 };
 
 use crate::{
-    encoding::base64::{decode_no_padding as b64_decode, encode_no_padding as b64_encode},
+    encoding::{self, Base64NoPad as B64, Encoding as _},
     token::paseto::util::collapse_to_vec,
 };
 
@@ -141,8 +141,8 @@ impl From<UnexpectedNumberOfPeriods> for UnpackingError {
         UnpackingError::IncorrectNumberOfSections
     }
 }
-impl From<base64::DecodeError> for UnpackingError {
-    fn from(_: base64::DecodeError) -> Self {
+impl From<encoding::DecodeError> for UnpackingError {
+    fn from(_: encoding::DecodeError) -> Self {
         UnpackingError::MalformedEncoding
     }
 }
@@ -294,15 +294,15 @@ impl Packed {
         let possible_footer = tok
             .footer
             .as_ref()
-            .map_or(b"".to_vec(), |f| b64_encode(f.as_slice()));
+            .map_or(String::new(), |f| B64::encode(f.as_slice()));
         Packed(collapse_to_vec(&[
             tok.version.as_slice(),
             b".",
             tok.purpose.as_slice(),
             b".",
-            b64_encode(tok.body.as_slice()).as_slice(),
+            B64::encode(tok.body.as_slice()).as_bytes(),
             tok.footer.as_ref().map_or(b"", |_| b"."),
-            possible_footer.as_slice(),
+            possible_footer.as_bytes(),
         ]))
     }
 }
@@ -372,10 +372,10 @@ impl Unpacked {
         Ok(Self {
             version: Self::extract_bounds(packed, period_indices.version_range()).to_vec(),
             purpose: Self::extract_bounds(packed, period_indices.purpose_range()).to_vec(),
-            body: b64_decode(Self::extract_bounds(packed, period_indices.body_range()))?,
+            body: B64::decode_bytes(Self::extract_bounds(packed, period_indices.body_range()))?,
             footer: period_indices
                 .footer_range()
-                .map(|r| b64_decode(Self::extract_bounds(packed, r)))
+                .map(|r| B64::decode_bytes(Self::extract_bounds(packed, r)))
                 .transpose()?,
         })
     }
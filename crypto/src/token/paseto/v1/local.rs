@@ -117,6 +117,7 @@ pub struct CombinedAlgo(usize);
 
 impl A for CombinedAlgo {
     type Key = CombinedKey;
+    type ConstructionData = ();
     fn key_settings(&self) -> &<Self::Key as K>::Settings {
         &self.0
     }
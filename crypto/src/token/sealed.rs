@@ -0,0 +1,511 @@
+//! Self-describing tokens of the form `v1.<purpose>.<payload>.<footer>`, modeled after PASETO.
+//!
+//! The purpose is `local` for tokens encrypted with XChaCha20-Poly1305, and `public` for tokens
+//! signed with Ed25519. The payload and footer are base64url encoded without padding. The footer
+//! holds the generation id of the key the token was sealed with, so that the right key of a
+//! [`RotatingKeyStore`](crate::RotatingKeyStore) can be picked without trying each of them, and is
+//! authenticated along with the payload.
+//!
+//! The version is of this format, and is unrelated to the versions of PASETO.
+//!
+//! ```rust
+//! use bundled_crypto::token::sealed::Token;
+//! use bundled_crypto::algo::{cipher::xchacha20::poly1305, SafeGenerateKey};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let key = poly1305::Key::safe_generate(&());
+//! let now = SystemTime::now();
+//! let sealed = Token::new("Hello World!", 0, now, Duration::from_secs(60)).seal(&key).unwrap();
+//! let tok: Token<String> = Token::unseal(&sealed, &key, now).unwrap();
+//! assert_eq!(tok.claims.data, "Hello World!");
+//! ```
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json as json;
+use sodiumoxide::crypto::{
+    aead::xchacha20poly1305_ietf::{gen_nonce, Nonce, NONCEBYTES},
+    sign::ed25519::{PublicKey, SIGNATUREBYTES},
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    algo::{
+        cipher::{
+            symmetric::{CanDecrypt, CanEncrypt},
+            xchacha20::poly1305,
+        },
+        hash::{asymmetric::Algo as _, asymmetric::KeyPair as _, ecc::ed25519},
+    },
+    encoding::{self, Base64UrlNoPad as B64, Encoding as _},
+    token::paseto::util::multi_part_pre_auth_encoding,
+};
+
+/// The version of the format.
+pub const VERSION: &str = "v1";
+/// How far in the future a token may claim to have been issued, to allow for clocks drifting
+/// between servers.
+const CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// Errors while sealing or unsealing a token.
+#[derive(Debug)]
+pub enum Error {
+    /// The token is not made up of a version, purpose, payload, and footer.
+    Malformed,
+    /// The token is of another version of the format.
+    WrongVersion,
+    /// The token was sealed for another purpose than the key given to unseal it.
+    WrongPurpose,
+    /// The token was not sealed with the key, or was tampered with.
+    BadSignature,
+    /// The token has expired.
+    Expired,
+    /// The token claims to have been issued in the future.
+    IssuedInTheFuture,
+    /// The claims or footer could not be serialized or deserialized.
+    Serialization(json::Error),
+    /// The key could not seal the token, such as a key pair without a private key.
+    Sealing,
+}
+impl From<json::Error> for Error {
+    fn from(e: json::Error) -> Self {
+        Self::Serialization(e)
+    }
+}
+impl From<encoding::DecodeError> for Error {
+    fn from(_: encoding::DecodeError) -> Self {
+        Self::Malformed
+    }
+}
+
+/// A key that can seal tokens.
+pub trait SealingKey {
+    /// The purpose in the header of the tokens sealed with the key.
+    const PURPOSE: &'static str;
+    /// Seals the serialized claims into the payload. Both `header` and `footer` must be
+    /// authenticated along with the claims.
+    fn seal(&self, header: &[u8], claims: &[u8], footer: &[u8]) -> Result<Vec<u8>, Error>;
+}
+/// A key that can unseal tokens.
+pub trait UnsealingKey {
+    /// The purpose in the header of the tokens the key unseals.
+    const PURPOSE: &'static str;
+    /// Verifies the payload along with the `header` and `footer`, returning the serialized claims.
+    fn unseal(&self, header: &[u8], payload: &[u8], footer: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// Encrypts the claims with `nonce`. The payload is the nonce followed by the ciphertext.
+fn seal_local(
+    key: &poly1305::Key,
+    nonce: Nonce,
+    header: &[u8],
+    claims: &[u8],
+    footer: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let aad = multi_part_pre_auth_encoding(&[header, nonce.as_ref(), footer])
+        .map_err(|_| Error::Sealing)?;
+    let args = poly1305::EncryptArgs {
+        plaintext: claims.to_vec(),
+        aad: Some(aad),
+        nonce: Some(nonce),
+    };
+    let ciphertext = poly1305::Algo
+        .encrypt(key, &args)
+        .map_err(|_| Error::Sealing)?;
+    let mut payload = nonce.as_ref().to_vec();
+    payload.extend(ciphertext);
+    Ok(payload)
+}
+impl SealingKey for poly1305::Key {
+    const PURPOSE: &'static str = "local";
+    fn seal(&self, header: &[u8], claims: &[u8], footer: &[u8]) -> Result<Vec<u8>, Error> {
+        seal_local(self, gen_nonce(), header, claims, footer)
+    }
+}
+impl UnsealingKey for poly1305::Key {
+    const PURPOSE: &'static str = "local";
+    fn unseal(&self, header: &[u8], payload: &[u8], footer: &[u8]) -> Result<Vec<u8>, Error> {
+        if payload.len() < NONCEBYTES {
+            return Err(Error::Malformed);
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCEBYTES);
+        let aad =
+            multi_part_pre_auth_encoding(&[header, nonce, footer]).map_err(|_| Error::Malformed)?;
+        let args = poly1305::DecryptArgs {
+            ciphertext: ciphertext.to_vec(),
+            aad: Some(aad),
+            nonce: Nonce::from_slice(nonce).ok_or(Error::Malformed)?,
+        };
+        poly1305::Algo
+            .decrypt(self, &args)
+            .map_err(|_| Error::BadSignature)
+    }
+}
+
+/// Signs the claims. The payload is the claims followed by the signature.
+impl SealingKey for ed25519::KeyPair {
+    const PURPOSE: &'static str = "public";
+    fn seal(&self, header: &[u8], claims: &[u8], footer: &[u8]) -> Result<Vec<u8>, Error> {
+        let signed =
+            multi_part_pre_auth_encoding(&[header, claims, footer]).map_err(|_| Error::Sealing)?;
+        let sig = ed25519::Algo::sign_private(signed.as_slice(), self.private_key())
+            .map_err(|_| Error::Sealing)?;
+        let mut payload = claims.to_vec();
+        payload.extend(sig);
+        Ok(payload)
+    }
+}
+impl UnsealingKey for PublicKey {
+    const PURPOSE: &'static str = "public";
+    fn unseal(&self, header: &[u8], payload: &[u8], footer: &[u8]) -> Result<Vec<u8>, Error> {
+        if payload.len() < SIGNATUREBYTES {
+            return Err(Error::Malformed);
+        }
+        let (claims, sig) = payload.split_at(payload.len() - SIGNATUREBYTES);
+        let signed = multi_part_pre_auth_encoding(&[header, claims, footer])
+            .map_err(|_| Error::Malformed)?;
+        match ed25519::Algo::verify_public(signed.as_slice(), sig, self) {
+            Ok(true) => Ok(claims.to_vec()),
+            _ => Err(Error::BadSignature),
+        }
+    }
+}
+impl UnsealingKey for ed25519::KeyPair {
+    const PURPOSE: &'static str = "public";
+    fn unseal(&self, header: &[u8], payload: &[u8], footer: &[u8]) -> Result<Vec<u8>, Error> {
+        self.public_key().unseal(header, payload, footer)
+    }
+}
+
+/// The claims of a token, wrapping its data with when it was issued and when it expires, both in
+/// seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims<T> {
+    /// When the token was issued.
+    pub iat: u64,
+    /// When the token expires.
+    pub exp: u64,
+    /// The data carried by the token.
+    pub data: T,
+}
+
+/// The footer of a token.
+#[derive(Serialize, Deserialize)]
+struct Footer {
+    /// The generation id of the key the token was sealed with.
+    kid: u64,
+}
+
+/// A token before sealing or after unsealing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<T> {
+    /// The claims of the token.
+    pub claims: Claims<T>,
+    /// The generation id of the key the token is sealed with.
+    pub key_id: u64,
+}
+impl<T> Token<T> {
+    /// Creates a token carrying `data`, issued at `issued_at`, and expiring after `lifetime`.
+    pub fn new(data: T, key_id: u64, issued_at: SystemTime, lifetime: Duration) -> Self {
+        let iat = secs_since_epoch(issued_at);
+        Self {
+            claims: Claims {
+                iat,
+                exp: iat.saturating_add(lifetime.as_secs()),
+                data,
+            },
+            key_id,
+        }
+    }
+}
+impl<T: Serialize> Token<T> {
+    /// Seals the token with `key`.
+    pub fn seal<K: SealingKey>(&self, key: &K) -> Result<String, Error> {
+        let header = header(K::PURPOSE);
+        let claims = json::to_vec(&self.claims)?;
+        let footer = json::to_vec(&Footer { kid: self.key_id })?;
+        let payload = key.seal(header.as_bytes(), claims.as_slice(), footer.as_slice())?;
+        Ok(format!(
+            "{}{}.{}",
+            header,
+            B64::encode(payload.as_slice()),
+            B64::encode(footer.as_slice())
+        ))
+    }
+}
+impl<T: DeserializeOwned> Token<T> {
+    /// Unseals the token with `key`, checking that it is valid at `now`.
+    pub fn unseal<K: UnsealingKey>(token: &str, key: &K, now: SystemTime) -> Result<Self, Error> {
+        let (purpose, payload, footer) = split(token)?;
+        if purpose != K::PURPOSE {
+            return Err(Error::WrongPurpose);
+        }
+        let header = header(K::PURPOSE);
+        let claims = key.unseal(header.as_bytes(), payload.as_slice(), footer.as_slice())?;
+        let claims: Claims<T> = json::from_slice(claims.as_slice())?;
+        let Footer { kid } = json::from_slice(footer.as_slice())?;
+        let now = secs_since_epoch(now);
+        if claims.exp <= now {
+            return Err(Error::Expired);
+        }
+        if claims.iat > now.saturating_add(CLOCK_SKEW.as_secs()) {
+            return Err(Error::IssuedInTheFuture);
+        }
+        Ok(Self {
+            claims,
+            key_id: kid,
+        })
+    }
+}
+
+/// Reads the generation id of the key the token claims to be sealed with, without verifying the
+/// token. Only use this to pick the key to unseal the token with.
+pub fn key_id(token: &str) -> Result<u64, Error> {
+    let (_, _, footer) = split(token)?;
+    let Footer { kid } = json::from_slice(footer.as_slice())?;
+    Ok(kid)
+}
+
+/// Checks that a token looks like it is of this format, without decoding it.
+pub fn is_sealed(token: &str) -> bool {
+    token.starts_with(&format!("{}.", VERSION))
+}
+
+/// The header of tokens with `purpose`, including the trailing period.
+fn header(purpose: &str) -> String {
+    format!("{}.{}.", VERSION, purpose)
+}
+
+/// Splits a token into its purpose and its decoded payload and footer.
+fn split(token: &str) -> Result<(&str, Vec<u8>, Vec<u8>), Error> {
+    let sections: Vec<_> = token.split('.').collect();
+    let (version, purpose, payload, footer) = match sections.as_slice() {
+        [version, purpose, payload, footer] => (*version, *purpose, *payload, *footer),
+        [version, ..] if *version != VERSION => return Err(Error::WrongVersion),
+        _ => return Err(Error::Malformed),
+    };
+    if version != VERSION {
+        return Err(Error::WrongVersion);
+    }
+    Ok((purpose, B64::decode(payload)?, B64::decode(footer)?))
+}
+
+/// The number of whole seconds since the Unix epoch, or zero for times before it.
+fn secs_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use crate::{algo::SafeGenerateKey, key_rotation::PersistableKey};
+    use sodiumoxide::crypto::sign::ed25519::Seed;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn issued_at() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+    }
+
+    fn token() -> Token<String> {
+        Token::new("Hello World!".to_owned(), 7, issued_at(), HOUR)
+    }
+
+    fn local_key() -> poly1305::Key {
+        poly1305::Key::from_bytes(&[0x42; 32]).unwrap()
+    }
+
+    fn key_pair() -> ed25519::KeyPair {
+        ed25519::KeyPair::safe_generate(&Some(Seed([0x24; 32])))
+    }
+
+    /// Replaces the first character of the payload so that it no longer verifies.
+    fn tamper(token: &str) -> String {
+        let payload_start = header("").len() + token.split('.').nth(1).unwrap().len();
+        let replacement = if &token[payload_start..=payload_start] == "A" {
+            "B"
+        } else {
+            "A"
+        };
+        let mut tampered = token.to_owned();
+        tampered.replace_range(payload_start..=payload_start, replacement);
+        tampered
+    }
+
+    #[test]
+    fn local_known_answer() {
+        let tok = token();
+        let header = header("local");
+        let claims = json::to_vec(&tok.claims).unwrap();
+        let footer = json::to_vec(&Footer { kid: tok.key_id }).unwrap();
+        let nonce = Nonce::from_slice(&[0x11; NONCEBYTES]).unwrap();
+        let payload = seal_local(&local_key(), nonce, header.as_bytes(), &claims, &footer).unwrap();
+        let sealed = format!(
+            "{}{}.{}",
+            header,
+            B64::encode(&payload),
+            B64::encode(&footer)
+        );
+        assert_eq!(sealed, LOCAL_KNOWN_ANSWER);
+        let unsealed = Token::unseal(&sealed, &local_key(), issued_at()).unwrap();
+        assert_eq!(unsealed, tok);
+    }
+
+    #[test]
+    fn public_known_answer() {
+        let sealed = token().seal(&key_pair()).unwrap();
+        assert_eq!(sealed, PUBLIC_KNOWN_ANSWER);
+        let public_key = key_pair().public_key().clone();
+        let unsealed = Token::unseal(&sealed, &public_key, issued_at()).unwrap();
+        assert_eq!(unsealed, token());
+        assert_eq!(key_id(&sealed).unwrap(), 7);
+    }
+
+    #[test]
+    fn tokens_only_unseal_with_the_right_key_and_purpose() {
+        let now = issued_at();
+        let local = token().seal(&local_key()).unwrap();
+        let public = token().seal(&key_pair()).unwrap();
+        let other_key = poly1305::Key::safe_generate(&());
+        let unseal_local =
+            |tok: &str, key: &poly1305::Key| Token::<String>::unseal(tok, key, now).err();
+        assert!(matches!(
+            unseal_local(&local, &other_key),
+            Some(Error::BadSignature)
+        ));
+        assert!(matches!(
+            unseal_local(&tamper(&local), &local_key()),
+            Some(Error::BadSignature)
+        ));
+        assert!(matches!(
+            unseal_local(&public, &local_key()),
+            Some(Error::WrongPurpose)
+        ));
+        assert!(matches!(
+            unseal_local(&local.replacen("v1.", "v2.", 1), &local_key()),
+            Some(Error::WrongVersion)
+        ));
+        assert!(matches!(
+            unseal_local("v1.local.payload", &local_key()),
+            Some(Error::Malformed)
+        ));
+        let other_pair = ed25519::KeyPair::safe_generate(&None);
+        assert!(matches!(
+            Token::<String>::unseal(&public, &other_pair, now),
+            Err(Error::BadSignature)
+        ));
+        assert!(matches!(
+            Token::<String>::unseal(&tamper(&public), &key_pair(), now),
+            Err(Error::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn claims_are_enforced() {
+        let sealed = token().seal(&local_key()).unwrap();
+        let unseal = |now| Token::<String>::unseal(&sealed, &local_key(), now).err();
+        assert!(unseal(issued_at() + HOUR - Duration::from_secs(1)).is_none());
+        assert!(matches!(unseal(issued_at() + HOUR), Some(Error::Expired)));
+        assert!(unseal(issued_at() - CLOCK_SKEW).is_none());
+        assert!(matches!(
+            unseal(issued_at() - 2 * CLOCK_SKEW),
+            Some(Error::IssuedInTheFuture)
+        ));
+    }
+
+    /// [`token`] sealed with [`local_key`] and a nonce of `0x11` bytes.
+    const LOCAL_KNOWN_ANSWER: &str = concat!(
+        "v1.local.ERERERERERERERERERERERERERERERERh3jLDP55oJoK62PZMjLiCj585bYobalxpJnlB3E8BdQ28wqEvU",
+        "46Ae2XCstJigta4Js4e0fYBRhx_s9kYWL0Y2Say8Se_M1VPw.eyJraWQiOjd9",
+    );
+    /// [`token`] sealed with [`key_pair`].
+    const PUBLIC_KNOWN_ANSWER: &str = concat!(
+        "v1.public.eyJpYXQiOjE2MDAwMDAwMDAsImV4cCI6MTYwMDAwMzYwMCwiZGF0YSI6IkhlbGxvIFdvcmxkISJ9bTuMZ",
+        "u6MFqBKXRlBEqDa4udkbNj8HFKA06fqcY_ICg3MHcte07DHDL9Toozk9UqORSi31og6A2zVxQmkopukBQ.eyJraWQiOj",
+        "d9",
+    );
+}
+
+#[cfg(test)]
+mod prop_test {
+    use super::*;
+    use crate::{algo::SafeGenerateKey, prop_seed::run};
+    use proptest::{prelude::*, sample::Index};
+
+    const CASES: u32 = 128;
+    const LIFETIME: Duration = Duration::from_secs(60);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Payload {
+        name: String,
+        count: u64,
+        bytes: Vec<u8>,
+        flag: Option<bool>,
+        tags: Vec<String>,
+    }
+
+    fn payload() -> impl Strategy<Value = Payload> {
+        (
+            any::<String>(),
+            any::<u64>(),
+            prop::collection::vec(any::<u8>(), 0..64),
+            any::<Option<bool>>(),
+            prop::collection::vec(any::<String>(), 0..4),
+        )
+            .prop_map(|(name, count, bytes, flag, tags)| Payload {
+                name,
+                count,
+                bytes,
+                flag,
+                tags,
+            })
+    }
+
+    #[test]
+    fn arbitrary_payloads_round_trip() {
+        let local = poly1305::Key::safe_generate(&());
+        let pair = ed25519::KeyPair::safe_generate(&None);
+        let now = SystemTime::now();
+        run(CASES, (payload(), any::<u64>()), |(data, kid)| {
+            let token = Token::new(data, kid, now, LIFETIME);
+            let sealed = token.seal(&local).unwrap();
+            let unsealed: Token<Payload> = Token::unseal(&sealed, &local, now).unwrap();
+            prop_assert_eq!(&unsealed, &token);
+            let signed = token.seal(&pair).unwrap();
+            let unsealed: Token<Payload> = Token::unseal(&signed, &pair, now).unwrap();
+            prop_assert_eq!(&unsealed, &token);
+            prop_assert_eq!(key_id(&signed).unwrap(), kid);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn any_single_byte_change_is_refused() {
+        let local = poly1305::Key::safe_generate(&());
+        let pair = ed25519::KeyPair::safe_generate(&None);
+        let now = SystemTime::now();
+        // Printable ASCII, so that the changed token is still text.
+        let change = (payload(), any::<bool>(), any::<Index>(), 0x20_u8..0x7f);
+        run(CASES * 4, change, |(data, public, at, byte)| {
+            let token = Token::new(data, 0, now, LIFETIME);
+            let sealed = if public {
+                token.seal(&pair)
+            } else {
+                token.seal(&local)
+            };
+            let mut bytes = sealed.unwrap().into_bytes();
+            let at = at.index(bytes.len());
+            prop_assume!(bytes[at] != byte);
+            bytes[at] = byte;
+            let tampered = String::from_utf8(bytes).unwrap();
+            let unsealed = if public {
+                Token::<Payload>::unseal(&tampered, &pair, now)
+            } else {
+                Token::<Payload>::unseal(&tampered, &local, now)
+            };
+            prop_assert!(unsealed.is_err(), "{} unsealed after changing byte {}", tampered, at);
+            Ok(())
+        });
+    }
+}
@@ -2,7 +2,7 @@
 
 use chrono::{Datelike, Utc};
 use maud::{html, Markup, PreEscaped, Render};
-use std::fs;
+use std::{borrow::Cow, collections::HashMap, fs};
 use typed_builder::TypedBuilder;
 
 /// Represents a logo.
@@ -47,12 +47,35 @@ pub struct MetaData<'a> {
     /// CSS to include in the website.
     #[builder(default=&[])]
     pub css: &'a [Css<'a>],
-    /// The title of the website.
+    /// The title of the website, which ends the title of every page.
     #[builder(default = "Benjamin Xu")]
-    pub title: &'a str,
+    pub site_title: &'a str,
+    /// The title of the page itself, if it is not the whole site.
+    #[builder(default)]
+    pub title: Option<&'a str>,
+    /// The pages leading to this one from the home page, ending with this one. Left out if empty.
+    #[builder(default = &[])]
+    pub breadcrumbs: &'a [Crumb<'a>],
     /// The description of the website.
     #[builder(default = "Benjamin Xu's personal site.")]
     pub description: &'a str,
+    /// The title shown when the page is linked to elsewhere, if it differs from the title of the
+    /// page.
+    #[builder(default)]
+    pub og_title: Option<&'a str>,
+    /// The description shown when the page is linked to elsewhere, if it differs from the
+    /// description.
+    #[builder(default)]
+    pub og_description: Option<&'a str>,
+    /// The absolute url of the image shown when the page is linked to elsewhere, if any.
+    #[builder(default)]
+    pub og_image: Option<&'a str>,
+    /// The kind of page, as [Open Graph](https://ogp.me/#types) names it.
+    #[builder(default = "website")]
+    pub og_type: &'a str,
+    /// The absolute url the page is known by, if it can be reached through several.
+    #[builder(default)]
+    pub canonical_url: Option<&'a str>,
     /// The copyright data of the website.
     #[builder(default = Copyright {
         name: &Name {
@@ -68,6 +91,9 @@ pub struct MetaData<'a> {
     /// The menu of the website.
     #[builder(default)]
     pub menu: Option<&'a Menu<'a>>,
+    /// The path of the page, marked as current in the menu.
+    #[builder(default)]
+    pub current_path: Option<&'a str>,
     /// The points of contact for the owner of the website.
     #[builder(default)]
     pub contact: Option<&'a Contact<'a>>,
@@ -77,27 +103,140 @@ pub struct MetaData<'a> {
     /// The theme color of the website. Affects mobile address name bars.
     #[builder(default = "#00003f")]
     pub theme_color: &'a str,
+    /// The manifest used to find the hashed versions of scripts and css, if any.
+    #[builder(default)]
+    pub assets: Option<&'a AssetManifest>,
+    /// The content security policy nonce placed on inline scripts and styles, if any.
+    #[builder(default)]
+    pub nonce: Option<&'a str>,
 }
 impl<'a> Default for MetaData<'a> {
     fn default() -> Self {
         Self::builder().build()
     }
 }
+impl<'a> MetaData<'a> {
+    /// The title of the document, as [`page_title`] formats it.
+    pub fn document_title(&self) -> String {
+        page_title(self.title, self.site_title)
+    }
+}
+
+/// The title of a document showing `page` of the site titled `site`, or the whole site. Shared by
+/// the server and `blog_client`, so that pages are titled the same whether they were served or
+/// navigated to.
+pub fn page_title(page: Option<&str>, site: &str) -> String {
+    match page {
+        Some(page) if !page.trim().is_empty() => format!("{} | {}", page, site),
+        _ => site.to_owned(),
+    }
+}
+
+/// A page leading to the current one, such as the blog leading to a post on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crumb<'a> {
+    pub name: &'a str,
+    /// The absolute url of the page.
+    pub url: &'a str,
+}
+impl<'a> Crumb<'a> {
+    /// The trail of `crumbs` as links, the last marked as the current page, along with the same
+    /// trail as a schema.org `BreadcrumbList` for search engines. Nothing if there are no crumbs.
+    pub fn render_trail(crumbs: &[Self], nonce: Option<&str>) -> Markup {
+        if crumbs.is_empty() {
+            return html! {};
+        }
+        let last = crumbs.len() - 1;
+        html! {
+            nav.breadcrumbs aria-label="Breadcrumbs" {
+                ol {
+                    @for (i, crumb) in crumbs.iter().enumerate() {
+                        li {
+                            @if i == last {
+                                a href=(crumb.url) aria-current="page" { (crumb.name) }
+                            } @else {
+                                a href=(crumb.url) { (crumb.name) }
+                            }
+                        }
+                    }
+                }
+            }
+            script type="application/ld+json" nonce=[nonce] {
+                (PreEscaped(Self::structured_data(crumbs)))
+            }
+        }
+    }
+    /// The trail as JSON-LD, safe to place inside a script.
+    fn structured_data(crumbs: &[Self]) -> String {
+        let items: Vec<_> = crumbs
+            .iter()
+            .enumerate()
+            .map(|(i, crumb)| {
+                format!(
+                    r#"{{"@type":"ListItem","position":{},"name":{},"item":{}}}"#,
+                    i + 1,
+                    json_string(crumb.name),
+                    json_string(crumb.url)
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"@context":"https://schema.org","@type":"BreadcrumbList","itemListElement":[{}]}}"#,
+            items.join(",")
+        )
+    }
+}
+
+/// `text` as a JSON string. The characters that could end the script it is placed in are escaped
+/// too, which JSON allows.
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '<' => escaped.push_str("\\u003c"),
+            '>' => escaped.push_str("\\u003e"),
+            '&' => escaped.push_str("\\u0026"),
+            '\u{2028}' => escaped.push_str("\\u2028"),
+            '\u{2029}' => escaped.push_str("\\u2029"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
 /// Information regarding the logo. (This is very simple).
 pub struct Logo<'a> {
-    /// The url to the actual image.
-    pub src: &'a str,
+    /// The picture itself.
+    pub image: LogoImage<'a>,
+    /// What the picture shows, for those who cannot see it.
+    pub alt: &'a str,
     pub href: Option<&'a str>,
 }
+/// Where the picture of a logo comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogoImage<'a> {
+    /// The url to the actual image.
+    Src(&'a str),
+    /// The markup of an svg, placed in the page as is. Must be trusted.
+    Svg(&'a str),
+}
 impl<'a> Render for Logo<'a> {
     fn render(&self) -> Markup {
+        let image = match self.image {
+            LogoImage::Src(src) => html! { img.logo-img src=(src) alt=(self.alt); },
+            LogoImage::Svg(svg) => html! {
+                span.logo-img role="img" aria-label=(self.alt) { (PreEscaped(svg)) }
+            },
+        };
         html! {
             div.logo {
                 @match self.href {
-                    Some(link) => a.logo-wrapper href=(link) {
-                        img.logo-img src=(self.src);
-                    },
-                    None => img.logo-img src=(self.src);,
+                    Some(link) => a.logo-wrapper href=(link) { (image) },
+                    None => (image),
                 }
             }
         }
@@ -108,6 +247,49 @@ impl<'a> Render for &Logo<'a> {
         (*self).render()
     }
 }
+/// Maps paths of static resources, relative to `/public`, to their content hashed counterparts so
+/// that they can be cached indefinitely.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AssetManifest {
+    /// Hashed paths keyed by the original path.
+    hashed: HashMap<String, String>,
+    /// Original paths keyed by the hashed path.
+    original: HashMap<String, String>,
+    /// The contents of resources inlined into pages, keyed by their original path.
+    inline: HashMap<String, String>,
+}
+impl AssetManifest {
+    /// Creates an empty manifest, which resolves every path to itself.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Records that `original` can also be found at `hashed`.
+    pub fn insert(&mut self, original: String, hashed: String) {
+        self.original.insert(hashed.clone(), original.clone());
+        self.hashed.insert(original, hashed);
+    }
+    /// Gets the hashed path of the resource, or the path itself if it is not in the manifest.
+    pub fn resolve<'a>(&'a self, original: &'a str) -> &'a str {
+        self.hashed.get(original).map_or(original, String::as_str)
+    }
+    /// Gets the original path of a hashed path, if it is one.
+    pub fn original(&self, hashed: &str) -> Option<&str> {
+        self.original.get(hashed).map(String::as_str)
+    }
+    /// Records `contents` as what is inlined into pages in place of `original`.
+    pub fn insert_inline(&mut self, original: String, contents: String) {
+        self.inline.insert(original, contents);
+    }
+    /// Gets the contents inlined in place of the resource, if they were read.
+    pub fn inline(&self, original: &str) -> Option<&str> {
+        self.inline.get(original).map(String::as_str)
+    }
+}
+/// Resolves the path through the manifest if present.
+fn resolve<'a>(assets: Option<&'a AssetManifest>, original: &'a str) -> &'a str {
+    assets.map_or(original, |assets| assets.resolve(original))
+}
+
 /// Information regarding the `<script>` tags to include.
 pub enum Script<'a> {
     /// Represents a script externally linked (in the `public/js` directory).
@@ -117,10 +299,7 @@ pub enum Script<'a> {
 }
 impl<'a> Render for Script<'a> {
     fn render(&self) -> Markup {
-        match self {
-            Script::External(src) => html! { script defer?[true] src={ "/public/js/"(src) } {} },
-            Script::Embedded(src) => html! { script { (PreEscaped(src)) } },
-        }
+        self.render_with(None, None)
     }
 }
 impl<'a> Render for &Script<'a> {
@@ -129,15 +308,46 @@ impl<'a> Render for &Script<'a> {
     }
 }
 impl<'a> Script<'a> {
+    /// Renders the script, linking to the hashed version of external scripts in `assets` and
+    /// placing `nonce` on embedded scripts.
+    pub fn render_with(&self, assets: Option<&AssetManifest>, nonce: Option<&str>) -> Markup {
+        match self {
+            Script::External(src) => {
+                let path = format!("js/{}", src);
+                html! { script defer?[true] src={ "/public/"(resolve(assets, &path)) } {} }
+            }
+            Script::Embedded(src) => html! { script nonce=[nonce] { (PreEscaped(src)) } },
+        }
+    }
+    /// Renders a hint to start fetching external scripts while the rest of the head is parsed.
+    /// The wasm-bindgen glue is a classic script rather than a module, so it is preloaded as one.
+    pub fn preload_with(&self, assets: Option<&AssetManifest>) -> Markup {
+        match self {
+            Script::External(src) => {
+                let path = format!("js/{}", src);
+                html! { link rel="preload" as="script" href={ "/public/"(resolve(assets, &path)) }; }
+            }
+            Script::Embedded(_) => html! {},
+        }
+    }
     /// A script for hooking in the WASM loading script
     pub fn wasm_bindgen_loader(name: &str) -> (String, String) {
+        Self::wasm_bindgen_loader_with(name, None)
+    }
+    /// A script for hooking in the WASM loading script, loading the hashed version of the WASM
+    /// binary in `assets`. The glue is left unresolved, as [`Script::render_with`] resolves it.
+    pub fn wasm_bindgen_loader_with(
+        name: &str,
+        assets: Option<&AssetManifest>,
+    ) -> (String, String) {
         let glue = format!("wasm-bindgen-glue/{}.js", name);
+        let wasm = format!("wasm/{}_bg.wasm", name);
         let load = format!(
             "\
              document.addEventListener(\
                 \"DOMContentLoaded\",\
                 function(){{\
-                    var mod = wasm_bindgen(\"/public/wasm/{}_bg.wasm\")\
+                    var mod = wasm_bindgen(\"/public/{}\")\
                         .catch(function(e) {{\
                             console.log(\"Promise received from wasm load.\");\
                             console.log(e);\
@@ -151,28 +361,76 @@ impl<'a> Script<'a> {
                 }}\
              );\
             ",
-            name
+            resolve(assets, &wasm)
         );
         (glue, load)
     }
 }
 /// Information regarding the `<style>` tags to include.
 pub enum Css<'a> {
-    /// Above the fold CSS. This get linked in from the resources directory, `/public`.
+    /// Above the fold CSS. This gets inlined from the resources directory, `/public`, as read into
+    /// the [`AssetManifest`] at startup.
     Critical { src: &'a str },
-    /// Under the fold CSS. This get linked in from the resources directory, `/public`.
+    /// Under the fold CSS. This gets preloaded from the resources directory, `/public`, and applied
+    /// once loaded, so that it never holds back the first render.
     NonCritical { src: &'a str },
+    /// CSS generated by the server, such as the styles of highlighted code. This gets copy and
+    /// pasted into the website.
+    Embedded { style: &'a str },
 }
 impl<'a> Render for Css<'a> {
     fn render(&self) -> Markup {
+        self.render_with(None, None)
+    }
+}
+/// Applies the non-critical css once it is preloaded. An inline `onload` would be refused by the
+/// content security policy, so the links are found by the marker they carry instead.
+const APPLY_PRELOADED_CSS: &str = "\
+    document.querySelectorAll(\"link[data-preloaded-css]\").forEach(function(link){\
+        link.addEventListener(\"load\",function(){link.rel=\"stylesheet\";});\
+    });\
+";
+impl<'a> Css<'a> {
+    /// The path of the stylesheet, relative to `/public`, if it is read from there.
+    pub fn path(&self) -> Option<String> {
         match self {
-            Css::NonCritical { src } => html! { link rel="stylesheet" href={
-                "/public/css/"(src)".css"
-            }{} },
+            Css::Critical { src } | Css::NonCritical { src } => Some(format!("css/{}.css", src)),
+            Css::Embedded { .. } => None,
+        }
+    }
+    /// Renders the css, preloading the hashed version of non-critical css in `assets` with a
+    /// fallback for browsers without scripts. Critical and embedded css is inlined, so it is never
+    /// hashed, but gets `nonce` instead. Critical css missing from `assets` is read from disk.
+    pub fn render_with(&self, assets: Option<&AssetManifest>, nonce: Option<&str>) -> Markup {
+        match self {
+            Css::NonCritical { src } => {
+                let path = format!("css/{}.css", src);
+                let href = format!("/public/{}", resolve(assets, &path));
+                html! {
+                    link rel="preload" as="style" href=(href) data-preloaded-css;
+                    noscript { link rel="stylesheet" href=(href); }
+                }
+            }
             Css::Critical { src } => {
-                let style = fs::read_to_string(format!("./public/css/{}.css", src).as_str())
-                    .unwrap_or_else(|src| panic!("./public/css/{}.css is missing", src));
-                html! { style { (PreEscaped(style)) } }
+                let path = format!("css/{}.css", src);
+                let style = match assets.and_then(|assets| assets.inline(&path)) {
+                    Some(style) => Cow::Borrowed(style),
+                    None => Cow::Owned(
+                        fs::read_to_string(format!("./public/{}", path))
+                            .unwrap_or_else(|_| panic!("./public/{} is missing", path)),
+                    ),
+                };
+                html! { style nonce=[nonce] { (PreEscaped(style)) } }
+            }
+            Css::Embedded { style } => html! { style nonce=[nonce] { (PreEscaped(style)) } },
+        }
+    }
+    /// Renders the script applying the non-critical css among `css`, if there is any.
+    pub fn render_loader(css: &[Css], nonce: Option<&str>) -> Markup {
+        let any_preloaded = css.iter().any(|css| matches!(css, Css::NonCritical { .. }));
+        html! {
+            @if any_preloaded {
+                script nonce=[nonce] { (PreEscaped(APPLY_PRELOADED_CSS)) }
             }
         }
     }
@@ -310,15 +568,28 @@ impl<'a> Render for &Copyright<'a> {
     }
 }
 /// An entry in the menu.
-pub struct MenuItem<'a> {
-    /// Text to display.
-    pub text: &'a str,
-    /// Where the entry links to, if it links to one.
-    pub link: Option<&'a str>,
-    /// A child menu, if one exists.
-    pub children: Option<&'a Menu<'a>>,
+pub enum MenuItem<'a> {
+    /// An entry with text, and possibly a link and a menu of its own.
+    Link {
+        /// Text to display.
+        text: &'a str,
+        /// Where the entry links to, if it links to one.
+        link: Option<&'a str>,
+        /// A child menu, if one exists.
+        children: Option<&'a Menu<'a>>,
+    },
+    /// A line between groups of entries.
+    Divider,
 }
 impl<'a> MenuItem<'a> {
+    /// An entry linking to `link`, without children.
+    pub fn link(text: &'a str, link: &'a str) -> Self {
+        MenuItem::Link {
+            text,
+            link: Some(link),
+            children: None,
+        }
+    }
     /// Render a link to [`Markup`] if present.
     fn render_possible_link(link: Option<&str>, text: &str) -> Markup {
         html! {
@@ -329,39 +600,106 @@ impl<'a> MenuItem<'a> {
             }
         }
     }
-}
-impl<'a> Render for MenuItem<'a> {
-    fn render(&self) -> Markup {
-        html! {
-            li {
-                (MenuItem::render_possible_link(self.link, self.text))
-                @if let Some(children) = self.children {
-                    (children)
+    /// Every link of the entry and the entries under it.
+    fn links(&self) -> Vec<&'a str> {
+        match self {
+            MenuItem::Link { link, children, .. } => link
+                .iter()
+                .copied()
+                .chain(children.iter().flat_map(|children| children.links()))
+                .collect(),
+            MenuItem::Divider => vec![],
+        }
+    }
+    /// Render to [`Markup`], marking the link to `active` as the current page.
+    fn render_active(&self, active: Option<&str>) -> Markup {
+        match self {
+            MenuItem::Link {
+                text,
+                link,
+                children,
+            } => {
+                let is_active = link.is_some() && *link == active;
+                let class = match (is_active, children.is_some()) {
+                    (true, true) => Some("active has-children"),
+                    (true, false) => Some("active"),
+                    (false, true) => Some("has-children"),
+                    (false, false) => None,
+                };
+                html! {
+                    li class=[class] {
+                        @if let (Some(link), true) = (link, is_active) {
+                            a.active href=(link) aria-current="page" { (text) }
+                        } @else {
+                            (MenuItem::render_possible_link(*link, text))
+                        }
+                        @if let Some(children) = children {
+                            ul.submenu {
+                                @for item in children.0.iter() {
+                                    (item.render_active(active))
+                                }
+                            }
+                        }
+                    }
                 }
             }
+            MenuItem::Divider => html! { li.menu-divider role="separator" {} },
         }
     }
 }
+impl<'a> Render for MenuItem<'a> {
+    fn render(&self) -> Markup {
+        self.render_active(None)
+    }
+}
 impl<'a> Render for &MenuItem<'a> {
     fn render(&self) -> Markup {
         (*self).render()
     }
 }
-/// A newtype for a list of [`MenuItem`](crate::data::MenuItem)s.
+/// The link among `links` going to the page at `path`, or else to the section it is in, the
+/// innermost section if several are linked. The root is only matched by itself, since every page
+/// is under it.
+pub fn current_link<'a>(links: impl IntoIterator<Item = &'a str>, path: &str) -> Option<&'a str> {
+    let path = path.trim_end_matches('/');
+    links
+        .into_iter()
+        .filter(|link| {
+            let link = link.trim_end_matches('/');
+            let in_section =
+                !link.is_empty() && path.starts_with(link) && path[link.len()..].starts_with('/');
+            path == link || in_section
+        })
+        .max_by_key(|link| link.trim_end_matches('/').len())
+}
+/// A newtype for a list of [`MenuItem`](crate::data::MenuItem)s, rendered with its children as
+/// nested lists.
 pub struct Menu<'a>(pub &'a [MenuItem<'a>]);
-impl<'a> Render for Menu<'a> {
-    fn render(&self) -> Markup {
+impl<'a> Menu<'a> {
+    /// Every link in the menu, those of nested menus included.
+    pub fn links(&self) -> Vec<&'a str> {
+        self.0.iter().flat_map(MenuItem::links).collect()
+    }
+    /// Render to [`Markup`], marking the link to `current`, or else to the section it is in, as
+    /// the current page.
+    pub fn render_at(&self, current: Option<&str>) -> Markup {
+        let active = current.and_then(|current| current_link(self.links(), current));
         html! {
-            nav.menu {
+            nav.menu role="navigation" {
                 ul {
                     @for item in self.0.iter() {
-                        (item)
+                        (item.render_active(active))
                     }
                 }
             }
         }
     }
 }
+impl<'a> Render for Menu<'a> {
+    fn render(&self) -> Markup {
+        self.render_at(None)
+    }
+}
 impl<'a> Render for &Menu<'a> {
     fn render(&self) -> Markup {
         (*self).render()
@@ -371,4 +709,9 @@ impl<'a> Menu<'a> {
     pub fn into_string(self) -> String {
         self.render().into_string()
     }
+    /// Render to a string, marking the link to `current`, or else to the section it is in, as the
+    /// current page.
+    pub fn into_string_at(self, current: Option<&str>) -> String {
+        self.render_at(current).into_string()
+    }
 }
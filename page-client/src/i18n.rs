@@ -0,0 +1,175 @@
+//! The text shown to visitors, in each language the site is available in. Text is looked up by
+//! key, and templates name the arguments filled into them in braces, such as `{name}`.
+
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashSet};
+
+mod en;
+mod es;
+
+/// A language the site is available in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Es,
+}
+impl Default for Locale {
+    fn default() -> Self {
+        Self::En
+    }
+}
+impl Locale {
+    /// Every locale, in the order they are offered in.
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Es];
+
+    /// The language tag of the locale, as used by `lang` attributes.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
+        }
+    }
+    /// The name of the locale, in its own language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::Es => "Español",
+        }
+    }
+    /// The locale for a language tag such as `es-MX`. Only the language is looked at.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(|c| c == '-' || c == '_').next()?;
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|locale| locale.tag().eq_ignore_ascii_case(language))
+    }
+    /// Picks the locale most preferred by a list of `Accept-Language` header values, or the
+    /// default one if none of the languages accepted are available.
+    pub fn negotiate<'a>(accept_language: impl Iterator<Item = &'a str>) -> Self {
+        let mut best: Option<(Self, f32)> = None;
+        for range in accept_language.flat_map(|value| value.split(',')) {
+            let mut parts = range.split(';').map(str::trim);
+            let tag = parts.next().unwrap_or("");
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.);
+            if quality <= 0. {
+                continue;
+            }
+            // Earlier languages win ties, so only a strictly better one replaces the best so far.
+            if let Some(locale) = Self::from_tag(tag) {
+                if best.map_or(true, |(_, best)| quality > best) {
+                    best = Some((locale, quality));
+                }
+            }
+        }
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::En => en::CATALOG,
+            Self::Es => es::CATALOG,
+        }
+    }
+}
+
+thread_local! {
+    /// Keys already reported missing, so that each is only logged once.
+    static REPORTED: RefCell<HashSet<(Locale, String)>> = RefCell::new(HashSet::new());
+}
+
+/// The template for `key`, falling back to English if the locale lacks it.
+fn template(locale: Locale, key: &str) -> Option<&'static str> {
+    let find = |locale: Locale| {
+        locale
+            .catalog()
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, template)| *template)
+    };
+    find(locale).or_else(|| {
+        let is_new = REPORTED.with(|reported| reported.borrow_mut().insert((locale, key.to_owned())));
+        if is_new {
+            log::warn!("No {} text for {:?}.", locale.tag(), key);
+        }
+        find(Locale::En)
+    })
+}
+
+/// The text for `key` in `locale`, with `args` filled in. Keys missing from English as well come
+/// back as is, so that they stand out.
+pub fn translate(locale: Locale, key: &str, args: &[(&str, String)]) -> String {
+    let template = match template(locale, key) {
+        Some(template) => template,
+        None => return key.to_owned(),
+    };
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = match after.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let name = &after[..end];
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => text.push_str(value),
+            None => text.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arguments_are_filled_in() {
+        assert_eq!(
+            translate(Locale::En, "editor.remove_tag", &[("name", "rust".to_owned())]),
+            "Remove tag rust",
+        );
+        assert_eq!(
+            translate(Locale::Es, "editor.remove_tag", &[("name", "rust".to_owned())]),
+            "Quitar la etiqueta rust",
+        );
+        // Arguments that were not passed are left in the text rather than dropped.
+        assert_eq!(translate(Locale::En, "editor.remove_tag", &[]), "Remove tag {name}");
+    }
+
+    #[test]
+    fn keys_missing_everywhere_come_back_as_is() {
+        assert_eq!(translate(Locale::Es, "test.nowhere", &[]), "test.nowhere");
+        assert_eq!(translate(Locale::En, "test.nowhere", &[]), "test.nowhere");
+    }
+
+    #[test]
+    fn every_key_has_english_text() {
+        for locale in Locale::ALL.iter() {
+            for (key, _) in locale.catalog() {
+                assert!(
+                    template(Locale::En, key).is_some(),
+                    "{} has text for {:?}, which English lacks.",
+                    locale.tag(),
+                    key,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn accept_language_is_negotiated() {
+        assert_eq!(Locale::negotiate(std::iter::empty()), Locale::En);
+        assert_eq!(Locale::negotiate(std::iter::once("es-MX,es;q=0.9,en;q=0.8")), Locale::Es);
+        assert_eq!(Locale::negotiate(std::iter::once("fr-FR,en;q=0.5,es;q=0.7")), Locale::Es);
+        assert_eq!(Locale::negotiate(std::iter::once("es;q=0,en")), Locale::En);
+        assert_eq!(Locale::negotiate(std::iter::once("fr")), Locale::En);
+    }
+}
@@ -0,0 +1,171 @@
+//! The English text, which other locales fall back to.
+
+pub const CATALOG: &[(&str, &str)] = &[
+    // Menu
+    ("menu.home", "Home"),
+    ("menu.blog", "Blog"),
+    ("menu.create_post", "Create Post"),
+    ("menu.settings", "Settings"),
+    ("menu.profile", "Profile"),
+    ("menu.login", "Login"),
+    ("menu.logout", "Logout"),
+    // Shared
+    ("shell.loading", "Loading. Please wait..."),
+    ("shell.maintenance", "The blog is down for maintenance."),
+    ("site_mode.read_only", "The blog is read only for now. Changes will not be saved."),
+    ("site_mode.maintenance", "The blog is down for maintenance."),
+    ("common.loading", "Loading!"),
+    ("common.refreshing", "Refreshing…"),
+    ("common.try_again", "Try again"),
+    ("common.not_found", "There is nothing here. It may have been moved or deleted."),
+    ("common.back_to_posts", "Back to the posts"),
+    ("common.cancel", "Cancel"),
+    ("pager.previous", "Previous"),
+    ("pager.next", "Next"),
+    ("pager.position", "{first}–{last} of {total}"),
+    ("locale.label", "Language"),
+    ("theme.switch", "Switch to the {theme} theme"),
+    ("theme.light", "light"),
+    ("theme.dark", "dark"),
+    ("theme.light_mode", "Light mode"),
+    ("theme.dark_mode", "Dark mode"),
+    ("unsaved.prompt", "This post has unsaved changes."),
+    ("unsaved.stay", "Stay"),
+    ("unsaved.discard", "Discard"),
+    ("unsaved.save_and_go", "Save and go"),
+    ("offline.notice", "Offline — changes saved locally. Saving again once the server can be reached."),
+    // Toasts
+    ("toast.dismiss", "Dismiss"),
+    ("toast.log_in_again", "Log in again"),
+    ("failure.network", "Could not {attempt}: the server could not be reached. Check your connection and try again."),
+    ("failure.unauthorized", "Could not {attempt}: you are no longer logged in."),
+    ("failure.forbidden", "Could not {attempt}: you are not allowed to do that."),
+    ("failure.not_found", "Could not {attempt}: it does not exist."),
+    ("failure.conflict", "Could not {attempt}: something with the same name already exists."),
+    ("failure.invalid", "Could not {attempt}: it refers to something that no longer exists."),
+    ("failure.too_large", "Could not {attempt}: it is larger than the server accepts."),
+    ("failure.unavailable", "Could not {attempt}: the server is busy. Try again in a few seconds."),
+    ("failure.server", "Could not {attempt}: the server ran into a problem ({code}). Try again later."),
+    ("failure.other", "Could not {attempt}: the server sent something unexpected."),
+    // What failed, filled into the failures above
+    ("attempt.load_posts", "load the posts"),
+    ("attempt.load_more_posts", "load more posts"),
+    ("attempt.load_post", "load the post"),
+    ("attempt.load_series", "load the series"),
+    ("attempt.load_tags", "load the tags of the post"),
+    ("attempt.create_post", "create the post"),
+    ("attempt.save_post", "save the post"),
+    ("attempt.save_tags", "save the tags, though the rest of the post was saved"),
+    ("attempt.save_authors", "save the authors, though the rest of the post was saved"),
+    ("attempt.publish_post", "publish the post"),
+    ("attempt.archive_post", "archive the post"),
+    ("attempt.delete_post", "delete the post"),
+    ("attempt.react", "react to the post"),
+    ("attempt.load_stats", "load the site stats"),
+    ("attempt.load_permissions", "load your permissions"),
+    ("attempt.revoke_permission", "revoke the permission"),
+    ("attempt.delete_account", "delete your account"),
+    ("attempt.change_password", "change your password"),
+    ("attempt.add_sign_in", "add a password to your account"),
+    ("attempt.remove_sign_in", "remove the password from your account"),
+    ("attempt.save_name", "save your name"),
+    ("attempt.save_post_defaults", "save your defaults for new posts"),
+    ("attempt.save_preferences", "save your preferences to your account"),
+    ("attempt.create_account", "create the account"),
+    ("attempt.set_password", "set the password"),
+    ("attempt.log_in", "log in"),
+    ("attempt.log_out", "log out"),
+    ("not_found.posts", "Posts not found"),
+    ("not_found.post", "Post not found"),
+    ("not_found.series", "Series not found"),
+    // Editor
+    ("editor.title", "Title"),
+    ("editor.create_at_slug", "Start a new post at /blog/posts/{slug}"),
+    ("editor.title_count", "{count} of {max} characters"),
+    ("editor.body", "Body"),
+    ("editor.body_placeholder", "Write your post here!"),
+    ("editor.tags", "Tags"),
+    ("editor.add_tag", "Add a tag"),
+    ("editor.remove_tag", "Remove tag {name}"),
+    ("editor.loading_tags", "Loading tags..."),
+    ("editor.preview", "Preview"),
+    ("editor.hide_preview", "Hide preview"),
+    ("editor.authors", "Authors"),
+    ("editor.add_author", "Credit someone"),
+    ("editor.remove_author", "Stop crediting {name}"),
+    ("editor.author_role", "Role of {name}"),
+    ("editor.role.author", "Author"),
+    ("editor.role.editor", "Editor"),
+    ("editor.credited_to_you", "You are credited as the author."),
+    ("editor.credit_yourself", "Credit yourself"),
+    ("editor.authors_restricted", "You can only credit yourself and those already credited."),
+    ("editor.seo", "Search and sharing"),
+    ("editor.seo.description", "Description"),
+    ("editor.seo.description_placeholder", "Left empty, the start of the post is used."),
+    ("editor.seo.count", "{count} of {shown} characters"),
+    ("editor.seo.too_long", "Search results may cut the rest off."),
+    ("editor.seo.image", "Image"),
+    ("editor.seo.image_placeholder", "https://... or /public/..., left empty for the first image of the post"),
+    ("editor.publish_confirm.title", "Publish this post?"),
+    ("editor.publish_confirm.at", "It will be published at {path}."),
+    ("editor.publish_confirm.at_id", "No slug is set, so it will be published at {path}."),
+    ("editor.publish_confirm.at_new_id", "No slug is set, so it will be published at its id once created."),
+    ("editor.publish_confirm.byline", "By: {names}"),
+    ("editor.publish_confirm.tags", "Tagged: {names}"),
+    ("editor.publish_confirm.none", "none"),
+    ("editor.publish_confirm.visibility", "It will be public to everyone as soon as it is published."),
+    ("editor.publish_confirm.confirm", "Publish"),
+    ("post_actions.menu", "More actions"),
+    ("post_actions.download", "Download"),
+    ("post_actions.archive", "Archive"),
+    ("post_actions.delete", "Delete"),
+    ("post_actions.archive_confirm.title", "Archive “{title}”?"),
+    ("post_actions.archive_confirm.body", "It will stay at its url, marked as no longer kept up to date, and leave the listing."),
+    ("post_actions.delete_confirm.title", "Delete “{title}”?"),
+    ("post_actions.delete_confirm.body", "It will be taken down right away, and purged for good later on."),
+    ("post_actions.archived", "Archived the post."),
+    ("post_actions.deleted", "Deleted the post."),
+    ("listing.tabs", "Posts by state"),
+    ("listing.tab.published", "Published"),
+    ("listing.tab.drafts", "Drafts"),
+    ("listing.tab.archived", "Archived"),
+    ("listing.empty.published", "Nothing is published yet."),
+    ("listing.empty.drafts", "No drafts yet."),
+    ("listing.start_writing", "Start writing"),
+    ("listing.empty.archived", "No posts are archived."),
+    ("listing.load_more", "Load more"),
+    ("listing.end", "You've reached the end."),
+    ("listing.reactions", "Reactions: {count}"),
+    // Viewer
+    ("viewer.by", "By"),
+    ("viewer.edited_by", "edited by"),
+    ("viewer.more_by_author", "More posts by this author"),
+    ("viewer.series_part", "Part {part} of {parts} of {title}"),
+    ("viewer.prev_part", "Previous: {title}"),
+    ("viewer.next_part", "Next: {title}"),
+    ("viewer.contents", "Contents"),
+    ("viewer.archived", "This post is archived and may be out of date."),
+    ("viewer.draft", "This post is not published yet, and only those who may edit it can see it."),
+    ("viewer.reactions", "Reactions"),
+    // Reactions
+    ("reactions.like", "Like ({count})"),
+    ("reactions.heart", "Love ({count})"),
+    ("reactions.rocket", "Rocket ({count})"),
+    ("reactions.eyes", "Watching ({count})"),
+    // Series
+    ("series.empty", "There are no posts in this series yet."),
+    ("series.draft", "draft"),
+    // Mail
+    ("mail.greeting", "Hi {name},"),
+    ("mail.signature", "— {site}"),
+    ("mail.password_reset.subject", "Reset your password"),
+    ("mail.password_reset.body", "Someone asked to reset the password of your account. The link below lets you choose a new one."),
+    ("mail.password_reset.ignore", "If it was not you, ignore this mail. Your password stays as it is."),
+    ("mail.password_reset.action", "Choose a new password"),
+    ("mail.email_verification.subject", "Confirm your email address"),
+    ("mail.email_verification.body", "Confirm that this email address is yours to finish setting up your account."),
+    ("mail.email_verification.action", "Confirm email address"),
+    ("mail.comment_moderation.subject", "A comment on \"{title}\" is waiting for you"),
+    ("mail.comment_moderation.body", "A new comment on \"{title}\" needs to be approved before it is shown."),
+    ("mail.comment_moderation.action", "Review the comment"),
+];
@@ -0,0 +1,171 @@
+//! The Spanish text.
+
+pub const CATALOG: &[(&str, &str)] = &[
+    // Menu
+    ("menu.home", "Inicio"),
+    ("menu.blog", "Blog"),
+    ("menu.create_post", "Nueva entrada"),
+    ("menu.settings", "Ajustes"),
+    ("menu.profile", "Perfil"),
+    ("menu.login", "Iniciar sesión"),
+    ("menu.logout", "Cerrar sesión"),
+    // Shared
+    ("shell.loading", "Cargando. Espera un momento..."),
+    ("shell.maintenance", "El blog está en mantenimiento."),
+    ("site_mode.read_only", "Por ahora el blog es de solo lectura. Los cambios no se guardarán."),
+    ("site_mode.maintenance", "El blog está en mantenimiento."),
+    ("common.loading", "¡Cargando!"),
+    ("common.refreshing", "Actualizando…"),
+    ("common.try_again", "Reintentar"),
+    ("common.not_found", "No hay nada aquí. Puede que se haya movido o borrado."),
+    ("common.back_to_posts", "Volver a las entradas"),
+    ("common.cancel", "Cancelar"),
+    ("pager.previous", "Anterior"),
+    ("pager.next", "Siguiente"),
+    ("pager.position", "{first}–{last} de {total}"),
+    ("locale.label", "Idioma"),
+    ("theme.switch", "Cambiar al tema {theme}"),
+    ("theme.light", "claro"),
+    ("theme.dark", "oscuro"),
+    ("theme.light_mode", "Modo claro"),
+    ("theme.dark_mode", "Modo oscuro"),
+    ("unsaved.prompt", "Esta entrada tiene cambios sin guardar."),
+    ("unsaved.stay", "Quedarse"),
+    ("unsaved.discard", "Descartar"),
+    ("unsaved.save_and_go", "Guardar y salir"),
+    ("offline.notice", "Sin conexión — los cambios se guardaron localmente. Se volverán a guardar cuando se pueda acceder al servidor."),
+    // Toasts
+    ("toast.dismiss", "Cerrar"),
+    ("toast.log_in_again", "Volver a iniciar sesión"),
+    ("failure.network", "No se pudo {attempt}: no se pudo acceder al servidor. Comprueba tu conexión e inténtalo de nuevo."),
+    ("failure.unauthorized", "No se pudo {attempt}: ya no has iniciado sesión."),
+    ("failure.forbidden", "No se pudo {attempt}: no tienes permiso para hacerlo."),
+    ("failure.not_found", "No se pudo {attempt}: no existe."),
+    ("failure.conflict", "No se pudo {attempt}: ya existe algo con el mismo nombre."),
+    ("failure.invalid", "No se pudo {attempt}: hace referencia a algo que ya no existe."),
+    ("failure.too_large", "No se pudo {attempt}: es más grande de lo que acepta el servidor."),
+    ("failure.unavailable", "No se pudo {attempt}: el servidor está ocupado. Inténtalo de nuevo en unos segundos."),
+    ("failure.server", "No se pudo {attempt}: el servidor tuvo un problema ({code}). Inténtalo más tarde."),
+    ("failure.other", "No se pudo {attempt}: el servidor envió algo inesperado."),
+    // What failed, filled into the failures above
+    ("attempt.load_posts", "cargar las entradas"),
+    ("attempt.load_more_posts", "cargar más entradas"),
+    ("attempt.load_post", "cargar la entrada"),
+    ("attempt.load_series", "cargar la serie"),
+    ("attempt.load_tags", "cargar las etiquetas de la entrada"),
+    ("attempt.create_post", "crear la entrada"),
+    ("attempt.save_post", "guardar la entrada"),
+    ("attempt.save_tags", "guardar las etiquetas, aunque el resto de la entrada se guardó"),
+    ("attempt.save_authors", "guardar los autores, aunque el resto de la entrada se guardó"),
+    ("attempt.publish_post", "publicar la entrada"),
+    ("attempt.archive_post", "archivar la entrada"),
+    ("attempt.delete_post", "eliminar la entrada"),
+    ("attempt.react", "reaccionar a la entrada"),
+    ("attempt.load_stats", "cargar las estadísticas del sitio"),
+    ("attempt.load_permissions", "cargar tus permisos"),
+    ("attempt.revoke_permission", "revocar el permiso"),
+    ("attempt.delete_account", "borrar tu cuenta"),
+    ("attempt.change_password", "cambiar tu contraseña"),
+    ("attempt.add_sign_in", "añadir una contraseña a tu cuenta"),
+    ("attempt.remove_sign_in", "quitar la contraseña de tu cuenta"),
+    ("attempt.save_name", "guardar tu nombre"),
+    ("attempt.save_post_defaults", "guardar tus valores predeterminados para publicaciones nuevas"),
+    ("attempt.save_preferences", "guardar tus preferencias en tu cuenta"),
+    ("attempt.create_account", "crear la cuenta"),
+    ("attempt.set_password", "establecer la contraseña"),
+    ("attempt.log_in", "iniciar sesión"),
+    ("attempt.log_out", "cerrar sesión"),
+    ("not_found.posts", "No se encontraron entradas"),
+    ("not_found.post", "No se encontró la entrada"),
+    ("not_found.series", "No se encontró la serie"),
+    // Editor
+    ("editor.title", "Título"),
+    ("editor.create_at_slug", "Empezar una entrada nueva en /blog/posts/{slug}"),
+    ("editor.title_count", "{count} de {max} caracteres"),
+    ("editor.body", "Contenido"),
+    ("editor.body_placeholder", "¡Escribe tu entrada aquí!"),
+    ("editor.tags", "Etiquetas"),
+    ("editor.add_tag", "Añadir una etiqueta"),
+    ("editor.remove_tag", "Quitar la etiqueta {name}"),
+    ("editor.loading_tags", "Cargando etiquetas..."),
+    ("editor.preview", "Vista previa"),
+    ("editor.hide_preview", "Ocultar vista previa"),
+    ("editor.authors", "Autores"),
+    ("editor.add_author", "Acreditar a alguien"),
+    ("editor.remove_author", "Dejar de acreditar a {name}"),
+    ("editor.author_role", "Papel de {name}"),
+    ("editor.role.author", "Autor"),
+    ("editor.role.editor", "Editor"),
+    ("editor.credited_to_you", "Se te acredita como autor."),
+    ("editor.credit_yourself", "Acreditarte"),
+    ("editor.authors_restricted", "Solo puedes acreditarte a ti y a quienes ya están acreditados."),
+    ("editor.seo", "Búsqueda y enlaces"),
+    ("editor.seo.description", "Descripción"),
+    ("editor.seo.description_placeholder", "Si se deja vacía, se usa el inicio de la publicación."),
+    ("editor.seo.count", "{count} de {shown} caracteres"),
+    ("editor.seo.too_long", "Los resultados de búsqueda pueden cortar el resto."),
+    ("editor.seo.image", "Imagen"),
+    ("editor.seo.image_placeholder", "https://... o /public/..., vacía para la primera imagen de la publicación"),
+    ("editor.publish_confirm.title", "¿Publicar esta entrada?"),
+    ("editor.publish_confirm.at", "Se publicará en {path}."),
+    ("editor.publish_confirm.at_id", "No tiene slug, así que se publicará en {path}."),
+    ("editor.publish_confirm.at_new_id", "No tiene slug, así que se publicará en su id una vez creada."),
+    ("editor.publish_confirm.byline", "Por: {names}"),
+    ("editor.publish_confirm.tags", "Etiquetas: {names}"),
+    ("editor.publish_confirm.none", "ninguna"),
+    ("editor.publish_confirm.visibility", "Será pública para todos en cuanto se publique."),
+    ("editor.publish_confirm.confirm", "Publicar"),
+    ("post_actions.menu", "Más acciones"),
+    ("post_actions.download", "Descargar"),
+    ("post_actions.archive", "Archivar"),
+    ("post_actions.delete", "Eliminar"),
+    ("post_actions.archive_confirm.title", "¿Archivar «{title}»?"),
+    ("post_actions.archive_confirm.body", "Seguirá en su url, marcada como ya no actualizada, y saldrá del listado."),
+    ("post_actions.delete_confirm.title", "¿Eliminar «{title}»?"),
+    ("post_actions.delete_confirm.body", "Se retirará de inmediato y se purgará definitivamente más adelante."),
+    ("post_actions.archived", "Se archivó la entrada."),
+    ("post_actions.deleted", "Se eliminó la entrada."),
+    ("listing.tabs", "Entradas por estado"),
+    ("listing.tab.published", "Publicadas"),
+    ("listing.tab.drafts", "Borradores"),
+    ("listing.tab.archived", "Archivadas"),
+    ("listing.empty.published", "Aún no hay nada publicado."),
+    ("listing.empty.drafts", "Aún no hay borradores."),
+    ("listing.start_writing", "Empieza a escribir"),
+    ("listing.empty.archived", "No hay entradas archivadas."),
+    ("listing.load_more", "Cargar más"),
+    ("listing.end", "Has llegado al final."),
+    ("listing.reactions", "Reacciones: {count}"),
+    // Viewer
+    ("viewer.by", "Por"),
+    ("viewer.edited_by", "editado por"),
+    ("viewer.more_by_author", "Más entradas de este autor"),
+    ("viewer.series_part", "Parte {part} de {parts} de {title}"),
+    ("viewer.prev_part", "Anterior: {title}"),
+    ("viewer.next_part", "Siguiente: {title}"),
+    ("viewer.contents", "Contenido"),
+    ("viewer.archived", "Esta entrada está archivada y puede estar desactualizada."),
+    ("viewer.draft", "Esta entrada aún no está publicada, y solo quienes pueden editarla la ven."),
+    ("viewer.reactions", "Reacciones"),
+    // Reactions
+    ("reactions.like", "Me gusta ({count})"),
+    ("reactions.heart", "Me encanta ({count})"),
+    ("reactions.rocket", "Cohete ({count})"),
+    ("reactions.eyes", "Siguiendo ({count})"),
+    // Series
+    ("series.empty", "Todavía no hay entradas en esta serie."),
+    ("series.draft", "borrador"),
+    // Mail
+    ("mail.greeting", "Hola, {name}:"),
+    ("mail.signature", "— {site}"),
+    ("mail.password_reset.subject", "Restablece tu contraseña"),
+    ("mail.password_reset.body", "Alguien pidió restablecer la contraseña de tu cuenta. Con el enlace de abajo puedes elegir una nueva."),
+    ("mail.password_reset.ignore", "Si no fuiste tú, ignora este correo. Tu contraseña no cambiará."),
+    ("mail.password_reset.action", "Elegir una contraseña nueva"),
+    ("mail.email_verification.subject", "Confirma tu correo electrónico"),
+    ("mail.email_verification.body", "Confirma que este correo electrónico es tuyo para terminar de configurar tu cuenta."),
+    ("mail.email_verification.action", "Confirmar correo electrónico"),
+    ("mail.comment_moderation.subject", "Un comentario en \"{title}\" te espera"),
+    ("mail.comment_moderation.body", "Un comentario nuevo en \"{title}\" debe aprobarse antes de mostrarse."),
+    ("mail.comment_moderation.action", "Revisar el comentario"),
+];
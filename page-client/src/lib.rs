@@ -1,6 +1,6 @@
-#![feature(proc_macro_hygiene, decl_macro)]
-
 //! Provides a few utility functions to generate parts of static webpages at compile time.
 
 pub mod data;
+pub mod i18n;
+pub mod markdown;
 pub mod partials;
@@ -0,0 +1,351 @@
+//! Renders the markdown posts are written in, the same way on the server and in the browser, so
+//! that the ids the server hands out for headings are the ones the rendered post carries.
+//!
+//! On top of [CommonMark](https://commonmark.org), posts may use tables, strikethrough, and
+//! footnotes (`[^1]`). Headings get ids slugified from their text, deduplicated in the order they
+//! appear (`#intro`, `#intro-1`), along with a link to themselves. The html is sanitized before it
+//! is handed back, keeping only the ids and classes added here.
+//!
+//! Fenced code blocks are laid out as `<pre class="code"><code class="language-rust">`. With the
+//! `highlight` feature, the code of languages that are known is [highlighted](highlight) inside
+//! them. Without it, as in the browser, the code is left plain in the same markup.
+
+#[cfg(feature = "highlight")]
+pub mod highlight;
+
+use pulldown_cmark::{escape::escape_html, html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A heading of a post, as listed in its table of contents.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Heading {
+    /// 1 for the largest headings, through 6.
+    pub level: u8,
+    /// The text of the heading, without any formatting.
+    pub text: String,
+    /// The id of the heading in the rendered html, without the `#`.
+    pub id: String,
+}
+
+/// Markdown rendered to html, along with its headings in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendered {
+    pub html: String,
+    pub outline: Vec<Heading>,
+}
+
+/// Hands out ids, each at most once.
+#[derive(Default)]
+struct Ids(HashSet<String>);
+impl Ids {
+    /// `base` if it is free, or else `base` with the first number after it that is.
+    fn claim(&mut self, base: &str) -> String {
+        let mut id = base.to_owned();
+        let mut n = 0;
+        while self.0.contains(&id) {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        self.0.insert(id.clone());
+        id
+    }
+}
+
+/// Lowercases `text`, keeping only letters and digits and joining words with dashes.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "section".to_owned()
+    } else {
+        slug.to_owned()
+    }
+}
+
+/// A footnote, numbered by where it is first referred to.
+struct Footnote {
+    number: usize,
+    /// The id of the note itself.
+    id: String,
+    /// The id of the first reference to the note, which the note links back to.
+    ref_id: String,
+}
+
+/// Numbers the footnotes of a post and hands out their ids ahead of the headings, so that the
+/// references and the notes agree on them wherever the notes are written.
+fn footnotes<'a>(events: &[Event<'a>], ids: &mut Ids) -> HashMap<CowStr<'a>, Footnote> {
+    let mut notes = HashMap::new();
+    let references = events.iter().filter_map(|event| match event {
+        Event::FootnoteReference(name) => Some(name),
+        Event::Start(Tag::FootnoteDefinition(name)) => Some(name),
+        _ => None,
+    });
+    for name in references {
+        if !notes.contains_key(name) {
+            let slug = slugify(name);
+            let note = Footnote {
+                number: notes.len() + 1,
+                id: ids.claim(&format!("fn-{}", slug)),
+                ref_id: ids.claim(&format!("fnref-{}", slug)),
+            };
+            notes.insert(name.clone(), note);
+        }
+    }
+    notes
+}
+
+/// The text of a heading, without any formatting.
+fn plain_text(events: &[Event]) -> String {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(&**text),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The language a code block is tagged with, if it is tagged with one that can be put in a class.
+fn language_of(kind: &CodeBlockKind) -> Option<String> {
+    let info = match kind {
+        CodeBlockKind::Fenced(info) => info,
+        CodeBlockKind::Indented => return None,
+    };
+    let language = info.split_whitespace().next()?;
+    let fits = language
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "+#-_.".contains(c));
+    if fits {
+        Some(language.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Lays out a code block, highlighting it if possible.
+fn code_block(language: Option<&str>, code: &str) -> String {
+    let class = language.map_or_else(String::new, |l| format!(" class=\"language-{}\"", l));
+    #[cfg(feature = "highlight")]
+    {
+        if let Some(highlighted) = language.and_then(|l| highlight::highlight(code, l)) {
+            return format!("<pre class=\"code\"><code{}>{}</code></pre>\n", class, highlighted);
+        }
+    }
+    let mut escaped = String::with_capacity(code.len());
+    escape_html(&mut escaped, code).expect("Writing to a string cannot fail.");
+    format!("<pre class=\"code\"><code{}>{}</code></pre>\n", class, escaped)
+}
+
+/// Strips everything that could run scripts or restyle the page from `html`, keeping the ids and
+/// classes of the headings and footnotes.
+fn sanitize(html: &str) -> String {
+    let headings = ["h1", "h2", "h3", "h4", "h5", "h6"];
+    let mut builder = ammonia::Builder::default();
+    for heading in headings.iter() {
+        builder.add_tag_attributes(*heading, &["id"]);
+    }
+    builder
+        .add_tag_attributes("a", &["class", "aria-hidden"])
+        .add_tag_attributes("sup", &["id", "class"])
+        .add_tag_attributes("div", &["id", "class"])
+        .add_tag_attributes("code", &["class"])
+        .add_tag_attributes("pre", &["class"])
+        .add_tag_attributes("span", &["class"])
+        .clean(html)
+        .to_string()
+}
+
+/// Renders `source` to sanitized html, and lists its headings.
+pub fn render_markdown(source: &str) -> Rendered {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_FOOTNOTES;
+    let parsed: Vec<_> = Parser::new_ext(source, options).collect();
+    let mut ids = Ids::default();
+    let notes = footnotes(&parsed, &mut ids);
+    let mut outline = vec![];
+    let mut events = Vec::with_capacity(parsed.len());
+    let mut heading: Option<Vec<Event>> = None;
+    let mut code: Option<(Option<String>, String)> = None;
+    for event in parsed {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => code = Some((language_of(&kind), String::new())),
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, buffer)) = code.as_mut() {
+                    buffer.push_str(&text);
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                let (language, text) = code.take().unwrap_or_default();
+                events.push(Event::Html(code_block(language.as_deref(), &text).into()));
+            }
+            Event::Start(Tag::Heading(_)) => heading = Some(vec![]),
+            Event::End(Tag::Heading(level)) => {
+                let inner = heading.take().unwrap_or_default();
+                let text = plain_text(&inner);
+                let id = ids.claim(&slugify(&text));
+                events.push(Event::Html(format!("<h{} id=\"{}\">", level, id).into()));
+                events.extend(inner);
+                events.push(Event::Html(
+                    format!(
+                        "<a class=\"heading-anchor\" href=\"#{}\" aria-hidden=\"true\">#</a></h{}>",
+                        id, level
+                    )
+                    .into(),
+                ));
+                outline.push(Heading {
+                    level: level as u8,
+                    text,
+                    id,
+                });
+            }
+            Event::FootnoteReference(name) => {
+                let note = &notes[&name];
+                heading.as_mut().unwrap_or(&mut events).push(Event::Html(
+                    format!(
+                        "<sup class=\"footnote-ref\" id=\"{}\"><a href=\"#{}\">{}</a></sup>",
+                        note.ref_id, note.id, note.number
+                    )
+                    .into(),
+                ));
+            }
+            Event::Start(Tag::FootnoteDefinition(name)) => {
+                let note = &notes[&name];
+                events.push(Event::Html(
+                    format!(
+                        "<div class=\"footnote\" id=\"{}\"><sup class=\"footnote-label\">{}</sup>",
+                        note.id, note.number
+                    )
+                    .into(),
+                ));
+            }
+            Event::End(Tag::FootnoteDefinition(name)) => {
+                let note = &notes[&name];
+                events.push(Event::Html(
+                    format!(
+                        "<a class=\"footnote-backref\" href=\"#{}\">↩</a></div>",
+                        note.ref_id
+                    )
+                    .into(),
+                ));
+            }
+            event => match heading.as_mut() {
+                Some(inner) => inner.push(event),
+                None => events.push(event),
+            },
+        }
+    }
+    let mut unsanitized = String::with_capacity(source.len() * 3 / 2);
+    html::push_html(&mut unsanitized, events.into_iter());
+    Rendered {
+        html: sanitize(&unsanitized),
+        outline,
+    }
+}
+
+/// The first `max_chars` characters of the text of `source`, without any formatting or code, on a
+/// single line. Used to describe posts where only a line or two fits.
+pub fn summarize(source: &str, max_chars: usize) -> String {
+    let mut text = String::new();
+    let mut in_code = false;
+    for event in Parser::new_ext(source, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code = true,
+            Event::End(Tag::CodeBlock(_)) => in_code = false,
+            Event::Text(t) | Event::Code(t) if !in_code => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(Tag::Paragraph)
+            | Event::End(Tag::Heading(_))
+            | Event::End(Tag::Item)
+            | Event::End(Tag::TableCell) => text.push(' '),
+            _ => {}
+        }
+    }
+    let mut summary = String::with_capacity(max_chars);
+    for word in text.split_whitespace() {
+        let space = if summary.is_empty() { 0 } else { 1 };
+        let room = max_chars.saturating_sub(summary.chars().count() + space);
+        if room == 0 {
+            break;
+        }
+        if space == 1 {
+            summary.push(' ');
+        }
+        summary.extend(word.chars().take(room));
+    }
+    summary
+}
+
+/// The url of the first image in `source`, if it has one.
+pub fn first_image(source: &str) -> Option<String> {
+    Parser::new(source).find_map(|event| match event {
+        Event::Start(Tag::Image(_, url, _)) if !url.is_empty() => Some(url.into_string()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn headings_get_deduplicated_ids_and_anchors() {
+        let rendered = render_markdown("# Intro\n\ntext\n\n## Intro\n\n### *Why* `Rust`?\n");
+        let ids: Vec<_> = rendered.outline.iter().map(|h| (h.level, h.id.as_str())).collect();
+        assert_eq!(ids, vec![(1, "intro"), (2, "intro-1"), (3, "why-rust")]);
+        assert_eq!(rendered.outline[2].text, "Why Rust?");
+        assert!(rendered.html.contains(r#"<h2 id="intro-1">"#));
+        assert!(rendered.html.contains(r##"href="#intro-1""##));
+    }
+
+    #[test]
+    fn footnotes_link_both_ways() {
+        let rendered = render_markdown("A claim.[^source]\n\n[^source]: Where it is from.\n");
+        assert!(rendered.html.contains(r##"<sup class="footnote-ref" id="fnref-source"><a href="#fn-source""##));
+        assert!(rendered.html.contains(r#"<div class="footnote" id="fn-source">"#));
+        assert!(rendered.html.contains(r##"class="footnote-backref" href="#fnref-source""##));
+    }
+
+    #[test]
+    fn dangerous_markup_is_stripped_but_generated_ids_stay() {
+        let source = "# Fn source\n\n<img src=x onerror=alert(1)><script>alert(2)</script>\n\
+                      <a href=\"javascript:alert(3)\" class=\"heading-anchor\">x</a>[^source]\n\n\
+                      [^source]: note\n";
+        let rendered = render_markdown(source);
+        assert!(!rendered.html.contains("onerror"));
+        assert!(!rendered.html.contains("<script"));
+        assert!(!rendered.html.contains("javascript:"));
+        // The heading steps aside for the footnote, which claimed its ids first.
+        assert_eq!(rendered.outline[0].id, "fn-source-1");
+        assert!(rendered.html.contains(r#"id="fn-source""#));
+    }
+
+    #[test]
+    fn code_blocks_keep_their_language_and_escape_their_code() {
+        let rendered = render_markdown("```Rust\nlet a = \"<b>\";\n```\n\n```\nplain\n```\n");
+        assert!(rendered.html.contains(r#"<pre class="code"><code class="language-rust">"#));
+        assert!(rendered.html.contains(r#"<pre class="code"><code>plain"#));
+        assert!(!rendered.html.contains("<b>"));
+    }
+
+    #[test]
+    fn summaries_are_plain_single_lines_of_bounded_length() {
+        let source = "# Title\n\nSome *emphasis* and `code`.\nNext line.\n\n```\nlet hidden = 1;\n```\n";
+        assert_eq!(summarize(source, 200), "Title Some emphasis and code. Next line.");
+        assert_eq!(summarize(source, 14), "Title Some emp");
+        assert_eq!(summarize("héllo wörld", 7), "héllo w");
+    }
+
+    #[test]
+    fn the_first_image_is_found_anywhere() {
+        let source = "Text\n\n> quoted ![a](/public/img/one.jpg)\n\n![b](two.png)\n";
+        assert_eq!(first_image(source).as_deref(), Some("/public/img/one.jpg"));
+        assert_eq!(first_image("no images"), None);
+    }
+}
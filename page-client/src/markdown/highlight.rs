@@ -0,0 +1,81 @@
+//! Highlights the code blocks of posts, marking tokens up with classes that [`theme_css`] styles.
+//!
+//! Loading the syntaxes and themes takes a while, so they are loaded once, the first time they are
+//! needed, and kept.
+
+use once_cell::sync::Lazy;
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+/// Prefixes the classes of tokens, so that they do not clash with the classes of the site.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+/// The theme of code on the dark theme of the site, which is the default.
+const DARK_THEME: &str = "base16-ocean.dark";
+/// The theme of code on the light theme of the site.
+const LIGHT_THEME: &str = "InspiredGitHub";
+
+static SYNTAXES: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+
+/// `code` marked up as `language`, or [`None`] if the language is not one that is known.
+pub fn highlight(code: &str, language: &str) -> Option<String> {
+    let syntax = SYNTAXES.find_syntax_by_token(language)?;
+    let mut generator =
+        ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator.parse_html_for_line_which_includes_newline(line);
+    }
+    Some(generator.finalize())
+}
+
+/// Puts every rule of `css` under `scope`.
+fn scoped(css: &str, scope: &str) -> String {
+    let mut out = String::with_capacity(css.len() * 2);
+    for line in css.lines() {
+        match line.trim_end().strip_suffix('{') {
+            Some(selectors) => {
+                let selectors: Vec<_> = selectors
+                    .split(',')
+                    .map(|selector| format!("{} {}", scope, selector.trim()))
+                    .collect();
+                out.push_str(&selectors.join(", "));
+                out.push_str(" {");
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The styles of highlighted code, following the theme of the site.
+pub fn theme_css() -> &'static str {
+    static CSS: Lazy<String> = Lazy::new(|| {
+        let themes = ThemeSet::load_defaults();
+        let dark = css_for_theme_with_class_style(&themes.themes[DARK_THEME], CLASS_STYLE);
+        let light = css_for_theme_with_class_style(&themes.themes[LIGHT_THEME], CLASS_STYLE);
+        format!("{}\n{}", dark, scoped(&light, r#":root[data-theme="light"]"#))
+    });
+    &CSS
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_languages_get_classes_and_unknown_ones_nothing() {
+        let highlighted = highlight("fn main() {}\n", "rust").unwrap();
+        assert!(highlighted.contains(r#"class="hl-"#));
+        assert!(highlight("fn main() {}\n", "not-a-language").is_none());
+    }
+
+    #[test]
+    fn light_rules_only_apply_to_the_light_theme() {
+        let css = scoped(".hl-code, .hl-comment {\n color: #000;\n}\n", ":root");
+        assert_eq!(css, ":root .hl-code, :root .hl-comment {\n color: #000;\n}\n");
+    }
+}
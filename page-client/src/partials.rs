@@ -2,20 +2,57 @@
 use crate::data::*;
 use maud::{html, Markup, DOCTYPE};
 
+/// Collapses the whitespace of `text`, newlines included, into single spaces, since previews of
+/// links show a single line.
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The tags describing the page when it is linked to elsewhere, read by
+/// [Open Graph](https://ogp.me) and Twitter cards alike.
+fn preview(meta: &MetaData) -> Markup {
+    let title = one_line(meta.og_title.or(meta.title).unwrap_or(meta.site_title));
+    let description = one_line(meta.og_description.unwrap_or(meta.description));
+    let card = if meta.og_image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+    html! {
+        @if let Some(url) = meta.canonical_url {
+            link rel="canonical" href=(url);
+            meta property="og:url" content=(url);
+        }
+        meta property="og:type" content=(meta.og_type);
+        meta property="og:site_name" content=(one_line(meta.site_title));
+        meta property="og:title" content=(title);
+        meta property="og:description" content=(description);
+        @if let Some(image) = meta.og_image {
+            meta property="og:image" content=(image);
+        }
+        meta name="twitter:card" content=(card);
+    }
+}
+
 /// The `<head>` portion of the webpage.
 pub fn head(meta: &MetaData) -> Markup {
     html! {
         head {
             meta charset=(meta.charset);
-            title { (meta.title) }
+            title { (meta.document_title()) }
             meta name="description" content=(meta.description);
+            (preview(meta))
             meta name="viewport" content="width=device-width, initial-scale=1";
             meta name="theme-color" content=(meta.theme_color);
+            @for js in meta.scripts {
+                (js.preload_with(meta.assets))
+            }
             @for css in meta.css {
-                (css)
+                (css.render_with(meta.assets, meta.nonce))
             }
+            (Css::render_loader(meta.css, meta.nonce))
             @for js in meta.scripts {
-                (js)
+                (js.render_with(meta.assets, meta.nonce))
             }
         }
     }
@@ -28,7 +65,7 @@ pub fn header(meta: &MetaData) -> Markup {
                 (logo)
             }
             @if let Some(menu) = meta.menu {
-                (menu)
+                (menu.render_at(meta.current_path))
             }
         }
     }
@@ -52,6 +89,7 @@ pub fn body(m: Markup, meta: &MetaData) -> Markup {
             div.bg-img {}
             (header(meta))
             main.site-body {
+                (Crumb::render_trail(meta.breadcrumbs, meta.nonce))
                 (m)
             }
             (footer(meta))
@@ -80,3 +118,133 @@ pub fn basic_page(m: Markup, meta_data: Option<&MetaData>) -> Markup {
     }
     page(m, meta)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn occurrences(html: &str, needle: &str) -> usize {
+        html.matches(needle).count()
+    }
+
+    #[test]
+    fn previews_escape_quotes_and_keep_to_one_line() {
+        let meta = MetaData::builder()
+            .title(Some("A \"quoted\"\ntitle"))
+            .og_description(Some("Line one.\n\n  Line <two>."))
+            .build();
+        let html = head(&meta).into_string();
+        assert!(html.contains(r#"<meta property="og:title" content="A &quot;quoted&quot; title">"#));
+        assert!(html.contains(r#"content="Line one. Line &lt;two&gt;.""#));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#));
+        assert_eq!(occurrences(&html, "rel=\"canonical\""), 0);
+    }
+
+    #[test]
+    fn preview_tags_appear_once_when_values_overlap() {
+        let url = "https://benxu.dev/blog/posts/hello";
+        let meta = MetaData::builder()
+            .site_title("Hello")
+            .description("Hi.")
+            .og_title(Some("Hello"))
+            .og_description(Some("Hi."))
+            .og_image(Some("https://benxu.dev/public/img/propic-big.jpg"))
+            .og_type("article")
+            .canonical_url(Some(url))
+            .build();
+        let html = head(&meta).into_string();
+        for tag in &[
+            "property=\"og:site_name\"",
+            "property=\"og:title\"",
+            "property=\"og:description\"",
+            "property=\"og:image\"",
+            "property=\"og:type\"",
+            "property=\"og:url\"",
+            "name=\"twitter:card\"",
+            "rel=\"canonical\"",
+        ] {
+            assert_eq!(occurrences(&html, tag), 1, "{} should appear once", tag);
+        }
+        assert!(html.contains(r#"content="summary_large_image""#));
+        assert!(html.contains(r#"content="article""#));
+    }
+
+    #[test]
+    fn pages_are_titled_and_placed_within_the_site() {
+        let crumbs = [
+            Crumb { name: "Home", url: "https://benxu.dev/" },
+            Crumb { name: "Blog", url: "https://benxu.dev/blog" },
+            Crumb { name: "</script> \"Hi\"", url: "https://benxu.dev/blog/posts/hi" },
+        ];
+        let meta = MetaData::builder()
+            .site_title("Ben")
+            .title(Some("</script> \"Hi\""))
+            .breadcrumbs(&crumbs[..])
+            .nonce(Some("n"))
+            .build();
+        let html = page(html! {}, &meta).into_string();
+        assert!(html.contains("<title>&lt;/script&gt; &quot;Hi&quot; | Ben</title>"));
+        assert_eq!(meta.document_title(), page_title(Some("</script> \"Hi\""), "Ben"));
+        assert_eq!(page_title(None, "Ben"), "Ben");
+        assert!(html.contains(r#"<a href="https://benxu.dev/blog/posts/hi" aria-current="page">"#));
+        assert_eq!(occurrences(&html, "aria-current"), 1);
+        assert!(html.contains(r#"{"@type":"ListItem","position":2,"name":"Blog","item":"https://benxu.dev/blog"}"#));
+        assert!(html.contains(r#""name":"\u003c/script\u003e \"Hi\"""#));
+        assert_eq!(occurrences(&html, "</script>"), 1);
+
+        let html = page(html! {}, &MetaData::default()).into_string();
+        assert!(!html.contains("breadcrumbs"));
+    }
+
+    #[test]
+    fn menus_nest_and_mark_the_section_of_the_page() {
+        let posts = [MenuItem::link("New post", "/blog/editor/new"), MenuItem::Divider];
+        let posts = Menu(&posts);
+        let items = [
+            MenuItem::link("Home", "/"),
+            MenuItem::Link {
+                text: "Blog",
+                link: Some("/blog"),
+                children: Some(&posts),
+            },
+        ];
+        let menu = Menu(&items);
+        let html = menu.render_at(Some("/blog/posts/hello")).into_string();
+        assert!(html.contains(r#"<li class="active has-children"><a class="active" href="/blog" aria-current="page">Blog</a><ul class="submenu">"#));
+        assert!(html.contains(r#"<li class="menu-divider" role="separator"></li>"#));
+        assert_eq!(occurrences(&html, "aria-current"), 1);
+
+        // The innermost section wins, and the root only matches itself.
+        let html = menu.render_at(Some("/blog/editor/new")).into_string();
+        assert!(html.contains(r#"<a class="active" href="/blog/editor/new" aria-current="page">"#));
+        assert_eq!(occurrences(&html, "aria-current"), 1);
+        assert_eq!(current_link(menu.links(), "/about"), None);
+        assert_eq!(current_link(menu.links(), "/"), Some("/"));
+    }
+
+    #[test]
+    fn no_stylesheet_holds_back_the_first_render() {
+        let mut assets = AssetManifest::new();
+        assets.insert_inline("css/main.css".to_owned(), "body{margin:0}".to_owned());
+        assets.insert("css/blog.css".to_owned(), "css/blog.0123.css".to_owned());
+        let css = [Css::Critical { src: "main" }, Css::NonCritical { src: "blog" }];
+        let scripts = [Script::External("wasm-bindgen-glue/blog_client.js")];
+        let meta = MetaData::builder()
+            .css(&css[..])
+            .scripts(&scripts[..])
+            .assets(Some(&assets))
+            .nonce(Some("n"))
+            .build();
+        let html = head(&meta).into_string();
+        assert!(html.contains("body{margin:0}"));
+        assert!(html.contains(r#"<link rel="preload" as="style" href="/public/css/blog.0123.css""#));
+        assert!(html.contains(r#"<link rel="preload" as="script""#));
+        // The only stylesheet links are the fallbacks for browsers without scripts.
+        let outside_noscript: String = html
+            .split("<noscript>")
+            .map(|part| part.split("</noscript>").last().unwrap_or(""))
+            .collect();
+        assert_eq!(occurrences(&outside_noscript, "rel=\"stylesheet\""), 0);
+        assert_eq!(occurrences(&html, "rel=\"stylesheet\""), 1);
+    }
+}
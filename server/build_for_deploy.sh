@@ -2,4 +2,5 @@
 
 rm -rf public/wasm
 rm -rf public/js/wasm-bindgen-glue
-cargo build --release --out-dir=. -Z unstable-options
+cargo build --release
+cp ../target/release/benxu server
@@ -0,0 +1,244 @@
+//! The `admin` subcommand, for administering the site from the host it runs on, such as when
+//! logging in through the site is broken.
+//!
+//! Commands go straight to the database through [`blog_db`], through the same functions as the
+//! handlers doing the same, so that users made or passwords set here are no different. Nothing is
+//! done unless the database was migrated exactly as far as this binary knows of, since the tool
+//! never migrates it.
+//!
+//! The tool acts as no user of the site, so what it records as made by someone is recorded as made
+//! by the user it was done for.
+
+use diesel::{Connection, PgConnection};
+use structopt::StructOpt;
+
+use crate::{
+    cfg::{self, ServerConfig},
+    urls::{create_account, set_password, validate_change},
+    util::{
+        auth,
+        blog::db::{self, CapabilityQuery, DBConn, GuardedDeletionError, UserQuery},
+    },
+};
+use blog_db::{migrations, models::*};
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// List or create users.
+    User(UserCommand),
+    /// Set the password of a user, prompting for it.
+    Password(PasswordCommand),
+    /// Grant or revoke capabilities.
+    Perm(PermCommand),
+}
+
+#[derive(Debug, StructOpt)]
+pub enum UserCommand {
+    /// List every user, ordered by user name.
+    List,
+    /// Create a user, who has no password or capabilities until given them.
+    Create {
+        user_name: String,
+        #[structopt(long)]
+        first_name: String,
+        #[structopt(long)]
+        last_name: String,
+        #[structopt(long)]
+        email: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum PasswordCommand {
+    /// Set the password of a user, whether or not they already have one.
+    Set {
+        /// The user name or id of the user.
+        user: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum PermCommand {
+    /// Grant a capability to a user, everywhere and for good.
+    Grant {
+        /// The user name or id of the user.
+        user: String,
+        /// The capability, such as `view_site_stats`.
+        capability: auth::Capability,
+    },
+    /// Revoke every direct grant of a capability from a user. Grants through roles are kept.
+    Revoke {
+        /// The user name or id of the user.
+        user: String,
+        /// The capability, such as `view_site_stats`.
+        capability: auth::Capability,
+    },
+}
+
+/// The connection commands are run through.
+struct AdminConn(PgConnection);
+impl DBConn for AdminConn {
+    fn conn(&self) -> &PgConnection {
+        &self.0
+    }
+}
+
+/// Runs `command` against the database in `config`. Returns what went wrong otherwise.
+pub fn run(command: &Command, config: &ServerConfig) -> Result<(), String> {
+    let conn = PgConnection::establish(config.database_url.as_str())
+        .map(AdminConn)
+        .map_err(|e| format!("Could not connect to the database: {}.", e))?;
+    migrations::check_schema_current(conn.conn())
+        .map_err(|e| format!("Refusing to change the database: {}.", e))?;
+    match command {
+        Command::User(UserCommand::List) => {
+            let users = conn.list_users().map_err(failed("list users"))?;
+            for user in users {
+                println!(
+                    "{}\t{}\t{}",
+                    user.id,
+                    user.user_name,
+                    user.email.as_deref().unwrap_or("")
+                );
+            }
+        }
+        Command::User(UserCommand::Create {
+            user_name,
+            first_name,
+            last_name,
+            email,
+        }) => {
+            let new = users::NewNoMeta {
+                user_name: user_name.clone(),
+                first_name: first_name.clone(),
+                last_name: last_name.clone(),
+                email: email.clone(),
+            };
+            let user = create_account(&conn, None, new).map_err(failed("create the user"))?;
+            println!("Created {} with id {}.", user.user_name, user.id);
+        }
+        Command::Password(PasswordCommand::Set { user }) => {
+            let user = find_user(&conn, user)?;
+            let password = prompt_password()?;
+            crypto::multithread_init()
+                .map_err(|_| "Could not initialize crate `crypto` for multithreaded use.")?;
+            let pw_key_store = cfg::pw_secret(config);
+            set_password(&conn, &user, user.id, &password, &pw_key_store)
+                .map_err(failed("set the password"))?;
+            println!("Set the password of {}.", user.user_name);
+        }
+        Command::Perm(PermCommand::Grant { user, capability }) => {
+            let user = find_user(&conn, user)?;
+            conn.create_all_capabilities(vec![capabilities::New {
+                created_by: user.id,
+                user_id: user.id,
+                capability: capability.as_str(),
+                expires_at: None,
+                scope: None,
+            }])
+            .map_err(failed("grant the capability"))?;
+            println!("Granted {} to {}.", capability, user.user_name);
+        }
+        Command::Perm(PermCommand::Revoke { user, capability }) => {
+            let user = find_user(&conn, user)?;
+            let revoked = conn
+                .delete_preserving_capability(auth::caps::ADMIN_CRITICAL, || {
+                    let ids: Vec<_> = conn
+                        .get_user_capability_grants(user.id)?
+                        .into_iter()
+                        .filter(|grant| grant.capability == capability.as_str())
+                        .filter_map(|grant| match grant.source {
+                            capabilities::GrantSource::Direct { id } => Some(id),
+                            capabilities::GrantSource::Role { .. } => None,
+                        })
+                        .collect();
+                    conn.delete_capabilities_with_ids(&ids)
+                })
+                .map_err(|e| match e {
+                    GuardedDeletionError::LastHolder => format!(
+                        "Refusing to revoke {}, since nobody else holds {} everywhere.",
+                        capability,
+                        auth::caps::ADMIN_CRITICAL
+                    ),
+                    GuardedDeletionError::Query(e) => failed("revoke the capability")(e),
+                })?;
+            println!(
+                "Revoked {} grants of {} from {}.",
+                revoked.len(),
+                capability,
+                user.user_name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Formats a failure to do `what`.
+fn failed(what: &'static str) -> impl Fn(db::Error) -> String {
+    move |e| format!("Could not {} due to {:?}.", what, e)
+}
+
+/// The user with `user` as their id or user name.
+fn find_user(conn: &AdminConn, user: &str) -> Result<users::Data, String> {
+    let found = match user.parse::<uuid::Uuid>() {
+        Ok(id) => conn.find_user_by_id(id),
+        Err(_) => conn.find_user_by_user_name(user),
+    };
+    found.map_err(|e| match e {
+        db::Error::NotFound => format!("No user is called or has the id {}.", user),
+        e => failed("find the user")(e),
+    })
+}
+
+/// Reads a new password from the terminal without echoing it, twice, and checks it the way
+/// changes to passwords are checked by the site.
+fn prompt_password() -> Result<String, String> {
+    let read = |prompt| {
+        rpassword::read_password_from_tty(Some(prompt))
+            .map_err(|e| format!("Could not read the password: {}.", e))
+    };
+    let password = read("New password: ")?;
+    if read("Repeat the new password: ")? != password {
+        return Err("The passwords do not match.".to_owned());
+    }
+    let change = login_enum::ChangePassword {
+        current: None,
+        new: password,
+    };
+    match validate_change(&change, false).first() {
+        Some(error) => Err(error.message.clone()),
+        None => Ok(change.new),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cfg::{Opt, Subcommand};
+
+    fn parse(args: &[&str]) -> Result<Command, structopt::clap::Error> {
+        let args = ["benxu", "admin"].iter().chain(args).copied();
+        Opt::from_iter_safe(args).map(|opt| match opt.command {
+            Some(Subcommand::Admin(command)) => command,
+            None => panic!("Parsed without a command."),
+        })
+    }
+
+    #[test]
+    fn capabilities_are_parsed_by_name() {
+        match parse(&["perm", "grant", "ben", "view_site_stats"]) {
+            Ok(Command::Perm(PermCommand::Grant { user, capability })) => {
+                assert_eq!(user, "ben");
+                assert_eq!(capability, auth::Capability::ViewSiteStats);
+            }
+            res => panic!("Expected a grant, got {:?}.", res),
+        }
+        assert!(parse(&["perm", "revoke", "ben", "be_admin"]).is_err());
+    }
+
+    #[test]
+    fn passwords_are_never_arguments() {
+        assert!(parse(&["password", "set", "ben"]).is_ok());
+        assert!(parse(&["password", "set", "ben", "hunter2"]).is_err());
+    }
+}
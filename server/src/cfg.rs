@@ -1,11 +1,13 @@
 use crypto;
-use log::*;
 use std::{
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 use structopt::StructOpt;
-use tap::*;
+
+mod server_config;
+pub use server_config::{CookieConfig, ServerConfig, SmtpConfig, TokenKeyPersistence};
 
 /// Algorithm utilized for hashing passwords
 pub type PWAlgo = crypto::algo::hash::argon2::d::Algo;
@@ -14,6 +16,9 @@ pub type PWKeyFixture = Arc<crypto::StableKeyStore<PWAlgo>>;
 pub type TokenAlgo = <crypto::token::paseto::V2Local as crypto::token::paseto::Protocol>::CoreAlgo;
 pub type TokenKeyStore = crypto::RotatingKeyStore<TokenAlgo>;
 pub type TokenKeyFixture = crypto::RotatingKeyFixture<TokenAlgo>;
+/// Algorithm utilized for signing tokens that other services verify with the published public keys.
+pub type SigningAlgo = crypto::algo::sign::asymmetric::ed25519::Algo;
+pub type SigningKeyFixture = crypto::RotatingKeyFixture<SigningAlgo>;
 
 /// Default path for the password secret.
 pub const PW_SECRET_KEY_DEFAULT_PATH: &'static str = "./.pw_secret";
@@ -36,12 +41,9 @@ pub const BLOG_SPA_ROOT: &'static str = "/blog";
 pub struct Opt {
     #[structopt(long)]
     pub ignore_dotenv: bool,
-    #[structopt(
-        long,
-        default_value = PW_SECRET_KEY_DEFAULT_PATH,
-        env = PW_SECRET_KEY_ENV_VAR_NAME
-    )]
-    pub pw_secret_path: PathBuf,
+    /// A TOML file with fallback values for the [`ServerConfig`].
+    #[structopt(long)]
+    pub config: Option<PathBuf>,
     #[structopt(
         long,
         default_value = PUBLIC_ROOT
@@ -67,6 +69,35 @@ pub struct Opt {
         default_value = BLOG_SPA_ROOT,
     )]
     pub blog_spa_root_route: String,
+    /// Log each request as a line of JSON instead of plain text.
+    #[structopt(long)]
+    pub json_request_logs: bool,
+    /// Measure how fast this machine hashes passwords, print the argon2 parameters that take about
+    /// this many milliseconds per hash, and exit.
+    #[structopt(long)]
+    pub calibrate_argon2_ms: Option<u64>,
+    /// Apply pending database migrations before serving.
+    #[structopt(long)]
+    pub migrate: bool,
+    /// Apply pending database migrations and exit without serving.
+    #[structopt(long)]
+    pub migrate_only: bool,
+    /// Fill the database with development fixtures and exit without serving. Refuses databases
+    /// that already have users.
+    #[structopt(long)]
+    pub seed: bool,
+    /// Seed even if the database already has users.
+    #[structopt(long, requires = "seed")]
+    pub force: bool,
+    #[structopt(subcommand)]
+    pub command: Option<Subcommand>,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Subcommand {
+    /// Administer users and their capabilities straight through the database, then exit without
+    /// serving. Refuses databases that are not migrated exactly up to this binary.
+    Admin(crate::admin::Command),
 }
 
 impl Opt {
@@ -91,76 +122,63 @@ impl Opt {
     }
 }
 
-/// Initializes the key rotation system for the token's secret key.
-pub fn token_key() -> crypto::KeyRotator<TokenAlgo> {
-    crypto::KeyRotator::init(TokenAlgo {}, None)
+/// Initializes the key rotation system for the token's secret key, which stops once `shutdown` is
+/// signaled. The keys are persisted if the configuration says where to.
+pub fn token_key(
+    config: &ServerConfig,
+    shutdown: crypto::Shutdown,
+) -> crypto::KeyRotator<TokenAlgo> {
+    match config.token_key_persistence.as_ref() {
+        Some(persistence) => {
+            let persistence = crypto::key_rotation::FilePersistence::new(
+                persistence.path.clone(),
+                persistence.master_key.clone(),
+            );
+            crypto::KeyRotator::init_with_persistence(
+                TokenAlgo {},
+                config.key_rotation,
+                shutdown,
+                Box::new(persistence),
+            )
+        }
+        None => crypto::KeyRotator::init_with_shutdown(TokenAlgo {}, config.key_rotation, shutdown),
+    }
+}
+
+/// Initializes the key rotation system for the signing key pairs, which stops once `shutdown` is
+/// signaled. The key pairs are rotated on the same schedule as the token keys, but are never
+/// persisted, since verifiers fetch the public keys again when they see an unknown key id.
+pub fn signing_key(
+    config: &ServerConfig,
+    shutdown: crypto::Shutdown,
+) -> crypto::KeyRotator<SigningAlgo> {
+    crypto::KeyRotator::init_with_shutdown(SigningAlgo {}, config.key_rotation, shutdown)
+}
+
+/// Prints the argon2 parameters that take about `target` to hash a password on this machine, as
+/// the environment variables that configure them.
+pub fn print_calibrated_pw_params(target: Duration) {
+    use server_config::{
+        ARGON2_HASH_LEN_ENV_VAR_NAME, ARGON2_ITERATIONS_ENV_VAR_NAME, ARGON2_MEM_KIB_ENV_VAR_NAME,
+        ARGON2_PARALLELISM_ENV_VAR_NAME,
+    };
+    let params = crypto::algo::hash::argon2::calibrate(target);
+    let vars = [
+        (ARGON2_MEM_KIB_ENV_VAR_NAME, params.mem_kib),
+        (ARGON2_ITERATIONS_ENV_VAR_NAME, params.iterations),
+        (ARGON2_PARALLELISM_ENV_VAR_NAME, params.parallelism),
+        (ARGON2_HASH_LEN_ENV_VAR_NAME, params.hash_len),
+    ];
+    for (name, value) in vars.iter() {
+        println!("{}={}", name, value);
+    }
 }
 
 /// Initializes the key store for the password's hashing secret key.
-pub fn pw_secret(opt: &Opt) -> crypto::StableKeyStore<PWAlgo> {
-    use std::{fs::File, io::Read};
-    log::debug!("Locating password hashing secret...");
-    let secret_path = opt.pw_secret_path
-        .canonicalize()
-        .tap_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => {
-                error!(
-                    "Could not find the secret key file `{}` for password hashing!",
-                    opt.pw_secret_path.display()
-                );
-            }
-            _ => error!(
-                "Unknown error encountered when attempting to file `{}`\n{:?}",
-                opt.pw_secret_path.display(), e
-            ),
-        })
-        .expect("Password secret to be present.");
-    log::info!("Password secret located.");
-    log::info!("Loading secret from file {}...", secret_path.display());
-    let secret = File::open(secret_path)
-        .and_then(|mut f| {
-            info!("Loading file metadata...");
-            let meta = f.metadata()?;
-            if meta.len() == 0 {
-                log::warn!("No secret key provided!");
-                Ok(vec![])
-            } else {
-                info!("Reading file...");
-                let to_read = if meta.len() > 32 {
-                    log::warn!("Secret key larger than expected! Truncating the secret to 32 bytes. (Check if limit is actully 2^32 - 1 bytes.)");
-                    32u64
-                } else if meta.len() < 16 {
-                    log::warn!("Secret key far smaller than suggested 32 bytes! Please consider lengthening the secret.");
-                    meta.len()
-                } else {
-                    meta.len()
-                } as usize;
-                let mut read_bytes = vec![0; to_read];
-                f.read_exact(read_bytes.as_mut_slice())?;
-                log::info!("File read.");
-                Ok(read_bytes)
-            }
-        })
-        .tap_err(|e| {
-            use std::io::ErrorKind;
-            match e.kind() {
-                // This should be taken care of already when finding the path, but jic.
-                ErrorKind::NotFound => log::error!(
-                    "Could not find file at `{}`.",
-                    opt.pw_secret_path.display()
-                ),
-                ErrorKind::PermissionDenied => log::error!(
-                    "Lacking valid permissions for accessing file at `{}`.",
-                    opt.pw_secret_path.display()
-                ),
-                _ => log::error!(
-                    "Could not find secret key at path `{}`! Caused by: {:?}",
-                    opt.pw_secret_path.display(),
-                    e,
-                ),
-            };
-        })
-        .expect("Located secret key file.");
+pub fn pw_secret(config: &ServerConfig) -> crypto::StableKeyStore<PWAlgo> {
     use crypto::algo::Algo as A;
-    crypto::key_rotation::StableKeyStore::new(PWAlgo::new(None), <PWAlgo as A>::Key::new(secret))
-}
\ No newline at end of file
+    crypto::key_rotation::StableKeyStore::new(
+        PWAlgo::new(config.pw_params),
+        <PWAlgo as A>::Key::new(config.pw_secret.clone()),
+    )
+}
@@ -0,0 +1,1021 @@
+//! Configuration for the server that is read once at startup, as opposed to the command line
+//! options in [`Opt`](crate::cfg::Opt).
+//!
+//! Values are read from the environment, falling back to the TOML file passed through
+//! `--config`, if any, and then to the defaults listed for each variable. Every field is
+//! validated before the server starts so that all problems are reported at once.
+
+use crypto::{
+    algo::{
+        cipher::envelope::{self, MasterKey},
+        hash::argon2::{Argon2Params, ParamsError},
+        SecretBytes,
+    },
+    RotationConfig, RotationMode,
+};
+use rocket::http::SameSite;
+use serde::Deserialize;
+use std::{
+    fmt,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    cfg::{Opt, PW_SECRET_KEY_DEFAULT_PATH, PW_SECRET_KEY_ENV_VAR_NAME},
+    util::{
+        blog::db::StatementTimeouts, body_limits::BodyClass, retention::RetentionConfig,
+        site_identity,
+    },
+};
+use blog_db::models::site;
+
+/// Name for environment variable holding the url of the blog database.
+pub const DATABASE_URL_ENV_VAR_NAME: &str = "DATABASE_URL";
+/// Name for environment variable deciding whether cookies are only sent over https.
+pub const COOKIE_SECURE_ENV_VAR_NAME: &str = "BENXU_DEV_COOKIE_SECURE";
+/// Name for environment variable holding the domain cookies are restricted to.
+pub const COOKIE_DOMAIN_ENV_VAR_NAME: &str = "BENXU_DEV_COOKIE_DOMAIN";
+/// Name for environment variable holding the `SameSite` attribute of cookies, one of `strict`,
+/// `lax`, or `none`.
+pub const COOKIE_SAME_SITE_ENV_VAR_NAME: &str = "BENXU_DEV_COOKIE_SAME_SITE";
+/// Name for environment variable holding the comma separated origins allowed to call the API.
+pub const CORS_ORIGINS_ENV_VAR_NAME: &str = "BENXU_DEV_CORS_ORIGINS";
+/// Name for environment variable holding the seconds between token key rotations.
+pub const KEY_ROTATION_ENV_VAR_NAME: &str = "BENXU_DEV_KEY_ROTATION_SECS";
+/// Name for environment variable holding the path of the file token keys are persisted to, so that
+/// tokens survive restarts.
+pub const TOKEN_KEY_FILE_ENV_VAR_NAME: &str = "BENXU_DEV_TOKEN_KEY_FILE";
+/// Name for environment variable holding the path to the secret the persisted token keys are
+/// encrypted with.
+pub const TOKEN_MASTER_KEY_ENV_VAR_NAME: &str = "BENXU_DEV_TOKEN_MASTER_KEY";
+/// Name for environment variable deciding whether the content security policy is only reported.
+pub const CSP_REPORT_ONLY_ENV_VAR_NAME: &str = "BENXU_DEV_CSP_REPORT_ONLY";
+/// Name for environment variable holding the number of generations of token keys kept, which is
+/// how many rotation periods tokens stay valid for.
+pub const KEY_GENERATIONS_ENV_VAR_NAME: &str = "BENXU_DEV_KEY_GENERATIONS_KEPT";
+/// Name for environment variable deciding whether token keys are rotated by a background thread
+/// (`threaded`, the default) or when they are used after being due (`lazy`).
+pub const KEY_ROTATION_MODE_ENV_VAR_NAME: &str = "BENXU_DEV_KEY_ROTATION_MODE";
+/// Name for environment variable holding the memory, in KiB, argon2 fills while hashing passwords.
+pub const ARGON2_MEM_KIB_ENV_VAR_NAME: &str = "BENXU_DEV_ARGON2_MEM_KIB";
+/// Name for environment variable holding the number of passes argon2 makes over its memory.
+pub const ARGON2_ITERATIONS_ENV_VAR_NAME: &str = "BENXU_DEV_ARGON2_ITERATIONS";
+/// Name for environment variable holding the number of lanes argon2 splits its memory into.
+pub const ARGON2_PARALLELISM_ENV_VAR_NAME: &str = "BENXU_DEV_ARGON2_PARALLELISM";
+/// Name for environment variable holding the length, in bytes, of new password hashes.
+pub const ARGON2_HASH_LEN_ENV_VAR_NAME: &str = "BENXU_DEV_ARGON2_HASH_LEN";
+/// Name for environment variable holding the token `/metrics` must be scraped with, as a bearer
+/// token. Without one, only requests from the machine itself may scrape the metrics.
+pub const METRICS_TOKEN_ENV_VAR_NAME: &str = "BENXU_DEV_METRICS_TOKEN";
+/// Name for environment variable holding the host mail is sent through. Without one, mail is only
+/// logged.
+pub const SMTP_HOST_ENV_VAR_NAME: &str = "BENXU_DEV_SMTP_HOST";
+/// Name for environment variable holding the port of the SMTP host.
+pub const SMTP_PORT_ENV_VAR_NAME: &str = "BENXU_DEV_SMTP_PORT";
+/// Name for environment variable holding the user name to log in to the SMTP host with.
+pub const SMTP_USERNAME_ENV_VAR_NAME: &str = "BENXU_DEV_SMTP_USERNAME";
+/// Name for environment variable holding the password to log in to the SMTP host with.
+pub const SMTP_PASSWORD_ENV_VAR_NAME: &str = "BENXU_DEV_SMTP_PASSWORD";
+/// Name for environment variable holding the address mail is sent from, such as
+/// `benxu.dev <no-reply@benxu.dev>`.
+pub const MAIL_FROM_ENV_VAR_NAME: &str = "BENXU_DEV_MAIL_FROM";
+/// Name for environment variable holding how many leading zero bits the proof of work asked of
+/// anonymous callers of public endpoints must have. Zero turns the proof of work off.
+pub const CHALLENGE_DIFFICULTY_ENV_VAR_NAME: &str = "BENXU_DEV_CHALLENGE_DIFFICULTY";
+/// Name for environment variable holding the origin the site is reached at, such as
+/// `https://benxu.dev`, which links to its pages are made absolute with.
+pub const SITE_ORIGIN_ENV_VAR_NAME: &str = "BENXU_DEV_SITE_ORIGIN";
+/// Name for environment variable holding the milliseconds statements may run before they are
+/// cancelled.
+pub const STATEMENT_TIMEOUT_ENV_VAR_NAME: &str = "BENXU_DEV_STATEMENT_TIMEOUT_MS";
+/// Name for environment variable holding the milliseconds statements of routes such as exports and
+/// bulk changes may run before they are cancelled.
+pub const LONG_STATEMENT_TIMEOUT_ENV_VAR_NAME: &str = "BENXU_DEV_LONG_STATEMENT_TIMEOUT_MS";
+/// Name for environment variable holding the days deleted posts are kept before they are deleted
+/// for good.
+pub const RETAIN_DELETED_POSTS_ENV_VAR_NAME: &str = "BENXU_DEV_RETAIN_DELETED_POSTS_DAYS";
+/// Name for environment variable holding the days the dead letters of webhooks are kept.
+pub const RETAIN_DEAD_LETTERS_ENV_VAR_NAME: &str = "BENXU_DEV_RETAIN_DEAD_LETTERS_DAYS";
+/// Name for environment variable holding the most bytes the body of a post may take up.
+pub const POST_BODY_MAX_BYTES_ENV_VAR_NAME: &str = "BENXU_DEV_POST_BODY_MAX_BYTES";
+/// Name for environment variable holding the title of the site, until admins change it.
+pub const SITE_TITLE_ENV_VAR_NAME: &str = "BENXU_DEV_SITE_TITLE";
+/// Name for environment variable holding the tagline of the site, until admins change it.
+pub const SITE_TAGLINE_ENV_VAR_NAME: &str = "BENXU_DEV_SITE_TAGLINE";
+/// Name for environment variable holding the logo of the site, until admins change it: either a
+/// path on the site, such as `/public/img/branding.svg`, or the markup of an svg.
+pub const SITE_LOGO_ENV_VAR_NAME: &str = "BENXU_DEV_SITE_LOGO";
+/// Default days deleted posts are kept.
+pub const RETAIN_DELETED_POSTS_DEFAULT_DAYS: u32 = 30;
+/// Default days dead letters are kept.
+pub const RETAIN_DEAD_LETTERS_DEFAULT_DAYS: u32 = 90;
+/// Default most bytes the body of a post may take up.
+pub const POST_BODY_MAX_BYTES_DEFAULT: usize = 1024 * 1024;
+/// Default milliseconds statements may run.
+pub const STATEMENT_TIMEOUT_DEFAULT_MS: u64 = 5_000;
+/// Default milliseconds statements of routes such as exports and bulk changes may run.
+pub const LONG_STATEMENT_TIMEOUT_DEFAULT_MS: u64 = 60_000;
+/// Default origin of the site.
+pub const SITE_ORIGIN_DEFAULT: &str = "https://benxu.dev";
+/// Default port of the SMTP host, which takes mail over STARTTLS.
+pub const SMTP_PORT_DEFAULT: u16 = 587;
+/// Default difficulty of the proof of work, which takes a browser around a second.
+pub const CHALLENGE_DIFFICULTY_DEFAULT: u8 = 18;
+/// Highest difficulty of the proof of work, past which browsers would take minutes.
+pub const CHALLENGE_DIFFICULTY_MAX: u8 = 28;
+/// Default number of seconds between token key rotations.
+pub const KEY_ROTATION_DEFAULT_SECS: u64 = 2 * 60 * 60;
+/// Default number of generations of token keys kept.
+pub const KEY_GENERATIONS_DEFAULT: usize = 2;
+
+/// Name of the database in the Rocket configuration. Must match the name given to
+/// [`DB`](crate::util::blog::DB).
+const DATABASE_NAME: &str = "blog";
+
+/// Settings for the cookies issued by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieConfig {
+    /// Whether cookies should only be sent over https.
+    pub secure: bool,
+    /// The domain cookies are restricted to, if any.
+    pub domain: Option<String>,
+    /// The `SameSite` attribute of cookies. Rocket cannot send `SameSite=None`, so
+    /// [`SameSite::None`] leaves the attribute out, which browsers treat as `Lax`. Origins allowed
+    /// through CORS must therefore be on the same site, such as a subdomain, to be sent the auth
+    /// cookie.
+    pub same_site: SameSite,
+}
+
+/// Where the token keys are persisted between restarts.
+#[derive(Debug, Clone)]
+pub struct TokenKeyPersistence {
+    /// The file the keys are kept in.
+    pub path: PathBuf,
+    /// The key the keys are sealed with.
+    pub master_key: MasterKey,
+}
+
+/// Where mail is sent through.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    /// The user name and password to log in with, if the host asks for them.
+    pub credentials: Option<(String, String)>,
+    /// The address mail is sent from.
+    pub from: String,
+}
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field(
+                "credentials",
+                &self
+                    .credentials
+                    .as_ref()
+                    .map(|(user_name, _)| (user_name, "<redacted>")),
+            )
+            .field("from", &self.from)
+            .finish()
+    }
+}
+
+/// Validated configuration for the server. Available as managed state.
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// Url of the blog database.
+    pub database_url: String,
+    /// Secret key for hashing passwords with argon2.
+    pub pw_secret: SecretBytes,
+    /// The argon2 parameters new passwords are hashed with. Existing hashes are checked with the
+    /// parameters stored alongside them.
+    pub pw_params: Argon2Params,
+    /// Settings for the cookies issued by the server.
+    pub cookies: CookieConfig,
+    /// How often the key used for tokens is rotated, and how many generations of it are kept.
+    pub key_rotation: RotationConfig,
+    /// Where the token keys are persisted, if they should survive restarts.
+    pub token_key_persistence: Option<TokenKeyPersistence>,
+    /// Whether the content security policy is only reported, rather than enforced.
+    pub csp_report_only: bool,
+    /// The origins, such as `https://static.benxu.dev`, allowed to call the API through CORS.
+    pub cors_origins: Vec<String>,
+    /// The bearer token `/metrics` must be scraped with, if any.
+    pub metrics_token: Option<String>,
+    /// Where mail is sent through. Mail is only logged without it.
+    pub smtp: Option<SmtpConfig>,
+    /// How many leading zero bits the proof of work of anonymous callers must have, if any is
+    /// asked of them.
+    pub challenge_difficulty: u8,
+    /// The origin the site is reached at, without a trailing slash.
+    pub site_origin: String,
+    /// How long statements may run before they are cancelled.
+    pub statement_timeouts: StatementTimeouts,
+    /// How long records are kept before the retention job removes them.
+    pub retention: RetentionConfig,
+    /// The most bytes the body of a post may take up.
+    pub post_body_max_bytes: usize,
+    /// How the site presents itself until admins change it.
+    pub identity: site::Identity,
+}
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("database_url", &"<redacted>")
+            .field(
+                "pw_secret",
+                &format_args!("<{} bytes>", self.pw_secret.len()),
+            )
+            .field("pw_params", &self.pw_params)
+            .field("cookies", &self.cookies)
+            .field("key_rotation", &self.key_rotation)
+            .field("token_key_persistence", &self.token_key_persistence)
+            .field("csp_report_only", &self.csp_report_only)
+            .field("cors_origins", &self.cors_origins)
+            .field(
+                "metrics_token",
+                &self.metrics_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("smtp", &self.smtp)
+            .field("challenge_difficulty", &self.challenge_difficulty)
+            .field("site_origin", &self.site_origin)
+            .field("statement_timeouts", &self.statement_timeouts)
+            .field("retention", &self.retention)
+            .field("post_body_max_bytes", &self.post_body_max_bytes)
+            .field("identity", &self.identity)
+            .finish()
+    }
+}
+
+/// A single field that is missing or invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// The environment variable the field is read from.
+    pub field: &'static str,
+    /// What is wrong with the field.
+    pub problem: String,
+}
+
+/// Every problem found while loading the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The configuration file could not be read or parsed.
+    File(PathBuf, String),
+    /// One or more fields are missing or invalid.
+    Fields(Vec<FieldError>),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::File(path, e) => {
+                write!(
+                    f,
+                    "Could not load configuration file `{}`: {}",
+                    path.display(),
+                    e
+                )
+            }
+            Error::Fields(errs) => {
+                write!(f, "Invalid server configuration:")?;
+                for e in errs {
+                    write!(f, "\n  - {}: {}", e.field, e.problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// The contents of the configuration file. Keys are the lowercased environment variable names
+/// without the `BENXU_DEV_` prefix.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    database_url: Option<String>,
+    pw_secret: Option<PathBuf>,
+    argon2_mem_kib: Option<u32>,
+    argon2_iterations: Option<u32>,
+    argon2_parallelism: Option<u32>,
+    argon2_hash_len: Option<u32>,
+    cookie_secure: Option<bool>,
+    cookie_domain: Option<String>,
+    cookie_same_site: Option<String>,
+    key_rotation_secs: Option<u64>,
+    key_generations_kept: Option<usize>,
+    key_rotation_mode: Option<String>,
+    token_key_file: Option<PathBuf>,
+    token_master_key: Option<PathBuf>,
+    csp_report_only: Option<bool>,
+    cors_origins: Option<Vec<String>>,
+    metrics_token: Option<String>,
+    smtp_host: Option<String>,
+    smtp_port: Option<u16>,
+    smtp_username: Option<String>,
+    smtp_password: Option<String>,
+    mail_from: Option<String>,
+    challenge_difficulty: Option<u8>,
+    site_origin: Option<String>,
+    statement_timeout_ms: Option<u64>,
+    long_statement_timeout_ms: Option<u64>,
+    retain_deleted_posts_days: Option<u32>,
+    retain_dead_letters_days: Option<u32>,
+    post_body_max_bytes: Option<usize>,
+    site_title: Option<String>,
+    site_tagline: Option<String>,
+    site_logo: Option<String>,
+}
+impl FileConfig {
+    fn read(path: &Path) -> Result<Self, Error> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| Error::File(path.to_owned(), e.to_string()))?;
+        toml::from_str(&contents).map_err(|e| Error::File(path.to_owned(), e.to_string()))
+    }
+}
+
+/// Collects field errors while building the configuration.
+struct Validator<'a> {
+    var: &'a dyn Fn(&str) -> Option<String>,
+    errors: Vec<FieldError>,
+}
+impl<'a> Validator<'a> {
+    fn fail(&mut self, field: &'static str, problem: impl Into<String>) {
+        self.errors.push(FieldError {
+            field,
+            problem: problem.into(),
+        });
+    }
+    /// Reads and parses the variable, falling back to `file` if the variable is not set.
+    fn parsed<T>(&mut self, field: &'static str, file: Option<T>) -> Option<T>
+    where
+        T: std::str::FromStr,
+        T::Err: fmt::Display,
+    {
+        match (self.var)(field) {
+            Some(v) => v
+                .parse()
+                .map_err(|e| self.fail(field, format!("could not parse `{}`: {}", v, e)))
+                .ok(),
+            None => file,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Loads the configuration from the environment and the configuration file in `opt`.
+    pub fn load(opt: &Opt) -> Result<Self, Error> {
+        let file = match opt.config.as_ref() {
+            Some(path) => FileConfig::read(path)?,
+            None => FileConfig::default(),
+        };
+        Self::from_sources(&|name| std::env::var(name).ok(), file)
+    }
+
+    fn from_sources(var: &dyn Fn(&str) -> Option<String>, file: FileConfig) -> Result<Self, Error> {
+        let mut v = Validator {
+            var,
+            errors: vec![],
+        };
+
+        let database_url: Option<String> = v.parsed(DATABASE_URL_ENV_VAR_NAME, file.database_url);
+        let database_url = match database_url {
+            Some(url) if url.is_empty() => {
+                v.fail(DATABASE_URL_ENV_VAR_NAME, "must not be empty");
+                None
+            }
+            None => {
+                v.fail(DATABASE_URL_ENV_VAR_NAME, "missing");
+                None
+            }
+            url => url,
+        };
+
+        let pw_secret_path = v
+            .parsed(PW_SECRET_KEY_ENV_VAR_NAME, file.pw_secret)
+            .unwrap_or_else(|| PathBuf::from(PW_SECRET_KEY_DEFAULT_PATH));
+        let pw_secret = read_pw_secret(&pw_secret_path)
+            .map_err(|e| v.fail(PW_SECRET_KEY_ENV_VAR_NAME, e))
+            .ok();
+        let defaults = Argon2Params::default();
+        let pw_params = Argon2Params {
+            mem_kib: v
+                .parsed(ARGON2_MEM_KIB_ENV_VAR_NAME, file.argon2_mem_kib)
+                .unwrap_or(defaults.mem_kib),
+            iterations: v
+                .parsed(ARGON2_ITERATIONS_ENV_VAR_NAME, file.argon2_iterations)
+                .unwrap_or(defaults.iterations),
+            parallelism: v
+                .parsed(ARGON2_PARALLELISM_ENV_VAR_NAME, file.argon2_parallelism)
+                .unwrap_or(defaults.parallelism),
+            hash_len: v
+                .parsed(ARGON2_HASH_LEN_ENV_VAR_NAME, file.argon2_hash_len)
+                .unwrap_or(defaults.hash_len),
+        };
+        match pw_params.validate() {
+            Ok(()) => {}
+            Err(ParamsError::TooFewIterations) => {
+                v.fail(ARGON2_ITERATIONS_ENV_VAR_NAME, "must be greater than zero")
+            }
+            Err(ParamsError::InvalidParallelism) => v.fail(
+                ARGON2_PARALLELISM_ENV_VAR_NAME,
+                "must be between 1 and 16777215",
+            ),
+            Err(ParamsError::TooLittleMemory { min_kib }) => v.fail(
+                ARGON2_MEM_KIB_ENV_VAR_NAME,
+                format!(
+                    "must be at least {} with the configured parallelism",
+                    min_kib
+                ),
+            ),
+            Err(ParamsError::HashTooShort) => {
+                v.fail(ARGON2_HASH_LEN_ENV_VAR_NAME, "must be at least 4")
+            }
+        }
+
+        let secure = v
+            .parsed(COOKIE_SECURE_ENV_VAR_NAME, file.cookie_secure)
+            .unwrap_or(true);
+        let domain = v.parsed(COOKIE_DOMAIN_ENV_VAR_NAME, file.cookie_domain);
+        let same_site: Option<String> =
+            v.parsed(COOKIE_SAME_SITE_ENV_VAR_NAME, file.cookie_same_site);
+        let same_site = match same_site.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            None | Some("lax") => SameSite::Lax,
+            Some("strict") => SameSite::Strict,
+            Some("none") => SameSite::None,
+            Some(other) => {
+                let problem = format!("expected `strict`, `lax`, or `none`, got `{}`", other);
+                v.fail(COOKIE_SAME_SITE_ENV_VAR_NAME, problem);
+                SameSite::Lax
+            }
+        };
+        let cookies = CookieConfig {
+            secure,
+            domain,
+            same_site,
+        };
+
+        let key_rotation_secs = v
+            .parsed(KEY_ROTATION_ENV_VAR_NAME, file.key_rotation_secs)
+            .unwrap_or(KEY_ROTATION_DEFAULT_SECS);
+        if key_rotation_secs == 0 {
+            v.fail(KEY_ROTATION_ENV_VAR_NAME, "must be greater than zero");
+        }
+        let key_generations_kept = v
+            .parsed(KEY_GENERATIONS_ENV_VAR_NAME, file.key_generations_kept)
+            .unwrap_or(KEY_GENERATIONS_DEFAULT);
+        if key_generations_kept == 0 {
+            v.fail(KEY_GENERATIONS_ENV_VAR_NAME, "must be greater than zero");
+        }
+        let mode: Option<String> = v.parsed(KEY_ROTATION_MODE_ENV_VAR_NAME, file.key_rotation_mode);
+        let key_rotation_mode = match mode.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            None | Some("threaded") => RotationMode::Threaded,
+            Some("lazy") => RotationMode::Lazy,
+            Some(other) => {
+                let problem = format!("expected `threaded` or `lazy`, got `{}`", other);
+                v.fail(KEY_ROTATION_MODE_ENV_VAR_NAME, problem);
+                RotationMode::Threaded
+            }
+        };
+
+        let token_key_file = v.parsed(TOKEN_KEY_FILE_ENV_VAR_NAME, file.token_key_file);
+        let token_master_key = v.parsed(TOKEN_MASTER_KEY_ENV_VAR_NAME, file.token_master_key);
+        let token_key_persistence = match (token_key_file, token_master_key) {
+            (Some(path), Some(master_key_path)) => read_secret(&master_key_path)
+                .and_then(|secret| match secret.len() {
+                    len if len < envelope::MIN_SECRET_BYTES => Err(format!(
+                        "must hold at least {} bytes",
+                        envelope::MIN_SECRET_BYTES
+                    )),
+                    _ => Ok(SecretBytes::new(secret)),
+                })
+                .map_err(|e| v.fail(TOKEN_MASTER_KEY_ENV_VAR_NAME, e))
+                .ok()
+                .map(|master_secret| TokenKeyPersistence {
+                    path,
+                    master_key: MasterKey::from_secret(&master_secret),
+                }),
+            (Some(_), None) => {
+                let problem = format!("must be set along with `{}`", TOKEN_KEY_FILE_ENV_VAR_NAME);
+                v.fail(TOKEN_MASTER_KEY_ENV_VAR_NAME, problem);
+                None
+            }
+            (None, Some(_)) => {
+                let problem = format!("must be set along with `{}`", TOKEN_MASTER_KEY_ENV_VAR_NAME);
+                v.fail(TOKEN_KEY_FILE_ENV_VAR_NAME, problem);
+                None
+            }
+            (None, None) => None,
+        };
+
+        let csp_report_only = v
+            .parsed(CSP_REPORT_ONLY_ENV_VAR_NAME, file.csp_report_only)
+            .unwrap_or(false);
+
+        let cors_origins = match (v.var)(CORS_ORIGINS_ENV_VAR_NAME) {
+            Some(origins) => origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            None => file.cors_origins.unwrap_or_default(),
+        };
+        for origin in cors_origins.iter().filter(|origin| !is_origin(origin)) {
+            let problem = format!("`{}` is not an origin like `https://benxu.dev`", origin);
+            v.fail(CORS_ORIGINS_ENV_VAR_NAME, problem);
+        }
+
+        let metrics_token: Option<String> =
+            v.parsed(METRICS_TOKEN_ENV_VAR_NAME, file.metrics_token);
+        let metrics_token = match metrics_token {
+            Some(token) if token.is_empty() => {
+                v.fail(METRICS_TOKEN_ENV_VAR_NAME, "must not be empty");
+                None
+            }
+            token => token,
+        };
+
+        let smtp_host: Option<String> = v.parsed(SMTP_HOST_ENV_VAR_NAME, file.smtp_host);
+        let smtp_port = v
+            .parsed(SMTP_PORT_ENV_VAR_NAME, file.smtp_port)
+            .unwrap_or(SMTP_PORT_DEFAULT);
+        let smtp_username: Option<String> =
+            v.parsed(SMTP_USERNAME_ENV_VAR_NAME, file.smtp_username);
+        let smtp_password: Option<String> =
+            v.parsed(SMTP_PASSWORD_ENV_VAR_NAME, file.smtp_password);
+        let mail_from: Option<String> = v.parsed(MAIL_FROM_ENV_VAR_NAME, file.mail_from);
+        let credentials = match (smtp_username, smtp_password) {
+            (Some(user_name), Some(password)) => Some((user_name, password)),
+            (Some(_), None) => {
+                let problem = format!("must be set along with `{}`", SMTP_USERNAME_ENV_VAR_NAME);
+                v.fail(SMTP_PASSWORD_ENV_VAR_NAME, problem);
+                None
+            }
+            (None, Some(_)) => {
+                let problem = format!("must be set along with `{}`", SMTP_PASSWORD_ENV_VAR_NAME);
+                v.fail(SMTP_USERNAME_ENV_VAR_NAME, problem);
+                None
+            }
+            (None, None) => None,
+        };
+        let smtp = match (smtp_host, mail_from) {
+            (Some(host), _) if host.is_empty() => {
+                v.fail(SMTP_HOST_ENV_VAR_NAME, "must not be empty");
+                None
+            }
+            (Some(host), Some(from)) => Some(SmtpConfig {
+                host,
+                port: smtp_port,
+                credentials,
+                from,
+            }),
+            (Some(_), None) => {
+                let problem = format!("must be set along with `{}`", SMTP_HOST_ENV_VAR_NAME);
+                v.fail(MAIL_FROM_ENV_VAR_NAME, problem);
+                None
+            }
+            (None, _) => None,
+        };
+
+        let challenge_difficulty = v
+            .parsed(CHALLENGE_DIFFICULTY_ENV_VAR_NAME, file.challenge_difficulty)
+            .unwrap_or(CHALLENGE_DIFFICULTY_DEFAULT);
+        if challenge_difficulty > CHALLENGE_DIFFICULTY_MAX {
+            let problem = format!("must be at most {}", CHALLENGE_DIFFICULTY_MAX);
+            v.fail(CHALLENGE_DIFFICULTY_ENV_VAR_NAME, problem);
+        }
+
+        let site_origin: String = v
+            .parsed(SITE_ORIGIN_ENV_VAR_NAME, file.site_origin)
+            .unwrap_or_else(|| SITE_ORIGIN_DEFAULT.to_owned());
+        if !is_origin(&site_origin) {
+            let problem = format!("`{}` is not an origin like `https://benxu.dev`", site_origin);
+            v.fail(SITE_ORIGIN_ENV_VAR_NAME, problem);
+        }
+
+        let statement_timeout_ms = v
+            .parsed(STATEMENT_TIMEOUT_ENV_VAR_NAME, file.statement_timeout_ms)
+            .unwrap_or(STATEMENT_TIMEOUT_DEFAULT_MS);
+        let long_statement_timeout_ms = v
+            .parsed(LONG_STATEMENT_TIMEOUT_ENV_VAR_NAME, file.long_statement_timeout_ms)
+            .unwrap_or(LONG_STATEMENT_TIMEOUT_DEFAULT_MS);
+        if statement_timeout_ms == 0 {
+            // Postgres reads zero as no timeout at all.
+            v.fail(STATEMENT_TIMEOUT_ENV_VAR_NAME, "must be greater than zero");
+        } else if long_statement_timeout_ms < statement_timeout_ms {
+            let problem = format!("must be at least `{}`", STATEMENT_TIMEOUT_ENV_VAR_NAME);
+            v.fail(LONG_STATEMENT_TIMEOUT_ENV_VAR_NAME, problem);
+        }
+        let statement_timeouts = StatementTimeouts {
+            standard: Duration::from_millis(statement_timeout_ms),
+            long: Duration::from_millis(long_statement_timeout_ms),
+        };
+
+        let retain_deleted_posts_days = v
+            .parsed(RETAIN_DELETED_POSTS_ENV_VAR_NAME, file.retain_deleted_posts_days)
+            .unwrap_or(RETAIN_DELETED_POSTS_DEFAULT_DAYS);
+        if retain_deleted_posts_days == 0 {
+            // Posts would be gone before they could be restored.
+            v.fail(RETAIN_DELETED_POSTS_ENV_VAR_NAME, "must be greater than zero");
+        }
+        let retain_dead_letters_days = v
+            .parsed(RETAIN_DEAD_LETTERS_ENV_VAR_NAME, file.retain_dead_letters_days)
+            .unwrap_or(RETAIN_DEAD_LETTERS_DEFAULT_DAYS);
+        if retain_dead_letters_days == 0 {
+            v.fail(RETAIN_DEAD_LETTERS_ENV_VAR_NAME, "must be greater than zero");
+        }
+        let retention =
+            RetentionConfig::from_days(retain_deleted_posts_days, retain_dead_letters_days);
+
+        let post_body_max_bytes = v
+            .parsed(POST_BODY_MAX_BYTES_ENV_VAR_NAME, file.post_body_max_bytes)
+            .unwrap_or(POST_BODY_MAX_BYTES_DEFAULT);
+        if post_body_max_bytes == 0 {
+            v.fail(POST_BODY_MAX_BYTES_ENV_VAR_NAME, "must be greater than zero");
+        } else if post_body_max_bytes as u64 > BodyClass::Medium.limit() {
+            // Larger bodies would be refused before they are ever checked.
+            let problem = format!("must be at most {} bytes", BodyClass::Medium.limit());
+            v.fail(POST_BODY_MAX_BYTES_ENV_VAR_NAME, problem);
+        }
+
+        let defaults = site::Identity::default();
+        let identity = site::Identity {
+            title: v
+                .parsed(SITE_TITLE_ENV_VAR_NAME, file.site_title)
+                .unwrap_or(defaults.title),
+            tagline: v
+                .parsed(SITE_TAGLINE_ENV_VAR_NAME, file.site_tagline)
+                .unwrap_or(defaults.tagline),
+            logo: v
+                .parsed(SITE_LOGO_ENV_VAR_NAME, file.site_logo)
+                .map(|logo: String| parse_logo(&logo))
+                .unwrap_or(defaults.logo),
+        };
+        let errors = site_identity::validate(&identity);
+        for part in errors.fields() {
+            let field = match part {
+                "title" => SITE_TITLE_ENV_VAR_NAME,
+                "tagline" => SITE_TAGLINE_ENV_VAR_NAME,
+                _ => SITE_LOGO_ENV_VAR_NAME,
+            };
+            for e in errors.field(part) {
+                v.fail(field, e.message.trim_end_matches('.').to_lowercase());
+            }
+        }
+
+        match (database_url, pw_secret) {
+            (Some(database_url), Some(pw_secret)) if v.errors.is_empty() => Ok(ServerConfig {
+                database_url,
+                pw_secret,
+                pw_params,
+                cookies,
+                key_rotation: RotationConfig {
+                    period: Duration::from_secs(key_rotation_secs),
+                    generations_kept: key_generations_kept,
+                    mode: key_rotation_mode,
+                },
+                token_key_persistence,
+                csp_report_only,
+                cors_origins,
+                metrics_token,
+                smtp,
+                challenge_difficulty,
+                site_origin,
+                statement_timeouts,
+                retention,
+                post_body_max_bytes,
+                identity,
+            }),
+            _ => Err(Error::Fields(v.errors)),
+        }
+    }
+
+    /// A configuration for tests. Uses the database at `DATABASE_URL` if set, otherwise a local
+    /// one, and a fixed password secret.
+    #[cfg(test)]
+    pub fn from_env_for_tests() -> Self {
+        ServerConfig {
+            database_url: std::env::var(DATABASE_URL_ENV_VAR_NAME)
+                .unwrap_or_else(|_| "postgres://localhost/blog".to_owned()),
+            pw_secret: vec![0; 32].into(),
+            pw_params: Argon2Params::default(),
+            cookies: CookieConfig {
+                secure: false,
+                domain: None,
+                same_site: SameSite::Lax,
+            },
+            key_rotation: RotationConfig {
+                generations_kept: KEY_GENERATIONS_DEFAULT,
+                ..RotationConfig::new(Duration::from_secs(KEY_ROTATION_DEFAULT_SECS))
+            },
+            token_key_persistence: None,
+            csp_report_only: false,
+            cors_origins: vec![],
+            metrics_token: None,
+            smtp: None,
+            challenge_difficulty: 0,
+            site_origin: SITE_ORIGIN_DEFAULT.to_owned(),
+            statement_timeouts: StatementTimeouts {
+                standard: Duration::from_millis(STATEMENT_TIMEOUT_DEFAULT_MS),
+                long: Duration::from_millis(LONG_STATEMENT_TIMEOUT_DEFAULT_MS),
+            },
+            retention: RetentionConfig::from_days(
+                RETAIN_DELETED_POSTS_DEFAULT_DAYS,
+                RETAIN_DEAD_LETTERS_DEFAULT_DAYS,
+            ),
+            post_body_max_bytes: POST_BODY_MAX_BYTES_DEFAULT,
+            identity: site::Identity::default(),
+        }
+    }
+
+    /// Adds the database url to the Rocket configuration so that the [`DB`](crate::util::blog::DB)
+    /// fairing can find it.
+    pub fn apply_to(&self, mut config: rocket::Config) -> rocket::Config {
+        use rocket::config::{Table, Value};
+        let mut database = Table::new();
+        database.insert("url".to_owned(), Value::from(self.database_url.as_str()));
+        let mut databases = Table::new();
+        databases.insert(DATABASE_NAME.to_owned(), Value::from(database));
+        config
+            .extras
+            .insert("databases".to_owned(), Value::from(databases));
+        // Caps json bodies sent without a declared length. Declared ones are held to the limit of
+        // their route by the `BodyLimits` fairing.
+        config.limits = config.limits.clone().limit("json", BodyClass::Medium.limit());
+        config
+    }
+}
+
+/// Reads a logo as configured: the markup of an svg if it looks like one, a path otherwise.
+fn parse_logo(logo: &str) -> site::Logo {
+    if logo.trim_start().starts_with('<') {
+        site::Logo::Svg {
+            svg: logo.to_owned(),
+        }
+    } else {
+        site::Logo::Asset {
+            path: logo.to_owned(),
+        }
+    }
+}
+
+/// Checks that the origin is an http or https scheme followed by a host and optional port, with
+/// nothing after them.
+fn is_origin(origin: &str) -> bool {
+    let host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"));
+    match host {
+        Some(host) => !host.is_empty() && !host.contains('/'),
+        None => false,
+    }
+}
+
+/// Reads the password hashing secret, truncating it to 32 bytes.
+fn read_pw_secret(path: &Path) -> Result<SecretBytes, String> {
+    log::debug!("Loading password secret from file {}...", path.display());
+    let mut secret = read_secret(path)?;
+    if secret.is_empty() {
+        log::warn!("No secret key provided!");
+    } else if secret.len() > 32 {
+        log::warn!("Secret key larger than expected! Truncating the secret to 32 bytes.");
+        secret.truncate(32);
+    } else if secret.len() < 16 {
+        log::warn!("Secret key far smaller than suggested 32 bytes! Please consider lengthening the secret.");
+    }
+    Ok(secret.into())
+}
+
+/// Reads the whole of a file holding a secret.
+fn read_secret(path: &Path) -> Result<Vec<u8>, String> {
+    let mut f = File::open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("could not find file `{}`", path.display()),
+        std::io::ErrorKind::PermissionDenied => {
+            format!("lacking permissions to read `{}`", path.display())
+        }
+        _ => format!("could not open `{}`: {}", path.display(), e),
+    })?;
+    let mut secret = vec![];
+    f.read_to_end(&mut secret)
+        .map_err(|e| format!("could not read `{}`: {}", path.display(), e))?;
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn load(vars: &[(&str, &str)], file: FileConfig) -> Result<ServerConfig, Error> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ServerConfig::from_sources(&|name| vars.get(name).cloned(), file)
+    }
+
+    fn secret_file() -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pw_secret_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, [1; 32]).expect("A writable temporary directory.");
+        path
+    }
+
+    #[test]
+    fn valid_environment_loads() {
+        let secret = secret_file();
+        let master_key = secret_file();
+        let config = load(
+            &[
+                (DATABASE_URL_ENV_VAR_NAME, "postgres://localhost/blog"),
+                (PW_SECRET_KEY_ENV_VAR_NAME, secret.to_str().unwrap()),
+                (ARGON2_MEM_KIB_ENV_VAR_NAME, "65536"),
+                (ARGON2_PARALLELISM_ENV_VAR_NAME, "4"),
+                (COOKIE_SECURE_ENV_VAR_NAME, "false"),
+                (COOKIE_SAME_SITE_ENV_VAR_NAME, "Strict"),
+                (KEY_ROTATION_ENV_VAR_NAME, "60"),
+                (KEY_GENERATIONS_ENV_VAR_NAME, "3"),
+                (KEY_ROTATION_MODE_ENV_VAR_NAME, "Lazy"),
+                (TOKEN_KEY_FILE_ENV_VAR_NAME, "/var/lib/benxu/token_keys"),
+                (TOKEN_MASTER_KEY_ENV_VAR_NAME, master_key.to_str().unwrap()),
+                (
+                    CORS_ORIGINS_ENV_VAR_NAME,
+                    "https://a.benxu.dev, http://localhost:8000",
+                ),
+                (METRICS_TOKEN_ENV_VAR_NAME, "scrape-me"),
+            ],
+            FileConfig::default(),
+        )
+        .expect("A valid configuration.");
+        assert_eq!(config.pw_secret, SecretBytes::new(vec![1; 32]));
+        assert_eq!(
+            config.pw_params,
+            Argon2Params {
+                mem_kib: 65536,
+                parallelism: 4,
+                ..Argon2Params::default()
+            }
+        );
+        assert!(!config.cookies.secure);
+        assert_eq!(config.cookies.same_site, SameSite::Strict);
+        assert_eq!(
+            config.cors_origins,
+            vec!["https://a.benxu.dev", "http://localhost:8000"]
+        );
+        assert_eq!(config.metrics_token.as_deref(), Some("scrape-me"));
+        assert_eq!(
+            config.key_rotation,
+            RotationConfig {
+                period: Duration::from_secs(60),
+                generations_kept: 3,
+                mode: RotationMode::Lazy,
+            }
+        );
+        let persistence = config.token_key_persistence.expect("Persisted token keys.");
+        assert_eq!(persistence.path, Path::new("/var/lib/benxu/token_keys"));
+        assert_eq!(
+            persistence.master_key.id(),
+            MasterKey::from_secret(&[1; 32]).id()
+        );
+    }
+
+    #[test]
+    fn environment_overrides_file() {
+        let file = FileConfig {
+            database_url: Some("postgres://file/blog".to_owned()),
+            pw_secret: Some(secret_file()),
+            key_rotation_secs: Some(60),
+            csp_report_only: Some(true),
+            ..FileConfig::default()
+        };
+        let config = load(&[(KEY_ROTATION_ENV_VAR_NAME, "120")], file).unwrap();
+        assert_eq!(config.database_url, "postgres://file/blog");
+        assert!(config.csp_report_only);
+        assert_eq!(config.key_rotation.period, Duration::from_secs(120));
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let missing = std::env::temp_dir().join(format!("missing_{}", uuid::Uuid::new_v4()));
+        let errs = match load(
+            &[
+                (PW_SECRET_KEY_ENV_VAR_NAME, missing.to_str().unwrap()),
+                (ARGON2_ITERATIONS_ENV_VAR_NAME, "0"),
+                (COOKIE_SECURE_ENV_VAR_NAME, "sometimes"),
+                (COOKIE_SAME_SITE_ENV_VAR_NAME, "loose"),
+                (KEY_ROTATION_ENV_VAR_NAME, "0"),
+                (KEY_GENERATIONS_ENV_VAR_NAME, "0"),
+                (KEY_ROTATION_MODE_ENV_VAR_NAME, "eager"),
+                (TOKEN_KEY_FILE_ENV_VAR_NAME, "/var/lib/benxu/token_keys"),
+                (CORS_ORIGINS_ENV_VAR_NAME, "https://benxu.dev/blog"),
+                (METRICS_TOKEN_ENV_VAR_NAME, ""),
+                (CHALLENGE_DIFFICULTY_ENV_VAR_NAME, "40"),
+                (STATEMENT_TIMEOUT_ENV_VAR_NAME, "0"),
+                (RETAIN_DEAD_LETTERS_ENV_VAR_NAME, "0"),
+                (POST_BODY_MAX_BYTES_ENV_VAR_NAME, "0"),
+                (SITE_TITLE_ENV_VAR_NAME, " "),
+                (SITE_LOGO_ENV_VAR_NAME, "https://benxu.dev/logo.svg"),
+            ],
+            FileConfig::default(),
+        ) {
+            Err(Error::Fields(errs)) => errs,
+            res => panic!("Expected field errors, got {:?}.", res),
+        };
+        let fields: Vec<_> = errs.iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                DATABASE_URL_ENV_VAR_NAME,
+                PW_SECRET_KEY_ENV_VAR_NAME,
+                ARGON2_ITERATIONS_ENV_VAR_NAME,
+                COOKIE_SECURE_ENV_VAR_NAME,
+                COOKIE_SAME_SITE_ENV_VAR_NAME,
+                KEY_ROTATION_ENV_VAR_NAME,
+                KEY_GENERATIONS_ENV_VAR_NAME,
+                KEY_ROTATION_MODE_ENV_VAR_NAME,
+                TOKEN_MASTER_KEY_ENV_VAR_NAME,
+                CORS_ORIGINS_ENV_VAR_NAME,
+                METRICS_TOKEN_ENV_VAR_NAME,
+                CHALLENGE_DIFFICULTY_ENV_VAR_NAME,
+                STATEMENT_TIMEOUT_ENV_VAR_NAME,
+                RETAIN_DEAD_LETTERS_ENV_VAR_NAME,
+                POST_BODY_MAX_BYTES_ENV_VAR_NAME,
+                SITE_TITLE_ENV_VAR_NAME,
+                SITE_LOGO_ENV_VAR_NAME,
+            ]
+        );
+    }
+
+    #[test]
+    fn long_statements_may_not_be_cut_shorter() {
+        let file = || FileConfig {
+            database_url: Some("postgres://localhost/blog".to_owned()),
+            pw_secret: Some(secret_file()),
+            ..FileConfig::default()
+        };
+        let errs = match load(&[(LONG_STATEMENT_TIMEOUT_ENV_VAR_NAME, "1000")], file()) {
+            Err(Error::Fields(errs)) => errs,
+            res => panic!("Expected field errors, got {:?}.", res),
+        };
+        let fields: Vec<_> = errs.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec![LONG_STATEMENT_TIMEOUT_ENV_VAR_NAME]);
+        let config = load(&[(STATEMENT_TIMEOUT_ENV_VAR_NAME, "1000")], file()).unwrap();
+        assert_eq!(
+            config.statement_timeouts,
+            StatementTimeouts {
+                standard: Duration::from_secs(1),
+                long: Duration::from_millis(LONG_STATEMENT_TIMEOUT_DEFAULT_MS),
+            }
+        );
+    }
+
+    #[test]
+    fn smtp_needs_a_sender() {
+        let secret = secret_file();
+        let base = [
+            (DATABASE_URL_ENV_VAR_NAME, "postgres://localhost/blog"),
+            (PW_SECRET_KEY_ENV_VAR_NAME, secret.to_str().unwrap()),
+        ];
+        let config = load(&base, FileConfig::default()).unwrap();
+        assert_eq!(config.smtp, None);
+
+        let mut vars = base.to_vec();
+        vars.push((SMTP_HOST_ENV_VAR_NAME, "smtp.example.com"));
+        vars.push((SMTP_USERNAME_ENV_VAR_NAME, "mailer"));
+        let errs = match load(&vars, FileConfig::default()) {
+            Err(Error::Fields(errs)) => errs,
+            res => panic!("Expected field errors, got {:?}.", res),
+        };
+        let fields: Vec<_> = errs.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec![SMTP_PASSWORD_ENV_VAR_NAME, MAIL_FROM_ENV_VAR_NAME]);
+
+        vars.push((SMTP_PASSWORD_ENV_VAR_NAME, "hunter2"));
+        vars.push((MAIL_FROM_ENV_VAR_NAME, "benxu.dev <no-reply@benxu.dev>"));
+        let smtp = load(&vars, FileConfig::default()).unwrap().smtp.unwrap();
+        assert_eq!(smtp.port, SMTP_PORT_DEFAULT);
+        assert_eq!(
+            smtp.credentials,
+            Some(("mailer".to_owned(), "hunter2".to_owned()))
+        );
+        assert!(!format!("{:?}", smtp).contains("hunter2"));
+    }
+
+    #[test]
+    fn database_url_is_given_to_rocket() {
+        let config = ServerConfig::from_env_for_tests();
+        let rocket_config = config.apply_to(rocket::Config::development());
+        let url = rocket_config.extras["databases"]
+            .get(DATABASE_NAME)
+            .and_then(|db| db.get("url"))
+            .and_then(|url| url.as_str());
+        assert_eq!(url, Some(config.database_url.as_str()));
+        let json_limit = rocket_config.limits.get("json");
+        assert_eq!(json_limit, Some(BodyClass::Medium.limit()));
+    }
+}
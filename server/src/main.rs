@@ -1,4 +1,7 @@
-#![feature(proc_macro_hygiene, type_ascription, decl_macro)]
+// Rocket 0.4 generates its routes with these, so the server alone still needs a nightly toolchain.
+// Every other crate builds on stable.
+#![feature(proc_macro_hygiene, decl_macro)]
+#![cfg_attr(feature = "bench", feature(test))]
 
 //! Server crate for marshalling and unmarshalling information between the blog-db and blog-client
 //! crates as well as serving a set of static pages.
@@ -6,31 +9,73 @@
 //! This utilizes the following path structure:
 //! - `/` -> Home pages and other static pages are attached here. See the [`fixed`] module for more information.
 //! - `/blog/*` -> Blog related information. See the [`blog`] module for more information.
-//! - `/public/*` -> All static resources for the site. These are served from `./public/` using the
-//!   [`StaticFiles`] module.
+//! - `/public/*` -> All static resources for the site. These are served from `./public/`. See the
+//!   [`public`] module for more information.
+//! - `/metrics` -> Metrics for scraping. See the [`metrics`] module for more information.
 
 #[macro_use]
 extern crate rocket;
+#[cfg(feature = "bench")]
+extern crate test;
 
+use blog_db::migrations;
 use crypto;
-use rocket_contrib::serve::StaticFiles;
-use std::{sync::Arc};
+use std::{sync::Arc, thread};
 use tap::*;
 
+mod admin;
 mod cfg;
 
 mod urls;
 mod util;
 
+#[cfg(test)]
+mod test_harness;
+
 use crate::{
-    urls::{blog_api_routes, blog_spa_routes, fixed_routes},
-    util::blog::DB as BlogDB,
+    urls::{
+        blog_api_routes, blog_spa_routes, fixed_routes, hash_for_fixture, metrics_routes,
+        public_routes,
+    },
+    util::{
+        assets::Assets,
+        auth::escalation::{Escalation, ProofOfWork},
+        blog::{db::CHECKOUT_TIMEOUT, DB as BlogDB},
+        body_limits::BodyLimits,
+        challenge::Challenges,
+        compression::Compression,
+        cors::Cors,
+        mailer::{self, MailQueue},
+        metrics::Metrics,
+        refuse::Refusals,
+        render_cache::RenderCache,
+        response_cache::ResponseCache,
+        retention::Retention,
+        request_log::RequestLogger,
+        security_headers::SecurityHeaders,
+        shutdown::{self, Drain},
+        site_identity::SiteIdentity,
+        site_mode::SiteMode,
+        unavailable,
+        validation,
+        visitor::ActorKey,
+        webhooks::Webhooks,
+    },
 };
 
 mod shared_html {
-    pub fn logo_markup() -> Option<page_client::data::Logo<'static>> {
-        Some(page_client::data::Logo {
-            src: "/public/img/branding.svg",
+    use blog_db::models::site;
+    use page_client::data::{Logo, LogoImage};
+
+    /// The logo of the site as it presents itself, linking home.
+    pub fn logo_markup(identity: &site::Identity) -> Option<Logo> {
+        let image = match &identity.logo {
+            site::Logo::Asset { path } => LogoImage::Src(path),
+            site::Logo::Svg { svg } => LogoImage::Svg(svg),
+        };
+        Some(Logo {
+            image,
+            alt: &identity.title,
             href: Some("/"),
         })
     }
@@ -40,16 +85,22 @@ mod shared_html {
 struct Server {
     /// SodiumOxide crypto library initialization -- used as a reminder.
     _sodiumoxide_init: (),
-    /// Key store + key rotation for the PASETO v2 local tokens used for authz.
-    _paseto_key: crypto::KeyRotator<cfg::TokenAlgo>,
+    /// Key store + key rotation for the sealed tokens used for authz.
+    paseto_key: crypto::KeyRotator<cfg::TokenAlgo>,
+    /// Key store + key rotation for the key pairs whose public halves are published.
+    signing_key: crypto::KeyRotator<cfg::SigningAlgo>,
     /// Key store for passwords secret keys.
     _local_loaded_key: Arc<crypto::StableKeyStore<cfg::PWAlgo>>,
     /// The Rocket instance managing all handlers and data routing.
     rocket: Option<rocket::Rocket>,
+    /// Signal for stopping the server, sent on SIGINT/SIGTERM or if Rocket stops.
+    shutdown: crypto::Shutdown,
+    /// Tracks the requests that must finish before stopping the server.
+    drain: Drain,
 }
 impl Server {
     /// Initializes and launches all required components that manage state in the server.
-    fn new(opt: &cfg::Opt) -> Self {
+    fn new(opt: &cfg::Opt, config: cfg::ServerConfig) -> Self {
         // Initializing environment variables.
         let public_path = {
             log::info!("Locating static files directory...");
@@ -67,10 +118,22 @@ impl Server {
             log::info!("Public directory located.");
             path
         };
+        let assets = {
+            log::info!("Hashing static resources...");
+            let mut assets = Assets::load(public_path)
+                .tap_err(|e| log::error!("Could not hash static resources due to {:?}.", e))
+                .expect("Static resources to be readable.");
+            assets
+                .inline_critical(&urls::page_css())
+                .tap_err(|e| log::error!("{}.", e))
+                .expect("Critical css to be readable.");
+            log::info!("Static resources hashed.");
+            assets
+        };
         // Initializing cryptographic system.
         let local_loaded_key = {
             log::info!("Initializing password secret key...");
-            let static_key_store = Arc::new(cfg::pw_secret(&opt));
+            let static_key_store = Arc::new(cfg::pw_secret(&config));
             log::info!("Password secret key initialized.");
             static_key_store
         };
@@ -84,39 +147,236 @@ impl Server {
             log::info!("Crypto crate initialized.");
             res
         };
+        let shutdown = {
+            log::info!("Listening for termination signals...");
+            let shutdown = crypto::Shutdown::new();
+            shutdown::signal_on_termination(shutdown.clone())
+                .tap_err(|e| log::error!("Could not listen for termination signals: {:?}", e))
+                .expect("Termination signal handler to be installed.");
+            log::info!("Listening for termination signals.");
+            shutdown
+        };
+        let drain = Drain::new(shutdown.clone());
+        let mail_queue = {
+            log::info!("Starting mail queue...");
+            let mailer = mailer::from_config(config.smtp.as_ref())
+                .tap_err(|e| log::error!("Could not set up the mailer: {}.", e))
+                .expect("The mailer to be set up.");
+            let queue = MailQueue::spawn(mailer);
+            log::info!("Mail queue started.");
+            queue
+        };
         let paseto_key = {
             log::info!("Initializing token cryptographic key rotation...");
-            let rotator = cfg::token_key();
+            let rotator = cfg::token_key(&config, shutdown.clone());
             log::info!("Token cryptographic key rotation initialized.");
             rotator
         };
+        let signing_key = {
+            log::info!("Initializing signing key pair rotation...");
+            let rotator = cfg::signing_key(&config, shutdown.clone());
+            log::info!("Signing key pair rotation initialized.");
+            rotator
+        };
         // Initializing rocket and attaching all the things.
         let rocket = {
             log::info!("Prepping Rocket...");
-            let rocket = rocket::ignite()
-                .mount(cfg::STATIC_ROOT, fixed_routes())
-                .mount(cfg::PUBLIC_ROOT, StaticFiles::from(public_path))
-                .attach(BlogDB::fairing())
-                .manage(Arc::clone(&local_loaded_key))
-                .manage(paseto_key.get_key_fixture())
-                .mount(cfg::BLOG_API_ROOT, blog_api_routes())
-                .mount(cfg::BLOG_SPA_ROOT, blog_spa_routes());
+            let rocket = build_rocket(
+                config,
+                Parts {
+                    assets,
+                    pw_key_store: Arc::clone(&local_loaded_key),
+                    token_keys: paseto_key.get_key_fixture(),
+                    signing_keys: signing_key.get_key_fixture(),
+                    mail_queue,
+                    drain: drain.clone(),
+                    shutdown: shutdown.clone(),
+                    json_request_logs: opt.json_request_logs,
+                },
+            );
             log::info!("Rocket ready for launch!");
             rocket
         };
         Server {
             _sodiumoxide_init: crypto_init,
-            _paseto_key: paseto_key,
+            paseto_key,
+            signing_key,
             _local_loaded_key: local_loaded_key,
 
             rocket: Some(rocket),
+            shutdown,
+            drain,
         }
     }
-    /// Passes off execution to [`Rocket`](rocket::Rocket).
+    /// Passes off execution to [`Rocket`](rocket::Rocket) on another thread. Sends the shutdown
+    /// signal if Rocket terminates. Returns false if Rocket has already been launched.
     ///
     /// NOTE: This behavior may change in an async version of [`Rocket`](rocket::Rocket).
-    fn start(&mut self) -> Option<rocket::error::LaunchError> {
-        self.rocket.take().map(|r| r.launch())
+    fn start(&mut self) -> bool {
+        match self.rocket.take() {
+            Some(rocket) => {
+                let shutdown = self.shutdown.clone();
+                thread::spawn(move || {
+                    let e = rocket.launch();
+                    log::error!(
+                        "Rocket has terminated with error {:?}. Server will now shutdown.",
+                        e
+                    );
+                    shutdown.signal();
+                });
+                true
+            }
+            None => false,
+        }
+    }
+    /// Blocks until the shutdown signal is sent, then waits for in-flight requests to finish and
+    /// stops the key rotation.
+    ///
+    /// Rocket 0.4 cannot be stopped, so the database pool is closed once the process exits.
+    fn wait_for_shutdown(self) {
+        self.shutdown.wait();
+        log::info!(
+            "Shutting down. Waiting up to {:?} for in-flight requests...",
+            shutdown::DRAIN_TIMEOUT
+        );
+        if self.drain.wait(shutdown::DRAIN_TIMEOUT) {
+            log::info!("All in-flight requests finished.");
+        } else {
+            log::warn!(
+                "Giving up on {} in-flight requests.",
+                self.drain.in_flight()
+            );
+        }
+        match self.paseto_key.cleanup() {
+            Ok(()) => log::info!("Token key rotation stopped."),
+            Err(e) => log::error!("Token key rotation crashed with {:?}.", e),
+        }
+        match self.signing_key.cleanup() {
+            Ok(()) => log::info!("Signing key rotation stopped."),
+            Err(e) => log::error!("Signing key rotation crashed with {:?}.", e),
+        }
+    }
+}
+
+/// What the [`Rocket`](rocket::Rocket) instance is built from, besides its configuration. Made by
+/// [`Server::new`], or by the tests in [`test_harness`].
+struct Parts {
+    assets: Assets,
+    pw_key_store: Arc<crypto::StableKeyStore<cfg::PWAlgo>>,
+    token_keys: cfg::TokenKeyFixture,
+    signing_keys: cfg::SigningKeyFixture,
+    mail_queue: MailQueue,
+    drain: Drain,
+    /// Stops the background jobs started along with the instance.
+    shutdown: crypto::Shutdown,
+    json_request_logs: bool,
+}
+
+/// Builds the [`Rocket`](rocket::Rocket) instance serving the whole site, with every fairing,
+/// route and piece of managed state attached.
+fn build_rocket(config: cfg::ServerConfig, parts: Parts) -> rocket::Rocket {
+    let rocket_config = config.apply_to(rocket::ignite().config().clone());
+    let site_mode = SiteMode::default();
+    let site_identity = SiteIdentity::new(config.identity.clone());
+    let webhooks = Webhooks::spawn(config.database_url.clone());
+    let retention = Retention::spawn(
+        config.database_url.clone(),
+        config.retention,
+        parts.shutdown,
+    );
+    let challenges = Challenges::new(config.challenge_difficulty);
+    let escalation = Escalation::new(ProofOfWork::new(&challenges));
+    let actor_key = ActorKey::derive(&config.pw_secret);
+    rocket::custom(rocket_config)
+        // Answers the requests other fairings refuse, before anything else sees their responses.
+        .attach(Refusals)
+        .attach(RequestLogger {
+            json: parts.json_request_logs,
+        })
+        .attach(parts.drain)
+        .attach(BodyLimits)
+        .attach(Compression::default())
+        .attach(SecurityHeaders::new(config.csp_report_only))
+        .attach(Cors::new(
+            config.cors_origins.clone(),
+            cfg::BLOG_API_ROOT,
+            &blog_api_routes(),
+        ))
+        .register(catchers![unavailable::catch, validation::catch])
+        .mount(cfg::STATIC_ROOT, fixed_routes())
+        .mount(cfg::STATIC_ROOT, metrics_routes())
+        .mount(cfg::PUBLIC_ROOT, public_routes())
+        .manage(parts.assets)
+        .manage(Metrics::default())
+        .manage(RenderCache::default())
+        .manage(ResponseCache::default())
+        .attach(BlogDB::fairing_with_timeouts(
+            CHECKOUT_TIMEOUT,
+            config.statement_timeouts,
+        ))
+        // Read what was saved through the pool, so they come after it.
+        .attach(site_mode.clone())
+        .manage(site_mode)
+        .attach(site_identity.clone())
+        .manage(site_identity)
+        .manage(webhooks)
+        .manage(retention)
+        .manage(parts.mail_queue)
+        .manage(challenges)
+        .manage(escalation)
+        .manage(actor_key)
+        .manage(parts.pw_key_store)
+        .manage(parts.token_keys)
+        .manage(parts.signing_keys)
+        .manage(config)
+        .mount(cfg::BLOG_API_ROOT, blog_api_routes())
+        .mount(cfg::BLOG_SPA_ROOT, blog_spa_routes())
+}
+
+/// Refuses databases migrated past what this binary knows of, and applies pending migrations if
+/// asked to with `--migrate` or `--migrate-only`.
+fn prepare_database(opt: &cfg::Opt, config: &cfg::ServerConfig) -> Result<(), String> {
+    use diesel::{Connection, PgConnection};
+    let conn = PgConnection::establish(config.database_url.as_str())
+        .map_err(|e| format!("Could not connect to the database: {}.", e))?;
+    if opt.migrate || opt.migrate_only {
+        log::info!("Applying pending migrations...");
+        migrations::run_pending_migrations(&conn)
+    } else {
+        log::info!("Checking database schema version...");
+        migrations::check_schema_version(&conn)
+    }
+    .map_err(|e| format!("Refusing to start: {}.", e))?;
+    log::info!("Database schema is usable.");
+    Ok(())
+}
+
+/// Fills the database with the development [`fixtures`](blog_db::fixtures), hashing passwords
+/// as the server does.
+fn seed_database(config: &cfg::ServerConfig, force: bool) -> Result<(), String> {
+    use diesel::{Connection, PgConnection};
+    crypto::multithread_init()
+        .map_err(|_| "Could not initialize crate `crypto` for multithreaded use.".to_owned())?;
+    let pw_key_store = cfg::pw_secret(config);
+    let conn = PgConnection::establish(config.database_url.as_str())
+        .map_err(|e| format!("Could not connect to the database: {}.", e))?;
+    log::info!("Seeding the database...");
+    blog_db::fixtures::seed(&SeedConn(conn), force, |password| {
+        hash_for_fixture(password, &pw_key_store)
+    })
+    .map_err(|e| format!("Refusing to seed: {}.", e))?;
+    log::info!(
+        "Database seeded. Log in as `{}` with password `{}`.",
+        blog_db::fixtures::ADMIN_USER_NAME,
+        blog_db::fixtures::ADMIN_PASSWORD
+    );
+    Ok(())
+}
+/// The connection used for seeding.
+struct SeedConn(diesel::PgConnection);
+impl blog_db::query::DBConn for SeedConn {
+    fn conn(&self) -> &diesel::PgConnection {
+        &self.0
     }
 }
 
@@ -125,17 +385,47 @@ fn main() {
     let opt = cfg::Opt::load();
     simple_logger::init_with_level(log::Level::Trace)
         .expect("No problems initializing simple_logger.");
+    if let Some(ms) = opt.calibrate_argon2_ms {
+        log::info!("Calibrating password hashing to take {}ms...", ms);
+        cfg::print_calibrated_pw_params(std::time::Duration::from_millis(ms));
+        return;
+    }
+    log::info!("Loading server configuration...");
+    let config = cfg::ServerConfig::load(&opt).unwrap_or_else(|e| {
+        log::error!("{}", e);
+        std::process::exit(1)
+    });
+    log::info!("Server configuration loaded.");
+    if let Some(cfg::Subcommand::Admin(command)) = opt.command.as_ref() {
+        admin::run(command, &config).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            std::process::exit(1)
+        });
+        return;
+    }
+    prepare_database(&opt, &config).unwrap_or_else(|e| {
+        log::error!("{}", e);
+        std::process::exit(1)
+    });
+    if opt.migrate_only {
+        return;
+    }
+    if opt.seed {
+        seed_database(&config, opt.force).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            std::process::exit(1)
+        });
+        return;
+    }
     log::info!("Initializing server...");
-    let mut server = Server::new(&opt);
+    let mut server = Server::new(&opt, config);
     log::info!("Server initialized!");
     log::info!("Launching rocket into the ether (aka, passing control to Rocket)...");
-    match server.start() {
-        None => log::warn!("Rocket has already been launched somehow!"),
-        Some(e) => log::error!(
-            "Rocket has terminated with error {:?}. Server will now shutdown.",
-            e
-        ),
-    };
+    if !server.start() {
+        log::warn!("Rocket has already been launched somehow!");
+    }
+    server.wait_for_shutdown();
+    log::info!("Server shut down.");
 }
 
 // TODO tests?
@@ -0,0 +1,325 @@
+//! Runs the whole server in process for tests of handlers working together, from the guards
+//! checking credentials through to the database.
+//!
+//! Each [`TestApp`] gets a scratch database of its own, cloned from a template migrated once per
+//! run and dropped along with the app, so tests neither see nor leave behind each other's rows.
+//! Users are logged in through the real login handler, so their cookies are the ones browsers get.
+//!
+//! These need a Postgres server, and the database at `DATABASE_URL` is only used to reach it. Run
+//! with `cargo test -- --ignored`.
+
+use diesel::{Connection, PgConnection, RunQueryDsl};
+use rocket::{
+    http::{ContentType, Cookie, Method, Status},
+    local::{Client, LocalResponse},
+};
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, Once};
+
+use crate::{
+    cfg::{self, ServerConfig, SigningAlgo, TokenAlgo},
+    util::{assets::Assets, auth, mailer, mailer::MailQueue, shutdown::Drain},
+    Parts,
+};
+use blog_db::{
+    models::{capabilities, credentials, users},
+    query::{CapabilityQuery, DBConn, PWQuery, UserQuery},
+};
+
+/// The database every scratch database is cloned from.
+const TEMPLATE: &str = "blog_test_template";
+/// The password of every user made by [`TestApp::user_with`].
+pub const PASSWORD: &str = "test-password";
+
+static MIGRATE_TEMPLATE: Once = Once::new();
+
+/// `url` pointing at the database `name` on the same server instead.
+fn with_database(url: &str, name: &str) -> String {
+    let (base, query) = match url.find('?') {
+        Some(at) => url.split_at(at),
+        None => (url, ""),
+    };
+    let server = base.rsplitn(2, '/').nth(1).unwrap_or(base);
+    format!("{}/{}{}", server, name, query)
+}
+
+/// A connection to the server at `DATABASE_URL`, outside any particular database.
+fn maintenance_connection() -> PgConnection {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for route tests.");
+    PgConnection::establish(&with_database(&url, "postgres"))
+        .expect("Failed to connect to the database server.")
+}
+
+/// Drops the database `name`, kicking out whoever is still connected to it.
+fn drop_database(conn: &PgConnection, name: &str) -> diesel::QueryResult<usize> {
+    diesel::sql_query(format!(
+        "SELECT pg_terminate_backend(pid) FROM pg_stat_activity \
+         WHERE datname = '{}' AND pid <> pg_backend_pid()",
+        name
+    ))
+    .execute(conn)?;
+    diesel::sql_query(format!("DROP DATABASE IF EXISTS {}", name)).execute(conn)
+}
+
+/// Creates the template afresh and migrates it, once per run.
+fn migrate_template() {
+    MIGRATE_TEMPLATE.call_once(|| {
+        crypto::multithread_init().expect("The crypto crate to initialize.");
+        let conn = maintenance_connection();
+        drop_database(&conn, TEMPLATE).expect("Failed to drop the old template database.");
+        diesel::sql_query(format!("CREATE DATABASE {}", TEMPLATE))
+            .execute(&conn)
+            .expect("Failed to create the template database.");
+        let template = PgConnection::establish(&with_database(&database_url(), TEMPLATE))
+            .expect("Failed to connect to the template database.");
+        blog_db::migrations::run_pending_migrations(&template)
+            .expect("Failed to migrate the template database.");
+    });
+}
+
+fn database_url() -> String {
+    ServerConfig::from_env_for_tests().database_url
+}
+
+/// A database of a test's own, dropped along with it.
+struct ScratchDatabase {
+    name: String,
+    url: String,
+}
+impl ScratchDatabase {
+    fn new() -> Self {
+        migrate_template();
+        let name = format!("blog_test_{}", uuid::Uuid::new_v4().to_simple());
+        // Nobody stays connected to the template, so tests may clone it at the same time.
+        diesel::sql_query(format!("CREATE DATABASE {} TEMPLATE {}", name, TEMPLATE))
+            .execute(&maintenance_connection())
+            .expect("Failed to clone the template database.");
+        let url = with_database(&database_url(), &name);
+        Self { name, url }
+    }
+}
+impl Drop for ScratchDatabase {
+    fn drop(&mut self) {
+        if let Err(e) = drop_database(&maintenance_connection(), &self.name) {
+            log::warn!("Leaving scratch database {} behind due to {:?}.", self.name, e);
+        }
+    }
+}
+
+/// A connection straight to the scratch database, for setting up what no handler can.
+pub struct ScratchConn(PgConnection);
+impl DBConn for ScratchConn {
+    fn conn(&self) -> &PgConnection {
+        &self.0
+    }
+}
+
+/// A user made for a test, along with the password they log in with.
+#[derive(Debug, Clone)]
+pub struct TestUser {
+    pub id: uuid::Uuid,
+    pub user_name: String,
+    pub password: String,
+}
+
+/// The server, running in process against a scratch database.
+pub struct TestApp {
+    // Dropped first, so that the pool lets go of the scratch database before it is dropped.
+    client: Client,
+    pw_key_store: Arc<crypto::StableKeyStore<cfg::PWAlgo>>,
+    token_keys: Option<crypto::KeyRotator<TokenAlgo>>,
+    signing_keys: Option<crypto::KeyRotator<SigningAlgo>>,
+    shutdown: crypto::Shutdown,
+    db: ScratchDatabase,
+}
+impl TestApp {
+    /// Builds the server the way [`Server::new`](crate::Server::new) does, on a fresh database.
+    pub fn new() -> Self {
+        let db = ScratchDatabase::new();
+        let config = ServerConfig {
+            database_url: db.url.clone(),
+            ..ServerConfig::from_env_for_tests()
+        };
+        let shutdown = crypto::Shutdown::new();
+        let token_keys = cfg::token_key(&config, shutdown.clone());
+        let signing_keys = cfg::signing_key(&config, shutdown.clone());
+        let pw_key_store = Arc::new(cfg::pw_secret(&config));
+        let mut assets = Assets::load("public".into()).expect("Static resources to be readable.");
+        assets
+            .inline_critical(&crate::urls::page_css())
+            .expect("Critical css to be readable.");
+        let mailer = mailer::from_config(None).expect("The development mailer to be set up.");
+        let parts = Parts {
+            assets,
+            pw_key_store: Arc::clone(&pw_key_store),
+            token_keys: token_keys.get_key_fixture(),
+            signing_keys: signing_keys.get_key_fixture(),
+            mail_queue: MailQueue::spawn(mailer),
+            drain: Drain::new(shutdown.clone()),
+            shutdown: shutdown.clone(),
+            json_request_logs: false,
+        };
+        let rocket = crate::build_rocket(config, parts);
+        // Cookies are attached by hand, so that each request says who it is from.
+        let client = Client::untracked(rocket).expect("A valid rocket instance.");
+        Self {
+            client,
+            pw_key_store,
+            token_keys: Some(token_keys),
+            signing_keys: Some(signing_keys),
+            shutdown,
+            db,
+        }
+    }
+
+    /// A connection to the database the app runs against.
+    pub fn db(&self) -> ScratchConn {
+        ScratchConn(
+            PgConnection::establish(&self.db.url)
+                .expect("Failed to connect to the scratch database."),
+        )
+    }
+
+    /// Makes a user holding `caps` everywhere, who logs in with [`PASSWORD`].
+    pub fn user_with(&self, user_name: &str, caps: &[auth::Capability]) -> TestUser {
+        let db = self.db();
+        let email = format!("{}@example.com", user_name);
+        let user = db
+            .create_user(users::New {
+                user_name,
+                created_by: None,
+                updated_by: None,
+                first_name: "Test",
+                last_name: user_name,
+                email: email.as_str(),
+            })
+            .expect("Failed to create the user.");
+        let hashed = crate::hash_for_fixture(PASSWORD, &self.pw_key_store);
+        db.create_pw_hash(credentials::pw::New {
+            created_by: user.id,
+            updated_by: user.id,
+            user_id: user.id,
+            hash: hashed.hash.as_str(),
+            salt: hashed.salt.as_str(),
+            argon2_mem_kib: hashed.argon2_mem_kib,
+            argon2_iterations: hashed.argon2_iterations,
+            argon2_parallelism: hashed.argon2_parallelism,
+        })
+        .expect("Failed to set the password of the user.");
+        let grants = caps
+            .iter()
+            .map(|cap| capabilities::New {
+                created_by: user.id,
+                user_id: user.id,
+                capability: cap.as_str(),
+                expires_at: None,
+                scope: None,
+            })
+            .collect();
+        db.create_all_capabilities(grants)
+            .expect("Failed to grant the capabilities.");
+        TestUser {
+            id: user.id,
+            user_name: user.user_name,
+            password: PASSWORD.to_owned(),
+        }
+    }
+
+    /// Logs `user` in through the login handler, returning the cookie carrying their credential.
+    pub fn log_in(&self, user: &TestUser) -> Cookie<'static> {
+        let body = serde_json::json!({
+            "Password": { "user_name": user.user_name, "password": user.password },
+        });
+        let res = self.send(Method::Post, "/api/login", None, Some(body));
+        assert_eq!(res.status(), Status::Ok, "{} could not log in", user.user_name);
+        res.cookies()
+            .into_iter()
+            .find(|cookie| cookie.name() == auth::AUTH_COOKIE_NAME)
+            .map(Cookie::into_owned)
+            .expect("Logging in to set the credential cookie.")
+    }
+
+    /// Makes a user holding `caps` and logs them in. See [`user_with`](Self::user_with).
+    pub fn logged_in_with(
+        &self,
+        user_name: &str,
+        caps: &[auth::Capability],
+    ) -> (TestUser, Cookie<'static>) {
+        let user = self.user_with(user_name, caps);
+        let cookie = self.log_in(&user);
+        (user, cookie)
+    }
+
+    /// Sends a request to `path` as whoever `credential` belongs to, or as a visitor without one,
+    /// with `body` as JSON.
+    pub fn send(
+        &self,
+        method: Method,
+        path: &str,
+        credential: Option<&Cookie<'static>>,
+        body: Option<serde_json::Value>,
+    ) -> LocalResponse {
+        let mut req = self.client.req(method, path.to_owned());
+        if let Some(cookie) = credential {
+            req = req.cookie(cookie.clone());
+        }
+        if let Some(body) = body {
+            req = req.header(ContentType::JSON).body(body.to_string());
+        }
+        req.dispatch()
+    }
+}
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        self.shutdown.signal();
+        for cleanup in vec![
+            self.token_keys.take().map(|keys| keys.cleanup()),
+            self.signing_keys.take().map(|keys| keys.cleanup()),
+        ] {
+            if let Some(Err(e)) = cleanup {
+                log::warn!("Key rotation crashed with {:?}.", e);
+            }
+        }
+    }
+}
+
+/// The body of `res` as a `T`, after checking that it was answered with `status`.
+pub fn json_body<T: DeserializeOwned>(res: &mut LocalResponse, status: Status) -> T {
+    let body = res.body_string().unwrap_or_default();
+    assert_eq!(res.status(), status, "unexpected status with body {:?}", body);
+    serde_json::from_str(&body)
+        .unwrap_or_else(|e| panic!("body {:?} is not the expected JSON: {}", body, e))
+}
+
+/// Checks that every field of `expected` is in `actual` with the same value, recursing into
+/// objects. Fields only in `actual` are left unchecked, as are the orders of object fields.
+pub fn assert_json_includes(actual: &serde_json::Value, expected: &serde_json::Value) {
+    use serde_json::Value;
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => {
+            for (key, expected) in expected {
+                match actual.get(key) {
+                    Some(actual) => assert_json_includes(actual, expected),
+                    None => panic!("missing field {:?}, expected {}", key, expected),
+                }
+            }
+        }
+        (actual, expected) => assert_eq!(actual, expected),
+    }
+}
+
+mod test {
+    use super::with_database;
+
+    #[test]
+    fn scratch_urls_keep_the_server_and_options() {
+        assert_eq!(
+            with_database("postgres://u:p@localhost:5432/blog", "blog_test_1"),
+            "postgres://u:p@localhost:5432/blog_test_1"
+        );
+        assert_eq!(
+            with_database("postgres://localhost/blog?sslmode=disable", "postgres"),
+            "postgres://localhost/postgres?sslmode=disable"
+        );
+    }
+}
@@ -1,6 +1,19 @@
 mod blog;
 mod fixed;
+mod metrics;
+mod public;
 
 pub use blog::api_routes as blog_api_routes;
 pub use blog::spa_routes as blog_spa_routes;
 pub use fixed::routes as fixed_routes;
+pub use metrics::routes as metrics_routes;
+pub use public::routes as public_routes;
+
+pub(crate) use blog::{create_account, hash_for_fixture, set_password, validate_change};
+
+/// Every stylesheet the pages are rendered with, so that the critical ones can be read at startup.
+pub fn page_css() -> Vec<page_client::data::Css<'static>> {
+    let mut css = Vec::from(blog::css_scripts());
+    css.extend(Vec::from(fixed::htmlgen::css_scripts()));
+    css
+}
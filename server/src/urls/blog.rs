@@ -1,60 +1,343 @@
 //! Marshalls the data between the [`blog_client`](../blog_client) and [`blog_db`](../blog_db).
 
 mod accounts;
+mod admin;
 mod capabilities;
+mod challenge;
 mod credentials;
 mod login;
+mod openapi;
 mod posts;
+mod roles;
+mod series;
+mod site;
+mod tags;
+mod webhooks;
+mod well_known;
 
-use crate::util::auth;
+pub(crate) use accounts::create_account;
+pub(crate) use credentials::pws::{hash_for_fixture, set_password, validate_change};
+pub(crate) use htmlgen::css_scripts;
+
+use crate::{
+    cfg::{self, ServerConfig},
+    util::{
+        assets::Assets,
+        auth,
+        blog::{db::UserQuery, DB},
+        locale::RequestLocale,
+        response_cache::{ResponseCache, Shows},
+        security_headers::CspNonce,
+        site_identity::SiteIdentity,
+        site_mode::SiteMode,
+    },
+};
+use blog_db::models::{
+    posts::Visibility,
+    site::{Identity, InitialState, Mode},
+};
 use maud::Markup;
-use rocket::Route;
+use rocket::{
+    http::{uri::Origin, RawStr, Status},
+    response::status,
+    Route, State,
+};
+use tap::*;
+
+/// The page the web app is loaded from, or the maintenance page with a 503 while the site is under
+/// maintenance. Described by `preview` when linked to, or else as the rest of the site is.
+///
+/// The web app is handed who is logged in, the mode of the site and how it presents itself along
+/// with the page, if `db` is there to find the user with. The menu marks its link to `path` as the
+/// current page.
+fn shell(
+    c: Option<&auth::UnverifiedCapabilities>,
+    db: Option<&DB>,
+    assets: &Assets,
+    nonce: &CspNonce,
+    locale: RequestLocale,
+    site_mode: &SiteMode,
+    site_identity: &SiteIdentity,
+    config: &ServerConfig,
+    path: &str,
+    preview: Option<&htmlgen::Preview>,
+) -> status::Custom<Markup> {
+    let origin = config.site_origin.as_str();
+    let identity = site_identity.get();
+    let is_logged_in = c.is_some();
+    match site_mode.get() {
+        Mode::Maintenance { message } => status::Custom(
+            Status::ServiceUnavailable,
+            htmlgen::maintenance(
+                is_logged_in,
+                locale.0,
+                &assets.manifest,
+                nonce,
+                origin,
+                &identity,
+                path,
+                &message,
+            ),
+        ),
+        mode => {
+            let state = db.and_then(|db| initial_state(db, c, mode, identity.clone()));
+            status::Custom(
+                Status::Ok,
+                htmlgen::index(
+                    is_logged_in,
+                    locale.0,
+                    &assets.manifest,
+                    nonce,
+                    origin,
+                    &identity,
+                    path,
+                    preview,
+                    state.as_ref(),
+                ),
+            )
+        }
+    }
+}
+
+/// What the web app would otherwise ask for as it loads. [`None`] if the user logged in could not
+/// be found, so that the web app asks for them itself.
+fn initial_state(
+    db: &DB,
+    c: Option<&auth::UnverifiedCapabilities>,
+    mode: Mode,
+    identity: Identity,
+) -> Option<InitialState> {
+    let user = match c {
+        Some(c) => {
+            let user = db
+                .find_user_by_id(c.user_id())
+                .tap_err(|e| log::warn!("Serving the shell without its user due to {:?}.", e))
+                .ok()?;
+            Some(user.strip_meta())
+        }
+        None => None,
+    };
+    Some(InitialState {
+        user,
+        mode,
+        identity,
+    })
+}
 
 /// Handler for serving the primary web app.
-#[get("/<_path..>")]
+#[get("/<_path..>", rank = 2)]
 pub fn get(
     _path: Option<rocket::http::uri::Segments>,
+    uri: &Origin,
     c: Option<auth::UnverifiedCapabilities>,
-) -> Markup {
-    htmlgen::index(c.is_some())
+    db: Option<DB>,
+    assets: State<Assets>,
+    nonce: CspNonce,
+    locale: RequestLocale,
+    site_mode: State<SiteMode>,
+    site_identity: State<SiteIdentity>,
+    config: State<ServerConfig>,
+) -> status::Custom<Markup> {
+    let path = uri.path();
+    shell(
+        c.as_ref(),
+        db.as_ref(),
+        &assets,
+        &nonce,
+        locale,
+        &site_mode,
+        &site_identity,
+        &config,
+        path,
+        None,
+    )
 }
 
 /// Handler for serving the primary web app for when there is no path.
 #[get("/")]
-pub fn get_unadorned(c: Option<auth::UnverifiedCapabilities>) -> Markup {
-    htmlgen::index(c.is_some())
+pub fn get_unadorned(
+    uri: &Origin,
+    c: Option<auth::UnverifiedCapabilities>,
+    db: Option<DB>,
+    assets: State<Assets>,
+    nonce: CspNonce,
+    locale: RequestLocale,
+    site_mode: State<SiteMode>,
+    site_identity: State<SiteIdentity>,
+    config: State<ServerConfig>,
+) -> status::Custom<Markup> {
+    let path = uri.path();
+    shell(
+        c.as_ref(),
+        db.as_ref(),
+        &assets,
+        &nonce,
+        locale,
+        &site_mode,
+        &site_identity,
+        &config,
+        path,
+        None,
+    )
+}
+
+/// How the page at the post `marker` names is described, or [`None`] for drafts, which are
+/// described as the rest of the site is.
+///
+/// Visitors who are not logged in are answered from `responses` if they can be, which keeps whether
+/// the post was found along with how it is described, since visitors are never shown drafts.
+fn preview_post(
+    db: &DB,
+    marker: &str,
+    c: Option<&auth::UnverifiedCapabilities>,
+    config: &ServerConfig,
+    responses: &ResponseCache,
+) -> Result<Option<htmlgen::Preview>, posts::viewable::ResolveError> {
+    use posts::viewable::ResolveError;
+    let key = format!("{}/posts/{}", cfg::BLOG_SPA_ROOT, marker);
+    let cached = match c {
+        Some(_) => None,
+        None => responses
+            .get(&key)
+            .and_then(|json| serde_json::from_str::<Option<htmlgen::Preview>>(&json).ok()),
+    };
+    if let Some(cached) = cached {
+        return cached.map(Some).ok_or(ResolveError::NotFound);
+    }
+    let found = match posts::viewable::resolve_post_for_viewer(db, marker, c) {
+        Ok(found) => found,
+        Err(ResolveError::NotFound) => {
+            if c.is_none() {
+                responses.insert(key, Shows::Nothing, "null".to_owned());
+            }
+            return Err(ResolveError::NotFound);
+        }
+        Err(e) => return Err(e),
+    };
+    let preview = if found.visibility == Visibility::Draft {
+        None
+    } else {
+        Some(htmlgen::Preview::of(&found.post, &config.site_origin))
+    };
+    if c.is_none() {
+        if let Ok(json) = serde_json::to_string(&preview) {
+            responses.insert(key, Shows::Post(found.post.id), json);
+        }
+    }
+    Ok(preview)
+}
+
+/// Handler for serving the primary web app at a post, found by its id or friendly name. The page
+/// is described by the post, so that links to it unfurl with its title, the start of its body, and
+/// its first image. Drafts are described as the rest of the site is, and posts the viewer may not
+/// see are served with a 404, as [`posts::viewable`] decides.
+#[get("/posts/<marker>", rank = 1)]
+pub fn get_post(
+    marker: &RawStr,
+    uri: &Origin,
+    db: DB,
+    c: Option<auth::UnverifiedCapabilities>,
+    assets: State<Assets>,
+    nonce: CspNonce,
+    locale: RequestLocale,
+    site_mode: State<SiteMode>,
+    site_identity: State<SiteIdentity>,
+    config: State<ServerConfig>,
+    responses: State<ResponseCache>,
+) -> status::Custom<Markup> {
+    let marker = marker.percent_decode_lossy();
+    let found = preview_post(&db, &marker, c.as_ref(), &config, &responses)
+        .tap_err(|e| log::debug!("Serving post {:?} without a preview due to {:?}.", marker, e));
+    let preview = found.as_ref().ok().and_then(Option::as_ref);
+    let mut page = shell(
+        c.as_ref(),
+        Some(&db),
+        &assets,
+        &nonce,
+        locale,
+        &site_mode,
+        &site_identity,
+        &config,
+        uri.path(),
+        preview,
+    );
+    if let (Err(posts::viewable::ResolveError::NotFound), Status::Ok) = (found, page.0) {
+        page.0 = Status::NotFound;
+    }
+    page
 }
 
 /// Provides a [`Vec`] of [`Route`]s to be attached with [`rocket::Rocket::mount()`]. Used for the
 /// SPA endpoints.
 pub fn spa_routes() -> Vec<Route> {
-    routes![get, get_unadorned]
+    routes![get, get_unadorned, get_post]
 }
 /// Provides a [`Vec`] of [`Route`]s to be attached with [`rocket::Rocket::mount()`]. Used for the
 /// api endpoints.
 pub fn api_routes() -> Vec<Route> {
     routes![
         posts::get,
+        posts::counts,
         posts::post,
         posts::post::get,
+        posts::post::rendered,
+        posts::post::export,
+        posts::post::editable,
         posts::post::patch,
         posts::post::delete,
         posts::post::publish,
         posts::post::archive,
+        posts::post::get_tags,
+        posts::post::put_tags,
+        posts::post::react,
+        posts::post::retract,
+        tags::get,
+        series::get,
+        series::post,
+        series::one::get,
+        series::one::patch,
+        series::one::delete,
+        series::one::put_posts,
         accounts::post,
+        accounts::search,
         accounts::account::get,
         accounts::account::get_self,
+        accounts::account::get_capabilities,
+        accounts::account::get_own_credentials,
         accounts::account::patch,
+        accounts::account::patch_self,
         accounts::account::delete,
         login::post,
         login::delete,
         credentials::pws::post,
+        credentials::pws::pw::get_own_id,
         credentials::pws::pw::patch,
         credentials::pws::pw::delete,
         capabilities::post,
         capabilities::delete,
         capabilities::capability::get,
         capabilities::capability::delete,
+        roles::post,
+        roles::role::get,
+        roles::role::add_capabilities,
+        roles::role::remove_capabilities,
+        roles::role::grant,
+        roles::role::revoke,
+        admin::stats,
+        admin::retention,
+        admin::rotate_keys,
+        admin::mode,
+        admin::identity,
+        webhooks::get,
+        webhooks::post,
+        webhooks::webhook::patch,
+        webhooks::webhook::delete,
+        webhooks::webhook::test,
+        site::status,
+        site::identity,
+        challenge::issue,
+        openapi::get,
+        well_known::keys,
     ]
 }
 
@@ -62,69 +345,323 @@ pub fn api_routes() -> Vec<Route> {
 /// [`blog_client`](blog_client).
 mod htmlgen {
     use maud::{html, Markup};
-    use page_client::{data, partials};
-
-    /// Create a basic menu.
-    pub fn menu() -> Option<data::Menu<'static>> {
-        Some(data::Menu(&[
-            data::MenuItem {
-                text: "Home",
-                link: Some("/"),
-                children: None,
-            },
-            data::MenuItem {
-                text: "Blog",
-                link: Some("/blog"),
-                children: None,
-            },
-        ]))
-    }
-    /// Create a basic menu for a special user.
-    pub fn logged_in_menu() -> Option<data::Menu<'static>> {
-        Some(data::Menu(&[
-            data::MenuItem {
-                text: "Blog",
-                link: Some("/blog"),
-                children: None,
-            },
-            data::MenuItem {
-                text: "Profile",
-                link: Some("/blog/profile"),
-                children: None,
-            },
-        ]))
+    use page_client::{
+        data,
+        i18n::{self, Locale},
+        markdown, partials,
+    };
+    use serde::{Deserialize, Serialize};
+
+    use crate::{cfg, util::security_headers::CspNonce};
+    use blog_db::models::{
+        posts,
+        site::{Identity, InitialState},
+    };
+
+    /// The links of the basic menu, each with the key of its text.
+    const MENU: &[(&str, &str)] = &[("menu.home", "/"), ("menu.blog", "/blog")];
+    /// The links of the menu for a special user, each with the key of its text.
+    const LOGGED_IN_MENU: &[(&str, &str)] = &[("menu.blog", "/blog"), ("menu.profile", "/blog/profile")];
+
+    /// The image shown when a page without one of its own is linked to, under the public root.
+    const DEFAULT_IMAGE: &str = "/img/propic-big.jpg";
+    /// How many characters of its body a post is described with.
+    const DESCRIPTION_CHARS: usize = 200;
+
+    /// How a page describes itself when it is linked to elsewhere.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Preview {
+        pub title: String,
+        pub description: String,
+        /// The absolute url of the image shown alongside the page.
+        pub image: String,
+        /// The absolute url the page is known by.
+        pub url: String,
+    }
+    impl Preview {
+        /// Describes `post` with its title, its description and image if it was given them, or
+        /// else the start of its body and its first image, or the site's image if it has none.
+        /// Urls are made absolute with `origin`.
+        pub fn of(post: &posts::Data, origin: &str) -> Self {
+            let marker = match &post.slug {
+                Some(slug) => slug.clone(),
+                None => post.id.to_hyphenated_ref().to_string(),
+            };
+            let image = Some(post.social_image.clone())
+                .filter(|src| !src.is_empty())
+                .or_else(|| markdown::first_image(&post.body))
+                .and_then(|src| absolute(origin, &src))
+                .unwrap_or_else(|| default_image(origin));
+            let description = if post.meta_description.is_empty() {
+                markdown::summarize(&post.body, DESCRIPTION_CHARS)
+            } else {
+                post.meta_description.clone()
+            };
+            Self {
+                title: post.title.clone(),
+                description,
+                image,
+                url: format!("{}{}/posts/{}", origin, cfg::BLOG_SPA_ROOT, marker),
+            }
+        }
+    }
+
+    /// `src` as an absolute url, if it is one already or a path on the site.
+    fn absolute(origin: &str, src: &str) -> Option<String> {
+        if src.starts_with("https://") || src.starts_with("http://") {
+            Some(src.to_owned())
+        } else if src.starts_with('/') && !src.starts_with("//") {
+            Some(format!("{}{}", origin, src))
+        } else {
+            None
+        }
+    }
+
+    fn default_image(origin: &str) -> String {
+        format!("{}{}{}", origin, cfg::PUBLIC_ROOT, DEFAULT_IMAGE)
+    }
+
+    /// The script handing `state` to `blog_client`, which reads it from
+    /// `window.__INITIAL_STATE__`.
+    ///
+    /// The characters that could end the script or the page around it are escaped, which JSON
+    /// allows since they only ever show up inside its strings.
+    pub fn initial_state_script(state: &InitialState) -> String {
+        let json = serde_json::to_string(state).unwrap_or_else(|e| {
+            log::error!("Failed to serialize the initial state due to {:?}.", e);
+            "null".to_owned()
+        });
+        let mut escaped = String::with_capacity(json.len());
+        for c in json.chars() {
+            match c {
+                '<' => escaped.push_str("\\u003c"),
+                '>' => escaped.push_str("\\u003e"),
+                '&' => escaped.push_str("\\u0026"),
+                '\u{2028}' => escaped.push_str("\\u2028"),
+                '\u{2029}' => escaped.push_str("\\u2029"),
+                c => escaped.push(c),
+            }
+        }
+        format!("window.__INITIAL_STATE__ = {};", escaped)
     }
 
     /// Returns a list of [`Css`](crate::data::Css) scripts that go in my blog page.
-    fn css_scripts<'a>() -> [data::Css<'a>; 4] {
+    pub(crate) fn css_scripts<'a>() -> [data::Css<'a>; 5] {
         [
             data::Css::Critical { src: "reset" },
             data::Css::Critical { src: "typography" },
             data::Css::Critical { src: "main" },
             data::Css::NonCritical { src: "blog" },
+            data::Css::Embedded {
+                style: markdown::highlight::theme_css(),
+            },
         ]
     }
 
-    /// Returns a basic page, as everything will be managed by `blog_client`.
-    pub fn index(is_logged_in: bool) -> Markup {
-        let (glue, load) = data::Script::wasm_bindgen_loader("blog_client");
-        let js_scripts = [
-            data::Script::External(glue.as_str()),
-            data::Script::Embedded(load.as_str()),
-        ];
-        let css_scripts = css_scripts();
-        let menu = if is_logged_in {
-            logged_in_menu()
-        } else {
-            menu()
+    /// Returns a basic page, as everything will be managed by `blog_client`. Resources are linked
+    /// through their hashed names in `assets`, and inline ones carry `nonce`. The page is described
+    /// by `preview` when linked to, if provided, and hands `blog_client` the `state` it starts
+    /// from, if known.
+    pub fn index(
+        is_logged_in: bool,
+        locale: Locale,
+        assets: &data::AssetManifest,
+        nonce: &CspNonce,
+        origin: &str,
+        identity: &Identity,
+        path: &str,
+        preview: Option<&Preview>,
+        state: Option<&InitialState>,
+    ) -> Markup {
+        let (glue, load) = data::Script::wasm_bindgen_loader_with("blog_client", Some(assets));
+        let state = state.map(initial_state_script);
+        let mut js_scripts = vec![];
+        js_scripts.extend(state.as_deref().map(data::Script::Embedded));
+        js_scripts.push(data::Script::External(glue.as_str()));
+        js_scripts.push(data::Script::Embedded(load.as_str()));
+        let content = html! { (i18n::translate(locale, "shell.loading", &[])) };
+        page(
+            is_logged_in,
+            locale,
+            assets,
+            nonce,
+            origin,
+            identity,
+            path,
+            preview,
+            &js_scripts,
+            content,
+        )
+    }
+
+    /// Returns the page shown in place of the blog while the site is under maintenance, with
+    /// `message` from whoever put it there. Leaves `blog_client` out, so that it does not make
+    /// requests the api would refuse.
+    pub fn maintenance(
+        is_logged_in: bool,
+        locale: Locale,
+        assets: &data::AssetManifest,
+        nonce: &CspNonce,
+        origin: &str,
+        identity: &Identity,
+        path: &str,
+        message: &str,
+    ) -> Markup {
+        let content = html! {
+            h1 { (i18n::translate(locale, "shell.maintenance", &[])) }
+            p { (message) }
         };
-        let logo = crate::shared_html::logo_markup();
+        page(
+            is_logged_in,
+            locale,
+            assets,
+            nonce,
+            origin,
+            identity,
+            path,
+            None,
+            &[],
+            content,
+        )
+    }
+
+    fn page(
+        is_logged_in: bool,
+        locale: Locale,
+        assets: &data::AssetManifest,
+        nonce: &CspNonce,
+        origin: &str,
+        identity: &Identity,
+        path: &str,
+        preview: Option<&Preview>,
+        js_scripts: &[data::Script],
+        content: Markup,
+    ) -> Markup {
+        let css_scripts = css_scripts();
+        let links = if is_logged_in { LOGGED_IN_MENU } else { MENU };
+        let text: Vec<_> = links.iter().map(|(key, _)| i18n::translate(locale, key, &[])).collect();
+        let items: Vec<_> = links
+            .iter()
+            .zip(text.iter())
+            .map(|((_, link), text)| data::MenuItem::link(text, link))
+            .collect();
+        let menu = data::Menu(&items);
+        let logo = crate::shared_html::logo_markup(identity);
+        let site_image = default_image(origin);
+        let crumb_names = [
+            i18n::translate(locale, "menu.home", &[]),
+            i18n::translate(locale, "menu.blog", &[]),
+        ];
+        let crumb_urls = [
+            format!("{}/", origin),
+            format!("{}{}", origin, cfg::BLOG_SPA_ROOT),
+        ];
+        let mut crumbs: Vec<_> = crumb_names
+            .iter()
+            .zip(crumb_urls.iter())
+            .map(|(name, url)| data::Crumb { name, url })
+            .collect();
+        crumbs.extend(preview.map(|p| data::Crumb {
+            name: &p.title,
+            url: &p.url,
+        }));
         let meta = data::MetaData::builder()
-            .scripts(&js_scripts[..])
+            .lang(locale.tag())
+            .site_title(&identity.title)
+            .title(preview.map(|p| p.title.as_str()))
+            .breadcrumbs(&crumbs[..])
+            .description(&identity.tagline)
+            .scripts(js_scripts)
             .css(&css_scripts[..])
-            .menu(menu.as_ref())
+            .menu(Some(&menu))
+            .current_path(Some(path))
             .logo(logo.as_ref())
+            .assets(Some(assets))
+            .nonce(Some(nonce.as_str()))
+            .og_title(preview.map(|p| p.title.as_str()))
+            .og_description(preview.map(|p| p.description.as_str()))
+            .og_image(Some(preview.map_or(site_image.as_str(), |p| p.image.as_str())))
+            .og_type(if preview.is_some() { "article" } else { "website" })
+            .canonical_url(preview.map(|p| p.url.as_str()))
             .build();
-        partials::basic_page(html! { "Loading. Please wait..." }, Some(&meta))
+        partials::basic_page(content, Some(&meta))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::htmlgen::{initial_state_script, Preview};
+    use blog_db::models::{posts, site, users};
+    use chrono::Utc;
+
+    fn post(slug: Option<&str>, body: &str) -> posts::Data {
+        posts::Data {
+            id: uuid::Uuid::from_u128(1),
+            created_at: Utc::now(),
+            created_by: uuid::Uuid::nil(),
+            updated_at: Utc::now(),
+            updated_by: None,
+            published_at: Some(Utc::now()),
+            published_by: None,
+            archived_at: None,
+            archived_by: None,
+            deleted_at: None,
+            deleted_by: None,
+            title: "Hello".to_owned(),
+            body: body.to_owned(),
+            slug: slug.map(str::to_owned),
+            meta_description: String::new(),
+            social_image: String::new(),
+        }
+    }
+
+    #[test]
+    fn posts_are_previewed_with_absolute_urls() {
+        let origin = "https://benxu.dev";
+        let preview = Preview::of(&post(Some("hello"), "Hi.\n\n![me](/public/img/me.jpg)"), origin);
+        assert_eq!(preview.url, "https://benxu.dev/blog/posts/hello");
+        assert_eq!(preview.image, "https://benxu.dev/public/img/me.jpg");
+        assert_eq!(preview.description, "Hi. me");
+
+        let preview = Preview::of(&post(None, "![relative](me.jpg)"), origin);
+        assert_eq!(preview.url, "https://benxu.dev/blog/posts/00000000-0000-0000-0000-000000000001");
+        assert_eq!(preview.image, "https://benxu.dev/public/img/propic-big.jpg");
+
+        let mut described = post(None, "Hi.\n\n![me](/public/img/me.jpg)");
+        described.meta_description = "Chosen words.".to_owned();
+        described.social_image = "https://cdn.benxu.dev/card.png".to_owned();
+        let preview = Preview::of(&described, origin);
+        assert_eq!(preview.description, "Chosen words.");
+        assert_eq!(preview.image, "https://cdn.benxu.dev/card.png");
+    }
+
+    #[test]
+    fn initial_state_cannot_break_out_of_its_script() {
+        let user = users::DataNoMeta {
+            id: uuid::Uuid::nil(),
+            user_name: "</script><script>alert(1)</script>".to_owned(),
+            created_at: Utc::now(),
+            created_by: None,
+            updated_at: Utc::now(),
+            updated_by: None,
+            first_name: Some("Ben & \u{2028}co".to_owned()),
+            last_name: None,
+            email: None,
+            preferences: Default::default(),
+        };
+        let state = site::InitialState {
+            user: Some(user),
+            mode: site::Mode::Normal,
+            identity: Default::default(),
+        };
+        let script = initial_state_script(&state);
+        assert!(!script.contains('<'));
+        assert!(!script.contains('&'));
+        assert!(!script.contains('\u{2028}'));
+        let json = script
+            .trim_start_matches("window.__INITIAL_STATE__ = ")
+            .trim_end_matches(';');
+        let parsed: site::InitialState = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed, state);
     }
 }
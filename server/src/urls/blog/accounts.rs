@@ -2,36 +2,52 @@
 
 use rocket::{
     http::{Cookies, Status},
+    response::status,
     State,
 };
 use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
 use tap::*;
 
 use crate::{
-    cfg::TokenKeyFixture,
+    cfg::{ServerConfig, TokenKeyFixture},
     util::{
         auth,
-        blog::{db::UserQuery, DB},
+        blog::{
+            db::{self, CapabilityQuery, CredentialQuery, GuardedDeletionError, UserQuery},
+            DB,
+        },
+        challenge,
         uuid_compat::ruuid_to_uuid,
+        validation::{validate_slug, Refusal, ValidationErrors},
     },
 };
 use blog_db::models::*;
 use crypto::Generational;
 
+/// Explains why deleting a user was refused when they still have posts, which keep the user who
+/// created them.
+pub const HAS_POSTS_MSG: &str = "Refusing to delete a user who still has posts.";
+/// The most users a [`search`] responds with.
+pub const SEARCH_LIMIT: i64 = 20;
+
+blog_api::assert_route!(blog_api::accounts::CREATE, Post, "/accounts");
 /// Handler for creating an account.
 ///
 /// Creates the `user_to_create` as stated in [`create_account`]. Also logs the user in question
 /// into the newly created account provided that they are not already logged in. Must have caps
-/// for [`CreateUser`][crate::blog::auth::caps::CreateUser] if already logged in.
+/// for [`CreateUser`][crate::blog::auth::caps::CreateUser] if already logged in, and a solved
+/// [challenge](crate::util::challenge) if not.
 ///
 /// As of now, no default account capabilities are provided on creation on the server side.
 #[post("/accounts", format = "json", data = "<user_to_create>")]
 pub fn post(
     capabilities: Option<auth::UnverifiedCapabilities>,
+    _proof: challenge::Proven,
     user_to_create: Json<users::NewNoMeta>,
     db: DB,
     mut cookies: Cookies,
     tok_key_store: State<TokenKeyFixture>,
+    config: State<ServerConfig>,
 ) -> Result<Json<users::DataNoMeta>, Status> {
     let user_to_create = user_to_create.into_inner();
     log::debug!("Attempting to create account {:?}.", user_to_create);
@@ -43,39 +59,88 @@ pub fn post(
         .map(|cr| cr.user_id());
     let created = create_account(&db, creator, user_to_create)
         .tap_err(|e| log::error!("Account creation failed due to {:?}", e))
-        .map_err(|_| Status::InternalServerError)?;
+        .map_err(Status::from)?;
     // Add token if not already logged in to facilitate credential creation.
     // If a credential is not created in the first session, they will currently need to contact the
     // site admin to log in again.
     if creator.is_none() {
-        let key = &tok_key_store
+        let key_store = tok_key_store
             .get_store()
             .tap_err(|_| log::error!("Token key service crashed."))
-            .map_err(|_| Status::InternalServerError)?
-            .curr;
-        let new_capabilities = auth::Capabilities::<()>::safe_new(created.id, vec![]);
-        auth::attach_capabilities_token(key, new_capabilities, &mut cookies)
             .map_err(|_| Status::InternalServerError)?;
+        let new_capabilities = auth::Capabilities::<()>::safe_new(created.id, vec![]);
+        auth::attach_capabilities_token(
+            &key_store,
+            new_capabilities,
+            &config.cookies,
+            &mut cookies,
+        )
+        .map_err(|_| Status::InternalServerError)?;
     }
     Ok(Json(created.strip_meta()))
 }
+blog_api::assert_route!(blog_api::accounts::SEARCH, Get, "/accounts?<name>");
+/// Handler for finding the users whose user name contains `name`, such as to credit them on a
+/// post. Responds with at most [`SEARCH_LIMIT`] users, ordered by user name. Requires caller to
+/// have the [`EditForeignPosts`](crate::blog::auth::caps::EditForeignPosts) capability.
+#[get("/accounts?<name>")]
+pub fn search(
+    db: DB,
+    _capabilities: auth::Capabilities<auth::caps::EditForeignPosts>,
+    name: String,
+) -> Result<Json<Vec<users::AuthorSummary>>, Status> {
+    db.search_users(name.trim(), SEARCH_LIMIT)
+        .tap_err(|e| log::error!("Failed to search users due to {:?}.", e))
+        .map(Json)
+        .map_err(Status::from)
+}
+
 /// Creates an account.
 ///
 /// Creates the `user_to_create` in the provided `db`. If the `creator` is not `None`, the
 /// created_by field will be set to the creator.
 #[must_use]
 pub fn create_account(
-    db: &DB,
+    db: &impl UserQuery,
     creator: Option<uuid::Uuid>,
     user_to_create: users::NewNoMeta,
-) -> Result<users::Data, diesel::result::Error> {
-    Ok(db.create_user(users::New::from((&user_to_create, creator)))?)
+) -> Result<users::Data, db::Error> {
+    db.create_user(users::New::from((&user_to_create, creator)))
+}
+
+/// Checks the fields being changed on an account. Names can be left alone, but not blanked out.
+///
+/// The defaults for new posts are held to the rules of posts, so that they never start a post off
+/// with something it could not be saved with.
+fn validate_changes(changes: &users::ChangedNoMeta) -> ValidationErrors {
+    let fields = [
+        ("user_name", &changes.user_name, "Enter a user name."),
+        ("first_name", &changes.first_name, "Enter a first name."),
+        ("last_name", &changes.last_name, "Enter a last name."),
+    ];
+    let mut errors = ValidationErrors::new();
+    for (field, value, message) in fields.iter() {
+        if value.as_deref().map_or(false, |v| v.trim().is_empty()) {
+            errors.add(field, "required", *message);
+        }
+    }
+    if let Some(defaults) = changes.preferences.as_ref().map(|p| &p.post_defaults) {
+        if defaults.tags.iter().any(|name| name.trim().is_empty()) {
+            errors.add("tags", "blank", "Remove the blank tags.");
+        }
+        let prefix = defaults.slug_prefix.as_str();
+        if !prefix.is_empty() {
+            validate_slug("slug_prefix", prefix, &mut errors);
+        }
+    }
+    errors
 }
 
 /// Handlers and functions for managing individual accounts.
 pub mod account {
     use super::*;
 
+    blog_api::assert_route!(blog_api::accounts::GET, Get, "/accounts/<id>");
     /// Handler to get the account info. Accounts are private only for now -- you can only
     /// view this page if you're logged in as the correct user.
     #[get("/accounts/<id>")]
@@ -91,8 +156,9 @@ pub mod account {
         db.find_user_by_id(id)
             .map(users::Data::strip_meta)
             .map(Json)
-            .map_err(|_| Status::InternalServerError)
+            .map_err(Status::from)
     }
+    blog_api::assert_route!(blog_api::accounts::GET_SELF, Get, "/accounts/me");
     /// Handler to get the account info page. Accounts are private only for now -- you can only
     /// view this page if you're logged in as the correct user.
     #[get("/accounts/me")]
@@ -105,8 +171,55 @@ pub mod account {
         db.find_user_by_id(id)
             .map(users::Data::strip_meta)
             .map(Json)
-            .map_err(|_| Status::InternalServerError)
+            .map_err(Status::from)
     }
+    blog_api::assert_route!(
+        blog_api::accounts::CAPABILITIES,
+        Get,
+        "/accounts/<id>/capabilities?<limit>&<offset>"
+    );
+    /// Handler to list a page of the capabilities a user holds, including those granted through
+    /// roles, and where each came from. Users can always view their own capabilities, but need
+    /// [`ViewCapability`](crate::blog::auth::caps::ViewCapability) to view anyone else's.
+    #[get("/accounts/<id>/capabilities?<limit>&<offset>")]
+    pub fn get_capabilities(
+        db: DB,
+        id: RUuid,
+        limit: Option<i64>,
+        offset: Option<i64>,
+        capabilities: auth::UnverifiedCapabilities,
+    ) -> Result<Json<page::Page<capabilities::Grant>>, Status> {
+        let id = ruuid_to_uuid(id);
+        if capabilities.user_id() != id {
+            capabilities
+                .into_inner()
+                .change_level::<auth::caps::ViewCapability>()
+                .map_err(|_| Status::Unauthorized)?;
+        }
+        db.get_user_capability_grants_page(id, limit, offset)
+            .map(Json)
+            .map_err(Status::from)
+    }
+    blog_api::assert_route!(
+        blog_api::accounts::OWN_CREDENTIALS,
+        Get,
+        "/accounts/me/credentials?<limit>&<offset>"
+    );
+    /// Handler to list a page of the ways the logged in user can sign in, of whatever kind, so
+    /// that they can tell what they have set up. Nothing that could be used to sign in is
+    /// included.
+    #[get("/accounts/me/credentials?<limit>&<offset>")]
+    pub fn get_own_credentials(
+        db: DB,
+        limit: Option<i64>,
+        offset: Option<i64>,
+        capabilities: auth::UnverifiedCapabilities,
+    ) -> Result<Json<page::Page<credentials::Summary>>, Status> {
+        db.find_credentials_page(capabilities.user_id(), limit, offset)
+            .map(Json)
+            .map_err(Status::from)
+    }
+    blog_api::assert_route!(blog_api::accounts::UPDATE, Patch, "/accounts/<id>");
     /// Handler to allow editing of user information if logged in as same user or has capabilities
     /// to edit users.
     #[patch("/accounts/<id>", format = "json", data = "<changes>")]
@@ -115,7 +228,7 @@ pub mod account {
         id: RUuid,
         capabilities: auth::UnverifiedCapabilities,
         changes: Json<users::ChangedNoMeta>,
-    ) -> Result<Json<users::DataNoMeta>, Status> {
+    ) -> Result<Json<users::DataNoMeta>, Refusal> {
         let id = ruuid_to_uuid(id);
         let changes = changes.into_inner();
         let updater = capabilities
@@ -129,20 +242,44 @@ pub mod account {
                     Err(Status::Unauthorized)
                 }
             })?;
+        Refusal::check(validate_changes(&changes))?;
         let changes = (&changes, Some(updater)).into();
         db.update_user_by_id(id, changes)
             .map(users::Data::strip_meta)
             .map(Json)
-            .map_err(|_| Status::InternalServerError)
+            .map_err(|e| Status::from(e).into())
     }
+    blog_api::assert_route!(blog_api::accounts::UPDATE_SELF, Patch, "/accounts/me");
+    /// Handler to edit the account the user is logged in as, which is how preferences such as the
+    /// theme are saved. Setting preferences replaces all of the ones set before.
+    #[patch("/accounts/me", format = "json", data = "<changes>")]
+    pub fn patch_self(
+        db: DB,
+        capabilities: auth::UnverifiedCapabilities,
+        changes: Json<users::ChangedNoMeta>,
+    ) -> Result<Json<users::DataNoMeta>, Refusal> {
+        let id = capabilities.user_id();
+        let changes = changes.into_inner();
+        Refusal::check(validate_changes(&changes))?;
+        let changes = (&changes, Some(id)).into();
+        db.update_user_by_id(id, changes)
+            .map(users::Data::strip_meta)
+            .map(Json)
+            .map_err(|e| Status::from(e).into())
+    }
+    blog_api::assert_route!(blog_api::accounts::DELETE, Delete, "/accounts/<id>");
     /// Handler to allow for the deletion of accounts if logged in as same user or has capabilities
     /// to delete users.
+    ///
+    /// Refuses with a 409 if this would leave nobody holding
+    /// [`ADMIN_CRITICAL`](crate::util::auth::caps::ADMIN_CRITICAL), or if the user still has posts.
+    /// Their credentials and capabilities are deleted along with them.
     #[delete("/accounts/<id>")]
     pub fn delete(
         db: DB,
         id: RUuid,
         capabilities: auth::UnverifiedCapabilities,
-    ) -> Result<Status, Status> {
+    ) -> Result<Status, status::Custom<&'static str>> {
         let id = ruuid_to_uuid(id);
         capabilities
             .into_inner()
@@ -152,11 +289,21 @@ pub mod account {
                 if id == cr.user_id() {
                     Ok(cr.user_id())
                 } else {
-                    Err(Status::Unauthorized)
+                    Err(status::Custom(Status::Unauthorized, ""))
                 }
             })?;
-        db.delete_user_by_id(id)
-            .map(|_| Status::Ok)
-            .map_err(|_| Status::InternalServerError)
+        db.delete_preserving_capability(auth::caps::ADMIN_CRITICAL, || {
+            db.delete_user_by_id(id)
+        })
+        .map(|_| Status::Ok)
+        .map_err(|e| match e {
+            GuardedDeletionError::LastHolder => {
+                status::Custom(Status::Conflict, auth::caps::LAST_ADMIN_MSG)
+            }
+            GuardedDeletionError::Query(db::Error::ForeignKeyViolation) => {
+                status::Custom(Status::Conflict, HAS_POSTS_MSG)
+            }
+            GuardedDeletionError::Query(e) => status::Custom(e.into(), ""),
+        })
     }
 }
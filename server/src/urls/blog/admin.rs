@@ -0,0 +1,188 @@
+//! Handlers for administering the site as a whole.
+
+use crypto::Generational;
+use rocket::{http::Status, State};
+use rocket_contrib::json::Json;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tap::*;
+
+use crate::{
+    cfg::TokenKeyFixture,
+    util::{
+        auth,
+        blog::{
+            db::{LongDB, SiteQuery, StatsQuery},
+            DB,
+        },
+        retention::Retention,
+        site_identity::{self, SiteIdentity},
+        site_mode::SiteMode,
+        validation::Refusal,
+    },
+};
+use blog_db::models::*;
+
+/// The result of rotating the token keys.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct KeyRotation {
+    /// The id of the generation of keys now used for new tokens.
+    pub generation: u64,
+}
+
+blog_api::assert_route!(blog_api::admin::STATS, Get, "/admin/stats");
+/// Gets counts of the users and posts of the site, and when old records were last removed.
+/// Requires caller to have the [`ViewSiteStats`](crate::blog::auth::caps::ViewSiteStats)
+/// capability. Counting goes over every row, so it gets the long statement timeout.
+#[get("/admin/stats")]
+pub fn stats(
+    db: LongDB,
+    retention: State<Retention>,
+    _capabilities: auth::Capabilities<auth::caps::ViewSiteStats>,
+) -> Result<Json<stats::Overview>, Status> {
+    let site = db
+        .get_site_stats()
+        .tap_err(|e| log::error!("Failed to compute site stats due to error {:?}.", e))
+        .map_err(Status::from)?;
+    Ok(Json(stats::Overview {
+        site,
+        retention: retention.last_runs(),
+    }))
+}
+
+blog_api::assert_route!(blog_api::admin::RETENTION, Post, "/admin/retention");
+/// Starts removing old records, such as posts deleted long ago, instead of waiting for the daily
+/// run. Answers before the records are removed, which the stats show once they are. Requires
+/// caller to have the [`RunRetention`](crate::blog::auth::caps::RunRetention) capability.
+#[post("/admin/retention")]
+pub fn retention(
+    retention: State<Retention>,
+    capabilities: auth::Capabilities<auth::caps::RunRetention>,
+) -> Status {
+    log::info!(
+        "User {} asked for old records to be removed.",
+        capabilities.user_id()
+    );
+    retention.trigger();
+    Status::Accepted
+}
+
+/// Rotates the token keys immediately, such as after a suspected compromise. Requires caller to
+/// have the [`RotateKeys`](crate::blog::auth::caps::RotateKeys) capability.
+///
+/// Tokens issued with older generations of keys stay valid for as long as those generations are
+/// kept.
+#[post("/admin/rotate-keys")]
+pub fn rotate_keys(
+    key_store: State<TokenKeyFixture>,
+    capabilities: auth::Capabilities<auth::caps::RotateKeys>,
+) -> Result<Json<KeyRotation>, Status> {
+    let rotated = key_store
+        .rotate_now()
+        .tap_err(|_| log::error!("Token key service crashed."))
+        .map_err(|_| Status::InternalServerError)?;
+    let generation = rotated.curr_generation().id;
+    log::warn!(
+        "User {} rotated the token keys to generation {}.",
+        capabilities.user_id(),
+        generation
+    );
+    Ok(Json(KeyRotation { generation }))
+}
+
+blog_api::assert_route!(blog_api::admin::MODE, Post, "/admin/mode");
+/// Switches the site to another mode, such as read only before a risky migration. The mode is
+/// saved, so that restarts keep it. Requires caller to have the
+/// [`ChangeSiteMode`](crate::blog::auth::caps::ChangeSiteMode) capability.
+#[post("/admin/mode", data = "<mode>")]
+pub fn mode(
+    db: DB,
+    site_mode: State<SiteMode>,
+    mode: Json<site::Mode>,
+    capabilities: auth::Capabilities<auth::caps::ChangeSiteMode>,
+) -> Result<Json<site::Mode>, Status> {
+    let mode = db
+        .set_site_mode(&mode.into_inner(), capabilities.user_id())
+        .tap_err(|e| log::error!("Failed to save the site mode due to error {:?}.", e))
+        .map_err(Status::from)?;
+    log::warn!(
+        "User {} switched the site to {:?}.",
+        capabilities.user_id(),
+        mode
+    );
+    site_mode.set(mode.clone());
+    Ok(Json(mode))
+}
+
+blog_api::assert_route!(blog_api::admin::IDENTITY, Patch, "/admin/identity");
+/// Changes how the site presents itself, such as its title or logo, without a restart. The
+/// identity is saved, so that restarts keep it. Requires caller to have the
+/// [`ChangeSiteIdentity`](crate::blog::auth::caps::ChangeSiteIdentity) capability.
+#[patch("/admin/identity", data = "<changes>")]
+pub fn identity(
+    db: DB,
+    site_identity: State<SiteIdentity>,
+    changes: Json<site::IdentityChanges>,
+    capabilities: auth::Capabilities<auth::caps::ChangeSiteIdentity>,
+) -> Result<Json<site::Identity>, Refusal> {
+    let identity = changes.into_inner().apply_to(site_identity.get());
+    Refusal::check(site_identity::validate(&identity))?;
+    let identity = db
+        .set_site_identity(&identity, capabilities.user_id())
+        .tap_err(|e| log::error!("Failed to save the site identity due to error {:?}.", e))
+        .map_err(Status::from)?;
+    log::info!(
+        "User {} changed the site identity to {:?}.",
+        capabilities.user_id(),
+        identity.title
+    );
+    site_identity.set(identity.clone());
+    Ok(Json(identity))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cfg::TokenAlgo;
+    use rocket::{http::Cookie, local::Client};
+
+    fn token(key_store: &TokenKeyFixture, capabilities: &[auth::Capability]) -> Cookie<'static> {
+        let held = capabilities
+            .iter()
+            .copied()
+            .map(auth::caps::Held::unrestricted)
+            .collect();
+        let capabilities = auth::Capabilities::safe_new(uuid::Uuid::new_v4(), held);
+        let value = auth::seal_capabilities(&key_store.get_store().unwrap(), capabilities).unwrap();
+        Cookie::new(auth::AUTH_COOKIE_NAME, value)
+    }
+
+    #[test]
+    fn rotating_keys_requires_the_capability() {
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, crypto::RotationConfig::default());
+        let key_store = rotator.get_key_fixture();
+        let admin = token(&key_store, &[auth::Capability::RotateKeys]);
+        let user = token(&key_store, &[auth::Capability::ViewSiteStats]);
+        let rocket = rocket::custom(rocket::Config::development())
+            .manage(rotator.get_rocket_managed_state())
+            .mount("/api", routes![rotate_keys]);
+        let client = Client::new(rocket).unwrap();
+
+        let res = client
+            .post("/api/admin/rotate-keys")
+            .cookie(user)
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+        for generation in 1..=2 {
+            let mut res = client
+                .post("/api/admin/rotate-keys")
+                .cookie(admin.clone())
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            let expected = format!(r#"{{"generation":{}}}"#, generation);
+            assert_eq!(res.body_string(), Some(expected));
+        }
+        assert_eq!(key_store.get_store().unwrap().curr_generation().id, 2);
+        rotator.cleanup().unwrap();
+    }
+}
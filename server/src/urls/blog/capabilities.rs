@@ -2,14 +2,17 @@
 
 mod error;
 use error::Error;
-mod data;
+pub mod data;
 
-use rocket::http::Status;
+use rocket::{http::Status, response::status};
 use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
 
 use crate::util::{
     auth,
-    blog::{db::CapabilityQuery, DB},
+    blog::{
+        db::{CapabilityQuery, LongDB},
+        DB,
+    },
     uuid_compat::ruuid_to_uuid,
 };
 use blog_db::models::*;
@@ -18,38 +21,46 @@ use blog_db::models::*;
 ///
 /// Only allows for requested capabilities to be created if the user logged in has all the requested
 /// capabilities as well as [`GrantCapabilities`](crate::blog::auth::caps::GrantCapability).
+/// Holding a capability only for some scope is not enough to grant it, even for that scope.
 pub fn validate_and_create_all(
     db: &DB,
     capabilities: auth::Capabilities<auth::caps::GrantCapability>,
     target_user_id: uuid::Uuid,
-    capabilities_to_create: Json<Vec<auth::Capability>>,
+    capabilities_to_create: Json<Vec<auth::caps::Held>>,
 ) -> Result<Vec<capabilities::Data>, Error> {
-    if !capabilities.has_capabilities(capabilities_to_create.as_slice()) {
+    let requested: Vec<auth::Capability> = capabilities_to_create
+        .iter()
+        .map(|held| held.capability)
+        .collect();
+    if !capabilities.has_capabilities(requested.as_slice()) {
         return Err(Error::Unauthorized);
     }
     let capabilities_to_create = capabilities_to_create
         .iter()
-        .map(|p| capabilities::New {
+        .map(|held| capabilities::New {
             created_by: capabilities.user_id(),
             user_id: target_user_id,
-            capability: p.as_str(),
+            capability: held.capability.as_str(),
+            expires_at: held.expires_at,
+            scope: held.scope,
         })
         .collect();
     Ok(db.create_all_capabilities(capabilities_to_create)?)
 }
-/// Create a list of capabilities. Requires caller to have the
+/// Create a list of capabilities, each optionally expiring or restricted to a
+/// [`Scope`](crate::util::auth::caps::Scope). Requires caller to have the
 /// [`GrantCapability`](crate::blog::auth::caps::GrantCapability) capability as well as any
-/// capabilities they wish to grant.
+/// capabilities they wish to grant. Granting in bulk gets the long statement timeout.
 #[post(
     "/capabilities/<target_user_id>",
     format = "json",
     data = "<capabilities_to_create>"
 )]
 pub fn post(
-    db: DB,
+    db: LongDB,
     capabilities: auth::Capabilities<auth::caps::GrantCapability>,
     target_user_id: RUuid,
-    capabilities_to_create: Json<Vec<auth::Capability>>,
+    capabilities_to_create: Json<Vec<auth::caps::Held>>,
 ) -> Status {
     let target_user_id = ruuid_to_uuid(target_user_id);
     validate_and_create_all(&db, capabilities, target_user_id, capabilities_to_create)
@@ -58,37 +69,43 @@ pub fn post(
 
 /// Deletes capabilities satisfying the provided [`Query`](crate::blog::capabilities::data::Query).
 /// Requires caller to have the
-/// [`DeleteCapability`](crate::blog::auth::caps::DeleteCapability) capability.
+/// [`DeleteCapability`](crate::blog::auth::caps::DeleteCapability) capability. Deleting in bulk gets
+/// the long statement timeout.
+///
+/// Refuses with a 409 if this would leave nobody holding
+/// [`ADMIN_CRITICAL`](crate::util::auth::caps::ADMIN_CRITICAL).
 #[delete("/capabilities", format = "json", data = "<to_delete>")]
 pub fn delete(
-    db: DB,
+    db: LongDB,
     _capabilities: auth::Capabilities<auth::caps::DeleteCapability>,
     to_delete: Json<data::Query>,
-) -> Result<Json<Vec<capabilities::Data>>, Status> {
+) -> Result<Json<Vec<capabilities::Data>>, status::Custom<&'static str>> {
     let to_delete = to_delete.into_inner();
-    let capabilities = vec![
-        to_delete
-            .user_id()
-            .map(|id| db.delete_capabilities_by_user_id(id))
-            .transpose()
-            .map_err(Error::from)?
-            .unwrap_or_else(Vec::new),
-        to_delete
-            .capability_ids()
-            .map(|id| db.delete_capabilities_with_ids(id))
-            .transpose()
-            .map_err(Error::from)?
-            .unwrap_or_else(Vec::new),
-    ]
-    .into_iter()
-    .flatten()
-    .collect();
+    let capabilities = db
+        .delete_preserving_capability(auth::caps::ADMIN_CRITICAL, || {
+            Ok(vec![
+                to_delete
+                    .user_id()
+                    .map(|id| db.delete_capabilities_by_user_id(id))
+                    .transpose()?
+                    .unwrap_or_else(Vec::new),
+                to_delete
+                    .capability_ids()
+                    .map(|id| db.delete_capabilities_with_ids(id))
+                    .transpose()?
+                    .unwrap_or_else(Vec::new),
+            ])
+        })
+        .map_err(Error::from)?
+        .into_iter()
+        .flatten()
+        .collect();
     Ok(Json(capabilities))
 }
 
 /// Handlers and functions for managing individual capabilities.
 pub mod capability {
-    use rocket::http::Status;
+    use rocket::{http::Status, response::status};
     use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
 
     use crate::{
@@ -101,6 +118,7 @@ pub mod capability {
     };
     use blog_db::models::*;
 
+    blog_api::assert_route!(blog_api::capabilities::GET, Get, "/capabilities/<id>");
     /// Gets the capability with the requested id. Requires caller to have the
     /// [`ViewCapability`](crate::blog::auth::caps::ViewCapability`) capability.
     #[get("/capabilities/<id>")]
@@ -114,17 +132,23 @@ pub mod capability {
             .map(Json)
             .map_err(|e| Error::from(e).into())
     }
+    blog_api::assert_route!(blog_api::capabilities::DELETE, Delete, "/capabilities/<id>");
     /// Deletes the capability with the requested id. Requires caller to have the
     /// [`DeleteCapability`](crate::blog::auth::caps::DeleteCapability`) capability.
+    ///
+    /// Refuses with a 409 if this would leave nobody holding
+    /// [`ADMIN_CRITICAL`](crate::util::auth::caps::ADMIN_CRITICAL).
     #[delete("/capabilities/<id>")]
     pub fn delete(
         db: DB,
         _capabilities: auth::Capabilities<auth::caps::DeleteCapability>,
         id: RUuid,
-    ) -> Result<Json<capabilities::Data>, Status> {
+    ) -> Result<Json<capabilities::Data>, status::Custom<&'static str>> {
         let id = ruuid_to_uuid(id);
-        db.delete_capability_with_id(id)
-            .map(Json)
-            .map_err(|e| Error::from(e).into())
+        db.delete_preserving_capability(auth::caps::ADMIN_CRITICAL, || {
+            db.delete_capability_with_id(id)
+        })
+        .map(Json)
+        .map_err(|e| Error::from(e).into())
     }
 }
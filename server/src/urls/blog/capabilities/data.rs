@@ -1,10 +1,11 @@
 //! Data structures representing specific transactions.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Represents a query for looking up capabilities in the database. TODO this is really bad, so it
 /// would be nice if we make it a kind of capability instead of a capability id.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct Query {
     /// The user id the capabilities being searched for belongs to.
     ///
@@ -1,24 +1,50 @@
 //! Errors that can occur while using the capability endpoints.
 
-pub(super) use diesel::result::Error as Diesel;
+use rocket::response::status;
+
+use crate::util::{
+    auth::caps::LAST_ADMIN_MSG,
+    blog::db::{self, GuardedDeletionError},
+};
 
 /// Represents possible errors from using the database for capabilities.
 pub enum Error {
     /// Database errors of many kinds.
-    DB(Diesel),
+    DB(db::Error),
     /// Insufficient capabilities for accessing an endpoint for capabilities.
     Unauthorized,
+    /// The operation would leave nobody holding the
+    /// [`ADMIN_CRITICAL`](crate::util::auth::caps::ADMIN_CRITICAL) capability.
+    LastAdmin,
 }
-impl From<Diesel> for Error {
-    fn from(e: Diesel) -> Self {
+impl From<db::Error> for Error {
+    fn from(e: db::Error) -> Self {
         Self::DB(e)
     }
 }
+impl From<GuardedDeletionError> for Error {
+    fn from(e: GuardedDeletionError) -> Self {
+        match e {
+            GuardedDeletionError::LastHolder => Self::LastAdmin,
+            GuardedDeletionError::Query(e) => Self::DB(e),
+        }
+    }
+}
 impl From<Error> for rocket::http::Status {
     fn from(e: Error) -> Self {
         match e {
-            Error::DB(_) => Self::InternalServerError,
+            Error::DB(e) => e.into(),
             Error::Unauthorized => Self::Unauthorized,
+            Error::LastAdmin => Self::Conflict,
         }
     }
 }
+impl From<Error> for status::Custom<&'static str> {
+    fn from(e: Error) -> Self {
+        let msg = match e {
+            Error::LastAdmin => LAST_ADMIN_MSG,
+            _ => "",
+        };
+        status::Custom(e.into(), msg)
+    }
+}
@@ -0,0 +1,28 @@
+//! Handlers for the proof of work asked of anonymous callers of public endpoints. See
+//! [`util::challenge`](crate::util::challenge) for how it is checked.
+
+use rocket::{http::Status, State};
+use rocket_contrib::json::Json;
+use std::time::SystemTime;
+use tap::*;
+
+use crate::{cfg::TokenKeyFixture, util::challenge::Challenges};
+use login_enum::challenge::Challenge;
+
+blog_api::assert_route!(blog_api::challenge::ISSUE, Get, "/challenge");
+/// Hands out a new challenge. Anyone may ask for one.
+#[get("/challenge")]
+pub fn issue(
+    challenges: State<Challenges>,
+    tok_key_store: State<TokenKeyFixture>,
+) -> Result<Json<Challenge>, Status> {
+    let key_store = tok_key_store
+        .get_store()
+        .tap_err(|_| log::error!("Token key service crashed."))
+        .map_err(|_| Status::InternalServerError)?;
+    challenges
+        .issue(&key_store, SystemTime::now())
+        .tap_err(|e| log::error!("Failed to seal a challenge due to {:?}.", e))
+        .map(Json)
+        .map_err(|_| Status::InternalServerError)
+}
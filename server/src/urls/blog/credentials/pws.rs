@@ -2,18 +2,30 @@
 
 mod data;
 
-use rocket::{http::Status, State};
+pub(crate) use data::{hash_for_fixture, set_password, validate_change};
+
+use rocket::{http::Status, response::status, State};
 use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
 
 use crate::{
     cfg::PWKeyFixture,
     util::{
         auth::{self, credentials::SavableCredential},
-        blog::{db::PWQuery, DB},
+        blog::{
+            db::{self, CredentialQuery, DBConn, GuardedDeletionError, PWQuery, UserQuery},
+            DB,
+        },
+        request_log::RequestId,
         uuid_compat::ruuid_to_uuid,
+        validation::{Refusal, ValidationErrors},
     },
 };
 
+/// Explains why deleting a credential was refused when it is the last one its user can sign in
+/// with.
+pub const LAST_CREDENTIAL_MSG: &str = "Refusing to delete the last way to sign in to the account.";
+
+blog_api::assert_route!(blog_api::credentials::CREATE_PASSWORD, Post, "/credentials/pws");
 /// Allows for the creation of new passwords. Only functions if attempting to create a password
 /// for self or if the caller possesses the
 /// [`EditUserCapabilities`](crate::blog::auth::caps::EditUserCapabilities) capabilities.
@@ -25,17 +37,22 @@ pub fn post(
     capabilities: auth::UnverifiedCapabilities,
     pw_key_store: State<PWKeyFixture>,
     to_create: Json<data::CreatePassword>,
+    req_id: RequestId,
 ) -> Status {
     use log::*;
-    let key = pw_key_store.key();
-    let to_create = data::PasswordWithBackingInfo {
-        db: &db,
-        capabilities: &capabilities,
-        argon2d_key: &key,
-        pw: &to_create,
-    };
-    let res = to_create.convert_and_save_with_capabilities();
-    debug!("Running query resulted in: {:?}", res);
+    // The checks run in the same transaction as the insert, which is rolled back if they fail.
+    let res = db.transaction(|tx| {
+        let to_create = data::PasswordWithBackingInfo {
+            db: tx,
+            capabilities: &capabilities,
+            pw_key_store: &pw_key_store,
+            pw: &to_create,
+        };
+        to_create
+            .convert_and_save_with_capabilities()
+            .map_err(|()| db::Error::from(diesel::result::Error::RollbackTransaction))
+    });
+    debug!("[{}] Running query resulted in: {:?}", req_id, res);
     res.map_or_else(|_| Status::InternalServerError, |_| Status::Ok)
 }
 
@@ -43,24 +60,37 @@ pub fn post(
 pub mod pw {
     use super::*;
 
+    blog_api::assert_route!(blog_api::credentials::OWN_PASSWORD, Get, "/credentials/pws/me");
+    /// Handler for finding the id of the password of the signed in account, which is needed to
+    /// change it.
+    #[get("/credentials/pws/me")]
+    pub fn get_own_id(
+        db: DB,
+        capabilities: auth::UnverifiedCapabilities,
+    ) -> Result<Json<uuid::Uuid>, Status> {
+        db.find_user_by_id(capabilities.user_id())
+            .and_then(|user| db.find_pw_hash_by_user(&user))
+            .map(|pw_rec| Json(pw_rec.id))
+            .map_err(Status::from)
+    }
+    blog_api::assert_route!(blog_api::credentials::CHANGE_PASSWORD, Patch, "/credentials/pws/<id>");
     /// Handler for changing a password. Must be chaning own capabilities or have the
     /// [`EditUserCapability`](crate::blog::auth::caps::EditUserCapability) capabilities.
+    ///
+    /// Changing your own password also requires the current one. Refuses with a 422 naming the
+    /// fields at fault if the new password is empty or the current one is missing or wrong.
     #[patch("/credentials/pws/<id>", format = "json", data = "<changed_pw>")]
     pub fn patch(
         db: DB,
         pw_key_store: State<PWKeyFixture>,
         capabilities: auth::UnverifiedCapabilities,
         id: RUuid,
-        changed_pw: Json<String>,
-    ) -> Result<Status, Status> {
+        changed_pw: Json<data::ChangePassword>,
+        req_id: RequestId,
+    ) -> Result<Status, Refusal> {
         let id = ruuid_to_uuid(id);
-        let target_user_id =
-            db.find_pw_by_id(id)
-                .map(|pw_rec| pw_rec.user_id)
-                .map_err(|e| match e {
-                    diesel::result::Error::NotFound => Status::NotFound,
-                    _ => Status::InternalServerError,
-                })?;
+        let stored = db.find_pw_by_id(id).map_err(Status::from)?;
+        let target_user_id = stored.user_id;
         let capabilities: auth::UnverifiedCapabilities = capabilities
             .into_inner()
             .change_level::<auth::caps::EditUserCredentials>()
@@ -73,40 +103,55 @@ pub mod pw {
                 }
             })?
             .into();
+        let changed_pw = changed_pw.into_inner();
+        let own = target_user_id == capabilities.user_id();
+        Refusal::check(data::validate_change(&changed_pw, own))?;
+        if own && !data::is_current(&changed_pw, stored, &pw_key_store) {
+            return Err(ValidationErrors::single(
+                "current",
+                "incorrect",
+                "This is not your current password.",
+            )
+            .into());
+        }
         let update = data::CreatePassword {
-            user_id: capabilities.user_id(),
-            password: changed_pw.into_inner(),
-        };
-        let key = pw_key_store.key();
-        let to_create = data::PasswordWithBackingInfo {
-            db: &db,
-            capabilities: &capabilities,
-            argon2d_key: &key,
-            pw: &update,
+            user_id: target_user_id,
+            password: changed_pw.new,
         };
-        to_create
-            .convert_and_update_with_capabilities()
-            .map(|_| Status::Ok)
-            .map_err(|_| Status::InternalServerError)
+        db.transaction(|tx| {
+            let to_update = data::PasswordWithBackingInfo {
+                db: tx,
+                capabilities: &capabilities,
+                pw_key_store: &pw_key_store,
+                pw: &update,
+            };
+            to_update
+                .convert_and_update_with_capabilities()
+                .map_err(|()| db::Error::from(diesel::result::Error::RollbackTransaction))
+        })
+        .map(|_| Status::Ok)
+        .map_err(|e| {
+            log::error!("[{}] Failed to update password {:?}: {:?}.", req_id, id, e);
+            Status::InternalServerError.into()
+        })
     }
+    blog_api::assert_route!(blog_api::credentials::DELETE_PASSWORD, Delete, "/credentials/pws/<id>");
     /// Handler for deleting a password. Must be changing own credentials or have the
     /// [`EditUserCredentials`](crate::blog::auth::caps::EditUserCredentials) capabilities.
     ///
-    /// An example use case is when you wish to utilize only FIDO or OAuth to log in.
+    /// An example use case is when you wish to utilize only FIDO or OAuth to log in. Refuses with
+    /// a 409 if the user has no other credential to sign in with.
     #[delete("/credentials/pws/<id>")]
     pub fn delete(
         db: DB,
         capabilities: auth::UnverifiedCapabilities,
         id: RUuid,
-    ) -> Result<Status, Status> {
+    ) -> Result<Status, status::Custom<&'static str>> {
         let id = ruuid_to_uuid(id);
-        let target_user_id =
-            db.find_pw_by_id(id)
-                .map(|pw_rec| pw_rec.user_id)
-                .map_err(|e| match e {
-                    diesel::result::Error::NotFound => Status::NotFound,
-                    _ => Status::InternalServerError,
-                })?;
+        let target_user_id = db
+            .find_pw_by_id(id)
+            .map(|pw_rec| pw_rec.user_id)
+            .map_err(|e| status::Custom(e.into(), ""))?;
         capabilities
             .into_inner()
             .change_level::<auth::caps::EditUserCredentials>()
@@ -115,11 +160,16 @@ pub mod pw {
                 if target_user_id == cr.user_id() {
                     Ok(())
                 } else {
-                    Err(Status::Unauthorized)
+                    Err(status::Custom(Status::Unauthorized, ""))
                 }
             })?;
-        db.delete_pw_by_id(id)
+        db.delete_preserving_credential(target_user_id, || db.delete_pw_by_id(id))
             .map(|_| Status::Ok)
-            .map_err(|_| Status::InternalServerError)
+            .map_err(|e| match e {
+                GuardedDeletionError::LastHolder => {
+                    status::Custom(Status::Conflict, LAST_CREDENTIAL_MSG)
+                }
+                GuardedDeletionError::Query(e) => status::Custom(e.into(), ""),
+            })
     }
 }
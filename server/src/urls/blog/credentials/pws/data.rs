@@ -1,33 +1,159 @@
 //! Data structures holding pertinent login information per request.
 
 use crate::{
-    cfg::PWAlgo,
+    cfg::{PWAlgo, PWKeyFixture},
+    urls::blog::login::data::{AuthnWithStored, Password},
     util::{
         auth::{self, caps::Verifiable, credentials::SavableCredential},
-        blog::{
-            db::{PWQuery, UserQuery},
-            DB,
-        },
+        blog::db::{self, DBConn, PWQuery, UserQuery},
+        validation::ValidationErrors,
     },
 };
 use blog_db::models::*;
 use boolinator::Boolinator;
-use crypto::algo::{hash::symmetric::Algo as HashA, Algo as A};
-pub(super) use login_enum::CreatePassword;
+use crypto::{
+    algo::{
+        hash::{argon2::Argon2Params, symmetric::Algo as HashA},
+        SecretBytes,
+    },
+    encoding::{Base64, Encoding},
+};
+pub(super) use login_enum::{ChangePassword, CreatePassword};
+
+/// Checks the shape of a password change. Changes to your own password need the current one.
+pub(crate) fn validate_change(change: &ChangePassword, own: bool) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    if own && change.current.as_deref().map_or(true, str::is_empty) {
+        errors.add("current", "required", "Enter your current password.");
+    }
+    if change.new.is_empty() {
+        errors.add("new", "required", "Enter a new password.");
+    }
+    errors
+}
+
+/// Whether the current password given with the change matches the stored one.
+pub(super) fn is_current(
+    change: &ChangePassword,
+    stored: credentials::pw::Data,
+    pw_key_store: &PWKeyFixture,
+) -> bool {
+    let current = Password {
+        // Only needed to find the stored password, which is already at hand.
+        user_name: String::new(),
+        password: change.current.clone().unwrap_or_default(),
+    };
+    AuthnWithStored::Password(&current, stored)
+        .verify_with_err(&*pw_key_store.key())
+        .is_ok()
+}
+
+/// Hashes the password with a generated salt. Returns the generated salt, the hashed password,
+/// and the parameters it was hashed with.
+fn hash(
+    password: &str,
+    pw_key_store: &crypto::StableKeyStore<PWAlgo>,
+) -> (SecretBytes, SecretBytes, Argon2Params) {
+    let msg = &<PWAlgo as HashA>::VerificationInput::new_default_hash_len(
+        password.as_bytes().to_vec(),
+        None,
+    );
+    let generated_salt = msg.salt();
+    let algo = pw_key_store.alg();
+    let pw_hash = algo.sign(msg, pw_key_store.key());
+    (generated_salt.into(), pw_hash.into(), algo.params())
+}
+
+/// A password hashed and encoded the way it is stored.
+struct Stored {
+    hash: String,
+    salt: String,
+    params: Argon2Params,
+}
+impl Stored {
+    fn new(password: &str, pw_key_store: &crypto::StableKeyStore<PWAlgo>) -> Self {
+        let (generated_salt, pw_hash, params) = hash(password, pw_key_store);
+        Self {
+            hash: Base64::encode(&pw_hash),
+            salt: Base64::encode(&generated_salt),
+            params,
+        }
+    }
+    /// The row for the password of the user with the id `user_id`, set by `by`.
+    fn row(&self, user_id: uuid::Uuid, by: uuid::Uuid) -> credentials::pw::New {
+        credentials::pw::New {
+            created_by: by,
+            updated_by: by,
+            user_id,
+            hash: self.hash.as_str(),
+            salt: self.salt.as_str(),
+            argon2_mem_kib: self.params.mem_kib as i32,
+            argon2_iterations: self.params.iterations as i32,
+            argon2_parallelism: self.params.parallelism as i32,
+        }
+    }
+    /// The change replacing a password with this one, made by `by`.
+    fn change(self, by: uuid::Uuid) -> credentials::pw::Changed {
+        credentials::pw::Changed {
+            updated_by: by,
+            hash: Some(self.hash),
+            salt: Some(self.salt),
+            argon2_mem_kib: Some(self.params.mem_kib as i32),
+            argon2_iterations: Some(self.params.iterations as i32),
+            argon2_parallelism: Some(self.params.parallelism as i32),
+        }
+    }
+}
+
+/// Hashes the password the way it is stored when created through [`post`](super::post), for
+/// seeding the database with [`fixtures`](blog_db::fixtures).
+pub(crate) fn hash_for_fixture(
+    password: &str,
+    pw_key_store: &crypto::StableKeyStore<PWAlgo>,
+) -> blog_db::fixtures::HashedPassword {
+    let stored = Stored::new(password, pw_key_store);
+    blog_db::fixtures::HashedPassword {
+        hash: stored.hash,
+        salt: stored.salt,
+        argon2_mem_kib: stored.params.mem_kib as i32,
+        argon2_iterations: stored.params.iterations as i32,
+        argon2_parallelism: stored.params.parallelism as i32,
+    }
+}
+
+/// Sets the password of `user`, as `by`, creating it if they have none. Hashes and stores the
+/// password the same way as [`post`](super::post) and [`patch`](super::pw::patch), without the
+/// checks on who may set it, for the [`admin`](crate::admin) tool. Check the password with
+/// [`validate_change`] first.
+pub(crate) fn set_password<C: DBConn>(
+    db: &C,
+    user: &users::Data,
+    by: uuid::Uuid,
+    password: &str,
+    pw_key_store: &crypto::StableKeyStore<PWAlgo>,
+) -> Result<(), db::Error> {
+    let stored = Stored::new(password, pw_key_store);
+    if db.count_pw_by_user(user)? == 0 {
+        db.create_pw_hash(stored.row(user.id, by)).map(|_| ())
+    } else {
+        db.update_pw_hash_for_user_id(user.id, stored.change(by))
+            .map(|_| ())
+    }
+}
 
 /// A view into [`Password`](crate::blog::credentials::data::Password) together with the database
-/// used to store credentials, and the secret key for the password hash.
-pub(super) struct PasswordWithBackingInfo<'a> {
-    /// A reference to the [`DB`](crate::blog::DB) we will be using for verification.
-    pub(super) db: &'a DB,
+/// used to store credentials, and the password hash with its secret key.
+pub(super) struct PasswordWithBackingInfo<'a, C: DBConn> {
+    /// A reference to the connection, usually in a transaction, we will be using for verification.
+    pub(super) db: &'a C,
     /// A reference to the [`Capabilities`](crate::blog::auth::Capabilities) related to the request.
     pub(super) capabilities: &'a auth::UnverifiedCapabilities,
-    /// A reference to the secret key for the password hashing.
-    pub(super) argon2d_key: &'a <PWAlgo as A>::Key,
+    /// The password hash, with the configured parameters, and its secret key.
+    pub(super) pw_key_store: &'a crypto::StableKeyStore<PWAlgo>,
     /// A reference to the password credential data. Notice that this is not just a [`String`].
     pub(super) pw: &'a CreatePassword,
 }
-impl<'a> PasswordWithBackingInfo<'a> {
+impl<'a, C: DBConn> PasswordWithBackingInfo<'a, C> {
     /// Checks to ensure that the capabilities provided matches the (assumed) owner of the password
     /// to be changed. This means that the capabilities have the
     /// [`EditUserCredentials`](crate::blog::auth::caps::EditUserCredentials) capabilities or
@@ -48,7 +174,7 @@ impl<'a> PasswordWithBackingInfo<'a> {
     }
     /// Checks if there are duplicate password entries, aka multiple passwords per user. This
     /// should not be allowed, and this helps detecting such situations.
-    fn verify_duplicates(&self, target_count: usize) -> Result<bool, diesel::result::Error> {
+    fn verify_duplicates(&self, target_count: usize) -> Result<bool, db::Error> {
         use log::*;
         debug!(
             "Attempting to check for duplicate password entries for {:?}.",
@@ -67,22 +193,15 @@ impl<'a> PasswordWithBackingInfo<'a> {
     /// [`verify_requester`](crate::blog::credentials::data::PasswordWithBackingInfo::verify_requester)
     /// and
     /// [`verify_duplicates`](crate::blog::credentials::data::PasswordWithBackingInfo::verify_duplicates).
-    fn verify(&self, duplicate_count: usize) -> Result<bool, diesel::result::Error> {
+    fn verify(&self, duplicate_count: usize) -> Result<bool, db::Error> {
         Ok(self.verify_requester() && self.verify_duplicates(duplicate_count)?)
     }
-    /// Hashes the password with a generated salt. Returns first the generated salt, then the
-    /// hashed password.
-    fn hash(&self) -> (Vec<u8>, Vec<u8>) {
-        let msg = &<PWAlgo as HashA>::VerificationInput::new_default_hash_len(
-            self.pw.password.as_bytes().to_vec(),
-            None,
-        );
-        let generated_salt = msg.salt();
-        let pw_hash = PWAlgo::new(None).sign(msg, self.argon2d_key);
-        (generated_salt.to_vec(), pw_hash)
+    /// Hashes the password. See [`hash`].
+    fn hash(&self) -> Stored {
+        Stored::new(&self.pw.password, self.pw_key_store)
     }
 }
-impl<'a> SavableCredential for PasswordWithBackingInfo<'a> {
+impl<'a, C: DBConn> SavableCredential for PasswordWithBackingInfo<'a, C> {
     type Success = ();
     type Error = ();
     fn convert_and_save_with_capabilities(self) -> Result<Self::Success, Self::Error> {
@@ -96,15 +215,11 @@ impl<'a> SavableCredential for PasswordWithBackingInfo<'a> {
             .map_err(|_| ())
             .and_then(|b| b.as_result((), ()))?;
         debug!("Verified. Hashing.");
-        let (generated_salt, pw_hash) = self.hash();
+        let stored = self.hash();
         debug!("Hashed. Saving.");
-        let creation = self.db.create_pw_hash(credentials::pw::New {
-            created_by: self.capabilities.user_id(),
-            updated_by: self.capabilities.user_id(),
-            user_id: self.pw.user_id,
-            hash: base64::encode(pw_hash.as_slice()).as_str(),
-            salt: base64::encode(generated_salt.as_slice()).as_str(),
-        });
+        let creation = self
+            .db
+            .create_pw_hash(stored.row(self.pw.user_id, self.capabilities.user_id()));
         debug!("Attempt: {:?}", creation);
         creation.map(|_| ()).map_err(|_| ())
     }
@@ -112,17 +227,69 @@ impl<'a> SavableCredential for PasswordWithBackingInfo<'a> {
         self.verify(1)
             .map_err(|_| ())
             .and_then(|b| b.as_result((), ()))?;
-        let (generated_salt, pw_hash) = self.hash();
+        let stored = self.hash();
         self.db
             .update_pw_hash_for_user_id(
                 self.pw.user_id,
-                credentials::pw::Changed {
-                    updated_by: self.capabilities.user_id(),
-                    hash: Some(base64::encode(pw_hash.as_slice())),
-                    salt: Some(base64::encode(generated_salt.as_slice())),
-                },
+                stored.change(self.capabilities.user_id()),
             )
             .map(|_| ())
             .map_err(|_| ())
     }
 }
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+
+    fn change(current: Option<&str>, new: &str) -> ChangePassword {
+        ChangePassword {
+            current: current.map(str::to_owned),
+            new: new.to_owned(),
+        }
+    }
+    fn fields(errors: ValidationErrors) -> Vec<String> {
+        errors.fields().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn own_changes_need_the_current_password() {
+        assert_eq!(
+            fields(validate_change(&change(None, "new"), true)),
+            vec!["current"]
+        );
+        assert_eq!(
+            fields(validate_change(&change(Some(""), "new"), true)),
+            vec!["current"]
+        );
+        assert!(validate_change(&change(Some("old"), "new"), true).is_empty());
+        assert!(validate_change(&change(None, "new"), false).is_empty());
+    }
+
+    #[test]
+    fn new_password_cannot_be_empty() {
+        assert_eq!(
+            fields(validate_change(&change(None, ""), true)),
+            vec!["current", "new"]
+        );
+        assert_eq!(
+            fields(validate_change(&change(None, ""), false)),
+            vec!["new"]
+        );
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::hash_for_fixture;
+    use crate::cfg::{self, ServerConfig};
+
+    /// Hashing a password and encoding it for storage, as done whenever a password is set, with
+    /// the parameters the server is configured with.
+    #[bench]
+    fn hash_and_encode(b: &mut test::Bencher) {
+        crypto::multithread_init().expect("The crypto crate to initialize.");
+        let pw_key_store = cfg::pw_secret(&ServerConfig::from_env_for_tests());
+        b.iter(|| hash_for_fixture("correct horse battery staple", &pw_key_store));
+    }
+}
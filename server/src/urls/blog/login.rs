@@ -1,59 +1,175 @@
 //! Handlers and functions for handling logins/seessions.
 
-mod data;
+pub(super) mod data;
 use data::Authenticate;
 
 use rocket::{
     http::{Cookies, Status},
+    request::Request,
+    response::{self, status, Responder},
     State,
 };
 use rocket_contrib::json::Json;
+use std::time::SystemTime;
 
 use crate::{
-    cfg::{PWKeyFixture, TokenKeyFixture},
-    util::{auth, blog::db},
+    cfg::{PWKeyFixture, ServerConfig, TokenKeyFixture},
+    util::{
+        auth::{
+            self,
+            escalation::{Attempt, Escalation},
+        },
+        blog::db,
+        metrics::Metrics,
+    },
 };
 use blog_db::models::*;
 use crypto::Generational;
+use login_enum::LoginFailure;
 
+/// Why a login did not go through. Failures the user can do something about are explained in the
+/// body, so that the client can say which it was.
+#[derive(Debug)]
+pub enum Failure {
+    Refused(LoginFailure),
+    Status(Status),
+}
+impl From<auth::Error> for Failure {
+    fn from(e: auth::Error) -> Self {
+        match e {
+            // An unknown user name is reported the same as a wrong password.
+            auth::Error::BadCredentials | auth::Error::DB(db::Error::NotFound) => {
+                Self::Refused(LoginFailure::BadCredentials)
+            }
+            e => Self::Status(e.into()),
+        }
+    }
+}
+impl From<Status> for Failure {
+    fn from(status: Status) -> Self {
+        Self::Status(status)
+    }
+}
+impl<'r> Responder<'r> for Failure {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        match self {
+            Self::Refused(failure) => {
+                let status =
+                    Status::from_code(failure.status_code()).unwrap_or(Status::Unauthorized);
+                status::Custom(status, Json(failure)).respond_to(req)
+            }
+            Self::Status(status) => Err(status),
+        }
+    }
+}
+
+blog_api::assert_route!(blog_api::login::LOG_IN, Post, "/login");
 /// Route handler for creating a session. Capabilities passed in will be ignored if caller is
-/// already logged in.
+/// already logged in. Credentials are [normalized](data::Authentication::normalize) first, and
+/// those that could not belong to anyone are refused as if the password were wrong.
+///
+/// Logins from an address with too many recent failures must pass a
+/// [challenge](auth::escalation) first, which is checked before the credentials are.
 #[post("/login", format = "json", data = "<auth_data>")]
 pub fn post(
     auth_data: Json<data::Authentication>,
+    attempt: Attempt,
+    escalation: State<Escalation>,
     tok_key_store: State<TokenKeyFixture>,
     pw_key_store: State<PWKeyFixture>,
+    config: State<ServerConfig>,
     mut cookies: Cookies,
     db: db::DB,
-) -> Result<Json<users::DataNoMeta>, Status> {
+    metrics: State<Metrics>,
+) -> Result<Json<users::DataNoMeta>, Failure> {
     use log::*;
+    let now = SystemTime::now();
+    let challenge = escalation.challenge_for(
+        &*tok_key_store
+            .get_store()
+            .map_err(|_| Status::InternalServerError)?,
+        &attempt,
+        now,
+    );
+    match challenge {
+        Ok(None) => {}
+        Ok(Some(challenge)) => {
+            info!("Asking for a challenge before trying the login.");
+            return Err(Failure::Refused(LoginFailure::ChallengeRequired { challenge }));
+        }
+        Err(e) => {
+            error!("Failed to check the login challenge due to {:?}.", e);
+            return Err(Status::InternalServerError.into());
+        }
+    }
     info!("Processing data.");
+    let auth_data = auth_data.into_inner().normalize().map_err(|e| {
+        warn!("Refusing credentials that could not belong to anyone: {:?}.", e);
+        metrics.record_login(false);
+        escalation.record_failure(&attempt, now);
+        Failure::Refused(LoginFailure::BadCredentials)
+    })?;
     let (user, caps) = match auth_data.authenticate(&db, &pw_key_store) {
         Err(e) => {
             error!("{:?}", e);
-            let e = Err(e.into());
+            metrics.record_login(false);
+            let e: Failure = e.into();
             error!("Converted to: {:?}", e);
-            return e;
+            if let Failure::Refused(_) = e {
+                escalation.record_failure(&attempt, now);
+            }
+            return Err(e);
         }
         Ok(user_and_p) => user_and_p,
     };
     debug!("Resolved to user {}.", user.user_name);
     auth::attach_capabilities_token(
-        &tok_key_store
+        &*tok_key_store
             .get_store()
-            .map_err(|_| Status::InternalServerError)?
-            .curr,
+            .map_err(|_| Status::InternalServerError)?,
         auth::UnverifiedCapabilities::new(user.id, caps).into_inner(),
+        &config.cookies,
         &mut cookies,
     )
     .map_err(|_| Status::InternalServerError)?;
     debug!("Attached credential.");
+    metrics.record_login(true);
     Ok(Json(user.strip_meta()))
 }
 
+blog_api::assert_route!(blog_api::login::LOG_OUT, Delete, "/login");
 /// Route handler for deleting a session. Will do nothing if not already in a session and will
 /// alaways return OK.
 #[delete("/login")]
 pub fn delete(mut cookies: Cookies) {
     auth::detach_capabilities_token_if_exists(&mut cookies);
 }
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use rocket::local::Client;
+
+    #[get("/fail?<wrong_password>")]
+    fn fail(wrong_password: bool) -> Failure {
+        if wrong_password {
+            auth::Error::BadCredentials.into()
+        } else {
+            auth::Error::KeyStorePoisoned.into()
+        }
+    }
+
+    #[test]
+    fn bad_credentials_are_explained() {
+        let rocket = rocket::custom(rocket::Config::development()).mount("/", routes![fail]);
+        let client = Client::new(rocket).unwrap();
+        let mut res = client.get("/fail?wrong_password=true").dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+        assert_eq!(
+            res.body_string(),
+            Some(r#"{"reason":"bad_credentials"}"#.to_owned())
+        );
+        let res = client.get("/fail?wrong_password=false").dispatch();
+        assert_eq!(res.status(), Status::InternalServerError);
+    }
+}
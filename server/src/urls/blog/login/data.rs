@@ -7,31 +7,50 @@ use crate::{
     util::{
         auth,
         blog::{
-            db::{CapabilityQuery, PWQuery, UserQuery},
+            db::{self, CapabilityQuery, PWQuery, UserQuery},
             DB,
         },
     },
 };
 use blog_db::models::*;
-use crypto::algo::{hash::symmetric::Algo as HashA, Algo as A};
+use crypto::{
+    algo::{
+        hash::{argon2::Argon2Params, symmetric::Algo as HashA},
+        Algo as A, SecretBytes,
+    },
+    encoding::{Base64, Encoding},
+};
 pub use login_enum::*;
+use std::convert::TryFrom;
 
 /// Encodes a pairing of input and stored credentials of same type.
 pub enum AuthnWithStored<'a> {
     Password(&'a Password, credentials::pw::Data),
 }
 impl<'a> AuthnWithStored<'a> {
-    /// Verify a credential against the stored version. This is currently specific to passwords.
-    fn verify_with_err(self, key: &<PWAlgo as A>::Key) -> Result<(), ()> {
+    /// The id of the stored credential, within the table of its kind.
+    fn stored_id(&self) -> uuid::Uuid {
+        match self {
+            Self::Password(_, stored) => stored.id,
+        }
+    }
+    /// Verify a credential against the stored version, with the parameters it was stored with. This
+    /// is currently specific to passwords.
+    pub(crate) fn verify_with_err(self, key: &<PWAlgo as A>::Key) -> Result<(), ()> {
         use log::*;
         match self {
             Self::Password(pw, hash_and_salt) => {
                 debug!("Decode pw from base64.");
-                let hash = base64::decode(hash_and_salt.hash.as_bytes()).map_err(|_| ())?;
-                let salt = base64::decode(hash_and_salt.salt.as_bytes()).map_err(|_| ())?;
+                let decode = |column, stored: &str| {
+                    Base64::decode(stored)
+                        .map(SecretBytes::new)
+                        .map_err(|e| error!("Stored password {} is not base64: {:?}.", column, e))
+                };
+                let hash = decode("hash", &hash_and_salt.hash)?;
+                let salt = decode("salt", &hash_and_salt.salt)?;
                 let salt = {
                     let mut buf = [0; PWAlgo::SALT_LEN as usize];
-                    buf.copy_from_slice(salt.as_slice());
+                    buf.copy_from_slice(&salt);
                     buf
                 };
                 let hash_input = <PWAlgo as HashA>::VerificationInput::new(
@@ -40,10 +59,20 @@ impl<'a> AuthnWithStored<'a> {
                     Some(hash.len() as u32),
                 )
                 .map_err(|_| ())?;
+                let params = Argon2Params {
+                    mem_kib: u32::try_from(hash_and_salt.argon2_mem_kib).map_err(|_| ())?,
+                    iterations: u32::try_from(hash_and_salt.argon2_iterations).map_err(|_| ())?,
+                    parallelism: u32::try_from(hash_and_salt.argon2_parallelism).map_err(|_| ())?,
+                    hash_len: hash.len() as u32,
+                };
+                let algo = PWAlgo::with_params(params).map_err(|e| {
+                    error!(
+                        "Stored password parameters {:?} are invalid: {:?}.",
+                        params, e
+                    )
+                })?;
                 trace!("attempting verification.");
-                PWAlgo::new(None)
-                    .verify(&hash_input, hash.as_slice(), key)
-                    .as_result((), ())
+                algo.verify(&hash_input, &hash, key).as_result((), ())
             }
         }
     }
@@ -56,27 +85,27 @@ pub trait Authenticate {
         &self,
         db: &DB,
         pw_key_store: &PWKeyFixture,
-    ) -> Result<(users::Data, Vec<auth::Capability>), auth::Error>;
+    ) -> Result<(users::Data, Vec<auth::caps::Held>), auth::Error>;
     /// Find user this credential belongs to along with a list of capabilities belonging to the
-    /// user.
+    /// user, including those granted through roles.
     fn find_targeted_user(
         &self,
         db: &DB,
-    ) -> Result<(users::Data, Vec<capabilities::Data>), diesel::result::Error>;
+    ) -> Result<(users::Data, Vec<capabilities::Effective>), db::Error>;
     /// Create a reference of the submitted credentials alongside the official credentials. This
     /// will be verified later on.
     fn pair_with_stored(
         &self,
         db: &DB,
         user: &users::Data,
-    ) -> Result<AuthnWithStored, diesel::result::Error>;
+    ) -> Result<AuthnWithStored, db::Error>;
 }
 impl Authenticate for Authentication {
     fn authenticate(
         &self,
         db: &DB,
         pw_key_store: &PWKeyFixture,
-    ) -> Result<(users::Data, Vec<auth::Capability>), auth::Error> {
+    ) -> Result<(users::Data, Vec<auth::caps::Held>), auth::Error> {
         use log::*;
         trace!("Beginning authentication process.");
         let (user, caps) = self.find_targeted_user(db)?;
@@ -85,22 +114,27 @@ impl Authenticate for Authentication {
         trace!("Found secret key.");
         let targeted_credential = self.pair_with_stored(db, &user)?;
         trace!("Found secret key.");
+        let used = targeted_credential.stored_id();
         targeted_credential
             .verify_with_err(&*key)
-            .map(|_| (user, caps.iter().map(auth::Capability::from).collect()))
-            .map_err(|_| auth::Error::BadCredentials)
+            .map_err(|_| auth::Error::BadCredentials)?;
+        // Only shown to the user, so failing to note it doesn't keep them from logging in.
+        if let Err(e) = db.mark_pw_used(used) {
+            warn!("Failed to note that password {} was used: {:?}.", used, e);
+        }
+        Ok((user, auth::caps::known(caps.as_slice())))
     }
     fn find_targeted_user(
         &self,
         db: &DB,
-    ) -> Result<(users::Data, Vec<capabilities::Data>), diesel::result::Error> {
+    ) -> Result<(users::Data, Vec<capabilities::Effective>), db::Error> {
         use log::*;
         trace!("Beginning user search.");
         let user = match self {
             Self::Password(p) => db.find_user_by_user_name(p.user_name.as_str()),
         }?;
         trace!("Getting capabilities for user.");
-        let capabilities = db.get_user_capabilities(&user)?;
+        let capabilities = db.get_effective_user_capabilities(&user)?;
         trace!("Both located. Returning.");
         Ok((user, capabilities))
     }
@@ -108,7 +142,7 @@ impl Authenticate for Authentication {
         &self,
         db: &DB,
         user: &users::Data,
-    ) -> Result<AuthnWithStored, diesel::result::Error> {
+    ) -> Result<AuthnWithStored, db::Error> {
         match self {
             Self::Password(p) => db
                 .find_pw_hash_by_user(user)
@@ -0,0 +1,837 @@
+//! Describes the blog API as an OpenAPI 3 document, so that the requests made by the
+//! [`blog_client`](../blog_client) can be checked against the routes rather than kept in sync by
+//! hand.
+//!
+//! Every route in [`api_routes`](super::api_routes) must have an [`Operation`] in
+//! [`operations`]. The tests fail otherwise.
+
+use rocket::http::Method::{self, *};
+use rocket_contrib::json::Json;
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::Schema,
+    JsonSchema,
+};
+use serde_json::{json, Map, Value};
+
+use super::{admin, capabilities::data::Query as CapabilityQuery, well_known};
+use crate::{
+    cfg::BLOG_API_ROOT,
+    util::{
+        auth::{
+            self,
+            caps::{self, Verifiable},
+        },
+        validation::ValidationErrors,
+    },
+};
+use blog_db::models::*;
+
+/// Name of the security scheme for the auth cookie in the document.
+const COOKIE_SCHEME: &str = "authCookie";
+
+/// Who may call an operation.
+enum Auth {
+    /// Anyone.
+    Public,
+    /// Anyone, though the auth cookie changes the result if present.
+    Optional,
+    /// Any signed in user. The handler may restrict this further, such as to the user themselves.
+    SignedIn,
+    /// Signed in users holding every listed capability, possibly only for the target.
+    Capabilities(&'static [auth::Capability]),
+}
+
+/// Generates the schema of a type, adding its definition to the generator.
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// Documentation of a single route.
+struct Operation {
+    method: Method,
+    /// The path of the route, without the query.
+    path: &'static str,
+    summary: &'static str,
+    auth: Auth,
+    /// The query parameters and their schemas. All query parameters are optional.
+    query: Vec<(&'static str, Value)>,
+    /// The schema of the body, along with whether the body is required.
+    request: Option<(SchemaFn, bool)>,
+    /// The schema of the body of successful responses.
+    response: Option<SchemaFn>,
+    /// The statuses refusing the request that explain why in the body, and the schema of the body.
+    refusal: Option<(&'static [u16], SchemaFn)>,
+    /// The statuses responded with, besides the `401` implied by `auth`. The first is the status
+    /// of successful responses.
+    statuses: &'static [u16],
+}
+impl Operation {
+    fn new(method: Method, path: &'static str, summary: &'static str, auth: Auth) -> Self {
+        Self {
+            method,
+            path,
+            summary,
+            auth,
+            query: vec![],
+            request: None,
+            response: None,
+            refusal: None,
+            statuses: &[200],
+        }
+    }
+    fn query(mut self, name: &'static str, schema: Value) -> Self {
+        self.query.push((name, schema));
+        self
+    }
+    fn accepts<T: JsonSchema>(mut self) -> Self {
+        self.request = Some((schema::<T>, true));
+        self
+    }
+    fn accepts_optionally<T: JsonSchema>(mut self) -> Self {
+        self.request = Some((schema::<T>, false));
+        self
+    }
+    fn responds<T: JsonSchema>(mut self) -> Self {
+        self.response = Some(schema::<T>);
+        self
+    }
+    fn refuses<T: JsonSchema>(mut self, statuses: &'static [u16]) -> Self {
+        self.refusal = Some((statuses, schema::<T>));
+        self
+    }
+    fn statuses(mut self, statuses: &'static [u16]) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    /// The path in OpenAPI form, where `/posts/<id>` becomes `/posts/{id}`.
+    fn templated_path(&self) -> String {
+        self.path.replace('<', "{").replace('>', "}")
+    }
+    /// Every path parameter of the API is a uuid.
+    fn parameters(&self) -> Vec<Value> {
+        let path = self
+            .path
+            .split('/')
+            .filter(|segment| segment.starts_with('<'))
+            .map(|segment| {
+                json!({
+                    "name": segment.trim_start_matches('<').trim_end_matches('>'),
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string", "format": "uuid" },
+                })
+            });
+        let query = self.query.iter().map(|(name, schema)| {
+            json!({
+                "name": name,
+                "in": "query",
+                "required": false,
+                "schema": schema,
+            })
+        });
+        path.chain(query).collect()
+    }
+    fn describe(&self, gen: &mut SchemaGenerator) -> Value {
+        let mut responses = Map::new();
+        let mut statuses = self.statuses.to_vec();
+        if let Auth::SignedIn | Auth::Capabilities(_) = self.auth {
+            statuses.push(401);
+        }
+        for (i, &code) in statuses.iter().enumerate() {
+            let reason = rocket::http::Status::from_code(code).map_or("", |s| s.reason);
+            let mut response = json!({ "description": reason });
+            if let (0, Some(response_schema)) = (i, self.response) {
+                response["content"] = json!({
+                    "application/json": { "schema": response_schema(gen) },
+                });
+            }
+            match self.refusal {
+                Some((refusals, refusal_schema)) if refusals.contains(&code) => {
+                    response["content"] = json!({
+                        "application/json": { "schema": refusal_schema(gen) },
+                    });
+                }
+                _ => {}
+            }
+            responses.insert(code.to_string(), response);
+        }
+        let mut operation = json!({
+            "summary": self.summary,
+            "parameters": self.parameters(),
+            "responses": responses,
+        });
+        if let Some((request_schema, required)) = self.request {
+            operation["requestBody"] = json!({
+                "required": required,
+                "content": { "application/json": { "schema": request_schema(gen) } },
+            });
+        }
+        match self.auth {
+            Auth::Public => {}
+            Auth::Optional => {
+                operation["security"] = json!([{}, { COOKIE_SCHEME: [] }]);
+            }
+            Auth::SignedIn => {
+                operation["security"] = json!([{ COOKIE_SCHEME: [] }]);
+            }
+            Auth::Capabilities(required) => {
+                operation["security"] = json!([{ COOKIE_SCHEME: [] }]);
+                operation["x-required-capabilities"] = json!(required);
+            }
+        }
+        operation
+    }
+}
+
+/// Schema of the query parameters bounding a listing of posts.
+fn count_param() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+fn time_param() -> Value {
+    json!({ "type": "string", "format": "date-time" })
+}
+
+/// Documentation for every route in [`api_routes`](super::api_routes).
+fn operations() -> Vec<Operation> {
+    use Auth::*;
+    vec![
+        Operation::new(Get, "/posts", "Lists posts.", Optional)
+            .query("offset", count_param())
+            .query("lim", count_param())
+            .query("start_time", time_param())
+            .query("stop_time", time_param())
+            .query(
+                "ord_criteria",
+                json!({ "type": "string", "enum": ["date", "title"] }),
+            )
+            .query("ord", json!({ "type": "string", "enum": ["asc", "dsc"] }))
+            .query(
+                "state",
+                json!({ "type": "string", "enum": ["published", "draft", "archived"] }),
+            )
+            .query("author", json!({ "type": "string", "format": "uuid" }))
+            .query("tag", json!({ "type": "string" }))
+            .responds::<Vec<posts::Listed>>()
+            .statuses(&[200, 400, 500]),
+        Operation::new(
+            Get,
+            "/posts/counts",
+            "Counts the posts that are published, drafts, or archived.",
+            SignedIn,
+        )
+        .responds::<stats::PostCounts>()
+        .statuses(&[200, 401, 500]),
+        Operation::new(
+            Post,
+            "/posts",
+            "Creates a post.",
+            Capabilities(caps::Post::REQUIRED_CAPS),
+        )
+        .accepts::<posts::NewNoMeta>()
+        .responds::<posts::Authored>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 422, 500]),
+        Operation::new(
+            Get,
+            "/posts/<id>",
+            "Retrieves a post by its id or friendly name, along with its authors, its body \
+             rendered, the outline of its headings, and where it is in each series it is part of. \
+             Deleted posts are not found. Unpublished posts are only found for those who may edit \
+             them, and unpublished parts of a series are only counted for those signed in.",
+            Optional,
+        )
+        .responds::<posts::Authored>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Get,
+            "/posts/<id>/rendered",
+            "Retrieves the body of a post rendered to html, with its code highlighted, along with \
+             the outline of its headings. Found under the same rules as the post itself.",
+            Optional,
+        )
+        .responds::<posts::Rendered>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Get,
+            "/posts/<id>/export",
+            "Downloads a post as an html document that stands on its own, with its images \
+             embedded and styled for printing. Found under the same rules as the post itself.",
+            Optional,
+        )
+        .query("format", json!({ "type": "string", "enum": ["html"] }))
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Get,
+            "/posts/<id>/editable",
+            "Checks whether the post may be edited.",
+            Capabilities(caps::Edit::REQUIRED_CAPS),
+        )
+        .statuses(&[204, 403, 404, 500]),
+        Operation::new(
+            Patch,
+            "/posts/<id>",
+            "Edits a post, replacing its authors if they are listed.",
+            Capabilities(caps::Edit::REQUIRED_CAPS),
+        )
+        .accepts::<posts::Update>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 403, 404, 422, 500]),
+        Operation::new(
+            Delete,
+            "/posts/<id>",
+            "Deletes a post.",
+            Capabilities(caps::Delete::REQUIRED_CAPS),
+        )
+        .statuses(&[200, 403, 404, 500]),
+        Operation::new(
+            Post,
+            "/posts/<id>/publish",
+            "Publishes a post, applying the changes first if any.",
+            Capabilities(caps::Publish::REQUIRED_CAPS),
+        )
+        .accepts_optionally::<posts::Changed>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 403, 404, 422, 500]),
+        Operation::new(
+            Post,
+            "/posts/<id>/archive",
+            "Archives a post.",
+            Capabilities(caps::Archive::REQUIRED_CAPS),
+        )
+        .statuses(&[200, 403, 404, 500]),
+        Operation::new(Get, "/posts/<id>/tags", "Lists the tags on a post.", Public)
+            .responds::<Vec<tags::Data>>()
+            .statuses(&[200, 500]),
+        Operation::new(
+            Put,
+            "/posts/<id>/tags",
+            "Replaces the tags on a post with the named tags, creating the tags that do not \
+             exist yet.",
+            Capabilities(caps::Edit::REQUIRED_CAPS),
+        )
+        .accepts::<Vec<String>>()
+        .responds::<Vec<tags::Data>>()
+        .statuses(&[200, 403, 404, 500]),
+        Operation::new(
+            Post,
+            "/posts/<id>/reactions",
+            "Leaves a reaction on a published post, once per kind for each reader. Readers who \
+             are not logged in are told apart by a session cookie.",
+            Public,
+        )
+        .accepts::<reactions::Reaction>()
+        .responds::<reactions::Counts>()
+        .refuses::<reactions::Counts>(&[409])
+        .statuses(&[200, 404, 409, 500]),
+        Operation::new(
+            Delete,
+            "/posts/<id>/reactions",
+            "Takes back a reaction left on a published post.",
+            Public,
+        )
+        .accepts::<reactions::Reaction>()
+        .responds::<reactions::Counts>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Get,
+            "/tags",
+            "Lists a page of the tags, by name. Pages hold at most 200 tags, however many are \
+             asked for.",
+            Public,
+        )
+        .query("limit", count_param())
+        .query("offset", count_param())
+        .responds::<page::Page<tags::Data>>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Get,
+            "/series",
+            "Lists a page of the series, by title. Pages hold at most 200 series, however many \
+             are asked for.",
+            Public,
+        )
+        .query("limit", count_param())
+        .query("offset", count_param())
+        .responds::<page::Page<series::Data>>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Post,
+            "/series",
+            "Creates a series without any posts.",
+            Capabilities(caps::Edit::REQUIRED_CAPS),
+        )
+        .accepts::<series::NewNoMeta>()
+        .responds::<series::Data>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 409, 500]),
+        Operation::new(
+            Get,
+            "/series/<id>",
+            "Retrieves a series by its id or slug, along with its posts in order. Unpublished \
+             posts are only listed for those signed in.",
+            Optional,
+        )
+        .responds::<series::WithParts>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Patch,
+            "/series/<id>",
+            "Changes the title, slug, or description of a series.",
+            Capabilities(caps::Edit::REQUIRED_CAPS),
+        )
+        .accepts::<series::Changed>()
+        .responds::<series::Data>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 404, 409, 500]),
+        Operation::new(
+            Delete,
+            "/series/<id>",
+            "Deletes a series, leaving its posts as they are.",
+            Capabilities(caps::Edit::REQUIRED_CAPS),
+        )
+        .responds::<series::Data>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Put,
+            "/series/<id>/posts",
+            "Replaces the posts in a series with those listed, in order. Either every post is \
+             placed or none are.",
+            Capabilities(caps::Edit::REQUIRED_CAPS),
+        )
+        .accepts::<Vec<uuid::Uuid>>()
+        .responds::<Vec<series::Part>>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Post,
+            "/accounts",
+            "Creates an account. Signing up needs no capabilities, but creating an account for \
+             someone else requires `create_user`. Signing up needs a solved `/challenge` in the \
+             `X-Challenge-Token` and `X-Challenge-Nonce` headers.",
+            Optional,
+        )
+        .accepts::<users::NewNoMeta>()
+        .responds::<users::DataNoMeta>()
+        .statuses(&[200, 401, 428, 500]),
+        Operation::new(
+            Get,
+            "/accounts",
+            "Finds the users whose user name contains `name`, such as to credit them on a post.",
+            Capabilities(caps::EditForeignPosts::REQUIRED_CAPS),
+        )
+        .query("name", json!({ "type": "string" }))
+        .responds::<Vec<users::AuthorSummary>>()
+        .statuses(&[200, 401, 500]),
+        Operation::new(
+            Get,
+            "/accounts/<id>",
+            "Retrieves your own account.",
+            SignedIn,
+        )
+        .responds::<users::DataNoMeta>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Get,
+            "/accounts/me",
+            "Retrieves the signed in account.",
+            SignedIn,
+        )
+        .responds::<users::DataNoMeta>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Get,
+            "/accounts/<id>/capabilities",
+            "Lists a page of the capabilities of an account, oldest first. Requires \
+             `view_capability` for accounts other than your own. Pages hold at most 200 \
+             capabilities, however many are asked for.",
+            SignedIn,
+        )
+        .query("limit", count_param())
+        .query("offset", count_param())
+        .responds::<page::Page<capabilities::Grant>>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Get,
+            "/accounts/me/credentials",
+            "Lists a page of the ways the signed in account can sign in, oldest first, without \
+             any secrets. Pages hold at most 200 of them, however many are asked for.",
+            SignedIn,
+        )
+        .query("limit", count_param())
+        .query("offset", count_param())
+        .responds::<page::Page<credentials::Summary>>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Patch,
+            "/accounts/<id>",
+            "Edits an account. Requires `edit_user` for accounts other than your own.",
+            SignedIn,
+        )
+        .accepts::<users::ChangedNoMeta>()
+        .responds::<users::DataNoMeta>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 422, 500]),
+        Operation::new(
+            Patch,
+            "/accounts/me",
+            "Edits the signed in account, such as to save its preferences.",
+            SignedIn,
+        )
+        .accepts::<users::ChangedNoMeta>()
+        .responds::<users::DataNoMeta>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 401, 422, 500]),
+        Operation::new(
+            Delete,
+            "/accounts/<id>",
+            "Deletes an account. Requires `delete_user` for accounts other than your own.",
+            SignedIn,
+        )
+        .statuses(&[200, 409, 500]),
+        Operation::new(Post, "/login", "Signs in, setting the auth cookie.", Public)
+            .accepts::<login_enum::Authentication>()
+            .responds::<users::DataNoMeta>()
+            .refuses::<login_enum::LoginFailure>(login_enum::LoginFailure::STATUS_CODES)
+            .statuses(&[200, 401, 423, 428, 429, 500]),
+        Operation::new(
+            Delete,
+            "/login",
+            "Signs out, removing the auth cookie.",
+            Public,
+        ),
+        Operation::new(
+            Post,
+            "/credentials/pws",
+            "Creates a password for your own account.",
+            SignedIn,
+        )
+        .accepts::<login_enum::CreatePassword>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Get,
+            "/credentials/pws/me",
+            "Finds the id of the password of the signed in account.",
+            SignedIn,
+        )
+        .responds::<uuid::Uuid>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Patch,
+            "/credentials/pws/<id>",
+            "Changes a password. Requires `edit_user_credentials` for accounts other than your \
+             own, and the current password for your own.",
+            SignedIn,
+        )
+        .accepts::<login_enum::ChangePassword>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 404, 422, 500]),
+        Operation::new(
+            Delete,
+            "/credentials/pws/<id>",
+            "Deletes a password. Requires `edit_user_credentials` for accounts other than your \
+             own. Refused if the account has no other way to sign in.",
+            SignedIn,
+        )
+        .statuses(&[200, 404, 409, 500]),
+        Operation::new(
+            Post,
+            "/capabilities/<target_user_id>",
+            "Grants capabilities to a user. Only capabilities you hold can be granted.",
+            Capabilities(caps::GrantCapability::REQUIRED_CAPS),
+        )
+        .accepts::<Vec<caps::Held>>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Delete,
+            "/capabilities",
+            "Deletes the capabilities matching the query.",
+            Capabilities(caps::DeleteCapability::REQUIRED_CAPS),
+        )
+        .accepts::<CapabilityQuery>()
+        .responds::<Vec<capabilities::Data>>()
+        .statuses(&[200, 409, 500]),
+        Operation::new(
+            Get,
+            "/capabilities/<id>",
+            "Retrieves a capability.",
+            Capabilities(caps::ViewCapability::REQUIRED_CAPS),
+        )
+        .responds::<capabilities::Data>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Delete,
+            "/capabilities/<id>",
+            "Deletes a capability.",
+            Capabilities(caps::DeleteCapability::REQUIRED_CAPS),
+        )
+        .responds::<capabilities::Data>()
+        .statuses(&[200, 409, 500]),
+        Operation::new(
+            Post,
+            "/roles",
+            "Creates a role.",
+            Capabilities(caps::GrantCapability::REQUIRED_CAPS),
+        )
+        .accepts::<roles::NewNoMeta>()
+        .responds::<roles::Data>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Get,
+            "/roles/<id>",
+            "Retrieves a role along with the capabilities it grants.",
+            Capabilities(caps::ViewCapability::REQUIRED_CAPS),
+        )
+        .responds::<(roles::Data, Vec<auth::Capability>)>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Post,
+            "/roles/<id>/capabilities",
+            "Adds capabilities to a role. Only capabilities you hold can be added.",
+            Capabilities(caps::GrantCapability::REQUIRED_CAPS),
+        )
+        .accepts::<Vec<auth::Capability>>()
+        .responds::<Vec<role_capabilities::Data>>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Delete,
+            "/roles/<id>/capabilities",
+            "Removes capabilities from a role.",
+            Capabilities(caps::DeleteCapability::REQUIRED_CAPS),
+        )
+        .accepts::<Vec<auth::Capability>>()
+        .responds::<Vec<role_capabilities::Data>>()
+        .statuses(&[200, 404, 409, 500]),
+        Operation::new(
+            Post,
+            "/roles/<id>/users/<user_id>",
+            "Grants a role to a user. Only roles whose capabilities you hold can be granted.",
+            Capabilities(caps::GrantCapability::REQUIRED_CAPS),
+        )
+        .responds::<user_roles::Data>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Delete,
+            "/roles/<id>/users/<user_id>",
+            "Revokes a role from a user.",
+            Capabilities(caps::DeleteCapability::REQUIRED_CAPS),
+        )
+        .responds::<user_roles::Data>()
+        .statuses(&[200, 404, 409, 500]),
+        Operation::new(
+            Get,
+            "/admin/stats",
+            "Retrieves statistics about the site, and when old records were last removed.",
+            Capabilities(caps::ViewSiteStats::REQUIRED_CAPS),
+        )
+        .responds::<stats::Overview>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Post,
+            "/admin/retention",
+            "Starts removing old records, such as posts deleted long ago, in the background.",
+            Capabilities(caps::RunRetention::REQUIRED_CAPS),
+        )
+        .statuses(&[202]),
+        Operation::new(
+            Post,
+            "/admin/rotate-keys",
+            "Rotates the keys tokens are encrypted with immediately.",
+            Capabilities(caps::RotateKeys::REQUIRED_CAPS),
+        )
+        .responds::<admin::KeyRotation>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Post,
+            "/admin/mode",
+            "Switches the site to normal, read only, or under maintenance, and saves the mode.",
+            Capabilities(caps::ChangeSiteMode::REQUIRED_CAPS),
+        )
+        .accepts::<site::Mode>()
+        .responds::<site::Mode>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Patch,
+            "/admin/identity",
+            "Changes the title, tagline, or logo of the site, and saves them.",
+            Capabilities(caps::ChangeSiteIdentity::REQUIRED_CAPS),
+        )
+        .accepts::<site::IdentityChanges>()
+        .responds::<site::Identity>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 422, 500]),
+        Operation::new(
+            Get,
+            "/admin/webhooks",
+            "Lists a page of the webhooks, oldest first. Secrets are left out. Pages hold at most \
+             200 webhooks, however many are asked for.",
+            Capabilities(caps::ManageWebhooks::REQUIRED_CAPS),
+        )
+        .query("limit", count_param())
+        .query("offset", count_param())
+        .responds::<page::Page<webhooks::Data>>()
+        .statuses(&[200, 500]),
+        Operation::new(
+            Post,
+            "/admin/webhooks",
+            "Creates a webhook, pinged with signed deliveries of the events in its mask.",
+            Capabilities(caps::ManageWebhooks::REQUIRED_CAPS),
+        )
+        .accepts::<webhooks::NewNoMeta>()
+        .responds::<webhooks::Data>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 500]),
+        Operation::new(
+            Patch,
+            "/admin/webhooks/<id>",
+            "Changes a webhook, such as to disable it.",
+            Capabilities(caps::ManageWebhooks::REQUIRED_CAPS),
+        )
+        .accepts::<webhooks::Changed>()
+        .responds::<webhooks::Data>()
+        .refuses::<ValidationErrors>(&[422])
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Delete,
+            "/admin/webhooks/<id>",
+            "Deletes a webhook, along with its record of failed deliveries.",
+            Capabilities(caps::ManageWebhooks::REQUIRED_CAPS),
+        )
+        .responds::<webhooks::Data>()
+        .statuses(&[200, 404, 500]),
+        Operation::new(
+            Post,
+            "/admin/webhooks/<id>/test",
+            "Sends a sample event to a webhook right away, failing if it is not accepted.",
+            Capabilities(caps::ManageWebhooks::REQUIRED_CAPS),
+        )
+        .statuses(&[204, 404, 500, 502]),
+        Operation::new(
+            Get,
+            "/site/status",
+            "Retrieves the mode the site is in.",
+            Public,
+        )
+        .responds::<site::Mode>(),
+        Operation::new(
+            Get,
+            "/site/identity",
+            "Retrieves the title, tagline, and logo of the site.",
+            Public,
+        )
+        .responds::<site::Identity>(),
+        Operation::new(
+            Get,
+            "/challenge",
+            "Hands out a proof of work to solve before creating an account without signing in.",
+            Public,
+        )
+        .responds::<login_enum::challenge::Challenge>()
+        .statuses(&[200, 500]),
+        Operation::new(Get, "/openapi.json", "Retrieves this document.", Public),
+        Operation::new(
+            Get,
+            "/.well-known/keys",
+            "Lists the public keys tokens signed by the site can be verified with.",
+            Public,
+        )
+        .responds::<well_known::Jwks>()
+        .statuses(&[200, 500]),
+    ]
+}
+
+/// Builds the OpenAPI document describing the blog API.
+pub fn document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for operation in operations() {
+        let item = paths
+            .entry(operation.templated_path())
+            .or_insert_with(|| json!({}));
+        let method = operation.method.as_str().to_ascii_lowercase();
+        item[method] = operation.describe(&mut gen);
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "benxu.dev blog API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": BLOG_API_ROOT }],
+        "paths": paths,
+        "components": {
+            "schemas": gen.definitions(),
+            "securitySchemes": {
+                COOKIE_SCHEME: {
+                    "type": "apiKey",
+                    "in": "cookie",
+                    "name": auth::AUTH_COOKIE_NAME,
+                },
+            },
+        },
+    })
+}
+
+/// Handler serving the OpenAPI document of the blog API.
+#[get("/openapi.json")]
+pub fn get() -> Json<Value> {
+    Json(document())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_route_is_documented() {
+        let document = document();
+        for route in super::super::api_routes() {
+            let path = route.uri.path().replace('<', "{").replace('>', "}");
+            let method = route.method.as_str().to_ascii_lowercase();
+            let operation = &document["paths"][path.as_str()][method.as_str()];
+            assert!(
+                operation.is_object(),
+                "{} {} is missing from the OpenAPI document.",
+                route.method,
+                route.uri
+            );
+            let documented: Vec<_> = operation["parameters"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| p["name"].as_str().unwrap().to_owned())
+                .collect();
+            let params = route.uri.query().into_iter().flat_map(|q| q.split('&'));
+            for param in params.chain(route.uri.path().split('/')) {
+                if param.starts_with('<') {
+                    let name = param.trim_start_matches('<').trim_end_matches('>');
+                    assert!(
+                        documented.iter().any(|d| d == name),
+                        "Parameter `{}` of {} {} is undocumented.",
+                        name,
+                        route.method,
+                        route.uri
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn schemas_are_referenced_from_components() {
+        let document = document();
+        let schemas = &document["components"]["schemas"];
+        let login = &document["paths"]["/login"]["post"];
+        let body = &login["requestBody"]["content"]["application/json"]["schema"];
+        let reference = body["$ref"].as_str().unwrap();
+        let name = reference.trim_start_matches("#/components/schemas/");
+        assert!(schemas[name].is_object());
+        assert_eq!(login["responses"]["200"]["description"], "OK");
+        let stats = &document["paths"]["/admin/stats"]["get"];
+        assert_eq!(stats["x-required-capabilities"], json!(["view_site_stats"]));
+        assert!(stats["responses"]["401"].is_object());
+    }
+}
@@ -1,24 +1,46 @@
 //! Handlers and functions for managing posts.
 
-use rocket::http::{RawStr, Status};
+mod error;
+use error::Error;
+mod export;
+pub mod viewable;
+
+use rocket::{
+    http::{RawStr, Status},
+    response::{content, status},
+    State,
+};
 use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
 
 use chrono::DateTime;
 use tap::*;
 
-use crate::util::{
-    auth,
-    blog::{
-        db::{self, PostQuery},
-        DB,
+use crate::{
+    cfg::ServerConfig,
+    util::{
+        assets::Assets,
+        auth,
+        blog::{
+            db::{self, DBConn, PostQuery, ReactionQuery, StatsQuery, TagQuery, UserQuery},
+            DB,
+        },
+        locale::RequestLocale,
+        render_cache::RenderCache,
+        request_log::RequestId,
+        response_cache::{ResponseCache, Shows},
+        uuid_compat::ruuid_to_uuid,
+        validation::{validate_slug, ValidationErrors},
+        visitor::Visitor,
+        webhooks::Webhooks,
     },
-    uuid_compat::ruuid_to_uuid,
 };
 use blog_db::models::*;
 
-/// Handler for getting posts with criteria.
+blog_api::assert_route!(blog_api::posts::LIST, Get, "/posts?<offset>&<lim>&<start_time>&<stop_time>&<ord_criteria>&<ord>&<state>&<author>&<tag>");
+/// Handler for getting posts with criteria, narrowed down to those in `state`, credited to
+/// `author`, and tagged with `tag`, if given.
 #[get(
-    "/posts?<offset>&<lim>&<start_time>&<stop_time>&<ord_criteria>&<ord>",
+    "/posts?<offset>&<lim>&<start_time>&<stop_time>&<ord_criteria>&<ord>&<state>&<author>&<tag>",
     format = "json"
 )]
 pub fn get(
@@ -29,8 +51,16 @@ pub fn get(
     lim: Option<usize>,
     ord_criteria: Option<db::OrderingField>,
     ord: Option<db::SortOrdering>,
+    state: Option<db::PostState>,
+    author: Option<RUuid>,
+    tag: Option<String>,
     capabilities: Option<auth::UnverifiedCapabilities>,
-) -> Result<Json<Vec<posts::BasicData>>, Status> {
+    responses: State<ResponseCache>,
+) -> Result<content::Json<String>, Status> {
+    let filter = db::PostFilter {
+        author: author.map(ruuid_to_uuid),
+        tag,
+    };
     let ord_criteria = ord_criteria.unwrap_or(db::OrderingField::Date);
     let ord = ord.unwrap_or_else(|| match ord_criteria {
         db::OrderingField::Date => db::SortOrdering::Descending,
@@ -49,9 +79,9 @@ pub fn get(
         log::error!("Post search request made with more or less than 2 restrictions.");
         Err(Status::BadRequest)
     } else if let (Some(start_time), Some(stop_time)) = (start_time, stop_time) {
-        get_by_date_range(db, start_time, stop_time, ord_criteria, ord, capabilities)
+        get_by_date_range(db, start_time, stop_time, ord_criteria, ord, state, filter, capabilities, responses)
     } else if let (Some(lim), Some(offset)) = (lim, offset) {
-        get_by_limit_and_offset(db, offset, lim, ord_criteria, ord, capabilities)
+        get_by_limit_and_offset(db, offset, lim, ord_criteria, ord, state, filter, capabilities, responses)
     } else {
         log::error!("Post search request made with a mismatched pair of restrictions.");
         Err(Status::BadRequest)
@@ -64,8 +94,11 @@ pub fn get_by_date_range(
     stop_time: &RawStr,
     ord_criteria: db::OrderingField,
     ord: db::SortOrdering,
+    state: Option<db::PostState>,
+    filter: db::PostFilter,
     capabilities: Option<auth::UnverifiedCapabilities>,
-) -> Result<Json<Vec<posts::BasicData>>, Status> {
+    responses: State<ResponseCache>,
+) -> Result<content::Json<String>, Status> {
     let start_time = start_time
         .percent_decode()
         .as_ref()
@@ -81,137 +114,1092 @@ pub fn get_by_date_range(
         .map_err(|_| Status::BadRequest)?
         .map_err(|_| Status::BadRequest)?;
     let max_posts = 500;
-    db.find_posts_with_post_listing_conditions(db::PostListing::Date {
-        start: start_time.into(),
-        stop: stop_time.into(),
-        order_by: ord_criteria,
+    let key = format!(
+        "/posts?start_time={}&stop_time={}&ord_criteria={:?}&ord={:?}&state={:?}&author={:?}&tag={:?}",
+        start_time.to_rfc3339(),
+        stop_time.to_rfc3339(),
+        ord_criteria,
         ord,
-        limit: max_posts,
-    }, capabilities.is_some())
-    .tap_err(|e| log::error!("Failed to find posts by date range due to error {:?}.", e))
-    .map(Json)
-    .map_err(|_| Status::InternalServerError)
+        state,
+        filter.author,
+        filter.tag,
+    );
+    for_visitors(&responses, key, capabilities.is_none(), |_| Shows::Listing, || {
+        db.posts_with_authors(db::PostListing::Date {
+            start: start_time.into(),
+            stop: stop_time.into(),
+            order_by: ord_criteria,
+            ord,
+            limit: max_posts,
+        }, &filter, capabilities.is_some(), state)
+        .tap_err(|e| log::error!("Failed to find posts by date range due to error {:?}.", e))
+        .map_err(Status::from)
+    })
 }
 
 /// Handler for getting posts with an offset and a limit.
-#[get("/posts?<offset>&<lim>&<ord_criteria>&<ord>", format = "json")]
 pub fn get_by_limit_and_offset(
     db: DB,
     offset: usize,
     lim: usize,
     ord_criteria: db::OrderingField,
     ord: db::SortOrdering,
+    state: Option<db::PostState>,
+    filter: db::PostFilter,
     capabilities: Option<auth::UnverifiedCapabilities>,
-) -> Result<Json<Vec<posts::BasicData>>, Status> {
-    db.find_posts_with_post_listing_conditions(db::PostListing::LimAndOffset {
-        offset,
-        lim: std::cmp::min(lim, 500),
-        order_by: ord_criteria,
-        ord,
-    }, capabilities.is_some())
-    .tap_err(|e| log::error!("Failed to find posts due to error {:?}.", e))
-    .map(Json)
-    .map_err(|_| Status::InternalServerError)
+    responses: State<ResponseCache>,
+) -> Result<content::Json<String>, Status> {
+    let lim = std::cmp::min(lim, 500);
+    let key = format!(
+        "/posts?offset={}&lim={}&ord_criteria={:?}&ord={:?}&state={:?}&author={:?}&tag={:?}",
+        offset, lim, ord_criteria, ord, state, filter.author, filter.tag,
+    );
+    for_visitors(&responses, key, capabilities.is_none(), |_| Shows::Listing, || {
+        db.posts_with_authors(db::PostListing::LimAndOffset {
+            offset,
+            lim,
+            order_by: ord_criteria,
+            ord,
+        }, &filter, capabilities.is_some(), state)
+        .tap_err(|e| log::error!("Failed to find posts due to error {:?}.", e))
+        .map_err(Status::from)
+    })
+}
+
+blog_api::assert_route!(blog_api::posts::COUNTS, Get, "/posts/counts");
+/// Handler for counting the posts under each state, for the tabs of the listing. Requires the user
+/// to be logged in, since only they are shown drafts and archived posts.
+#[get("/posts/counts")]
+pub fn counts(
+    db: DB,
+    capabilities: Option<auth::UnverifiedCapabilities>,
+) -> Result<Json<stats::PostCounts>, Status> {
+    capabilities.ok_or(Status::Unauthorized)?;
+    db.count_posts_by_state()
+        .tap_err(|e| log::error!("Failed to count posts due to error {:?}.", e))
+        .map(Json)
+        .map_err(Status::from)
+}
+
+/// Answers with what `answer` finds, as JSON. Visitors who are not logged in are answered from
+/// `responses` under `key` if they can be, and what they are answered with is kept there, as
+/// showing what `shows` says it does. See [`response_cache`](crate::util::response_cache).
+fn for_visitors<T: serde::Serialize>(
+    responses: &ResponseCache,
+    key: String,
+    is_visitor: bool,
+    shows: impl FnOnce(&T) -> Shows,
+    answer: impl FnOnce() -> Result<T, Status>,
+) -> Result<content::Json<String>, Status> {
+    if is_visitor {
+        if let Some(json) = responses.get(&key) {
+            return Ok(content::Json(json));
+        }
+    }
+    let found = answer()?;
+    let json = serde_json::to_string(&found)
+        .tap_err(|e| log::error!("Failed to serialize the response to {} due to {:?}.", key, e))
+        .map_err(|_| Status::InternalServerError)?;
+    if is_visitor {
+        responses.insert(key, shows(&found), json.clone());
+    }
+    Ok(content::Json(json))
+}
+
+/// Checks the users to credit on a post, given the ids of those credited on it now.
+///
+/// Unless `may_credit_others`, the `caller` may only credit themselves and the users already
+/// credited.
+fn validate_authors(
+    authors: &[post_authors::Assigned],
+    current: &[uuid::Uuid],
+    caller: uuid::Uuid,
+    may_credit_others: bool,
+) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    if authors.is_empty() {
+        errors.add("authors", "required", "Credit at least one user.");
+        return errors;
+    }
+    let repeated = authors
+        .iter()
+        .enumerate()
+        .any(|(i, a)| authors[..i].iter().any(|b| a.user_id == b.user_id));
+    if repeated {
+        errors.add("authors", "duplicate", "Credit each user only once.");
+    }
+    if !authors.iter().any(|a| a.role == post_authors::Role::Author) {
+        errors.add("authors", "no_author", "Credit at least one user as an author.");
+    }
+    let foreign = authors
+        .iter()
+        .any(|a| a.user_id != caller && !current.contains(&a.user_id));
+    if foreign && !may_credit_others {
+        errors.add(
+            "authors",
+            "not_allowed",
+            "Only credit yourself or the users already credited.",
+        );
+    }
+    errors
+}
+
+/// Checks what a post is described with where it is linked to, for the fields that are present.
+/// Either may be empty, to derive it from the body instead.
+fn validate_seo(meta_description: Option<&str>, social_image: Option<&str>) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    let too_long = meta_description
+        .map_or(false, |description| description.chars().count() > posts::META_DESCRIPTION_MAX_CHARS);
+    if too_long {
+        errors
+            .add(
+                "meta_description",
+                "too_long",
+                format!("Keep the description to {} characters.", posts::META_DESCRIPTION_MAX_CHARS),
+            )
+            .param("max_chars", posts::META_DESCRIPTION_MAX_CHARS);
+    }
+    if let Some(image) = social_image.filter(|image| !image.is_empty()) {
+        let located = image.starts_with("https://")
+            || image.starts_with("http://")
+            || (image.starts_with('/') && !image.starts_with("//"));
+        let plain = !image.chars().any(|c| c.is_whitespace() || "\"'<>".contains(c));
+        if !located || !plain {
+            errors.add(
+                "social_image",
+                "invalid",
+                "Use a link starting with https:// or a path on this site.",
+            );
+        }
+    }
+    errors
 }
 
+/// Checks the title, body, and slug of a post, for the fields that are present. A body may take up
+/// at most `body_max_bytes`.
+///
+/// Input with NUL is refused throughout, since postgres cannot store it in text. Lone surrogates
+/// never get this far, since the JSON escaping them is refused as malformed.
+fn validate_post(
+    title: Option<&str>,
+    body: Option<&str>,
+    slug: Option<&str>,
+    body_max_bytes: usize,
+) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    if let Some(title) = title {
+        if title.trim().is_empty() {
+            errors.add("title", "required", "Enter a title.");
+        } else if title.chars().count() > posts::TITLE_MAX_CHARS {
+            errors
+                .add(
+                    "title",
+                    "too_long",
+                    format!("Keep the title to {} characters.", posts::TITLE_MAX_CHARS),
+                )
+                .param("max_chars", posts::TITLE_MAX_CHARS);
+        } else if title.chars().any(char::is_control) {
+            errors.add("title", "multiline", "Keep the title to a single line.");
+        }
+    }
+    if let Some(body) = body {
+        if body.len() > body_max_bytes {
+            errors
+                .add(
+                    "body",
+                    "too_large",
+                    format!("Keep the body to {} KiB.", body_max_bytes / 1024),
+                )
+                .param("max_bytes", body_max_bytes);
+        } else if body.contains('\0') {
+            errors.add("body", "invalid", "Remove the NUL characters from the body.");
+        }
+    }
+    if let Some(slug) = slug {
+        validate_slug("slug", slug, &mut errors);
+    }
+    errors
+}
+
+/// Fills in the rendered body of `post` and its outline, from `cache` if it was rendered before.
+fn with_rendered(cache: &RenderCache, post: posts::Authored) -> posts::Authored {
+    let rendered = cache.rendered(&post.post);
+    posts::Authored {
+        outline: rendered.outline,
+        html: Some(rendered.html),
+        ..post
+    }
+}
+
+blog_api::assert_route!(blog_api::posts::CREATE, Post, "/posts");
 /// Handler for posting a post to the database. Requires user to be logged in and have the
 /// [`Post`](crate::blog::auth::caps::Post) capability.
+///
+/// Credits the listed authors, or else the user alone. Crediting anyone else requires the
+/// [`EditForeignPosts`](crate::blog::auth::caps::EditForeignPosts) capability.
+///
+/// Tags the post with the tags the user tags new posts with by default, if asked to.
 #[post("/posts", format = "json", data = "<post>")]
 pub fn post(
     db: DB,
     capabilities: auth::Capabilities<auth::caps::Post>,
     post: Json<posts::NewNoMeta>,
-) -> Result<Json<posts::Data>, Status> {
+    req_id: RequestId,
+    cache: State<RenderCache>,
+    responses: State<ResponseCache>,
+    config: State<ServerConfig>,
+) -> Result<Json<posts::Authored>, Error> {
     let post = post.into_inner();
-    db.insert_post((&post, capabilities.user_id()))
-        .tap_err(|e| log::error!("Failed to create new post due to error {:?}.", e))
-        .map(Json)
-        .map_err(|_| Status::InternalServerError)
+    let user_id = capabilities.user_id();
+    let mut errors = validate_post(
+        Some(&post.title),
+        Some(&post.body),
+        post.slug.as_deref(),
+        config.post_body_max_bytes,
+    );
+    errors.merge(validate_seo(Some(&post.meta_description), Some(&post.social_image)));
+    Error::check(errors)?;
+    if !post.authors.is_empty() {
+        let may_credit_others =
+            capabilities.has_capabilities(&[auth::Capability::EditForeignPosts]);
+        Error::check(validate_authors(&post.authors, &[], user_id, may_credit_others))?;
+    }
+    db.transaction(|tx| {
+        let created = tx.insert_post((&post, user_id))?;
+        if post.default_tags {
+            let defaults = tx.find_user_by_id(user_id)?.preferences.post_defaults;
+            let names = post::normalize_tag_names(&defaults.tags);
+            tx.replace_post_tags(created.id, names.as_slice(), user_id)?;
+        }
+        let authors = if post.authors.is_empty() {
+            tx.find_post_authors(created.id)?
+        } else {
+            tx.replace_post_authors(created.id, &post.authors, user_id)?
+        };
+        Ok(posts::Authored {
+            visibility: posts::Visibility::of(&created).unwrap_or_default(),
+            post: created,
+            authors,
+            series: vec![],
+            outline: vec![],
+            html: None,
+            reactions: reactions::Counts::default(),
+        })
+    })
+    .tap_err(|e| log::error!("[{}] Failed to create new post due to {:?}.", req_id, e))
+    .tap_ok(|created| responses.invalidate_post(created.post.id))
+    .map(|created| Json(with_rendered(&cache, created)))
+    .map_err(Error::from)
 }
 
 /// Handlers and functions for managing or retrieving individual posts.
 pub mod post {
     use super::*;
 
-    /// Map a rather common diesel error to it's corresponding http [`Status`](rocket::http::Status).
-    ///
-    /// If there is exactly one result, it is [`Ok`]. If there are no results OR the error is the
-    /// [`Error::NotFound`](diesel::result::Error::NotFound) error,
-    /// [`Status::NotFound`](rocket::http::Status::NotFound) is returned.
+    /// Checks the fields of a post that are being changed.
+    fn validate_changes(changes: &posts::Changed, body_max_bytes: usize) -> ValidationErrors {
+        let mut errors = validate_post(
+            changes.title.as_deref(),
+            changes.body.as_deref(),
+            None,
+            body_max_bytes,
+        );
+        errors.merge(validate_seo(
+            changes.meta_description.as_deref(),
+            changes.social_image.as_deref(),
+        ));
+        errors
+    }
+
+    /// Map the number of rows a change touched to its http [`Status`](rocket::http::Status).
     ///
-    /// Otherwise, we return
+    /// If there is exactly one result, it is [`Ok`]. If there are no results,
+    /// [`Status::NotFound`](rocket::http::Status::NotFound) is returned, and if there are more,
     /// [`Status::InternalServerError`](rocket::http::Status::InternalServerError).
-    fn map_to_status(res: Result<usize, diesel::result::Error>) -> Status {
+    ///
+    /// Errors are answered with the status of their kind.
+    fn map_to_status(res: Result<usize, db::Error>) -> Status {
         match res {
             Ok(1) => Status::Ok,
-            Ok(0) | Err(diesel::result::Error::NotFound) => Status::NotFound,
-            _ => Status::InternalServerError,
+            Ok(0) => Status::NotFound,
+            Ok(_) => Status::InternalServerError,
+            Err(e) => e.into(),
+        }
+    }
+
+    /// Verifies the credential at level `L` for a post created by `owner` and crediting
+    /// `authors`. Capabilities scoped to the target are accepted alongside unscoped ones.
+    ///
+    /// Anyone other than `owner` and the `authors` must also have the
+    /// [`EditForeignPosts`](crate::blog::auth::caps::EditForeignPosts) capability for the target.
+    pub(super) fn authorize<L: auth::caps::Verifiable>(
+        capabilities: auth::Capabilities<auth::caps::Any>,
+        owner: uuid::Uuid,
+        authors: &[uuid::Uuid],
+        target: &auth::caps::Target,
+    ) -> Result<auth::Capabilities<L>, Error> {
+        use auth::caps::Verifiable;
+        let capabilities = capabilities
+            .change_level_for::<L>(target)
+            .map_err(|_| Error::Unauthorized)?;
+        let held = capabilities.capabilities_for(target);
+        let user_id = capabilities.user_id();
+        if user_id == owner
+            || authors.contains(&user_id)
+            || auth::caps::EditForeignPosts::verify_slice(held.as_slice())
+        {
+            Ok(capabilities)
+        } else {
+            Err(Error::NotOwner)
         }
     }
 
-    /// Handler for retrieving a post with a specific id. No capabilities needed.
+    /// Verifies the credential at level `L` for the post with the provided id. See
+    /// [`authorize`] for the rules.
+    ///
+    /// Returns the verified credential along with the tags of the post and the ids of the users
+    /// credited on it.
+    fn verify_for_post<L: auth::caps::Verifiable>(
+        db: &DB,
+        id: uuid::Uuid,
+        capabilities: auth::UnverifiedCapabilities,
+    ) -> Result<(auth::Capabilities<L>, Vec<uuid::Uuid>, Vec<uuid::Uuid>), Error> {
+        let post = db
+            .find_post_with_id(id)
+            .tap_err(|e| log::error!("Failed to find post {:?} due to {:?}.", id, e))?;
+        let tag_ids = db
+            .find_post_tag_ids(id)
+            .tap_err(|e| log::error!("Failed to find tags of post {:?} due to {:?}.", id, e))?;
+        let author_ids = db
+            .find_post_author_ids(id)
+            .tap_err(|e| log::error!("Failed to find authors of post {:?} due to {:?}.", id, e))?;
+        let target = auth::caps::Target::Post {
+            id,
+            tag_ids: tag_ids.as_slice(),
+        };
+        let capabilities = authorize(
+            capabilities.into_inner(),
+            post.created_by,
+            author_ids.as_slice(),
+            &target,
+        )?;
+        Ok((capabilities, tag_ids, author_ids))
+    }
+
+    /// Pings the webhooks about `event` on the post with the provided id, if the change `res` came
+    /// from went through.
+    fn notify(
+        db: &DB,
+        hooks: &Webhooks,
+        event: webhooks::Event,
+        id: uuid::Uuid,
+        res: &Result<usize, db::Error>,
+    ) {
+        if let Ok(1) = res {
+            match db.find_post_with_id(id) {
+                Ok(post) => hooks.notify(db, event, &post),
+                Err(e) => log::error!("Failed to find post {:?} for webhooks due to {:?}.", id, e),
+            }
+        }
+    }
+
+    blog_api::assert_route!(blog_api::posts::GET, Get, "/posts/<id>");
+    /// Handler for retrieving a post by its id or friendly name, along with the users credited on
+    /// it, where it is in its series, and its rendered body. No capabilities needed, but unpublished
+    /// posts, and unpublished parts of a series, are only found for those who may edit them. See
+    /// [`viewable`](super::viewable) for which posts are found.
     #[get("/posts/<id>")]
-    pub fn get(db: DB, id: RUuid) -> Result<Json<posts::Data>, Status> {
+    pub fn get(
+        db: DB,
+        id: &RawStr,
+        capabilities: Option<auth::UnverifiedCapabilities>,
+        cache: State<RenderCache>,
+        responses: State<ResponseCache>,
+    ) -> Result<content::Json<String>, Status> {
+        let marker = id.percent_decode_lossy();
+        let key = format!("/posts/{}", marker);
+        let shows = |post: &posts::Authored| Shows::Post(post.post.id);
+        for_visitors(&responses, key, capabilities.is_none(), shows, || {
+            let viewable = viewable::resolve_post_for_viewer(&db, &marker, capabilities.as_ref())
+                .tap_err(|e| log_resolve_error(&marker, e))?;
+            db.find_authored_post(viewable.post, capabilities.is_some())
+                .tap_err(|e| log::error!("Failed to retrieve post {:?} due to DB error {:?}.", marker, e))
+                .map(|post| with_rendered(&cache, post))
+                .map_err(Status::from)
+        })
+    }
+    blog_api::assert_route!(blog_api::posts::RENDERED, Get, "/posts/<id>/rendered");
+    /// Handler for retrieving the body of a post by its id or friendly name rendered to html, with
+    /// its code highlighted. No capabilities needed, but found under the same rules as [`get`].
+    #[get("/posts/<id>/rendered")]
+    pub fn rendered(
+        db: DB,
+        id: &RawStr,
+        capabilities: Option<auth::UnverifiedCapabilities>,
+        cache: State<RenderCache>,
+    ) -> Result<Json<posts::Rendered>, Status> {
+        let marker = id.percent_decode_lossy();
+        let viewable = viewable::resolve_post_for_viewer(&db, &marker, capabilities.as_ref())
+            .tap_err(|e| log_resolve_error(&marker, e))?;
+        Ok(Json(cache.rendered(&viewable.post)))
+    }
+    blog_api::assert_route!(blog_api::posts::EXPORT, Get, "/posts/<id>/export?<format>");
+    /// Handler for downloading a post by its id or friendly name as a document that stands on its
+    /// own, to be sent as a file or printed. No capabilities needed, but found under the same rules
+    /// as [`get`]. See [`export`](super::export) for what the document holds.
+    #[get("/posts/<id>/export?<format>")]
+    pub fn export(
+        db: DB,
+        id: &RawStr,
+        format: export::Format,
+        capabilities: Option<auth::UnverifiedCapabilities>,
+        cache: State<RenderCache>,
+        assets: State<Assets>,
+        locale: RequestLocale,
+    ) -> Result<export::Export, Status> {
+        let marker = id.percent_decode_lossy();
+        let viewable = viewable::resolve_post_for_viewer(&db, &marker, capabilities.as_ref())
+            .tap_err(|e| log_resolve_error(&marker, e))?;
+        let post = db
+            .find_authored_post(viewable.post, capabilities.is_some())
+            .tap_err(|e| log::error!("Failed to retrieve post {:?} due to DB error {:?}.", marker, e))?;
+        let rendered = cache.rendered(&post.post);
+        Ok(match format {
+            export::Format::Html => export::Export::html(&post, &rendered.html, locale.0, &assets),
+        })
+    }
+    /// Logs failures to find a post, save for it not being there, which is no failure of ours.
+    fn log_resolve_error(marker: &str, e: &viewable::ResolveError) {
+        if let viewable::ResolveError::DB(e) = e {
+            log::error!("Failed to retrieve post {:?} due to DB error {:?}.", marker, e);
+        }
+    }
+    blog_api::assert_route!(blog_api::posts::EDITABLE, Get, "/posts/<id>/editable");
+    /// Handler for checking whether the user may edit a post with a specific id, without changing
+    /// it. Responds the same way [`patch`] would refuse, so that editors can warn ahead of time.
+    #[get("/posts/<id>/editable")]
+    pub fn editable(
+        id: RUuid,
+        editor: auth::UnverifiedCapabilities,
+        db: DB,
+    ) -> Result<Status, status::Custom<&'static str>> {
         let id = ruuid_to_uuid(id);
-        db.find_post_with_id(id)
-            .tap_err(|e| log::error!("Failed to retrieve post {:?} due to DB error {:?}.", id, e))
-            .map(Json)
-            .map_err(|_| Status::BadRequest)
+        verify_for_post::<auth::caps::Edit>(&db, id, editor)?;
+        Ok(Status::NoContent)
     }
+    blog_api::assert_route!(blog_api::posts::UPDATE, Patch, "/posts/<id>");
     /// Handler for editing a post with a specific id. Requires user to be logged in and have the
-    /// [`Post`](crate::blog::auth::caps::Edit) capability.
+    /// [`Post`](crate::blog::auth::caps::Edit) capability for the post.
+    ///
+    /// Replaces the users credited on the post if they are listed. Crediting anyone not already
+    /// credited, other than the user, requires the
+    /// [`EditForeignPosts`](crate::blog::auth::caps::EditForeignPosts) capability for the post.
     #[patch("/posts/<id>", data = "<update>")]
     pub fn patch(
         id: RUuid,
-        update: Json<posts::Changed>,
-        _editor: auth::Capabilities<auth::caps::Edit>,
+        update: Json<posts::Update>,
+        editor: auth::UnverifiedCapabilities,
         db: DB,
-    ) -> Status {
+        responses: State<ResponseCache>,
+        config: State<ServerConfig>,
+    ) -> Result<Status, Error> {
+        use auth::caps::Verifiable;
         let id = ruuid_to_uuid(id);
-        let res = db.update_post_with_id(id, &update.into_inner());
-        map_to_status(res)
+        let (editor, tag_ids, author_ids) = verify_for_post::<auth::caps::Edit>(&db, id, editor)?;
+        let posts::Update { changes, authors } = update.into_inner();
+        Error::check(validate_changes(&changes, config.post_body_max_bytes))?;
+        if let Some(authors) = authors.as_ref() {
+            let target = auth::caps::Target::Post {
+                id,
+                tag_ids: tag_ids.as_slice(),
+            };
+            let held = editor.capabilities_for(&target);
+            let may_credit_others = auth::caps::EditForeignPosts::verify_slice(held.as_slice());
+            Error::check(validate_authors(
+                authors,
+                author_ids.as_slice(),
+                editor.user_id(),
+                may_credit_others,
+            ))?;
+        }
+        // Only the authors may be changing, which leaves no columns to update.
+        let res = db.transaction(|tx| {
+            let updated = if changes.is_empty() {
+                1
+            } else {
+                tx.update_post_with_id(id, &changes)?
+            };
+            if let (1, Some(authors)) = (updated, authors.as_ref()) {
+                tx.replace_post_authors(id, authors, editor.user_id())?;
+            }
+            Ok(updated)
+        });
+        responses.invalidate_post(id);
+        Ok(map_to_status(res))
     }
+    blog_api::assert_route!(blog_api::posts::DELETE, Delete, "/posts/<id>");
     /// Handler for deleting a post with a specific id. Requires user to be logged in and have
-    /// the [`Delete`](crate::blog::auth::caps::Delete) capability.
+    /// the [`Delete`](crate::blog::auth::caps::Delete) capability for the post.
     #[delete("/posts/<id>")]
-    pub fn delete(id: RUuid, db: DB, deleter: auth::Capabilities<auth::caps::Delete>) -> Status {
+    pub fn delete(
+        id: RUuid,
+        db: DB,
+        deleter: auth::UnverifiedCapabilities,
+        req_id: RequestId,
+        responses: State<ResponseCache>,
+    ) -> Result<Status, status::Custom<&'static str>> {
         let id = ruuid_to_uuid(id);
+        let (deleter, ..) = verify_for_post::<auth::caps::Delete>(&db, id, deleter)?;
         let deletion_update = posts::Deletion::new(deleter.user_id());
         let req = db
             .delete_post_with_id(id, &deletion_update)
-            .tap_err(|e| log::error!("Failed to delete post {:?} due to error {:?}.", id, e));
-        map_to_status(req)
+            .tap_err(|e| log::error!("[{}] Failed to delete post {:?}: {:?}.", req_id, id, e));
+        responses.invalidate_post(id);
+        Ok(map_to_status(req))
     }
+    blog_api::assert_route!(blog_api::posts::PUBLISH, Post, "/posts/<id>/publish");
     /// Handler for publishing a post with a specific id. Requires user to be logged in and have
-    /// the [`Publish`](crate::blog::auth::caps::Publish) capability.
+    /// the [`Publish`](crate::blog::auth::caps::Publish) capability for the post.
+    ///
+    /// Pings the webhooks for [`PostPublished`](webhooks::Event::PostPublished) once published.
     #[post("/posts/<id>/publish", data = "<update>")]
     pub fn publish(
         id: RUuid,
         db: DB,
+        hooks: State<Webhooks>,
         update: Option<Json<posts::Changed>>,
-        publisher: auth::Capabilities<auth::caps::Publish>,
-    ) -> Status {
+        publisher: auth::UnverifiedCapabilities,
+        responses: State<ResponseCache>,
+        config: State<ServerConfig>,
+    ) -> Result<Status, Error> {
         let id = ruuid_to_uuid(id);
-        if let Some(update) = update {
-            let update = update.into_inner();
-            let changed_credential = publisher.clone().change_level::<auth::caps::Edit>();
-            if let Ok(_) = changed_credential {
-                let status = map_to_status(db.update_post_with_id(id, &update));
-                if status != Status::Ok {
-                    return status;
+        let (publisher, tag_ids, _) = verify_for_post::<auth::caps::Publish>(&db, id, publisher)?;
+        let update = update.map(Json::into_inner);
+        if let Some(update) = update.as_ref() {
+            Error::check(validate_changes(update, config.post_body_max_bytes))?;
+            let target = auth::caps::Target::Post {
+                id,
+                tag_ids: tag_ids.as_slice(),
+            };
+            publisher
+                .clone()
+                .change_level_for::<auth::caps::Edit>(&target)
+                .map_err(|_| Error::Unauthorized)?;
+        }
+        let publishing = posts::Publishing::new(publisher.user_id());
+        // The edits are only kept if the post is published along with them.
+        let res = db.transaction(|tx| {
+            if let Some(update) = update.as_ref() {
+                let updated = tx.update_post_with_id(id, update)?;
+                if updated != 1 {
+                    return Ok(updated);
                 }
-            } else {
-                return Status::Unauthorized;
             }
-        }
-        let publisher = publisher.user_id();
-        map_to_status(db.publish_post_with_id(id, posts::Publishing::new(publisher)))
+            match tx.publish_post_with_id(id, publishing)? {
+                1 => Ok(1),
+                _ => Err(db::Error::NotFound),
+            }
+        });
+        responses.invalidate_post(id);
+        notify(&db, &hooks, webhooks::Event::PostPublished, id, &res);
+        Ok(map_to_status(res))
     }
-    /// Handler for publishing a post with a specific id. Requires user to be logged in and have
-    /// the [`Publish`](crate::blog::auth::caps::Publish) capability.
+    blog_api::assert_route!(blog_api::posts::ARCHIVE, Post, "/posts/<id>/archive");
+    /// Handler for archiving a post with a specific id. Requires user to be logged in and have
+    /// the [`Archive`](crate::blog::auth::caps::Archive) capability for the post.
+    ///
+    /// Pings the webhooks for [`PostArchived`](webhooks::Event::PostArchived) once archived.
     #[post("/posts/<id>/archive")]
-    pub fn archive(id: RUuid, db: DB, archiver: auth::Capabilities<auth::caps::Archive>) -> Status {
+    pub fn archive(
+        id: RUuid,
+        db: DB,
+        hooks: State<Webhooks>,
+        archiver: auth::UnverifiedCapabilities,
+        responses: State<ResponseCache>,
+    ) -> Result<Status, status::Custom<&'static str>> {
+        let id = ruuid_to_uuid(id);
+        let (archiver, ..) = verify_for_post::<auth::caps::Archive>(&db, id, archiver)?;
+        let archival = posts::Archival::new(archiver.user_id());
+        let res = db.archive_post_with_id(id, archival);
+        responses.invalidate_post(id);
+        notify(&db, &hooks, webhooks::Event::PostArchived, id, &res);
+        Ok(map_to_status(res))
+    }
+
+    blog_api::assert_route!(blog_api::posts::TAGS, Get, "/posts/<id>/tags");
+    /// Handler for retrieving the tags on a post with a specific id, ordered by name. No
+    /// capabilities needed.
+    #[get("/posts/<id>/tags")]
+    pub fn get_tags(db: DB, id: RUuid) -> Result<Json<Vec<tags::Data>>, Status> {
         let id = ruuid_to_uuid(id);
-        map_to_status(db.archive_post_with_id(id, posts::Archival::new(archiver.user_id())))
+        db.find_post_tags(id)
+            .tap_err(|e| log::error!("Failed to find tags of post {:?} due to {:?}.", id, e))
+            .map(Json)
+            .map_err(Status::from)
+    }
+    blog_api::assert_route!(blog_api::posts::SET_TAGS, Put, "/posts/<id>/tags");
+    /// Handler for replacing the tags on a post with a specific id with the named tags, creating
+    /// the tags that do not exist yet. Requires user to be logged in and have the
+    /// [`Edit`](crate::blog::auth::caps::Edit) capability for the post.
+    ///
+    /// Responds with the tags now on the post, ordered by name.
+    #[put("/posts/<id>/tags", data = "<names>")]
+    pub fn put_tags(
+        id: RUuid,
+        names: Json<Vec<String>>,
+        editor: auth::UnverifiedCapabilities,
+        db: DB,
+        req_id: RequestId,
+        responses: State<ResponseCache>,
+    ) -> Result<Json<Vec<tags::Data>>, status::Custom<&'static str>> {
+        let id = ruuid_to_uuid(id);
+        let (editor, ..) = verify_for_post::<auth::caps::Edit>(&db, id, editor)?;
+        let names = normalize_tag_names(&names);
+        db.replace_post_tags(id, names.as_slice(), editor.user_id())
+            .tap_err(|e| log::error!("[{}] Failed to tag post {:?}: {:?}.", req_id, id, e))
+            .tap_ok(|_| responses.invalidate_post(id))
+            .map(Json)
+            .map_err(|e| Error::from(e).into())
+    }
+    /// Checks that the post with the provided id takes reactions, which only published posts do.
+    fn check_reactable(db: &DB, id: uuid::Uuid) -> Result<(), Status> {
+        let post = db
+            .find_post_with_id(id)
+            .tap_err(|e| log::error!("Failed to find post {:?} due to {:?}.", id, e))?;
+        match posts::Visibility::of(&post) {
+            Some(posts::Visibility::Published) => Ok(()),
+            _ => Err(Status::NotFound),
+        }
+    }
+    /// Counts the reactions on the post with the provided id, answering with `status`.
+    fn answer_reactions(
+        db: &DB,
+        id: uuid::Uuid,
+        status: Status,
+    ) -> Result<status::Custom<Json<reactions::Counts>>, Status> {
+        db.count_reactions(id)
+            .tap_err(|e| log::error!("Failed to count reactions on post {:?} due to {:?}.", id, e))
+            .map(|counts| status::Custom(status, Json(counts)))
+            .map_err(Status::from)
+    }
+    blog_api::assert_route!(blog_api::posts::REACT, Post, "/posts/<id>/reactions");
+    /// Handler for leaving a reaction on a published post with a specific id. No capabilities
+    /// needed, as each reader is told apart by the [`Visitor`] they are known as.
+    ///
+    /// Responds with the reactions the post now has, with `409 Conflict` if the reader had already
+    /// left the reaction.
+    #[post("/posts/<id>/reactions", format = "json", data = "<reaction>")]
+    pub fn react(
+        id: RUuid,
+        reaction: Json<reactions::Reaction>,
+        visitor: Visitor,
+        db: DB,
+        responses: State<ResponseCache>,
+    ) -> Result<status::Custom<Json<reactions::Counts>>, Status> {
+        let id = ruuid_to_uuid(id);
+        check_reactable(&db, id)?;
+        let new = reactions::New {
+            post_id: id,
+            kind: reaction.kind.as_str(),
+            actor: visitor.actor(),
+        };
+        let added = db
+            .add_reaction(&new)
+            .tap_err(|e| log::error!("Failed to react to post {:?} due to {:?}.", id, e))?;
+        if added {
+            responses.invalidate_shown_post(id);
+        }
+        answer_reactions(&db, id, if added { Status::Ok } else { Status::Conflict })
+    }
+    blog_api::assert_route!(blog_api::posts::RETRACT, Delete, "/posts/<id>/reactions");
+    /// Handler for taking back a reaction left on a post with a specific id. Responds with the
+    /// reactions the post now has, whether or not the reader had left the reaction.
+    #[delete("/posts/<id>/reactions", format = "json", data = "<reaction>")]
+    pub fn retract(
+        id: RUuid,
+        reaction: Json<reactions::Reaction>,
+        visitor: Visitor,
+        db: DB,
+        responses: State<ResponseCache>,
+    ) -> Result<status::Custom<Json<reactions::Counts>>, Status> {
+        let id = ruuid_to_uuid(id);
+        check_reactable(&db, id)?;
+        let removed = db
+            .remove_reaction(id, reaction.kind, visitor.actor())
+            .tap_err(|e| log::error!("Failed to retract reaction on post {:?} due to {:?}.", id, e))?;
+        if removed {
+            responses.invalidate_shown_post(id);
+        }
+        answer_reactions(&db, id, Status::Ok)
+    }
+
+    /// Trims the tag names, dropping the blank and repeated ones.
+    pub(super) fn normalize_tag_names(names: &[String]) -> Vec<&str> {
+        let mut normalized: Vec<&str> = vec![];
+        for name in names.iter().map(|name| name.trim()) {
+            if !name.is_empty() && !normalized.contains(&name) {
+                normalized.push(name);
+            }
+        }
+        normalized
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        type Held = auth::caps::Held;
+
+        fn credential(user_id: uuid::Uuid, caps: &[auth::Capability]) -> auth::Capabilities<()> {
+            let caps = caps.iter().copied().map(Held::unrestricted).collect();
+            auth::Capabilities::safe_new(user_id, caps)
+        }
+
+        /// Checks the owner, co-author, foreign with permission, and foreign without permission
+        /// cases for the verb represented by `L`, which requires `verb`.
+        fn check_verb<L: auth::caps::Verifiable>(verb: auth::Capability) {
+            let owner = uuid::Uuid::new_v4();
+            let other = uuid::Uuid::new_v4();
+            let co_author = uuid::Uuid::new_v4();
+            let authors = [owner, co_author];
+            let post = uuid::Uuid::new_v4();
+            let target = auth::caps::Target::Post {
+                id: post,
+                tag_ids: &[],
+            };
+            let foreign = auth::Capability::EditForeignPosts;
+            let check = |credential, authors: &[uuid::Uuid]| {
+                authorize::<L>(credential, owner, authors, &target)
+            };
+
+            assert!(check(credential(owner, &[verb]), &authors).is_ok());
+            assert!(check(credential(owner, &[verb]), &[]).is_ok());
+            assert!(check(credential(co_author, &[verb]), &authors).is_ok());
+            assert!(check(credential(other, &[verb, foreign]), &authors).is_ok());
+            assert!(matches!(
+                check(credential(other, &[verb]), &authors),
+                Err(Error::NotOwner)
+            ));
+            assert!(matches!(
+                check(credential(co_author, &[foreign]), &authors),
+                Err(Error::Unauthorized)
+            ));
+        }
+
+        #[test]
+        fn patch_requires_ownership_or_foreign_capability() {
+            check_verb::<auth::caps::Edit>(auth::Capability::EditPost);
+        }
+
+        #[test]
+        fn delete_requires_ownership_or_foreign_capability() {
+            check_verb::<auth::caps::Delete>(auth::Capability::DeletePost);
+        }
+
+        #[test]
+        fn publish_requires_ownership_or_foreign_capability() {
+            check_verb::<auth::caps::Publish>(auth::Capability::PublishPost);
+        }
+
+        #[test]
+        fn archive_requires_ownership_or_foreign_capability() {
+            check_verb::<auth::caps::Archive>(auth::Capability::ArchivePost);
+        }
+
+        #[test]
+        fn tag_names_are_trimmed_and_deduplicated() {
+            let names = vec![
+                " rust".to_owned(),
+                "web ".to_owned(),
+                "   ".to_owned(),
+                "rust".to_owned(),
+                "".to_owned(),
+            ];
+            assert_eq!(normalize_tag_names(&names), vec!["rust", "web"]);
+        }
+
+        #[test]
+        fn foreign_capability_may_be_scoped() {
+            let owner = uuid::Uuid::new_v4();
+            let other = uuid::Uuid::new_v4();
+            let post = uuid::Uuid::new_v4();
+            let target = auth::caps::Target::Post {
+                id: post,
+                tag_ids: &[],
+            };
+            let scoped_to = |post| {
+                auth::Capabilities::safe_new(
+                    other,
+                    vec![
+                        Held::unrestricted(auth::Capability::EditPost),
+                        Held {
+                            scope: Some(auth::caps::Scope::Post(post)),
+                            ..Held::unrestricted(auth::Capability::EditForeignPosts)
+                        },
+                    ],
+                )
+            };
+            let check = |credential| authorize::<auth::caps::Edit>(credential, owner, &[], &target);
+            assert!(check(scoped_to(post)).is_ok());
+            assert!(matches!(
+                check(scoped_to(uuid::Uuid::new_v4())),
+                Err(Error::NotOwner)
+            ));
+        }
+
+        #[test]
+        fn authors_may_only_be_credited_by_those_allowed() {
+            let caller = uuid::Uuid::new_v4();
+            let credited = uuid::Uuid::new_v4();
+            let stranger = uuid::Uuid::new_v4();
+            let assigned = |user_id, role| post_authors::Assigned { user_id, role };
+            let author = post_authors::Role::Author;
+            let editor = post_authors::Role::Editor;
+            let fields = |authors: &[post_authors::Assigned], may_credit_others| {
+                validate_authors(authors, &[credited], caller, may_credit_others)
+                    .field("authors")
+                    .len()
+            };
+
+            let own = [assigned(caller, author), assigned(credited, editor)];
+            assert_eq!(fields(&own, false), 0);
+            let foreign = [assigned(caller, author), assigned(stranger, editor)];
+            assert_eq!(fields(&foreign, false), 1);
+            assert_eq!(fields(&foreign, true), 0);
+            assert_eq!(fields(&[], true), 1);
+            assert_eq!(fields(&[assigned(caller, editor)], true), 1);
+            let repeated = [assigned(caller, author), assigned(caller, editor)];
+            assert_eq!(fields(&repeated, true), 1);
+        }
+
+        #[test]
+        fn descriptions_are_bounded_and_images_are_links() {
+            let fields = |description: Option<&str>, image: Option<&str>| -> Vec<String> {
+                validate_seo(description, image).fields().map(str::to_owned).collect()
+            };
+            assert!(fields(Some(""), Some("")).is_empty());
+            assert!(fields(None, None).is_empty());
+            assert!(fields(Some("Short."), Some("/public/img/card.png")).is_empty());
+            assert!(fields(None, Some("https://cdn.benxu.dev/card.png")).is_empty());
+            let long = "é".repeat(posts::META_DESCRIPTION_MAX_CHARS + 1);
+            assert_eq!(fields(Some(&long), None), vec!["meta_description"]);
+            assert_eq!(fields(None, Some("//elsewhere.com/card.png")), vec!["social_image"]);
+            assert_eq!(fields(None, Some("javascript:alert(1)")), vec!["social_image"]);
+            assert_eq!(fields(None, Some("/card.png\" onload=\"x")), vec!["social_image"]);
+        }
+
+        #[test]
+        fn titles_bodies_and_slugs_are_checked() {
+            let fields = |title: Option<&str>, body: Option<&str>, slug: Option<&str>| {
+                let errors = validate_post(title, body, slug, 16);
+                errors.fields().map(str::to_owned).collect::<Vec<_>>()
+            };
+            assert!(fields(Some("A title"), Some("A body."), Some("a-title")).is_empty());
+            assert!(fields(None, None, None).is_empty());
+            assert!(fields(Some("Title"), Some(""), None).is_empty());
+            assert_eq!(fields(Some(" \t"), None, None), vec!["title"]);
+            assert_eq!(fields(Some("Two\nlines"), None, None), vec!["title"]);
+            assert_eq!(fields(Some("Nul\0"), None, None), vec!["title"]);
+            let long = "é".repeat(posts::TITLE_MAX_CHARS + 1);
+            assert_eq!(fields(Some(&long), None, None), vec!["title"]);
+            assert!(fields(Some(&long[2..]), None, None).is_empty());
+            assert_eq!(fields(None, Some(&"x".repeat(17)), None), vec!["body"]);
+            assert_eq!(fields(None, Some("x\0"), None), vec!["body"]);
+            assert_eq!(fields(None, None, Some("A Title")), vec!["slug"]);
+            assert_eq!(fields(None, None, Some("")), vec!["slug"]);
+            assert_eq!(
+                fields(Some(""), Some(&"x".repeat(17)), Some("nul\0")),
+                vec!["body", "slug", "title"]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Takes posts through their whole life over http, as each kind of user. Needs a database,
+    //! see [`test_harness`](crate::test_harness).
+
+    use super::*;
+    use crate::test_harness::{assert_json_includes, json_body, TestApp};
+    use rocket::http::Method::{self, *};
+    use serde_json::json;
+
+    /// Everything an author needs to take their own posts through their life.
+    const AUTHOR: &[auth::Capability] = &[
+        auth::Capability::CreatePost,
+        auth::Capability::EditPost,
+        auth::Capability::PublishPost,
+        auth::Capability::ArchivePost,
+        auth::Capability::DeletePost,
+    ];
+
+    /// Creates a draft as the owner, returning its path.
+    fn draft(app: &TestApp, owner: &rocket::http::Cookie<'static>) -> String {
+        let new = posts::NewNoMeta::new_with_no_flags("Life".to_owned(), "Drafted.".to_owned());
+        let body = serde_json::to_value(new).unwrap();
+        let mut res = app.send(Post, "/api/posts", Some(owner), Some(body));
+        let created: posts::Authored = json_body(&mut res, Status::Ok);
+        format!("/api/posts/{}", created.post.id)
+    }
+
+    /// Each step of the life of a post after its creation, along with its body.
+    fn steps(path: &str) -> Vec<(Method, String, Option<serde_json::Value>)> {
+        vec![
+            (Patch, path.to_owned(), Some(json!({ "body": "Edited." }))),
+            (Post, format!("{}/publish", path), None),
+            (Post, format!("{}/archive", path), None),
+            (Delete, path.to_owned(), None),
+        ]
+    }
+
+    #[test]
+    #[ignore]
+    fn owners_take_their_posts_through_every_step() {
+        let app = TestApp::new();
+        let (_, owner) = app.logged_in_with("owner", AUTHOR);
+        let path = draft(&app, &owner);
+        let shown = |credential| {
+            let mut res = app.send(Get, &path, credential, None);
+            let status = res.status();
+            let body = res.body_string().unwrap_or_default();
+            (status, serde_json::from_str(&body).unwrap_or(serde_json::Value::Null))
+        };
+
+        assert_eq!(shown(None).0, Status::NotFound);
+        assert_json_includes(&shown(Some(&owner)).1, &json!({ "visibility": "draft" }));
+        let visible_after = [None, Some("published"), Some("archived"), None];
+        for ((method, step, body), visible) in steps(&path).into_iter().zip(visible_after.iter()) {
+            let res = app.send(method, &step, Some(&owner), body);
+            assert_eq!(res.status(), Status::Ok, "{} {}", method, step);
+            if let Some(visibility) = visible {
+                let (status, post) = shown(None);
+                assert_eq!(status, Status::Ok, "after {} {}", method, step);
+                assert_json_includes(&post, &json!({ "body": "Edited.", "visibility": visibility }));
+            }
+        }
+        assert_eq!(shown(None).0, Status::NotFound);
+        assert_eq!(shown(Some(&owner)).0, Status::NotFound);
+    }
+
+    #[test]
+    #[ignore]
+    fn other_authors_may_not_touch_posts_they_are_not_credited_on() {
+        let app = TestApp::new();
+        let (_, owner) = app.logged_in_with("owner", AUTHOR);
+        let (_, other) = app.logged_in_with("other", AUTHOR);
+        let path = draft(&app, &owner);
+
+        assert_eq!(app.send(Get, &path, Some(&other), None).status(), Status::NotFound);
+        for (method, step, body) in steps(&path) {
+            let res = app.send(method, &step, Some(&other), body);
+            assert_eq!(res.status(), Status::Forbidden, "{} {}", method, step);
+        }
+        let mut res = app.send(Get, &path, Some(&owner), None);
+        let post: serde_json::Value = json_body(&mut res, Status::Ok);
+        assert_json_includes(&post, &json!({ "body": "Drafted.", "visibility": "draft" }));
+    }
+
+    #[test]
+    #[ignore]
+    fn visitors_must_log_in_for_every_step() {
+        let app = TestApp::new();
+        let (_, owner) = app.logged_in_with("owner", AUTHOR);
+        let new = posts::NewNoMeta::new_with_no_flags("Life".to_owned(), "Drafted.".to_owned());
+        let res = app.send(Post, "/api/posts", None, Some(serde_json::to_value(new).unwrap()));
+        assert_eq!(res.status(), Status::Unauthorized);
+
+        let path = draft(&app, &owner);
+        for (method, step, body) in steps(&path) {
+            let res = app.send(method, &step, None, body);
+            assert_eq!(res.status(), Status::Unauthorized, "{} {}", method, step);
+        }
+        let mut res = app.send(Get, &path, Some(&owner), None);
+        let post: serde_json::Value = json_body(&mut res, Status::Ok);
+        assert_json_includes(&post, &json!({ "body": "Drafted.", "visibility": "draft" }));
+    }
+
+    #[test]
+    #[ignore]
+    fn visitors_see_changes_to_posts_they_were_answered_with_before() {
+        let app = TestApp::new();
+        let (_, owner) = app.logged_in_with("owner", AUTHOR);
+        let path = draft(&app, &owner);
+        let publish = app.send(Post, &format!("{}/publish", path), Some(&owner), None);
+        assert_eq!(publish.status(), Status::Ok);
+        let listing = "/api/posts?offset=0&lim=10";
+        let visit = |path: &str| {
+            let mut res = app.send(Get, path, None, None);
+            json_body::<serde_json::Value>(&mut res, Status::Ok)
+        };
+
+        // Answered once from the database, and then from the cache.
+        for _ in 0..2 {
+            assert_json_includes(&visit(&path), &json!({ "title": "Life", "body": "Drafted." }));
+            assert_eq!(visit(listing)[0][0]["title"], "Life");
+        }
+        let edit = json!({ "title": "Afterlife", "body": "Edited." });
+        let res = app.send(Patch, &path, Some(&owner), Some(edit));
+        assert_eq!(res.status(), Status::Ok);
+        assert_json_includes(&visit(&path), &json!({ "title": "Afterlife", "body": "Edited." }));
+        assert_eq!(visit(listing)[0][0]["title"], "Afterlife");
+    }
+
+    #[test]
+    #[ignore]
+    fn visitors_see_listings_narrowed_down_to_an_author() {
+        let app = TestApp::new();
+        let (author, owner) = app.logged_in_with("owner", AUTHOR);
+        let path = draft(&app, &owner);
+        let publish = app.send(Post, &format!("{}/publish", path), Some(&owner), None);
+        assert_eq!(publish.status(), Status::Ok);
+        let visit = |author: uuid::Uuid| {
+            let path = format!("/api/posts?offset=0&lim=10&author={}", author);
+            let mut res = app.send(Get, &path, None, None);
+            json_body::<serde_json::Value>(&mut res, Status::Ok)
+        };
+
+        // Each author is answered, and kept in the cache, apart from the others.
+        for _ in 0..2 {
+            assert_eq!(visit(author.id)[0][0]["title"], "Life");
+            assert_eq!(visit(uuid::Uuid::new_v4()), json!([]));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn readers_react_to_published_posts_once_per_kind() {
+        let app = TestApp::new();
+        let (_, owner) = app.logged_in_with("owner", AUTHOR);
+        let path = draft(&app, &owner);
+        let reacting = format!("{}/reactions", path);
+        let heart = || Some(json!({ "kind": "heart" }));
+        let res = app.send(Post, &reacting, Some(&owner), heart());
+        assert_eq!(res.status(), Status::NotFound);
+        let publish = app.send(Post, &format!("{}/publish", path), Some(&owner), None);
+        assert_eq!(publish.status(), Status::Ok);
+
+        let mut res = app.send(Post, &reacting, None, heart());
+        let counts: reactions::Counts = json_body(&mut res, Status::Ok);
+        assert_eq!(counts.heart, 1);
+        let visitor = res
+            .cookies()
+            .into_iter()
+            .find(|cookie| cookie.name() == crate::util::visitor::SESSION_COOKIE_NAME)
+            .map(rocket::http::Cookie::into_owned)
+            .expect("Reacting to hand visitors a session.");
+        let mut res = app.send(Post, &reacting, Some(&visitor), heart());
+        let counts: reactions::Counts = json_body(&mut res, Status::Conflict);
+        assert_eq!(counts.heart, 1);
+        let mut res = app.send(Post, &reacting, Some(&owner), heart());
+        let counts: reactions::Counts = json_body(&mut res, Status::Ok);
+        assert_eq!(counts.heart, 2);
+
+        let mut res = app.send(Delete, &reacting, Some(&visitor), heart());
+        let counts: reactions::Counts = json_body(&mut res, Status::Ok);
+        assert_eq!(counts, reactions::Counts { heart: 1, ..reactions::Counts::default() });
+        let mut res = app.send(Get, &path, None, None);
+        let post: serde_json::Value = json_body(&mut res, Status::Ok);
+        assert_json_includes(&post, &json!({ "reactions": { "heart": 1 } }));
     }
 }
@@ -0,0 +1,69 @@
+//! Errors that can occur while using the post endpoints.
+
+use rocket::{
+    http::Status,
+    request::Request,
+    response::{self, status, Responder},
+};
+use rocket_contrib::json::Json;
+
+use crate::util::{blog::db, validation::ValidationErrors};
+
+use blog_db::models::posts::NOT_OWNER_ERROR;
+
+/// Represents possible errors from modifying posts.
+#[derive(Debug)]
+pub enum Error {
+    /// Database errors of many kinds.
+    DB(db::Error),
+    /// Insufficient capabilities for modifying the post.
+    Unauthorized,
+    /// The post was created by someone else, and the user cannot modify posts of others.
+    NotOwner,
+    /// The fields at fault, such as an author list the user may not set. Never empty.
+    Invalid(ValidationErrors),
+}
+impl Error {
+    /// Refuses with the errors, if there are any.
+    pub fn check(errors: ValidationErrors) -> Result<(), Self> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self::Invalid(errors))
+        }
+    }
+}
+impl From<db::Error> for Error {
+    fn from(e: db::Error) -> Self {
+        Self::DB(e)
+    }
+}
+impl From<Error> for rocket::http::Status {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::DB(e) => e.into(),
+            Error::Unauthorized => Self::Unauthorized,
+            Error::NotOwner => Self::Forbidden,
+            Error::Invalid(_) => Self::UnprocessableEntity,
+        }
+    }
+}
+impl From<Error> for status::Custom<&'static str> {
+    fn from(e: Error) -> Self {
+        let msg = match e {
+            Error::NotOwner => NOT_OWNER_ERROR,
+            _ => "",
+        };
+        status::Custom(e.into(), msg)
+    }
+}
+impl<'r> Responder<'r> for Error {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        match self {
+            Self::Invalid(errors) => {
+                status::Custom(Status::UnprocessableEntity, Json(errors)).respond_to(req)
+            }
+            e => status::Custom::<&'static str>::from(e).respond_to(req),
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Turns a post into a document that stands on its own, to be sent to someone as a file or printed.
+//!
+//! The document holds the rendered body under a header with the title, byline and date, styled by
+//! the critical css of the pages. Images served from the public directory are embedded as data
+//! urls, up to [`MAX_IMAGE_BYTES`] each. Those that are not are left linked, after a comment
+//! saying why. The document carries no scripts.
+
+use crypto::encoding::{Base64, Encoding};
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+use page_client::{
+    data::Css,
+    i18n::{self, Locale},
+};
+use rocket::{
+    http::{ContentType, RawStr, Status},
+    request::{FromFormValue, Request},
+    response::{self, Responder, Response},
+};
+use std::{
+    fs,
+    io::Cursor,
+    path::{Component, Path},
+};
+
+use crate::{cfg, util::assets::Assets};
+use blog_db::models::{post_authors, posts};
+
+/// The most bytes an image may take up to be embedded.
+pub const MAX_IMAGE_BYTES: u64 = 1024 * 1024;
+/// Styles for printing, so that printing to a pdf starts each section on a page of its own.
+const PRINT_CSS: &str = "\
+    img { max-width: 100%; }\
+    @media print {\
+        h2 { break-before: page; page-break-before: always; }\
+        pre { white-space: pre-wrap; }\
+        .heading-anchor { display: none; }\
+    }\
+";
+
+/// What the post is exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+}
+impl<'v> FromFormValue<'v> for Format {
+    type Error = &'v RawStr;
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "html" => Ok(Self::Html),
+            _ => Err(form_value),
+        }
+    }
+}
+
+/// Why an image was left linked rather than embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Skipped {
+    /// The image is not served from the public directory.
+    External,
+    /// The image is not in the public directory, or is not an image.
+    Missing,
+    /// The image takes up this many bytes, more than [`MAX_IMAGE_BYTES`].
+    TooLarge(u64),
+}
+impl Skipped {
+    /// The comment left before the image. Never holds `--`, which would end the comment early.
+    fn comment(self) -> String {
+        let why = match self {
+            Self::External => "it is not served by this site".to_owned(),
+            Self::Missing => "it could not be found".to_owned(),
+            Self::TooLarge(len) => format!("its {} bytes are over the limit of {}", len, MAX_IMAGE_BYTES),
+        };
+        format!("<!-- Warning: the image below was not embedded, since {}. -->", why)
+    }
+}
+
+/// Reads the image at `src` from the public directory of `assets` as a data url.
+fn read_image(assets: &Assets, src: &str) -> Result<String, Skipped> {
+    let path = src
+        .strip_prefix(cfg::PUBLIC_ROOT)
+        .and_then(|path| path.strip_prefix('/'))
+        .ok_or(Skipped::External)?;
+    let path = Path::new(path.split(|c| c == '?' || c == '#').next().unwrap_or(path));
+    // Only paths that stay in the public directory are read.
+    if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(Skipped::Missing);
+    }
+    let (file, _) = assets.locate(path);
+    let content_type = file
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(ContentType::from_extension)
+        .filter(|content_type| content_type.top() == "image")
+        .ok_or(Skipped::Missing)?;
+    let len = match fs::metadata(&file) {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return Err(Skipped::Missing),
+    };
+    if len > MAX_IMAGE_BYTES {
+        return Err(Skipped::TooLarge(len));
+    }
+    let bytes = fs::read(&file).map_err(|e| {
+        log::warn!("Failed to read image {:?} to embed due to {:?}.", file, e);
+        Skipped::Missing
+    })?;
+    Ok(format!("data:{};base64,{}", content_type, Base64::encode(&bytes)))
+}
+
+/// The index just past the end of the tag `html` starts with, skipping over quoted attributes.
+fn tag_end(html: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (i, c) in html.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '>' if !quoted => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Undoes the escaping of attribute values in sanitized html.
+fn unescape_attribute(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&amp;", "&")
+}
+
+/// Replaces the source of every image in `html` with what `embed` reads it as, leaving a comment
+/// before those it could not read. Relies on attributes being double quoted, as the sanitizer
+/// writes them.
+fn embed_images(html: &str, mut embed: impl FnMut(&str) -> Result<String, Skipped>) -> String {
+    const SRC: &str = " src=\"";
+    let mut embedded = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<img ") {
+        let end = match tag_end(&rest[start..]) {
+            Some(end) => start + end,
+            None => break,
+        };
+        embedded.push_str(&rest[..start]);
+        let tag = &rest[start..end];
+        let src = tag.find(SRC).map(|at| at + SRC.len()).and_then(|value_start| {
+            let value_end = value_start + tag[value_start..].find('"')?;
+            Some((value_start, value_end))
+        });
+        match src.map(|(start, end)| (start, end, embed(&unescape_attribute(&tag[start..end])))) {
+            Some((value_start, value_end, Ok(data))) => {
+                embedded.push_str(&tag[..value_start]);
+                embedded.push_str(&data);
+                embedded.push_str(&tag[value_end..]);
+            }
+            Some((_, _, Err(skipped))) => {
+                embedded.push_str(&skipped.comment());
+                embedded.push_str(tag);
+            }
+            None => embedded.push_str(tag),
+        }
+        rest = &rest[end..];
+    }
+    embedded.push_str(rest);
+    embedded
+}
+
+/// The names of `authors`, joined into a line.
+fn names(authors: &[&post_authors::Author]) -> String {
+    authors
+        .iter()
+        .map(|author| author.user.display_name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The byline of the post, or [`None`] if nobody is credited on it.
+fn byline(post: &posts::Authored, locale: Locale) -> Option<String> {
+    let (writers, editors): (Vec<_>, Vec<_>) = post
+        .authors
+        .iter()
+        .partition(|author| author.role == post_authors::Role::Author);
+    let mut line = match (writers.is_empty(), editors.is_empty()) {
+        (true, true) => return None,
+        (true, false) => String::new(),
+        (false, _) => format!("{} {}", i18n::translate(locale, "viewer.by", &[]), names(&writers)),
+    };
+    if !editors.is_empty() {
+        if !line.is_empty() {
+            line.push_str(" · ");
+        }
+        line.push_str(&i18n::translate(locale, "viewer.edited_by", &[]));
+        line.push(' ');
+        line.push_str(&names(&editors));
+    }
+    Some(line)
+}
+
+/// The document for `post`, with its body rendered to `body_html` and styled by the critical and
+/// embedded stylesheets among `css` as read into `assets`. Images are embedded with `embed`.
+fn document(
+    post: &posts::Authored,
+    body_html: &str,
+    locale: Locale,
+    css: &[Css],
+    assets: &Assets,
+    embed: impl FnMut(&str) -> Result<String, Skipped>,
+) -> Markup {
+    let date = post.post.published_at.unwrap_or(post.post.updated_at);
+    let body = embed_images(body_html, embed);
+    html! {
+        (DOCTYPE)
+        html lang=(locale.tag()) {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                meta http-equiv="Content-Security-Policy" content="script-src 'none'";
+                title { (post.post.title) }
+                @for css in css.iter().filter(|css| !matches!(css, Css::NonCritical { .. })) {
+                    (css.render_with(Some(&assets.manifest), None))
+                }
+                style { (PreEscaped(PRINT_CSS)) }
+            }
+            body {
+                article class="post" {
+                    header {
+                        h1 { (post.post.title) }
+                        @if let Some(line) = byline(post, locale) {
+                            p class="post-byline" { (line) }
+                        }
+                        p class="post-date" {
+                            time datetime=(date.to_rfc3339()) { (date.format("%Y-%m-%d")) }
+                        }
+                    }
+                    (PreEscaped(body))
+                }
+            }
+        }
+    }
+}
+
+/// A post exported as a file, which browsers download rather than show.
+pub struct Export {
+    /// The name the file is saved as.
+    filename: String,
+    document: String,
+}
+impl Export {
+    /// Exports `post` as html, with its body rendered to `body_html` and embedding the images in
+    /// the public directory of `assets`.
+    pub fn html(post: &posts::Authored, body_html: &str, locale: Locale, assets: &Assets) -> Self {
+        let marker = match &post.post.slug {
+            Some(slug) => slug.clone(),
+            None => post.post.id.to_hyphenated_ref().to_string(),
+        };
+        let css = super::super::css_scripts();
+        let document = document(post, body_html, locale, &css, assets, |src| read_image(assets, src));
+        Self {
+            filename: format!("{}.html", marker),
+            document: document.into_string(),
+        }
+    }
+}
+impl<'r> Responder<'r> for Export {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        Response::build()
+            .status(Status::Ok)
+            .header(ContentType::HTML)
+            .raw_header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", self.filename),
+            )
+            .sized_body(Cursor::new(self.document))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blog_db::models::users;
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    fn post() -> posts::Authored {
+        let now = Utc::now();
+        let author = |id: u128, name: &str, role| post_authors::Author {
+            user: users::AuthorSummary {
+                id: uuid::Uuid::from_u128(id),
+                user_name: name.to_owned(),
+                first_name: None,
+                last_name: None,
+            },
+            role,
+        };
+        posts::Authored {
+            post: posts::Data {
+                id: uuid::Uuid::from_u128(1),
+                created_at: now,
+                created_by: uuid::Uuid::nil(),
+                updated_at: now,
+                updated_by: None,
+                published_at: Some(now),
+                published_by: None,
+                archived_at: None,
+                archived_by: None,
+                deleted_at: None,
+                deleted_by: None,
+                title: "Hello <world>".to_owned(),
+                body: String::new(),
+                slug: Some("hello".to_owned()),
+                meta_description: String::new(),
+                social_image: String::new(),
+            },
+            authors: vec![
+                author(2, "ben", post_authors::Role::Author),
+                author(3, "ed", post_authors::Role::Editor),
+            ],
+            series: vec![],
+            outline: vec![],
+            html: None,
+            visibility: Default::default(),
+            reactions: Default::default(),
+        }
+    }
+
+    fn public_dir() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("export_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("img")).unwrap();
+        fs::write(root.join("img/dot.png"), [1, 2, 3]).unwrap();
+        fs::write(root.join("img/notes.txt"), "notes").unwrap();
+        root
+    }
+
+    #[test]
+    fn only_images_of_the_public_directory_are_read() {
+        let root = public_dir();
+        let assets = Assets::load(root.clone()).unwrap();
+        assert_eq!(read_image(&assets, "/public/img/dot.png"), Ok("data:image/png;base64,AQID".to_owned()));
+        assert_eq!(read_image(&assets, "/public/img/dot.png?v=2"), Ok("data:image/png;base64,AQID".to_owned()));
+        assert_eq!(read_image(&assets, "https://example.com/dot.png"), Err(Skipped::External));
+        assert_eq!(read_image(&assets, "/public/img/missing.png"), Err(Skipped::Missing));
+        assert_eq!(read_image(&assets, "/public/img/notes.txt"), Err(Skipped::Missing));
+        assert_eq!(read_image(&assets, "/public/../export/img/dot.png"), Err(Skipped::Missing));
+
+        fs::write(root.join("img/big.png"), vec![0; MAX_IMAGE_BYTES as usize + 1]).unwrap();
+        let assets = Assets::load(root).unwrap();
+        assert_eq!(
+            read_image(&assets, "/public/img/big.png"),
+            Err(Skipped::TooLarge(MAX_IMAGE_BYTES + 1))
+        );
+    }
+
+    #[test]
+    fn images_are_embedded_or_explained() {
+        let html = "<p><img src=\"/a.png?x=1&amp;y=2\" alt=\"a > b\"> and \
+                    <img alt=\"big\" src=\"/big.png\"></p>";
+        let embedded = embed_images(html, |src| match src {
+            "/a.png?x=1&y=2" => Ok("data:image/png;base64,AQID".to_owned()),
+            _ => Err(Skipped::TooLarge(MAX_IMAGE_BYTES + 1)),
+        });
+        assert_eq!(
+            embedded,
+            format!(
+                "<p><img src=\"data:image/png;base64,AQID\" alt=\"a > b\"> and \
+                 {}<img alt=\"big\" src=\"/big.png\"></p>",
+                Skipped::TooLarge(MAX_IMAGE_BYTES + 1).comment()
+            )
+        );
+    }
+
+    #[test]
+    fn documents_stand_alone_without_scripts() {
+        let assets = Assets::load(public_dir()).unwrap();
+        let css = [Css::Embedded { style: "pre { color: red; }" }];
+        let document = document(
+            &post(),
+            "<h2 id=\"a\">A</h2><img src=\"/public/img/dot.png\">",
+            Locale::En,
+            &css,
+            &assets,
+            |src| read_image(&assets, src),
+        )
+        .into_string();
+        assert!(document.starts_with("<!DOCTYPE html>"));
+        assert!(document.contains("<title>Hello &lt;world&gt;</title>"));
+        assert!(document.contains("By ben · edited by ed"));
+        assert!(document.contains("pre { color: red; }"));
+        assert!(document.contains("page-break-before"));
+        assert!(document.contains("src=\"data:image/png;base64,AQID\""));
+        assert!(!document.contains("<script"));
+    }
+}
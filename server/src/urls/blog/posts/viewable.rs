@@ -0,0 +1,168 @@
+//! Finds posts by their id or friendly name for whoever is reading them, and decides whether and
+//! how they are shown.
+//!
+//! Deleted posts are gone for everyone. Posts that are not published yet are only shown to those
+//! who may edit them, and are missing for everyone else, so that drafts cannot be told apart from
+//! posts that never existed. Archived posts are shown to everyone, marked as archived.
+
+use rocket::http::Status;
+
+use super::post::authorize;
+use crate::util::{
+    auth,
+    blog::{
+        db::{self, PostQuery},
+        DB,
+    },
+};
+use blog_db::models::posts::{self, Visibility};
+
+/// Why a post could not be shown.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The post does not exist, was deleted, or is a draft the viewer may not see.
+    NotFound,
+    DB(db::Error),
+}
+impl From<db::Error> for ResolveError {
+    fn from(e: db::Error) -> Self {
+        match e {
+            db::Error::NotFound => Self::NotFound,
+            e => Self::DB(e),
+        }
+    }
+}
+impl From<ResolveError> for Status {
+    fn from(e: ResolveError) -> Self {
+        match e {
+            ResolveError::NotFound => Status::NotFound,
+            ResolveError::DB(e) => e.into(),
+        }
+    }
+}
+
+/// A post the viewer may see, along with how it is shown to them.
+#[derive(Debug)]
+pub struct ViewablePost {
+    pub post: posts::Data,
+    pub visibility: Visibility,
+}
+
+/// How a post in its state is shown to a viewer, who `may_edit` it or not.
+fn visibility_for(post: &posts::Data, may_edit: bool) -> Result<Visibility, ResolveError> {
+    match Visibility::of(post) {
+        None => Err(ResolveError::NotFound),
+        Some(Visibility::Draft) if !may_edit => Err(ResolveError::NotFound),
+        Some(visibility) => Ok(visibility),
+    }
+}
+
+/// Whether the viewer may edit the post, by the same rules as editing it through the api.
+fn may_edit_post(
+    db: &DB,
+    post: &posts::Data,
+    credential: &auth::UnverifiedCapabilities,
+) -> Result<bool, ResolveError> {
+    let tag_ids = db.find_post_tag_ids(post.id)?;
+    let author_ids = db.find_post_author_ids(post.id)?;
+    let target = auth::caps::Target::Post {
+        id: post.id,
+        tag_ids: tag_ids.as_slice(),
+    };
+    let capabilities = (**credential).clone();
+    let allowed = authorize::<auth::caps::Edit>(capabilities, post.created_by, &author_ids, &target);
+    Ok(allowed.is_ok())
+}
+
+/// Finds the post `marker` names, by its id or else its friendly name, if the viewer holding
+/// `credential` may see it.
+pub fn resolve_post_for_viewer(
+    db: &DB,
+    marker: &str,
+    credential: Option<&auth::UnverifiedCapabilities>,
+) -> Result<ViewablePost, ResolveError> {
+    let post = match uuid::Uuid::parse_str(marker) {
+        Ok(id) => db.find_post_with_id(id)?,
+        Err(_) => db.find_post_with_slug(marker)?,
+    };
+    // Only drafts need the viewer checked, which takes a few more queries.
+    let may_edit = match (Visibility::of(&post), credential) {
+        (Some(Visibility::Draft), Some(credential)) => may_edit_post(db, &post, credential)?,
+        _ => false,
+    };
+    let visibility = visibility_for(&post, may_edit)?;
+    Ok(ViewablePost { post, visibility })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    fn post(published: bool, archived: bool, deleted: bool) -> posts::Data {
+        let now = Utc::now();
+        let at = |set: bool| if set { Some(now) } else { None };
+        posts::Data {
+            id: uuid::Uuid::nil(),
+            created_at: now,
+            created_by: uuid::Uuid::nil(),
+            updated_at: now,
+            updated_by: None,
+            published_at: at(published),
+            published_by: None,
+            archived_at: at(archived),
+            archived_by: None,
+            deleted_at: at(deleted),
+            deleted_by: None,
+            title: "post".to_owned(),
+            body: String::new(),
+            slug: None,
+            meta_description: String::new(),
+            social_image: String::new(),
+        }
+    }
+
+    #[test]
+    fn every_state_is_shown_only_to_whom_it_should_be() {
+        use Visibility::*;
+        // (published, archived, deleted), then what a reader and an editor see.
+        let matrix = [
+            ((true, false, false), Some(Published), Some(Published)),
+            ((true, true, false), Some(Archived), Some(Archived)),
+            ((false, false, false), None, Some(Draft)),
+            ((false, true, false), None, Some(Draft)),
+            ((true, false, true), None, None),
+            ((true, true, true), None, None),
+            ((false, false, true), None, None),
+            ((false, true, true), None, None),
+        ];
+        for &((published, archived, deleted), reader, editor) in matrix.iter() {
+            let post = post(published, archived, deleted);
+            let shown = |may_edit| visibility_for(&post, may_edit).ok();
+            assert_eq!(shown(false), reader, "reader of {:?}", (published, archived, deleted));
+            assert_eq!(shown(true), editor, "editor of {:?}", (published, archived, deleted));
+        }
+    }
+
+    #[test]
+    fn only_those_who_may_edit_a_post_count_as_its_editors() {
+        type Held = auth::caps::Held;
+        let owner = uuid::Uuid::new_v4();
+        let other = uuid::Uuid::new_v4();
+        let target = auth::caps::Target::Post {
+            id: uuid::Uuid::nil(),
+            tag_ids: &[],
+        };
+        let edits = |user_id, caps: &[auth::Capability]| {
+            let caps = caps.iter().copied().map(Held::unrestricted).collect();
+            let credential = auth::Capabilities::safe_new(user_id, caps);
+            authorize::<auth::caps::Edit>(credential, owner, &[], &target).is_ok()
+        };
+        let edit = auth::Capability::EditPost;
+        let foreign = auth::Capability::EditForeignPosts;
+        assert!(edits(owner, &[edit]));
+        assert!(!edits(owner, &[]));
+        assert!(!edits(other, &[edit]));
+        assert!(edits(other, &[edit, foreign]));
+    }
+}
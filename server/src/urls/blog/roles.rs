@@ -0,0 +1,159 @@
+//! Handlers and functions for managing roles, which are named sets of capabilities that can be
+//! granted to users all at once.
+//!
+//! Capabilities granted through roles are merged with directly granted capabilities at login.
+
+mod error;
+use error::Error;
+
+use rocket::{http::Status, response::status};
+use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
+use std::convert::TryFrom;
+
+use crate::util::{
+    auth,
+    blog::{
+        db::{CapabilityQuery, RoleQuery},
+        DB,
+    },
+    uuid_compat::ruuid_to_uuid,
+};
+use blog_db::models::*;
+
+/// Create a role. Requires caller to have the
+/// [`GrantCapability`](crate::blog::auth::caps::GrantCapability) capability.
+#[post("/roles", format = "json", data = "<role_to_create>")]
+pub fn post(
+    db: DB,
+    capabilities: auth::Capabilities<auth::caps::GrantCapability>,
+    role_to_create: Json<roles::NewNoMeta>,
+) -> Result<Json<roles::Data>, Status> {
+    let role_to_create = role_to_create.into_inner();
+    db.create_role(roles::New::from((&role_to_create, capabilities.user_id())))
+        .map(Json)
+        .map_err(|e| Error::from(e).into())
+}
+
+/// Handlers and functions for managing individual roles.
+pub mod role {
+    use super::*;
+
+    /// Gets the role with the requested id along with the capabilities it grants. Requires
+    /// caller to have the [`ViewCapability`](crate::blog::auth::caps::ViewCapability) capability.
+    #[get("/roles/<id>")]
+    pub fn get(
+        db: DB,
+        _capabilities: auth::Capabilities<auth::caps::ViewCapability>,
+        id: RUuid,
+    ) -> Result<Json<(roles::Data, Vec<auth::Capability>)>, Status> {
+        let id = ruuid_to_uuid(id);
+        let role = db.find_role_with_id(id).map_err(Error::from)?;
+        let role_capabilities = db
+            .get_role_capabilities(role.id)
+            .map_err(Error::from)?
+            .iter()
+            .map(auth::Capability::try_from)
+            .collect::<Result<_, _>>()
+            .map_err(Error::from)?;
+        Ok(Json((role, role_capabilities)))
+    }
+
+    /// Adds capabilities to a role. Requires caller to have the
+    /// [`GrantCapability`](crate::blog::auth::caps::GrantCapability) capability as well as every
+    /// capability being added.
+    #[post("/roles/<id>/capabilities", format = "json", data = "<to_add>")]
+    pub fn add_capabilities(
+        db: DB,
+        capabilities: auth::Capabilities<auth::caps::GrantCapability>,
+        id: RUuid,
+        to_add: Json<Vec<auth::Capability>>,
+    ) -> Result<Json<Vec<role_capabilities::Data>>, Status> {
+        let id = ruuid_to_uuid(id);
+        if !capabilities.has_capabilities(to_add.as_slice()) {
+            return Err(Error::Unauthorized.into());
+        }
+        let to_add = to_add
+            .iter()
+            .map(|c| role_capabilities::New {
+                role_id: id,
+                capability: c.as_str(),
+                created_by: capabilities.user_id(),
+            })
+            .collect();
+        db.add_capabilities_to_role(to_add)
+            .map(Json)
+            .map_err(|e| Error::from(e).into())
+    }
+
+    /// Removes capabilities from a role. Requires caller to have the
+    /// [`DeleteCapability`](crate::blog::auth::caps::DeleteCapability) capability.
+    ///
+    /// Refuses with a 409 if this would leave nobody holding
+    /// [`ADMIN_CRITICAL`](crate::util::auth::caps::ADMIN_CRITICAL).
+    #[delete("/roles/<id>/capabilities", format = "json", data = "<to_remove>")]
+    pub fn remove_capabilities(
+        db: DB,
+        _capabilities: auth::Capabilities<auth::caps::DeleteCapability>,
+        id: RUuid,
+        to_remove: Json<Vec<auth::Capability>>,
+    ) -> Result<Json<Vec<role_capabilities::Data>>, status::Custom<&'static str>> {
+        let id = ruuid_to_uuid(id);
+        db.delete_preserving_capability(auth::caps::ADMIN_CRITICAL, || {
+            db.remove_capabilities_from_role(id, to_remove.as_slice())
+        })
+        .map(Json)
+        .map_err(|e| Error::from(e).into())
+    }
+
+    /// Grants a role to a user. Requires caller to have the
+    /// [`GrantCapability`](crate::blog::auth::caps::GrantCapability) capability as well as every
+    /// capability the role grants.
+    #[post("/roles/<id>/users/<user_id>")]
+    pub fn grant(
+        db: DB,
+        capabilities: auth::Capabilities<auth::caps::GrantCapability>,
+        id: RUuid,
+        user_id: RUuid,
+    ) -> Result<Json<user_roles::Data>, Status> {
+        let id = ruuid_to_uuid(id);
+        let user_id = ruuid_to_uuid(user_id);
+        let role_capabilities: Vec<auth::Capability> = db
+            .get_role_capabilities(id)
+            .map_err(Error::from)?
+            .iter()
+            .map(auth::Capability::try_from)
+            .collect::<Result<_, _>>()
+            .map_err(Error::from)?;
+        if !capabilities.has_capabilities(role_capabilities.as_slice()) {
+            return Err(Error::Unauthorized.into());
+        }
+        db.grant_role_to_user(user_roles::New {
+            user_id,
+            role_id: id,
+            created_by: capabilities.user_id(),
+        })
+        .map(Json)
+        .map_err(|e| Error::from(e).into())
+    }
+
+    /// Revokes a role from a user. Requires caller to have the
+    /// [`DeleteCapability`](crate::blog::auth::caps::DeleteCapability) capability.
+    ///
+    /// Refuses with a 409 if this would leave nobody holding
+    /// [`ADMIN_CRITICAL`](crate::util::auth::caps::ADMIN_CRITICAL).
+    #[delete("/roles/<id>/users/<user_id>")]
+    pub fn revoke(
+        db: DB,
+        _capabilities: auth::Capabilities<auth::caps::DeleteCapability>,
+        id: RUuid,
+        user_id: RUuid,
+    ) -> Result<Json<user_roles::Data>, status::Custom<&'static str>> {
+        let id = ruuid_to_uuid(id);
+        let user_id = ruuid_to_uuid(user_id);
+        db.delete_preserving_capability(auth::caps::ADMIN_CRITICAL, || {
+            db.revoke_role_from_user(user_id, id)
+        })
+        .map(Json)
+        .map_err(|e| Error::from(e).into())
+    }
+}
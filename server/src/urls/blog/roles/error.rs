@@ -0,0 +1,61 @@
+//! Errors that can occur while using the role endpoints.
+
+use rocket::response::status;
+
+use crate::util::{
+    auth::caps::{UnknownCapability, LAST_ADMIN_MSG},
+    blog::db::{self, GuardedDeletionError},
+};
+
+/// Represents possible errors from using the database for roles.
+pub enum Error {
+    /// Database errors of many kinds.
+    DB(db::Error),
+    /// Insufficient capabilities for accessing an endpoint for roles.
+    Unauthorized,
+    /// The operation would leave nobody holding the
+    /// [`ADMIN_CRITICAL`](crate::util::auth::caps::ADMIN_CRITICAL) capability.
+    LastAdmin,
+    /// A role grants a capability this version of the site does not recognize.
+    UnknownCapability(UnknownCapability),
+}
+impl From<db::Error> for Error {
+    fn from(e: db::Error) -> Self {
+        Self::DB(e)
+    }
+}
+impl From<UnknownCapability> for Error {
+    fn from(e: UnknownCapability) -> Self {
+        Self::UnknownCapability(e)
+    }
+}
+impl From<GuardedDeletionError> for Error {
+    fn from(e: GuardedDeletionError) -> Self {
+        match e {
+            GuardedDeletionError::LastHolder => Self::LastAdmin,
+            GuardedDeletionError::Query(e) => Self::DB(e),
+        }
+    }
+}
+impl From<Error> for rocket::http::Status {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::DB(e) => e.into(),
+            Error::Unauthorized => Self::Unauthorized,
+            Error::LastAdmin => Self::Conflict,
+            Error::UnknownCapability(e) => {
+                log::error!("Role contains {}.", e);
+                Self::InternalServerError
+            }
+        }
+    }
+}
+impl From<Error> for status::Custom<&'static str> {
+    fn from(e: Error) -> Self {
+        let msg = match e {
+            Error::LastAdmin => LAST_ADMIN_MSG,
+            _ => "",
+        };
+        status::Custom(e.into(), msg)
+    }
+}
@@ -0,0 +1,185 @@
+//! Handlers for managing series, which are posts meant to be read in order.
+//!
+//! Anyone may read a series. Changing one requires the
+//! [`Edit`](crate::blog::auth::caps::Edit) capability, the same as editing posts.
+
+use rocket::{
+    http::{RawStr, Status},
+    State,
+};
+use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
+use tap::*;
+
+use crate::util::{
+    auth,
+    blog::{db::SeriesQuery, DB},
+    response_cache::ResponseCache,
+    uuid_compat::ruuid_to_uuid,
+    validation::{validate_slug, Refusal, ValidationErrors},
+};
+use blog_db::models::*;
+
+/// Checks the fields of a series that are present.
+fn validate(title: Option<&str>, slug: Option<&str>) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    if title.map_or(false, |title| title.trim().is_empty()) {
+        errors.add("title", "required", "Enter a title.");
+    }
+    if let Some(slug) = slug {
+        validate_slug("slug", slug, &mut errors);
+    }
+    errors
+}
+
+/// Checks the posts making up a series.
+fn validate_parts(post_ids: &[uuid::Uuid]) -> ValidationErrors {
+    let repeated = post_ids
+        .iter()
+        .enumerate()
+        .any(|(i, a)| post_ids[..i].contains(a));
+    if repeated {
+        ValidationErrors::single("posts", "duplicate", "Add each post only once.")
+    } else {
+        ValidationErrors::new()
+    }
+}
+
+blog_api::assert_route!(blog_api::series::LIST, Get, "/series?<limit>&<offset>");
+/// Lists a page of the series, by title. No capabilities needed.
+#[get("/series?<limit>&<offset>")]
+pub fn get(
+    db: DB,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Json<page::Page<series::Data>>, Status> {
+    db.find_series_page(limit, offset)
+        .tap_err(|e| log::error!("Failed to list series due to error {:?}.", e))
+        .map(Json)
+        .map_err(Status::from)
+}
+
+blog_api::assert_route!(blog_api::series::CREATE, Post, "/series");
+/// Creates a series without any posts. Requires caller to have the
+/// [`Edit`](crate::blog::auth::caps::Edit) capability.
+#[post("/series", format = "json", data = "<new>")]
+pub fn post(
+    db: DB,
+    capabilities: auth::Capabilities<auth::caps::Edit>,
+    new: Json<series::NewNoMeta>,
+) -> Result<Json<series::Data>, Refusal> {
+    let new = new.into_inner();
+    Refusal::check(validate(Some(&new.title), Some(&new.slug)))?;
+    db.create_series((&new, capabilities.user_id()))
+        .tap_err(|e| log::error!("Failed to create series due to error {:?}.", e))
+        .map(Json)
+        .map_err(|e| Status::from(e).into())
+}
+
+/// Handlers for managing individual series.
+pub mod one {
+    use super::*;
+
+    blog_api::assert_route!(blog_api::series::GET, Get, "/series/<id>");
+    /// Finds a series by its id or friendly name, along with its parts in order. No capabilities
+    /// needed, but unpublished parts are only listed for those logged in.
+    #[get("/series/<id>")]
+    pub fn get(
+        db: DB,
+        id: &RawStr,
+        capabilities: Option<auth::UnverifiedCapabilities>,
+    ) -> Result<Json<series::WithParts>, Status> {
+        let found = match uuid::Uuid::parse_str(id.as_str()) {
+            Ok(id) => db.find_series_with_id(id),
+            Err(_) => db.find_series_with_slug(&id.percent_decode_lossy()),
+        };
+        let series = found
+            .tap_err(|e| log::error!("Failed to find series {:?} due to {:?}.", id, e))?;
+        let parts = db
+            .find_series_parts(series.id, capabilities.is_some())
+            .tap_err(|e| log::error!("Failed to find parts of {:?} due to {:?}.", series.id, e))?;
+        Ok(Json(series::WithParts { series, parts }))
+    }
+
+    blog_api::assert_route!(blog_api::series::UPDATE, Patch, "/series/<id>");
+    /// Changes the title, friendly name, or description of a series. Requires caller to have the
+    /// [`Edit`](crate::blog::auth::caps::Edit) capability.
+    #[patch("/series/<id>", format = "json", data = "<changes>")]
+    pub fn patch(
+        db: DB,
+        capabilities: auth::Capabilities<auth::caps::Edit>,
+        id: RUuid,
+        changes: Json<series::Changed>,
+        responses: State<ResponseCache>,
+    ) -> Result<Json<series::Data>, Refusal> {
+        let id = ruuid_to_uuid(id);
+        Refusal::check(validate(changes.title.as_deref(), changes.slug.as_deref()))?;
+        db.update_series_with_id(id, &changes, capabilities.user_id())
+            .tap_err(|e| log::error!("Failed to update series {:?} due to {:?}.", id, e))
+            .tap_ok(|_| responses.clear())
+            .map(Json)
+            .map_err(|e| Status::from(e).into())
+    }
+
+    blog_api::assert_route!(blog_api::series::DELETE, Delete, "/series/<id>");
+    /// Deletes a series, leaving its posts as they are. Requires caller to have the
+    /// [`Edit`](crate::blog::auth::caps::Edit) capability.
+    #[delete("/series/<id>")]
+    pub fn delete(
+        db: DB,
+        _capabilities: auth::Capabilities<auth::caps::Edit>,
+        id: RUuid,
+        responses: State<ResponseCache>,
+    ) -> Result<Json<series::Data>, Status> {
+        let id = ruuid_to_uuid(id);
+        db.delete_series_with_id(id)
+            .tap_err(|e| log::error!("Failed to delete series {:?} due to {:?}.", id, e))
+            .tap_ok(|_| responses.clear())
+            .map(Json)
+            .map_err(Status::from)
+    }
+
+    blog_api::assert_route!(blog_api::series::SET_POSTS, Put, "/series/<id>/posts");
+    /// Replaces the posts in a series with those listed, in the order listed. Either every post
+    /// is placed or none are. Requires caller to have the
+    /// [`Edit`](crate::blog::auth::caps::Edit) capability.
+    #[put("/series/<id>/posts", format = "json", data = "<post_ids>")]
+    pub fn put_posts(
+        db: DB,
+        capabilities: auth::Capabilities<auth::caps::Edit>,
+        id: RUuid,
+        post_ids: Json<Vec<uuid::Uuid>>,
+        responses: State<ResponseCache>,
+    ) -> Result<Json<Vec<series::Part>>, Refusal> {
+        let id = ruuid_to_uuid(id);
+        Refusal::check(validate_parts(&post_ids))?;
+        db.find_series_with_id(id)
+            .tap_err(|e| log::error!("Failed to find series {:?} due to {:?}.", id, e))
+            .map_err(Status::from)?;
+        db.replace_series_posts(id, &post_ids, capabilities.user_id())
+            .tap_err(|e| log::error!("Failed to place posts in {:?} due to {:?}.", id, e))
+            .tap_ok(|_| responses.clear())
+            .map(Json)
+            .map_err(|e| Status::from(e).into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn series_need_a_title_a_friendly_slug_and_distinct_parts() {
+        assert!(validate(Some("Rust in anger"), Some("rust-in-anger-2")).is_empty());
+        assert!(validate(None, None).is_empty());
+        let fields = |errors: ValidationErrors| -> Vec<String> {
+            errors.fields().map(str::to_owned).collect()
+        };
+        assert_eq!(fields(validate(Some("  "), Some(""))), vec!["slug", "title"]);
+        assert_eq!(fields(validate(None, Some("Rust In Anger"))), vec!["slug"]);
+
+        let (a, b) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        assert!(validate_parts(&[a, b]).is_empty());
+        assert!(validate_parts(&[]).is_empty());
+        assert_eq!(fields(validate_parts(&[a, b, a])), vec!["posts"]);
+    }
+}
@@ -0,0 +1,22 @@
+//! Handlers describing the site as a whole.
+
+use rocket::State;
+use rocket_contrib::json::Json;
+
+use crate::util::{site_identity::SiteIdentity, site_mode::SiteMode};
+use blog_db::models::site;
+
+blog_api::assert_route!(blog_api::site::STATUS, Get, "/site/status");
+/// Gets the mode the site is in, so that the client can tell users when it is read only.
+#[get("/site/status")]
+pub fn status(site_mode: State<SiteMode>) -> Json<site::Mode> {
+    Json(site_mode.get())
+}
+
+blog_api::assert_route!(blog_api::site::IDENTITY, Get, "/site/identity");
+/// Gets how the site presents itself, so that the client shows the same title and logo as the
+/// pages around it.
+#[get("/site/identity")]
+pub fn identity(site_identity: State<SiteIdentity>) -> Json<site::Identity> {
+    Json(site_identity.get())
+}
@@ -0,0 +1,23 @@
+//! Handlers for listing tags.
+
+use rocket::http::Status;
+use rocket_contrib::json::Json;
+use tap::*;
+
+use crate::util::blog::{db::TagQuery, DB};
+use blog_db::models::*;
+
+blog_api::assert_route!(blog_api::tags::LIST, Get, "/tags?<limit>&<offset>");
+/// Handler for listing a page of the tags, such as for suggesting tags while editing a post. No
+/// capabilities needed.
+#[get("/tags?<limit>&<offset>")]
+pub fn get(
+    db: DB,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Json<page::Page<tags::Data>>, Status> {
+    db.find_tags_page(limit, offset)
+        .tap_err(|e| log::error!("Failed to list tags due to error {:?}.", e))
+        .map(Json)
+        .map_err(Status::from)
+}
@@ -0,0 +1,166 @@
+//! Handlers for managing the webhooks pinged when things happen on the site. See
+//! [`util::webhooks`](crate::util::webhooks) for how they are delivered to.
+
+use rocket::http::Status;
+use rocket_contrib::{json::Json, uuid::Uuid as RUuid};
+use serde_json::json;
+use tap::*;
+
+use crate::util::{
+    auth,
+    blog::{db::WebhookQuery, DB},
+    uuid_compat::ruuid_to_uuid,
+    validation::{Refusal, ValidationErrors},
+    webhooks::send_sample,
+};
+use blog_db::models::*;
+
+/// Checks the fields of a webhook that are present.
+fn validate(url: Option<&str>, secret: Option<&str>, event_mask: Option<i32>) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    if let Some(url) = url {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            errors.add("url", "invalid", "Enter an http or https url.");
+        }
+    }
+    if secret.map_or(false, str::is_empty) {
+        errors.add("secret", "required", "Enter a secret.");
+    }
+    if let Some(event_mask) = event_mask {
+        let known = webhooks::Event::mask(&webhooks::Event::ALL);
+        if event_mask == 0 || event_mask & !known != 0 {
+            errors.add("event_mask", "invalid", "Choose events to deliver.");
+        }
+    }
+    errors
+}
+
+blog_api::assert_route!(blog_api::admin::WEBHOOKS, Get, "/admin/webhooks?<limit>&<offset>");
+/// Lists a page of the webhooks, oldest first, without their secrets. Pages hold at most
+/// [`MAX_LIMIT`](page::MAX_LIMIT) webhooks, however many are asked for. Requires caller to have
+/// the [`ManageWebhooks`](crate::blog::auth::caps::ManageWebhooks) capability.
+#[get("/admin/webhooks?<limit>&<offset>")]
+pub fn get(
+    db: DB,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    _capabilities: auth::Capabilities<auth::caps::ManageWebhooks>,
+) -> Result<Json<page::Page<webhooks::Data>>, Status> {
+    db.find_webhooks_page(limit, offset)
+        .tap_err(|e| log::error!("Failed to list webhooks due to error {:?}.", e))
+        .map(Json)
+        .map_err(Status::from)
+}
+
+blog_api::assert_route!(blog_api::admin::CREATE_WEBHOOK, Post, "/admin/webhooks");
+/// Creates a webhook. Requires caller to have the
+/// [`ManageWebhooks`](crate::blog::auth::caps::ManageWebhooks) capability.
+#[post("/admin/webhooks", format = "json", data = "<hook>")]
+pub fn post(
+    db: DB,
+    capabilities: auth::Capabilities<auth::caps::ManageWebhooks>,
+    hook: Json<webhooks::NewNoMeta>,
+) -> Result<Json<webhooks::Data>, Refusal> {
+    let hook = hook.into_inner();
+    Refusal::check(validate(
+        Some(&hook.url),
+        Some(&hook.secret),
+        Some(hook.event_mask),
+    ))?;
+    db.create_webhook((&hook, capabilities.user_id()))
+        .tap_err(|e| log::error!("Failed to create webhook due to error {:?}.", e))
+        .map(Json)
+        .map_err(|e| Status::from(e).into())
+}
+
+/// Handlers for managing individual webhooks.
+pub mod webhook {
+    use super::*;
+
+    blog_api::assert_route!(blog_api::admin::UPDATE_WEBHOOK, Patch, "/admin/webhooks/<id>");
+    /// Changes a webhook, such as to disable it. Requires caller to have the
+    /// [`ManageWebhooks`](crate::blog::auth::caps::ManageWebhooks) capability.
+    #[patch("/admin/webhooks/<id>", format = "json", data = "<changes>")]
+    pub fn patch(
+        db: DB,
+        capabilities: auth::Capabilities<auth::caps::ManageWebhooks>,
+        id: RUuid,
+        changes: Json<webhooks::Changed>,
+    ) -> Result<Json<webhooks::Data>, Refusal> {
+        let id = ruuid_to_uuid(id);
+        Refusal::check(validate(
+            changes.url.as_deref(),
+            changes.secret.as_deref(),
+            changes.event_mask,
+        ))?;
+        db.update_webhook_with_id(id, &changes, capabilities.user_id())
+            .tap_err(|e| log::error!("Failed to update webhook {:?} due to {:?}.", id, e))
+            .map(Json)
+            .map_err(|e| Status::from(e).into())
+    }
+
+    blog_api::assert_route!(blog_api::admin::DELETE_WEBHOOK, Delete, "/admin/webhooks/<id>");
+    /// Deletes a webhook, along with its dead letters. Requires caller to have the
+    /// [`ManageWebhooks`](crate::blog::auth::caps::ManageWebhooks) capability.
+    #[delete("/admin/webhooks/<id>")]
+    pub fn delete(
+        db: DB,
+        _capabilities: auth::Capabilities<auth::caps::ManageWebhooks>,
+        id: RUuid,
+    ) -> Result<Json<webhooks::Data>, Status> {
+        let id = ruuid_to_uuid(id);
+        db.delete_webhook_with_id(id)
+            .tap_err(|e| log::error!("Failed to delete webhook {:?} due to {:?}.", id, e))
+            .map(Json)
+            .map_err(Status::from)
+    }
+
+    blog_api::assert_route!(blog_api::admin::TEST_WEBHOOK, Post, "/admin/webhooks/<id>/test");
+    /// Sends a sample [`PostPublished`](webhooks::Event::PostPublished) event to a webhook right
+    /// away, once, even if it is disabled. Answers with a 502 if the webhook does not accept it.
+    /// Requires caller to have the [`ManageWebhooks`](crate::blog::auth::caps::ManageWebhooks)
+    /// capability.
+    #[post("/admin/webhooks/<id>/test")]
+    pub fn test(
+        db: DB,
+        _capabilities: auth::Capabilities<auth::caps::ManageWebhooks>,
+        id: RUuid,
+    ) -> Result<Status, Status> {
+        let id = ruuid_to_uuid(id);
+        let hook = db
+            .find_webhook_with_id(id)
+            .tap_err(|e| log::error!("Failed to find webhook {:?} due to {:?}.", id, e))
+            .map_err(Status::from)?;
+        let sample = json!({
+            "id": uuid::Uuid::nil(),
+            "title": "A sample post",
+            "slug": "a-sample-post",
+        });
+        match send_sample(&hook, webhooks::Event::PostPublished, &sample) {
+            Ok(status) if (200..300).contains(&status) => Ok(Status::NoContent),
+            res => {
+                log::warn!("Webhook {:?} refused the sample event: {:?}.", id, res);
+                Err(Status::BadGateway)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn webhooks_need_a_url_a_secret_and_known_events() {
+        let mask = webhooks::Event::mask(&[webhooks::Event::PostPublished]);
+        let ok = validate(Some("https://example.com/hook"), Some("secret"), Some(mask));
+        assert!(ok.is_empty());
+        assert!(validate(None, None, None).is_empty());
+        let fields = |errors: ValidationErrors| -> Vec<String> {
+            errors.fields().map(str::to_owned).collect()
+        };
+        let bad = validate(Some("ftp://example.com"), Some(""), Some(0));
+        assert_eq!(fields(bad), vec!["event_mask", "secret", "url"]);
+        assert_eq!(fields(validate(None, None, Some(1 << 30))), vec!["event_mask"]);
+    }
+}
@@ -0,0 +1,114 @@
+//! Handlers for the well-known resources other services discover the site through.
+
+use crypto::{
+    encoding::{Base64UrlNoPad, Encoding},
+    Generational,
+};
+use rocket::{http::Status, State};
+use rocket_contrib::json::Json;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tap::*;
+
+use crate::cfg::SigningKeyFixture;
+
+/// A public key as a JSON Web Key.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Jwk {
+    /// The key type, always `OKP`.
+    pub kty: &'static str,
+    /// The curve, always `Ed25519`.
+    pub crv: &'static str,
+    /// The algorithm, always `EdDSA`.
+    pub alg: &'static str,
+    /// What the key is for, always `sig`.
+    #[serde(rename = "use")]
+    pub use_: &'static str,
+    /// The id of the generation of the key, as found in the footer of the tokens it signs.
+    pub kid: String,
+    /// The base64url encoded public key.
+    pub x: String,
+}
+
+/// A set of public keys as a JSON Web Key Set.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Jwks {
+    /// The current key, followed by the previous keys that tokens may still be signed with.
+    pub keys: Vec<Jwk>,
+}
+
+/// Serves the public halves of the current and previous signing key pairs, so that other services
+/// can verify the tokens signed by the site without sharing any secrets.
+#[get("/.well-known/keys")]
+pub fn keys(key_store: State<SigningKeyFixture>) -> Result<Json<Jwks>, Status> {
+    let key_store = key_store
+        .get_store()
+        .tap_err(|_| log::error!("Signing key service crashed."))
+        .map_err(|_| Status::InternalServerError)?;
+    let keys = key_store
+        .public_keys()
+        .into_iter()
+        .map(|generation| Jwk {
+            kty: "OKP",
+            crv: "Ed25519",
+            alg: "EdDSA",
+            use_: "sig",
+            kid: generation.id.to_string(),
+            x: Base64UrlNoPad::encode(generation.key.as_ref()),
+        })
+        .collect();
+    Ok(Json(Jwks { keys }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cfg::SigningAlgo;
+    use crypto::{
+        algo::sign::asymmetric::ed25519::PublicKey, token::sealed, RotationConfig, RotationMode,
+    };
+    use rocket::local::Client;
+    use serde_json::Value;
+    use std::time::SystemTime;
+
+    #[test]
+    fn previous_keys_still_verify_after_rotating() {
+        let config = RotationConfig {
+            mode: RotationMode::Lazy,
+            ..RotationConfig::default()
+        };
+        let rotator = crypto::KeyRotator::init(SigningAlgo {}, config);
+        let key_store = rotator.get_key_fixture();
+        let now = SystemTime::now();
+        let signer = key_store.get_store().unwrap();
+        let generation = signer.curr_generation();
+        let signed = sealed::Token::new("hello", generation.id, now, config.lifetime())
+            .seal(&*generation.key)
+            .unwrap();
+        key_store.rotate_now().unwrap();
+        let rocket = rocket::custom(rocket::Config::development())
+            .manage(rotator.get_rocket_managed_state())
+            .mount("/api", routes![keys]);
+        let client = Client::new(rocket).unwrap();
+
+        let mut res = client.get("/api/.well-known/keys").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let jwks: Value = serde_json::from_str(&res.body_string().unwrap()).unwrap();
+        let kids: Vec<_> = jwks["keys"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| key["kid"].as_str().unwrap())
+            .collect();
+        assert_eq!(kids, vec!["1", "0"]);
+        let previous = &jwks["keys"][1];
+        assert_eq!(previous["kty"], "OKP");
+        assert!(previous.get("d").is_none());
+        let x = previous["x"].as_str().unwrap();
+        let bytes = Base64UrlNoPad::decode(x).unwrap();
+        let public_key = PublicKey::from_slice(&bytes).unwrap();
+        let verified = sealed::Token::<String>::unseal(&signed, &public_key, now).unwrap();
+        assert_eq!(verified.claims.data, "hello");
+        rotator.cleanup().unwrap();
+    }
+}
@@ -1,7 +1,9 @@
 //! Groups all the static pages together.
 
 use maud::Markup;
-use rocket::Route;
+use rocket::{Route, State};
+
+use crate::util::{assets::Assets, security_headers::CspNonce, site_identity::SiteIdentity};
 
 mod contacts;
 mod links;
@@ -12,8 +14,12 @@ mod resume;
 ///
 /// This simply calls [`page_client::home::index()`] from [`page_client`].
 #[get("/")]
-fn get_index() -> Markup {
-    htmlgen::index()
+fn get_index(
+    assets: State<Assets>,
+    nonce: CspNonce,
+    site_identity: State<SiteIdentity>,
+) -> Markup {
+    htmlgen::index(&assets.manifest, &nonce, &site_identity.get())
 }
 
 /// Provides a [`Vec`] of [`Route`]s to be attached with [`rocket::Rocket::mount()`].
@@ -33,9 +39,12 @@ pub mod htmlgen {
     use maud::{html, Markup, Render};
     use page_client::{data, partials};
 
+    use crate::util::security_headers::CspNonce;
+    use blog_db::models::site::Identity;
+
     /// Create a basic menu.
     pub fn menu() -> Option<data::Menu<'static>> {
-        Some(data::Menu(&[data::MenuItem {
+        Some(data::Menu(&[data::MenuItem::Link {
             text: "Blog",
             link: Some("/blog"),
             children: None,
@@ -272,7 +281,7 @@ pub mod htmlgen {
     }
 
     /// Returns a list of [`Css`](crate::data::Css) scripts that go in my home page.
-    fn css_scripts<'a>() -> [data::Css<'a>; 4] {
+    pub(crate) fn css_scripts<'a>() -> [data::Css<'a>; 4] {
         [
             data::Css::Critical { src: "reset" },
             data::Css::Critical { src: "typography" },
@@ -281,21 +290,27 @@ pub mod htmlgen {
         ]
     }
 
-    /// Returns the [`Markup`] version of my home page.
-    pub fn index() -> Markup {
-        let (glue, load) = data::Script::wasm_bindgen_loader("wasm_slideshow");
+    /// Returns the [`Markup`] version of my home page. Resources are linked through their hashed
+    /// names in `assets`, and inline ones carry `nonce`. The site presents itself as `identity`.
+    pub fn index(assets: &data::AssetManifest, nonce: &CspNonce, identity: &Identity) -> Markup {
+        let (glue, load) = data::Script::wasm_bindgen_loader_with("wasm_slideshow", Some(assets));
         let js_scripts = [
             data::Script::External(glue.as_str()),
             data::Script::Embedded(load.as_str()),
         ];
         let css_scripts = css_scripts();
         let menu = menu();
-        let logo = crate::shared_html::logo_markup();
+        let logo = crate::shared_html::logo_markup(identity);
         let meta = data::MetaData::builder()
+            .site_title(&identity.title)
+            .description(&identity.tagline)
             .scripts(&js_scripts[..])
             .css(&css_scripts[..])
             .menu(menu.as_ref())
+            .current_path(Some("/"))
             .logo(logo.as_ref())
+            .assets(Some(assets))
+            .nonce(Some(nonce.as_str()))
             .build();
         partials::basic_page(
             html! {
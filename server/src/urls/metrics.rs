@@ -0,0 +1,137 @@
+//! Serves the [`Metrics`] of the server for scraping.
+
+use crypto::Generational;
+use rocket::{http::ContentType, response::content::Content, Route, State};
+
+use crate::{
+    cfg::TokenKeyFixture,
+    util::{
+        blog::db::DBPool,
+        metrics::{Metrics, MetricsAccess, Sampled},
+        response_cache::ResponseCache,
+    },
+};
+
+/// The metrics, in the Prometheus text format. See [`MetricsAccess`] for who may scrape them.
+#[get("/metrics")]
+pub fn get(
+    metrics: State<Metrics>,
+    pool: Option<State<DBPool>>,
+    token_key: Option<State<TokenKeyFixture>>,
+    responses: Option<State<ResponseCache>>,
+    _access: MetricsAccess,
+) -> Content<String> {
+    let token_key = token_key
+        .and_then(|fixture| fixture.get_store().ok())
+        .map(|store| {
+            let generation = store.curr_generation();
+            let age = generation.generated_at.elapsed().unwrap_or_default();
+            (generation.id, age)
+        });
+    let sampled = Sampled {
+        pool: pool.map(|pool| pool.stats()),
+        token_key,
+        responses: responses.map(|responses| responses.stats()),
+    };
+    let text = ContentType::new("text", "plain").with_params(("version", "0.0.4"));
+    Content(text, metrics.render(sampled))
+}
+
+/// Provides a [`Vec`] of [`Route`]s to be attached with [`rocket::Rocket::mount()`].
+pub fn routes() -> Vec<Route> {
+    routes![get]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        cfg::{ServerConfig, TokenAlgo},
+        util::request_log::RequestLogger,
+    };
+    use rocket::{
+        http::{Header, Status},
+        local::Client,
+    };
+
+    #[get("/ping")]
+    fn ping() -> &'static str {
+        "pong"
+    }
+
+    fn client(metrics_token: Option<&str>, rotator: &crypto::KeyRotator<TokenAlgo>) -> Client {
+        let config = ServerConfig {
+            metrics_token: metrics_token.map(str::to_owned),
+            ..ServerConfig::from_env_for_tests()
+        };
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(RequestLogger { json: false })
+            .manage(Metrics::default())
+            .manage(config)
+            .manage(rotator.get_rocket_managed_state())
+            .mount("/", routes())
+            .mount("/api", routes![ping]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    fn scraping_shows_requests_served() {
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, crypto::RotationConfig::default());
+        let client = client(None, &rotator);
+        for _ in 0..3 {
+            client.get("/api/ping").dispatch();
+        }
+        client.get("/api/nowhere").dispatch();
+        let mut res = client.get("/metrics").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let body = res.body_string().unwrap();
+        assert!(body.contains(
+            "benxu_request_duration_seconds_count{method=\"GET\",route=\"/api/ping\"} 3\n"
+        ));
+        assert!(body.contains(
+            "benxu_request_duration_seconds_count{method=\"GET\",route=\"unmatched\"} 1\n"
+        ));
+        assert!(body.contains("benxu_token_key_generation 0\n"));
+        assert!(body.contains("benxu_logins_total{outcome=\"success\"} 0\n"));
+
+        // The scrape itself is counted once it is served.
+        let body = client.get("/metrics").dispatch().body_string().unwrap();
+        assert!(body.contains(
+            "benxu_request_duration_seconds_count{method=\"GET\",route=\"/metrics\"} 1\n"
+        ));
+        rotator.cleanup().unwrap();
+    }
+
+    #[test]
+    fn only_the_machine_itself_scrapes_without_a_token() {
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, crypto::RotationConfig::default());
+        let client = client(None, &rotator);
+        let res = client
+            .get("/metrics")
+            .remote("203.0.113.7:4000".parse().unwrap())
+            .header(Header::new("X-Real-IP", "127.0.0.1"))
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        rotator.cleanup().unwrap();
+    }
+
+    #[test]
+    fn configured_token_is_required() {
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, crypto::RotationConfig::default());
+        let client = client(Some("scrape-me"), &rotator);
+        let res = client.get("/metrics").dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+        let res = client
+            .get("/metrics")
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+        let res = client
+            .get("/metrics")
+            .remote("203.0.113.7:4000".parse().unwrap())
+            .header(Header::new("Authorization", "Bearer scrape-me"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        rotator.cleanup().unwrap();
+    }
+}
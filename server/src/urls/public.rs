@@ -0,0 +1,162 @@
+//! Serves the static resources in the public directory. Resources requested through their content
+//! hashed names may be cached forever, while everything else is only cached briefly.
+
+use rocket::{response::NamedFile, Route, State};
+use std::path::PathBuf;
+
+use crate::util::assets::{Asset, Assets};
+
+/// Handler for static resources.
+#[get("/<path..>")]
+pub fn get(path: PathBuf, assets: State<Assets>) -> Option<Asset> {
+    let (file, hashed) = assets.locate(&path);
+    NamedFile::open(file)
+        .ok()
+        .map(|file| Asset { file, hashed })
+}
+
+/// Provides a [`Vec`] of [`Route`]s to be attached with [`rocket::Rocket::mount()`].
+pub fn routes() -> Vec<Route> {
+    routes![get]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::assets::{IMMUTABLE_CACHE_CONTROL, SHORT_CACHE_CONTROL};
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::Client,
+    };
+    use std::fs;
+
+    fn client() -> (Client, PathBuf) {
+        let root = std::env::temp_dir().join(format!("public_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("css")).unwrap();
+        fs::create_dir_all(root.join("img")).unwrap();
+        fs::write(root.join("css/blog.css"), "body {}").unwrap();
+        fs::write(root.join("css/blog.css.gz"), "compressed").unwrap();
+        fs::write(root.join("img/logo.svg"), "<svg></svg>").unwrap();
+        fs::write(root.join("img/clip.webm"), "0123456789").unwrap();
+        let assets = Assets::load(root.clone()).unwrap();
+        let rocket = rocket::custom(rocket::Config::development())
+            .manage(assets)
+            .mount("/public", routes());
+        (Client::new(rocket).unwrap(), root)
+    }
+
+    fn cache_control(client: &Client, url: &str) -> (Status, Option<String>) {
+        let res = client.get(url).dispatch();
+        let header = res.headers().get_one("Cache-Control").map(str::to_owned);
+        (res.status(), header)
+    }
+
+    #[test]
+    fn hashed_resources_are_immutable() {
+        let (client, root) = client();
+        let hashed = Assets::load(root)
+            .unwrap()
+            .manifest
+            .resolve("css/blog.css")
+            .to_owned();
+        assert_ne!(hashed, "css/blog.css");
+        let (status, header) = cache_control(&client, &format!("/public/{}", hashed));
+        assert_eq!(status, Status::Ok);
+        assert_eq!(header.as_deref(), Some(IMMUTABLE_CACHE_CONTROL));
+    }
+
+    #[test]
+    fn other_resources_are_cached_briefly() {
+        let (client, _) = client();
+        for url in &["/public/css/blog.css", "/public/img/logo.svg"] {
+            let (status, header) = cache_control(&client, url);
+            assert_eq!(status, Status::Ok);
+            assert_eq!(header.as_deref(), Some(SHORT_CACHE_CONTROL));
+        }
+        assert_eq!(
+            cache_control(&client, "/public/css/missing.css").0,
+            Status::NotFound
+        );
+    }
+
+    #[test]
+    fn precompressed_copies_are_served_when_accepted() {
+        let (client, root) = client();
+        let manifest = Assets::load(root).unwrap().manifest;
+        assert_eq!(manifest.resolve("css/blog.css.gz"), "css/blog.css.gz");
+        let url = format!("/public/{}", manifest.resolve("css/blog.css"));
+        let mut res = client
+            .get(url.as_str())
+            .header(Header::new("Accept-Encoding", "br, gzip"))
+            .dispatch();
+        assert_eq!(res.headers().get_one("Content-Encoding"), Some("gzip"));
+        assert_eq!(res.content_type(), Some(ContentType::CSS));
+        assert_eq!(res.headers().get_one("Vary"), Some("Accept-Encoding"));
+        assert_eq!(res.body_string().as_deref(), Some("compressed"));
+        let mut res = client.get(url.as_str()).dispatch();
+        assert_eq!(res.headers().get_one("Content-Encoding"), None);
+        assert_eq!(res.body_string().as_deref(), Some("body {}"));
+    }
+
+    fn ranged(client: &Client, range: &str) -> (Status, Option<String>, Option<String>) {
+        let mut res = client
+            .get("/public/img/clip.webm")
+            .header(Header::new("Range", range.to_owned()))
+            .dispatch();
+        let content_range = res.headers().get_one("Content-Range").map(str::to_owned);
+        (res.status(), content_range, res.body_string())
+    }
+
+    #[test]
+    fn single_ranges_are_served_in_part() {
+        let (client, _) = client();
+        let (status, content_range, body) = ranged(&client, "bytes=0-0");
+        assert_eq!(status, Status::PartialContent);
+        assert_eq!(content_range.as_deref(), Some("bytes 0-0/10"));
+        assert_eq!(body.as_deref(), Some("0"));
+        let (status, content_range, body) = ranged(&client, "bytes=-4");
+        assert_eq!(status, Status::PartialContent);
+        assert_eq!(content_range.as_deref(), Some("bytes 6-9/10"));
+        assert_eq!(body.as_deref(), Some("6789"));
+
+        let res = client.get("/public/img/clip.webm").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(res.headers().get_one("Accept-Ranges"), Some("bytes"));
+    }
+
+    #[test]
+    fn unservable_ranges_are_refused() {
+        let (client, _) = client();
+        for range in &["bytes=10-20", "bytes=0-1,4-5", "bytes=4-1", "lines=0-1"] {
+            let (status, content_range, _) = ranged(&client, range);
+            assert_eq!(status, Status::RangeNotSatisfiable, "{}", range);
+            assert_eq!(content_range.as_deref(), Some("bytes */10"), "{}", range);
+        }
+    }
+
+    #[test]
+    fn fresh_copies_are_not_sent_again() {
+        let (client, _) = client();
+        let res = client.get("/public/img/logo.svg").dispatch();
+        let etag = res.headers().get_one("ETag").unwrap().to_owned();
+        let modified = res.headers().get_one("Last-Modified").unwrap().to_owned();
+        assert!(etag.starts_with('"') && !etag.starts_with("W/"));
+        let conditional = |name: &'static str, value: String| {
+            let mut res = client
+                .get("/public/img/logo.svg")
+                .header(Header::new(name, value))
+                .dispatch();
+            (res.status(), res.body_string())
+        };
+
+        let (status, body) = conditional("If-None-Match", format!("\"stale\", {}", etag));
+        assert_eq!(status, Status::NotModified);
+        assert_eq!(body, None);
+        assert_eq!(conditional("If-Modified-Since", modified).0, Status::NotModified);
+        let (status, body) = conditional("If-None-Match", "\"stale\"".to_owned());
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body.as_deref(), Some("<svg></svg>"));
+        let long_ago = "Thu, 01 Jan 1970 00:00:00 GMT".to_owned();
+        assert_eq!(conditional("If-Modified-Since", long_ago).0, Status::Ok);
+    }
+}
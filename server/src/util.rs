@@ -1,4 +1,26 @@
+pub mod assets;
 pub mod auth;
 pub mod blog;
+pub mod body_limits;
+pub mod challenge;
+pub mod compression;
+pub mod cors;
+pub mod locale;
+pub mod mailer;
+pub mod metrics;
+pub mod ranges;
+pub mod refuse;
+pub mod render_cache;
+pub mod request_log;
+pub mod response_cache;
+pub mod retention;
+pub mod security_headers;
+pub mod shutdown;
+pub mod site_identity;
+pub mod site_mode;
+pub mod unavailable;
+pub mod validation;
+pub mod visitor;
+pub mod webhooks;
 
 pub mod uuid_compat;
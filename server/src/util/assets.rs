@@ -0,0 +1,387 @@
+//! Content hashing for the static resources in the public directory, so that they can be cached
+//! indefinitely by browsers while still picking up new builds immediately.
+//!
+//! Every resource is served with a strong `ETag` made from its content hash, and a `Last-Modified`
+//! date, so that browsers can check whether what they have is still fresh. Parts of resources are
+//! served as asked for with the `Range` header, as [`ranges`](crate::util::ranges) describes.
+
+use crypto::algo::{
+    hash::{
+        blake::two_b,
+        symmetric::{Signer as _, StreamingAlgo as _},
+    },
+    Algo as _,
+};
+use page_client::data::{AssetManifest, Css};
+use chrono::{DateTime, Utc};
+use rocket::{
+    http::{ContentType, Header, Status},
+    response::{NamedFile, Responder, Response},
+    Request, State,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::util::{
+    compression::{vary_on_encoding, Encoding},
+    ranges::{self, Requested, Window},
+};
+
+/// Directories of the public directory whose files are content hashed. Images are linked to
+/// directly by the static pages, so they are left out.
+pub const HASHED_DIRS: [&str; 3] = ["css", "js", "wasm"];
+/// Number of bytes of the content hash to put into the file name.
+const HASH_LEN: usize = 8;
+/// Number of bytes read from a resource at a time while hashing it.
+const CHUNK_LEN: usize = 64 * 1024;
+/// The `Cache-Control` value for content hashed resources.
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// The `Cache-Control` value for everything else.
+pub const SHORT_CACHE_CONTROL: &str = "public, max-age=300";
+
+/// The public directory and the content hashed names of its resources. Available as managed
+/// state.
+pub struct Assets {
+    /// The canonical path of the public directory.
+    pub root: PathBuf,
+    /// The manifest of content hashed resources.
+    pub manifest: AssetManifest,
+    /// The content hash of every file in the public directory, pre-compressed copies included, in
+    /// hex.
+    hashes: HashMap<PathBuf, String>,
+}
+impl Assets {
+    /// Hashes the contents of every file of `root`, naming those in its [`HASHED_DIRS`] by their
+    /// hash.
+    pub fn load(root: PathBuf) -> io::Result<Self> {
+        let mut assets = Self {
+            root,
+            manifest: AssetManifest::new(),
+            hashes: HashMap::new(),
+        };
+        for dir in HASHED_DIRS.iter() {
+            let dir = assets.root.join(dir);
+            if !dir.is_dir() {
+                log::warn!(
+                    "Static resource directory `{}` does not exist.",
+                    dir.display()
+                );
+            }
+        }
+        let root = assets.root.clone();
+        if root.is_dir() {
+            assets.add_dir(&root)?;
+        }
+        Ok(assets)
+    }
+    /// Adds every file in `dir` and its subdirectories.
+    fn add_dir(&mut self, dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.add_dir(&path)?;
+                continue;
+            }
+            let hash = content_hash(fs::File::open(&path)?)?;
+            self.hashes.insert(path.clone(), hex(&hash));
+            if is_precompressed(&path) {
+                continue;
+            }
+            let original = match path.strip_prefix(&self.root).ok().and_then(Path::to_str) {
+                Some(original) => original.to_owned(),
+                None => {
+                    log::warn!(
+                        "Skipping static resource with a non-UTF-8 path `{:?}`.",
+                        path
+                    );
+                    continue;
+                }
+            };
+            let in_hashed_dir = HASHED_DIRS
+                .iter()
+                .any(|dir| original.starts_with(dir) && original[dir.len()..].starts_with('/'));
+            if in_hashed_dir {
+                let hashed = name_with_hash(&original, &hash);
+                log::debug!("Serving `{}` as `{}`.", original, hashed);
+                self.manifest.insert(original, hashed);
+            }
+        }
+        Ok(())
+    }
+    /// Reads the critical stylesheets among `css` into the manifest, so that pages inline them
+    /// without reading them again. Fails if any of them is missing, since pages cannot be rendered
+    /// without them.
+    pub fn inline_critical(&mut self, css: &[Css]) -> io::Result<()> {
+        for css in css {
+            let path = match (css, css.path()) {
+                (Css::Critical { .. }, Some(path)) => path,
+                _ => continue,
+            };
+            let contents = fs::read_to_string(self.root.join(&path)).map_err(|e| {
+                io::Error::new(e.kind(), format!("Could not read critical css `{}`: {}", path, e))
+            })?;
+            self.manifest.insert_inline(path, contents);
+        }
+        Ok(())
+    }
+    /// The strong `ETag` of the file at `path`, made from its content hash, if it was there when
+    /// the public directory was hashed.
+    pub fn etag(&self, path: &Path) -> Option<String> {
+        self.hashes.get(path).map(|hash| format!("\"{}\"", hash))
+    }
+    /// Finds the file for a path relative to the public directory, along with whether the path was
+    /// a content hashed one.
+    pub fn locate(&self, path: &Path) -> (PathBuf, bool) {
+        let original = path.to_str().and_then(|p| self.manifest.original(p));
+        match original {
+            Some(original) => (self.root.join(original), true),
+            None => (self.root.join(path), false),
+        }
+    }
+}
+
+/// Checks if the file is a pre-compressed copy of another file, which is served in place of the
+/// original rather than on its own.
+fn is_precompressed(path: &Path) -> bool {
+    let ext = path.extension().and_then(|ext| ext.to_str());
+    Encoding::PREFERENCE
+        .iter()
+        .any(|encoding| ext == Some(encoding.extension()))
+}
+
+/// Finds a pre-compressed copy of the file in an encoding the client accepts.
+fn find_precompressed(path: &Path, req: &Request) -> Option<(Encoding, NamedFile)> {
+    Encoding::accepted(req).into_iter().find_map(|encoding| {
+        let mut compressed = path.as_os_str().to_owned();
+        compressed.push(".");
+        compressed.push(encoding.extension());
+        NamedFile::open(compressed)
+            .ok()
+            .map(|file| (encoding, file))
+    })
+}
+
+/// Hashes everything read from `reader` a chunk at a time, so that large resources are never held
+/// in memory whole.
+pub fn content_hash(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut signer = two_b::Algo::new(HASH_LEN).signer(&two_b::Key::new(vec![], HASH_LEN));
+    let mut chunk = vec![0; CHUNK_LEN];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => return Ok(signer.finalize()),
+            Ok(read) => signer.update(&chunk[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Inserts the hash of the contents before the extension of the path, such that `css/blog.css`
+/// becomes `css/blog.0123456789abcdef.css`.
+pub fn hashed_name(path: &str, contents: &[u8]) -> String {
+    let hash = content_hash(contents).expect("Reading from a slice never fails.");
+    name_with_hash(path, &hash)
+}
+
+fn hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Inserts an already computed hash before the extension of the path.
+fn name_with_hash(path: &str, hash: &[u8]) -> String {
+    let hash = hex(hash);
+    let file_start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[file_start..].find('.') {
+        Some(i) => format!(
+            "{}.{}{}",
+            &path[..file_start + i],
+            hash,
+            &path[file_start + i..]
+        ),
+        None => format!("{}.{}", path, hash),
+    }
+}
+
+/// A static resource along with how long it may be cached. Served from a pre-compressed copy next
+/// to it, named with an added `.br` or `.gz` extension, if the client accepts it and asked for all
+/// of it.
+///
+/// Answered with a 304 if the client has it already, as told by `If-None-Match`, or else by
+/// `If-Modified-Since`.
+pub struct Asset {
+    /// The file to serve.
+    pub file: NamedFile,
+    /// Whether the file was requested through its content hashed name.
+    pub hashed: bool,
+}
+impl<'r> Responder<'r> for Asset {
+    fn respond_to(self, req: &Request) -> rocket::response::Result<'r> {
+        let cache_control = if self.hashed {
+            IMMUTABLE_CACHE_CONTROL
+        } else {
+            SHORT_CACHE_CONTROL
+        };
+        let content_type = self
+            .file
+            .path()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(ContentType::from_extension);
+        let range = req.headers().get_one("Range");
+        // Parts are served from the file itself, since browsers only ask for parts of media,
+        // which is compressed already.
+        let precompressed = match range {
+            Some(_) => None,
+            None => find_precompressed(self.file.path(), req),
+        };
+        let (file, encoding) = match precompressed {
+            Some((encoding, compressed)) => (compressed, Some(encoding)),
+            None => (self.file, None),
+        };
+        let etag = req
+            .guard::<State<Assets>>()
+            .succeeded()
+            .and_then(|assets| assets.etag(file.path()));
+        let metadata = file.metadata().map_err(|e| {
+            log::error!("Failed to read the metadata of {:?} due to {:?}.", file.path(), e);
+            Status::InternalServerError
+        })?;
+        let modified = metadata.modified().ok();
+
+        let mut res = Response::build();
+        res.raw_header("Cache-Control", cache_control)
+            .raw_header("Accept-Ranges", "bytes");
+        if let Some(etag) = etag.as_ref() {
+            res.raw_header("ETag", etag.clone());
+        }
+        if let Some(modified) = modified {
+            res.raw_header("Last-Modified", http_date(modified));
+        }
+        if let Some(encoding) = encoding {
+            res.raw_header("Content-Encoding", encoding.name());
+        }
+        if let Some(content_type) = content_type {
+            res.header(content_type);
+        }
+        if is_fresh(req, etag.as_deref(), modified) {
+            res.status(Status::NotModified);
+            let mut res = res.finalize();
+            vary_on_encoding(&mut res);
+            return Ok(res);
+        }
+
+        // A range made for another version of the resource is ignored.
+        let range = match req.headers().get_one("If-Range") {
+            Some(if_range) if Some(if_range) != etag.as_deref() => None,
+            _ => range,
+        };
+        let len = metadata.len();
+        match Requested::parse(range, len) {
+            Requested::Whole => {
+                res.sized_body(file.take_file());
+            }
+            Requested::Part { start, end } => {
+                let window = Window::new(file.take_file(), start, end).map_err(|e| {
+                    log::error!("Failed to seek to byte {} due to {:?}.", start, e);
+                    Status::InternalServerError
+                })?;
+                res.status(Status::PartialContent)
+                    .raw_header("Content-Range", ranges::content_range(Some((start, end)), len))
+                    .sized_body(window);
+            }
+            Requested::Unsatisfiable => {
+                res.status(Status::RangeNotSatisfiable)
+                    .raw_header("Content-Range", ranges::content_range(None, len));
+            }
+        }
+        let mut res = res.finalize();
+        vary_on_encoding(&mut res);
+        Ok(res)
+    }
+}
+
+/// Whether the client has the resource with `etag`, last modified at `modified`, already. Going by
+/// `If-None-Match` if it was sent, since the time a resource was modified is only known to the
+/// second.
+fn is_fresh(req: &Request, etag: Option<&str>, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = req.headers().get_one("If-None-Match") {
+        return etag.map_or(false, |etag| {
+            if_none_match.split(',').map(str::trim).any(|sent| {
+                // Compared weakly, as `If-None-Match` is.
+                sent == "*" || sent.trim_start_matches("W/") == etag
+            })
+        });
+    }
+    let since = req
+        .headers()
+        .get_one("If-Modified-Since")
+        .and_then(|since| DateTime::parse_from_rfc2822(since).ok());
+    match (since, modified) {
+        (Some(since), Some(modified)) => {
+            DateTime::<Utc>::from(modified).timestamp() <= since.timestamp()
+        }
+        _ => false,
+    }
+}
+
+/// `time` as an http date, such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn http_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hash_goes_before_the_extensions() {
+        let hashed = hashed_name("wasm/blog_client_bg.wasm", b"wasm");
+        assert!(hashed.starts_with("wasm/blog_client_bg."));
+        assert!(hashed.ends_with(".wasm"));
+        assert_eq!(
+            hashed.len(),
+            "wasm/blog_client_bg.wasm".len() + 2 * HASH_LEN + 1
+        );
+        let hashed = hashed_name("js/glue/blog.min.js", b"js");
+        assert!(hashed.starts_with("js/glue/blog."));
+        assert!(hashed.ends_with(".min.js"));
+    }
+
+    #[test]
+    fn streamed_hash_matches_hashing_at_once() {
+        use crypto::algo::hash::symmetric::Algo as _;
+        let contents: Vec<u8> = (0..3 * CHUNK_LEN + 17).map(|i| i as u8).collect();
+        let at_once =
+            two_b::Algo::new(HASH_LEN).sign(&contents, &two_b::Key::new(vec![], HASH_LEN));
+        assert_eq!(content_hash(contents.as_slice()).unwrap(), at_once);
+    }
+
+    #[test]
+    fn missing_critical_css_is_an_error() {
+        let mut assets = Assets::load("public".into()).unwrap();
+        assets.inline_critical(&crate::urls::page_css()).unwrap();
+        assert!(assets.manifest.inline("css/reset.css").is_some());
+        assert!(assets.manifest.inline("css/blog.css").is_none());
+        let missing = assets.inline_critical(&[Css::Critical { src: "missing" }]);
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn hash_changes_with_contents() {
+        assert_eq!(
+            hashed_name("css/a.css", b"a"),
+            hashed_name("css/a.css", b"a")
+        );
+        assert_ne!(
+            hashed_name("css/a.css", b"a"),
+            hashed_name("css/a.css", b"b")
+        );
+    }
+}
@@ -6,7 +6,9 @@ pub use caps::Capability;
 mod error;
 pub use error::Error;
 pub mod credentials;
+pub mod escalation;
 
+use chrono::{DateTime, Utc};
 use rocket::{
     http::{Cookie, Cookies, Status},
     outcome::IntoOutcome,
@@ -14,14 +16,22 @@ use rocket::{
     State,
 };
 use serde::{Deserialize, Deserializer, Serialize};
-use std::{marker::PhantomData, ops::Deref, str};
+use std::{marker::PhantomData, ops::Deref, time::SystemTime};
 use tap::*;
 
-use crate::cfg::{TokenKeyFixture, TokenKeyStore};
+use crate::{
+    cfg::{CookieConfig, TokenKeyFixture, TokenKeyStore},
+    util::{
+        blog::{db::CapabilityQuery, DB},
+        request_log,
+    },
+};
 use crypto::{
-    algo::Algo as A,
     key_rotation::Generational,
-    token::paseto::{self, Protocol},
+    token::{
+        paseto::{self, Protocol},
+        sealed,
+    },
 };
 
 /// The name of the cookie holding the capabilities to be deserialized.
@@ -29,28 +39,47 @@ pub const AUTH_COOKIE_NAME: &str = "_atk";
 
 /// A struct representing the list of capabilities a user has.
 ///
-/// TODO query the database for the list of capabilities instead of serializing it.
+/// Expired capabilities are kept until they are next loaded, but are treated as absent.
+///
+/// When obtained as a request guard, the capabilities are loaded from the database once per
+/// request, however many checks are made with them. Those serialized into the token are ignored.
+///
+/// TODO stop serializing the capabilities into the token.
 #[derive(Debug, Serialize)]
 pub struct Capabilities<L> {
     #[serde(skip)]
     level: PhantomData<L>,
-    capabilities: Vec<Capability>,
+    capabilities: Vec<caps::Held>,
     user_id: uuid::Uuid,
 }
 impl<L> Capabilities<L> {
-    /// Check if a list of capabilities is satisfied.
+    /// Check if a list of capabilities is satisfied by the unscoped capabilities of the user.
     pub fn has_capabilities(&self, req_perms: &[Capability]) -> bool {
-        req_perms
-            .iter()
-            .all(|req_perm| self.capabilities.contains(req_perm))
+        let held = self.capabilities();
+        req_perms.iter().all(|req_perm| held.contains(req_perm))
     }
     /// Gets the a copy of the id of the user this credential belongs to.
     pub fn user_id(&self) -> uuid::Uuid {
         self.user_id
     }
-    /// Gets the a copy of the capabilities of the user.
-    pub fn capabilities(&self) -> &[Capability] {
-        self.capabilities.as_slice()
+    /// Gets the unexpired, unscoped capabilities of the user.
+    pub fn capabilities(&self) -> Vec<Capability> {
+        self.capabilities_at(None, Utc::now())
+    }
+    /// Gets the unexpired capabilities of the user that apply to the target.
+    pub fn capabilities_for(&self, target: &caps::Target) -> Vec<Capability> {
+        self.capabilities_at(Some(target), Utc::now())
+    }
+    fn capabilities_at(
+        &self,
+        target: Option<&caps::Target>,
+        now: DateTime<Utc>,
+    ) -> Vec<Capability> {
+        self.capabilities
+            .iter()
+            .filter(|held| held.applies_at(target, now))
+            .map(|held| held.capability)
+            .collect()
     }
     /// Attempts to change the credential's level, returning the old credential on error
     /// (insufficient capabilities) and the new credential on success.
@@ -68,33 +97,57 @@ impl<L> Capabilities<L> {
             capabilities,
         })
     }
+    /// Like [`change_level`](Self::change_level), but also accepts capabilities scoped to the
+    /// target.
+    pub fn change_level_for<NewLevel: caps::Verifiable>(
+        self,
+        target: &caps::Target,
+    ) -> Result<Capabilities<NewLevel>, Capabilities<L>> {
+        if NewLevel::verify_slice(self.capabilities_for(target).as_slice()) {
+            Ok(Capabilities {
+                level: PhantomData,
+                user_id: self.user_id,
+                capabilities: self.capabilities,
+            })
+        } else {
+            Err(self)
+        }
+    }
     /// Revert the credential back to an unverified state.
     pub fn back_to_any(self) -> Capabilities<caps::Any> {
         Capabilities::safe_new(self.user_id, self.capabilities)
     }
+    /// Loads the capabilities of the user afresh, for handlers that change them and need to check
+    /// them again within the same request. The credential is reverted to an unverified state,
+    /// since the user may no longer hold what it was verified for.
+    pub fn refresh(self, db: &impl CapabilityQuery) -> Result<Capabilities<caps::Any>, Error> {
+        let held = db.get_effective_capabilities_by_user_id(self.user_id)?;
+        Ok(Capabilities::safe_new(self.user_id, caps::known(&held)))
+    }
 }
 impl<L: caps::Verifiable> Capabilities<L> {
     /// Creates a new Capabilities object from a set of capabilities and validates the capabilities
     /// at the level requested by `L`.
     pub fn new(
         user_id: uuid::Uuid,
-        capabilities: Vec<Capability>,
-    ) -> Result<Self, (uuid::Uuid, Vec<Capability>)> {
-        if L::verify_slice(capabilities.as_slice()) {
-            Ok(Self {
-                level: PhantomData,
-                user_id,
-                capabilities,
-            })
+        capabilities: Vec<caps::Held>,
+    ) -> Result<Self, (uuid::Uuid, Vec<caps::Held>)> {
+        let candidate = Self {
+            level: PhantomData,
+            user_id,
+            capabilities,
+        };
+        if L::verify(&candidate) {
+            Ok(candidate)
         } else {
-            Err((user_id, capabilities))
+            Err((candidate.user_id, candidate.capabilities))
         }
     }
 }
 impl Capabilities<caps::Any> {
     /// Creating an unverified credential has no chance of failure; this allows for the provided
     /// specialization of no error.
-    pub fn safe_new(user_id: uuid::Uuid, capabilities: Vec<Capability>) -> Self {
+    pub fn safe_new(user_id: uuid::Uuid, capabilities: Vec<caps::Held>) -> Self {
         Self {
             level: PhantomData,
             user_id,
@@ -107,14 +160,41 @@ impl Capabilities<caps::Any> {
         key_store: &TokenKeyStore,
     ) -> Result<Capabilities<caps::Any>, Error> {
         let auth_cookie = cookies.get(AUTH_COOKIE_NAME).ok_or(Error::Unauthorized)?;
-
+        Self::unseal(auth_cookie.value(), key_store, SystemTime::now())
+    }
+    /// Unseals a credential from a token minted by [`seal_capabilities`], checking that it has not
+    /// expired by `now`.
+    fn unseal(
+        token: &str,
+        key_store: &TokenKeyStore,
+        now: SystemTime,
+    ) -> Result<Capabilities<caps::Any>, Error> {
+        if !sealed::is_sealed(token) {
+            return Self::unseal_legacy(token, key_store);
+        }
+        let generation = sealed::key_id(token)
+            .ok()
+            .and_then(|id| key_store.generation(id))
+            .ok_or(Error::Unauthorized)?;
+        let token: sealed::Token<Capabilities<caps::Any>> =
+            sealed::Token::unseal(token, &*generation.key, now)?;
+        Ok(token.claims.data)
+    }
+    /// Unseals a credential from a PASETO v2 local token, as minted before the switch to sealed
+    /// tokens, so that sessions survive the upgrade.
+    ///
+    /// TODO remove in the next release. Legacy tokens stop verifying once the keys they were minted
+    /// with are rotated out anyway.
+    fn unseal_legacy(
+        token: &str,
+        key_store: &TokenKeyStore,
+    ) -> Result<Capabilities<caps::Any>, Error> {
         type TokenData = paseto::token::Data<Capabilities<caps::Any>, ()>;
         // TODO no-copy once paseto is no copy on the input
         let token: TokenData = key_store.attempt_with_retry(&mut |key, _opt_err| {
-            let token = paseto::token::Packed::new(auth_cookie.value().as_bytes().to_vec());
+            let token = paseto::token::Packed::new(token.as_bytes().to_vec());
             paseto::V2Local::decrypt(token, key)
         })?;
-
         Ok(token.msg)
     }
 }
@@ -198,7 +278,7 @@ impl<'de> Deserialize<'de> for Capabilities<caps::Any> {
             where
                 A: serde::de::SeqAccess<'de>,
             {
-                let capabilities = serde::de::SeqAccess::next_element::<Vec<Capability>>(&mut seq)?
+                let capabilities = serde::de::SeqAccess::next_element::<Vec<caps::Held>>(&mut seq)?
                     .ok_or_else(|| {
                         serde::de::Error::invalid_length(
                             0usize,
@@ -233,7 +313,7 @@ impl<'de> Deserialize<'de> for Capabilities<caps::Any> {
                                     "capabilities",
                                 ));
                             } else {
-                                Some(serde::de::MapAccess::next_value::<Vec<Capability>>(
+                                Some(serde::de::MapAccess::next_value::<Vec<caps::Held>>(
                                     &mut map,
                                 )?)
                             }
@@ -311,7 +391,7 @@ impl UnverifiedCapabilities {
     /// Create a new struct, simply wrapping up the
     /// [`Capabilities::new()`](crate::blog::auth::Capabilities::new) function with the newtype
     /// struct.
-    pub fn new(user_id: uuid::Uuid, caps: Vec<Capability>) -> Self {
+    pub fn new(user_id: uuid::Uuid, caps: Vec<caps::Held>) -> Self {
         // Should not be able to error
         Self(Capabilities::safe_new(user_id, caps))
     }
@@ -326,6 +406,33 @@ impl Deref for UnverifiedCapabilities {
         &self.0
     }
 }
+/// The capabilities of the user a request is from, loaded by the first [`UnverifiedCapabilities`]
+/// guard of the request and shared by the rest. Holds the status to fail with if they could not
+/// be loaded.
+struct LoadedCapabilities(Result<Vec<caps::Held>, Status>);
+impl LoadedCapabilities {
+    /// Gets the capabilities of the request, calling `load` only if no guard has yet.
+    fn of<'a>(
+        req: &'a Request,
+        load: impl FnOnce() -> Result<Vec<caps::Held>, Status>,
+    ) -> &'a Result<Vec<caps::Held>, Status> {
+        &req.local_cache(|| Self(load())).0
+    }
+    /// Loads the capabilities of the credential from the database. The connection is returned to
+    /// the pool as soon as they are loaded.
+    fn load(req: &Request, cr: Capabilities<caps::Any>) -> Result<Vec<caps::Held>, Status> {
+        let db = match req.guard::<DB>() {
+            Outcome::Success(db) => db,
+            Outcome::Failure((status, _)) => return Err(status),
+            Outcome::Forward(_) => return Err(Status::ServiceUnavailable),
+        };
+        cr.refresh(&db)
+            .map(|cr| cr.capabilities)
+            .tap_err(|e| log::error!("Failed to load capabilities due to {:?}.", e))
+            .map_err(Status::from)
+    }
+}
+
 impl<'a, 'r> FromRequest<'a, 'r> for UnverifiedCapabilities {
     type Error = Error;
     fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
@@ -337,9 +444,14 @@ impl<'a, 'r> FromRequest<'a, 'r> for UnverifiedCapabilities {
             .map_err(|_| Error::KeyStoreAbsent)
             .into_outcome(Status::InternalServerError)?;
 
-        Capabilities::extract(&cookies, &*key_store)
-            .into_outcome(Status::Unauthorized)
-            .map(|cr: Capabilities<caps::Any>| cr.into())
+        let cr = Capabilities::extract(&cookies, &*key_store)
+            .tap_ok(|cr| request_log::record_user(req, cr.user_id()))
+            .into_outcome(Status::Unauthorized)?;
+        let user_id = cr.user_id();
+        match LoadedCapabilities::of(req, || LoadedCapabilities::load(req, cr)) {
+            Ok(held) => Outcome::Success(Capabilities::safe_new(user_id, held.clone()).into()),
+            Err(status) => Outcome::Failure((*status, Error::CapabilitiesUnavailable)),
+        }
     }
 }
 impl From<Capabilities<caps::Any>> for UnverifiedCapabilities {
@@ -349,27 +461,37 @@ impl From<Capabilities<caps::Any>> for UnverifiedCapabilities {
     }
 }
 
+/// Seals a [`Capabilities`](crate::blog::auth::Capabilities) into a token with the current key of
+/// `key_store`. The token expires once the key is rotated out.
+pub fn seal_capabilities(
+    key_store: &TokenKeyStore,
+    capabilities: Capabilities<caps::Any>,
+) -> Result<String, sealed::Error> {
+    let generation = key_store.curr_generation();
+    let lifetime = key_store.config().lifetime();
+    sealed::Token::new(capabilities, generation.id, SystemTime::now(), lifetime)
+        .seal(&*generation.key)
+}
+
 /// Attaches a [`Capabilities`](crate::blog::auth::Capabilities) to the cookies so that they
-/// can be verified later.
+/// can be verified later. The cookie is built according to `cookie_config`.
 #[must_use]
 pub fn attach_capabilities_token(
-    key: &<<paseto::V2Local as paseto::Protocol>::CoreAlgo as A>::Key,
+    key_store: &TokenKeyStore,
     capabilities: Capabilities<caps::Any>,
+    cookie_config: &CookieConfig,
     cookies: &mut Cookies,
 ) -> Result<(), ()> {
     detach_capabilities_token_if_exists(cookies);
-    let opt_none: Option<()> = None;
-    let tok = paseto::token::Data {
-        msg: capabilities,
-        footer: opt_none,
-    };
-    let token_str = paseto::V2Local::encrypt(tok, key)
-        .map_err(|_| ())
-        .and_then(|s| Ok(str::from_utf8(&s).map_err(|_| ())?.to_owned()))?;
-    let auth_cookie = Cookie::build(AUTH_COOKIE_NAME, token_str)
-        .secure(true)
+    let token_str = seal_capabilities(key_store, capabilities).map_err(|_| ())?;
+    let mut auth_cookie = Cookie::build(AUTH_COOKIE_NAME, token_str)
+        .secure(cookie_config.secure)
         .http_only(true)
+        .same_site(cookie_config.same_site)
         .finish();
+    if let Some(domain) = cookie_config.domain.as_ref() {
+        auth_cookie.set_domain(domain.clone());
+    }
     cookies.add(auth_cookie);
     Ok(())
 }
@@ -380,3 +502,135 @@ pub fn detach_capabilities_token_if_exists(cookies: &mut Cookies) {
         cookies.remove(Cookie::named(AUTH_COOKIE_NAME));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Duration;
+
+    fn held(expires_at: Option<DateTime<Utc>>, scope: Option<caps::Scope>) -> caps::Held {
+        caps::Held {
+            capability: Capability::EditPost,
+            expires_at,
+            scope,
+        }
+    }
+
+    fn verify_for(caps: Vec<caps::Held>, post_id: uuid::Uuid, tag_ids: &[uuid::Uuid]) -> bool {
+        let target = caps::Target::Post {
+            id: post_id,
+            tag_ids,
+        };
+        Capabilities::safe_new(uuid::Uuid::nil(), caps)
+            .change_level_for::<caps::Edit>(&target)
+            .is_ok()
+    }
+
+    #[test]
+    fn expired_grant_is_absent() {
+        let post = uuid::Uuid::new_v4();
+        let yesterday = Utc::now() - Duration::days(1);
+        let tomorrow = Utc::now() + Duration::days(1);
+        assert!(!verify_for(vec![held(Some(yesterday), None)], post, &[]));
+        assert!(Capabilities::<caps::Edit>::new(post, vec![held(Some(yesterday), None)]).is_err());
+        assert!(verify_for(vec![held(Some(tomorrow), None)], post, &[]));
+    }
+
+    #[test]
+    fn scoped_grant_only_covers_its_target() {
+        let post = uuid::Uuid::new_v4();
+        let other_post = uuid::Uuid::new_v4();
+        let tag = uuid::Uuid::new_v4();
+        let by_post = vec![held(None, Some(caps::Scope::Post(post)))];
+        assert!(verify_for(by_post.clone(), post, &[]));
+        assert!(!verify_for(by_post, other_post, &[]));
+        let by_tag = vec![held(None, Some(caps::Scope::Tag(tag)))];
+        assert!(verify_for(by_tag.clone(), other_post, &[tag]));
+        assert!(!verify_for(by_tag, other_post, &[]));
+    }
+
+    #[test]
+    fn scoped_grants_do_not_satisfy_unscoped_checks() {
+        let post = uuid::Uuid::new_v4();
+        let scoped = vec![held(None, Some(caps::Scope::Post(post)))];
+        assert!(Capabilities::<caps::Edit>::new(post, scoped.clone()).is_err());
+        assert!(!Capabilities::safe_new(post, scoped).has_capabilities(&[Capability::EditPost]));
+    }
+
+    #[test]
+    fn unscoped_grants_cover_every_target() {
+        let post = uuid::Uuid::new_v4();
+        let other_post = uuid::Uuid::new_v4();
+        let mixed = vec![held(None, Some(caps::Scope::Post(post))), held(None, None)];
+        assert!(verify_for(mixed.clone(), post, &[]));
+        assert!(verify_for(mixed.clone(), other_post, &[]));
+        assert!(Capabilities::<caps::Edit>::new(post, mixed).is_ok());
+    }
+
+    /// Stands in for a capability check, counting how often the capabilities are loaded.
+    struct Check;
+    static LOADS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    impl<'a, 'r> FromRequest<'a, 'r> for Check {
+        type Error = ();
+        fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+            let _ = LoadedCapabilities::of(req, || {
+                LOADS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(vec![held(None, None)])
+            });
+            Outcome::Success(Check)
+        }
+    }
+
+    #[get("/checked")]
+    fn checked(_first: Check, _second: Check, _third: Check) -> &'static str {
+        "checked"
+    }
+
+    #[test]
+    fn capabilities_are_loaded_once_per_request() {
+        use std::sync::atomic::Ordering;
+        let rocket = rocket::custom(rocket::Config::development()).mount("/", routes![checked]);
+        let client = rocket::local::Client::new(rocket).expect("A valid rocket instance.");
+        assert_eq!(client.get("/checked").dispatch().status(), Status::Ok);
+        assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+        assert_eq!(client.get("/checked").dispatch().status(), Status::Ok);
+        assert_eq!(LOADS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn sealed_and_legacy_tokens_are_accepted() {
+        use crate::cfg::TokenAlgo;
+        let config = crypto::RotationConfig {
+            mode: crypto::RotationMode::Lazy,
+            ..crypto::RotationConfig::default()
+        };
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, config);
+        let key_store = rotator.get_key_fixture().get_store().unwrap();
+        let user_id = uuid::Uuid::new_v4();
+        let capabilities = || Capabilities::safe_new(user_id, vec![held(None, None)]);
+        let now = SystemTime::now();
+
+        let sealed = seal_capabilities(&key_store, capabilities()).unwrap();
+        assert!(sealed.starts_with("v1.local."));
+        let unsealed = Capabilities::unseal(&sealed, &key_store, now).unwrap();
+        assert_eq!(unsealed.user_id(), user_id);
+        assert_eq!(unsealed.capabilities(), vec![Capability::EditPost]);
+        let expired = now + key_store.config().lifetime();
+        assert!(Capabilities::unseal(&sealed, &key_store, expired).is_err());
+
+        let legacy = paseto::token::Data {
+            msg: capabilities(),
+            footer: None::<()>,
+        };
+        let legacy = paseto::V2Local::encrypt(legacy, key_store.curr()).unwrap();
+        let legacy = String::from_utf8(legacy.to_vec()).unwrap();
+        let unsealed = Capabilities::unseal(&legacy, &key_store, now).unwrap();
+        assert_eq!(unsealed.user_id(), user_id);
+
+        let rotated = rotator.get_key_fixture().rotate_now().unwrap();
+        assert!(Capabilities::unseal(&sealed, &rotated, now).is_ok());
+        let retired = rotator.get_key_fixture().rotate_now().unwrap();
+        assert!(Capabilities::unseal(&sealed, &retired, now).is_err());
+        rotator.cleanup().unwrap();
+    }
+}
@@ -1,90 +1,105 @@
 //! A collection of classes used to represent and verify capabilities in the
 //! [`Capabilities`](crate::blog::auth::Capabilities) struct.
 
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::str;
 
-use blog_db::models::*;
-
-/// Used to indicate that a type represents a capabilities level. A Custom variant is listed for
-/// anything not covered by the enum.
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Hash)]
-pub enum Capability {
-    /// Capability allowing for editing of posts.
-    EditPost,
-    /// Capability allowing for creation of posts.
-    CreatePost,
-    /// Capability allowing for deletion of posts.
-    DeletePost,
-    /// Capability allowing for publishing of posts.
-    PublishPost,
-    /// Capability allowing for archival of posts.
-    ArchivePost,
-    /// Capability allowing for creation of other users.
-    CreateUser,
-    /// Capability allowing for editing of other users.
-    EditUser,
-    /// Capability allowing for deletion of other users.
-    DeleteUser,
-    /// Capability allowing for editing/deletion of login credentials.
-    EditUserCredentials,
-    /// Capability to grant capabilities to other users.
-    GrantCapability,
-    /// Capability to view capabilities of other users.
-    ViewCapability,
-    /// Capability to delete capabilities of other users.
-    DeleteCapability,
-    /// Arbitrary capability, just in case.
-    Custom { name: String },
-}
-// TODO make string const and lift into enum declaration when const generics.
-impl Capability {
-    /// Convert a [`Capability`](crate::blog::auth::capabilities::Capability) to a [`&str`].
-    pub fn as_str(&self) -> &str {
-        match self {
-            Self::EditPost => "edit_post",
-            Self::CreatePost => "create_post",
-            Self::DeletePost => "delete_post",
-            Self::PublishPost => "publish_post",
-            Self::ArchivePost => "archive_post",
-            Self::CreateUser => "create_user",
-            Self::EditUser => "edit_user",
-            Self::DeleteUser => "delete_user",
-            Self::EditUserCredentials => "edit_user_credentials",
-            Self::GrantCapability => "grant_capability",
-            Self::ViewCapability => "view_capability",
-            Self::DeleteCapability => "delete_capability",
-            Self::Custom { name } => &name,
+pub use blog_db::models::capabilities::{Capability, InvalidScope, Scope, UnknownCapability};
+use blog_db::models::capabilities::Effective;
+
+/// A capability held by a user, possibly only until some time or only for some target.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct Held {
+    /// The capability held.
+    pub capability: Capability,
+    /// When the capability stops applying. Never, if absent.
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// What the capability is restricted to. Everything, if absent.
+    #[serde(default)]
+    pub scope: Option<Scope>,
+}
+impl Held {
+    /// A capability that applies everywhere and never expires.
+    pub fn unrestricted(capability: Capability) -> Self {
+        Self {
+            capability,
+            expires_at: None,
+            scope: None,
         }
     }
+    /// Whether the capability still applies at `now`.
+    pub fn is_live_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.map_or(true, |expires_at| now < expires_at)
+    }
+    /// Whether the capability applies to the target at `now`. Unscoped capabilities apply to
+    /// every target, while scoped ones only apply to targets the scope covers. Without a target,
+    /// only unscoped capabilities apply.
+    pub fn applies_at(&self, target: Option<&Target>, now: DateTime<Utc>) -> bool {
+        self.is_live_at(now)
+            && match (self.scope, target) {
+                (None, _) => true,
+                (Some(scope), Some(target)) => target.is_covered_by(&scope),
+                (Some(_), None) => false,
+            }
+    }
 }
-// TODO make string const and lift into enum declaration when const generics.
-impl From<&capabilities::Data> for Capability {
-    fn from(perm: &capabilities::Data) -> Self {
-        match perm.capability.as_str() {
-            "edit_post" => Self::EditPost,
-            "create_post" => Self::CreatePost,
-            "delete_post" => Self::DeletePost,
-            "publish_post" => Self::PublishPost,
-            "archive_post" => Self::ArchivePost,
-            "create_user" => Self::CreateUser,
-            "edit_user" => Self::EditUser,
-            "delete_user" => Self::DeleteUser,
-            "edit_user_credentials" => Self::EditUserCredentials,
-            "grant_capability" => Self::GrantCapability,
-            "view_capability" => Self::ViewCapability,
-            "delete_capability" => Self::DeleteCapability,
-            p => Self::Custom { name: p.to_owned() },
+
+/// Converts the capabilities found for a user, dropping any this version of the site does not
+/// recognize or whose scope cannot be parsed. They are logged so that stale rows can be cleaned
+/// up.
+pub fn known(caps: &[Effective]) -> Vec<Held> {
+    use std::convert::TryFrom;
+    fn convert(cap: &Effective) -> Result<Held, Box<dyn std::error::Error>> {
+        Ok(Held {
+            capability: Capability::try_from(cap)?,
+            expires_at: cap.expires_at,
+            scope: cap.scope.as_deref().map(str::parse).transpose()?,
+        })
+    }
+    caps.iter()
+        .filter_map(|cap| {
+            convert(cap)
+                .map_err(|e| log::warn!("Ignoring {} held by a user.", e))
+                .ok()
+        })
+        .collect()
+}
+
+/// What a request acts upon, used for checking scoped capabilities.
+#[derive(Debug, Clone, Copy)]
+pub enum Target<'a> {
+    /// A post, along with the tags it has.
+    Post {
+        /// The id of the post.
+        id: uuid::Uuid,
+        /// The ids of the tags on the post.
+        tag_ids: &'a [uuid::Uuid],
+    },
+}
+impl<'a> Target<'a> {
+    /// Whether the scope includes the target.
+    pub fn is_covered_by(&self, scope: &Scope) -> bool {
+        match self {
+            Self::Post { id, tag_ids } => scope.covers_post(*id, tag_ids),
         }
     }
 }
 
+/// The capability that must always be held by at least one user. Without it, nobody can grant
+/// capabilities, and the site can no longer be administered.
+pub const ADMIN_CRITICAL: Capability = Capability::GrantCapability;
+/// Explains why an operation was refused when it would remove the last holder of
+/// [`ADMIN_CRITICAL`].
+pub const LAST_ADMIN_MSG: &str = "Refusing to remove the last user able to grant capabilities.";
+
 /// Used to indicate that a type represents a capabilities level.
 pub trait Verifiable {
     const REQUIRED_CAPS: &'static [Capability];
     /// Verifies that the credentials passed in satisfies the capabilities.
     fn verify<T>(cred: &super::Capabilities<T>) -> bool {
-        Self::verify_slice(cred.capabilities())
+        Self::verify_slice(cred.capabilities().as_slice())
     }
     /// Verifies if a provided slice of capabilities satisfies the capabilities level.
     fn verify_slice(caps: &[Capability]) -> bool {
@@ -94,91 +109,42 @@ pub trait Verifiable {
     }
 }
 
-/// This level of privlege represents at least the right to create user accounts.
-#[derive(Debug)]
-pub struct CreateUser;
-impl Verifiable for CreateUser {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::CreateUser];
-}
-
-/// This level of privlege represents at least the right to change user account information.
-#[derive(Debug)]
-pub struct EditUser;
-impl Verifiable for EditUser {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::EditUser];
-}
-
-/// This level of privlege represents at least the right to delete user accounts.
-#[derive(Debug)]
-pub struct DeleteUser;
-impl Verifiable for DeleteUser {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::DeleteUser];
-}
-
-/// This level of privlege represents at least the right to edit blog posts.
-#[derive(Debug)]
-pub struct EditUserCredentials;
-impl Verifiable for EditUserCredentials {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::EditUserCredentials];
+/// Declares marker types implementing [`Verifiable`], each requiring the listed capabilities.
+/// Markers requiring a single capability are declared for every capability by
+/// [`verifiable_capabilities`], so this is only used directly for levels requiring several.
+macro_rules! verifiable {
+    ($($(#[$meta:meta])* $marker:ident => [$($cap:ident),* $(,)?],)*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug)]
+            pub struct $marker;
+            impl Verifiable for $marker {
+                const REQUIRED_CAPS: &'static [Capability] = &[$(Capability::$cap),*];
+            }
+        )*
+    };
+}
+/// Declares a marker named after each capability, requiring just that capability. Called with
+/// every capability by [`blog_db::for_each_capability`].
+macro_rules! verifiable_capabilities {
+    ($($(#[$meta:meta])* $capability:ident,)*) => {
+        verifiable! {
+            $($(#[$meta])* $capability => [$capability],)*
+        }
+    };
 }
+blog_db::for_each_capability!(verifiable_capabilities);
 
 /// This level of privlege represents at least the right to edit blog posts.
-#[derive(Debug)]
-pub struct Edit;
-impl Verifiable for Edit {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::EditPost];
-}
-
+pub type Edit = EditPost;
 /// This level of privlege represents at least the right to delete blog posts.
-#[derive(Debug)]
-pub struct Delete;
-impl Verifiable for Delete {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::DeletePost];
-}
-
+pub type Delete = DeletePost;
 /// This level of privlege represents at least the right to create blog posts.
-#[derive(Debug)]
-pub struct Post;
-impl Verifiable for Post {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::CreatePost];
-}
-
+pub type Post = CreatePost;
 /// This level of privlege represents at least the right to publish blog posts.
-#[derive(Debug)]
-pub struct Publish;
-impl Verifiable for Publish {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::PublishPost];
-}
-
+pub type Publish = PublishPost;
 /// This level of privlege represents at least the right to archive blog posts.
-#[derive(Debug)]
-pub struct Archive;
-impl Verifiable for Archive {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::ArchivePost];
-}
-
-/// This level of privlege represents at least the right to grant capabilities.
-///
-/// NOTE: Can only grant capabilities they already have.
-#[derive(Debug)]
-pub struct GrantCapability;
-impl Verifiable for GrantCapability {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::GrantCapability];
-}
-
-/// This level of privlege represents at least the right to view capabilities.
-#[derive(Debug)]
-pub struct ViewCapability;
-impl Verifiable for ViewCapability {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::ViewCapability];
-}
-
-/// This level of privlege represents at least the right to delete capabilities.
-#[derive(Debug)]
-pub struct DeleteCapability;
-impl Verifiable for DeleteCapability {
-    const REQUIRED_CAPS: &'static [Capability] = &[Capability::DeleteCapability];
-}
+pub type Archive = ArchivePost;
 
 /// Type to allow for the verification of a Capabilities allowing for arbitrary capabilities. Simply
 /// a rename of the () type to make purpose clearer.
@@ -189,3 +155,20 @@ impl Verifiable for Any {
         true
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! assert_marked {
+        ($($(#[$meta:meta])* $capability:ident,)*) => {
+            $(assert_eq!($capability::REQUIRED_CAPS, &[Capability::$capability]);)*
+        };
+    }
+
+    #[test]
+    fn every_capability_has_a_marker_requiring_it() {
+        blog_db::for_each_capability!(assert_marked);
+        assert_eq!(Edit::REQUIRED_CAPS, &[Capability::EditPost]);
+    }
+}
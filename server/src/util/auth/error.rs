@@ -1,15 +1,15 @@
 //! Error data and conversions.
 
-use diesel::result::Error as DieselError;
 use rocket::{http::Status, response::status};
 
-use crypto::token::paseto::V2LocalError as DecryptError;
+use crate::util::blog::db;
+use crypto::token::{paseto::V2LocalError as DecryptError, sealed};
 
 /// Errors for authentication.
 #[derive(Debug)]
 pub enum Error {
     /// Database errored when attempting operation.
-    Diesel(diesel::result::Error),
+    DB(db::Error),
     /// Authenticated user lacks capabilities to create a user.
     LackingCapabilities,
     /// Capabilities do not match user.
@@ -20,15 +20,22 @@ pub enum Error {
     KeyStorePoisoned,
     /// Did not initialize a key store. Probably forgot to [`rocket::Rocket::manage()`] it.
     KeyStoreAbsent,
+    /// The capabilities of the user could not be loaded.
+    CapabilitiesUnavailable,
 }
 impl From<DecryptError> for Error {
     fn from(_: DecryptError) -> Self {
         Self::Unauthorized
     }
 }
-impl From<diesel::result::Error> for Error {
-    fn from(e: diesel::result::Error) -> Self {
-        Self::Diesel(e)
+impl From<sealed::Error> for Error {
+    fn from(_: sealed::Error) -> Self {
+        Self::Unauthorized
+    }
+}
+impl From<db::Error> for Error {
+    fn from(e: db::Error) -> Self {
+        Self::DB(e)
     }
 }
 impl<G> From<std::sync::PoisonError<G>> for Error {
@@ -39,13 +46,13 @@ impl<G> From<std::sync::PoisonError<G>> for Error {
 impl From<&Error> for Status {
     fn from(e: &Error) -> Self {
         match e {
-            Error::Diesel(DieselError::NotFound) => Status::NotFound,
-            Error::Diesel(_) => Status::InternalServerError,
+            Error::DB(e) => e.into(),
             Error::LackingCapabilities => Status::InternalServerError,
             Error::BadCredentials => Status::InternalServerError,
             Error::KeyStorePoisoned => Status::InternalServerError,
             Error::Unauthorized => Status::Unauthorized,
             Error::KeyStoreAbsent => Status::InternalServerError,
+            Error::CapabilitiesUnavailable => Status::ServiceUnavailable,
         }
     }
 }
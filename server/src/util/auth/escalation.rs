@@ -0,0 +1,209 @@
+//! Asks for a challenge before logging in from an address that failed too many times recently,
+//! rather than refusing it outright, since many people may share an address.
+//!
+//! Failed logins are counted per address. Once an address has failed [`SUSPICIOUS_AFTER`] times
+//! within the [`WINDOW`], logins from it must carry the answer to a challenge set by the
+//! [`ChallengeProvider`], which is checked before the password is. The default provider hands out
+//! a [proof of work](ProofOfWork). Others, such as a CAPTCHA, plug in by implementing the trait
+//! and describing themselves as [external](Descriptor::External).
+
+use rocket::request::{FromRequest, Outcome, Request};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    cfg::TokenKeyStore,
+    util::challenge::{Challenges, Error},
+};
+use login_enum::challenge::{Answer, Descriptor};
+
+/// How many failed logins from an address are let through before it is asked for a challenge.
+pub const SUSPICIOUS_AFTER: usize = 5;
+/// How long a failed login counts against the address it came from.
+pub const WINDOW: Duration = Duration::from_secs(15 * 60);
+/// The least difficulty of the proof of work asked of suspicious addresses, however low the one
+/// asked of anonymous callers is configured.
+pub const MIN_DIFFICULTY: u8 = 16;
+
+/// Sets the challenges suspicious addresses must pass, and checks their answers.
+pub trait ChallengeProvider: Send + Sync {
+    /// Sets a new challenge.
+    fn issue(&self, key_store: &TokenKeyStore, now: SystemTime) -> Result<Descriptor, Error>;
+    /// Checks the answer to a challenge this provider set, spending it.
+    fn verify(&self, key_store: &TokenKeyStore, answer: &Answer, now: SystemTime)
+        -> Result<(), Error>;
+}
+
+/// The [proof of work](crate::util::challenge) asked of anonymous callers, made at least
+/// [`MIN_DIFFICULTY`] hard.
+pub struct ProofOfWork(Challenges);
+impl ProofOfWork {
+    pub fn new(challenges: &Challenges) -> Self {
+        Self(challenges.with_difficulty(challenges.difficulty().max(MIN_DIFFICULTY)))
+    }
+}
+impl ChallengeProvider for ProofOfWork {
+    fn issue(&self, key_store: &TokenKeyStore, now: SystemTime) -> Result<Descriptor, Error> {
+        self.0
+            .issue(key_store, now)
+            .map(Descriptor::ProofOfWork)
+            .map_err(|e| {
+                log::error!("Failed to seal a challenge due to {:?}.", e);
+                Error::Unavailable
+            })
+    }
+    fn verify(
+        &self,
+        key_store: &TokenKeyStore,
+        answer: &Answer,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        match answer {
+            Answer::ProofOfWork { token, nonce } => self.0.redeem(key_store, token, *nonce, now),
+            Answer::External { .. } => Err(Error::Invalid),
+        }
+    }
+}
+
+/// Where a login came from, and the answer to a challenge sent along with it, if any.
+#[derive(Debug)]
+pub struct Attempt {
+    ip: Option<IpAddr>,
+    answer: Option<Answer>,
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Attempt {
+    type Error = ();
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        let headers = req.headers();
+        Outcome::Success(Self {
+            // The remote address rather than the client ip, since the latter trusts headers the
+            // client sets, and would let each attempt claim a fresh address.
+            ip: req.remote().map(|addr| addr.ip()),
+            answer: Answer::from_headers(|name| headers.get_one(name)),
+        })
+    }
+}
+
+/// Counts failed logins per address, and asks suspicious ones for a challenge. Managed by rocket
+/// for the login handler.
+pub struct Escalation {
+    provider: Box<dyn ChallengeProvider>,
+    /// When each failed login that still counts happened, by the address it came from.
+    failures: Mutex<HashMap<IpAddr, Vec<SystemTime>>>,
+}
+impl Escalation {
+    pub fn new(provider: impl ChallengeProvider + 'static) -> Self {
+        Self {
+            provider: Box::new(provider),
+            failures: Mutex::default(),
+        }
+    }
+    fn failures(&self) -> MutexGuard<HashMap<IpAddr, Vec<SystemTime>>> {
+        match self.failures.lock() {
+            Ok(failures) => failures,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+    /// Counts a failed login against the address it came from. Failures that no longer count are
+    /// forgotten along the way.
+    pub fn record_failure(&self, attempt: &Attempt, now: SystemTime) {
+        let mut failures = self.failures();
+        failures.retain(|_, at| {
+            at.retain(|when| *when + WINDOW > now);
+            !at.is_empty()
+        });
+        if let Some(ip) = attempt.ip {
+            failures.entry(ip).or_insert_with(Vec::new).push(now);
+        }
+    }
+    /// Whether logins from `ip` must pass a challenge first.
+    pub fn is_suspicious(&self, ip: IpAddr, now: SystemTime) -> bool {
+        self.failures().get(&ip).map_or(false, |at| {
+            at.iter().filter(|at| **at + WINDOW > now).count() >= SUSPICIOUS_AFTER
+        })
+    }
+    /// The challenge `attempt` must pass before its password is checked, if any. An answer sent
+    /// along with it is spent, and only passes if it answers a challenge set for a login.
+    pub fn challenge_for(
+        &self,
+        key_store: &TokenKeyStore,
+        attempt: &Attempt,
+        now: SystemTime,
+    ) -> Result<Option<Descriptor>, Error> {
+        match attempt.ip {
+            Some(ip) if self.is_suspicious(ip, now) => {}
+            _ => return Ok(None),
+        }
+        if let Some(answer) = &attempt.answer {
+            match self.provider.verify(key_store, answer, now) {
+                Ok(()) => return Ok(None),
+                Err(Error::Unavailable) => return Err(Error::Unavailable),
+                Err(e) => log::info!("Refused the answer to a login challenge: {:?}.", e),
+            }
+        }
+        self.provider.issue(key_store, now).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cfg::TokenAlgo;
+    use std::net::Ipv4Addr;
+
+    fn attempt(answer: Option<Answer>) -> Attempt {
+        Attempt {
+            ip: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+            answer,
+        }
+    }
+
+    #[test]
+    fn addresses_are_suspicious_while_their_failures_count() {
+        let escalation = Escalation::new(ProofOfWork::new(&Challenges::new(0)));
+        let ip = attempt(None).ip.unwrap();
+        let now = SystemTime::now();
+        for _ in 0..SUSPICIOUS_AFTER - 1 {
+            escalation.record_failure(&attempt(None), now);
+        }
+        assert!(!escalation.is_suspicious(ip, now));
+        escalation.record_failure(&attempt(None), now);
+        assert!(escalation.is_suspicious(ip, now));
+        assert!(!escalation.is_suspicious(ip, now + WINDOW));
+        escalation.record_failure(&attempt(None), now + WINDOW);
+        assert_eq!(escalation.failures()[&ip].len(), 1);
+    }
+
+    #[test]
+    fn suspicious_addresses_are_let_through_with_an_answer() {
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, crypto::RotationConfig::default());
+        let key_store = rotator.get_key_fixture().get_store().unwrap();
+        let escalation = Escalation::new(ProofOfWork::new(&Challenges::new(0)));
+        let now = SystemTime::now();
+        assert_eq!(escalation.challenge_for(&key_store, &attempt(None), now), Ok(None));
+        for _ in 0..SUSPICIOUS_AFTER {
+            escalation.record_failure(&attempt(None), now);
+        }
+
+        let challenge = match escalation.challenge_for(&key_store, &attempt(None), now) {
+            Ok(Some(Descriptor::ProofOfWork(challenge))) => challenge,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(challenge.difficulty, MIN_DIFFICULTY);
+        let answer = Answer::ProofOfWork {
+            nonce: (0..).find(|nonce| challenge.is_solved_by(*nonce)).unwrap(),
+            token: challenge.token,
+        };
+        let answered = attempt(Some(answer));
+        assert_eq!(escalation.challenge_for(&key_store, &answered, now), Ok(None));
+        // Spent answers are met with a new challenge.
+        let again = escalation.challenge_for(&key_store, &answered, now);
+        assert!(matches!(again, Ok(Some(_))), "unexpected {:?}", again);
+        rotator.cleanup().unwrap();
+    }
+}
@@ -2,3 +2,67 @@
 
 pub use blog_db::rocket as db;
 pub use db::DB;
+
+#[cfg(test)]
+mod test {
+    use super::db::{self, DBConn, LongDB, StatementTimeouts, CHECKOUT_TIMEOUT};
+    use super::DB;
+    use crate::cfg::ServerConfig;
+    use diesel::RunQueryDsl;
+    use rocket::{http::Status, local::Client};
+    use std::time::{Duration, Instant};
+
+    const TIMEOUTS: StatementTimeouts = StatementTimeouts {
+        standard: Duration::from_millis(200),
+        long: Duration::from_secs(5),
+    };
+    /// How long the statement the routes run takes, between the two timeouts.
+    const SLEEP_SECS: f64 = 1.0;
+
+    fn sleep(db: &impl DBConn) -> Result<(), Status> {
+        diesel::sql_query(format!("SELECT pg_sleep({})", SLEEP_SECS))
+            .execute(db.conn())
+            .map(|_| ())
+            .map_err(|e| db::Error::from(e).into())
+    }
+
+    #[get("/sleep")]
+    fn standard(db: DB) -> Result<(), Status> {
+        sleep(&db)
+    }
+
+    #[get("/sleep/long")]
+    fn long(db: LongDB) -> Result<(), Status> {
+        sleep(&db)
+    }
+
+    /// A server with a pool of a single connection, so that every request gets the same one.
+    fn client() -> Client {
+        use rocket::config::Value;
+        let config = ServerConfig::from_env_for_tests();
+        let mut rocket_config = config.apply_to(rocket::Config::development());
+        if let Some(Value::Table(databases)) = rocket_config.extras.get_mut("databases") {
+            if let Some(Value::Table(blog)) = databases.get_mut("blog") {
+                blog.insert("pool_size".to_owned(), Value::from(1));
+            }
+        }
+        let rocket = rocket::custom(rocket_config)
+            .attach(DB::fairing_with_timeouts(CHECKOUT_TIMEOUT, TIMEOUTS))
+            .mount("/", routes![standard, long]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    #[ignore]
+    fn slow_statements_time_out_with_a_504() {
+        let client = client();
+        let started = Instant::now();
+        let res = client.get("/sleep").dispatch();
+        assert_eq!(res.status(), Status::GatewayTimeout);
+        assert!(started.elapsed() < Duration::from_secs_f64(SLEEP_SECS));
+
+        assert_eq!(client.get("/sleep/long").dispatch().status(), Status::Ok);
+        // The one connection went back to the pool with the standard timeout.
+        assert_eq!(client.get("/sleep").dispatch().status(), Status::GatewayTimeout);
+    }
+}
@@ -0,0 +1,170 @@
+//! Caps how large a request body the api accepts, by the kind of route it is sent to.
+//!
+//! The [`BodyLimits`] fairing reads the declared `Content-Length` of requests to the api before
+//! any handler runs, and [refuses](crate::util::refuse) bodies larger than the [`BodyClass`] of
+//! the route with a 413 carrying a [`TooLarge`] body, without reading them. Bodies without a
+//! declared length are capped by the `json` limit rocket is
+//! [configured](crate::cfg::ServerConfig::apply_to) with, which is that of the largest json route.
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Status},
+    Data, Request,
+};
+
+use crate::{
+    cfg,
+    util::refuse::{self, Answer},
+};
+use login_enum::TooLarge;
+
+/// Where routes receiving uploads are mounted under the api. They stream their bodies rather
+/// than buffering them, and must stop reading at [`BodyClass::Large`] themselves.
+pub const UPLOADS_PREFIX: &str = "/uploads";
+/// Where posts are mounted under the api.
+const POSTS_PREFIX: &str = "/posts";
+
+/// How large a body a route accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyClass {
+    /// Routes taking a handful of fields, such as logging in, credentials, and capabilities.
+    Small,
+    /// Routes taking the contents of a post.
+    Medium,
+    /// Routes receiving uploads.
+    Large,
+}
+impl BodyClass {
+    /// The most bytes a body sent to the route may have.
+    pub const fn limit(self) -> u64 {
+        match self {
+            Self::Small => 64 * 1024,
+            Self::Medium => 2 * 1024 * 1024,
+            Self::Large => 64 * 1024 * 1024,
+        }
+    }
+    /// The class of the route at `path`, if it is under the api.
+    pub fn of(path: &str) -> Option<Self> {
+        let rest = path.strip_prefix(cfg::BLOG_API_ROOT)?;
+        let under = |prefix: &str| {
+            rest.strip_prefix(prefix)
+                .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+        };
+        if !rest.is_empty() && !rest.starts_with('/') {
+            None
+        } else if under(UPLOADS_PREFIX) {
+            Some(Self::Large)
+        } else if under(POSTS_PREFIX) {
+            Some(Self::Medium)
+        } else {
+            Some(Self::Small)
+        }
+    }
+}
+
+/// Refuses requests to the api declaring bodies larger than their route accepts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyLimits;
+
+impl Fairing for BodyLimits {
+    fn info(&self) -> Info {
+        Info {
+            name: "Body limits",
+            kind: Kind::Request,
+        }
+    }
+    fn on_request(&self, req: &mut Request, _: &Data) {
+        let limit = match BodyClass::of(req.uri().path()) {
+            Some(class) => class.limit(),
+            None => return,
+        };
+        let declared = req
+            .headers()
+            .get_one("Content-Length")
+            .and_then(|len| len.trim().parse::<u64>().ok());
+        match declared {
+            Some(declared) if declared > limit => {
+                log::info!(
+                    "Refusing a body of {} bytes to {}, which accepts {}.",
+                    declared,
+                    req.uri().path(),
+                    limit
+                );
+                let body = serde_json::to_string(&TooLarge { limit_bytes: limit })
+                    .expect("A serializable body.");
+                let answer = Answer::new(Status::PayloadTooLarge).body(ContentType::JSON, body);
+                refuse::reroute(req, answer);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::refuse::Refusals;
+    use rocket::{
+        http::Header,
+        local::{Client, LocalResponse},
+    };
+
+    #[post("/posts", data = "<body>")]
+    fn create(body: String) -> String {
+        body
+    }
+
+    #[post("/login", data = "<body>")]
+    fn log_in(body: String) -> String {
+        body
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(Refusals)
+            .attach(BodyLimits)
+            .mount(cfg::BLOG_API_ROOT, routes![create, log_in]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    fn routes_fall_into_classes_by_prefix() {
+        assert_eq!(BodyClass::of("/api/login"), Some(BodyClass::Small));
+        assert_eq!(BodyClass::of("/api/credentials/pws"), Some(BodyClass::Small));
+        assert_eq!(BodyClass::of("/api/posts"), Some(BodyClass::Medium));
+        assert_eq!(BodyClass::of("/api/posts/1/tags"), Some(BodyClass::Medium));
+        assert_eq!(BodyClass::of("/api/postscript"), Some(BodyClass::Small));
+        assert_eq!(BodyClass::of("/api/uploads/1"), Some(BodyClass::Large));
+        assert_eq!(BodyClass::of("/apiary"), None);
+        assert_eq!(BodyClass::of("/blog/posts"), None);
+    }
+
+    /// Declares a body of `declared` bytes while only sending a few, so that a refusal shows the
+    /// body was never read.
+    fn declaring<'c>(client: &'c Client, path: &'static str, declared: u64) -> LocalResponse<'c> {
+        client
+            .post(path)
+            .header(Header::new("Content-Length", declared.to_string()))
+            .body("{}")
+            .dispatch()
+    }
+
+    #[test]
+    fn bodies_over_the_limit_of_their_class_are_refused_unread() {
+        let client = client();
+        let classes = [("/api/login", BodyClass::Small), ("/api/posts", BodyClass::Medium)];
+        for &(path, class) in &classes {
+            let mut res = declaring(&client, path, class.limit() + 1);
+            assert_eq!(res.status(), Status::PayloadTooLarge);
+            assert_eq!(res.content_type(), Some(ContentType::JSON));
+            let refusal: TooLarge = serde_json::from_str(&res.body_string().unwrap()).unwrap();
+            assert_eq!(refusal.limit_bytes, class.limit());
+
+            let res = declaring(&client, path, 2);
+            assert_eq!(res.status(), Status::Ok);
+        }
+        // A post body is fine, a login body of the same size is not.
+        let res = declaring(&client, "/api/posts", BodyClass::Small.limit() + 1);
+        assert_eq!(res.status(), Status::Ok);
+    }
+}
@@ -0,0 +1,209 @@
+//! Hands out and checks the [proof of work](login_enum::challenge) asked of anonymous callers of
+//! the public endpoints that change things.
+//!
+//! Challenge tokens are sealed with the token keys, so that the server does not need to remember
+//! the challenges it handed out, only the ones already spent. Spent challenges are forgotten once
+//! they would have expired anyway. Handlers ask for a solved challenge by taking a [`Proven`]
+//! guard, which lets logged in callers through without one.
+
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+    State,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tap::*;
+
+use crate::{
+    cfg::{TokenKeyFixture, TokenKeyStore},
+    util::auth,
+};
+use crypto::{key_rotation::Generational, token::sealed};
+use login_enum::challenge::{Challenge, NONCE_HEADER, TOKEN_HEADER};
+
+/// How long a challenge may be solved and spent for after it is handed out.
+pub const LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+/// What a challenge token holds.
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    /// Tells challenges apart, so that each can only be spent once.
+    challenge_id: uuid::Uuid,
+    difficulty: u8,
+}
+
+/// Why a request was refused for its challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// No challenge was sent.
+    Missing,
+    /// The token was not handed out by this server, or has expired.
+    Invalid,
+    /// The nonce does not solve the challenge.
+    Unsolved,
+    /// The challenge was already spent on another request.
+    Spent,
+    /// The token keys could not be reached.
+    KeyStoreAbsent,
+    /// The challenge could not be set or checked, such as when an external provider is down.
+    Unavailable,
+}
+
+/// Hands out challenges of the configured difficulty, and remembers the ones spent. Managed by
+/// rocket for the [`Proven`] guard.
+#[derive(Clone)]
+pub struct Challenges {
+    difficulty: u8,
+    /// Spent challenges, with when they expire.
+    spent: Arc<Mutex<HashMap<uuid::Uuid, SystemTime>>>,
+}
+impl Challenges {
+    pub fn new(difficulty: u8) -> Self {
+        Self {
+            difficulty,
+            spent: Arc::default(),
+        }
+    }
+    pub fn difficulty(&self) -> u8 {
+        self.difficulty
+    }
+    /// Challenges of another difficulty, sharing which were spent with these, so that a token
+    /// can't be spent once on each.
+    pub fn with_difficulty(&self, difficulty: u8) -> Self {
+        Self {
+            difficulty,
+            spent: self.spent.clone(),
+        }
+    }
+    /// Hands out a new challenge, sealed with the current token key.
+    pub fn issue(
+        &self,
+        key_store: &TokenKeyStore,
+        now: SystemTime,
+    ) -> Result<Challenge, sealed::Error> {
+        let claims = Claims {
+            challenge_id: uuid::Uuid::new_v4(),
+            difficulty: self.difficulty,
+        };
+        let generation = key_store.curr_generation();
+        let token =
+            sealed::Token::new(claims, generation.id, now, LIFETIME).seal(&*generation.key)?;
+        Ok(Challenge {
+            token,
+            difficulty: self.difficulty,
+        })
+    }
+    /// Spends the challenge `token`, if `nonce` solves it and it was not spent before.
+    pub fn redeem(
+        &self,
+        key_store: &TokenKeyStore,
+        token: &str,
+        nonce: u64,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        let generation = sealed::key_id(token)
+            .ok()
+            .and_then(|id| key_store.generation(id))
+            .ok_or(Error::Invalid)?;
+        let claims: Claims = sealed::Token::unseal(token, &*generation.key, now)
+            .map_err(|_| Error::Invalid)?
+            .claims
+            .data;
+        let challenge = Challenge {
+            token: token.to_owned(),
+            difficulty: claims.difficulty,
+        };
+        // Easier challenges handed out by challenges sharing these are not enough.
+        if claims.difficulty < self.difficulty || !challenge.is_solved_by(nonce) {
+            return Err(Error::Unsolved);
+        }
+        let mut spent = match self.spent.lock() {
+            Ok(spent) => spent,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        spent.retain(|_, expires_at| *expires_at > now);
+        if spent.insert(claims.challenge_id, now + LIFETIME).is_some() {
+            return Err(Error::Spent);
+        }
+        Ok(())
+    }
+}
+
+/// Proof that the caller is logged in, or solved a challenge for this request.
+#[derive(Debug)]
+pub struct Proven;
+
+impl<'a, 'r> FromRequest<'a, 'r> for Proven {
+    type Error = Error;
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        if req.guard::<auth::UnverifiedCapabilities>().is_success() {
+            return Outcome::Success(Proven);
+        }
+        let challenges = req
+            .guard::<State<Challenges>>()
+            .map_failure(|_| (Status::InternalServerError, Error::KeyStoreAbsent))?;
+        if challenges.difficulty == 0 {
+            return Outcome::Success(Proven);
+        }
+        let key_store = req
+            .guard::<State<TokenKeyFixture>>()
+            .map_failure(|_| (Status::InternalServerError, Error::KeyStoreAbsent))?
+            .get_store()
+            .map_err(|_| Error::KeyStoreAbsent)
+            .into_outcome(Status::InternalServerError)?;
+        let headers = req.headers();
+        let token = headers.get_one(TOKEN_HEADER);
+        let nonce = headers
+            .get_one(NONCE_HEADER)
+            .and_then(|nonce| nonce.parse::<u64>().ok());
+        let (token, nonce) = match (token, nonce) {
+            (Some(token), Some(nonce)) => (token, nonce),
+            _ => return Outcome::Failure((Status::PreconditionRequired, Error::Missing)),
+        };
+        challenges
+            .redeem(&*key_store, token, nonce, SystemTime::now())
+            .map(|()| Proven)
+            .tap_err(|e| log::info!("Refused a challenge: {:?}.", e))
+            .into_outcome(Status::PreconditionRequired)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cfg::TokenAlgo;
+
+    /// Searches for the first nonce solving `challenge`.
+    fn solve(challenge: &Challenge) -> u64 {
+        (0..).find(|nonce| challenge.is_solved_by(*nonce)).unwrap()
+    }
+
+    #[test]
+    fn solved_challenges_are_spent_once() {
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, crypto::RotationConfig::default());
+        let key_store = rotator.get_key_fixture().get_store().unwrap();
+        let challenges = Challenges::new(8);
+        let now = SystemTime::now();
+        let challenge = challenges.issue(&key_store, now).unwrap();
+        assert_eq!(challenge.difficulty, 8);
+
+        let nonce = solve(&challenge);
+        let unsolved = (0..).find(|nonce| !challenge.is_solved_by(*nonce)).unwrap();
+        let redeem = |token: &str, nonce, at| challenges.redeem(&key_store, token, nonce, at);
+        assert_eq!(redeem(&challenge.token, unsolved, now), Err(Error::Unsolved));
+        assert_eq!(redeem("v1.local.made.up", nonce, now), Err(Error::Invalid));
+        let late = now + LIFETIME + Duration::from_secs(1);
+        assert_eq!(redeem(&challenge.token, nonce, late), Err(Error::Invalid));
+        assert_eq!(redeem(&challenge.token, nonce, now), Ok(()));
+        assert_eq!(redeem(&challenge.token, nonce, now), Err(Error::Spent));
+
+        let easy = challenges.with_difficulty(0).issue(&key_store, now).unwrap();
+        assert_eq!(redeem(&easy.token, 0, now), Err(Error::Unsolved));
+        rotator.cleanup().unwrap();
+    }
+}
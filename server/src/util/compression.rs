@@ -0,0 +1,262 @@
+//! Compression of responses based on the `Accept-Encoding` of the request.
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Header, Status},
+    Request, Response,
+};
+use std::io::{self, Cursor, Write};
+
+/// Bodies smaller than this are not worth compressing.
+pub const DEFAULT_MIN_SIZE: usize = 1024;
+
+/// An encoding that responses can be compressed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+impl Encoding {
+    /// The encodings, from most to least preferred.
+    pub const PREFERENCE: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    /// The name of the encoding in `Accept-Encoding` and `Content-Encoding`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+    /// The extension of files pre-compressed with the encoding.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+    /// Lists the encodings the client accepts from most to least preferred, going by the
+    /// `Accept-Encoding` header of the request.
+    pub fn accepted(req: &Request) -> Vec<Self> {
+        Self::accepted_from(req.headers().get("Accept-Encoding"))
+    }
+    /// Lists the encodings allowed by a list of `Accept-Encoding` header values, from most to least
+    /// preferred.
+    pub fn accepted_from<'a>(accept_encoding: impl Iterator<Item = &'a str>) -> Vec<Self> {
+        let mut accepted = vec![];
+        let mut wildcard = false;
+        for coding in accept_encoding.flat_map(|value| value.split(',')) {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or("");
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.);
+            if quality <= 0. {
+                continue;
+            }
+            if name == "*" {
+                wildcard = true;
+            } else {
+                accepted.push(name.to_ascii_lowercase());
+            }
+        }
+        Self::PREFERENCE
+            .iter()
+            .copied()
+            .filter(|e| wildcard || accepted.iter().any(|name| name == e.name()))
+            .collect()
+    }
+    /// Compresses the bytes with the encoding.
+    pub fn compress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut compressed = vec![];
+                {
+                    let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+                    writer.write_all(bytes)?;
+                }
+                Ok(compressed)
+            }
+            Encoding::Gzip => {
+                let mut writer =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                writer.write_all(bytes)?;
+                writer.finish()
+            }
+        }
+    }
+}
+
+/// Adds `Accept-Encoding` to the `Vary` header of the response.
+pub fn vary_on_encoding(res: &mut Response) {
+    let vary: Vec<_> = res.headers().get("Vary").map(str::to_owned).collect();
+    if !vary
+        .iter()
+        .flat_map(|v| v.split(','))
+        .any(|v| v.trim().eq_ignore_ascii_case("Accept-Encoding"))
+    {
+        res.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+    }
+}
+
+/// Compresses JSON and HTML responses with the encoding preferred by the client.
+///
+/// Responses that already have a `Content-Encoding` are left alone, as are bodies smaller than
+/// `min_size`.
+pub struct Compression {
+    /// The smallest body that will be compressed.
+    pub min_size: usize,
+}
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+}
+impl Compression {
+    fn is_compressible(res: &Response) -> bool {
+        match res.content_type() {
+            Some(ct) => ct == ContentType::JSON || ct == ContentType::HTML,
+            None => false,
+        }
+    }
+}
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response compression",
+            kind: Kind::Response,
+        }
+    }
+    fn on_response(&self, req: &Request, res: &mut Response) {
+        // Parts are of the uncompressed resource, and resources with a strong `ETag` have to be
+        // served as they were tagged.
+        let as_tagged = res.status() == Status::PartialContent || res.headers().contains("ETag");
+        if res.headers().contains("Content-Encoding") || as_tagged || !Self::is_compressible(res) {
+            return;
+        }
+        vary_on_encoding(res);
+        let encoding = match Encoding::accepted(req).first() {
+            Some(&encoding) => encoding,
+            None => return,
+        };
+        let body = match res.body_bytes() {
+            Some(body) => body,
+            None => return,
+        };
+        if body.len() < self.min_size {
+            res.set_sized_body(Cursor::new(body));
+            return;
+        }
+        match encoding.compress(&body) {
+            Ok(compressed) => {
+                res.set_raw_header("Content-Encoding", encoding.name());
+                res.set_sized_body(Cursor::new(compressed));
+            }
+            Err(e) => {
+                log::error!("Failed to compress response due to {:?}.", e);
+                res.set_sized_body(Cursor::new(body));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rocket::{
+        http::{Header, Status},
+        local::Client,
+    };
+    use rocket_contrib::json::Json;
+    use std::io::Read;
+
+    #[derive(serde::Serialize)]
+    struct Post {
+        title: String,
+        body: String,
+    }
+
+    fn large_posts() -> Vec<Post> {
+        (0..100)
+            .map(|i| Post {
+                title: format!("Post {}", i),
+                body: "Lorem ipsum dolor sit amet. ".repeat(20),
+            })
+            .collect()
+    }
+
+    #[get("/api/posts")]
+    fn posts() -> Json<Vec<Post>> {
+        Json(large_posts())
+    }
+
+    #[get("/api/small")]
+    fn small() -> Json<&'static str> {
+        Json("small")
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(Compression::default())
+            .mount("/", routes![posts, small]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    fn decode(encoding: &str, bytes: &[u8]) -> Vec<u8> {
+        let mut decoded = vec![];
+        match encoding {
+            "br" => brotli::Decompressor::new(bytes, 4096)
+                .read_to_end(&mut decoded)
+                .unwrap(),
+            "gzip" => flate2::read::GzDecoder::new(bytes)
+                .read_to_end(&mut decoded)
+                .unwrap(),
+            e => panic!("Unexpected encoding {}.", e),
+        };
+        decoded
+    }
+
+    #[test]
+    fn large_listing_is_compressed() {
+        let client = client();
+        let expected = serde_json::to_vec(&large_posts()).unwrap();
+        for &encoding in &["br", "gzip"] {
+            let mut res = client
+                .get("/api/posts")
+                .header(Header::new("Accept-Encoding", encoding))
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            assert_eq!(res.headers().get_one("Content-Encoding"), Some(encoding));
+            assert_eq!(res.headers().get_one("Vary"), Some("Accept-Encoding"));
+            let body = res.body_bytes().unwrap();
+            assert!(body.len() < expected.len());
+            assert_eq!(decode(encoding, &body), expected);
+        }
+    }
+
+    #[test]
+    fn small_or_unaccepted_bodies_are_left_alone() {
+        let client = client();
+        let res = client
+            .get("/api/small")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+        assert_eq!(res.headers().get_one("Content-Encoding"), None);
+        assert_eq!(res.headers().get_one("Vary"), Some("Accept-Encoding"));
+        let res = client.get("/api/posts").dispatch();
+        assert_eq!(res.headers().get_one("Content-Encoding"), None);
+    }
+
+    #[test]
+    fn preference_respects_quality() {
+        use Encoding::*;
+        let pick = |v: &str| Encoding::accepted_from(std::iter::once(v));
+        assert_eq!(pick("gzip, deflate, br"), vec![Brotli, Gzip]);
+        assert_eq!(pick("gzip, br;q=0"), vec![Gzip]);
+        assert_eq!(pick("*"), vec![Brotli, Gzip]);
+        assert_eq!(pick("identity"), vec![]);
+        assert_eq!(pick(""), vec![]);
+    }
+}
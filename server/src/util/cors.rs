@@ -0,0 +1,232 @@
+//! Cross-origin resource sharing for the API, so that sites on other origins in the allowlist can
+//! call it with the auth cookie.
+//!
+//! Preflights are [rerouted](crate::util::refuse) and answered by the fairing, so that no handler
+//! runs for them.
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{Header, Method, Status},
+    Data, Request, Response, Route,
+};
+
+use crate::util::refuse::{self, Answer};
+use login_enum::challenge;
+
+/// The header carrying the anti-CSRF token. Cross-origin clients need it allowed to send it.
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+/// The request headers the API reads, besides those browsers always allow.
+pub const ALLOWED_HEADERS: [&str; 4] = [
+    "Content-Type",
+    CSRF_HEADER,
+    challenge::TOKEN_HEADER,
+    challenge::NONCE_HEADER,
+];
+/// How long, in seconds, browsers may cache the answer to a preflight.
+const MAX_AGE_SECS: u64 = 10 * 60;
+
+/// Adds CORS headers to responses from the API for origins in the allowlist, and answers the
+/// preflights for them.
+///
+/// Requests without an `Origin`, or from origins not in the allowlist, get no CORS headers, so
+/// same-origin requests behave exactly as they would without the fairing.
+pub struct Cors {
+    /// The origins, such as `https://benxu.dev`, allowed to call the API.
+    allowed_origins: Vec<String>,
+    /// The path the API is mounted at.
+    root: &'static str,
+    /// The methods of the API routes.
+    allowed_methods: Vec<Method>,
+}
+impl Cors {
+    /// Creates the fairing for the `routes` mounted at `root`, allowing only the methods they use.
+    pub fn new(allowed_origins: Vec<String>, root: &'static str, routes: &[Route]) -> Self {
+        let mut allowed_methods: Vec<Method> = vec![];
+        for route in routes {
+            if !allowed_methods.contains(&route.method) {
+                allowed_methods.push(route.method);
+            }
+        }
+        Self {
+            allowed_origins,
+            root,
+            allowed_methods,
+        }
+    }
+    fn is_api(&self, req: &Request) -> bool {
+        let path = req.uri().path();
+        path.starts_with(self.root)
+            && (path.len() == self.root.len() || path[self.root.len()..].starts_with('/'))
+    }
+    /// The origin of the request, if it is in the allowlist.
+    fn allowed_origin<'r>(&self, req: &'r Request) -> Option<&'r str> {
+        req.headers()
+            .get_one("Origin")
+            .filter(|origin| self.allowed_origins.iter().any(|o| o == origin))
+    }
+    /// Whether the method and headers the preflight asks for are allowed.
+    fn allows_preflight(&self, req: &Request) -> bool {
+        let method = req
+            .headers()
+            .get_one("Access-Control-Request-Method")
+            .and_then(|m| m.parse::<Method>().ok());
+        let method_allowed = method.map_or(false, |m| self.allowed_methods.contains(&m));
+        let headers_allowed = req
+            .headers()
+            .get("Access-Control-Request-Headers")
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .all(|header| {
+                ALLOWED_HEADERS
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(header))
+            });
+        method_allowed && headers_allowed
+    }
+    fn answer_preflight(&self, req: &Request) -> Answer {
+        let origin = match self.allowed_origin(req) {
+            Some(origin) if self.allows_preflight(req) => origin,
+            _ => return Answer::new(Status::Forbidden),
+        };
+        let methods: Vec<_> = self.allowed_methods.iter().map(|m| m.as_str()).collect();
+        Answer::new(Status::NoContent)
+            .header("Access-Control-Allow-Origin", origin)
+            .header("Access-Control-Allow-Credentials", "true")
+            .header("Access-Control-Allow-Methods", methods.join(", "))
+            .header("Access-Control-Allow-Headers", ALLOWED_HEADERS.join(", "))
+            .header("Access-Control-Max-Age", MAX_AGE_SECS.to_string())
+            .header("Vary", "Origin")
+    }
+}
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+    fn on_request(&self, req: &mut Request, _: &Data) {
+        let is_preflight = req.method() == Method::Options
+            && self.is_api(req)
+            && req.headers().contains("Origin")
+            && req.headers().contains("Access-Control-Request-Method");
+        if is_preflight {
+            let answer = self.answer_preflight(req);
+            refuse::reroute(req, answer);
+        }
+    }
+    fn on_response(&self, req: &Request, res: &mut Response) {
+        // Rerouted preflights are no longer at the api, and already answered.
+        if !self.is_api(req) || !req.headers().contains("Origin") {
+            return;
+        }
+        res.adjoin_header(Header::new("Vary", "Origin"));
+        if let Some(origin) = self.allowed_origin(req) {
+            res.set_raw_header("Access-Control-Allow-Origin", origin.to_owned());
+            res.set_raw_header("Access-Control-Allow-Credentials", "true");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::refuse::Refusals;
+    use rocket::local::Client;
+
+    const ALLOWED: &str = "https://static.benxu.dev";
+
+    #[get("/posts")]
+    fn list() -> &'static str {
+        "[]"
+    }
+
+    #[patch("/posts/<_id>")]
+    fn edit(_id: String) -> &'static str {
+        "ok"
+    }
+
+    fn client() -> Client {
+        let routes = routes![list, edit];
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(Refusals)
+            .attach(Cors::new(vec![ALLOWED.to_owned()], "/api", &routes))
+            .mount("/api", routes);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    fn allowed_origin_gets_cors_headers() {
+        let client = client();
+        let res = client
+            .get("/api/posts")
+            .header(Header::new("Origin", ALLOWED))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let headers = res.headers();
+        assert_eq!(
+            headers.get_one("Access-Control-Allow-Origin"),
+            Some(ALLOWED)
+        );
+        assert_eq!(
+            headers.get_one("Access-Control-Allow-Credentials"),
+            Some("true")
+        );
+        assert_eq!(headers.get_one("Vary"), Some("Origin"));
+    }
+
+    #[test]
+    fn rejected_origin_gets_no_cors_headers() {
+        let client = client();
+        let mut res = client
+            .get("/api/posts")
+            .header(Header::new("Origin", "https://evil.example"))
+            .dispatch();
+        assert_eq!(res.body_string().as_deref(), Some("[]"));
+        assert!(!res.headers().contains("Access-Control-Allow-Origin"));
+        let res = client
+            .options("/api/posts/1")
+            .header(Header::new("Origin", "https://evil.example"))
+            .header(Header::new("Access-Control-Request-Method", "PATCH"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Forbidden);
+        assert!(!res.headers().contains("Access-Control-Allow-Origin"));
+        let res = client.get("/api/posts").dispatch();
+        assert!(!res.headers().contains("Vary"));
+    }
+
+    #[test]
+    fn preflight_for_patch_with_csrf_header() {
+        let client = client();
+        let res = client
+            .options("/api/posts/1")
+            .header(Header::new("Origin", ALLOWED))
+            .header(Header::new("Access-Control-Request-Method", "PATCH"))
+            .header(Header::new(
+                "Access-Control-Request-Headers",
+                "content-type, x-csrf-token",
+            ))
+            .dispatch();
+        assert_eq!(res.status(), Status::NoContent);
+        let headers = res.headers();
+        assert_eq!(
+            headers.get_one("Access-Control-Allow-Origin"),
+            Some(ALLOWED)
+        );
+        assert_eq!(
+            headers.get_one("Access-Control-Allow-Methods"),
+            Some("GET, PATCH")
+        );
+        assert_eq!(
+            headers.get_one("Access-Control-Allow-Headers"),
+            Some("Content-Type, X-CSRF-Token, X-Challenge-Token, X-Challenge-Nonce")
+        );
+        let res = client
+            .options("/api/posts/1")
+            .header(Header::new("Origin", ALLOWED))
+            .header(Header::new("Access-Control-Request-Method", "PUT"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Forbidden);
+    }
+}
@@ -0,0 +1,15 @@
+//! The language pages rendered by the server are in.
+
+use page_client::i18n::Locale;
+use rocket::request::{FromRequest, Outcome, Request};
+
+/// The locale most preferred by the `Accept-Language` header of the request. Obtainable as a
+/// request guard, which never fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLocale(pub Locale);
+impl<'a, 'r> FromRequest<'a, 'r> for RequestLocale {
+    type Error = ();
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self(Locale::negotiate(req.headers().get("Accept-Language"))))
+    }
+}
@@ -0,0 +1,273 @@
+//! Sends mail, such as password resets and email verifications.
+//!
+//! Handlers hand mail to the [`MailQueue`], which a single background thread drains through a
+//! [`Mailer`], so that responses are not held up by the mail server. The queue is bounded, and mail
+//! that does not fit is refused right away rather than piling up while the mail server is down.
+//!
+//! Mail goes out over SMTP when the [`ServerConfig`](crate::cfg::ServerConfig) says where to, and
+//! is otherwise only logged by the [`DevMailer`], which tests also read sent mail back from.
+
+pub mod templates;
+
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    SmtpTransport, Transport,
+};
+use std::{
+    fmt,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use crate::cfg::SmtpConfig;
+use templates::Mail;
+
+/// How many messages may wait to be sent before more are refused.
+pub const QUEUE_CAPACITY: usize = 64;
+
+/// A message to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// Why a message was not sent.
+#[derive(Debug)]
+pub enum Error {
+    /// An address is not one mail can be sent to or from.
+    Address(String),
+    /// The mail server could not be reached, or refused the message.
+    Transport(String),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Address(e) => write!(f, "invalid address: {}", e),
+            Self::Transport(e) => write!(f, "could not send: {}", e),
+        }
+    }
+}
+
+/// Something that sends mail. Sends block until the message is handed off, so they should only
+/// happen on the thread of the [`MailQueue`].
+pub trait Mailer: Send + 'static {
+    fn send(&self, to: &str, subject: &str, text_body: &str, html_body: &str) -> Result<(), Error>;
+}
+
+impl<M: Mailer + ?Sized> Mailer for Box<M> {
+    fn send(&self, to: &str, subject: &str, text_body: &str, html_body: &str) -> Result<(), Error> {
+        (**self).send(to, subject, text_body, html_body)
+    }
+}
+
+/// Sends mail through an SMTP server, over STARTTLS.
+pub struct Smtp {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+impl Smtp {
+    pub fn new(config: &SmtpConfig) -> Result<Self, Error> {
+        let from = config
+            .from
+            .parse()
+            .map_err(|e: lettre::address::AddressError| Error::Address(e.to_string()))?;
+        let transport = SmtpTransport::starttls_relay(&config.host)
+            .map_err(|e| Error::Transport(e.to_string()))?
+            .port(config.port);
+        let transport = match config.credentials.as_ref() {
+            Some((user_name, password)) => {
+                transport.credentials(Credentials::new(user_name.clone(), password.clone()))
+            }
+            None => transport,
+        };
+        Ok(Self {
+            transport: transport.build(),
+            from,
+        })
+    }
+}
+impl Mailer for Smtp {
+    fn send(&self, to: &str, subject: &str, text_body: &str, html_body: &str) -> Result<(), Error> {
+        let to: Mailbox = to
+            .parse()
+            .map_err(|e: lettre::address::AddressError| Error::Address(e.to_string()))?;
+        let message = lettre::Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(
+                text_body.to_owned(),
+                html_body.to_owned(),
+            ))
+            .map_err(|e| Error::Address(e.to_string()))?;
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|e| Error::Transport(e.to_string()))
+    }
+}
+
+/// Logs mail instead of sending it, and keeps it so that it can be read back. Used when no SMTP
+/// server is configured, and by tests.
+#[derive(Debug, Clone, Default)]
+pub struct DevMailer {
+    sent: Arc<Mutex<Vec<Message>>>,
+}
+impl DevMailer {
+    /// Every message sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Message> {
+        match self.sent.lock() {
+            Ok(sent) => sent.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+impl Mailer for DevMailer {
+    fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        text_body: &str,
+        html_body: &str,
+    ) -> Result<(), Error> {
+        log::info!("Not sending mail to {} about {:?}:\n{}", to, subject, text_body);
+        let message = Message {
+            to: to.to_owned(),
+            subject: subject.to_owned(),
+            text_body: text_body.to_owned(),
+            html_body: html_body.to_owned(),
+        };
+        match self.sent.lock() {
+            Ok(mut sent) => sent.push(message),
+            Err(poisoned) => poisoned.into_inner().push(message),
+        }
+        Ok(())
+    }
+}
+
+/// The mailer the configuration asks for.
+pub fn from_config(config: Option<&SmtpConfig>) -> Result<Box<dyn Mailer>, Error> {
+    match config {
+        Some(config) => Smtp::new(config).map(|smtp| Box::new(smtp) as Box<dyn Mailer>),
+        None => {
+            log::warn!("No SMTP server configured. Mail will only be logged.");
+            Ok(Box::new(DevMailer::default()))
+        }
+    }
+}
+
+/// Why a message was not queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueError {
+    /// [`QUEUE_CAPACITY`] messages are already waiting.
+    Full,
+    /// The thread sending mail has stopped.
+    Stopped,
+}
+
+/// Queues mail for the background thread. Managed by rocket for the handlers sending mail.
+#[derive(Clone)]
+pub struct MailQueue {
+    queue: Arc<Mutex<mpsc::SyncSender<Message>>>,
+}
+impl MailQueue {
+    /// Starts the thread sending mail through `mailer`. It stops once every [`MailQueue`] is
+    /// dropped.
+    pub fn spawn(mailer: impl Mailer) -> Self {
+        let (queue, messages) = mpsc::sync_channel::<Message>(QUEUE_CAPACITY);
+        thread::spawn(move || {
+            for message in messages {
+                let res = mailer.send(
+                    &message.to,
+                    &message.subject,
+                    &message.text_body,
+                    &message.html_body,
+                );
+                if let Err(e) = res {
+                    let (to, subject) = (&message.to, &message.subject);
+                    log::error!("Failed to mail {} about {:?}: {}.", to, subject, e);
+                }
+            }
+        });
+        Self {
+            queue: Arc::new(Mutex::new(queue)),
+        }
+    }
+    /// Queues `mail` to be sent to `to`, without waiting for it to be sent.
+    pub fn enqueue(&self, to: &str, mail: Mail) -> Result<(), QueueError> {
+        let message = Message {
+            to: to.to_owned(),
+            subject: mail.subject,
+            text_body: mail.text_body,
+            html_body: mail.html_body,
+        };
+        let queue = match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        queue.try_send(message).map_err(|e| match e {
+            mpsc::TrySendError::Full(_) => QueueError::Full,
+            mpsc::TrySendError::Disconnected(_) => QueueError::Stopped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use page_client::i18n::Locale;
+    use std::time::{Duration, Instant};
+
+    /// Waits for the queue to hand `count` messages to `mailer`.
+    fn wait_for(mailer: &DevMailer, count: usize) -> Vec<Message> {
+        let started = Instant::now();
+        while mailer.sent().len() < count && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        mailer.sent()
+    }
+
+    #[test]
+    fn queued_mail_reaches_the_mailer() {
+        let mailer = DevMailer::default();
+        let queue = MailQueue::spawn(mailer.clone());
+        let link = "https://benxu.dev/blog/reset?token=abc";
+        let mail = templates::password_reset(Locale::En, "benxu.dev", "ada", link);
+        let subject = mail.subject.clone();
+        queue.enqueue("ada@example.com", mail).unwrap();
+
+        let sent = wait_for(&mailer, 1);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "ada@example.com");
+        assert_eq!(sent[0].subject, subject);
+        assert!(sent[0].text_body.contains(link));
+    }
+
+    /// Holds every send until released, so that the queue can be filled.
+    struct Blocked(Mutex<mpsc::Receiver<()>>);
+    impl Mailer for Blocked {
+        fn send(&self, _: &str, _: &str, _: &str, _: &str) -> Result<(), Error> {
+            let _ = self.0.lock().unwrap().recv();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn full_queues_refuse_mail_right_away() {
+        let (release, held) = mpsc::channel();
+        let queue = MailQueue::spawn(Blocked(Mutex::new(held)));
+        let mail = || {
+            templates::email_verification(Locale::En, "benxu.dev", "ada", "https://benxu.dev")
+        };
+        // One message is held by the mailer, the rest wait in the queue.
+        let refused = (0..QUEUE_CAPACITY + 2)
+            .map(|_| queue.enqueue("ada@example.com", mail()))
+            .find(Result::is_err);
+        assert_eq!(refused, Some(Err(QueueError::Full)));
+        drop(release);
+    }
+}
@@ -0,0 +1,137 @@
+//! The mail the site sends, in the language of the recipient.
+//!
+//! Every mail has a plain text body and an html body saying the same thing. Mail clients ignore
+//! stylesheets, so the html carries its few styles inline rather than sharing the site's css.
+
+use maud::{html, Markup, DOCTYPE};
+use page_client::i18n::{translate, Locale};
+
+/// A mail, ready to be [queued](super::MailQueue::enqueue).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// The paragraphs of a mail, followed by a link to act on, signed with the title of the `site`.
+/// Shared by every template so that mail looks the same whatever it is about.
+fn mail(
+    locale: Locale,
+    site: &str,
+    subject: String,
+    paragraphs: &[String],
+    action: &str,
+    link: &str,
+) -> Mail {
+    let signature = translate(locale, "mail.signature", &[("site", site.to_owned())]);
+    let mut text_body = String::new();
+    for paragraph in paragraphs {
+        text_body.push_str(paragraph);
+        text_body.push_str("\n\n");
+    }
+    text_body.push_str(&format!("{}: {}\n\n{}\n", action, link, signature));
+    let html_body = layout(
+        locale,
+        &subject,
+        html! {
+            @for paragraph in paragraphs {
+                p { (paragraph) }
+            }
+            p {
+                a href=(link) style="color: #0060df; font-weight: bold;" { (action) }
+            }
+            p style="color: #606060; font-size: 0.9em;" { (link) }
+            p { (signature) }
+        },
+    );
+    Mail {
+        subject,
+        text_body,
+        html_body: html_body.into_string(),
+    }
+}
+
+/// Wraps the content of a mail in a page readable by mail clients.
+fn layout(locale: Locale, title: &str, content: Markup) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(locale.tag()) {
+            head {
+                meta charset="utf-8";
+                title { (title) }
+            }
+            body style="font-family: sans-serif; line-height: 1.5; max-width: 40em; margin: 0 auto;" {
+                (content)
+            }
+        }
+    }
+}
+
+/// Sent to a user who asked to reset their password, with the link that lets them.
+pub fn password_reset(locale: Locale, site: &str, user_name: &str, link: &str) -> Mail {
+    let args = [("name", user_name.to_owned())];
+    mail(
+        locale,
+        site,
+        translate(locale, "mail.password_reset.subject", &[]),
+        &[
+            translate(locale, "mail.greeting", &args),
+            translate(locale, "mail.password_reset.body", &[]),
+            translate(locale, "mail.password_reset.ignore", &[]),
+        ],
+        &translate(locale, "mail.password_reset.action", &[]),
+        link,
+    )
+}
+
+/// Sent to a user to confirm that an email address is theirs, with the link that does.
+pub fn email_verification(locale: Locale, site: &str, user_name: &str, link: &str) -> Mail {
+    let args = [("name", user_name.to_owned())];
+    mail(
+        locale,
+        site,
+        translate(locale, "mail.email_verification.subject", &[]),
+        &[
+            translate(locale, "mail.greeting", &args),
+            translate(locale, "mail.email_verification.body", &[]),
+        ],
+        &translate(locale, "mail.email_verification.action", &[]),
+        link,
+    )
+}
+
+/// Sent to moderators when a comment on a post is waiting for them, with a link to it.
+pub fn comment_moderation(locale: Locale, site: &str, post_title: &str, link: &str) -> Mail {
+    let args = [("title", post_title.to_owned())];
+    mail(
+        locale,
+        site,
+        translate(locale, "mail.comment_moderation.subject", &args),
+        &[translate(locale, "mail.comment_moderation.body", &args)],
+        &translate(locale, "mail.comment_moderation.action", &[]),
+        link,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn both_bodies_carry_the_link_and_the_html_is_escaped() {
+        let link = "https://benxu.dev/blog/verify?token=a&b";
+        let mail = email_verification(Locale::Es, "Ben & co", "<ada>", link);
+        assert_eq!(
+            mail.subject,
+            translate(Locale::Es, "mail.email_verification.subject", &[])
+        );
+        assert!(mail.text_body.contains("<ada>"));
+        assert!(mail.text_body.contains(link));
+        assert!(mail.html_body.contains("&lt;ada&gt;"));
+        assert!(mail.text_body.contains("— Ben & co"));
+        assert!(mail.html_body.contains("— Ben &amp; co"));
+        assert!(mail.html_body.contains("token=a&amp;b"));
+        assert!(mail.html_body.contains(r#"lang="es""#));
+    }
+}
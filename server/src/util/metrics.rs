@@ -0,0 +1,321 @@
+//! Counters and histograms describing how the server is doing, rendered in the Prometheus text
+//! format by the `/metrics` route. Requests are counted by the
+//! [`RequestLogger`](crate::util::request_log::RequestLogger), while gauges such as the state of
+//! the database pool are sampled when scraped.
+
+use rocket::{
+    http::{Method, Status},
+    request::{FromRequest, Outcome, Request},
+    State,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use crate::{cfg::ServerConfig, util::response_cache::CacheStats};
+use blog_db::rocket::PoolStats;
+
+/// Upper bounds, in seconds, of the buckets request latencies are sorted into.
+pub const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1., 2.5, 5.];
+/// What requests that matched no route are recorded under, so that made up paths do not each get
+/// their own series.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The requests served by a single route.
+#[derive(Default)]
+struct RouteStats {
+    /// Requests whose latency fell in each of the [`LATENCY_BUCKETS`], but not an earlier one.
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    /// The total latency of every request, in microseconds.
+    sum_micros: AtomicU64,
+}
+impl RouteStats {
+    fn record(&self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Values that are read when scraped rather than counted as they happen.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Sampled {
+    /// The state of the database pool, if the server has one.
+    pub pool: Option<PoolStats>,
+    /// The id of the current generation of token keys, and how long ago it was generated.
+    pub token_key: Option<(u64, Duration)>,
+    /// How often visitors were answered from the
+    /// [`ResponseCache`](crate::util::response_cache::ResponseCache), if the server has one.
+    pub responses: Option<CacheStats>,
+}
+
+/// The metrics of the server. Available as managed state.
+#[derive(Default)]
+pub struct Metrics {
+    routes: RwLock<HashMap<(Method, String), Arc<RouteStats>>>,
+    logins_succeeded: AtomicU64,
+    logins_failed: AtomicU64,
+}
+impl Metrics {
+    /// Records a request served by `route`, which is the route as mounted, such as
+    /// `/api/posts/<id>`, or [`UNMATCHED_ROUTE`].
+    pub fn record_request(&self, method: Method, route: &str, latency: Duration) {
+        let key = (method, route.to_owned());
+        let existing = self
+            .routes
+            .read()
+            .ok()
+            .and_then(|routes| routes.get(&key).cloned());
+        let stats = match existing {
+            Some(stats) => stats,
+            None => match self.routes.write() {
+                Ok(mut routes) => Arc::clone(routes.entry(key).or_default()),
+                Err(_) => {
+                    log::error!(
+                        "Request metrics poisoned. Not recording {} {}.",
+                        method,
+                        route
+                    );
+                    return;
+                }
+            },
+        };
+        stats.record(latency);
+    }
+    /// Records an attempt at logging in.
+    pub fn record_login(&self, succeeded: bool) {
+        let counter = if succeeded {
+            &self.logins_succeeded
+        } else {
+            &self.logins_failed
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders every metric in the Prometheus text format.
+    pub fn render(&self, sampled: Sampled) -> String {
+        let mut out = String::new();
+        self.render_requests(&mut out);
+        out.push_str("# HELP benxu_logins_total Attempts at logging in, by outcome.\n");
+        out.push_str("# TYPE benxu_logins_total counter\n");
+        for (outcome, counter) in [
+            ("success", &self.logins_succeeded),
+            ("failure", &self.logins_failed),
+        ]
+        .iter()
+        {
+            let _ = writeln!(
+                out,
+                "benxu_logins_total{{outcome=\"{}\"}} {}",
+                outcome,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+        if let Some(pool) = sampled.pool {
+            gauge(
+                &mut out,
+                "benxu_db_connections_in_use",
+                "Database connections checked out.",
+                pool.in_use,
+            );
+            gauge(
+                &mut out,
+                "benxu_db_connections_idle",
+                "Database connections waiting to be checked out.",
+                pool.idle,
+            );
+            if let Some(wait) = pool.wait {
+                gauge(
+                    &mut out,
+                    "benxu_db_checkout_wait_seconds",
+                    "How long checking out a database connection took when scraped.",
+                    wait.as_secs_f64(),
+                );
+            }
+        }
+        if let Some(responses) = sampled.responses {
+            out.push_str("# HELP benxu_response_cache_total Response cache lookups, by outcome.\n");
+            out.push_str("# TYPE benxu_response_cache_total counter\n");
+            for (outcome, count) in [("hit", responses.hits), ("miss", responses.misses)].iter() {
+                let _ = writeln!(
+                    out,
+                    "benxu_response_cache_total{{outcome=\"{}\"}} {}",
+                    outcome, count
+                );
+            }
+        }
+        if let Some((generation, age)) = sampled.token_key {
+            gauge(
+                &mut out,
+                "benxu_token_key_generation",
+                "The current generation of token keys.",
+                generation,
+            );
+            gauge(
+                &mut out,
+                "benxu_token_key_age_seconds",
+                "How long ago the current generation of token keys was generated.",
+                age.as_secs_f64(),
+            );
+        }
+        out
+    }
+    fn render_requests(&self, out: &mut String) {
+        out.push_str("# HELP benxu_request_duration_seconds How long requests took, by route.\n");
+        out.push_str("# TYPE benxu_request_duration_seconds histogram\n");
+        let routes = match self.routes.read() {
+            Ok(routes) => routes,
+            Err(_) => {
+                log::error!("Request metrics poisoned. Leaving them out.");
+                return;
+            }
+        };
+        let mut sorted: Vec<_> = routes.iter().collect();
+        sorted.sort_by(|((a_method, a_route), _), ((b_method, b_route), _)| {
+            (a_route, a_method.as_str()).cmp(&(b_route, b_method.as_str()))
+        });
+        for ((method, route), stats) in sorted {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, escape(route));
+            let mut cumulative = 0;
+            for (bound, bucket) in LATENCY_BUCKETS.iter().zip(stats.buckets.iter()) {
+                cumulative += bucket.load(Ordering::Relaxed);
+                let _ = writeln!(
+                    out,
+                    "benxu_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                );
+            }
+            let count = stats.count.load(Ordering::Relaxed);
+            let sum = stats.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+            let _ = writeln!(
+                out,
+                "benxu_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, count
+            );
+            let _ = writeln!(out, "benxu_request_duration_seconds_sum{{{}}} {}", labels, sum);
+            let _ = writeln!(out, "benxu_request_duration_seconds_count{{{}}} {}", labels, count);
+        }
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Permission to scrape the metrics. If a metrics token is configured, requests must carry it as
+/// a bearer token. Otherwise only requests from the machine itself are let through, which counts
+/// requests forwarded by a reverse proxy on the same machine, so the proxy should not forward
+/// `/metrics`.
+pub struct MetricsAccess(());
+impl<'a, 'r> FromRequest<'a, 'r> for MetricsAccess {
+    type Error = ();
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        let config = req.guard::<State<ServerConfig>>()?;
+        match config.metrics_token.as_ref() {
+            Some(token) => {
+                let sent = req
+                    .headers()
+                    .get_one("Authorization")
+                    .and_then(|auth| auth.strip_prefix("Bearer "));
+                match sent {
+                    Some(sent) if crypto::algo::ct_eq(sent.as_bytes(), token.as_bytes()) => {
+                        Outcome::Success(Self(()))
+                    }
+                    _ => Outcome::Failure((Status::Unauthorized, ())),
+                }
+            }
+            // The remote address rather than the client ip, since the latter trusts headers the
+            // client sets.
+            None => match req.remote().map(|addr| addr.ip()) {
+                Some(ip) if is_loopback(ip) => Outcome::Success(Self(())),
+                _ => Outcome::Failure((Status::NotFound, ())),
+            },
+        }
+    }
+}
+
+fn is_loopback(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback(),
+        IpAddr::V6(ip) => ip.is_loopback() || ip.to_ipv4().map_or(false, |ip| ip.is_loopback()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn latencies_fill_cumulative_buckets() {
+        let metrics = Metrics::default();
+        metrics.record_request(Method::Get, "/api/posts", Duration::from_millis(3));
+        metrics.record_request(Method::Get, "/api/posts", Duration::from_millis(30));
+        metrics.record_request(Method::Get, "/api/posts", Duration::from_secs(10));
+        let out = metrics.render(Sampled::default());
+        let labels = "method=\"GET\",route=\"/api/posts\"";
+        for (le, count) in &[("0.005", 1), ("0.025", 1), ("0.05", 2), ("5", 2), ("+Inf", 3)] {
+            let line = format!(
+                "benxu_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                labels, le, count
+            );
+            assert!(out.contains(&line), "Missing `{}` in:\n{}", line, out);
+        }
+        let count = format!("benxu_request_duration_seconds_count{{{}}} 3", labels);
+        assert!(out.contains(&count));
+    }
+
+    #[test]
+    fn logins_are_counted_by_outcome() {
+        let metrics = Metrics::default();
+        metrics.record_login(true);
+        metrics.record_login(false);
+        metrics.record_login(false);
+        let out = metrics.render(Sampled::default());
+        assert!(out.contains("benxu_logins_total{outcome=\"success\"} 1"));
+        assert!(out.contains("benxu_logins_total{outcome=\"failure\"} 2"));
+    }
+
+    #[test]
+    fn sampled_gauges_are_rendered_when_present() {
+        let metrics = Metrics::default();
+        assert!(!metrics.render(Sampled::default()).contains("benxu_db_"));
+        let out = metrics.render(Sampled {
+            pool: Some(PoolStats {
+                in_use: 2,
+                idle: 3,
+                wait: Some(Duration::from_millis(250)),
+            }),
+            token_key: Some((4, Duration::from_secs(90))),
+            responses: Some(CacheStats { hits: 5, misses: 1 }),
+        });
+        assert!(out.contains("benxu_db_connections_in_use 2\n"));
+        assert!(out.contains("benxu_db_connections_idle 3\n"));
+        assert!(out.contains("benxu_db_checkout_wait_seconds 0.25\n"));
+        assert!(out.contains("benxu_token_key_generation 4\n"));
+        assert!(out.contains("benxu_token_key_age_seconds 90\n"));
+        assert!(out.contains("benxu_response_cache_total{outcome=\"hit\"} 5\n"));
+        assert!(out.contains("benxu_response_cache_total{outcome=\"miss\"} 1\n"));
+    }
+}
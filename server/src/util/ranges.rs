@@ -0,0 +1,172 @@
+//! Serves parts of files, as asked for with the `Range` header, so that audio and video can be
+//! seeked through without downloading them whole.
+//!
+//! Only a single range of bytes is served. Asking for several ranges at once, or for a range that
+//! cannot be read, is answered with a 416, as is a range in any other unit.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// What a `Range` header asked for of a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requested {
+    /// The whole resource, since no range was asked for.
+    Whole,
+    /// The bytes from `start` through `end`, inclusive, which are within the resource.
+    Part { start: u64, end: u64 },
+    /// A range that is malformed, not in bytes, one of several, or outside the resource.
+    Unsatisfiable,
+}
+impl Requested {
+    /// What `header` asks for of a resource `len` bytes long.
+    pub fn parse(header: Option<&str>, len: u64) -> Self {
+        let spec = match header {
+            None => return Self::Whole,
+            Some(header) => match header.trim().strip_prefix("bytes=") {
+                Some(spec) if !spec.contains(',') => spec.trim(),
+                _ => return Self::Unsatisfiable,
+            },
+        };
+        let (first, last) = match spec.find('-') {
+            Some(at) => (&spec[..at], &spec[at + 1..]),
+            None => return Self::Unsatisfiable,
+        };
+        let number = |digits: &str| {
+            Some(digits)
+                .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|digits| digits.parse::<u64>().ok())
+        };
+        let (start, end) = match (number(first), last) {
+            // The last bytes of the resource.
+            (None, suffix) if first.is_empty() => match number(suffix) {
+                Some(suffix) if suffix > 0 && len > 0 => (len.saturating_sub(suffix), len - 1),
+                _ => return Self::Unsatisfiable,
+            },
+            (Some(start), "") => (start, len.saturating_sub(1)),
+            (Some(start), last) => match number(last) {
+                Some(last) if last >= start => (start, last.min(len.saturating_sub(1))),
+                _ => return Self::Unsatisfiable,
+            },
+            (None, _) => return Self::Unsatisfiable,
+        };
+        if start >= len {
+            Self::Unsatisfiable
+        } else {
+            Self::Part { start, end }
+        }
+    }
+}
+
+/// The `Content-Range` value for the bytes from `start` through `end` of a resource `len` bytes
+/// long, or without a part, for a range of it that could not be served.
+pub fn content_range(part: Option<(u64, u64)>, len: u64) -> String {
+    match part {
+        Some((start, end)) => format!("bytes {}-{}/{}", start, end, len),
+        None => format!("bytes */{}", len),
+    }
+}
+
+/// Part of a file, which reads and seeks as if it were the whole of it, so that it can be served
+/// as a sized body.
+pub struct Window<F> {
+    inner: F,
+    start: u64,
+    len: u64,
+    /// Where in the window the next read starts.
+    pos: u64,
+}
+impl<F: Seek> Window<F> {
+    /// The bytes from `start` through `end` of `inner`, inclusive.
+    pub fn new(mut inner: F, start: u64, end: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(start))?;
+        Ok(Self {
+            inner,
+            start,
+            len: end - start + 1,
+            pos: 0,
+        })
+    }
+}
+impl<F: Read> Read for Window<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.len.saturating_sub(self.pos);
+        let max = buf.len().min(left as usize);
+        let read = self.inner.read(&mut buf[..max])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+impl<F: Seek> Seek for Window<F> {
+    fn seek(&mut self, to: SeekFrom) -> io::Result<u64> {
+        let pos = match to {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => offset_by(self.len, offset),
+            SeekFrom::Current(offset) => offset_by(self.pos, offset),
+        };
+        let pos = pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Seeked before the start of the window.")
+        })?;
+        self.inner.seek(SeekFrom::Start(self.start + pos))?;
+        self.pos = pos;
+        Ok(pos)
+    }
+}
+
+fn offset_by(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use Requested::*;
+
+    #[test]
+    fn single_ranges_are_clamped_to_the_resource() {
+        assert_eq!(Requested::parse(None, 10), Whole);
+        assert_eq!(Requested::parse(Some("bytes=0-0"), 10), Part { start: 0, end: 0 });
+        assert_eq!(Requested::parse(Some("bytes=2-"), 10), Part { start: 2, end: 9 });
+        assert_eq!(Requested::parse(Some("bytes=2-400"), 10), Part { start: 2, end: 9 });
+        assert_eq!(Requested::parse(Some("bytes=-3"), 10), Part { start: 7, end: 9 });
+        assert_eq!(Requested::parse(Some("bytes=-30"), 10), Part { start: 0, end: 9 });
+    }
+
+    #[test]
+    fn other_ranges_are_unsatisfiable() {
+        for header in &[
+            "bytes=10-",
+            "bytes=10-12",
+            "bytes=5-4",
+            "bytes=-0",
+            "bytes=0-1,4-5",
+            "bytes=a-b",
+            "bytes=-",
+            "bytes=+1-2",
+            "items=0-1",
+            "bytes 0-1",
+        ] {
+            assert_eq!(Requested::parse(Some(header), 10), Unsatisfiable, "{}", header);
+        }
+        assert_eq!(Requested::parse(Some("bytes=-1"), 0), Unsatisfiable);
+        assert_eq!(Requested::parse(Some("bytes=0-"), 0), Unsatisfiable);
+    }
+
+    #[test]
+    fn windows_read_and_seek_within_their_part() {
+        let file = io::Cursor::new(b"0123456789".to_vec());
+        let mut window = Window::new(file, 2, 5).unwrap();
+        assert_eq!(window.seek(SeekFrom::End(0)).unwrap(), 4);
+        window.seek(SeekFrom::Start(0)).unwrap();
+        let mut read = String::new();
+        window.read_to_string(&mut read).unwrap();
+        assert_eq!(read, "2345");
+        window.seek(SeekFrom::Current(-1)).unwrap();
+        read.clear();
+        window.read_to_string(&mut read).unwrap();
+        assert_eq!(read, "5");
+        assert!(window.seek(SeekFrom::Current(-5)).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Lets fairings answer requests themselves, such as to refuse them.
+//!
+//! Rocket 0.4 fairings cannot respond to requests, so a fairing [reroutes](reroute) the request to
+//! a path no handler is mounted at, so that no handlers run, along with the [`Answer`] it should
+//! get. The [`Refusals`] fairing then swaps the response to the unmounted path for that answer.
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{uri::Origin, ContentType, Header, Status},
+    Request, Response,
+};
+use std::io::Cursor;
+
+/// A path no handler is mounted at, where rerouted requests are sent.
+const REROUTED_PATH: &str = "/__refused";
+
+/// The response a rerouted request gets.
+#[derive(Debug, Clone)]
+pub struct Answer {
+    status: Status,
+    headers: Vec<Header<'static>>,
+    body: Option<(ContentType, String)>,
+}
+impl Answer {
+    /// An answer with `status`, and no body.
+    pub fn new(status: Status) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: None,
+        }
+    }
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push(Header::new(name, value.into()));
+        self
+    }
+    pub fn body(mut self, content_type: ContentType, body: impl Into<String>) -> Self {
+        self.body = Some((content_type, body.into()));
+        self
+    }
+}
+
+/// The answer a request was rerouted with, if it was.
+struct Rerouted(Option<Answer>);
+
+/// Answers `req` with `answer` rather than letting a handler run. Only the first fairing to
+/// reroute a request is heard, and later ones see it at the unmounted path.
+pub fn reroute(req: &mut Request, answer: Answer) {
+    req.local_cache(move || Rerouted(Some(answer)));
+    req.set_uri(Origin::parse(REROUTED_PATH).expect("A valid path."));
+}
+
+/// Answers the requests rerouted by other fairings. Attached before every fairing that changes
+/// responses, such as [`Compression`](crate::util::compression::Compression), so that they see the
+/// answer rather than the response to the unmounted path.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Refusals;
+
+impl Fairing for Refusals {
+    fn info(&self) -> Info {
+        Info {
+            name: "Refusals",
+            kind: Kind::Response,
+        }
+    }
+    fn on_response(&self, req: &Request, res: &mut Response) {
+        let answer = match req.local_cache(|| Rerouted(None)) {
+            Rerouted(Some(answer)) => answer,
+            Rerouted(None) => return,
+        };
+        res.set_status(answer.status);
+        for header in &answer.headers {
+            res.set_header(header.clone());
+        }
+        match &answer.body {
+            Some((content_type, body)) => {
+                res.set_header(content_type.clone());
+                res.set_sized_body(Cursor::new(body.clone()));
+            }
+            None => {
+                res.take_body();
+                res.remove_header("Content-Type");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rocket::{fairing::AdHoc, local::Client};
+
+    #[get("/")]
+    fn index() -> &'static str {
+        "ok"
+    }
+
+    #[test]
+    fn rerouted_requests_get_the_first_answer() {
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(Refusals)
+            .attach(AdHoc::on_request("Teapot", |req, _| {
+                if req.headers().contains("X-Refuse") {
+                    let answer = Answer::new(Status::ImATeapot)
+                        .header("Retry-After", "5")
+                        .body(ContentType::Plain, "Short and stout.");
+                    reroute(req, answer);
+                }
+            }))
+            .attach(AdHoc::on_request("Gone", |req, _| {
+                if req.headers().contains("X-Refuse") {
+                    reroute(req, Answer::new(Status::Gone));
+                }
+            }))
+            .mount("/", routes![index]);
+        let client = Client::new(rocket).expect("A valid rocket instance.");
+        let mut res = client.get("/").dispatch();
+        assert_eq!(res.body_string().as_deref(), Some("ok"));
+
+        let mut res = client.get("/").header(Header::new("X-Refuse", "1")).dispatch();
+        assert_eq!(res.status(), Status::ImATeapot);
+        assert_eq!(res.content_type(), Some(ContentType::Plain));
+        assert_eq!(res.headers().get_one("Retry-After"), Some("5"));
+        assert_eq!(res.body_string().as_deref(), Some("Short and stout."));
+    }
+}
@@ -0,0 +1,156 @@
+//! Keeps the rendered bodies of posts, since highlighting their code is comparatively expensive.
+//!
+//! Entries are keyed by post and stamped with when the post last changed, so that an edit is
+//! picked up the next time the post is read, without the cache having to be told about it. Once
+//! [`CAPACITY`] posts are kept, the one read least recently makes room for the next.
+
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use blog_db::models::posts;
+use page_client::markdown::{render_markdown, Rendered};
+
+/// How many rendered posts are kept.
+pub const CAPACITY: usize = 256;
+
+struct Entry {
+    updated_at: DateTime<Utc>,
+    rendered: Arc<Rendered>,
+    /// When the entry was last read, counted in reads of the cache.
+    read_at: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_post: HashMap<uuid::Uuid, Entry>,
+    reads: u64,
+}
+
+/// The rendered bodies of recently read posts. Managed by rocket for the handlers answering with
+/// rendered posts.
+#[derive(Default)]
+pub struct RenderCache {
+    entries: Mutex<Entries>,
+}
+impl RenderCache {
+    fn lock(&self) -> std::sync::MutexGuard<Entries> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+    /// The body of `post` rendered, from the cache unless the post changed since it was last
+    /// rendered.
+    pub fn render(&self, post: &posts::Data) -> Arc<Rendered> {
+        {
+            let mut entries = self.lock();
+            entries.reads += 1;
+            let reads = entries.reads;
+            if let Some(entry) = entries.by_post.get_mut(&post.id) {
+                if entry.updated_at == post.updated_at {
+                    entry.read_at = reads;
+                    return Arc::clone(&entry.rendered);
+                }
+            }
+        }
+        // Rendered without holding the lock, so that other posts can be read meanwhile.
+        let rendered = Arc::new(render_markdown(&post.body));
+        let mut entries = self.lock();
+        let full = entries.by_post.len() >= CAPACITY && !entries.by_post.contains_key(&post.id);
+        if full {
+            let least_recent = entries
+                .by_post
+                .iter()
+                .min_by_key(|(_, entry)| entry.read_at)
+                .map(|(id, _)| *id);
+            if let Some(id) = least_recent {
+                entries.by_post.remove(&id);
+            }
+        }
+        let read_at = entries.reads;
+        entries.by_post.insert(
+            post.id,
+            Entry {
+                updated_at: post.updated_at,
+                rendered: Arc::clone(&rendered),
+                read_at,
+            },
+        );
+        rendered
+    }
+    /// The body of `post` rendered, as answered to clients.
+    pub fn rendered(&self, post: &posts::Data) -> posts::Rendered {
+        let rendered = self.render(post);
+        let outline = rendered
+            .outline
+            .iter()
+            .map(|heading| posts::Heading {
+                level: heading.level,
+                text: heading.text.clone(),
+                id: heading.id.clone(),
+            })
+            .collect();
+        posts::Rendered {
+            html: rendered.html.clone(),
+            outline,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn post(id: u128, body: &str, updated_at: DateTime<Utc>) -> posts::Data {
+        posts::Data {
+            id: uuid::Uuid::from_u128(id),
+            created_at: updated_at,
+            created_by: uuid::Uuid::nil(),
+            updated_at,
+            updated_by: None,
+            published_at: None,
+            published_by: None,
+            archived_at: None,
+            archived_by: None,
+            deleted_at: None,
+            deleted_by: None,
+            title: "post".to_owned(),
+            body: body.to_owned(),
+            slug: None,
+            meta_description: String::new(),
+            social_image: String::new(),
+        }
+    }
+
+    #[test]
+    fn posts_are_rendered_again_once_they_change() {
+        let cache = RenderCache::default();
+        let then = Utc::now();
+        let first = cache.render(&post(1, "# Before", then));
+        let again = cache.render(&post(1, "# Ignored", then));
+        assert!(Arc::ptr_eq(&first, &again));
+        let edited = cache.render(&post(1, "# After", then + chrono::Duration::seconds(1)));
+        assert_eq!(edited.outline[0].id, "after");
+    }
+
+    #[test]
+    fn the_least_recently_read_post_makes_room() {
+        let cache = RenderCache::default();
+        let now = Utc::now();
+        for id in 0..CAPACITY as u128 {
+            cache.render(&post(id, "body", now));
+        }
+        // Reading the first post again leaves the second as the least recently read.
+        let first = cache.render(&post(0, "body", now));
+        cache.render(&post(CAPACITY as u128, "body", now));
+        let entries = cache.lock();
+        assert_eq!(entries.by_post.len(), CAPACITY);
+        assert!(entries.by_post.contains_key(&uuid::Uuid::from_u128(0)));
+        assert!(!entries.by_post.contains_key(&uuid::Uuid::from_u128(1)));
+        drop(entries);
+        assert!(Arc::ptr_eq(&first, &cache.render(&post(0, "body", now))));
+    }
+}
@@ -0,0 +1,166 @@
+//! A fairing for logging every request as a single structured line, along with the request id
+//! that ties the line to whatever the handlers log while serving it.
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    request::{FromRequest, Outcome, Request},
+    Data, Response, State,
+};
+use std::{fmt, time::Instant};
+
+use crate::util::metrics::{self, Metrics};
+
+/// The response header echoing the request id back to the client.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// An id unique to a single request. Obtainable as a request guard so that handlers can include
+/// it in their own log messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestId(pub uuid::Uuid);
+impl RequestId {
+    fn generate() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+    /// Gets the id of the request, assigning one if the [`RequestLogger`] has not already.
+    pub fn of(req: &Request) -> Self {
+        *req.local_cache(Self::generate)
+    }
+}
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl<'a, 'r> FromRequest<'a, 'r> for RequestId {
+    type Error = ();
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self::of(req))
+    }
+}
+
+/// When the request was first seen by the [`RequestLogger`].
+struct Started(Instant);
+
+/// The user the request was authenticated as, if any.
+struct ResolvedUser(Option<uuid::Uuid>);
+
+/// Records the user a request was authenticated as. Called once the credential of the request has
+/// been resolved; only the first call for a request has any effect.
+pub fn record_user(req: &Request, user_id: uuid::Uuid) {
+    req.local_cache(|| ResolvedUser(Some(user_id)));
+}
+
+/// Logs each request on completion and attaches the [`REQUEST_ID_HEADER`] to the response. The
+/// request is also recorded in the [`Metrics`], if they are managed.
+pub struct RequestLogger {
+    /// Whether to log the line as JSON instead of plain text.
+    pub json: bool,
+}
+impl RequestLogger {
+    fn line(
+        &self,
+        id: RequestId,
+        req: &Request,
+        status: u16,
+        latency_ms: f64,
+        user: Option<uuid::Uuid>,
+    ) -> String {
+        if self.json {
+            serde_json::json!({
+                "request_id": id.0,
+                "method": req.method().as_str(),
+                "path": req.uri().path(),
+                "status": status,
+                "latency_ms": latency_ms,
+                "user_id": user,
+            })
+            .to_string()
+        } else {
+            let user = user.map_or_else(|| "-".to_owned(), |u| u.to_string());
+            format!(
+                "{} {} {} {} {:.3}ms user={}",
+                id,
+                req.method(),
+                req.uri().path(),
+                status,
+                latency_ms,
+                user,
+            )
+        }
+    }
+}
+impl Fairing for RequestLogger {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request logger",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+    fn on_request(&self, req: &mut Request, _: &Data) {
+        req.local_cache(|| Started(Instant::now()));
+        RequestId::of(req);
+    }
+    fn on_response(&self, req: &Request, res: &mut Response) {
+        let id = RequestId::of(req);
+        let started = req.local_cache(|| Started(Instant::now())).0;
+        let latency = started.elapsed();
+        let latency_ms = latency.as_secs_f64() * 1000.;
+        let user = req.local_cache(|| ResolvedUser(None)).0;
+        res.set_raw_header(REQUEST_ID_HEADER, id.to_string());
+        log::info!(
+            target: "request",
+            "{}",
+            self.line(id, req, res.status().code, latency_ms, user)
+        );
+        if let Outcome::Success(metrics) = req.guard::<State<Metrics>>() {
+            let route = req
+                .route()
+                .map_or(metrics::UNMATCHED_ROUTE, |route| route.uri.path());
+            metrics.record_request(req.method(), route, latency);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rocket::local::Client;
+
+    #[get("/id")]
+    fn id(id: RequestId) -> String {
+        id.to_string()
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(RequestLogger { json: true })
+            .mount("/", routes![id]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    fn request_id_header_matches_handler_id() {
+        let client = client();
+        let mut res = client.get("/id").dispatch();
+        let header = res
+            .headers()
+            .get_one(REQUEST_ID_HEADER)
+            .map(str::to_owned)
+            .expect("The request id header to be present.");
+        assert!(uuid::Uuid::parse_str(&header).is_ok());
+        assert_eq!(res.body_string(), Some(header));
+    }
+
+    #[test]
+    fn request_ids_differ_between_requests() {
+        let client = client();
+        let first = client.get("/id").dispatch();
+        let second = client.get("/missing").dispatch();
+        let header = |res: &rocket::local::LocalResponse| {
+            res.headers().get_one(REQUEST_ID_HEADER).map(str::to_owned)
+        };
+        assert!(header(&first).is_some());
+        assert!(header(&second).is_some());
+        assert_ne!(header(&first), header(&second));
+    }
+}
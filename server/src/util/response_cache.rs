@@ -0,0 +1,254 @@
+//! Keeps what posts and listings were answered with to visitors who are not logged in, since they
+//! all see the same thing, and rendering it for each of them takes a few queries. Responses are
+//! kept serialized as JSON, which for the pages of the web app is what the page was rendered from,
+//! since the page itself differs between requests.
+//!
+//! Entries expire after a while, and are dropped early when a post they show changes. Whoever
+//! changes a post calls [`ResponseCache::invalidate_post`], which drops the responses showing it,
+//! every listing, and every response to a post that was not found, since the change may be what
+//! makes it found. Reactions change too often for that, so they go through
+//! [`ResponseCache::invalidate_shown_post`], which only drops the responses showing the post, and
+//! leaves listings showing stale totals until they expire. Changes to anything else a post is shown
+//! with, such as its series, go through [`ResponseCache::clear`]. Once the cache is full, expired
+//! entries make room first, and then the one read least recently.
+//!
+//! Responses to those logged in are never kept, since drafts are shown to some of them.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+/// How many responses are kept.
+pub const CAPACITY: usize = 512;
+/// How long a response is kept for at most.
+pub const TTL: Duration = Duration::from_secs(60);
+
+/// What a response shows, so that changing a post only drops the responses it is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shows {
+    /// A page of posts, which any post may come to be in.
+    Listing,
+    /// The post with the id.
+    Post(uuid::Uuid),
+    /// No post, since the one asked for could not be seen.
+    Nothing,
+}
+
+/// How often the cache had what was asked for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct Entry {
+    shows: Shows,
+    json: String,
+    expires_at: Instant,
+    /// When the entry was last read, counted in reads of the cache.
+    read_at: u64,
+}
+
+struct Entries {
+    by_key: HashMap<String, Entry>,
+    reads: u64,
+}
+
+/// Responses recently answered to visitors, by the route and the normalized query they answered.
+/// Managed by rocket for the handlers answering visitors, and for those changing what they see.
+pub struct ResponseCache {
+    entries: Mutex<Entries>,
+    ttl: Duration,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(TTL, CAPACITY)
+    }
+}
+impl ResponseCache {
+    /// A cache keeping up to `capacity` responses, for up to `ttl` each.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                by_key: HashMap::new(),
+                reads: 0,
+            }),
+            ttl,
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+    fn lock(&self) -> MutexGuard<Entries> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+    /// The response kept for `key`, unless it expired.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.get_at(key, Instant::now())
+    }
+    fn get_at(&self, key: &str, now: Instant) -> Option<String> {
+        let mut entries = self.lock();
+        entries.reads += 1;
+        let reads = entries.reads;
+        let found = match entries.by_key.get_mut(key) {
+            Some(entry) if entry.expires_at > now => {
+                entry.read_at = reads;
+                Some(entry.json.clone())
+            }
+            Some(_) => {
+                entries.by_key.remove(key);
+                None
+            }
+            None => None,
+        };
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Keeps `json` as the response for `key`, which `shows` what it says.
+    pub fn insert(&self, key: String, shows: Shows, json: String) {
+        self.insert_at(key, shows, json, Instant::now())
+    }
+    fn insert_at(&self, key: String, shows: Shows, json: String, now: Instant) {
+        let mut entries = self.lock();
+        if entries.by_key.len() >= self.capacity && !entries.by_key.contains_key(&key) {
+            entries.by_key.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.by_key.len() >= self.capacity && !entries.by_key.contains_key(&key) {
+            let least_recent = entries
+                .by_key
+                .iter()
+                .min_by_key(|(_, entry)| entry.read_at)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = least_recent {
+                entries.by_key.remove(&key);
+            }
+        }
+        // Counted as a read, so that of two responses never read, the older makes room first.
+        entries.reads += 1;
+        let read_at = entries.reads;
+        entries.by_key.insert(
+            key,
+            Entry {
+                shows,
+                json,
+                expires_at: now + self.ttl,
+                read_at,
+            },
+        );
+    }
+
+    /// Drops the responses that showed the post with the id `post`, or may show it now. Called
+    /// whenever a post is created or changed.
+    pub fn invalidate_post(&self, post: uuid::Uuid) {
+        self.lock().by_key.retain(|_, entry| match entry.shows {
+            Shows::Post(shown) => shown != post,
+            Shows::Listing | Shows::Nothing => false,
+        });
+    }
+    /// Drops only the responses showing the post with the id `post`. Called when what changed
+    /// would not make anything else show the post, such as its reactions, which anyone can change.
+    pub fn invalidate_shown_post(&self, post: uuid::Uuid) {
+        self.lock()
+            .by_key
+            .retain(|_, entry| entry.shows != Shows::Post(post));
+    }
+    /// Drops every response.
+    pub fn clear(&self) {
+        self.lock().by_key.clear();
+    }
+
+    /// How often the cache had what was asked for so far.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn post(id: u128) -> Shows {
+        Shows::Post(uuid::Uuid::from_u128(id))
+    }
+
+    #[test]
+    fn responses_expire() {
+        let cache = ResponseCache::new(Duration::from_secs(10), 4);
+        let now = Instant::now();
+        cache.insert_at("/posts/a".to_owned(), post(1), "1".to_owned(), now);
+        assert_eq!(cache.get_at("/posts/a", now + Duration::from_secs(9)), Some("1".to_owned()));
+        assert_eq!(cache.get_at("/posts/a", now + Duration::from_secs(10)), None);
+        assert_eq!(cache.get_at("/posts/a", now), None);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2 });
+    }
+
+    #[test]
+    fn changing_a_post_drops_what_may_show_it() {
+        let cache = ResponseCache::default();
+        cache.insert("/posts/a".to_owned(), post(1), "1".to_owned());
+        cache.insert("/posts/b".to_owned(), post(2), "2".to_owned());
+        cache.insert("/posts?offset=0&lim=10".to_owned(), Shows::Listing, "3".to_owned());
+        cache.insert("/posts/draft".to_owned(), Shows::Nothing, "4".to_owned());
+        cache.invalidate_post(uuid::Uuid::from_u128(1));
+        assert_eq!(cache.get("/posts/a"), None);
+        assert_eq!(cache.get("/posts/b"), Some("2".to_owned()));
+        assert_eq!(cache.get("/posts?offset=0&lim=10"), None);
+        assert_eq!(cache.get("/posts/draft"), None);
+        cache.clear();
+        assert_eq!(cache.get("/posts/b"), None);
+    }
+
+    #[test]
+    fn reacting_to_a_post_only_drops_its_own_responses() {
+        let cache = ResponseCache::default();
+        cache.insert("/posts/a".to_owned(), post(1), "1".to_owned());
+        cache.insert("/posts/b".to_owned(), post(2), "2".to_owned());
+        cache.insert("/posts?offset=0&lim=10".to_owned(), Shows::Listing, "3".to_owned());
+        cache.insert("/posts/draft".to_owned(), Shows::Nothing, "4".to_owned());
+        cache.invalidate_shown_post(uuid::Uuid::from_u128(1));
+        assert_eq!(cache.get("/posts/a"), None);
+        assert_eq!(cache.get("/posts/b"), Some("2".to_owned()));
+        assert_eq!(cache.get("/posts?offset=0&lim=10"), Some("3".to_owned()));
+        assert_eq!(cache.get("/posts/draft"), Some("4".to_owned()));
+    }
+
+    #[test]
+    fn expired_then_least_recently_read_responses_make_room() {
+        let cache = ResponseCache::new(Duration::from_secs(10), 3);
+        let then = Instant::now();
+        cache.insert_at("old".to_owned(), post(0), "0".to_owned(), then);
+        let now = then + Duration::from_secs(20);
+        cache.insert_at("a".to_owned(), post(1), "1".to_owned(), now);
+        cache.insert_at("b".to_owned(), post(2), "2".to_owned(), now);
+        cache.insert_at("c".to_owned(), post(3), "3".to_owned(), now);
+        assert_eq!(cache.lock().by_key.len(), 3);
+        assert!(!cache.lock().by_key.contains_key("old"));
+
+        // Reading the first again leaves the second as the least recently read.
+        assert_eq!(cache.get_at("a", now), Some("1".to_owned()));
+        cache.insert_at("d".to_owned(), post(4), "4".to_owned(), now);
+        assert_eq!(cache.get_at("b", now), None);
+        assert_eq!(cache.get_at("a", now), Some("1".to_owned()));
+        assert_eq!(cache.get_at("d", now), Some("4".to_owned()));
+    }
+}
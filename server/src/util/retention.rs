@@ -0,0 +1,245 @@
+//! Removes what the site keeps no longer, so that it does not pile up forever: posts deleted long
+//! enough ago, which can no longer be restored, the dead letters of webhooks, and capability grants
+//! past their expiry.
+//!
+//! A single background thread runs the job shortly after startup, then once a day, and whenever an
+//! admin asks for a run through [`Retention::trigger`]. Each kind of record is deleted in batches
+//! of [`BATCH`], each in its own statement, so that a large backlog neither holds locks for long
+//! nor runs into the statement timeout. When each kind was last cleared is kept for the admin
+//! stats.
+
+use chrono::{DateTime, Utc};
+use diesel::{Connection, PgConnection};
+use std::{
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::util::blog::db::{self, DBConn, RetentionQuery};
+use blog_db::models::stats;
+
+/// How many records are deleted by a single statement.
+pub const BATCH: i64 = 500;
+/// How long after startup the first run starts, so that it does not compete with the startup.
+const FIRST_RUN_DELAY: Duration = Duration::from_secs(60);
+/// How long between runs.
+const INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long records are kept before they are removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionConfig {
+    /// How long posts are kept after being deleted.
+    pub deleted_posts: Duration,
+    /// How long the dead letters of webhooks are kept.
+    pub dead_letters: Duration,
+}
+impl RetentionConfig {
+    pub fn from_days(deleted_posts: u32, dead_letters: u32) -> Self {
+        let days = |days: u32| Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+        Self {
+            deleted_posts: days(deleted_posts),
+            dead_letters: days(dead_letters),
+        }
+    }
+}
+
+/// What wakes the background thread.
+enum Wake {
+    Run,
+    Stop,
+}
+
+/// Asks the background thread for runs, and reads when its last ones finished. Managed by rocket
+/// for the admin handlers.
+pub struct Retention {
+    wake: Mutex<mpsc::Sender<Wake>>,
+    last_runs: Arc<Mutex<stats::Retention>>,
+}
+impl Retention {
+    /// Starts the background thread, which removes records older than `config` allows from the
+    /// database at `database_url` until `shutdown` is signaled.
+    pub fn spawn(
+        database_url: String,
+        config: RetentionConfig,
+        shutdown: crypto::Shutdown,
+    ) -> Self {
+        let (wake, woken) = mpsc::channel();
+        let last_runs = Arc::new(Mutex::new(stats::Retention::default()));
+        {
+            let wake = wake.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                shutdown.wait();
+                let _ = wake.send(Wake::Stop);
+            });
+        }
+        {
+            let last_runs = Arc::clone(&last_runs);
+            thread::spawn(move || {
+                let mut next_run = Instant::now() + FIRST_RUN_DELAY;
+                loop {
+                    let timeout = next_run.saturating_duration_since(Instant::now());
+                    match woken.recv_timeout(timeout) {
+                        Ok(Wake::Run) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+                        Ok(Wake::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    }
+                    run(&database_url, config, &shutdown, &last_runs);
+                    next_run = Instant::now() + INTERVAL;
+                }
+            });
+        }
+        Self {
+            wake: Mutex::new(wake),
+            last_runs,
+        }
+    }
+
+    /// Asks for a run right away. The run happens in the background, after any run in progress.
+    pub fn trigger(&self) {
+        let wake = match self.wake.lock() {
+            Ok(wake) => wake,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if wake.send(Wake::Run).is_err() {
+            log::warn!("Could not trigger a retention run, since the job has stopped.");
+        }
+    }
+
+    /// When each kind of record was last cleared.
+    pub fn last_runs(&self) -> stats::Retention {
+        lock(&self.last_runs).clone()
+    }
+}
+
+fn lock(last_runs: &Mutex<stats::Retention>) -> MutexGuard<stats::Retention> {
+    match last_runs.lock() {
+        Ok(last_runs) => last_runs,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// The connection records are removed through, outside of any request.
+struct RetentionConn(PgConnection);
+impl DBConn for RetentionConn {
+    fn conn(&self) -> &PgConnection {
+        &self.0
+    }
+}
+
+/// Removes every kind of record past its retention, logging how many of each were removed.
+fn run(
+    database_url: &str,
+    config: RetentionConfig,
+    shutdown: &crypto::Shutdown,
+    last_runs: &Mutex<stats::Retention>,
+) {
+    let conn = match PgConnection::establish(database_url) {
+        Ok(conn) => RetentionConn(conn),
+        Err(e) => {
+            log::error!("Could not connect to remove old records: {}.", e);
+            return;
+        }
+    };
+    let stop = || shutdown.is_signaled();
+    let started_at = Utc::now();
+    let before = |kept: Duration| -> DateTime<Utc> {
+        chrono::Duration::from_std(kept)
+            .ok()
+            .and_then(|kept| started_at.checked_sub_signed(kept))
+            .unwrap_or(chrono::MIN_DATETIME)
+    };
+
+    let posts = in_batches(stop, |batch| {
+        conn.purge_deleted_posts(before(config.deleted_posts), batch)
+    });
+    let letters = in_batches(stop, |batch| {
+        conn.purge_dead_letters(before(config.dead_letters), batch)
+    });
+    let grants = in_batches(stop, |batch| conn.purge_expired_capabilities(batch));
+    log::info!(
+        "Removed old records in {}ms: {} deleted posts, {} dead letters, {} expired grants.",
+        (Utc::now() - started_at).num_milliseconds(),
+        summary(&posts),
+        summary(&letters),
+        summary(&grants)
+    );
+
+    let mut last_runs = lock(last_runs);
+    if let Ok(Some(_)) = posts {
+        last_runs.deleted_posts_purged_at = Some(started_at);
+    }
+    if let Ok(Some(_)) = letters {
+        last_runs.dead_letters_purged_at = Some(started_at);
+    }
+    if let Ok(Some(_)) = grants {
+        last_runs.expired_grants_purged_at = Some(started_at);
+    }
+}
+
+fn summary(removed: &Result<Option<usize>, db::Error>) -> String {
+    match removed {
+        Ok(Some(removed)) => removed.to_string(),
+        Ok(None) => "stopped for shutdown".to_owned(),
+        Err(e) => format!("failed with {:?}", e),
+    }
+}
+
+/// Calls `purge` with [`BATCH`] until it removes fewer records than that, or until `stop` says to.
+/// Returns how many records were removed in all, or nothing if stopped before all were.
+fn in_batches<E>(
+    stop: impl Fn() -> bool,
+    mut purge: impl FnMut(i64) -> Result<usize, E>,
+) -> Result<Option<usize>, E> {
+    let mut removed = 0;
+    loop {
+        if stop() {
+            return Ok(None);
+        }
+        let batch = purge(BATCH)?;
+        removed += batch;
+        if (batch as i64) < BATCH {
+            return Ok(Some(removed));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn batches_run_until_one_is_short() {
+        let mut left = 2 * BATCH as usize + 3;
+        let mut calls = 0;
+        let removed = in_batches(
+            || false,
+            |batch| {
+                calls += 1;
+                let removed = left.min(batch as usize);
+                left -= removed;
+                Ok::<_, ()>(removed)
+            },
+        );
+        assert_eq!(removed, Ok(Some(2 * BATCH as usize + 3)));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn batches_stop_on_errors_and_shutdown() {
+        let calls = Cell::new(0);
+        let removed = in_batches(
+            || calls.get() == 2,
+            |batch| {
+                calls.set(calls.get() + 1);
+                Ok::<_, ()>(batch as usize)
+            },
+        );
+        assert_eq!(removed, Ok(None));
+        assert_eq!(calls.get(), 2);
+
+        let removed = in_batches(|| false, |_| Err::<usize, _>("timed out"));
+        assert_eq!(removed, Err("timed out"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Security headers and the content security policy for the HTML pages of the site.
+//!
+//! Each response gets its own nonce, available to handlers through the [`CspNonce`] guard, which
+//! must be placed on the inline scripts and styles of the page for the browser to run them.
+
+use crypto::encoding::{Base64, Encoding};
+use rand::RngCore;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Header},
+    request::{FromRequest, Outcome},
+    Request, Response,
+};
+
+/// Number of random bytes in a nonce.
+const NONCE_LEN: usize = 16;
+/// The header enforcing the content security policy.
+pub const CSP_HEADER: &str = "Content-Security-Policy";
+/// The header reporting, but not enforcing, the content security policy.
+pub const CSP_REPORT_ONLY_HEADER: &str = "Content-Security-Policy-Report-Only";
+
+/// The content security policy nonce of the response to the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspNonce(pub String);
+impl CspNonce {
+    /// The nonce of the request, generated the first time it is asked for.
+    pub fn of(req: &Request) -> Self {
+        req.local_cache(|| {
+            let mut bytes = [0; NONCE_LEN];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            CspNonce(Base64::encode(&bytes))
+        })
+        .clone()
+    }
+    /// The nonce, as it is placed on the tags of the page.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+impl<'a, 'r> FromRequest<'a, 'r> for CspNonce {
+    type Error = ();
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self::of(req))
+    }
+}
+
+/// Builds the content security policy allowing the inline scripts and styles with `nonce`.
+pub fn content_security_policy(nonce: &CspNonce) -> String {
+    format!(
+        "default-src 'self'; \
+         script-src 'self' 'nonce-{0}' 'wasm-unsafe-eval'; \
+         style-src 'self' 'nonce-{0}'; \
+         img-src 'self' data:; \
+         object-src 'none'; \
+         base-uri 'self'; \
+         frame-ancestors 'none'",
+        nonce.as_str()
+    )
+}
+
+/// Attaches security headers and a content security policy to all HTML responses.
+pub struct SecurityHeaders {
+    /// The headers set on every HTML response, besides the content security policy.
+    pub headers: Vec<Header<'static>>,
+    /// Whether the content security policy is only reported, rather than enforced.
+    pub report_only: bool,
+}
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+impl SecurityHeaders {
+    /// Creates the fairing with the default set of headers.
+    pub fn new(report_only: bool) -> Self {
+        Self {
+            headers: vec![
+                Header::new("X-Content-Type-Options", "nosniff"),
+                Header::new("X-Frame-Options", "DENY"),
+                Header::new("Referrer-Policy", "strict-origin-when-cross-origin"),
+            ],
+            report_only,
+        }
+    }
+}
+impl Fairing for SecurityHeaders {
+    fn info(&self) -> Info {
+        Info {
+            name: "Security headers",
+            kind: Kind::Response,
+        }
+    }
+    fn on_response(&self, req: &Request, res: &mut Response) {
+        if res.content_type() != Some(ContentType::HTML) {
+            return;
+        }
+        for header in self.headers.iter() {
+            res.set_header(header.clone());
+        }
+        let csp_header = if self.report_only {
+            CSP_REPORT_ONLY_HEADER
+        } else {
+            CSP_HEADER
+        };
+        res.set_raw_header(csp_header, content_security_policy(&CspNonce::of(req)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rocket::local::Client;
+    use rocket_contrib::json::Json;
+
+    #[get("/page")]
+    fn page(nonce: CspNonce) -> maud::Markup {
+        maud::html! { script nonce=(nonce.as_str()) { "" } }
+    }
+
+    #[get("/api")]
+    fn api() -> Json<&'static str> {
+        Json("ok")
+    }
+
+    fn client(fairing: SecurityHeaders) -> Client {
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(fairing)
+            .mount("/", routes![page, api]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    fn headers_are_only_on_html() {
+        let client = client(SecurityHeaders::default());
+        let res = client.get("/page").dispatch();
+        assert_eq!(res.headers().get_one("X-Frame-Options"), Some("DENY"));
+        assert!(res.headers().contains(CSP_HEADER));
+        assert!(!res.headers().contains(CSP_REPORT_ONLY_HEADER));
+        let res = client.get("/api").dispatch();
+        assert!(!res.headers().contains("X-Frame-Options"));
+        assert!(!res.headers().contains(CSP_HEADER));
+    }
+
+    #[test]
+    fn policy_allows_the_nonce_on_the_page() {
+        let client = client(SecurityHeaders::default());
+        let mut first = client.get("/page").dispatch();
+        let policy = first.headers().get_one(CSP_HEADER).unwrap().to_owned();
+        let body = first.body_string().unwrap();
+        let nonce = body.split('"').nth(1).unwrap();
+        assert!(policy.contains(&format!("'nonce-{}'", nonce)));
+        let second = client.get("/page").dispatch();
+        assert_ne!(second.headers().get_one(CSP_HEADER), Some(policy.as_str()));
+    }
+
+    #[test]
+    fn report_only_uses_its_own_header() {
+        let client = client(SecurityHeaders::new(true));
+        let res = client.get("/page").dispatch();
+        assert!(res.headers().contains(CSP_REPORT_ONLY_HEADER));
+        assert!(!res.headers().contains(CSP_HEADER));
+    }
+}
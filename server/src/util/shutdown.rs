@@ -0,0 +1,131 @@
+//! Utilities for shutting the server down without cutting off requests that are in progress.
+//!
+//! Rocket 0.4 cannot be asked to stop listening, so once the [`Shutdown`] signal is sent the
+//! [`Drain`] fairing [refuses](crate::util::refuse) new requests with a 503 while the ones in
+//! progress finish.
+
+use crypto::key_rotation::Shutdown;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Status},
+    Data, Request, Response,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::util::refuse::{self, Answer};
+
+/// How long to wait for in-flight requests to finish before shutting down anyways.
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to check if in-flight requests have finished.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// The body of refused requests.
+const REFUSED_MSG: &str = "The server is shutting down.";
+
+/// Whether the request arrived before the shutdown signal and was counted as in progress.
+struct Counted(bool);
+
+/// Counts requests in progress and refuses new ones after the shutdown signal is sent.
+#[derive(Clone)]
+pub struct Drain {
+    shutdown: Shutdown,
+    in_flight: Arc<AtomicUsize>,
+}
+impl Drain {
+    /// Creates a drain that starts refusing requests once `shutdown` is signaled.
+    pub fn new(shutdown: Shutdown) -> Self {
+        Self {
+            shutdown,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    /// The number of requests in progress.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+    /// Blocks until no requests are in progress or the timeout elapses, returning whether all
+    /// requests finished.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.in_flight() > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+        true
+    }
+}
+impl Fairing for Drain {
+    fn info(&self) -> Info {
+        Info {
+            name: "Shutdown drain",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+    fn on_request(&self, req: &mut Request, _: &Data) {
+        if self.shutdown.is_signaled() {
+            req.local_cache(|| Counted(false));
+            let answer = Answer::new(Status::ServiceUnavailable)
+                .header("Connection", "close")
+                .body(ContentType::Plain, REFUSED_MSG);
+            refuse::reroute(req, answer);
+        } else {
+            req.local_cache(|| Counted(true));
+            self.in_flight.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    fn on_response(&self, req: &Request, _: &mut Response) {
+        if req.local_cache(|| Counted(false)).0 {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Sends the shutdown signal when the process receives SIGINT or SIGTERM.
+pub fn signal_on_termination(shutdown: Shutdown) -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(move || {
+        log::info!("Termination requested.");
+        shutdown.signal();
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::refuse::Refusals;
+    use rocket::local::Client;
+
+    #[get("/")]
+    fn index() -> &'static str {
+        "ok"
+    }
+
+    fn client(drain: &Drain) -> Client {
+        let rocket = rocket::custom(rocket::Config::development())
+            .attach(Refusals)
+            .attach(drain.clone())
+            .mount("/", routes![index]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    fn requests_are_refused_after_signal() {
+        let shutdown = Shutdown::new();
+        let drain = Drain::new(shutdown.clone());
+        let client = client(&drain);
+        assert_eq!(client.get("/").dispatch().status(), Status::Ok);
+        shutdown.signal();
+        let mut res = client.get("/").dispatch();
+        assert_eq!(res.status(), Status::ServiceUnavailable);
+        assert_eq!(res.body_string().as_deref(), Some(REFUSED_MSG));
+        assert_eq!(drain.in_flight(), 0);
+        assert!(drain.wait(Duration::from_millis(1)));
+    }
+}
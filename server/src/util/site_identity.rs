@@ -0,0 +1,141 @@
+//! How the site presents itself: its title, tagline and logo, as shown by the shell, the previews
+//! of its pages, and its mail.
+//!
+//! The identity starts out as configured for the server, and is replaced by the one admins last
+//! saved, if any, once the database is attached. Admins change it through the api without a
+//! restart, which saves it and swaps it in here.
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    Rocket,
+};
+use std::sync::{Arc, RwLock};
+
+use crate::util::{
+    blog::{db::SiteQuery, DB},
+    validation::ValidationErrors,
+};
+use blog_db::models::site::{Identity, Logo};
+
+/// The most characters the title of the site may have.
+pub const TITLE_MAX_CHARS: usize = 100;
+/// The most characters the tagline of the site may have.
+pub const TAGLINE_MAX_CHARS: usize = 300;
+/// The most bytes the markup of an svg logo may take up, since it is part of every page.
+pub const SVG_MAX_BYTES: usize = 32 * 1024;
+
+/// Checks an identity the site could present itself as, listing what is wrong with each part.
+pub fn validate(identity: &Identity) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    if identity.title.trim().is_empty() {
+        errors.add("title", "required", "Enter a title.");
+    } else if identity.title.chars().count() > TITLE_MAX_CHARS {
+        let message = format!("Use at most {} characters.", TITLE_MAX_CHARS);
+        errors
+            .add("title", "too_long", message)
+            .param("max_chars", TITLE_MAX_CHARS);
+    }
+    if identity.tagline.chars().count() > TAGLINE_MAX_CHARS {
+        let message = format!("Use at most {} characters.", TAGLINE_MAX_CHARS);
+        errors
+            .add("tagline", "too_long", message)
+            .param("max_chars", TAGLINE_MAX_CHARS);
+    }
+    match &identity.logo {
+        Logo::Asset { path } if !path.starts_with('/') || path.starts_with("//") => {
+            errors.add(
+                "logo",
+                "invalid",
+                "Enter a path on the site, such as /public/img/logo.svg.",
+            );
+        }
+        Logo::Svg { svg } if !svg.trim_start().starts_with("<svg") => {
+            errors.add("logo", "invalid", "Enter the markup of an svg.");
+        }
+        Logo::Svg { svg } if svg.len() > SVG_MAX_BYTES => {
+            let message = format!("Use at most {} bytes.", SVG_MAX_BYTES);
+            errors
+                .add("logo", "too_large", message)
+                .param("max_bytes", SVG_MAX_BYTES);
+        }
+        Logo::Asset { .. } | Logo::Svg { .. } => {}
+    }
+    errors
+}
+
+/// How the site presents itself. Shared between the handlers that render pages or show and change
+/// it, through managed state.
+#[derive(Clone)]
+pub struct SiteIdentity(Arc<RwLock<Identity>>);
+impl SiteIdentity {
+    /// Starts out presenting the site as `configured`.
+    pub fn new(configured: Identity) -> Self {
+        Self(Arc::new(RwLock::new(configured)))
+    }
+    /// How the site presents itself.
+    pub fn get(&self) -> Identity {
+        match self.0.read() {
+            Ok(identity) => identity.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+    /// Presents the site as `identity`. Persisting it is up to the caller.
+    pub fn set(&self, identity: Identity) {
+        match self.0.write() {
+            Ok(mut current) => *current = identity,
+            Err(poisoned) => *poisoned.into_inner() = identity,
+        }
+    }
+}
+
+impl Fairing for SiteIdentity {
+    fn info(&self) -> Info {
+        Info {
+            name: "Site identity",
+            kind: Kind::Attach,
+        }
+    }
+    /// Picks up the identity admins last saved, so that restarts keep it. Must be attached after
+    /// the database pool.
+    fn on_attach(&self, rocket: Rocket) -> Result<Rocket, Rocket> {
+        match DB::get_one(&rocket).map(|db| db.find_site_identity()) {
+            Some(Ok(Some(identity))) => self.set(identity),
+            Some(Ok(None)) => {}
+            Some(Err(e)) => log::error!(
+                "Could not read the site identity: {}. Using the configured one.",
+                e
+            ),
+            None => {
+                log::error!("No database to read the site identity from. Using the configured one.")
+            }
+        }
+        Ok(rocket)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identities_are_checked_part_by_part() {
+        assert!(validate(&Identity::default()).is_empty());
+        let identity = Identity {
+            title: " ".to_owned(),
+            tagline: "a".repeat(TAGLINE_MAX_CHARS + 1),
+            logo: Logo::Asset {
+                path: "//evil.example/logo.svg".to_owned(),
+            },
+        };
+        let errors = validate(&identity);
+        assert_eq!(errors.fields().collect::<Vec<_>>(), vec!["logo", "tagline", "title"]);
+        assert_eq!(errors.field("tagline")[0].code, "too_long");
+        let svg = Identity {
+            logo: Logo::Svg {
+                svg: "<img src=x>".to_owned(),
+            },
+            ..Identity::default()
+        };
+        assert_eq!(validate(&svg).field("logo").len(), 1);
+    }
+}
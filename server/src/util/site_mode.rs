@@ -0,0 +1,182 @@
+//! Makes the site read only, or puts it under maintenance, such as before a risky migration.
+//!
+//! The [`SiteMode`] fairing refuses requests that would change something through the api with a
+//! 503 carrying the message of the mode, [rerouting](crate::util::refuse) them the way the
+//! [`Drain`](crate::util::shutdown::Drain) does while shutting down. Logging in and out and
+//! switching the mode back are always let through, and holders of
+//! [`ChangeSiteMode`](crate::util::auth::caps::ChangeSiteMode) may keep changing things while the
+//! site is only read only.
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Method, Status},
+    Data, Request, Rocket,
+};
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    cfg,
+    util::{
+        auth,
+        blog::{db::SiteQuery, DB},
+        refuse::{self, Answer},
+    },
+};
+use blog_db::models::site::Mode;
+
+/// The mode the site is in. Shared between the fairing, and the handlers that show or switch it,
+/// through managed state.
+#[derive(Clone, Default)]
+pub struct SiteMode(Arc<RwLock<Mode>>);
+impl SiteMode {
+    /// The mode the site is in.
+    pub fn get(&self) -> Mode {
+        match self.0.read() {
+            Ok(mode) => mode.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+    /// Switches the site to `mode`. Persisting it is up to the caller.
+    pub fn set(&self, mode: Mode) {
+        match self.0.write() {
+            Ok(mut current) => *current = mode,
+            Err(poisoned) => *poisoned.into_inner() = mode,
+        }
+    }
+}
+
+/// Whether the request changes something through the api, and is not one of the requests let
+/// through whatever the mode.
+fn changes_something(req: &Request) -> bool {
+    if matches!(req.method(), Method::Get | Method::Head | Method::Options) {
+        return false;
+    }
+    let path = req.uri().path();
+    let under_api = path
+        .strip_prefix(cfg::BLOG_API_ROOT)
+        .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'));
+    let always_allowed = [blog_api::login::LOG_IN.path, blog_api::admin::MODE.path]
+        .iter()
+        .any(|allowed| path == format!("{}{}", cfg::BLOG_API_ROOT, allowed));
+    under_api && !always_allowed
+}
+
+impl Fairing for SiteMode {
+    fn info(&self) -> Info {
+        Info {
+            name: "Site mode",
+            kind: Kind::Attach | Kind::Request,
+        }
+    }
+    /// Picks up the mode the site was last switched to, so that restarts keep it. Must be attached
+    /// after the database pool.
+    fn on_attach(&self, rocket: Rocket) -> Result<Rocket, Rocket> {
+        match DB::get_one(&rocket).map(|db| db.find_site_mode()) {
+            Some(Ok(mode)) => {
+                if let Some(message) = mode.message() {
+                    log::warn!("Site starts in mode {:?}: {}", mode, message);
+                }
+                self.set(mode);
+            }
+            Some(Err(e)) => log::error!("Could not read the site mode: {}. Starting normal.", e),
+            None => log::error!("No database to read the site mode from. Starting normal."),
+        }
+        Ok(rocket)
+    }
+    fn on_request(&self, req: &mut Request, _: &Data) {
+        let message = match self.get() {
+            Mode::Normal => return,
+            Mode::ReadOnly { .. } if !changes_something(req) => return,
+            Mode::ReadOnly { message } => {
+                let caps = req.guard::<auth::Capabilities<auth::caps::ChangeSiteMode>>();
+                if caps.is_success() {
+                    return;
+                }
+                message
+            }
+            Mode::Maintenance { .. } if !changes_something(req) => return,
+            Mode::Maintenance { message } => message,
+        };
+        let answer = Answer::new(Status::ServiceUnavailable).body(ContentType::Plain, message);
+        refuse::reroute(req, answer);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{cfg::TokenAlgo, util::refuse::Refusals};
+    use rocket::{http::Cookie, local::Client};
+
+    #[get("/posts")]
+    fn list() -> &'static str {
+        "posts"
+    }
+
+    #[post("/posts")]
+    fn create() -> &'static str {
+        "created"
+    }
+
+    #[post("/login")]
+    fn log_in() -> &'static str {
+        "logged in"
+    }
+
+    fn token(
+        rotator: &crypto::KeyRotator<TokenAlgo>,
+        caps: &[auth::Capability],
+    ) -> Cookie<'static> {
+        let held = caps
+            .iter()
+            .copied()
+            .map(auth::caps::Held::unrestricted)
+            .collect();
+        let caps = auth::Capabilities::safe_new(uuid::Uuid::new_v4(), held);
+        let store = rotator.get_key_fixture().get_store().unwrap();
+        let value = auth::seal_capabilities(&store, caps).unwrap();
+        Cookie::new(auth::AUTH_COOKIE_NAME, value)
+    }
+
+    fn client(mode: &SiteMode, rotator: &crypto::KeyRotator<TokenAlgo>) -> Client {
+        let rocket = rocket::custom(rocket::Config::development())
+            .manage(rotator.get_rocket_managed_state())
+            .attach(Refusals)
+            .attach(mode.clone())
+            .mount(cfg::BLOG_API_ROOT, routes![list, create, log_in]);
+        Client::new(rocket).expect("A valid rocket instance.")
+    }
+
+    #[test]
+    fn read_only_refuses_changes_unless_admin() {
+        let rotator = crypto::KeyRotator::init(TokenAlgo {}, crypto::RotationConfig::default());
+        let mode = SiteMode::default();
+        let client = client(&mode, &rotator);
+        assert_eq!(client.post("/api/posts").dispatch().status(), Status::Ok);
+
+        mode.set(Mode::ReadOnly {
+            message: "Back soon.".to_owned(),
+        });
+        let mut res = client.post("/api/posts").dispatch();
+        assert_eq!(res.status(), Status::ServiceUnavailable);
+        assert_eq!(res.body_string().as_deref(), Some("Back soon."));
+        assert_eq!(client.get("/api/posts").dispatch().status(), Status::Ok);
+        assert_eq!(client.post("/api/login").dispatch().status(), Status::Ok);
+
+        let author = token(&rotator, &[auth::Capability::CreatePost]);
+        let res = client.post("/api/posts").cookie(author).dispatch();
+        assert_eq!(res.status(), Status::ServiceUnavailable);
+        let admin = token(&rotator, &[auth::Capability::ChangeSiteMode]);
+        let res = client.post("/api/posts").cookie(admin.clone()).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        // Under maintenance, not even admins change anything but the mode.
+        mode.set(Mode::Maintenance {
+            message: "Migrating.".to_owned(),
+        });
+        let res = client.post("/api/posts").cookie(admin).dispatch();
+        assert_eq!(res.status(), Status::ServiceUnavailable);
+        assert_eq!(client.get("/api/posts").dispatch().status(), Status::Ok);
+        rotator.cleanup().unwrap();
+    }
+}
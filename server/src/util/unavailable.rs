@@ -0,0 +1,51 @@
+//! Tells clients turned away with a 503 when to try again. Requests get one when no database
+//! connection frees up within the [`CHECKOUT_TIMEOUT`](blog_db::rocket::CHECKOUT_TIMEOUT).
+
+use rocket::{
+    http::Status,
+    request::Request,
+    response::{self, Responder, Response},
+};
+
+/// How many seconds clients are asked to wait before retrying.
+pub const RETRY_AFTER_SECS: u64 = 5;
+
+/// A 503 with a `Retry-After` header.
+pub struct Unavailable;
+impl<'r> Responder<'r> for Unavailable {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        Response::build()
+            .status(Status::ServiceUnavailable)
+            .raw_header("Retry-After", RETRY_AFTER_SECS.to_string())
+            .ok()
+    }
+}
+
+/// Catches the 503s of handlers that could not get what they needed in time.
+#[catch(503)]
+pub fn catch() -> Unavailable {
+    Unavailable
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rocket::local::Client;
+
+    #[get("/busy")]
+    fn busy() -> Status {
+        Status::ServiceUnavailable
+    }
+
+    #[test]
+    fn unavailable_responses_say_when_to_retry() {
+        let rocket = rocket::custom(rocket::Config::development())
+            .register(catchers![catch])
+            .mount("/", routes![busy]);
+        let client = Client::new(rocket).expect("A valid rocket instance.");
+        let res = client.get("/busy").dispatch();
+        assert_eq!(res.status(), Status::ServiceUnavailable);
+        let expected = RETRY_AFTER_SECS.to_string();
+        assert_eq!(res.headers().get_one("Retry-After"), Some(expected.as_str()));
+    }
+}
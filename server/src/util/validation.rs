@@ -0,0 +1,145 @@
+//! Responses refusing requests whose fields do not check out.
+
+use rocket::{
+    http::Status,
+    request::Request,
+    response::{self, status, Responder},
+};
+use rocket_contrib::json::Json;
+
+pub use login_enum::{ValidationErrors, Violation};
+
+/// Why a request carrying user input was refused. Input that fails validation is sent back as a
+/// 422 with the [`ValidationErrors`] found, the body every 422 of the api has.
+#[derive(Debug)]
+pub enum Refusal {
+    /// The fields at fault. Never empty.
+    Invalid(ValidationErrors),
+    /// Anything else, sent as a bare status.
+    Status(Status),
+}
+impl Refusal {
+    /// Refuses with the errors, if there are any.
+    pub fn check(errors: ValidationErrors) -> Result<(), Self> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self::Invalid(errors))
+        }
+    }
+}
+impl From<Status> for Refusal {
+    fn from(status: Status) -> Self {
+        Self::Status(status)
+    }
+}
+impl From<ValidationErrors> for Refusal {
+    fn from(errors: ValidationErrors) -> Self {
+        Self::Invalid(errors)
+    }
+}
+/// Checks a slug the way every slug on the site is checked, so that it can be put in a url as is,
+/// noting what is wrong with it under `field`.
+pub fn validate_slug(field: &str, slug: &str, errors: &mut ValidationErrors) {
+    let friendly = slug
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if slug.is_empty() || !friendly {
+        errors.add(
+            field,
+            "invalid",
+            "Use only lowercase letters, digits, and dashes.",
+        );
+    }
+}
+
+/// Gives the 422s refused without naming the fields at fault, such as those for bodies that could
+/// not be parsed, the same body as every other.
+#[catch(422)]
+pub fn catch() -> status::Custom<Json<ValidationErrors>> {
+    status::Custom(
+        Status::UnprocessableEntity,
+        Json(ValidationErrors::single(
+            ValidationErrors::FORM,
+            "malformed",
+            "The form could not be read. Reload the page and try again.",
+        )),
+    )
+}
+
+impl<'r> Responder<'r> for Refusal {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        match self {
+            Self::Invalid(errors) => {
+                status::Custom(Status::UnprocessableEntity, Json(errors)).respond_to(req)
+            }
+            Self::Status(status) => Err(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use rocket::local::Client;
+
+    #[get("/check?<name>")]
+    fn check(name: String) -> Result<Status, Refusal> {
+        let mut errors = ValidationErrors::new();
+        if name.is_empty() {
+            errors.add("name", "required", "Enter a name.");
+        }
+        Refusal::check(errors)?;
+        if name == "missing" {
+            return Err(Status::NotFound.into());
+        }
+        Ok(Status::Ok)
+    }
+
+    #[post("/parsed", format = "json", data = "<_name>")]
+    fn parsed(_name: Json<String>) -> Status {
+        Status::Ok
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::custom(rocket::Config::development())
+            .register(catchers![catch])
+            .mount("/", routes![check, parsed]);
+        Client::new(rocket).unwrap()
+    }
+
+    #[test]
+    fn invalid_fields_are_listed_in_the_body() {
+        let client = client();
+        let mut res = client.get("/check?name=").dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        assert_eq!(
+            res.body_string(),
+            Some(r#"{"name":[{"code":"required","message":"Enter a name."}]}"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn other_refusals_keep_their_status() {
+        let client = client();
+        assert_eq!(
+            client.get("/check?name=missing").dispatch().status(),
+            Status::NotFound
+        );
+        assert_eq!(client.get("/check?name=x").dispatch().status(), Status::Ok);
+    }
+
+    #[test]
+    fn unreadable_bodies_are_refused_as_the_whole_form() {
+        let client = client();
+        let mut res = client
+            .post("/parsed")
+            .header(rocket::http::ContentType::JSON)
+            .body("{")
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        let errors: ValidationErrors =
+            serde_json::from_str(&res.body_string().unwrap()).unwrap();
+        assert_eq!(errors.field(ValidationErrors::FORM)[0].code, "malformed");
+    }
+}
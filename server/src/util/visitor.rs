@@ -0,0 +1,143 @@
+//! Tells readers apart without knowing who they are, for the features that count each reader once,
+//! such as the [`reactions`](blog_db::models::reactions) on posts.
+//!
+//! Logged in users are told apart by their id. Everyone else is handed a random session id in a
+//! cookie the first time they are asked for. Either is only ever kept hashed with a key derived
+//! from the server's secret, so that what is stored can't be traced back to a user or replayed as
+//! a session by anyone without the secret.
+
+use crypto::{
+    algo::{
+        hash::{blake::two_b, symmetric::Algo as _},
+        Algo as _,
+    },
+    encoding::{Encoding, Hex},
+};
+use rocket::{
+    http::{Cookie, Status},
+    request::{FromRequest, Outcome, Request},
+    State,
+};
+
+use crate::{cfg::ServerConfig, util::auth};
+
+/// The name of the cookie holding the session id of readers who are not logged in.
+pub const SESSION_COOKIE_NAME: &str = "visitor";
+/// The length, in bytes, of the hash a reader is known by.
+const HASH_LEN: usize = 32;
+/// Sets the key readers are hashed with apart from anything else derived from the same secret.
+const ACTOR_KEY_CONTEXT: &[u8] = b"visitor actor";
+
+/// The key readers are hashed with, managed by rocket for the [`Visitor`] guard.
+pub struct ActorKey(two_b::Key);
+impl ActorKey {
+    /// Derives the key from `secret`, so that readers are known by the same hash across restarts.
+    pub fn derive(secret: &[u8]) -> Self {
+        let key = two_b::Algo::new(HASH_LEN).sign(
+            ACTOR_KEY_CONTEXT,
+            &two_b::Key::new(secret.to_vec(), HASH_LEN),
+        );
+        Self(two_b::Key::new(key, HASH_LEN))
+    }
+    /// Hashes the id along with what kind of id it is, so that a session can never be mistaken
+    /// for the user sharing its id.
+    fn hash(&self, kind: &str, id: uuid::Uuid) -> String {
+        let named = format!("{}:{}", kind, id);
+        Hex::encode(&two_b::Algo::new(HASH_LEN).sign(named.as_bytes(), &self.0))
+    }
+}
+
+/// Who is reading, as a hash that is the same each time they come back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Visitor {
+    actor: String,
+}
+impl Visitor {
+    fn user(key: &ActorKey, id: uuid::Uuid) -> Self {
+        Self {
+            actor: key.hash("user", id),
+        }
+    }
+    fn session(key: &ActorKey, id: uuid::Uuid) -> Self {
+        Self {
+            actor: key.hash("session", id),
+        }
+    }
+    /// The hash the reader is known by, hex encoded.
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Visitor {
+    type Error = ();
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        let key = req
+            .guard::<State<ActorKey>>()
+            .map_failure(|_| (Status::InternalServerError, ()))?;
+        if let Outcome::Success(capabilities) = req.guard::<auth::UnverifiedCapabilities>() {
+            return Outcome::Success(Self::user(&key, capabilities.user_id()));
+        }
+        let mut cookies = req.cookies();
+        let session = cookies
+            .get(SESSION_COOKIE_NAME)
+            .and_then(|cookie| uuid::Uuid::parse_str(cookie.value()).ok());
+        let session = match session {
+            Some(session) => session,
+            None => {
+                let session = uuid::Uuid::new_v4();
+                let config = req
+                    .guard::<State<ServerConfig>>()
+                    .map_failure(|_| (Status::InternalServerError, ()))?;
+                let mut cookie = Cookie::build(SESSION_COOKIE_NAME, session.to_string())
+                    .path("/")
+                    .secure(config.cookies.secure)
+                    .http_only(true)
+                    .same_site(config.cookies.same_site)
+                    .permanent()
+                    .finish();
+                if let Some(domain) = config.cookies.domain.as_ref() {
+                    cookie.set_domain(domain.clone());
+                }
+                cookies.add(cookie);
+                session
+            }
+        };
+        Outcome::Success(Self::session(&key, session))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key() -> ActorKey {
+        ActorKey::derive(&[1; 32])
+    }
+
+    #[test]
+    fn visitors_are_known_by_a_stable_hash() {
+        let id = uuid::Uuid::from_u128(7);
+        assert_eq!(Visitor::user(&key(), id), Visitor::user(&key(), id));
+        assert_eq!(Visitor::user(&key(), id).actor().len(), 2 * HASH_LEN);
+        assert!(!Visitor::user(&key(), id).actor().contains(&id.to_string()));
+    }
+
+    #[test]
+    fn sessions_and_users_sharing_an_id_are_told_apart() {
+        let id = uuid::Uuid::from_u128(7);
+        assert_ne!(Visitor::user(&key(), id), Visitor::session(&key(), id));
+        assert_ne!(
+            Visitor::session(&key(), id),
+            Visitor::session(&key(), uuid::Uuid::from_u128(8))
+        );
+    }
+
+    #[test]
+    fn hashes_depend_on_the_key() {
+        let id = uuid::Uuid::from_u128(7);
+        let other = ActorKey::derive(&[2; 32]);
+        assert_ne!(Visitor::user(&key(), id), Visitor::user(&other, id));
+        assert_ne!(Visitor::session(&key(), id), Visitor::session(&other, id));
+    }
+}
@@ -0,0 +1,313 @@
+//! Pings the [`webhooks`](blog_db::models::webhooks) admins set up when things happen on the site,
+//! such as a post being published.
+//!
+//! Deliveries are queued by the handler that caused the event and posted by a single background
+//! thread, so that a slow webhook cannot delay the response. Each delivery is a JSON [`Payload`]
+//! signed with the secret of the webhook, and is retried up to [`MAX_RETRIES`] times while the
+//! webhook answers with a 5xx or cannot be reached. Deliveries that fail for good are recorded as
+//! dead letters.
+
+use chrono::{DateTime, Utc};
+use crypto::{
+    algo::{
+        hash::{hmac::sha384, symmetric::Algo as _},
+        Algo as _,
+    },
+    encoding::{Encoding, Hex},
+};
+use diesel::{Connection, PgConnection};
+use serde::Serialize;
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::util::blog::db::{self, DBConn, WebhookQuery};
+use blog_db::models::webhooks::{self, Event};
+
+/// The header carrying the signature of the body, as `sha384=` followed by the hex encoded
+/// HMAC-SHA384 of the body keyed with the secret of the webhook.
+pub const SIGNATURE_HEADER: &str = "X-Benxu-Signature";
+/// The header carrying the name of the event, so that receivers can route without parsing.
+pub const EVENT_HEADER: &str = "X-Benxu-Event";
+/// How many times a delivery is retried after its first attempt.
+pub const MAX_RETRIES: u32 = 3;
+/// How long a webhook has to answer an attempt.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait before the first retry. Doubles with every retry after.
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The body of a delivery.
+#[derive(Debug, Serialize)]
+pub struct Payload<'a, T: Serialize> {
+    pub event: Event,
+    pub sent_at: DateTime<Utc>,
+    /// Whether this is a sample sent by an admin testing the webhook rather than a real event.
+    pub test: bool,
+    /// What the event happened to, such as the post that was published.
+    pub data: &'a T,
+}
+
+/// Signs `body` with `secret`, in the form sent in the [`SIGNATURE_HEADER`].
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let key = sha384::Key::new(secret.as_bytes());
+    format!("sha384={}", Hex::encode(&sha384::Algo::new(()).sign(body, &key)))
+}
+
+/// A payload on its way to a webhook.
+struct Delivery {
+    webhook_id: uuid::Uuid,
+    url: String,
+    event: Event,
+    body: String,
+    signature: String,
+}
+impl Delivery {
+    fn new<T: Serialize>(hook: &webhooks::Data, payload: &Payload<T>) -> serde_json::Result<Self> {
+        let body = serde_json::to_string(payload)?;
+        Ok(Self {
+            webhook_id: hook.id,
+            url: hook.url.clone(),
+            event: payload.event,
+            signature: sign(&hook.secret, body.as_bytes()),
+            body,
+        })
+    }
+    /// Posts the payload once. Returns the status answered with, or why there was no answer.
+    fn post(&self) -> Result<u16, String> {
+        let res = ureq::post(&self.url)
+            .timeout(TIMEOUT)
+            .set("Content-Type", "application/json")
+            .set(EVENT_HEADER, self.event.as_str())
+            .set(SIGNATURE_HEADER, &self.signature)
+            .send_string(&self.body);
+        match res {
+            Ok(res) => Ok(res.status()),
+            Err(ureq::Error::Status(status, _)) => Ok(status),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Why a delivery failed for good.
+#[derive(Debug, PartialEq, Eq)]
+struct Failure {
+    attempts: u32,
+    /// The status of the last attempt, if the webhook answered at all.
+    last_status: Option<u16>,
+    error: String,
+}
+
+/// Attempts a delivery through `post` until it succeeds, fails with something other than a 5xx or
+/// an unreachable webhook, or runs out of retries. Waits `backoff`, doubled each time, between
+/// attempts. Returns how many attempts it took.
+fn deliver(
+    mut post: impl FnMut() -> Result<u16, String>,
+    mut backoff: Duration,
+) -> Result<u32, Failure> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let (last_status, error, retry) = match post() {
+            Ok(status) if (200..300).contains(&status) => return Ok(attempts),
+            Ok(status) => (Some(status), format!("answered with {}", status), status >= 500),
+            Err(e) => (None, e, true),
+        };
+        if !retry || attempts > MAX_RETRIES {
+            return Err(Failure {
+                attempts,
+                last_status,
+                error,
+            });
+        }
+        thread::sleep(backoff);
+        backoff *= 2;
+    }
+}
+
+/// Records a delivery that failed for good. Falls back to logging the whole delivery if the
+/// database cannot be reached, so that nothing is lost without a trace.
+fn record_dead_letter(
+    conn: &mut Option<DeadLetterConn>,
+    database_url: &str,
+    delivery: &Delivery,
+    failure: Failure,
+) {
+    log::error!(
+        "Giving up on delivering {} to webhook {} after {} attempts: {}.",
+        delivery.event.as_str(),
+        delivery.webhook_id,
+        failure.attempts,
+        failure.error
+    );
+    if conn.is_none() {
+        *conn = PgConnection::establish(database_url)
+            .map(DeadLetterConn)
+            .map_err(|e| log::error!("Could not connect to record dead letters: {}.", e))
+            .ok();
+    }
+    let letter = webhooks::NewDeadLetter {
+        id: uuid::Uuid::new_v4(),
+        webhook_id: delivery.webhook_id,
+        event: delivery.event.as_str(),
+        payload: &delivery.body,
+        attempts: failure.attempts as i32,
+        last_status: failure.last_status.map(i32::from),
+        error: &failure.error,
+    };
+    let res = match conn.as_ref() {
+        Some(conn) => conn.record_dead_letter(&letter).map(|_| ()),
+        None => Err(db::Error::Connection),
+    };
+    if let Err(e) = res {
+        log::error!("Could not record dead letter {:?}: {}.", letter, e);
+        // The connection may have been lost, so the next dead letter reconnects.
+        *conn = None;
+    }
+}
+
+/// The connection dead letters are recorded through, outside of any request.
+struct DeadLetterConn(PgConnection);
+impl DBConn for DeadLetterConn {
+    fn conn(&self) -> &PgConnection {
+        &self.0
+    }
+}
+
+/// Queues deliveries for the background thread. Managed by rocket for the handlers causing
+/// events.
+#[derive(Clone)]
+pub struct Webhooks {
+    queue: Arc<Mutex<mpsc::Sender<Delivery>>>,
+}
+impl Webhooks {
+    /// Starts the thread posting deliveries. It records dead letters through its own connection to
+    /// `database_url`, and stops once every [`Webhooks`] is dropped.
+    pub fn spawn(database_url: String) -> Self {
+        let (queue, deliveries) = mpsc::channel::<Delivery>();
+        thread::spawn(move || {
+            let mut conn = None;
+            for delivery in deliveries {
+                match deliver(|| delivery.post(), FIRST_BACKOFF) {
+                    Ok(attempts) => log::debug!(
+                        "Delivered {} to webhook {} in {} attempts.",
+                        delivery.event.as_str(),
+                        delivery.webhook_id,
+                        attempts
+                    ),
+                    Err(failure) => {
+                        record_dead_letter(&mut conn, &database_url, &delivery, failure)
+                    }
+                }
+            }
+        });
+        Self {
+            queue: Arc::new(Mutex::new(queue)),
+        }
+    }
+    /// Queues a delivery of `event` about `data` to every enabled webhook pinged about it. Only
+    /// looks the webhooks up before returning, so that the caller is not held up by them.
+    ///
+    /// Failures are logged rather than returned, since the event already happened.
+    pub fn notify<T: Serialize>(&self, db: &impl WebhookQuery, event: Event, data: &T) {
+        let hooks = match db.find_webhooks_for(event) {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                log::error!("Could not find webhooks for {}: {}.", event.as_str(), e);
+                return;
+            }
+        };
+        let payload = Payload {
+            event,
+            sent_at: Utc::now(),
+            test: false,
+            data,
+        };
+        let queue = match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for hook in hooks.iter() {
+            let queued = Delivery::new(hook, &payload)
+                .map_err(|e| e.to_string())
+                .and_then(|delivery| queue.send(delivery).map_err(|e| e.to_string()));
+            if let Err(e) = queued {
+                log::error!("Could not queue delivery to webhook {}: {}.", hook.id, e);
+            }
+        }
+    }
+}
+
+/// Posts a sample of `event` about `data` to `hook` right away, once, whether or not the hook is
+/// enabled or pinged about the event. Returns the status answered with, or why there was no
+/// answer.
+pub fn send_sample<T: Serialize>(
+    hook: &webhooks::Data,
+    event: Event,
+    data: &T,
+) -> Result<u16, String> {
+    let payload = Payload {
+        event,
+        sent_at: Utc::now(),
+        test: true,
+        data,
+    };
+    Delivery::new(hook, &payload)
+        .map_err(|e| e.to_string())?
+        .post()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crypto::algo::hash::symmetric::Algo as _;
+
+    /// Answers attempts with `statuses` in order, counting the attempts.
+    fn answers(statuses: &[Result<u16, &str>]) -> (Result<u32, Failure>, usize) {
+        let mut answered = statuses.iter();
+        let mut attempts = 0;
+        let res = deliver(
+            || {
+                attempts += 1;
+                answered
+                    .next()
+                    .expect("No more attempts than answers.")
+                    .map_err(str::to_owned)
+            },
+            Duration::from_millis(0),
+        );
+        (res, attempts)
+    }
+
+    #[test]
+    fn only_server_errors_and_unreachable_hooks_are_retried() {
+        assert_eq!(answers(&[Ok(204)]), (Ok(1), 1));
+        assert_eq!(answers(&[Ok(503), Err("timed out"), Ok(200)]), (Ok(3), 3));
+        let refused = Failure {
+            attempts: 1,
+            last_status: Some(404),
+            error: "answered with 404".to_owned(),
+        };
+        assert_eq!(answers(&[Ok(404)]), (Err(refused), 1));
+        let exhausted = Failure {
+            attempts: MAX_RETRIES + 1,
+            last_status: Some(500),
+            error: "answered with 500".to_owned(),
+        };
+        assert_eq!(
+            answers(&[Ok(500), Ok(500), Ok(500), Ok(500)]),
+            (Err(exhausted), 1 + MAX_RETRIES as usize)
+        );
+    }
+
+    #[test]
+    fn signatures_verify_with_the_secret() {
+        let body = br#"{"event":"post_published"}"#;
+        let signature = sign("secret", body);
+        let tag = Hex::decode(signature.trim_start_matches("sha384=")).unwrap();
+        let key = sha384::Key::new(b"secret");
+        assert!(sha384::Algo::new(()).verify(body, &tag, &key));
+        assert_ne!(signature, sign("other secret", body));
+    }
+}
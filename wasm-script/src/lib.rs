@@ -1,5 +1,3 @@
-#![feature(type_ascription)]
-
 //! The wasm functions for managing my slides. This crate should only function in a browser.
 //! Static globals are used to ensure that closures are kept in memory, while not simply forgetting
 //! them due to need for reuse at times.
@@ -170,11 +168,12 @@ where
     let next_active_slide_and_marker =
         determine_active_slide(&slides_and_markers, &curr_active_slide_indices);
 
-    (curr_active_slide_indices
+    let unset: Result<(), JsValue> = curr_active_slide_indices
         .iter()
         .map(|&idx| &slides_and_markers[idx])
         .map(unset_active)
-        .collect(): Result<(), JsValue>)?;
+        .collect();
+    unset?;
     set_active(next_active_slide_and_marker)
 }
 